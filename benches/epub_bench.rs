@@ -266,7 +266,7 @@ fn main() {
         "fixture,case,iterations,min_ns,median_ns,p90_ns,mean_ns,max_ns,min_peak_heap_bytes,median_peak_heap_bytes,p90_peak_heap_bytes,mean_peak_heap_bytes,max_peak_heap_bytes"
     );
 
-    let mut results: Vec<CaseResult> = Vec::new();
+    let mut results: Vec<CaseResult> = Vec::with_capacity(8);
 
     for fixture in FIXTURES {
         let (opf_path, spine_hrefs) = resolve_spine_hrefs(fixture.bytes);
@@ -0,0 +1,617 @@
+//! `mu-epub-cli`: inspect, validate, paginate, and render EPUBs from the shell.
+//!
+//! Meant for debugging user-reported books: every subcommand runs the same
+//! parser and layout engine as a device, with no harness code to write.
+
+use std::env;
+use std::fs::File;
+use std::process::ExitCode;
+
+use mu_epub::validate::{validate_epub_file, ValidationSeverity};
+use mu_epub::EpubBook;
+use mu_epub_render::{
+    PdfConfig, PdfWriter, RasterConfig, RenderConfig, RenderEngine, RenderEngineOptions,
+};
+
+/// Named display presets for common e-ink panel classes.
+const PRESETS: &[(&str, u32, u32)] = &[
+    ("small", 480, 800),
+    ("6in", 758, 1024),
+    ("6in-hd", 1072, 1448),
+    ("7in", 1264, 1680),
+    ("10in", 1404, 1872),
+];
+
+const DEFAULT_PRESET: &str = "small";
+/// Largest `--size` pixel count accepted, so rasterizing stays bounded.
+const MAX_DISPLAY_PIXELS: u64 = 4096 * 4096;
+const DEFAULT_OUT_DIR: &str = "mu-epub-pages";
+
+fn main() -> ExitCode {
+    match run(env::args().skip(1).collect()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(msg) => {
+            eprintln!("error: {}", msg);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(mut args: Vec<String>) -> Result<(), String> {
+    if args.is_empty() || args[0] == "--help" || args[0] == "-h" {
+        print_help();
+        return Ok(());
+    }
+
+    let cmd = args.remove(0);
+    match cmd.as_str() {
+        "inspect" => inspect(args),
+        "validate" => validate(args),
+        "paginate" => paginate(args),
+        "render" => render(args),
+        _ => Err(format!(
+            "unknown command '{}'; run `mu-epub-cli --help` for usage",
+            cmd
+        )),
+    }
+}
+
+fn inspect(args: Vec<String>) -> Result<(), String> {
+    let path = only_path(args, "inspect")?;
+    let mut book = EpubBook::open(&path).map_err(|e| e.to_string())?;
+
+    let metadata = book.metadata().clone();
+    let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+    println!("file:        {}", path);
+    println!("title:       {}", metadata.title);
+    println!("author:      {}", metadata.author);
+    println!("language:    {}", metadata.language);
+    println!("identifier:  {}", optional(&metadata.identifier));
+    println!("publisher:   {}", optional(&metadata.publisher));
+    println!("date:        {}", optional(&metadata.date));
+    println!("modified:    {}", optional(&metadata.modified));
+    println!("layout:      {}", optional(&metadata.rendition_layout));
+    println!("opf:         {}", optional(&metadata.opf_path));
+    println!("cover:       {}", optional(&book.cover_image_href()));
+    let toc_entries = book.navigation().map_or(0, |nav| nav.toc_flat().len());
+    println!("toc entries: {}", toc_entries);
+
+    let chapters = book.chapters().collect::<Vec<_>>();
+    let linear = book
+        .spine()
+        .items()
+        .iter()
+        .map(|item| item.linear)
+        .collect::<Vec<_>>();
+    let idref_width = column_width(chapters.iter().map(|c| c.idref.as_str()), "idref");
+    println!();
+    println!("spine ({}):", chapters.len());
+    println!(
+        "  {:>5}  {:<idref_width$}  {:<6}  {:>9}  href",
+        "index", "idref", "linear", "bytes"
+    );
+    for chapter in &chapters {
+        let bytes = book
+            .chapter_uncompressed_size(chapter.index)
+            .map_or_else(|_| "?".to_string(), |size| size.to_string());
+        let linear = match linear.get(chapter.index) {
+            Some(false) => "no",
+            _ => "yes",
+        };
+        println!(
+            "  {:>5}  {:<idref_width$}  {:<6}  {:>9}  {}",
+            chapter.index, chapter.idref, linear, bytes, chapter.href
+        );
+    }
+
+    let manifest = &metadata.manifest;
+    let id_width = column_width(manifest.iter().map(|item| item.id.as_str()), "id");
+    let type_width = column_width(
+        manifest.iter().map(|item| item.media_type.as_str()),
+        "media-type",
+    );
+    println!();
+    println!("resources ({}):", manifest.len());
+    println!("  {:<id_width$}  {:<type_width$}  href", "id", "media-type");
+    for item in manifest {
+        let properties = item
+            .properties
+            .as_deref()
+            .map(|props| format!("  [{}]", props))
+            .unwrap_or_default();
+        println!(
+            "  {:<id_width$}  {:<type_width$}  {}{}",
+            item.id, item.media_type, item.href, properties
+        );
+    }
+    Ok(())
+}
+
+fn validate(mut args: Vec<String>) -> Result<(), String> {
+    let strict = pop_flag(&mut args, "--strict");
+    let path = only_path(args, "validate")?;
+    let report = validate_epub_file(&path).map_err(|e| e.to_string())?;
+
+    for diag in report.diagnostics() {
+        let severity = match diag.severity {
+            ValidationSeverity::Error => "error",
+            ValidationSeverity::Warning => "warning",
+        };
+        let location = match (&diag.path, &diag.location) {
+            (Some(path), Some(location)) => format!("{} ({}): ", path, location),
+            (Some(path), None) => format!("{}: ", path),
+            _ => String::with_capacity(0),
+        };
+        println!("{:<7} {} {}{}", severity, diag.code, location, diag.message);
+        if let Some(hint) = &diag.hint {
+            println!("        hint: {}", hint);
+        }
+    }
+    println!(
+        "{}: {} error(s), {} warning(s)",
+        path,
+        report.error_count(),
+        report.warning_count()
+    );
+
+    if report.error_count() > 0 || (strict && report.warning_count() > 0) {
+        return Err(if strict {
+            "validation failed (strict mode)".to_string()
+        } else {
+            "validation failed".to_string()
+        });
+    }
+    Ok(())
+}
+
+fn paginate(mut args: Vec<String>) -> Result<(), String> {
+    let profile = Profile::from_args(&mut args)?;
+    let chapter = pop_usize(&mut args, "--chapter")?;
+    let path = only_path(args, "paginate")?;
+    let mut book = EpubBook::open(&path).map_err(|e| e.to_string())?;
+    let chapters = chapter_selection(book.chapter_count(), chapter)?;
+    let engine = RenderEngine::new(profile.engine_options());
+
+    println!("profile: {}", profile);
+    println!("  {:>5}  {:>5}  href", "index", "pages");
+    let mut total = 0usize;
+    let mut failed = 0usize;
+    for index in chapters.clone() {
+        let href = book.chapter(index).map(|c| c.href).unwrap_or_default();
+        let mut pages = 0usize;
+        match engine.prepare_chapter_with(&mut book, index, |_| pages += 1) {
+            Ok(()) => {
+                total += pages;
+                println!("  {:>5}  {:>5}  {}", index, pages, href);
+            }
+            Err(err) => {
+                failed += 1;
+                println!("  {:>5}  {:>5}  {}  (error: {})", index, "-", href, err);
+            }
+        }
+    }
+    println!("total: {} page(s) in {} chapter(s)", total, chapters.len());
+
+    if failed > 0 {
+        return Err(format!("{} chapter(s) failed to paginate", failed));
+    }
+    Ok(())
+}
+
+fn render(mut args: Vec<String>) -> Result<(), String> {
+    let profile = Profile::from_args(&mut args)?;
+    let chapter = pop_usize(&mut args, "--chapter")?;
+    let page = pop_usize(&mut args, "--page")?;
+    let json = pop_flag(&mut args, "--json");
+    let out_dir = pop_value(&mut args, "--out")?.unwrap_or_else(|| DEFAULT_OUT_DIR.to_string());
+    let pdf_path = pop_value(&mut args, "--pdf")?;
+    let path = only_path(args, "render")?;
+    if page.is_some() && chapter.is_none() {
+        return Err("--page requires --chapter".to_string());
+    }
+    let page_range = page.map(single_page).transpose()?;
+
+    let mut book = EpubBook::open(&path).map_err(|e| e.to_string())?;
+    let chapters = chapter_selection(book.chapter_count(), chapter)?;
+    let opts = profile.engine_options();
+    let raster = RasterConfig {
+        page_chrome: opts.layout.page_chrome,
+        ..RasterConfig::default()
+    };
+    let engine = RenderEngine::new(opts);
+    let mut pdf = match pdf_path {
+        Some(_) => {
+            let mut writer = PdfWriter::new(PdfConfig {
+                page_chrome: opts.layout.page_chrome,
+                title: Some(book.title().to_string()),
+                ..PdfConfig::new(profile.width, profile.height)
+            });
+            writer
+                .add_book_fonts(&mut book)
+                .map_err(|e| e.to_string())?;
+            Some(writer)
+        }
+        None => None,
+    };
+    if pdf.is_none() || json {
+        std::fs::create_dir_all(&out_dir).map_err(|e| format!("{}: {}", out_dir, e))?;
+    }
+
+    let mut written = 0usize;
+    for index in chapters {
+        let config = match page_range.clone() {
+            Some(range) => RenderConfig::default().with_page_range(range),
+            None => RenderConfig::default(),
+        };
+        let pages = engine
+            .prepare_chapter_with_config_collect(&mut book, index, config)
+            .map_err(|e| format!("chapter {}: {}", index, e))?;
+        if page.is_some() && pages.is_empty() {
+            return Err(format!(
+                "chapter {} has no page {}",
+                index,
+                page.unwrap_or(0)
+            ));
+        }
+        for (offset, rendered) in pages.iter().enumerate() {
+            let page_index = page.unwrap_or(0) + offset;
+            let stem = format!(
+                "{}/chapter_{:03}_page_{:04}",
+                out_dir,
+                index + 1,
+                page_index + 1
+            );
+            if json {
+                let json_path = format!("{}.json", stem);
+                std::fs::write(&json_path, rendered.to_debug_json())
+                    .map_err(|e| format!("{}: {}", json_path, e))?;
+            }
+            if let Some(pdf) = pdf.as_mut() {
+                pdf.add_page(rendered);
+                written += 1;
+                continue;
+            }
+            let file_path = format!("{}.png", stem);
+            let bitmap = rendered.rasterize(profile.width, profile.height, &raster);
+            let file = File::create(&file_path).map_err(|e| format!("{}: {}", file_path, e))?;
+            bitmap
+                .write_png(std::io::BufWriter::new(file))
+                .map_err(|e| format!("{}: {}", file_path, e))?;
+            written += 1;
+        }
+    }
+    if let (Some(pdf), Some(pdf_path)) = (pdf, pdf_path) {
+        std::fs::write(&pdf_path, pdf.finish()).map_err(|e| format!("{}: {}", pdf_path, e))?;
+        println!("rendered {} page(s) to {} ({})", written, pdf_path, profile);
+        return Ok(());
+    }
+    println!("rendered {} page(s) to {} ({})", written, out_dir, profile);
+    Ok(())
+}
+
+/// Display and typography settings pages are laid out under.
+#[derive(Clone, Debug)]
+struct Profile {
+    name: String,
+    width: u32,
+    height: u32,
+    font_size: Option<f32>,
+    margin: Option<i32>,
+    justify: bool,
+}
+
+impl Profile {
+    /// Consume `--profile`, `--size`, `--font-size`, `--margin`, and
+    /// `--justify` from `args`.
+    fn from_args(args: &mut Vec<String>) -> Result<Self, String> {
+        let name = pop_value(args, "--profile")?.unwrap_or_else(|| DEFAULT_PRESET.to_string());
+        let (_, width, height) = PRESETS
+            .iter()
+            .copied()
+            .find(|(preset, _, _)| *preset == name)
+            .ok_or_else(|| {
+                let names = PRESETS.iter().map(|(n, _, _)| *n).collect::<Vec<_>>();
+                format!("unknown profile '{}' (known: {})", name, names.join(", "))
+            })?;
+        let mut profile = Self {
+            name,
+            width,
+            height,
+            font_size: None,
+            margin: None,
+            justify: pop_flag(args, "--justify"),
+        };
+        if let Some(size) = pop_value(args, "--size")? {
+            let (width, height) = size
+                .split_once('x')
+                .and_then(|(w, h)| Some((w.parse::<u32>().ok()?, h.parse::<u32>().ok()?)))
+                .filter(|&(w, h)| w > 0 && h > 0 && w <= i32::MAX as u32 && h <= i32::MAX as u32)
+                .ok_or_else(|| format!("invalid --size value '{}' (expected WxH)", size))?;
+            if u64::from(width) * u64::from(height) > MAX_DISPLAY_PIXELS {
+                return Err(format!(
+                    "invalid --size value '{}' (at most {} pixels)",
+                    size, MAX_DISPLAY_PIXELS
+                ));
+            }
+            profile.name = "custom".to_string();
+            profile.width = width;
+            profile.height = height;
+        }
+        if let Some(value) = pop_value(args, "--font-size")? {
+            profile.font_size = Some(
+                value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|size| size.is_finite() && *size > 0.0)
+                    .ok_or_else(|| format!("invalid --font-size value '{}'", value))?,
+            );
+        }
+        if let Some(value) = pop_value(args, "--margin")? {
+            profile.margin = Some(
+                value
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|margin| *margin >= 0)
+                    .ok_or_else(|| format!("invalid --margin value '{}'", value))?,
+            );
+        }
+        Ok(profile)
+    }
+
+    fn engine_options(&self) -> RenderEngineOptions {
+        let mut opts = RenderEngineOptions::for_display(self.width as i32, self.height as i32);
+        if let Some(size) = self.font_size {
+            opts.prep.layout_hints.base_font_size_px = size;
+            opts.prep.style.hints = opts.prep.layout_hints;
+        }
+        if let Some(margin) = self.margin {
+            opts.layout.margin_left = margin;
+            opts.layout.margin_right = margin;
+            opts.layout.margin_top = margin;
+            opts.layout.margin_bottom = margin;
+        }
+        opts.layout.typography.justification.enabled = self.justify;
+        opts
+    }
+}
+
+impl std::fmt::Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}x{}", self.name, self.width, self.height)?;
+        if let Some(size) = self.font_size {
+            write!(f, ", font {}px", size)?;
+        }
+        if let Some(margin) = self.margin {
+            write!(f, ", margin {}px", margin)?;
+        }
+        if self.justify {
+            write!(f, ", justified")?;
+        }
+        Ok(())
+    }
+}
+
+/// Chapters a command covers: `chapter` alone, or all `count` chapters.
+fn chapter_selection(
+    count: usize,
+    chapter: Option<usize>,
+) -> Result<std::ops::Range<usize>, String> {
+    match chapter {
+        Some(index) if index >= count => Err(format!(
+            "chapter {} out of range (chapter_count={})",
+            index, count
+        )),
+        Some(index) => Ok(index..index + 1),
+        None => Ok(0..count),
+    }
+}
+
+/// Page range holding only `page`.
+fn single_page(page: usize) -> Result<std::ops::Range<usize>, String> {
+    let end = page
+        .checked_add(1)
+        .ok_or_else(|| format!("--page {} out of range", page))?;
+    Ok(page..end)
+}
+
+/// The single remaining positional argument; rejects unknown options.
+fn only_path(args: Vec<String>, command: &str) -> Result<String, String> {
+    if let Some(unknown) = args.iter().find(|arg| arg.starts_with("--")) {
+        return Err(format!("unknown option '{}' for {}", unknown, command));
+    }
+    match <[String; 1]>::try_from(args) {
+        Ok([path]) => Ok(path),
+        Err(_) => Err(format!("{} requires exactly one <epub_path>", command)),
+    }
+}
+
+fn pop_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    if let Some(pos) = args.iter().position(|a| a == flag) {
+        args.remove(pos);
+        true
+    } else {
+        false
+    }
+}
+
+fn pop_value(args: &mut Vec<String>, option: &str) -> Result<Option<String>, String> {
+    let Some(pos) = args.iter().position(|a| a == option) else {
+        return Ok(None);
+    };
+    if pos + 1 >= args.len() {
+        return Err(format!("{} requires a value", option));
+    }
+    let value = args.remove(pos + 1);
+    args.remove(pos);
+    Ok(Some(value))
+}
+
+fn pop_usize(args: &mut Vec<String>, option: &str) -> Result<Option<usize>, String> {
+    pop_value(args, option)?
+        .map(|value| {
+            value
+                .parse::<usize>()
+                .map_err(|_| format!("invalid {} value '{}'", option, value))
+        })
+        .transpose()
+}
+
+fn column_width<'a>(values: impl Iterator<Item = &'a str>, header: &str) -> usize {
+    values
+        .map(|value| value.chars().count())
+        .chain([header.len()])
+        .max()
+        .unwrap_or(0)
+}
+
+fn print_help() {
+    let mut presets = String::with_capacity(160);
+    for (name, width, height) in PRESETS {
+        presets.push_str(&format!("  {:<8} {}x{}\n", name, width, height));
+    }
+    println!(
+        r#"mu-epub-cli - debug EPUBs with the mu-epub parser and layout engine
+
+USAGE:
+  mu-epub-cli <command> [options] <epub_path>
+
+COMMANDS:
+  inspect <epub_path>
+      Metadata, spine (with entry sizes), and manifest resources.
+  validate <epub_path> [--strict]
+      Structural diagnostics; fails on errors (and warnings with --strict).
+  paginate <epub_path> [PROFILE] [--chapter <n>]
+      Page counts per chapter and for the whole book.
+  render <epub_path> [PROFILE] [--chapter <n> [--page <n>]] [--out <dir>] [--json]
+         [--pdf <file>]
+      Rasterize pages to grayscale PNG with the reference rasterizer
+      (default out: {out}/chapter_NNN_page_NNNN.png); --json also writes
+      each page's render IR as chapter_NNN_page_NNNN.json. --pdf writes
+      the pages to one PDF instead of PNGs, embedding the book's TrueType
+      fonts.
+
+PROFILE OPTIONS:
+  --profile <name>     Display preset (default: {default})
+  --size <W>x<H>       Custom display size in pixels (at most {max_pixels} pixels)
+  --font-size <px>     Base font size
+  --margin <px>        Uniform page margin
+  --justify            Enable inter-word justification
+
+PRESETS:
+{presets}
+NOTES:
+  - Chapter and page indices are 0-based; output file names are 1-based.
+  - PNG text is greeked (glyph boxes), so output is font-independent."#,
+        out = DEFAULT_OUT_DIR,
+        default = DEFAULT_PRESET,
+        max_pixels = MAX_DISPLAY_PIXELS,
+        presets = presets
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn profile_defaults_to_small_preset_and_consumes_its_options() {
+        let mut rest = args(&["--justify", "book.epub", "--margin", "12"]);
+        let profile = Profile::from_args(&mut rest).expect("valid profile");
+        assert_eq!((profile.width, profile.height), (480, 800));
+        assert_eq!(profile.margin, Some(12));
+        assert!(profile.justify);
+        assert_eq!(rest, ["book.epub"]);
+        assert_eq!(profile.to_string(), "small 480x800, margin 12px, justified");
+    }
+
+    #[test]
+    fn profile_reads_presets_and_custom_sizes() {
+        let profile = Profile::from_args(&mut args(&["--profile", "6in"])).expect("preset");
+        assert_eq!((profile.width, profile.height), (758, 1024));
+
+        let profile = Profile::from_args(&mut args(&["--size", "300x200", "--font-size", "18"]))
+            .expect("custom size");
+        assert_eq!(profile.to_string(), "custom 300x200, font 18px");
+    }
+
+    #[test]
+    fn profile_rejects_bad_values() {
+        let err = |values: &[&str]| Profile::from_args(&mut args(values)).unwrap_err();
+        assert_eq!(
+            err(&["--profile", "huge"]),
+            "unknown profile 'huge' (known: small, 6in, 6in-hd, 7in, 10in)"
+        );
+        assert_eq!(
+            err(&["--size", "0x10"]),
+            "invalid --size value '0x10' (expected WxH)"
+        );
+        assert_eq!(
+            err(&["--size", "wide"]),
+            "invalid --size value 'wide' (expected WxH)"
+        );
+        assert_eq!(
+            err(&["--size", "5000x5000"]),
+            format!("invalid --size value '5000x5000' (at most {MAX_DISPLAY_PIXELS} pixels)")
+        );
+        assert_eq!(
+            err(&["--font-size", "NaN"]),
+            "invalid --font-size value 'NaN'"
+        );
+        assert_eq!(err(&["--margin", "-1"]), "invalid --margin value '-1'");
+        assert_eq!(err(&["--margin"]), "--margin requires a value");
+    }
+
+    #[test]
+    fn option_helpers_pop_what_they_match() {
+        let mut rest = args(&["a.epub", "--chapter", "3", "--json"]);
+        assert_eq!(pop_usize(&mut rest, "--chapter"), Ok(Some(3)));
+        assert!(pop_flag(&mut rest, "--json"));
+        assert!(!pop_flag(&mut rest, "--json"));
+        assert_eq!(pop_value(&mut rest, "--out"), Ok(None));
+        assert_eq!(rest, ["a.epub"]);
+
+        assert_eq!(
+            pop_usize(&mut args(&["--page", "-2"]), "--page"),
+            Err("invalid --page value '-2'".to_string())
+        );
+    }
+
+    #[test]
+    fn only_path_requires_one_positional_and_no_unknown_options() {
+        assert_eq!(
+            only_path(args(&["a.epub"]), "inspect"),
+            Ok("a.epub".to_string())
+        );
+        assert_eq!(
+            only_path(args(&["a.epub", "--bogus"]), "inspect"),
+            Err("unknown option '--bogus' for inspect".to_string())
+        );
+        assert_eq!(
+            only_path(args(&[]), "render"),
+            Err("render requires exactly one <epub_path>".to_string())
+        );
+        assert_eq!(
+            only_path(args(&["a.epub", "b.epub"]), "render"),
+            Err("render requires exactly one <epub_path>".to_string())
+        );
+    }
+
+    #[test]
+    fn chapter_and_page_selection_stay_in_range() {
+        assert_eq!(chapter_selection(4, None), Ok(0..4));
+        assert_eq!(chapter_selection(4, Some(2)), Ok(2..3));
+        assert_eq!(
+            chapter_selection(4, Some(4)),
+            Err("chapter 4 out of range (chapter_count=4)".to_string())
+        );
+        assert_eq!(single_page(5), Ok(5..6));
+        assert_eq!(
+            single_page(usize::MAX),
+            Err(format!("--page {} out of range", usize::MAX))
+        );
+    }
+}
@@ -0,0 +1,301 @@
+//! Packed grayscale framebuffer draw target over a caller-owned slice.
+
+use core::fmt;
+
+use embedded_graphics::{
+    pixelcolor::{Gray8, GrayColor},
+    prelude::*,
+    primitives::Rectangle,
+};
+
+/// Bits per pixel of a packed framebuffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelDepth {
+    /// 1 bpp (black/white).
+    Bpp1,
+    /// 2 bpp (4 gray levels).
+    Bpp2,
+    /// 4 bpp (16 gray levels).
+    Bpp4,
+    /// 8 bpp (256 gray levels).
+    Bpp8,
+}
+
+impl PixelDepth {
+    /// Bits per pixel.
+    pub const fn bits(self) -> u32 {
+        match self {
+            Self::Bpp1 => 1,
+            Self::Bpp2 => 2,
+            Self::Bpp4 => 4,
+            Self::Bpp8 => 8,
+        }
+    }
+}
+
+/// Order of pixels within a byte at sub-byte depths.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BitOrder {
+    /// Leftmost pixel in the most significant bits.
+    #[default]
+    MsbFirst,
+    /// Leftmost pixel in the least significant bits.
+    LsbFirst,
+}
+
+/// Geometry and packing of a framebuffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FramebufferFormat {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Bytes per row; may exceed the packed row for panel padding.
+    pub stride: usize,
+    /// Bits per pixel.
+    pub depth: PixelDepth,
+    /// Pixel order within a byte.
+    pub bit_order: BitOrder,
+    /// Store `0` for white instead of black.
+    pub inverted_levels: bool,
+}
+
+impl FramebufferFormat {
+    /// Tightly packed, MSB-first format with `0` = black.
+    pub const fn packed(width: u32, height: u32, depth: PixelDepth) -> Self {
+        Self {
+            width,
+            height,
+            stride: Self::min_stride(width, depth),
+            depth,
+            bit_order: BitOrder::MsbFirst,
+            inverted_levels: false,
+        }
+    }
+
+    /// Smallest stride holding `width` pixels at `depth`.
+    pub const fn min_stride(width: u32, depth: PixelDepth) -> usize {
+        (width as usize * depth.bits() as usize).div_ceil(8)
+    }
+
+    /// Bytes a buffer needs for this format.
+    pub const fn required_len(&self) -> usize {
+        self.stride * self.height as usize
+    }
+}
+
+/// Reason a buffer cannot back a framebuffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FramebufferError {
+    /// `stride` is shorter than one packed row.
+    StrideTooSmall {
+        /// Minimum stride for the width and depth.
+        needed: usize,
+        /// Configured stride.
+        actual: usize,
+    },
+    /// The buffer is shorter than `stride * height`.
+    BufferTooSmall {
+        /// Required length in bytes.
+        needed: usize,
+        /// Supplied length in bytes.
+        actual: usize,
+    },
+}
+
+impl fmt::Display for FramebufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StrideTooSmall { needed, actual } => {
+                write!(f, "framebuffer stride {} below minimum {}", actual, needed)
+            }
+            Self::BufferTooSmall { needed, actual } => {
+                write!(f, "framebuffer needs {} bytes, got {}", needed, actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FramebufferError {}
+
+/// `DrawTarget` writing quantized gray levels into a packed byte slice.
+///
+/// Never allocates. Gray input is truncated to the target depth; pair with
+/// `ColorMappedTarget` to drive it from `EgRenderer`, or use
+/// `EgRenderer::render_framebuffer`.
+pub struct Framebuffer<'a> {
+    buf: &'a mut [u8],
+    format: FramebufferFormat,
+}
+
+impl<'a> Framebuffer<'a> {
+    /// Wrap `buf`, checking it is large enough for `format`.
+    pub fn new(buf: &'a mut [u8], format: FramebufferFormat) -> Result<Self, FramebufferError> {
+        let min_stride = FramebufferFormat::min_stride(format.width, format.depth);
+        if format.stride < min_stride {
+            return Err(FramebufferError::StrideTooSmall {
+                needed: min_stride,
+                actual: format.stride,
+            });
+        }
+        if buf.len() < format.required_len() {
+            return Err(FramebufferError::BufferTooSmall {
+                needed: format.required_len(),
+                actual: buf.len(),
+            });
+        }
+        Ok(Self { buf, format })
+    }
+
+    /// Buffer format.
+    pub fn format(&self) -> FramebufferFormat {
+        self.format
+    }
+
+    /// Stored level at `(x, y)`, or `None` when out of bounds.
+    pub fn level(&self, x: u32, y: u32) -> Option<u8> {
+        let (index, shift, mask) = self.locate(x, y)?;
+        self.buf.get(index).map(|byte| (byte >> shift) & mask)
+    }
+
+    /// Byte index, bit shift, and level mask for `(x, y)`.
+    fn locate(&self, x: u32, y: u32) -> Option<(usize, u32, u8)> {
+        if x >= self.format.width || y >= self.format.height {
+            return None;
+        }
+        let bits = self.format.depth.bits();
+        let bit = x as usize * bits as usize;
+        let index = y as usize * self.format.stride + bit / 8;
+        let within = (bit % 8) as u32;
+        let shift = match self.format.bit_order {
+            BitOrder::MsbFirst => 8 - bits - within,
+            BitOrder::LsbFirst => within,
+        };
+        let mask = ((1u16 << bits) - 1) as u8;
+        Some((index, shift, mask))
+    }
+
+    fn level_for(&self, color: Gray8) -> u8 {
+        let level = color.luma() >> (8 - self.format.depth.bits());
+        if self.format.inverted_levels {
+            let max = ((1u16 << self.format.depth.bits()) - 1) as u8;
+            max - level
+        } else {
+            level
+        }
+    }
+
+    fn put(&mut self, x: u32, y: u32, level: u8) {
+        let Some((index, shift, mask)) = self.locate(x, y) else {
+            return;
+        };
+        if let Some(byte) = self.buf.get_mut(index) {
+            *byte = (*byte & !(mask << shift)) | ((level & mask) << shift);
+        }
+    }
+}
+
+impl OriginDimensions for Framebuffer<'_> {
+    fn size(&self) -> Size {
+        Size::new(self.format.width, self.format.height)
+    }
+}
+
+impl DrawTarget for Framebuffer<'_> {
+    type Color = Gray8;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if point.x >= 0 && point.y >= 0 {
+                let level = self.level_for(color);
+                self.put(point.x as u32, point.y as u32, level);
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        let Some(bottom_right) = area.bottom_right() else {
+            return Ok(());
+        };
+        let level = self.level_for(color);
+        for y in area.top_left.y..=bottom_right.y {
+            for x in area.top_left.x..=bottom_right.x {
+                self.put(x as u32, y as u32, level);
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let level = self.level_for(color);
+        if self.format.stride == FramebufferFormat::min_stride(self.format.width, self.format.depth)
+        {
+            // Replicate the level across a byte and fill rows wholesale.
+            let bits = self.format.depth.bits();
+            let byte = (0..8 / bits).fold(0u8, |acc, i| acc | (level << (i * bits)));
+            let len = self.format.required_len();
+            if let Some(rows) = self.buf.get_mut(..len) {
+                rows.fill(byte);
+                return Ok(());
+            }
+        }
+        let size = self.size();
+        self.fill_solid(&Rectangle::new(Point::zero(), size), color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_levels_by_depth_stride_and_bit_order() {
+        let mut buf = [0u8; 4];
+        let format = FramebufferFormat {
+            stride: 2,
+            ..FramebufferFormat::packed(3, 2, PixelDepth::Bpp2)
+        };
+        let mut fb = Framebuffer::new(&mut buf, format).expect("buffer fits");
+        fb.clear(Gray8::WHITE).expect("infallible");
+        fb.draw_iter([
+            Pixel(Point::new(0, 0), Gray8::BLACK),
+            Pixel(Point::new(2, 1), Gray8::new(0x80)),
+            Pixel(Point::new(3, 1), Gray8::BLACK),
+        ])
+        .expect("infallible");
+        assert_eq!(fb.level(2, 1), Some(2));
+        assert_eq!(fb.level(3, 1), None);
+        // Padding past the row end is never written.
+        assert_eq!(buf, [0b0011_1100, 0, 0b1111_1000, 0]);
+
+        let mut buf = [0u8; 1];
+        let format = FramebufferFormat {
+            bit_order: BitOrder::LsbFirst,
+            inverted_levels: true,
+            ..FramebufferFormat::packed(8, 1, PixelDepth::Bpp1)
+        };
+        let mut fb = Framebuffer::new(&mut buf, format).expect("buffer fits");
+        fb.fill_solid(
+            &Rectangle::new(Point::new(0, 0), Size::new(2, 1)),
+            Gray8::BLACK,
+        )
+        .expect("infallible");
+        assert_eq!(buf, [0b0000_0011]);
+
+        let mut small = [0u8; 1];
+        let format = FramebufferFormat::packed(8, 2, PixelDepth::Bpp4);
+        assert_eq!(
+            Framebuffer::new(&mut small, format).err(),
+            Some(FramebufferError::BufferTooSmall {
+                needed: 8,
+                actual: 1
+            })
+        );
+    }
+}
@@ -22,17 +22,22 @@ use embedded_graphics::{
         },
         MonoFont, MonoTextStyle,
     },
-    pixelcolor::BinaryColor,
+    pixelcolor::{BinaryColor, Gray8},
     prelude::*,
     primitives::{Line, PrimitiveStyle, Rectangle},
-    text::{Baseline, Text},
+    text::{Alignment, Baseline, Text},
 };
 use mu_epub_render::{
-    DrawCommand, JustifyMode, PageChromeCommand, PageChromeConfig, PageChromeKind,
-    PageChromeTextStyle, RenderPage, ResolvedTextStyle, TextCommand,
+    composite_layers, Color, ColorTarget, CompositeItem, DitherMode, DrawCommand, GrayBitmap,
+    ImageCommand, JustifyMode, PageChromeCommand, PageChromeConfig, PageChromeKind,
+    PageChromeTextStyle, PageRect, RenderIntent, RenderPage, ResolvedTextStyle, TextCommand,
 };
 use std::borrow::Cow;
 
+mod framebuffer;
+
+pub use framebuffer::{BitOrder, Framebuffer, FramebufferError, FramebufferFormat, PixelDepth};
+
 /// Backend-local font identifier used for metrics and rasterization dispatch.
 pub type FontId = u8;
 
@@ -433,6 +438,16 @@ pub struct EgRenderConfig {
     pub clear_first: bool,
     /// Page chrome rendering policy and geometry.
     pub page_chrome: PageChromeConfig,
+    /// Night mode: paper is `On`, default ink is `Off`.
+    ///
+    /// Pair with an inverted `RenderIntent` so explicit colors in the page
+    /// agree with the paper.
+    pub inverted: bool,
+    /// Dither applied when blitting image bitmaps to 1-bit ink.
+    ///
+    /// `DitherMode::None` thresholds at mid-gray, which suits bitmaps
+    /// already dithered by the layout `RenderIntent`.
+    pub image_dither: DitherMode,
 }
 
 impl Default for EgRenderConfig {
@@ -440,6 +455,8 @@ impl Default for EgRenderConfig {
         Self {
             clear_first: true,
             page_chrome: PageChromeConfig::geometry_defaults(),
+            inverted: false,
+            image_dither: DitherMode::None,
         }
     }
 }
@@ -485,13 +502,26 @@ where
     }
 
     /// Render a page to a draw target.
+    ///
+    /// Layers are composited via `RenderPage::composite`, so explicit z-order
+    /// applies across content, chrome, and overlays.
     pub fn render_page<D>(&self, page: &RenderPage, display: &mut D) -> Result<(), D::Error>
     where
         D: DrawTarget<Color = BinaryColor>,
     {
-        self.render_content(page, display)?;
-        self.render_overlay(page, display)?;
-        Ok(())
+        if self.cfg.clear_first {
+            display.clear(self.paper())?;
+        }
+        self.draw_items(display, &page.composite())
+    }
+
+    /// Render a page straight into a packed framebuffer.
+    ///
+    /// `On` pixels are stored as black and `Off` as white, quantized to the
+    /// buffer's depth. Nothing is allocated beyond image scaling/dithering.
+    pub fn render_framebuffer(&self, page: &RenderPage, framebuffer: &mut Framebuffer<'_>) {
+        let mut target = ColorMappedTarget::new(framebuffer, Gray8::BLACK, Gray8::WHITE);
+        let Ok(()) = self.render_page(page, &mut target);
     }
 
     /// Render content commands from the current single-stream page output.
@@ -500,22 +530,14 @@ where
         D: DrawTarget<Color = BinaryColor>,
     {
         if self.cfg.clear_first {
-            display.clear(BinaryColor::Off)?;
+            display.clear(self.paper())?;
         }
-        let content_iter: Box<dyn Iterator<Item = &DrawCommand> + '_> =
-            if !page.content_commands.is_empty() {
-                Box::new(page.content_commands.iter())
-            } else {
-                Box::new(
-                    page.commands
-                        .iter()
-                        .filter(|cmd| !matches!(cmd, DrawCommand::PageChrome(_))),
-                )
-            };
-        for cmd in content_iter {
-            self.draw_command(display, cmd)?;
+        if !page.content_commands.is_empty() {
+            return self.draw_items(display, &composite_layers(&[&page.content_commands]));
         }
-        Ok(())
+        let mut items = composite_layers(&[&page.commands]);
+        items.retain(|item| !matches!(item.command, DrawCommand::PageChrome(_)));
+        self.draw_items(display, &items)
     }
 
     /// Render overlay/chrome commands from the current single-stream page output.
@@ -524,14 +546,8 @@ where
         D: DrawTarget<Color = BinaryColor>,
     {
         if !page.chrome_commands.is_empty() || !page.overlay_commands.is_empty() {
-            for cmd in page
-                .chrome_commands
-                .iter()
-                .chain(page.overlay_commands.iter())
-            {
-                self.draw_command(display, cmd)?;
-            }
-            return Ok(());
+            let items = composite_layers(&[&page.chrome_commands, &page.overlay_commands]);
+            return self.draw_items(display, &items);
         }
         for cmd in page
             .commands
@@ -553,12 +569,9 @@ where
         D: DrawTarget<Color = BinaryColor>,
     {
         if self.cfg.clear_first {
-            display.clear(BinaryColor::Off)?;
+            display.clear(self.paper())?;
         }
-        for cmd in commands {
-            self.draw_command(display, cmd)?;
-        }
-        Ok(())
+        self.draw_items(display, &composite_layers(&[commands]))
     }
 
     /// Render pre-split overlay commands (compatible with content/overlay page outputs).
@@ -570,13 +583,62 @@ where
     where
         D: DrawTarget<Color = BinaryColor>,
     {
-        for cmd in commands {
-            self.draw_command(display, cmd)?;
+        self.draw_items(display, &composite_layers(&[commands]))
+    }
+
+    fn draw_items<D>(&self, display: &mut D, items: &[CompositeItem<'_>]) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        for item in items {
+            match item.clip {
+                None => self.draw_command(display, item.command)?,
+                Some(clip) if clip.is_empty() => {}
+                Some(clip) => {
+                    self.draw_command(&mut display.clipped(&to_rectangle(clip)), item.command)?
+                }
+            }
         }
         Ok(())
     }
 
+    fn paper(&self) -> BinaryColor {
+        if self.cfg.inverted {
+            BinaryColor::On
+        } else {
+            BinaryColor::Off
+        }
+    }
+
+    /// Binary ink for an explicit color, or the default ink for `None`.
+    fn ink(&self, color: Option<Color>) -> BinaryColor {
+        match color {
+            Some(color) if color.luma() < 128 => BinaryColor::On,
+            Some(_) => BinaryColor::Off,
+            None => self.paper().invert(),
+        }
+    }
+
     fn draw_command<D>(&self, display: &mut D, cmd: &DrawCommand) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        // Primitives below draw with `On`; light ink goes through a flipping
+        // adapter instead of threading the color everywhere.
+        let ink = match cmd {
+            DrawCommand::Layered(layered) => return self.draw_command(display, &layered.command),
+            DrawCommand::Text(text) => self.ink(text.style.color),
+            DrawCommand::Rect(rect) => self.ink(rect.color),
+            DrawCommand::Image(_) => BinaryColor::On,
+            _ => self.ink(None),
+        };
+        if ink == BinaryColor::Off {
+            return self.draw_command_on(&mut Inverted(display), cmd);
+        }
+        self.draw_command_on(display, cmd)
+    }
+
+    fn draw_command_on<D>(&self, display: &mut D, cmd: &DrawCommand) -> Result<(), D::Error>
     where
         D: DrawTarget<Color = BinaryColor>,
     {
@@ -611,9 +673,84 @@ where
                 Ok(())
             }
             DrawCommand::PageChrome(chrome) => self.draw_page_chrome(display, chrome),
+            DrawCommand::Image(image) => self.draw_image(display, image),
+            // Layers are unwrapped by `draw_command`; clip state is resolved
+            // by `draw_items`.
+            DrawCommand::Layered(_) | DrawCommand::PushClip(_) | DrawCommand::PopClip => Ok(()),
         }
     }
 
+    fn draw_image<D>(&self, display: &mut D, cmd: &ImageCommand) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let Some(bitmap) = &cmd.bitmap else {
+            // Undecoded images render as an outlined placeholder box.
+            return Rectangle::new(Point::new(cmd.x, cmd.y), Size::new(cmd.width, cmd.height))
+                .into_styled(PrimitiveStyle::with_stroke(self.ink(None), 1))
+                .draw(display);
+        };
+        let bitmap = self.prepare_bitmap(bitmap, cmd.width, cmd.height);
+        let width = bitmap.width.max(1) as usize;
+        let paper = self.paper();
+        let pixels = bitmap
+            .pixels
+            .iter()
+            .enumerate()
+            .map(|(idx, luma)| (idx, self.ink(Some(Color::rgb(*luma, *luma, *luma)))))
+            .filter(|(_, ink)| *ink != paper)
+            .map(|(idx, ink)| {
+                let x = cmd.x + (idx % width) as i32;
+                let y = cmd.y + (idx / width) as i32;
+                Pixel(Point::new(x, y), ink)
+            });
+        display.draw_iter(pixels)
+    }
+
+    /// Scale `bitmap` to the layout size and apply `image_dither`.
+    fn prepare_bitmap<'b>(
+        &self,
+        bitmap: &'b GrayBitmap,
+        width: u32,
+        height: u32,
+    ) -> Cow<'b, GrayBitmap> {
+        let scaled = (bitmap.width, bitmap.height) != (width, height)
+            && bitmap.width > 0
+            && bitmap.height > 0
+            && width > 0
+            && height > 0;
+        if !scaled && self.cfg.image_dither == DitherMode::None {
+            return Cow::Borrowed(bitmap);
+        }
+        let mut out = if scaled {
+            // Nearest-neighbour; layout normally rasterizes at size already.
+            let mut pixels = Vec::with_capacity(width as usize * height as usize);
+            for y in 0..height {
+                let sy = (y as u64 * bitmap.height as u64 / height as u64) as u32;
+                for x in 0..width {
+                    let sx = (x as u64 * bitmap.width as u64 / width as u64) as u32;
+                    pixels.push(bitmap.pixel(sx, sy).unwrap_or(u8::MAX));
+                }
+            }
+            GrayBitmap {
+                width,
+                height,
+                pixels,
+            }
+        } else {
+            bitmap.clone()
+        };
+        if self.cfg.image_dither != DitherMode::None {
+            RenderIntent {
+                color_target: ColorTarget::Mono,
+                dither: self.cfg.image_dither,
+                ..RenderIntent::default()
+            }
+            .dither_bitmap(&mut out);
+        }
+        Cow::Owned(out)
+    }
+
     fn draw_text<D>(&self, display: &mut D, cmd: &TextCommand) -> Result<(), D::Error>
     where
         D: DrawTarget<Color = BinaryColor>,
@@ -623,6 +760,24 @@ where
         let metrics = self.backend.metrics(selection.font_id);
         let origin = Point::new(cmd.x, cmd.baseline_y);
 
+        if let Some(glyphs) = &cmd.glyphs {
+            // Layout already decided placement; draw glyph-by-glyph.
+            let mut pen = cmd.x;
+            let mut buf = [0u8; 4];
+            for (ch, glyph) in cmd.text.chars().zip(glyphs) {
+                if ch != ' ' {
+                    self.backend.draw_text_run(
+                        display,
+                        selection.font_id,
+                        ch.encode_utf8(&mut buf),
+                        Point::new(pen + glyph.x_offset, cmd.baseline_y),
+                    )?;
+                }
+                pen += glyph.x_advance;
+            }
+            return Ok(());
+        }
+
         match cmd.style.justify_mode {
             JustifyMode::None => self
                 .backend
@@ -745,11 +900,136 @@ where
                     .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
                     .draw(display)?;
             }
+            PageChromeKind::BookProgress => {
+                if !chrome_cfg.book_progress_enabled {
+                    return Ok(());
+                }
+                if let Some(text) = &chrome.text {
+                    let style = mono_text_style(chrome_cfg.footer_style);
+                    Text::with_alignment(
+                        text,
+                        Point::new(
+                            width.saturating_sub(chrome_cfg.footer_x),
+                            height.saturating_sub(chrome_cfg.footer_baseline_from_bottom),
+                        ),
+                        style,
+                        Alignment::Right,
+                    )
+                    .draw(display)?;
+                }
+            }
         }
         Ok(())
     }
 }
 
+/// Adapter drawing `BinaryColor` output onto a target of any pixel color.
+///
+/// Lets gray and color panels (`Gray4`, `Rgb565`, ...) use `EgRenderer`
+/// directly: `On` pixels become `ink`, `Off` pixels become `paper`.
+pub struct ColorMappedTarget<'a, D>
+where
+    D: DrawTarget,
+{
+    target: &'a mut D,
+    ink: D::Color,
+    paper: D::Color,
+}
+
+impl<'a, D> ColorMappedTarget<'a, D>
+where
+    D: DrawTarget,
+{
+    /// Wrap `target`, mapping `On` to `ink` and `Off` to `paper`.
+    pub fn new(target: &'a mut D, ink: D::Color, paper: D::Color) -> Self {
+        Self { target, ink, paper }
+    }
+
+    fn map(&self, color: BinaryColor) -> D::Color {
+        match color {
+            BinaryColor::On => self.ink,
+            BinaryColor::Off => self.paper,
+        }
+    }
+}
+
+impl<D> Dimensions for ColorMappedTarget<'_, D>
+where
+    D: DrawTarget,
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
+
+impl<D> DrawTarget for ColorMappedTarget<'_, D>
+where
+    D: DrawTarget,
+{
+    type Color = BinaryColor;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let (ink, paper) = (self.ink, self.paper);
+        self.target.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point, if color.is_on() { ink } else { paper })),
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let color = self.map(color);
+        self.target.fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let color = self.map(color);
+        self.target.clear(color)
+    }
+}
+
+/// Draw target adapter that swaps `On` and `Off`.
+struct Inverted<'a, D>(&'a mut D);
+
+impl<D> Dimensions for Inverted<'_, D>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.0.bounding_box()
+    }
+}
+
+impl<D> DrawTarget for Inverted<'_, D>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    type Color = BinaryColor;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.0.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point, color.invert())),
+        )
+    }
+}
+
+fn to_rectangle(rect: PageRect) -> Rectangle {
+    Rectangle::new(
+        Point::new(rect.x, rect.y),
+        Size::new(rect.width, rect.height),
+    )
+}
+
 fn mono_text_style(style: PageChromeTextStyle) -> MonoTextStyle<'static, BinaryColor> {
     match style {
         PageChromeTextStyle::Regular => MonoTextStyle::new(&FONT_8X13, BinaryColor::On),
@@ -777,8 +1057,8 @@ mod tests {
     use std::{cell::RefCell, rc::Rc};
 
     use mu_epub_render::{
-        BlockRole, DrawCommand, JustifyMode, PageChromeCommand, PageChromeKind, RenderPage,
-        ResolvedTextStyle, TextCommand,
+        BlockRole, DrawCommand, GlyphPosition, JustifyMode, PageChromeCommand, PageChromeKind,
+        RenderPage, ResolvedTextStyle, TextCommand, TextRenderHint, VerticalMetrics,
     };
 
     #[derive(Default)]
@@ -791,7 +1071,7 @@ mod tests {
         fn with_size(width: u32, height: u32) -> Self {
             Self {
                 size: Size::new(width, height),
-                on_pixels: Vec::new(),
+                on_pixels: Vec::with_capacity(8),
             }
         }
     }
@@ -839,6 +1119,7 @@ mod tests {
         resolve_calls: usize,
         metrics_calls: usize,
         draw_runs: Vec<String>,
+        draw_origins: Vec<Point>,
     }
 
     impl BackendSpy {
@@ -876,12 +1157,14 @@ mod tests {
             _display: &mut D,
             _font_id: FontId,
             text: &str,
-            _origin: Point,
+            origin: Point,
         ) -> Result<i32, D::Error>
         where
             D: DrawTarget<Color = BinaryColor>,
         {
-            self.state.borrow_mut().draw_runs.push(text.to_string());
+            let mut state = self.state.borrow_mut();
+            state.draw_runs.push(text.to_string());
+            state.draw_origins.push(origin);
             Ok(text.chars().count() as i32)
         }
     }
@@ -901,6 +1184,8 @@ mod tests {
             letter_spacing: 0.0,
             role: BlockRole::Body,
             justify_mode: JustifyMode::None,
+            color: None,
+            metrics: VerticalMetrics::estimate(16.0),
         };
         let page = page_with_commands(
             1,
@@ -910,6 +1195,8 @@ mod tests {
                 text: "Hello".to_string(),
                 font_id: None,
                 style,
+                glyphs: None,
+                hint: TextRenderHint::Aliased,
             })],
         );
 
@@ -934,6 +1221,8 @@ mod tests {
             letter_spacing: 0.0,
             role: BlockRole::Body,
             justify_mode: JustifyMode::None,
+            color: None,
+            metrics: VerticalMetrics::estimate(16.0),
         };
         let page = page_with_commands(
             1,
@@ -943,6 +1232,8 @@ mod tests {
                 text: "cmd".to_string(),
                 font_id: None,
                 style,
+                glyphs: None,
+                hint: TextRenderHint::Aliased,
             })],
         );
 
@@ -1027,6 +1318,8 @@ mod tests {
             letter_spacing: 0.0,
             role: BlockRole::Body,
             justify_mode: JustifyMode::None,
+            color: None,
+            metrics: VerticalMetrics::estimate(16.0),
         };
 
         let plain = TextCommand {
@@ -1035,6 +1328,8 @@ mod tests {
             text: "aa bb".to_string(),
             font_id: None,
             style: base_style.clone(),
+            glyphs: None,
+            hint: TextRenderHint::Aliased,
         };
         let justified = TextCommand {
             x: 0,
@@ -1045,6 +1340,8 @@ mod tests {
                 justify_mode: JustifyMode::InterWord { extra_px_total: 2 },
                 ..base_style
             },
+            glyphs: None,
+            hint: TextRenderHint::Aliased,
         };
         let page = page_with_commands(
             1,
@@ -1059,6 +1356,52 @@ mod tests {
         assert_eq!(snapshot.draw_runs, vec!["aa bb", "aa", "bb"]);
     }
 
+    #[test]
+    fn glyph_positions_override_backend_measurement() {
+        let mut display = MockDisplay::new();
+        display.set_allow_overdraw(true);
+        let backend = BackendSpy::default();
+        let state = backend.state();
+        let renderer = EgRenderer::with_backend(EgRenderConfig::default(), backend);
+        let style = ResolvedTextStyle {
+            font_id: None,
+            family: "serif".to_string(),
+            weight: 400,
+            italic: false,
+            size_px: 16.0,
+            line_height: 1.4,
+            letter_spacing: 0.0,
+            role: BlockRole::Body,
+            justify_mode: JustifyMode::InterWord { extra_px_total: 3 },
+            color: None,
+            metrics: VerticalMetrics::estimate(16.0),
+        };
+        let advance = |x_advance| GlyphPosition {
+            x_advance,
+            x_offset: 0,
+        };
+        let page = page_with_commands(
+            1,
+            vec![DrawCommand::Text(TextCommand {
+                x: 5,
+                baseline_y: 10,
+                text: "a b".to_string(),
+                font_id: None,
+                style,
+                glyphs: Some(vec![advance(7), advance(10), advance(7)]),
+                hint: TextRenderHint::Aliased,
+            })],
+        );
+
+        assert!(renderer.render_page(&page, &mut display).is_ok());
+        let snapshot = state.borrow();
+        assert_eq!(snapshot.draw_runs, vec!["a", "b"]);
+        assert_eq!(
+            snapshot.draw_origins,
+            vec![Point::new(5, 10), Point::new(22, 10)]
+        );
+    }
+
     #[test]
     fn mono_backend_reports_fallback_reason_for_unknown_family() {
         let backend = MonoFontBackend;
@@ -1072,6 +1415,8 @@ mod tests {
             letter_spacing: 0.0,
             role: BlockRole::Body,
             justify_mode: JustifyMode::None,
+            color: None,
+            metrics: VerticalMetrics::estimate(16.0),
         };
 
         let selection = backend.resolve_font(&style, None);
@@ -1094,6 +1439,8 @@ mod tests {
             letter_spacing: 0.0,
             role: BlockRole::Body,
             justify_mode: JustifyMode::None,
+            color: None,
+            metrics: VerticalMetrics::estimate(16.0),
         };
 
         let selection = backend.resolve_font(&style, Some(999));
@@ -1157,6 +1504,8 @@ mod tests {
             letter_spacing: 0.0,
             role: BlockRole::Body,
             justify_mode: JustifyMode::None,
+            color: None,
+            metrics: VerticalMetrics::estimate(16.0),
         };
         let content_commands = vec![
             DrawCommand::Text(TextCommand {
@@ -1165,6 +1514,8 @@ mod tests {
                 text: "content".to_string(),
                 font_id: None,
                 style: base_style,
+                glyphs: None,
+                hint: TextRenderHint::Aliased,
             }),
             DrawCommand::Rule(mu_epub_render::RuleCommand {
                 x: 0,
@@ -1201,6 +1552,47 @@ mod tests {
         assert_eq!(snap_single.draw_runs, snap_split.draw_runs);
     }
 
+    #[test]
+    fn push_clip_limits_drawn_pixels() {
+        let renderer = EgRenderer::new(EgRenderConfig {
+            clear_first: false,
+            ..EgRenderConfig::default()
+        });
+        let page = page_with_commands(
+            1,
+            vec![
+                DrawCommand::PushClip(PageRect::new(0, 0, 4, 4)),
+                DrawCommand::Rect(mu_epub_render::RectCommand {
+                    x: 0,
+                    y: 0,
+                    width: 8,
+                    height: 8,
+                    fill: true,
+                    color: None,
+                }),
+                DrawCommand::PopClip,
+                DrawCommand::Rect(mu_epub_render::RectCommand {
+                    x: 10,
+                    y: 10,
+                    width: 2,
+                    height: 2,
+                    fill: true,
+                    color: None,
+                }),
+            ],
+        );
+        let mut display = PixelCaptureDisplay::with_size(20, 20);
+
+        renderer
+            .render_page(&page, &mut display)
+            .expect("render should succeed");
+        assert_eq!(display.on_pixels.len(), 16 + 4);
+        assert!(display
+            .on_pixels
+            .iter()
+            .all(|p| (p.x < 4 && p.y < 4) || (p.x >= 10 && p.y >= 10)));
+    }
+
     #[test]
     fn page_chrome_config_changes_progress_geometry() {
         let mut cfg = EgRenderConfig {
@@ -1275,6 +1667,159 @@ mod tests {
         assert!(display.on_pixels.is_empty());
     }
 
+    #[test]
+    fn image_bitmap_is_thresholded_at_command_origin() {
+        let renderer = EgRenderer::new(EgRenderConfig {
+            clear_first: false,
+            ..EgRenderConfig::default()
+        });
+        let page = page_with_commands(
+            1,
+            vec![DrawCommand::Image(ImageCommand {
+                x: 10,
+                y: 20,
+                width: 2,
+                height: 2,
+                src: "images/fig.svg".to_string(),
+                alt: "Figure".to_string(),
+                bitmap: Some(mu_epub_render::GrayBitmap {
+                    width: 2,
+                    height: 2,
+                    pixels: vec![0, 255, 200, 40],
+                }),
+            })],
+        );
+        let mut display = PixelCaptureDisplay::with_size(40, 40);
+
+        let result = renderer.render_page(&page, &mut display);
+        assert!(result.is_ok());
+        assert_eq!(
+            display.on_pixels,
+            vec![Point::new(10, 20), Point::new(11, 21)]
+        );
+    }
+
+    #[test]
+    fn inverted_config_draws_light_ink_on_dark_paper() {
+        let renderer = EgRenderer::new(EgRenderConfig {
+            clear_first: false,
+            inverted: true,
+            ..EgRenderConfig::default()
+        });
+        let page = page_with_commands(
+            1,
+            vec![
+                DrawCommand::Rect(mu_epub_render::RectCommand {
+                    x: 0,
+                    y: 0,
+                    width: 1,
+                    height: 1,
+                    fill: true,
+                    color: Some(Color::BLACK),
+                }),
+                DrawCommand::Rect(mu_epub_render::RectCommand {
+                    x: 2,
+                    y: 0,
+                    width: 1,
+                    height: 1,
+                    fill: true,
+                    color: None,
+                }),
+            ],
+        );
+        let mut display = PixelCaptureDisplay::with_size(4, 4);
+
+        let result = renderer.render_page(&page, &mut display);
+        assert!(result.is_ok());
+        // Explicit black stays `On`; default ink is `Off` on inverted paper.
+        assert_eq!(display.on_pixels, vec![Point::new(0, 0)]);
+    }
+
+    #[test]
+    fn image_dither_scales_bitmap_to_layout_size() {
+        let renderer = EgRenderer::new(EgRenderConfig {
+            clear_first: false,
+            image_dither: DitherMode::Ordered,
+            ..EgRenderConfig::default()
+        });
+        let page = page_with_commands(
+            1,
+            vec![DrawCommand::Image(ImageCommand {
+                x: 0,
+                y: 0,
+                width: 8,
+                height: 8,
+                src: "images/gray.png".to_string(),
+                alt: String::with_capacity(0),
+                bitmap: Some(mu_epub_render::GrayBitmap {
+                    width: 2,
+                    height: 2,
+                    pixels: vec![128; 4],
+                }),
+            })],
+        );
+        let mut display = PixelCaptureDisplay::with_size(16, 16);
+
+        let result = renderer.render_page(&page, &mut display);
+        assert!(result.is_ok());
+        // Mid-gray dithers to half ink, spread over the full layout box.
+        assert_eq!(display.on_pixels.len(), 32);
+        assert!(display.on_pixels.iter().any(|p| p.x == 7 && p.y == 7));
+    }
+
+    #[test]
+    fn color_mapped_target_draws_onto_gray_displays() {
+        let renderer = EgRenderer::new(EgRenderConfig {
+            clear_first: false,
+            ..EgRenderConfig::default()
+        });
+        let page = page_with_commands(
+            1,
+            vec![DrawCommand::Rect(mu_epub_render::RectCommand {
+                x: 1,
+                y: 1,
+                width: 2,
+                height: 1,
+                fill: true,
+                color: None,
+            })],
+        );
+        let mut display = MockDisplay::<Gray8>::new();
+        let mut mapped = ColorMappedTarget::new(&mut display, Gray8::new(16), Gray8::WHITE);
+
+        let result = renderer.render_page(&page, &mut mapped);
+        assert!(result.is_ok());
+        assert_eq!(display.get_pixel(Point::new(1, 1)), Some(Gray8::new(16)));
+        assert_eq!(display.get_pixel(Point::new(2, 1)), Some(Gray8::new(16)));
+        assert_eq!(display.get_pixel(Point::new(0, 0)), None);
+    }
+
+    #[test]
+    fn render_framebuffer_packs_page_into_caller_buffer() {
+        let renderer = EgRenderer::new(EgRenderConfig::default());
+        let page = page_with_commands(
+            1,
+            vec![DrawCommand::Rect(mu_epub_render::RectCommand {
+                x: 2,
+                y: 1,
+                width: 3,
+                height: 1,
+                fill: true,
+                color: None,
+            })],
+        );
+        let mut buf = [0u8; 6];
+        let format = FramebufferFormat {
+            stride: 3,
+            ..FramebufferFormat::packed(8, 2, PixelDepth::Bpp1)
+        };
+        let mut fb = Framebuffer::new(&mut buf, format).expect("buffer fits");
+        renderer.render_framebuffer(&page, &mut fb);
+        assert_eq!(fb.level(2, 1), Some(0));
+        assert_eq!(fb.level(5, 1), Some(1));
+        assert_eq!(buf, [0xFF, 0, 0, 0b1100_0111, 0, 0]);
+    }
+
     #[cfg(feature = "ttf-backend")]
     #[test]
     fn ttf_backend_exposes_options_and_status() {
@@ -0,0 +1,291 @@
+//! Canvas-oriented backend for driving `RenderEngine` from a web page.
+//!
+//! Build for the browser with:
+//!
+//! ```text
+//! cargo build -p mu-epub-render --example wasm_canvas \
+//!     --target wasm32-unknown-unknown --release
+//! ```
+//!
+//! The module exports a small buffer-passing ABI, so no bindgen glue is
+//! needed: JS copies bytes into the slice returned by `mu_input`, calls an
+//! entry point, and reads `len` bytes from `mu_output` when the return value
+//! is non-negative (`-1` leaves an error message there instead). Page output
+//! is one Canvas 2D operation per line with tab-separated fields; see
+//! `docs/wasm.md` for the replay loop.
+//!
+//! Layout runs in this module with the same `RenderEngineOptions` a device
+//! would use, so page breaks match the device build for the same viewport.
+
+// defmt's JSON-like symbol names break the linker version script of host
+// cdylibs; the host copy only exists to keep the example compiling.
+#![cfg(any(target_family = "wasm", not(feature = "defmt")))]
+
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::io::Cursor;
+
+use mu_epub::EpubBook;
+use mu_epub_render::{
+    Color, DrawCommand, PageChromeKind, PageRect, RenderEngine, RenderEngineOptions, RenderPage,
+    TextCommand,
+};
+
+struct Reader {
+    book: EpubBook<Cursor<Vec<u8>>>,
+    engine: RenderEngine,
+    /// Pages of the most recently requested chapter.
+    chapter: Option<(usize, Vec<RenderPage>)>,
+}
+
+thread_local! {
+    static INPUT: RefCell<Vec<u8>> = RefCell::new(Vec::with_capacity(0));
+    static OUTPUT: RefCell<Vec<u8>> = RefCell::new(Vec::with_capacity(0));
+    static READER: RefCell<Option<Reader>> = const { RefCell::new(None) };
+}
+
+/// Resize the input buffer to `len` bytes and return its address.
+#[no_mangle]
+pub extern "C" fn mu_input(len: usize) -> *mut u8 {
+    INPUT.with_borrow_mut(|input| {
+        input.clear();
+        input.resize(len, 0);
+        input.as_mut_ptr()
+    })
+}
+
+/// Address of the output buffer filled by the last call.
+#[no_mangle]
+pub extern "C" fn mu_output() -> *const u8 {
+    OUTPUT.with_borrow(|output| output.as_ptr())
+}
+
+/// Open the EPUB held in the input buffer for a `width` x `height` viewport.
+///
+/// Returns the chapter count.
+#[no_mangle]
+pub extern "C" fn mu_open(width: i32, height: i32) -> i32 {
+    let bytes = INPUT.with_borrow_mut(std::mem::take);
+    respond(|_| {
+        let book = EpubBook::from_reader(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+        let count = book.chapter_count();
+        let engine = RenderEngine::new(RenderEngineOptions::for_display(width, height));
+        READER.set(Some(Reader {
+            book,
+            engine,
+            chapter: None,
+        }));
+        Ok(count)
+    })
+}
+
+/// Number of pages in `chapter`.
+#[no_mangle]
+pub extern "C" fn mu_page_count(chapter: usize) -> i32 {
+    respond(|_| with_chapter(chapter, |pages| Ok(pages.len())))
+}
+
+/// Write the canvas operations for one page to the output buffer.
+///
+/// Returns the output length.
+#[no_mangle]
+pub extern "C" fn mu_page(chapter: usize, page: usize) -> i32 {
+    respond(|out| {
+        with_chapter(chapter, |pages| {
+            let page = pages
+                .get(page)
+                .ok_or_else(|| format!("page {} out of range ({})", page, pages.len()))?;
+            write_page_ops(page, out);
+            Ok(out.len())
+        })
+    })
+}
+
+/// Copy the archive resource named by the UTF-8 href in the input buffer
+/// (an `image` op's `src`) to the output buffer.
+///
+/// Returns the output length.
+#[no_mangle]
+pub extern "C" fn mu_resource() -> i32 {
+    let href = INPUT.with_borrow(|input| String::from_utf8_lossy(input).into_owned());
+    respond(|out| {
+        READER.with_borrow_mut(|reader| {
+            let reader = reader.as_mut().ok_or("no book open")?;
+            let bytes = reader
+                .book
+                .read_resource(&href)
+                .map_err(|e| e.to_string())?;
+            out.extend_from_slice(&bytes);
+            Ok(out.len())
+        })
+    })
+}
+
+/// Run `f` against a cleared output buffer, mapping errors to `-1` with the
+/// message left in the buffer.
+fn respond<F>(f: F) -> i32
+where
+    F: FnOnce(&mut Vec<u8>) -> Result<usize, String>,
+{
+    OUTPUT.with_borrow_mut(|out| {
+        out.clear();
+        match f(out) {
+            Ok(value) => i32::try_from(value).unwrap_or(i32::MAX),
+            Err(message) => {
+                out.clear();
+                out.extend_from_slice(message.as_bytes());
+                -1
+            }
+        }
+    })
+}
+
+fn with_chapter<T, F>(chapter: usize, f: F) -> Result<T, String>
+where
+    F: FnOnce(&[RenderPage]) -> Result<T, String>,
+{
+    READER.with_borrow_mut(|reader| {
+        let reader = reader.as_mut().ok_or("no book open")?;
+        let cached = matches!(&reader.chapter, Some((index, _)) if *index == chapter);
+        if !cached {
+            let pages = reader
+                .engine
+                .prepare_chapter(&mut reader.book, chapter)
+                .map_err(|e| e.to_string())?;
+            reader.chapter = Some((chapter, pages));
+        }
+        match &reader.chapter {
+            Some((_, pages)) => f(pages),
+            None => Err("chapter not prepared".to_string()),
+        }
+    })
+}
+
+/// Emit `page` as Canvas 2D operations in composite order.
+///
+/// Lines are `op\targs...`; free text is always the last field:
+///
+/// - `fillRect`/`strokeRect x y w h color`
+/// - `fillText x y font color text` (`y` is the alphabetic baseline)
+/// - `image x y w h src alt`
+/// - `chrome kind current total text`
+/// - `clip x y w h` ... `restore` around clipped operations
+fn write_page_ops(page: &RenderPage, out: &mut Vec<u8>) {
+    let mut ops = String::with_capacity(page.commands.len() * 48);
+    for item in page.composite() {
+        if let Some(clip) = item.clip {
+            if clip.width == 0 || clip.height == 0 {
+                continue;
+            }
+            let PageRect {
+                x,
+                y,
+                width,
+                height,
+            } = clip;
+            let _ = writeln!(ops, "clip\t{x}\t{y}\t{width}\t{height}");
+        }
+        match item.command {
+            DrawCommand::Text(text) => write_text(text, &mut ops),
+            DrawCommand::Rule(rule) => {
+                let (w, h) = if rule.horizontal {
+                    (rule.length, rule.thickness)
+                } else {
+                    (rule.thickness, rule.length)
+                };
+                let _ = writeln!(ops, "fillRect\t{}\t{}\t{w}\t{h}\t#000000", rule.x, rule.y);
+            }
+            DrawCommand::Rect(rect) => {
+                let op = if rect.fill { "fillRect" } else { "strokeRect" };
+                let _ = writeln!(
+                    ops,
+                    "{op}\t{}\t{}\t{}\t{}\t{}",
+                    rect.x,
+                    rect.y,
+                    rect.width,
+                    rect.height,
+                    css_color(rect.color)
+                );
+            }
+            DrawCommand::Image(image) => {
+                let _ = writeln!(
+                    ops,
+                    "image\t{}\t{}\t{}\t{}\t{}\t{}",
+                    image.x,
+                    image.y,
+                    image.width,
+                    image.height,
+                    field(&image.src),
+                    field(&image.alt)
+                );
+            }
+            DrawCommand::PageChrome(chrome) => {
+                let kind = match chrome.kind {
+                    PageChromeKind::Header => "header",
+                    PageChromeKind::Footer => "footer",
+                    PageChromeKind::Progress => "progress",
+                    PageChromeKind::BookProgress => "bookProgress",
+                };
+                let _ = writeln!(
+                    ops,
+                    "chrome\t{kind}\t{}\t{}\t{}",
+                    chrome.current.unwrap_or(0),
+                    chrome.total.unwrap_or(0),
+                    field(chrome.text.as_deref().unwrap_or(""))
+                );
+            }
+            // `composite` resolves clips and layers into `item.clip`/order.
+            DrawCommand::PushClip(_) | DrawCommand::PopClip | DrawCommand::Layered(_) => {}
+        }
+        if item.clip.is_some() {
+            ops.push_str("restore\n");
+        }
+    }
+    out.extend_from_slice(ops.as_bytes());
+}
+
+fn write_text(text: &TextCommand, ops: &mut String) {
+    let style = &text.style;
+    let font = format!(
+        "{}{} {}px {}",
+        if style.italic { "italic " } else { "" },
+        style.weight,
+        style.size_px,
+        field(&style.family)
+    );
+    let color = css_color(style.color);
+    let y = text.baseline_y + style.baseline_offset_px().round() as i32;
+    match &text.glyphs {
+        // Layout already placed each glyph; canvas must not re-measure.
+        Some(glyphs) => {
+            let mut pen = text.x;
+            for (ch, glyph) in text.text.chars().zip(glyphs) {
+                let _ = writeln!(
+                    ops,
+                    "fillText\t{}\t{y}\t{font}\t{color}\t{}",
+                    pen + glyph.x_offset,
+                    field(ch.encode_utf8(&mut [0; 4]))
+                );
+                pen += glyph.x_advance;
+            }
+        }
+        None => {
+            let _ = writeln!(
+                ops,
+                "fillText\t{}\t{y}\t{font}\t{color}\t{}",
+                text.x,
+                field(&text.text)
+            );
+        }
+    }
+}
+
+fn css_color(color: Option<Color>) -> String {
+    let Color { r, g, b } = color.unwrap_or(Color::BLACK);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Keep free text from breaking the line/tab framing.
+fn field(text: &str) -> String {
+    text.replace(['\t', '\n', '\r'], " ")
+}
@@ -13,22 +13,107 @@
     )
 )]
 
+#[macro_use]
+mod render_trace;
+
+mod render_annotate;
+#[cfg(feature = "async")]
+mod render_async;
+mod render_bookmark;
+mod render_cache;
+mod render_compact;
+mod render_diff;
+mod render_dump;
 mod render_engine;
+mod render_estimate;
+mod render_export;
+mod render_highlight;
+mod render_image;
 mod render_ir;
+#[cfg(feature = "jpeg-baseline")]
+mod render_jpeg;
+mod render_kv;
 mod render_layout;
+#[cfg(feature = "pdf")]
+mod render_pdf;
+#[cfg(feature = "pdf")]
+mod render_pdf_font;
+#[cfg(feature = "png-strip")]
+mod render_png;
+mod render_profile;
+#[cfg(feature = "raster")]
+mod render_raster;
+mod render_reader;
+mod render_reverse;
+mod render_search;
+mod render_speech;
+mod render_spread;
+mod render_svg;
+mod render_sync;
+mod render_thumb;
+mod render_validate;
+#[cfg(feature = "testkit")]
+pub mod testkit;
 
-pub use mu_epub::BlockRole;
+pub use mu_epub::{BlockRole, Color};
+pub use render_annotate::{
+    Annotation, AnnotationStore, AnnotationStyle, KvAnnotationStore, MemoryAnnotationStore,
+};
+#[cfg(feature = "async")]
+pub use render_async::{AsyncBookSource, AsyncRenderError, RenderPageAsyncStream};
+pub use render_bookmark::{Bookmark, BookmarkStore, KvBookmarkStore, MemoryBookmarkStore};
+pub use render_cache::{
+    decode_chapter_pages, encode_chapter_pages, BookFingerprint, CacheDecodeError, CacheKey,
+    ChapterIr, DirCacheStore, KvCacheStore, LruCacheStore, PaginationProfile, ProfileChange,
+    SessionSnapshot, CACHE_FORMAT_VERSION, CHAPTER_IR_VERSION, PAGINATION_PROFILE_VERSION,
+    SESSION_SNAPSHOT_VERSION, SYNC_POSITION_VERSION,
+};
+pub use render_compact::{CompactCommand, CompactPage, CompactText};
+pub use render_diff::{CommandChange, PageDiff};
 pub use render_engine::{
-    CancelToken, LayoutSession, NeverCancel, PageRange, RenderCacheStore, RenderConfig,
-    RenderDiagnostic, RenderEngine, RenderEngineError, RenderEngineOptions, RenderPageIter,
-    RenderPageStreamIter,
+    BookTarget, CacheCapacity, CacheWritePolicy, CancelToken, ChapterErrorPolicy, ChapterRun,
+    DiagnosticCategory, DiagnosticFilter, DiagnosticLevel, DroppedContentKind, EngineTelemetry,
+    LayoutCheckpoint, LayoutSession, NeverCancel, PageRange, PaginationProgress, PrefetchStatus,
+    RenderCacheStore, RenderConfig, RenderDiagnostic, RenderEngine, RenderEngineError,
+    RenderEngineOptions, RenderPageIter, RenderPageStreamIter, RenderPhase, ReversePageIter,
+    YieldHook, YieldPoint,
 };
+pub use render_highlight::{HighlightStyle, SearchHit, TextSelection};
+#[cfg(feature = "webp")]
+pub use render_image::WebpDecoder;
+pub use render_image::{ImageDecodeOptions, ImageDecoder, ImageDecoderRegistry, NetpbmDecoder};
+#[cfg(feature = "image-decode")]
+pub use render_image::{JpegDecoder, PngDecoder};
 pub use render_ir::{
-    DitherMode, DrawCommand, FloatSupport, GrayscaleMode, HangingPunctuationConfig,
-    HyphenationConfig, HyphenationMode, JustificationConfig, JustifyMode, ObjectLayoutConfig,
-    OverlayComposer, OverlayContent, OverlayItem, OverlayRect, OverlaySize, OverlaySlot,
-    PageAnnotation, PageChromeCommand, PageChromeConfig, PageChromeKind, PageChromeTextStyle,
-    PageMeta, PageMetrics, PaginationProfileId, RectCommand, RenderIntent, RenderPage,
-    ResolvedTextStyle, RuleCommand, SvgMode, TextCommand, TypographyConfig, WidowOrphanControl,
+    composite_layers, BookPageMap, ChapterStats, ColorTarget, CompositeItem, ContentLocator,
+    DitherMode, DrawCommand, FloatSupport, GlyphPosition, GrayBitmap, GrayscaleMode,
+    GrayscaleWeights, HangingPunctuationConfig, HyphenationConfig, HyphenationMode, ImageCommand,
+    ImageScaleFilter, InternalLink, JustificationConfig, JustifyMode, LayeredCommand, LinkTarget,
+    ObjectLayoutConfig, OverlayAnchor, OverlayComposer, OverlayComposerChain, OverlayConflict,
+    OverlayContent, OverlayEdge, OverlayImage, OverlayItem, OverlayRect, OverlaySize, OverlaySlot,
+    PageAnnotation, PageChromeCommand, PageChromeConfig, PageChromeKind, PageChromeProvider,
+    PageChromeTextStyle, PageMeta, PageMetrics, PageRect, PaginationProfileId, RectCommand,
+    RenderIntent, RenderPage, ResolvedTextStyle, Rotation, RuleCommand, StaticPageChrome, SvgMode,
+    TextCommand, TextPosition, TextRenderHint, TypographyConfig, VerticalMetrics,
+    WidowOrphanControl, ANCHOR_TAG_KIND,
 };
+#[cfg(feature = "jpeg-baseline")]
+pub use render_jpeg::{StripJpegDecoder, DEFAULT_JPEG_SCRATCH_BYTES};
+pub use render_kv::{DirKvStore, KvError, KvLimits, KvStore, MemoryKvStore};
 pub use render_layout::{LayoutConfig, LayoutEngine, SoftHyphenPolicy};
+#[cfg(feature = "pdf")]
+pub use render_pdf::{PdfConfig, PdfFont, PdfWriter};
+#[cfg(feature = "pdf")]
+pub use render_pdf_font::PdfFontError;
+#[cfg(feature = "png-strip")]
+pub use render_png::{StripPngDecoder, DEFAULT_PNG_SCRATCH_BYTES};
+pub use render_profile::{ChapterProfile, ProfileReport};
+#[cfg(feature = "raster")]
+pub use render_raster::RasterConfig;
+pub use render_reader::{BookHandle, ReaderSession};
+pub use render_search::{BookSearchHit, SearchOptions};
+pub use render_speech::SpeechSegment;
+pub use render_spread::{RenderSpread, SpreadOptions, SpreadStart};
+pub use render_sync::SyncPosition;
+pub use render_thumb::ThumbnailConfig;
+pub use render_validate::{PageFinding, PageLayer};
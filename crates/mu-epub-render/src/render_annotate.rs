@@ -0,0 +1,397 @@
+//! Reader highlights and notes addressed by reflow-stable locators.
+
+use core::ops::Range;
+use std::sync::Mutex;
+
+use crate::render_cache::{decode_annotation, encode_annotation, hex_prefix, BookFingerprint};
+use crate::render_ir::{
+    ContentLocator, DrawCommand, PageRect, RectCommand, RenderPage, RuleCommand, TextPosition,
+};
+use crate::render_kv::{id_segment, KvStore};
+
+/// Side length of the margin marker drawn for noted annotations.
+const NOTE_MARKER_PX: u32 = 6;
+
+/// How an annotation is marked on the page.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnnotationStyle {
+    /// One-pixel rule under each covered line span.
+    #[default]
+    Underline,
+    /// Outline around each covered line span.
+    Outline,
+}
+
+/// Highlight or note over a range of book content.
+///
+/// Addressed by locators rather than pages, so it stays attached to the
+/// same text under any layout settings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Annotation {
+    /// Caller-assigned identifier, unique within a store.
+    pub id: u64,
+    /// Inclusive start.
+    pub start: ContentLocator,
+    /// Exclusive end.
+    pub end: ContentLocator,
+    /// Marking style.
+    pub style: AnnotationStyle,
+    /// Attached note; noted annotations also get a margin marker.
+    pub note: Option<String>,
+}
+
+impl Annotation {
+    /// Underlined annotation over `[start, end)`.
+    pub fn new(id: u64, start: ContentLocator, end: ContentLocator) -> Self {
+        Self {
+            id,
+            start,
+            end,
+            style: AnnotationStyle::default(),
+            note: None,
+        }
+    }
+
+    /// Annotation over a char range of `page.plain_text()`, e.g. a
+    /// `TextSelection::range`.
+    ///
+    /// `None` when the page has no recorded line positions.
+    pub fn from_selection(id: u64, page: &RenderPage, range: Range<usize>) -> Option<Self> {
+        let chapter_index = page.metrics.chapter_index;
+        let start = page.position_at(range.start)?;
+        let end = page.position_at(range.end)?;
+        Some(Self::new(
+            id,
+            ContentLocator::new(chapter_index, start),
+            ContentLocator::new(chapter_index, end),
+        ))
+    }
+
+    /// Set the marking style.
+    pub fn with_style(mut self, style: AnnotationStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Attach a note.
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+
+    /// True when the annotated range touches `chapter_index`.
+    pub fn covers_chapter(&self, chapter_index: usize) -> bool {
+        (self.start.chapter_index..=self.end.chapter_index).contains(&chapter_index)
+    }
+}
+
+/// Storage hooks for reader annotations.
+///
+/// Methods take `&self`; implementations use interior mutability so one
+/// store can be shared with `RenderConfig::with_annotations`.
+pub trait AnnotationStore {
+    /// Annotations touching `chapter_index`.
+    fn load_annotations(&self, chapter_index: usize) -> Vec<Annotation>;
+
+    /// Insert or replace the annotation with the same id.
+    fn save_annotation(&self, annotation: &Annotation);
+
+    /// Remove an annotation by id.
+    fn remove_annotation(&self, id: u64);
+}
+
+/// In-memory `AnnotationStore`.
+#[derive(Debug, Default)]
+pub struct MemoryAnnotationStore {
+    annotations: Mutex<Vec<Annotation>>,
+}
+
+impl MemoryAnnotationStore {
+    /// Empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Snapshot of all annotations, in insertion order.
+    pub fn annotations(&self) -> Vec<Annotation> {
+        self.annotations
+            .lock()
+            .map(|all| all.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl AnnotationStore for MemoryAnnotationStore {
+    fn load_annotations(&self, chapter_index: usize) -> Vec<Annotation> {
+        self.annotations
+            .lock()
+            .map(|all| {
+                all.iter()
+                    .filter(|annotation| annotation.covers_chapter(chapter_index))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn save_annotation(&self, annotation: &Annotation) {
+        let Ok(mut all) = self.annotations.lock() else {
+            return;
+        };
+        match all.iter_mut().find(|known| known.id == annotation.id) {
+            Some(known) => *known = annotation.clone(),
+            None => all.push(annotation.clone()),
+        }
+    }
+
+    fn remove_annotation(&self, id: u64) {
+        if let Ok(mut all) = self.annotations.lock() {
+            all.retain(|annotation| annotation.id != id);
+        }
+    }
+}
+
+/// `AnnotationStore` persisting one record per annotation in a `KvStore`.
+///
+/// Records live under `annotations/<book>/<id>` and load in id order.
+/// Writes the store rejects are dropped; unreadable records are skipped.
+#[derive(Clone, Debug)]
+pub struct KvAnnotationStore<S> {
+    store: S,
+    prefix: String,
+}
+
+impl<S: KvStore> KvAnnotationStore<S> {
+    /// Annotations of `book` in `store`.
+    pub fn new(store: S, book: BookFingerprint) -> Self {
+        Self {
+            store,
+            prefix: format!("annotations/{}/", hex_prefix(&book.0)),
+        }
+    }
+
+    /// Underlying key-value store.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+}
+
+impl<S: KvStore> AnnotationStore for KvAnnotationStore<S> {
+    fn load_annotations(&self, chapter_index: usize) -> Vec<Annotation> {
+        self.store
+            .keys_with_prefix(&self.prefix)
+            .iter()
+            .filter_map(|key| decode_annotation(&self.store.get(key)?).ok())
+            .filter(|annotation| annotation.covers_chapter(chapter_index))
+            .collect()
+    }
+
+    fn save_annotation(&self, annotation: &Annotation) {
+        let key = format!("{}{}", self.prefix, id_segment(annotation.id));
+        let _ = self.store.put(&key, &encode_annotation(annotation));
+    }
+
+    fn remove_annotation(&self, id: u64) {
+        self.store
+            .delete(&format!("{}{}", self.prefix, id_segment(id)));
+    }
+}
+
+impl RenderPage {
+    /// Source position of a char offset into `plain_text`.
+    ///
+    /// An offset at a line end maps just past its last char. `None` when the
+    /// offset is out of range or line positions were not recorded.
+    pub fn position_at(&self, offset: usize) -> Option<TextPosition> {
+        self.line_spans()
+            .find(|(start, len, _)| (*start..=start + len).contains(&offset))
+            .map(|(start, _, position)| TextPosition {
+                char_offset: position.char_offset + (offset - start),
+                ..position
+            })
+    }
+
+    /// Char ranges of `plain_text` covered by `annotation`, one per line.
+    pub fn annotation_ranges(&self, annotation: &Annotation) -> Vec<Range<usize>> {
+        let chapter = self.metrics.chapter_index;
+        let start = (annotation.start.chapter_index, annotation.start.position);
+        let end = (annotation.end.chapter_index, annotation.end.position);
+        self.line_spans()
+            .filter_map(|(offset, len, position)| {
+                let line = (chapter, position);
+                let from = line_local(start, line, len);
+                let to = line_local(end, line, len);
+                (from < to).then(|| offset + from..offset + to)
+            })
+            .collect()
+    }
+
+    /// Mark an annotation on the overlay layer at `z`.
+    ///
+    /// Covered line spans get the annotation's style; noted annotations
+    /// also get a filled marker centred on `marker_x` beside their first
+    /// line. Marked areas are added to `damage`. Returns the span rects.
+    pub fn push_annotation_overlay(
+        &mut self,
+        annotation: &Annotation,
+        marker_x: i32,
+        z: i32,
+    ) -> Vec<PageRect> {
+        let rects = self.text_range_rects(&self.annotation_ranges(annotation));
+        for rect in &rects {
+            let (area, cmd) = match annotation.style {
+                AnnotationStyle::Underline => {
+                    let area = PageRect::new(rect.x, rect.bottom() - 1, rect.width, 1);
+                    let rule = DrawCommand::Rule(RuleCommand {
+                        x: area.x,
+                        y: area.y,
+                        length: area.width,
+                        thickness: 1,
+                        horizontal: true,
+                    });
+                    (area, rule)
+                }
+                AnnotationStyle::Outline => (*rect, outline(*rect, false)),
+            };
+            self.push_overlay_rect(area, z, cmd);
+        }
+        if let Some(first) = rects.first().filter(|_| annotation.note.is_some()) {
+            let half = (NOTE_MARKER_PX / 2) as i32;
+            let marker = PageRect::new(
+                marker_x - half,
+                first.y + (first.height / 2) as i32 - half,
+                NOTE_MARKER_PX,
+                NOTE_MARKER_PX,
+            );
+            self.push_overlay_rect(marker, z, outline(marker, true));
+        }
+        self.sync_commands();
+        rects
+    }
+
+    /// `(plain_text offset, char count, source position)` per recorded line.
+    pub(crate) fn line_spans(&self) -> impl Iterator<Item = (usize, usize, TextPosition)> + '_ {
+        let text = self.plain_text();
+        let mut offset = 0usize;
+        let spans: Vec<(usize, usize)> = text
+            .split('\n')
+            .map(|line| {
+                let len = line.chars().count();
+                let span = (offset, len);
+                offset += len + 1;
+                span
+            })
+            .collect();
+        spans
+            .into_iter()
+            .zip(self.line_positions.iter().copied())
+            .map(|((offset, len), position)| (offset, len, position))
+    }
+}
+
+/// Offset of `bound` within a line starting at `line` with `len` chars,
+/// clamped to the line.
+fn line_local(bound: (usize, TextPosition), line: (usize, TextPosition), len: usize) -> usize {
+    if bound <= line {
+        0
+    } else if bound.0 == line.0 && bound.1.block_index == line.1.block_index {
+        (bound.1.char_offset - line.1.char_offset).min(len)
+    } else {
+        len
+    }
+}
+
+fn outline(rect: PageRect, fill: bool) -> DrawCommand {
+    DrawCommand::Rect(RectCommand {
+        x: rect.x,
+        y: rect.y,
+        width: rect.width,
+        height: rect.height,
+        fill,
+        color: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_ir::{
+        JustifyMode, OverlayContent, ResolvedTextStyle, TextCommand, TextRenderHint,
+        VerticalMetrics,
+    };
+    use mu_epub::BlockRole;
+
+    fn line(y: i32, text: &str) -> DrawCommand {
+        DrawCommand::Text(TextCommand {
+            x: 10,
+            baseline_y: y,
+            text: text.to_string(),
+            font_id: None,
+            style: ResolvedTextStyle {
+                font_id: None,
+                family: "serif".to_string(),
+                weight: 400,
+                italic: false,
+                size_px: 20.0,
+                line_height: 1.2,
+                letter_spacing: 0.0,
+                role: BlockRole::Body,
+                justify_mode: JustifyMode::None,
+                color: None,
+                metrics: VerticalMetrics::estimate(20.0),
+            },
+            glyphs: None,
+            hint: TextRenderHint::Aliased,
+        })
+    }
+
+    fn position(block_index: usize, char_offset: usize) -> TextPosition {
+        TextPosition {
+            block_index,
+            char_offset,
+        }
+    }
+
+    #[test]
+    fn selection_round_trips_through_locators_onto_overlay() {
+        // Block 4 wraps over two lines, then block 5 starts.
+        let mut page = RenderPage::new(1);
+        page.metrics.chapter_index = 2;
+        page.push_content_command(line(10, "alpha beta"));
+        page.push_content_command(line(40, "gamma"));
+        page.push_content_command(line(70, "delta"));
+        page.line_positions = vec![position(4, 0), position(4, 11), position(5, 0)];
+        page.sync_commands();
+
+        let annotation = Annotation::from_selection(7, &page, 6..19)
+            .expect("positions recorded")
+            .with_note("check");
+        assert_eq!(annotation.start.position, position(4, 6));
+        assert_eq!(annotation.end.position, position(5, 2));
+        assert_eq!(
+            page.annotation_ranges(&annotation),
+            vec![6..10, 11..16, 17..19]
+        );
+
+        let rects = page.push_annotation_overlay(&annotation, 470, 3);
+        assert_eq!(rects.len(), 3);
+        // Three underlines plus the note marker.
+        assert_eq!(page.overlay_items.len(), 4);
+        assert!(matches!(
+            page.overlay_items[3].content,
+            OverlayContent::Command(DrawCommand::Rect(RectCommand {
+                x: 467,
+                fill: true,
+                ..
+            }))
+        ));
+
+        let store = MemoryAnnotationStore::new();
+        store.save_annotation(&annotation);
+        store.save_annotation(&annotation.clone().with_style(AnnotationStyle::Outline));
+        assert_eq!(store.load_annotations(2).len(), 1);
+        assert!(store.load_annotations(3).is_empty());
+        store.remove_annotation(7);
+        assert!(store.annotations().is_empty());
+    }
+}
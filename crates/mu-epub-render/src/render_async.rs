@@ -0,0 +1,176 @@
+//! Async chapter preparation over async storage backends.
+
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::io::{Read, Seek};
+
+use futures_core::Stream;
+use mu_epub::EpubBook;
+
+use crate::render_engine::{RenderConfig, RenderEngine, RenderEngineError};
+use crate::render_ir::RenderPage;
+
+/// Async storage backend for `RenderEngine::prepare_chapter_async`.
+///
+/// Chapter bytes are awaited from the backend. Spine, stylesheet, and
+/// image lookups still go through `book`, so back it with an in-memory
+/// reader (e.g. the container directory) when storage is slow.
+pub trait AsyncBookSource {
+    /// Reader behind the opened book.
+    type Reader: Read + Seek;
+    /// Backend error.
+    type Error;
+
+    /// Fetch the XHTML bytes of a spine chapter.
+    fn load_chapter(
+        &mut self,
+        chapter_index: usize,
+    ) -> impl Future<Output = Result<Vec<u8>, Self::Error>>;
+
+    /// Opened book used for metadata and resource lookups.
+    fn book(&mut self) -> &mut EpubBook<Self::Reader>;
+}
+
+/// Error from an async preparation run.
+#[derive(Debug)]
+pub enum AsyncRenderError<E> {
+    /// The storage backend failed.
+    Source(E),
+    /// Layout failed after the chapter was loaded.
+    Render(RenderEngineError),
+}
+
+impl<E: fmt::Display> fmt::Display for AsyncRenderError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Source(err) => write!(f, "book source failed: {}", err),
+            Self::Render(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for AsyncRenderError<E> {}
+
+impl<E> From<RenderEngineError> for AsyncRenderError<E> {
+    fn from(err: RenderEngineError) -> Self {
+        Self::Render(err)
+    }
+}
+
+type PrepareFuture<'a, E> =
+    Pin<Box<dyn Future<Output = Result<Vec<RenderPage>, AsyncRenderError<E>>> + 'a>>;
+
+/// Pages of one chapter, produced by `RenderEngine::prepare_chapter_stream`.
+///
+/// Yields `Pending` while the backend loads the chapter, then each page in
+/// order. An error ends the stream.
+pub struct RenderPageAsyncStream<'a, E> {
+    state: StreamState<'a, E>,
+}
+
+enum StreamState<'a, E> {
+    Preparing(PrepareFuture<'a, E>),
+    Yielding(std::vec::IntoIter<RenderPage>),
+    Done,
+}
+
+impl<E> fmt::Debug for RenderPageAsyncStream<'_, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self.state {
+            StreamState::Preparing(_) => "preparing",
+            StreamState::Yielding(_) => "yielding",
+            StreamState::Done => "done",
+        };
+        f.debug_struct("RenderPageAsyncStream")
+            .field("state", &state)
+            .finish()
+    }
+}
+
+impl<E> Stream for RenderPageAsyncStream<'_, E> {
+    type Item = Result<RenderPage, AsyncRenderError<E>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match &mut self.state {
+                StreamState::Preparing(prepare) => match prepare.as_mut().poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Ok(pages)) => {
+                        self.state = StreamState::Yielding(pages.into_iter());
+                    }
+                    Poll::Ready(Err(err)) => {
+                        self.state = StreamState::Done;
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                },
+                StreamState::Yielding(pages) => {
+                    let next = pages.next();
+                    if next.is_none() {
+                        self.state = StreamState::Done;
+                    }
+                    return Poll::Ready(next.map(Ok));
+                }
+                StreamState::Done => return Poll::Ready(None),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.state {
+            StreamState::Preparing(_) => (0, None),
+            StreamState::Yielding(pages) => pages.size_hint(),
+            StreamState::Done => (0, Some(0)),
+        }
+    }
+}
+
+impl RenderEngine {
+    /// Async variant of `prepare_chapter`.
+    pub async fn prepare_chapter_async<S: AsyncBookSource>(
+        &self,
+        source: &mut S,
+        chapter_index: usize,
+    ) -> Result<Vec<RenderPage>, AsyncRenderError<S::Error>> {
+        self.prepare_chapter_with_config_async(source, chapter_index, RenderConfig::default())
+            .await
+    }
+
+    /// Async variant of `prepare_chapter_with_config_collect`.
+    ///
+    /// Only the chapter load is awaited; layout runs on the polling task
+    /// once the bytes arrive, with the same memory limits as the sync path.
+    pub async fn prepare_chapter_with_config_async<S: AsyncBookSource>(
+        &self,
+        source: &mut S,
+        chapter_index: usize,
+        config: RenderConfig<'_>,
+    ) -> Result<Vec<RenderPage>, AsyncRenderError<S::Error>> {
+        let html = source
+            .load_chapter(chapter_index)
+            .await
+            .map_err(AsyncRenderError::Source)?;
+        let book = source.book();
+        let pages = self.collect_pages(|on_page| {
+            self.prepare_chapter_bytes_with_config(book, chapter_index, &html, config, on_page)
+        })?;
+        Ok(pages)
+    }
+
+    /// Prepare a chapter as an async page stream.
+    pub fn prepare_chapter_stream<'a, S: AsyncBookSource>(
+        &'a self,
+        source: &'a mut S,
+        chapter_index: usize,
+        config: RenderConfig<'a>,
+    ) -> RenderPageAsyncStream<'a, S::Error> {
+        RenderPageAsyncStream {
+            state: StreamState::Preparing(Box::pin(self.prepare_chapter_with_config_async(
+                source,
+                chapter_index,
+                config,
+            ))),
+        }
+    }
+}
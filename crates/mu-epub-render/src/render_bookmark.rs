@@ -0,0 +1,261 @@
+//! Reader bookmarks addressed by reflow-stable locators.
+
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::render_cache::{decode_bookmark, encode_bookmark, hex_prefix, BookFingerprint};
+use crate::render_ir::{ContentLocator, DrawCommand, PageRect, RectCommand, RenderPage};
+use crate::render_kv::{id_segment, KvStore};
+
+/// Saved place in a book.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bookmark {
+    /// Caller-assigned identifier, unique within a store.
+    pub id: u64,
+    /// Bookmarked position.
+    pub locator: ContentLocator,
+    /// User-facing label.
+    pub label: String,
+    /// Creation time in seconds since the Unix epoch.
+    pub created_unix_secs: u64,
+}
+
+impl Bookmark {
+    /// Bookmark created now.
+    pub fn new(id: u64, locator: ContentLocator, label: impl Into<String>) -> Self {
+        Self {
+            id,
+            locator,
+            label: label.into(),
+            created_unix_secs: unix_now_secs(),
+        }
+    }
+}
+
+/// Wall-clock seconds since the Unix epoch.
+///
+/// `wasm32-unknown-unknown` has no wall clock and reports `0`; callers
+/// there set timestamps from `Date.now()` themselves.
+pub(crate) fn unix_now_secs() -> u64 {
+    if cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+        0
+    } else {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs())
+    }
+}
+
+/// Storage hooks for bookmarks.
+///
+/// Methods take `&self`; implementations use interior mutability so one
+/// store can be shared with `RenderConfig::with_bookmarks`.
+pub trait BookmarkStore {
+    /// Every bookmark, in reading order.
+    fn list_bookmarks(&self) -> Vec<Bookmark>;
+
+    /// Insert or replace the bookmark with the same id.
+    fn save_bookmark(&self, bookmark: &Bookmark);
+
+    /// Remove a bookmark by id.
+    fn remove_bookmark(&self, id: u64);
+
+    /// Bookmarks in `chapter_index`, in reading order.
+    fn bookmarks_for_chapter(&self, chapter_index: usize) -> Vec<Bookmark> {
+        let mut bookmarks = self.list_bookmarks();
+        bookmarks.retain(|bookmark| bookmark.locator.chapter_index == chapter_index);
+        bookmarks
+    }
+}
+
+/// In-memory `BookmarkStore`.
+#[derive(Debug, Default)]
+pub struct MemoryBookmarkStore {
+    bookmarks: Mutex<Vec<Bookmark>>,
+}
+
+impl MemoryBookmarkStore {
+    /// Empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl BookmarkStore for MemoryBookmarkStore {
+    fn list_bookmarks(&self) -> Vec<Bookmark> {
+        let mut bookmarks = self
+            .bookmarks
+            .lock()
+            .map(|all| all.clone())
+            .unwrap_or_default();
+        sort_reading_order(&mut bookmarks);
+        bookmarks
+    }
+
+    fn save_bookmark(&self, bookmark: &Bookmark) {
+        let Ok(mut all) = self.bookmarks.lock() else {
+            return;
+        };
+        match all.iter_mut().find(|known| known.id == bookmark.id) {
+            Some(known) => *known = bookmark.clone(),
+            None => all.push(bookmark.clone()),
+        }
+    }
+
+    fn remove_bookmark(&self, id: u64) {
+        if let Ok(mut all) = self.bookmarks.lock() {
+            all.retain(|bookmark| bookmark.id != id);
+        }
+    }
+}
+
+/// `BookmarkStore` persisting one record per bookmark in a `KvStore`.
+///
+/// Records live under `bookmarks/<book>/<id>`, so one store can also hold
+/// a `KvCacheStore` and a `KvAnnotationStore`. Writes the store rejects
+/// are dropped; unreadable records are skipped.
+#[derive(Clone, Debug)]
+pub struct KvBookmarkStore<S> {
+    store: S,
+    prefix: String,
+}
+
+impl<S: KvStore> KvBookmarkStore<S> {
+    /// Bookmarks of `book` in `store`.
+    pub fn new(store: S, book: BookFingerprint) -> Self {
+        Self {
+            store,
+            prefix: format!("bookmarks/{}/", hex_prefix(&book.0)),
+        }
+    }
+
+    /// Underlying key-value store.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+}
+
+impl<S: KvStore> BookmarkStore for KvBookmarkStore<S> {
+    fn list_bookmarks(&self) -> Vec<Bookmark> {
+        let mut bookmarks: Vec<Bookmark> = self
+            .store
+            .keys_with_prefix(&self.prefix)
+            .iter()
+            .filter_map(|key| decode_bookmark(&self.store.get(key)?).ok())
+            .collect();
+        sort_reading_order(&mut bookmarks);
+        bookmarks
+    }
+
+    fn save_bookmark(&self, bookmark: &Bookmark) {
+        let key = format!("{}{}", self.prefix, id_segment(bookmark.id));
+        let _ = self.store.put(&key, &encode_bookmark(bookmark));
+    }
+
+    fn remove_bookmark(&self, id: u64) {
+        self.store
+            .delete(&format!("{}{}", self.prefix, id_segment(id)));
+    }
+}
+
+fn sort_reading_order(bookmarks: &mut [Bookmark]) {
+    bookmarks.sort_by_key(|bookmark| {
+        (
+            bookmark.locator.chapter_index,
+            bookmark.locator.position,
+            bookmark.id,
+        )
+    });
+}
+
+impl RenderPage {
+    /// True when `locator` points at content laid out on this page.
+    ///
+    /// Uses recorded line positions, so pages built without them only match
+    /// their exact start position.
+    pub fn contains_locator(&self, locator: &ContentLocator) -> bool {
+        if locator.chapter_index != self.metrics.chapter_index {
+            return false;
+        }
+        let position = locator.position;
+        self.metrics.start_position == Some(position)
+            || self.line_spans().any(|(_, len, start)| {
+                start.block_index == position.block_index
+                    && (start.char_offset..start.char_offset + len.max(1))
+                        .contains(&position.char_offset)
+            })
+    }
+
+    /// Bookmarks pointing at this page.
+    pub fn bookmarks_on_page<'b>(&self, bookmarks: &'b [Bookmark]) -> Vec<&'b Bookmark> {
+        bookmarks
+            .iter()
+            .filter(|bookmark| self.contains_locator(&bookmark.locator))
+            .collect()
+    }
+
+    /// Draw a filled bookmark ribbon over `area` on the chrome layer.
+    pub fn push_bookmark_indicator(&mut self, area: PageRect) {
+        self.push_chrome_command(DrawCommand::Rect(RectCommand {
+            x: area.x,
+            y: area.y,
+            width: area.width,
+            height: area.height,
+            fill: true,
+            color: None,
+        }));
+        if let Some(damage) = self.damage.as_mut() {
+            damage.push(area);
+        }
+        self.sync_commands();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_ir::TextPosition;
+
+    fn at(chapter_index: usize, block_index: usize, char_offset: usize) -> ContentLocator {
+        ContentLocator::new(
+            chapter_index,
+            TextPosition {
+                block_index,
+                char_offset,
+            },
+        )
+    }
+
+    #[test]
+    fn store_lists_in_reading_order_and_pages_match_locators() {
+        let store = MemoryBookmarkStore::new();
+        store.save_bookmark(&Bookmark::new(1, at(2, 0, 0), "later"));
+        store.save_bookmark(&Bookmark::new(2, at(1, 3, 5), "earlier"));
+        store.save_bookmark(&Bookmark::new(1, at(2, 4, 0), "moved"));
+        let labels: Vec<String> = store
+            .list_bookmarks()
+            .into_iter()
+            .map(|bookmark| bookmark.label)
+            .collect();
+        assert_eq!(labels, ["earlier", "moved"]);
+        assert_eq!(store.bookmarks_for_chapter(1).len(), 1);
+        store.remove_bookmark(2);
+        assert!(store.bookmarks_for_chapter(1).is_empty());
+
+        let mut page = RenderPage::new(1);
+        page.metrics.chapter_index = 1;
+        page.metrics.start_position = Some(TextPosition {
+            block_index: 3,
+            char_offset: 0,
+        });
+        let bookmarks = [
+            Bookmark::new(3, at(1, 3, 0), "start"),
+            Bookmark::new(4, at(2, 3, 0), "other chapter"),
+        ];
+        assert_eq!(page.bookmarks_on_page(&bookmarks).len(), 1);
+        assert!(!page.contains_locator(&at(1, 3, 5)));
+
+        page.push_bookmark_indicator(PageRect::new(460, 0, 10, 20));
+        assert_eq!(page.chrome_commands.len(), 1);
+    }
+}
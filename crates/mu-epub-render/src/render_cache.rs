@@ -0,0 +1,1987 @@
+//! Versioned on-disk pagination cache format, a directory-backed store,
+//! and a bounded in-memory LRU store.
+//!
+//! One file holds one chapter's pages:
+//!
+//! ```text
+//! magic "MUPC" | version u16 | profile [u8; 32] | book [u8; 32]
+//! | chapter u32 | page count u32 | pages... | crc32 u32
+//! ```
+//!
+//! Integers are little-endian; the trailing CRC-32 covers every preceding
+//! byte. Overlay items are not persisted since composers re-attach them
+//! after layout.
+//!
+//! Styled chapter IR (`ChapterIr`) uses the same encoding under magic
+//! `"MUPI"` and its own version.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use mu_epub::{
+    BlockRole, ChapterRef, Color, ComputedTextStyle, EpubBook, LayoutHints, StyledEvent,
+    StyledEventOrRun, StyledImage, StyledRun,
+};
+
+use crate::render_annotate::{Annotation, AnnotationStyle};
+use crate::render_bookmark::Bookmark;
+use crate::render_engine::{CacheCapacity, CacheWritePolicy, RenderCacheStore};
+use crate::render_ir::{
+    ContentLocator, DrawCommand, GlyphPosition, GrayBitmap, ImageCommand, InternalLink,
+    JustifyMode, LayeredCommand, LinkTarget, PageAnnotation, PageChromeCommand, PageChromeKind,
+    PageMetrics, PageRect, PaginationProfileId, RectCommand, RenderPage, ResolvedTextStyle,
+    RuleCommand, TextCommand, TextPosition, TextRenderHint, VerticalMetrics,
+};
+use crate::render_kv::{DirKvStore, KvStore};
+use crate::render_sync::SyncPosition;
+
+/// Current cache file format version.
+///
+/// Bump on any change to the encoding; older files are rejected rather
+/// than misread.
+pub const CACHE_FORMAT_VERSION: u16 = 6;
+
+/// Current `PaginationProfile` derivation version.
+///
+/// Bump when fields are added, renamed, or formatted differently, so
+/// stored profiles report a version change instead of spurious field
+/// changes.
+pub const PAGINATION_PROFILE_VERSION: u16 = 1;
+
+const MAGIC: [u8; 4] = *b"MUPC";
+const PROFILE_MAGIC: [u8; 4] = *b"MUPP";
+const IR_MAGIC: [u8; 4] = *b"MUPI";
+const SNAPSHOT_MAGIC: [u8; 4] = *b"MUPS";
+const POSITION_MAGIC: [u8; 4] = *b"MURP";
+const BOOKMARK_MAGIC: [u8; 4] = *b"MUBM";
+const ANNOTATION_MAGIC: [u8; 4] = *b"MUAN";
+
+/// Encoding version of bookmark and annotation records in a `KvStore`.
+const RECORD_VERSION: u16 = 1;
+
+/// Current `ChapterIr` encoding version.
+pub const CHAPTER_IR_VERSION: u16 = 3;
+
+/// Current `SessionSnapshot` encoding version.
+pub const SESSION_SNAPSHOT_VERSION: u16 = 1;
+
+/// Current `SyncPosition` encoding version.
+pub const SYNC_POSITION_VERSION: u16 = 1;
+const HEADER_LEN: usize = 4 + 2 + 32 + 32 + 4 + 4;
+const MAX_LAYER_DEPTH: u8 = 8;
+
+/// Stable identity of a book's content for cache keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BookFingerprint(pub [u8; 32]);
+
+impl BookFingerprint {
+    /// Fingerprint from package identifier, modification date, title, and
+    /// spine order.
+    pub fn from_book<R: std::io::Read + std::io::Seek>(book: &EpubBook<R>) -> Self {
+        let metadata = book.metadata();
+        let mut payload = format!(
+            "{}|{}|{}",
+            metadata.identifier.as_deref().unwrap_or_default(),
+            metadata.modified.as_deref().unwrap_or_default(),
+            metadata.title
+        );
+        for chapter in book.chapters() {
+            payload.push('|');
+            payload.push_str(&chapter.href);
+        }
+        Self(PaginationProfileId::from_bytes(payload.as_bytes()).0)
+    }
+}
+
+/// Key identifying one cached chapter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// Pagination profile the pages were laid out under.
+    pub profile: PaginationProfileId,
+    /// Book the pages belong to.
+    pub book: BookFingerprint,
+    /// Spine index of the chapter.
+    pub chapter_index: usize,
+}
+
+/// Pagination-relevant engine settings behind a `PaginationProfileId`.
+///
+/// Built by `RenderEngine::pagination_profile`, one named field per
+/// setting. Persist it with `encode` next to cached pages and compare the
+/// stored copy with `changes_from` to explain why a repagination is needed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaginationProfile {
+    /// Derivation version the fields were built with.
+    pub version: u16,
+    /// `(name, value)` pairs in derivation order.
+    pub fields: Vec<(String, String)>,
+}
+
+impl PaginationProfile {
+    /// Profile at the current derivation version.
+    pub fn new(fields: Vec<(String, String)>) -> Self {
+        Self {
+            version: PAGINATION_PROFILE_VERSION,
+            fields,
+        }
+    }
+
+    /// Id derived from the encoded profile.
+    pub fn id(&self) -> PaginationProfileId {
+        PaginationProfileId::from_bytes(&self.encode())
+    }
+
+    /// Value of field `name`.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Encode as `magic "MUPP" | version u16 | count u32 | (name, value)...`.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut enc = Encoder(Vec::with_capacity(16 + self.fields.len() * 48));
+        enc.0.extend_from_slice(&PROFILE_MAGIC);
+        enc.u16(self.version);
+        enc.u32(self.fields.len() as u32);
+        for (name, value) in &self.fields {
+            enc.str(name);
+            enc.str(value);
+        }
+        enc.0
+    }
+
+    /// Decode a profile written by `encode`, at any derivation version.
+    pub fn decode(bytes: &[u8]) -> Result<Self, CacheDecodeError> {
+        if bytes.len() < PROFILE_MAGIC.len() || bytes[..PROFILE_MAGIC.len()] != PROFILE_MAGIC {
+            return Err(CacheDecodeError::BadMagic);
+        }
+        let mut dec = Decoder {
+            buf: bytes,
+            pos: PROFILE_MAGIC.len(),
+        };
+        let version = dec.u16()?;
+        let count = dec.len()?;
+        let mut fields = Vec::with_capacity(count);
+        for _ in 0..count {
+            fields.push((dec.str()?, dec.str()?));
+        }
+        if dec.pos != bytes.len() {
+            return Err(CacheDecodeError::Malformed("trailing bytes"));
+        }
+        Ok(Self { version, fields })
+    }
+
+    /// Fields that differ from `stored`, in this profile's order.
+    ///
+    /// A version change is reported first as a `version` field. Empty when
+    /// pages laid out under `stored` are still valid.
+    pub fn changes_from(&self, stored: &Self) -> Vec<ProfileChange> {
+        let mut changes = Vec::with_capacity(0);
+        if self.version != stored.version {
+            changes.push(ProfileChange {
+                field: "version".to_string(),
+                stored: Some(stored.version.to_string()),
+                current: Some(self.version.to_string()),
+            });
+        }
+        for (name, value) in &self.fields {
+            let before = stored.field(name);
+            if before != Some(value.as_str()) {
+                changes.push(ProfileChange {
+                    field: name.clone(),
+                    stored: before.map(str::to_string),
+                    current: Some(value.clone()),
+                });
+            }
+        }
+        for (name, value) in &stored.fields {
+            if self.field(name).is_none() {
+                changes.push(ProfileChange {
+                    field: name.clone(),
+                    stored: Some(value.clone()),
+                    current: None,
+                });
+            }
+        }
+        changes
+    }
+}
+
+/// One field that differs between a stored and the current profile.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProfileChange {
+    /// Field name, e.g. `prep.style.hints.base_font_size_px`.
+    pub field: String,
+    /// Value in the stored profile; `None` when the field is new.
+    pub stored: Option<String>,
+    /// Current value; `None` when the field no longer exists.
+    pub current: Option<String>,
+}
+
+impl core::fmt::Display for ProfileChange {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match (&self.stored, &self.current) {
+            (Some(stored), Some(current)) => {
+                write!(f, "{} changed from {} to {}", self.field, stored, current)
+            }
+            (None, Some(current)) => write!(f, "{} added as {}", self.field, current),
+            (Some(stored), None) => write!(f, "{} removed (was {})", self.field, stored),
+            (None, None) => write!(f, "{} changed", self.field),
+        }
+    }
+}
+
+/// Reading state of a `LayoutSession`, for resuming after power-off.
+///
+/// Taken with `LayoutSession::snapshot` and restored with
+/// `LayoutSession::restore`. Page counts and warm chapters only apply
+/// while the engine's pagination profile still equals `profile`; the
+/// locator is reflow-stable and always applies.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionSnapshot {
+    /// Start of the page on screen.
+    pub locator: ContentLocator,
+    /// Pagination profile the counts below were measured under.
+    pub profile: PaginationProfileId,
+    /// Page count of every chapter in spine order; empty when unknown.
+    pub book_page_counts: Vec<usize>,
+    /// `(chapter_index, page_count)` of chapters held laid out, whose
+    /// cache entries under `profile` are likely warm.
+    pub warm_chapters: Vec<(usize, usize)>,
+}
+
+impl SessionSnapshot {
+    /// Encode with a trailing CRC-32.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut enc = Encoder(Vec::with_capacity(
+            96 + (self.book_page_counts.len() + self.warm_chapters.len() * 2) * 4,
+        ));
+        enc.0.extend_from_slice(&SNAPSHOT_MAGIC);
+        enc.u16(SESSION_SNAPSHOT_VERSION);
+        enc.locator(&self.locator);
+        enc.0.extend_from_slice(&self.profile.0);
+        enc.u32(self.book_page_counts.len() as u32);
+        for &count in &self.book_page_counts {
+            enc.u32(count as u32);
+        }
+        enc.u32(self.warm_chapters.len() as u32);
+        for &(chapter_index, pages) in &self.warm_chapters {
+            enc.u32(chapter_index as u32);
+            enc.u32(pages as u32);
+        }
+        enc.finish_with_crc()
+    }
+
+    /// Decode a snapshot written by `encode`.
+    pub fn decode(bytes: &[u8]) -> Result<Self, CacheDecodeError> {
+        let mut dec = Decoder::framed(bytes, SNAPSHOT_MAGIC, SESSION_SNAPSHOT_VERSION)?;
+        let locator = dec.locator()?;
+        let profile = PaginationProfileId(dec.array32()?);
+        let count = dec.len()?;
+        let mut book_page_counts = Vec::with_capacity(count);
+        for _ in 0..count {
+            book_page_counts.push(dec.u32()? as usize);
+        }
+        let count = dec.len()?;
+        let mut warm_chapters = Vec::with_capacity(count);
+        for _ in 0..count {
+            warm_chapters.push((dec.u32()? as usize, dec.u32()? as usize));
+        }
+        dec.finish()?;
+        Ok(Self {
+            locator,
+            profile,
+            book_page_counts,
+            warm_chapters,
+        })
+    }
+}
+
+/// A chapter tokenized and styled once, for layout without the book.
+///
+/// Built by `RenderEngine::prepare_chapter_ir` and laid out by
+/// `RenderEngine::prepare_from_ir`, possibly on another engine: font sizes
+/// are rescaled by the change in base size times text scale, which also
+/// moves CSS pixel sizes that a fresh prep would keep. External SVG markup and
+/// raster intrinsic sizes are captured, so only raster pixels still need
+/// the book.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChapterIr {
+    /// Spine index of the chapter.
+    pub chapter_index: usize,
+    /// Style hints the items were resolved with.
+    pub hints: LayoutHints,
+    /// Navigation title used for page chrome.
+    pub chapter_title: Option<String>,
+    /// Spine of the book, for resolving internal links.
+    pub spine: Vec<ChapterRef>,
+    /// Styled blocks and runs in document order.
+    pub items: Vec<StyledEventOrRun>,
+}
+
+impl ChapterIr {
+    /// Encode with a trailing CRC-32.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut enc = Encoder(Vec::with_capacity(64 + self.items.len() * 64));
+        enc.0.extend_from_slice(&IR_MAGIC);
+        enc.u16(CHAPTER_IR_VERSION);
+        enc.u32(self.chapter_index as u32);
+        let hints = &self.hints;
+        for value in [
+            hints.base_font_size_px,
+            hints.min_font_size_px,
+            hints.max_font_size_px,
+            hints.min_line_height,
+            hints.max_line_height,
+            hints.text_scale,
+            hints.dpi,
+        ] {
+            enc.f32(value);
+        }
+        enc.opt(self.chapter_title.as_deref(), Encoder::str);
+        enc.u32(self.spine.len() as u32);
+        for chapter in &self.spine {
+            enc.u32(chapter.index as u32);
+            enc.str(&chapter.idref);
+            enc.str(&chapter.href);
+            enc.str(&chapter.media_type);
+        }
+        enc.u32(self.items.len() as u32);
+        for item in &self.items {
+            enc.styled_item(item);
+        }
+        let crc = crc32fast::hash(&enc.0);
+        enc.u32(crc);
+        enc.0
+    }
+
+    /// Decode IR written by `encode`.
+    pub fn decode(bytes: &[u8]) -> Result<Self, CacheDecodeError> {
+        if bytes.len() < IR_MAGIC.len() || bytes[..IR_MAGIC.len()] != IR_MAGIC {
+            return Err(CacheDecodeError::BadMagic);
+        }
+        if bytes.len() < IR_MAGIC.len() + 2 + 4 {
+            return Err(CacheDecodeError::Truncated);
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != CHAPTER_IR_VERSION {
+            return Err(CacheDecodeError::UnsupportedVersion(version));
+        }
+        let (body, crc) = bytes.split_at(bytes.len() - 4);
+        let crc = u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]]);
+        if crc32fast::hash(body) != crc {
+            return Err(CacheDecodeError::ChecksumMismatch);
+        }
+        let mut dec = Decoder { buf: body, pos: 6 };
+        let chapter_index = dec.u32()? as usize;
+        let hints = LayoutHints {
+            base_font_size_px: dec.f32()?,
+            min_font_size_px: dec.f32()?,
+            max_font_size_px: dec.f32()?,
+            min_line_height: dec.f32()?,
+            max_line_height: dec.f32()?,
+            text_scale: dec.f32()?,
+            dpi: dec.f32()?,
+        };
+        let chapter_title = dec.opt(Decoder::str)?;
+        let count = dec.len()?;
+        let mut spine = Vec::with_capacity(count);
+        for _ in 0..count {
+            spine.push(ChapterRef {
+                index: dec.u32()? as usize,
+                idref: dec.str()?,
+                href: dec.str()?,
+                media_type: dec.str()?,
+            });
+        }
+        let count = dec.len()?;
+        let mut items = Vec::with_capacity(count);
+        for _ in 0..count {
+            items.push(dec.styled_item()?);
+        }
+        if dec.pos != body.len() {
+            return Err(CacheDecodeError::Malformed("trailing bytes"));
+        }
+        Ok(Self {
+            chapter_index,
+            hints,
+            chapter_title,
+            spine,
+            items,
+        })
+    }
+
+    /// Items with run sizes rescaled from the captured hints to `hints`.
+    pub(crate) fn items_for<'a>(
+        &'a self,
+        hints: &'a LayoutHints,
+    ) -> impl Iterator<Item = StyledEventOrRun> + 'a {
+        let scale =
+            |hints: &LayoutHints| hints.base_font_size_px * hints.text_scale.clamp(0.5, 3.0);
+        let ratio = scale(hints) / scale(&self.hints);
+        let same = *hints == self.hints;
+        self.items.iter().map(move |item| match item {
+            StyledEventOrRun::Run(run) if !same => {
+                let mut run = run.clone();
+                let style = &mut run.style;
+                if ratio.is_finite() && ratio > 0.0 {
+                    style.size_px *= ratio;
+                }
+                style.size_px = style
+                    .size_px
+                    .clamp(hints.min_font_size_px, hints.max_font_size_px);
+                style.line_height = style
+                    .line_height
+                    .clamp(hints.min_line_height, hints.max_line_height);
+                StyledEventOrRun::Run(run)
+            }
+            other => other.clone(),
+        })
+    }
+}
+
+/// Why a cache file could not be decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CacheDecodeError {
+    /// File does not start with the cache magic.
+    BadMagic,
+    /// File was written by a different format version.
+    UnsupportedVersion(u16),
+    /// File belongs to a different profile, book, or chapter.
+    KeyMismatch,
+    /// File ended early.
+    Truncated,
+    /// Trailing checksum does not match the contents.
+    ChecksumMismatch,
+    /// Contents are structurally invalid.
+    Malformed(&'static str),
+}
+
+impl core::fmt::Display for CacheDecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "not a pagination cache file"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported cache format version {}", version)
+            }
+            Self::KeyMismatch => write!(f, "cache key mismatch"),
+            Self::Truncated => write!(f, "cache file truncated"),
+            Self::ChecksumMismatch => write!(f, "cache checksum mismatch"),
+            Self::Malformed(what) => write!(f, "malformed cache file: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for CacheDecodeError {}
+
+/// Encode one chapter's pages into the versioned cache format.
+pub fn encode_chapter_pages(key: &CacheKey, pages: &[RenderPage]) -> Vec<u8> {
+    let mut enc = Encoder(Vec::with_capacity(HEADER_LEN + pages.len() * 512));
+    enc.0.extend_from_slice(&MAGIC);
+    enc.u16(CACHE_FORMAT_VERSION);
+    enc.0.extend_from_slice(&key.profile.0);
+    enc.0.extend_from_slice(&key.book.0);
+    enc.u32(key.chapter_index as u32);
+    enc.u32(pages.len() as u32);
+    for page in pages {
+        enc.page(page);
+    }
+    let crc = crc32fast::hash(&enc.0);
+    enc.u32(crc);
+    enc.0
+}
+
+/// Decode a cache file written by `encode_chapter_pages`.
+///
+/// The header must match `key` exactly; any mismatch, version change, or
+/// checksum failure is reported instead of returning partial pages.
+pub fn decode_chapter_pages(
+    bytes: &[u8],
+    key: &CacheKey,
+) -> Result<Vec<RenderPage>, CacheDecodeError> {
+    if bytes.len() < MAGIC.len() || bytes[..MAGIC.len()] != MAGIC {
+        return Err(CacheDecodeError::BadMagic);
+    }
+    if bytes.len() < HEADER_LEN + 4 {
+        return Err(CacheDecodeError::Truncated);
+    }
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != CACHE_FORMAT_VERSION {
+        return Err(CacheDecodeError::UnsupportedVersion(version));
+    }
+    let (body, crc) = bytes.split_at(bytes.len() - 4);
+    let crc = u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]]);
+    if crc32fast::hash(body) != crc {
+        return Err(CacheDecodeError::ChecksumMismatch);
+    }
+    let mut dec = Decoder { buf: body, pos: 6 };
+    let profile = dec.array32()?;
+    let book = dec.array32()?;
+    let chapter_index = dec.u32()? as usize;
+    if profile != key.profile.0 || book != key.book.0 || chapter_index != key.chapter_index {
+        return Err(CacheDecodeError::KeyMismatch);
+    }
+    let count = dec.len()?;
+    let mut pages = Vec::with_capacity(count);
+    for _ in 0..count {
+        pages.push(dec.page()?);
+    }
+    if dec.pos != body.len() {
+        return Err(CacheDecodeError::Malformed("trailing bytes"));
+    }
+    Ok(pages)
+}
+
+/// `RenderCacheStore` over any `KvStore`, one value per chapter.
+///
+/// Keys are `<profile>/<book>/<chapter>.mupc` (16-hex-digit prefixes of
+/// the ids), so a `DirKvStore` yields the same layout as `DirCacheStore`.
+/// Corrupt values are treated as misses and deleted; values the store
+/// rejects (e.g. over its size limits) are simply not cached.
+#[derive(Clone, Debug)]
+pub struct KvCacheStore<S> {
+    store: S,
+    book: BookFingerprint,
+    capacity: Option<CacheCapacity>,
+}
+
+impl<S: KvStore> KvCacheStore<S> {
+    /// Cache for `book` in `store`.
+    pub fn new(store: S, book: BookFingerprint) -> Self {
+        Self {
+            store,
+            book,
+            capacity: None,
+        }
+    }
+
+    /// Ask the engine to keep this store within `capacity`, deleting the
+    /// least recently rendered chapters first.
+    pub fn with_capacity(mut self, capacity: CacheCapacity) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Underlying key-value store.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Key prefix holding every chapter of this book under `profile`.
+    pub fn profile_prefix(&self, profile: PaginationProfileId) -> String {
+        format!("{}/{}/", hex_prefix(&profile.0), hex_prefix(&self.book.0))
+    }
+
+    /// Key for one cached chapter.
+    pub fn chapter_key(&self, profile: PaginationProfileId, chapter_index: usize) -> String {
+        format!("{}{}.mupc", self.profile_prefix(profile), chapter_index)
+    }
+
+    /// Remove every cached chapter for this store's book and `profile`.
+    pub fn clear_profile(&self, profile: PaginationProfileId) {
+        for key in self.store.keys_with_prefix(&self.profile_prefix(profile)) {
+            self.store.delete(&key);
+        }
+    }
+
+    fn key(&self, profile: PaginationProfileId, chapter_index: usize) -> CacheKey {
+        CacheKey {
+            profile,
+            book: self.book,
+            chapter_index,
+        }
+    }
+}
+
+impl<S: KvStore> RenderCacheStore for KvCacheStore<S> {
+    fn load_chapter_pages(
+        &self,
+        profile: PaginationProfileId,
+        chapter_index: usize,
+    ) -> Option<Vec<RenderPage>> {
+        let key = self.chapter_key(profile, chapter_index);
+        let bytes = self.store.get(&key)?;
+        match decode_chapter_pages(&bytes, &self.key(profile, chapter_index)) {
+            Ok(pages) => Some(pages),
+            Err(_) => {
+                // Best effort: a bad value would otherwise miss forever.
+                self.store.delete(&key);
+                None
+            }
+        }
+    }
+
+    fn store_chapter_pages(
+        &self,
+        profile: PaginationProfileId,
+        chapter_index: usize,
+        pages: &[RenderPage],
+    ) {
+        let bytes = encode_chapter_pages(&self.key(profile, chapter_index), pages);
+        let _ = self
+            .store
+            .put(&self.chapter_key(profile, chapter_index), &bytes);
+    }
+
+    fn capacity_hint(&self) -> Option<CacheCapacity> {
+        self.capacity
+    }
+
+    fn evict_chapter_pages(&self, profile: PaginationProfileId, chapter_index: usize) {
+        self.store.delete(&self.chapter_key(profile, chapter_index));
+    }
+}
+
+/// `RenderCacheStore` backed by one file per chapter under a directory.
+///
+/// A `KvCacheStore` over a `DirKvStore`: files live at
+/// `<root>/<profile>/<book>/<chapter>.mupc`, writes go to a temporary file
+/// first and are renamed into place, and unreadable or corrupt files are
+/// treated as misses and removed. I/O errors never fail a render.
+#[derive(Clone, Debug)]
+pub struct DirCacheStore {
+    inner: KvCacheStore<DirKvStore>,
+}
+
+impl DirCacheStore {
+    /// Cache for `book` rooted at `root` (created on first store).
+    pub fn new(root: impl Into<PathBuf>, book: BookFingerprint) -> Self {
+        Self {
+            inner: KvCacheStore::new(DirKvStore::new(root), book),
+        }
+    }
+
+    /// Ask the engine to keep this store within `capacity`, deleting the
+    /// least recently rendered chapter files first.
+    pub fn with_capacity(mut self, capacity: CacheCapacity) -> Self {
+        self.inner = self.inner.with_capacity(capacity);
+        self
+    }
+
+    /// Root directory.
+    pub fn root(&self) -> &Path {
+        self.inner.store().root()
+    }
+
+    /// File path for one cached chapter.
+    pub fn chapter_path(&self, profile: PaginationProfileId, chapter_index: usize) -> PathBuf {
+        self.inner
+            .store()
+            .path_for(&self.inner.chapter_key(profile, chapter_index))
+    }
+
+    /// Remove every cached chapter for this store's book and `profile`.
+    pub fn clear_profile(&self, profile: PaginationProfileId) -> io::Result<()> {
+        let dir = self
+            .inner
+            .store()
+            .path_for(&self.inner.profile_prefix(profile));
+        match fs::remove_dir_all(dir) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            other => other,
+        }
+    }
+}
+
+impl RenderCacheStore for DirCacheStore {
+    fn load_chapter_pages(
+        &self,
+        profile: PaginationProfileId,
+        chapter_index: usize,
+    ) -> Option<Vec<RenderPage>> {
+        self.inner.load_chapter_pages(profile, chapter_index)
+    }
+
+    fn store_chapter_pages(
+        &self,
+        profile: PaginationProfileId,
+        chapter_index: usize,
+        pages: &[RenderPage],
+    ) {
+        self.inner
+            .store_chapter_pages(profile, chapter_index, pages);
+    }
+
+    fn capacity_hint(&self) -> Option<CacheCapacity> {
+        self.inner.capacity_hint()
+    }
+
+    fn evict_chapter_pages(&self, profile: PaginationProfileId, chapter_index: usize) {
+        self.inner.evict_chapter_pages(profile, chapter_index);
+    }
+}
+
+/// Chapters in least-recently-rendered order, for engine-driven eviction.
+#[derive(Debug, Default)]
+pub(crate) struct CacheLedger {
+    entries: VecDeque<LedgerEntry>,
+    bytes: usize,
+}
+
+#[derive(Debug)]
+struct LedgerEntry {
+    profile: PaginationProfileId,
+    chapter_index: usize,
+    bytes: usize,
+}
+
+impl CacheLedger {
+    /// Mark a chapter most recently rendered and return the chapters to
+    /// evict to get back within `capacity`, oldest first.
+    pub(crate) fn touch(
+        &mut self,
+        profile: PaginationProfileId,
+        chapter_index: usize,
+        bytes: usize,
+        capacity: CacheCapacity,
+    ) -> Vec<(PaginationProfileId, usize)> {
+        if let Some(pos) = self
+            .entries
+            .iter()
+            .position(|entry| entry.profile == profile && entry.chapter_index == chapter_index)
+        {
+            if let Some(old) = self.entries.remove(pos) {
+                self.bytes -= old.bytes;
+            }
+        }
+        self.entries.push_back(LedgerEntry {
+            profile,
+            chapter_index,
+            bytes,
+        });
+        self.bytes += bytes;
+        let mut evicted = Vec::with_capacity(0);
+        // The chapter just touched is never evicted.
+        while self.entries.len() > 1 && capacity.exceeded_by(self.entries.len(), self.bytes) {
+            let Some(oldest) = self.entries.pop_front() else {
+                break;
+            };
+            self.bytes -= oldest.bytes;
+            evicted.push((oldest.profile, oldest.chapter_index));
+        }
+        evicted
+    }
+}
+
+/// Bounded in-memory `RenderCacheStore` dropping least recently used
+/// chapters.
+///
+/// On its own it keeps recent chapters in RAM. With `with_backing` it
+/// fronts a slower store, such as a `DirCacheStore` on flash, write-back:
+/// stores stay in memory and reach the backing store only when a dirty
+/// chapter is evicted or on `flush`, and misses fall through to it.
+pub struct LruCacheStore {
+    capacity: CacheCapacity,
+    entries: Mutex<VecDeque<LruEntry>>,
+    backing: Option<Box<dyn RenderCacheStore>>,
+}
+
+struct LruEntry {
+    profile: PaginationProfileId,
+    chapter_index: usize,
+    pages: Vec<RenderPage>,
+    bytes: usize,
+    dirty: bool,
+}
+
+impl LruCacheStore {
+    /// In-memory store bounded by `capacity`.
+    pub fn new(capacity: CacheCapacity) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::new()),
+            backing: None,
+        }
+    }
+
+    /// Write back to `backing` instead of dropping evicted chapters.
+    pub fn with_backing(mut self, backing: impl RenderCacheStore + 'static) -> Self {
+        self.backing = Some(Box::new(backing));
+        self
+    }
+
+    /// Chapters held in memory, least recently used first.
+    pub fn chapters(&self) -> Vec<(PaginationProfileId, usize)> {
+        self.entries
+            .lock()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .map(|entry| (entry.profile, entry.chapter_index))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Insert as most recently used and evict past capacity.
+    fn insert(&self, entry: LruEntry) {
+        if !self.capacity.admits(entry.bytes) {
+            self.write_back(&entry);
+            return;
+        }
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        entries.retain(|known| {
+            known.profile != entry.profile || known.chapter_index != entry.chapter_index
+        });
+        entries.push_back(entry);
+        let mut evicted = Vec::with_capacity(0);
+        loop {
+            let bytes = entries.iter().map(|entry| entry.bytes).sum();
+            if entries.len() <= 1 || !self.capacity.exceeded_by(entries.len(), bytes) {
+                break;
+            }
+            evicted.extend(entries.pop_front());
+        }
+        drop(entries);
+        evicted.iter().for_each(|entry| self.write_back(entry));
+    }
+
+    fn write_back(&self, entry: &LruEntry) {
+        if let (Some(backing), true) = (&self.backing, entry.dirty) {
+            backing.store_chapter_pages(entry.profile, entry.chapter_index, &entry.pages);
+        }
+    }
+}
+
+impl core::fmt::Debug for LruCacheStore {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LruCacheStore")
+            .field("capacity", &self.capacity)
+            .field("chapters", &self.chapters().len())
+            .field("backed", &self.backing.is_some())
+            .finish()
+    }
+}
+
+impl RenderCacheStore for LruCacheStore {
+    fn load_chapter_pages(
+        &self,
+        profile: PaginationProfileId,
+        chapter_index: usize,
+    ) -> Option<Vec<RenderPage>> {
+        if let Ok(mut entries) = self.entries.lock() {
+            let pos = entries
+                .iter()
+                .position(|entry| entry.profile == profile && entry.chapter_index == chapter_index);
+            if let Some(entry) = pos.and_then(|pos| entries.remove(pos)) {
+                let pages = entry.pages.clone();
+                entries.push_back(entry);
+                return Some(pages);
+            }
+        }
+        let pages = self
+            .backing
+            .as_ref()?
+            .load_chapter_pages(profile, chapter_index)?;
+        self.insert(LruEntry {
+            profile,
+            chapter_index,
+            bytes: pages.iter().map(RenderPage::approx_bytes).sum(),
+            pages: pages.clone(),
+            dirty: false,
+        });
+        Some(pages)
+    }
+
+    fn store_chapter_pages(
+        &self,
+        profile: PaginationProfileId,
+        chapter_index: usize,
+        pages: &[RenderPage],
+    ) {
+        self.insert(LruEntry {
+            profile,
+            chapter_index,
+            bytes: pages.iter().map(RenderPage::approx_bytes).sum(),
+            pages: pages.to_vec(),
+            dirty: true,
+        });
+    }
+
+    fn write_policy(&self) -> CacheWritePolicy {
+        if self.backing.is_some() {
+            CacheWritePolicy::WriteBack
+        } else {
+            CacheWritePolicy::WriteThrough
+        }
+    }
+
+    fn evict_chapter_pages(&self, profile: PaginationProfileId, chapter_index: usize) {
+        if let Ok(mut entries) = self.entries.lock() {
+            // Evicted on request, so dirty pages are not written back.
+            entries
+                .retain(|entry| entry.profile != profile || entry.chapter_index != chapter_index);
+        }
+        if let Some(backing) = &self.backing {
+            backing.evict_chapter_pages(profile, chapter_index);
+        }
+    }
+
+    fn flush(&self) {
+        let Some(backing) = &self.backing else {
+            return;
+        };
+        if let Ok(mut entries) = self.entries.lock() {
+            for entry in entries.iter_mut().filter(|entry| entry.dirty) {
+                backing.store_chapter_pages(entry.profile, entry.chapter_index, &entry.pages);
+                entry.dirty = false;
+            }
+        }
+        backing.flush();
+    }
+}
+
+/// Encode a bookmark for a `KvStore`.
+pub(crate) fn encode_bookmark(bookmark: &Bookmark) -> Vec<u8> {
+    let mut enc = Encoder(Vec::with_capacity(64 + bookmark.label.len()));
+    enc.0.extend_from_slice(&BOOKMARK_MAGIC);
+    enc.u16(RECORD_VERSION);
+    enc.u64(bookmark.id);
+    enc.locator(&bookmark.locator);
+    enc.str(&bookmark.label);
+    enc.u64(bookmark.created_unix_secs);
+    enc.finish_with_crc()
+}
+
+/// Decode a bookmark written by `encode_bookmark`.
+pub(crate) fn decode_bookmark(bytes: &[u8]) -> Result<Bookmark, CacheDecodeError> {
+    let mut dec = Decoder::framed(bytes, BOOKMARK_MAGIC, RECORD_VERSION)?;
+    let bookmark = Bookmark {
+        id: dec.u64()?,
+        locator: dec.locator()?,
+        label: dec.str()?,
+        created_unix_secs: dec.u64()?,
+    };
+    dec.finish()?;
+    Ok(bookmark)
+}
+
+/// Encode a sync position with a trailing CRC-32.
+pub(crate) fn encode_sync_position(position: &SyncPosition) -> Vec<u8> {
+    let mut enc = Encoder(Vec::with_capacity(96));
+    enc.0.extend_from_slice(&POSITION_MAGIC);
+    enc.u16(SYNC_POSITION_VERSION);
+    enc.0.extend_from_slice(&position.book.0);
+    enc.locator(&position.locator);
+    enc.u64(position.updated_unix_secs);
+    enc.finish_with_crc()
+}
+
+/// Decode a sync position written by `encode_sync_position`.
+pub(crate) fn decode_sync_position(bytes: &[u8]) -> Result<SyncPosition, CacheDecodeError> {
+    let mut dec = Decoder::framed(bytes, POSITION_MAGIC, SYNC_POSITION_VERSION)?;
+    let position = SyncPosition {
+        book: BookFingerprint(dec.array32()?),
+        locator: dec.locator()?,
+        updated_unix_secs: dec.u64()?,
+    };
+    dec.finish()?;
+    Ok(position)
+}
+
+/// Encode an annotation for a `KvStore`.
+pub(crate) fn encode_annotation(annotation: &Annotation) -> Vec<u8> {
+    let note_len = annotation.note.as_deref().map_or(0, str::len);
+    let mut enc = Encoder(Vec::with_capacity(96 + note_len));
+    enc.0.extend_from_slice(&ANNOTATION_MAGIC);
+    enc.u16(RECORD_VERSION);
+    enc.u64(annotation.id);
+    enc.locator(&annotation.start);
+    enc.locator(&annotation.end);
+    enc.u8(match annotation.style {
+        AnnotationStyle::Underline => 0,
+        AnnotationStyle::Outline => 1,
+    });
+    enc.opt(annotation.note.as_deref(), Encoder::str);
+    enc.finish_with_crc()
+}
+
+/// Decode an annotation written by `encode_annotation`.
+pub(crate) fn decode_annotation(bytes: &[u8]) -> Result<Annotation, CacheDecodeError> {
+    let mut dec = Decoder::framed(bytes, ANNOTATION_MAGIC, RECORD_VERSION)?;
+    let annotation = Annotation {
+        id: dec.u64()?,
+        start: dec.locator()?,
+        end: dec.locator()?,
+        style: match dec.u8()? {
+            0 => AnnotationStyle::Underline,
+            1 => AnnotationStyle::Outline,
+            _ => return Err(CacheDecodeError::Malformed("annotation style")),
+        },
+        note: dec.opt(Decoder::str)?,
+    };
+    dec.finish()?;
+    Ok(annotation)
+}
+
+pub(crate) fn hex_prefix(bytes: &[u8; 32]) -> String {
+    bytes[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+
+    fn str(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.0.extend_from_slice(value.as_bytes());
+    }
+
+    fn opt<T>(&mut self, value: Option<T>, mut write: impl FnMut(&mut Self, T)) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                write(self, value);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn locator(&mut self, locator: &ContentLocator) {
+        self.u32(locator.chapter_index as u32);
+        self.opt(locator.chapter_href.as_deref(), Encoder::str);
+        self.position(locator.position);
+        self.opt(locator.cfi.as_deref(), Encoder::str);
+    }
+
+    /// Append the CRC-32 of everything written so far.
+    fn finish_with_crc(mut self) -> Vec<u8> {
+        let crc = crc32fast::hash(&self.0);
+        self.u32(crc);
+        self.0
+    }
+
+    fn rect(&mut self, rect: &PageRect) {
+        self.i32(rect.x);
+        self.i32(rect.y);
+        self.u32(rect.width);
+        self.u32(rect.height);
+    }
+
+    fn color(&mut self, color: Color) {
+        self.0.extend_from_slice(&[color.r, color.g, color.b]);
+    }
+
+    fn page(&mut self, page: &RenderPage) {
+        self.usize(page.page_number);
+        for layer in [
+            &page.content_commands,
+            &page.chrome_commands,
+            &page.overlay_commands,
+        ] {
+            self.u32(layer.len() as u32);
+            for cmd in layer {
+                self.command(cmd);
+            }
+        }
+        self.u32(page.annotations.len() as u32);
+        for annotation in &page.annotations {
+            self.annotation(annotation);
+        }
+        self.metrics(&page.metrics);
+        self.opt(page.damage.as_ref(), |enc, rects| {
+            enc.u32(rects.len() as u32);
+            for rect in rects {
+                enc.rect(rect);
+            }
+        });
+        self.opt(page.chapter_title.as_deref(), Self::str);
+        self.u32(page.line_positions.len() as u32);
+        for position in &page.line_positions {
+            self.position(*position);
+        }
+    }
+
+    fn position(&mut self, position: TextPosition) {
+        self.usize(position.block_index);
+        self.usize(position.char_offset);
+    }
+
+    fn command(&mut self, cmd: &DrawCommand) {
+        match cmd {
+            DrawCommand::Text(text) => {
+                self.u8(0);
+                self.i32(text.x);
+                self.i32(text.baseline_y);
+                self.str(&text.text);
+                self.opt(text.font_id, Self::u32);
+                self.style(&text.style);
+                self.opt(text.glyphs.as_ref(), |enc, glyphs| {
+                    enc.u32(glyphs.len() as u32);
+                    for glyph in glyphs {
+                        enc.i32(glyph.x_advance);
+                        enc.i32(glyph.x_offset);
+                    }
+                });
+                self.u8(match text.hint {
+                    TextRenderHint::Aliased => 0,
+                    TextRenderHint::Gray4 => 1,
+                    TextRenderHint::Grayscale => 2,
+                });
+            }
+            DrawCommand::Rule(rule) => {
+                self.u8(1);
+                self.i32(rule.x);
+                self.i32(rule.y);
+                self.u32(rule.length);
+                self.u32(rule.thickness);
+                self.bool(rule.horizontal);
+            }
+            DrawCommand::Rect(rect) => {
+                self.u8(2);
+                self.i32(rect.x);
+                self.i32(rect.y);
+                self.u32(rect.width);
+                self.u32(rect.height);
+                self.bool(rect.fill);
+                self.opt(rect.color, Self::color);
+            }
+            DrawCommand::PageChrome(chrome) => {
+                self.u8(3);
+                self.u8(match chrome.kind {
+                    PageChromeKind::Header => 0,
+                    PageChromeKind::Footer => 1,
+                    PageChromeKind::Progress => 2,
+                    PageChromeKind::BookProgress => 3,
+                });
+                self.opt(chrome.text.as_deref(), Self::str);
+                self.opt(chrome.current, Self::usize);
+                self.opt(chrome.total, Self::usize);
+            }
+            DrawCommand::Image(image) => {
+                self.u8(4);
+                self.i32(image.x);
+                self.i32(image.y);
+                self.u32(image.width);
+                self.u32(image.height);
+                self.str(&image.src);
+                self.str(&image.alt);
+                self.opt(image.bitmap.as_ref(), |enc, bitmap| {
+                    enc.u32(bitmap.width);
+                    enc.u32(bitmap.height);
+                    enc.u32(bitmap.pixels.len() as u32);
+                    enc.0.extend_from_slice(&bitmap.pixels);
+                });
+            }
+            DrawCommand::PushClip(rect) => {
+                self.u8(5);
+                self.rect(rect);
+            }
+            DrawCommand::PopClip => self.u8(6),
+            DrawCommand::Layered(layered) => {
+                self.u8(7);
+                self.i32(layered.z);
+                self.command(&layered.command);
+            }
+        }
+    }
+
+    fn styled_item(&mut self, item: &StyledEventOrRun) {
+        match item {
+            StyledEventOrRun::Event(event) => {
+                self.u8(0);
+                match event {
+                    StyledEvent::ParagraphStart => self.u8(0),
+                    StyledEvent::ParagraphEnd => self.u8(1),
+                    StyledEvent::HeadingStart(level) => {
+                        self.u8(2);
+                        self.u8(*level);
+                    }
+                    StyledEvent::HeadingEnd(level) => {
+                        self.u8(3);
+                        self.u8(*level);
+                    }
+                    StyledEvent::ListItemStart => self.u8(4),
+                    StyledEvent::ListItemEnd => self.u8(5),
+                    StyledEvent::LineBreak => self.u8(6),
+                }
+            }
+            StyledEventOrRun::Run(run) => {
+                self.u8(1);
+                self.str(&run.text);
+                let style = &run.style;
+                self.u32(style.family_stack.len() as u32);
+                for family in &style.family_stack {
+                    self.str(family);
+                }
+                self.u16(style.weight);
+                self.bool(style.italic);
+                self.f32(style.size_px);
+                self.f32(style.line_height);
+                self.f32(style.letter_spacing);
+                self.block_role(style.block_role);
+                self.opt(style.color, Self::color);
+                self.u32(run.font_id);
+                self.str(&run.resolved_family);
+                self.opt(run.link.as_deref(), Self::str);
+                self.u32(run.anchors.len() as u32);
+                for anchor in &run.anchors {
+                    self.str(anchor);
+                }
+            }
+            StyledEventOrRun::Image(image) => {
+                self.u8(2);
+                self.str(&image.src);
+                self.str(&image.alt);
+                self.opt(image.width_px, Self::f32);
+                self.opt(image.height_px, Self::f32);
+                self.opt(image.inline_svg.as_deref(), Self::str);
+                self.bool(image.svg_oversize);
+                self.opt(image.caption.as_deref(), Self::str);
+            }
+        }
+    }
+
+    fn block_role(&mut self, role: BlockRole) {
+        match role {
+            BlockRole::Body => self.u8(0),
+            BlockRole::Paragraph => self.u8(1),
+            BlockRole::Heading(level) => {
+                self.u8(2);
+                self.u8(level);
+            }
+            BlockRole::ListItem => self.u8(3),
+        }
+    }
+
+    fn style(&mut self, style: &ResolvedTextStyle) {
+        self.opt(style.font_id, Self::u32);
+        self.str(&style.family);
+        self.u16(style.weight);
+        self.bool(style.italic);
+        self.f32(style.size_px);
+        self.f32(style.line_height);
+        self.f32(style.letter_spacing);
+        self.block_role(style.role);
+        match style.justify_mode {
+            JustifyMode::None => self.u8(0),
+            JustifyMode::InterWord { extra_px_total } => {
+                self.u8(1);
+                self.i32(extra_px_total);
+            }
+        }
+        self.opt(style.color, Self::color);
+        self.f32(style.metrics.ascent_px);
+        self.f32(style.metrics.descent_px);
+        self.f32(style.metrics.x_height_px);
+        self.f32(style.metrics.cap_height_px);
+    }
+
+    fn annotation(&mut self, annotation: &PageAnnotation) {
+        match annotation {
+            PageAnnotation::Tag { kind, value } => {
+                self.u8(0);
+                self.str(kind);
+                self.opt(value.as_deref(), Self::str);
+            }
+            PageAnnotation::Link { rect, target } => {
+                self.u8(1);
+                self.rect(rect);
+                match target {
+                    LinkTarget::Internal(link) => {
+                        self.u8(0);
+                        self.str(&link.href);
+                        self.opt(link.fragment.as_deref(), Self::str);
+                        self.opt(link.chapter_index, Self::usize);
+                        self.opt(link.page_index, Self::usize);
+                    }
+                    LinkTarget::External(url) => {
+                        self.u8(1);
+                        self.str(url);
+                    }
+                }
+            }
+            PageAnnotation::ImageDescription {
+                rect,
+                src,
+                alt,
+                caption,
+            } => {
+                self.u8(2);
+                self.rect(rect);
+                self.str(src);
+                self.opt(alt.as_deref(), Self::str);
+                self.opt(caption.as_deref(), Self::str);
+            }
+            PageAnnotation::Heading { level, text, rect } => {
+                self.u8(3);
+                self.u8(*level);
+                self.str(text);
+                self.rect(rect);
+            }
+            PageAnnotation::PendingImage { rect, src } => {
+                self.u8(4);
+                self.rect(rect);
+                self.str(src);
+            }
+        }
+    }
+
+    fn metrics(&mut self, metrics: &PageMetrics) {
+        self.usize(metrics.chapter_index);
+        self.usize(metrics.chapter_page_index);
+        self.opt(metrics.chapter_page_count, Self::usize);
+        self.opt(metrics.global_page_index, Self::usize);
+        self.opt(metrics.global_page_count_estimate, Self::usize);
+        self.f32(metrics.progress_chapter);
+        self.opt(metrics.progress_book, Self::f32);
+        self.opt(metrics.physical_width_mm, Self::f32);
+        self.opt(metrics.physical_height_mm, Self::f32);
+        self.opt(metrics.pages_left_in_chapter, Self::usize);
+        self.opt(metrics.pages_left_in_book, Self::usize);
+        self.opt(metrics.start_position, Self::position);
+        self.usize(metrics.word_count);
+        self.usize(metrics.image_count);
+    }
+}
+
+struct Decoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    /// Check magic, version, and trailing CRC-32, returning a decoder
+    /// positioned after the version.
+    fn framed(bytes: &'a [u8], magic: [u8; 4], version: u16) -> Result<Self, CacheDecodeError> {
+        if bytes.len() < magic.len() || bytes[..magic.len()] != magic {
+            return Err(CacheDecodeError::BadMagic);
+        }
+        if bytes.len() < magic.len() + 2 + 4 {
+            return Err(CacheDecodeError::Truncated);
+        }
+        let found = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if found != version {
+            return Err(CacheDecodeError::UnsupportedVersion(found));
+        }
+        let (body, crc) = bytes.split_at(bytes.len() - 4);
+        let crc = u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]]);
+        if crc32fast::hash(body) != crc {
+            return Err(CacheDecodeError::ChecksumMismatch);
+        }
+        Ok(Decoder { buf: body, pos: 6 })
+    }
+
+    /// Reject bytes left over after the last field.
+    fn finish(&self) -> Result<(), CacheDecodeError> {
+        if self.pos != self.buf.len() {
+            return Err(CacheDecodeError::Malformed("trailing bytes"));
+        }
+        Ok(())
+    }
+
+    fn locator(&mut self) -> Result<ContentLocator, CacheDecodeError> {
+        Ok(ContentLocator {
+            chapter_index: self.u32()? as usize,
+            chapter_href: self.opt(Decoder::str)?,
+            position: self.position()?,
+            cfi: self.opt(Decoder::str)?,
+        })
+    }
+
+    fn take(&mut self, n: usize) -> Result<&[u8], CacheDecodeError> {
+        let end = self.pos.checked_add(n).ok_or(CacheDecodeError::Truncated)?;
+        let bytes = self
+            .buf
+            .get(self.pos..end)
+            .ok_or(CacheDecodeError::Truncated)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], CacheDecodeError> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    fn array32(&mut self) -> Result<[u8; 32], CacheDecodeError> {
+        self.array::<32>()
+    }
+
+    fn u8(&mut self) -> Result<u8, CacheDecodeError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, CacheDecodeError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(CacheDecodeError::Malformed("bool")),
+        }
+    }
+
+    fn u16(&mut self) -> Result<u16, CacheDecodeError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, CacheDecodeError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn i32(&mut self) -> Result<i32, CacheDecodeError> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, CacheDecodeError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, CacheDecodeError> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    fn usize(&mut self) -> Result<usize, CacheDecodeError> {
+        usize::try_from(u64::from_le_bytes(self.array()?))
+            .map_err(|_| CacheDecodeError::Malformed("usize"))
+    }
+
+    /// Element count, capped by the remaining bytes so corrupt lengths
+    /// cannot trigger huge allocations.
+    fn len(&mut self) -> Result<usize, CacheDecodeError> {
+        let len = self.u32()? as usize;
+        if len > self.buf.len().saturating_sub(self.pos) {
+            return Err(CacheDecodeError::Truncated);
+        }
+        Ok(len)
+    }
+
+    fn str(&mut self) -> Result<String, CacheDecodeError> {
+        let len = self.len()?;
+        let bytes = self.take(len)?;
+        core::str::from_utf8(bytes)
+            .map(str::to_string)
+            .map_err(|_| CacheDecodeError::Malformed("utf-8"))
+    }
+
+    fn opt<T>(
+        &mut self,
+        mut read: impl FnMut(&mut Self) -> Result<T, CacheDecodeError>,
+    ) -> Result<Option<T>, CacheDecodeError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => read(self).map(Some),
+            _ => Err(CacheDecodeError::Malformed("option tag")),
+        }
+    }
+
+    fn position(&mut self) -> Result<TextPosition, CacheDecodeError> {
+        Ok(TextPosition {
+            block_index: self.usize()?,
+            char_offset: self.usize()?,
+        })
+    }
+
+    fn rect(&mut self) -> Result<PageRect, CacheDecodeError> {
+        Ok(PageRect::new(
+            self.i32()?,
+            self.i32()?,
+            self.u32()?,
+            self.u32()?,
+        ))
+    }
+
+    fn color(&mut self) -> Result<Color, CacheDecodeError> {
+        let [r, g, b] = self.array()?;
+        Ok(Color::rgb(r, g, b))
+    }
+
+    fn page(&mut self) -> Result<RenderPage, CacheDecodeError> {
+        let mut page = RenderPage::new(self.usize()?);
+        page.content_commands = self.commands()?;
+        page.chrome_commands = self.commands()?;
+        page.overlay_commands = self.commands()?;
+        let annotations = self.len()?;
+        page.annotations = Vec::with_capacity(annotations);
+        for _ in 0..annotations {
+            page.annotations.push(self.annotation()?);
+        }
+        page.metrics = self.metrics()?;
+        page.damage = self.opt(|dec| {
+            let len = dec.len()?;
+            let mut rects = Vec::with_capacity(len);
+            for _ in 0..len {
+                rects.push(dec.rect()?);
+            }
+            Ok(rects)
+        })?;
+        page.chapter_title = self.opt(Self::str)?;
+        let positions = self.len()?;
+        page.line_positions = Vec::with_capacity(positions);
+        for _ in 0..positions {
+            page.line_positions.push(self.position()?);
+        }
+        page.sync_commands();
+        Ok(page)
+    }
+
+    fn commands(&mut self) -> Result<Vec<DrawCommand>, CacheDecodeError> {
+        let len = self.len()?;
+        let mut commands = Vec::with_capacity(len);
+        for _ in 0..len {
+            commands.push(self.command(0)?);
+        }
+        Ok(commands)
+    }
+
+    fn command(&mut self, depth: u8) -> Result<DrawCommand, CacheDecodeError> {
+        Ok(match self.u8()? {
+            0 => DrawCommand::Text(TextCommand {
+                x: self.i32()?,
+                baseline_y: self.i32()?,
+                text: self.str()?,
+                font_id: self.opt(Self::u32)?,
+                style: self.style()?,
+                glyphs: self.opt(|dec| {
+                    let len = dec.len()?;
+                    let mut glyphs = Vec::with_capacity(len);
+                    for _ in 0..len {
+                        glyphs.push(GlyphPosition {
+                            x_advance: dec.i32()?,
+                            x_offset: dec.i32()?,
+                        });
+                    }
+                    Ok(glyphs)
+                })?,
+                hint: match self.u8()? {
+                    0 => TextRenderHint::Aliased,
+                    1 => TextRenderHint::Gray4,
+                    2 => TextRenderHint::Grayscale,
+                    _ => return Err(CacheDecodeError::Malformed("text hint")),
+                },
+            }),
+            1 => DrawCommand::Rule(RuleCommand {
+                x: self.i32()?,
+                y: self.i32()?,
+                length: self.u32()?,
+                thickness: self.u32()?,
+                horizontal: self.bool()?,
+            }),
+            2 => DrawCommand::Rect(RectCommand {
+                x: self.i32()?,
+                y: self.i32()?,
+                width: self.u32()?,
+                height: self.u32()?,
+                fill: self.bool()?,
+                color: self.opt(Self::color)?,
+            }),
+            3 => DrawCommand::PageChrome(PageChromeCommand {
+                kind: match self.u8()? {
+                    0 => PageChromeKind::Header,
+                    1 => PageChromeKind::Footer,
+                    2 => PageChromeKind::Progress,
+                    3 => PageChromeKind::BookProgress,
+                    _ => return Err(CacheDecodeError::Malformed("chrome kind")),
+                },
+                text: self.opt(Self::str)?,
+                current: self.opt(Self::usize)?,
+                total: self.opt(Self::usize)?,
+            }),
+            4 => DrawCommand::Image(ImageCommand {
+                x: self.i32()?,
+                y: self.i32()?,
+                width: self.u32()?,
+                height: self.u32()?,
+                src: self.str()?,
+                alt: self.str()?,
+                bitmap: self.opt(|dec| {
+                    let width = dec.u32()?;
+                    let height = dec.u32()?;
+                    let len = dec.len()?;
+                    Ok(GrayBitmap {
+                        width,
+                        height,
+                        pixels: dec.take(len)?.to_vec(),
+                    })
+                })?,
+            }),
+            5 => DrawCommand::PushClip(self.rect()?),
+            6 => DrawCommand::PopClip,
+            7 if depth < MAX_LAYER_DEPTH => DrawCommand::Layered(LayeredCommand {
+                z: self.i32()?,
+                command: Box::new(self.command(depth + 1)?),
+            }),
+            7 => return Err(CacheDecodeError::Malformed("layer nesting")),
+            _ => return Err(CacheDecodeError::Malformed("command tag")),
+        })
+    }
+
+    fn styled_item(&mut self) -> Result<StyledEventOrRun, CacheDecodeError> {
+        Ok(match self.u8()? {
+            0 => StyledEventOrRun::Event(match self.u8()? {
+                0 => StyledEvent::ParagraphStart,
+                1 => StyledEvent::ParagraphEnd,
+                2 => StyledEvent::HeadingStart(self.u8()?),
+                3 => StyledEvent::HeadingEnd(self.u8()?),
+                4 => StyledEvent::ListItemStart,
+                5 => StyledEvent::ListItemEnd,
+                6 => StyledEvent::LineBreak,
+                _ => return Err(CacheDecodeError::Malformed("styled event")),
+            }),
+            1 => {
+                let text = self.str()?;
+                let count = self.len()?;
+                let mut family_stack = Vec::with_capacity(count);
+                for _ in 0..count {
+                    family_stack.push(self.str()?);
+                }
+                StyledEventOrRun::Run(StyledRun {
+                    text,
+                    style: ComputedTextStyle {
+                        family_stack,
+                        weight: self.u16()?,
+                        italic: self.bool()?,
+                        size_px: self.f32()?,
+                        line_height: self.f32()?,
+                        letter_spacing: self.f32()?,
+                        block_role: self.block_role()?,
+                        color: self.opt(Self::color)?,
+                    },
+                    font_id: self.u32()?,
+                    resolved_family: self.str()?,
+                    link: self.opt(Self::str)?,
+                    anchors: {
+                        let count = self.len()?;
+                        let mut anchors = Vec::with_capacity(count);
+                        for _ in 0..count {
+                            anchors.push(self.str()?);
+                        }
+                        anchors
+                    },
+                })
+            }
+            2 => StyledEventOrRun::Image(StyledImage {
+                src: self.str()?,
+                alt: self.str()?,
+                width_px: self.opt(Self::f32)?,
+                height_px: self.opt(Self::f32)?,
+                inline_svg: self.opt(Self::str)?,
+                svg_oversize: self.bool()?,
+                caption: self.opt(Self::str)?,
+            }),
+            _ => return Err(CacheDecodeError::Malformed("styled item")),
+        })
+    }
+
+    fn block_role(&mut self) -> Result<BlockRole, CacheDecodeError> {
+        Ok(match self.u8()? {
+            0 => BlockRole::Body,
+            1 => BlockRole::Paragraph,
+            2 => BlockRole::Heading(self.u8()?),
+            3 => BlockRole::ListItem,
+            _ => return Err(CacheDecodeError::Malformed("block role")),
+        })
+    }
+
+    fn style(&mut self) -> Result<ResolvedTextStyle, CacheDecodeError> {
+        Ok(ResolvedTextStyle {
+            font_id: self.opt(Self::u32)?,
+            family: self.str()?,
+            weight: self.u16()?,
+            italic: self.bool()?,
+            size_px: self.f32()?,
+            line_height: self.f32()?,
+            letter_spacing: self.f32()?,
+            role: self.block_role()?,
+            justify_mode: match self.u8()? {
+                0 => JustifyMode::None,
+                1 => JustifyMode::InterWord {
+                    extra_px_total: self.i32()?,
+                },
+                _ => return Err(CacheDecodeError::Malformed("justify mode")),
+            },
+            color: self.opt(Self::color)?,
+            metrics: VerticalMetrics {
+                ascent_px: self.f32()?,
+                descent_px: self.f32()?,
+                x_height_px: self.f32()?,
+                cap_height_px: self.f32()?,
+            },
+        })
+    }
+
+    fn annotation(&mut self) -> Result<PageAnnotation, CacheDecodeError> {
+        Ok(match self.u8()? {
+            0 => PageAnnotation::Tag {
+                kind: self.str()?,
+                value: self.opt(Self::str)?,
+            },
+            1 => PageAnnotation::Link {
+                rect: self.rect()?,
+                target: match self.u8()? {
+                    0 => LinkTarget::Internal(InternalLink {
+                        href: self.str()?,
+                        fragment: self.opt(Self::str)?,
+                        chapter_index: self.opt(Self::usize)?,
+                        page_index: self.opt(Self::usize)?,
+                    }),
+                    1 => LinkTarget::External(self.str()?),
+                    _ => return Err(CacheDecodeError::Malformed("link target")),
+                },
+            },
+            2 => PageAnnotation::ImageDescription {
+                rect: self.rect()?,
+                src: self.str()?,
+                alt: self.opt(Self::str)?,
+                caption: self.opt(Self::str)?,
+            },
+            3 => PageAnnotation::Heading {
+                level: self.u8()?,
+                text: self.str()?,
+                rect: self.rect()?,
+            },
+            4 => PageAnnotation::PendingImage {
+                rect: self.rect()?,
+                src: self.str()?,
+            },
+            _ => return Err(CacheDecodeError::Malformed("annotation tag")),
+        })
+    }
+
+    fn metrics(&mut self) -> Result<PageMetrics, CacheDecodeError> {
+        Ok(PageMetrics {
+            chapter_index: self.usize()?,
+            chapter_page_index: self.usize()?,
+            chapter_page_count: self.opt(Self::usize)?,
+            global_page_index: self.opt(Self::usize)?,
+            global_page_count_estimate: self.opt(Self::usize)?,
+            progress_chapter: self.f32()?,
+            progress_book: self.opt(Self::f32)?,
+            physical_width_mm: self.opt(Self::f32)?,
+            physical_height_mm: self.opt(Self::f32)?,
+            pages_left_in_chapter: self.opt(Self::usize)?,
+            pages_left_in_book: self.opt(Self::usize)?,
+            start_position: self.opt(Self::position)?,
+            word_count: self.usize()?,
+            image_count: self.usize()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(chapter_index: usize) -> CacheKey {
+        CacheKey {
+            profile: PaginationProfileId::from_bytes(b"profile"),
+            book: BookFingerprint([7; 32]),
+            chapter_index,
+        }
+    }
+
+    fn sample_page() -> RenderPage {
+        let mut page = RenderPage::new(2);
+        page.push_content_command(DrawCommand::Text(TextCommand {
+            x: 4,
+            baseline_y: 10,
+            text: "Héllo".to_string(),
+            font_id: Some(3),
+            style: ResolvedTextStyle {
+                font_id: Some(3),
+                family: "serif".to_string(),
+                weight: 700,
+                italic: true,
+                size_px: 18.0,
+                line_height: 1.3,
+                letter_spacing: 0.5,
+                role: BlockRole::Heading(2),
+                justify_mode: JustifyMode::InterWord { extra_px_total: 6 },
+                color: Some(Color::rgb(10, 20, 30)),
+                metrics: VerticalMetrics::estimate(18.0),
+            },
+            glyphs: Some(vec![GlyphPosition::default(); 5]),
+            hint: TextRenderHint::Gray4,
+        }));
+        page.push_content_command(DrawCommand::PushClip(PageRect::new(0, 0, 8, 8)));
+        page.push_content_command(DrawCommand::Layered(LayeredCommand {
+            z: -2,
+            command: Box::new(DrawCommand::Image(ImageCommand {
+                x: 1,
+                y: 2,
+                width: 2,
+                height: 1,
+                src: "img.png".to_string(),
+                alt: "alt".to_string(),
+                bitmap: Some(GrayBitmap {
+                    width: 2,
+                    height: 1,
+                    pixels: vec![0, 255],
+                }),
+            })),
+        }));
+        page.push_content_command(DrawCommand::PopClip);
+        page.push_chrome_command(DrawCommand::PageChrome(PageChromeCommand {
+            kind: PageChromeKind::Footer,
+            text: Some("Page 2".to_string()),
+            current: Some(2),
+            total: None,
+        }));
+        page.annotations.push(PageAnnotation::Link {
+            rect: PageRect::new(4, 10, 40, 20),
+            target: LinkTarget::from_href("notes.xhtml#n1"),
+        });
+        page.metrics.chapter_page_index = 1;
+        page.metrics.progress_book = Some(0.25);
+        page.metrics.start_position = Some(TextPosition {
+            block_index: 3,
+            char_offset: 17,
+        });
+        page.damage = Some(vec![PageRect::new(0, 0, 10, 10)]);
+        page.chapter_title = Some("Two".to_string());
+        page.line_positions = vec![TextPosition::default()];
+        page.sync_commands();
+        page
+    }
+
+    #[test]
+    fn chapter_pages_round_trip() {
+        let pages = vec![sample_page(), RenderPage::new(3)];
+        let bytes = encode_chapter_pages(&key(4), &pages);
+        assert_eq!(decode_chapter_pages(&bytes, &key(4)), Ok(pages));
+    }
+
+    #[test]
+    fn decode_rejects_corruption_version_and_key() {
+        let bytes = encode_chapter_pages(&key(1), &[sample_page()]);
+
+        let mut flipped = bytes.clone();
+        flipped[HEADER_LEN + 3] ^= 0x40;
+        assert_eq!(
+            decode_chapter_pages(&flipped, &key(1)),
+            Err(CacheDecodeError::ChecksumMismatch)
+        );
+
+        let mut old = bytes.clone();
+        old[4] = 0;
+        assert_eq!(
+            decode_chapter_pages(&old, &key(1)),
+            Err(CacheDecodeError::UnsupportedVersion(0))
+        );
+
+        assert_eq!(
+            decode_chapter_pages(&bytes, &key(2)),
+            Err(CacheDecodeError::KeyMismatch)
+        );
+        assert_eq!(
+            decode_chapter_pages(&bytes[..bytes.len() / 2], &key(1)),
+            Err(CacheDecodeError::ChecksumMismatch)
+        );
+    }
+
+    struct Recorder(std::sync::Arc<Mutex<Vec<usize>>>);
+
+    impl RenderCacheStore for Recorder {
+        fn store_chapter_pages(
+            &self,
+            _profile: PaginationProfileId,
+            chapter_index: usize,
+            _pages: &[RenderPage],
+        ) {
+            if let Ok(mut stored) = self.0.lock() {
+                stored.push(chapter_index);
+            }
+        }
+    }
+
+    #[test]
+    fn lru_store_writes_back_evicted_and_flushed_chapters() {
+        let written = std::sync::Arc::new(Mutex::new(Vec::with_capacity(4)));
+        let capacity = CacheCapacity {
+            max_chapters: Some(2),
+            max_bytes: None,
+        };
+        let store = LruCacheStore::new(capacity).with_backing(Recorder(written.clone()));
+        let profile = PaginationProfileId::from_bytes(b"lru");
+        let pages = vec![sample_page()];
+        assert_eq!(store.write_policy(), CacheWritePolicy::WriteBack);
+
+        store.store_chapter_pages(profile, 0, &pages);
+        store.store_chapter_pages(profile, 1, &pages);
+        assert!(store.load_chapter_pages(profile, 0).is_some());
+        store.store_chapter_pages(profile, 2, &pages);
+        assert_eq!(store.chapters(), vec![(profile, 0), (profile, 2)]);
+        assert_eq!(*written.lock().expect("lock"), vec![1]);
+
+        store.flush();
+        store.flush();
+        assert_eq!(*written.lock().expect("lock"), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn ledger_evicts_least_recently_rendered_first() {
+        let mut ledger = CacheLedger::default();
+        let profile = PaginationProfileId::from_bytes(b"ledger");
+        let capacity = CacheCapacity {
+            max_chapters: Some(2),
+            max_bytes: Some(250),
+        };
+        assert!(ledger.touch(profile, 0, 100, capacity).is_empty());
+        assert!(ledger.touch(profile, 1, 100, capacity).is_empty());
+        assert!(ledger.touch(profile, 0, 100, capacity).is_empty());
+        assert_eq!(ledger.touch(profile, 2, 100, capacity), vec![(profile, 1)]);
+        assert_eq!(
+            ledger.touch(profile, 3, 200, capacity),
+            vec![(profile, 0), (profile, 2)]
+        );
+        assert!(!capacity.admits(300));
+    }
+
+    #[test]
+    fn dir_store_persists_and_drops_corrupt_files() {
+        let root = std::env::temp_dir().join(format!("mu-epub-cache-{}", std::process::id()));
+        let store = DirCacheStore::new(&root, BookFingerprint([9; 32]));
+        let profile = PaginationProfileId::from_bytes(b"dir");
+        let pages = vec![sample_page()];
+
+        store.store_chapter_pages(profile, 0, &pages);
+        assert_eq!(store.load_chapter_pages(profile, 0), Some(pages));
+        assert_eq!(store.load_chapter_pages(profile, 1), None);
+
+        let path = store.chapter_path(profile, 0);
+        fs::write(&path, b"MUPC garbage").expect("overwrite cache file");
+        assert_eq!(store.load_chapter_pages(profile, 0), None);
+        assert!(!path.exists());
+
+        store.clear_profile(profile).expect("clear cache");
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,300 @@
+//! Interned, reduced-footprint page form for page caches.
+
+use crate::render_ir::{
+    DrawCommand, GlyphPosition, RenderPage, ResolvedTextStyle, TextCommand, TextRenderHint,
+};
+
+/// Text command whose string and style live in the owning page's tables.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompactText {
+    /// Left x.
+    pub x: i32,
+    /// Baseline y.
+    pub baseline_y: i32,
+    /// Font identifier for direct command-level lookup.
+    pub font_id: Option<u32>,
+    /// Anti-aliasing hint.
+    pub hint: TextRenderHint,
+    /// Per-char placement decided by layout.
+    pub glyphs: Option<Box<[GlyphPosition]>>,
+    text: u32,
+    style: u32,
+}
+
+/// Command stored in a `CompactPage`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompactCommand {
+    /// Text run with interned string and style.
+    Text(CompactText),
+    /// Any other command, stored as-is.
+    Other(DrawCommand),
+}
+
+/// A `RenderPage` with text strings and styles deduplicated into per-page
+/// tables.
+///
+/// Cached pages repeat the same style payload on every line and often the
+/// same short strings; keeping one copy of each and storing indices in the
+/// commands cuts resident size. The merged `commands` stream is dropped and
+/// rebuilt by `CompactPage::to_page`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompactPage {
+    /// Page fields other than command layers.
+    shell: RenderPage,
+    content: Vec<CompactCommand>,
+    chrome: Vec<CompactCommand>,
+    overlay: Vec<CompactCommand>,
+    strings: Vec<Box<str>>,
+    styles: Vec<ResolvedTextStyle>,
+}
+
+impl CompactPage {
+    /// Intern `page` into compact form.
+    pub fn new(page: &RenderPage) -> Self {
+        let mut out = Self {
+            shell: RenderPage {
+                commands: Vec::with_capacity(0),
+                content_commands: Vec::with_capacity(0),
+                chrome_commands: Vec::with_capacity(0),
+                overlay_commands: Vec::with_capacity(0),
+                ..page.clone()
+            },
+            content: Vec::with_capacity(0),
+            chrome: Vec::with_capacity(0),
+            overlay: Vec::with_capacity(0),
+            strings: Vec::with_capacity(0),
+            styles: Vec::with_capacity(0),
+        };
+        out.content = out.intern_layer(&page.content_commands);
+        out.chrome = out.intern_layer(&page.chrome_commands);
+        out.overlay = out.intern_layer(&page.overlay_commands);
+        out.strings.shrink_to_fit();
+        out.styles.shrink_to_fit();
+        out
+    }
+
+    /// 1-based page number.
+    pub fn page_number(&self) -> usize {
+        self.shell.page_number
+    }
+
+    /// Page fields other than commands (metrics, annotations, overlays).
+    pub fn shell(&self) -> &RenderPage {
+        &self.shell
+    }
+
+    /// Compact content-layer commands.
+    pub fn content_commands(&self) -> &[CompactCommand] {
+        &self.content
+    }
+
+    /// Compact chrome-layer commands.
+    pub fn chrome_commands(&self) -> &[CompactCommand] {
+        &self.chrome
+    }
+
+    /// Compact overlay-layer commands.
+    pub fn overlay_commands(&self) -> &[CompactCommand] {
+        &self.overlay
+    }
+
+    /// Text of a run interned by this page.
+    pub fn text(&self, cmd: &CompactText) -> Option<&str> {
+        self.strings.get(cmd.text as usize).map(|text| &**text)
+    }
+
+    /// Style of a run interned by this page.
+    pub fn style(&self, cmd: &CompactText) -> Option<&ResolvedTextStyle> {
+        self.styles.get(cmd.style as usize)
+    }
+
+    /// Distinct text strings held by this page.
+    pub fn string_count(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Distinct text styles held by this page.
+    pub fn style_count(&self) -> usize {
+        self.styles.len()
+    }
+
+    /// Expand a compact command back into a full draw command.
+    pub fn expand(&self, cmd: &CompactCommand) -> Option<DrawCommand> {
+        match cmd {
+            CompactCommand::Other(cmd) => Some(cmd.clone()),
+            CompactCommand::Text(text) => Some(DrawCommand::Text(TextCommand {
+                x: text.x,
+                baseline_y: text.baseline_y,
+                text: self.text(text)?.to_string(),
+                font_id: text.font_id,
+                style: self.style(text)?.clone(),
+                glyphs: text.glyphs.as_ref().map(|glyphs| glyphs.to_vec()),
+                hint: text.hint,
+            })),
+        }
+    }
+
+    /// Rebuild the full page, including the merged `commands` stream.
+    pub fn to_page(&self) -> RenderPage {
+        let mut page = self.shell.clone();
+        page.content_commands = self.expand_layer(&self.content);
+        page.chrome_commands = self.expand_layer(&self.chrome);
+        page.overlay_commands = self.expand_layer(&self.overlay);
+        page.sync_commands();
+        page
+    }
+
+    /// Approximate bytes held by this page, including tables.
+    ///
+    /// Comparable with `RenderPage::approx_bytes`.
+    pub fn approx_bytes(&self) -> usize {
+        let commands: usize = [&self.content, &self.chrome, &self.overlay]
+            .iter()
+            .flat_map(|layer| layer.iter())
+            .map(|cmd| match cmd {
+                CompactCommand::Other(cmd) => cmd.approx_bytes(),
+                CompactCommand::Text(text) => {
+                    core::mem::size_of::<CompactCommand>()
+                        + text.glyphs.as_ref().map_or(0, |glyphs| {
+                            glyphs.len() * core::mem::size_of::<GlyphPosition>()
+                        })
+                }
+            })
+            .sum();
+        let strings: usize = self
+            .strings
+            .iter()
+            .map(|text| core::mem::size_of::<Box<str>>() + text.len())
+            .sum();
+        let styles: usize = self
+            .styles
+            .iter()
+            .map(|style| core::mem::size_of::<ResolvedTextStyle>() + style.family.capacity())
+            .sum();
+        core::mem::size_of::<Self>() - core::mem::size_of::<RenderPage>()
+            + self.shell.approx_bytes()
+            + commands
+            + strings
+            + styles
+    }
+
+    fn expand_layer(&self, layer: &[CompactCommand]) -> Vec<DrawCommand> {
+        layer.iter().filter_map(|cmd| self.expand(cmd)).collect()
+    }
+
+    fn intern_layer(&mut self, layer: &[DrawCommand]) -> Vec<CompactCommand> {
+        layer.iter().map(|cmd| self.intern_command(cmd)).collect()
+    }
+
+    fn intern_command(&mut self, cmd: &DrawCommand) -> CompactCommand {
+        let DrawCommand::Text(text) = cmd else {
+            return CompactCommand::Other(cmd.clone());
+        };
+        CompactCommand::Text(CompactText {
+            x: text.x,
+            baseline_y: text.baseline_y,
+            font_id: text.font_id,
+            hint: text.hint,
+            glyphs: text.glyphs.as_ref().map(|glyphs| glyphs.clone().into()),
+            text: self.intern_str(&text.text),
+            style: self.intern_style(&text.style),
+        })
+    }
+
+    fn intern_str(&mut self, text: &str) -> u32 {
+        // Pages hold tens of runs; a linear scan beats hashing here.
+        match self.strings.iter().position(|known| &**known == text) {
+            Some(idx) => idx as u32,
+            None => {
+                self.strings.push(text.into());
+                (self.strings.len() - 1) as u32
+            }
+        }
+    }
+
+    fn intern_style(&mut self, style: &ResolvedTextStyle) -> u32 {
+        match self.styles.iter().position(|known| known == style) {
+            Some(idx) => idx as u32,
+            None => {
+                let mut style = style.clone();
+                style.family.shrink_to_fit();
+                self.styles.push(style);
+                (self.styles.len() - 1) as u32
+            }
+        }
+    }
+}
+
+impl From<&RenderPage> for CompactPage {
+    fn from(page: &RenderPage) -> Self {
+        Self::new(page)
+    }
+}
+
+impl RenderPage {
+    /// Intern this page's strings and styles into a `CompactPage`.
+    pub fn compact(&self) -> CompactPage {
+        CompactPage::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_ir::{JustifyMode, RuleCommand, VerticalMetrics};
+    use mu_epub::BlockRole;
+
+    fn line(y: i32, text: &str) -> DrawCommand {
+        DrawCommand::Text(TextCommand {
+            x: 10,
+            baseline_y: y,
+            text: text.to_string(),
+            font_id: None,
+            style: ResolvedTextStyle {
+                font_id: None,
+                family: "serif".to_string(),
+                weight: 400,
+                italic: false,
+                size_px: 20.0,
+                line_height: 1.2,
+                letter_spacing: 0.0,
+                role: BlockRole::Body,
+                justify_mode: JustifyMode::None,
+                color: None,
+                metrics: VerticalMetrics::estimate(20.0),
+            },
+            glyphs: None,
+            hint: TextRenderHint::Aliased,
+        })
+    }
+
+    #[test]
+    fn compact_page_dedups_and_round_trips() {
+        let mut page = RenderPage::new(3);
+        for (idx, text) in ["* * *", "Chapter text", "* * *", "Chapter text"]
+            .iter()
+            .enumerate()
+        {
+            page.push_content_command(line(idx as i32 * 24, text));
+        }
+        page.push_content_command(DrawCommand::Rule(RuleCommand {
+            x: 0,
+            y: 100,
+            length: 50,
+            thickness: 1,
+            horizontal: true,
+        }));
+        page.sync_commands();
+
+        let compact = page.compact();
+        assert_eq!(compact.page_number(), 3);
+        assert_eq!(compact.string_count(), 2);
+        assert_eq!(compact.style_count(), 1);
+        let CompactCommand::Text(first) = &compact.content_commands()[0] else {
+            panic!("expected text");
+        };
+        assert_eq!(compact.text(first), Some("* * *"));
+        assert!(compact.approx_bytes() < page.approx_bytes());
+        assert_eq!(compact.to_page(), page);
+    }
+}
@@ -0,0 +1,192 @@
+//! Command-level diffs between rendered pages.
+
+use crate::render_ir::{DrawCommand, PageRect, RenderPage};
+
+/// A command whose anchor stayed put but whose payload changed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandChange {
+    /// Command on the original page.
+    pub before: DrawCommand,
+    /// Command on the other page.
+    pub after: DrawCommand,
+}
+
+/// Difference between two pages' merged command streams.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PageDiff {
+    /// Commands only present on the other page.
+    pub added: Vec<DrawCommand>,
+    /// Commands only present on the original page.
+    pub removed: Vec<DrawCommand>,
+    /// Same-kind commands at the same anchor with different payloads.
+    pub changed: Vec<CommandChange>,
+    /// Merged bounds of every difference with known geometry.
+    ///
+    /// Page chrome markers have no IR geometry and are not included.
+    pub bounds: Vec<PageRect>,
+}
+
+impl PageDiff {
+    /// Diff two command streams.
+    ///
+    /// Identical commands are matched regardless of order; leftovers of the
+    /// same kind sharing an anchor point are reported as changes.
+    pub fn between(before: &[DrawCommand], after: &[DrawCommand]) -> Self {
+        let mut matched = vec![false; before.len()];
+        let mut unmatched_after = Vec::with_capacity(after.len());
+        for cmd in after {
+            let hit = before
+                .iter()
+                .enumerate()
+                .position(|(idx, old)| !matched[idx] && old == cmd);
+            match hit {
+                Some(idx) => matched[idx] = true,
+                None => unmatched_after.push(cmd),
+            }
+        }
+        let mut removed: Vec<&DrawCommand> = before
+            .iter()
+            .zip(&matched)
+            .filter(|(_, hit)| !**hit)
+            .map(|(cmd, _)| cmd)
+            .collect();
+
+        let mut diff = PageDiff::default();
+        for cmd in unmatched_after {
+            let anchor = command_anchor(cmd);
+            let paired = removed
+                .iter()
+                .position(|old| anchor.is_some() && command_anchor(old) == anchor);
+            match paired {
+                Some(idx) => diff.changed.push(CommandChange {
+                    before: removed.remove(idx).clone(),
+                    after: cmd.clone(),
+                }),
+                None => diff.added.push(cmd.clone()),
+            }
+        }
+        diff.removed = removed.into_iter().cloned().collect();
+        diff.bounds = merge_rects(diff.commands().filter_map(DrawCommand::bounds).collect());
+        diff
+    }
+
+    /// True when both pages draw the same commands.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Every command touched by the diff, from both pages.
+    pub fn commands(&self) -> impl Iterator<Item = &DrawCommand> {
+        self.removed
+            .iter()
+            .chain(&self.added)
+            .chain(self.changed.iter().flat_map(|c| [&c.before, &c.after]))
+    }
+}
+
+impl RenderPage {
+    /// Diff this page's merged commands against `other`.
+    pub fn diff(&self, other: &RenderPage) -> PageDiff {
+        PageDiff::between(&self.commands, &other.commands)
+    }
+}
+
+/// Variant tag plus origin used to pair changed commands.
+fn command_anchor(cmd: &DrawCommand) -> Option<(u8, i32, i32)> {
+    match cmd {
+        DrawCommand::Text(text) => Some((0, text.x, text.baseline_y)),
+        DrawCommand::Rule(rule) => Some((1, rule.x, rule.y)),
+        DrawCommand::Rect(rect) => Some((2, rect.x, rect.y)),
+        DrawCommand::Image(image) => Some((3, image.x, image.y)),
+        DrawCommand::PageChrome(chrome) => Some((4, chrome.kind as i32, 0)),
+        DrawCommand::PushClip(rect) => Some((5, rect.x, rect.y)),
+        DrawCommand::PopClip => None,
+        DrawCommand::Layered(layered) => command_anchor(&layered.command),
+    }
+}
+
+/// Union rectangles that overlap or touch until all are disjoint.
+pub(crate) fn merge_rects(mut rects: Vec<PageRect>) -> Vec<PageRect> {
+    rects.retain(|rect| !rect.is_empty());
+    let mut changed = true;
+    while changed {
+        changed = false;
+        'outer: for i in 0..rects.len() {
+            for j in (i + 1)..rects.len() {
+                if rects[i].touches(&rects[j]) {
+                    rects[i] = rects[i].union(&rects[j]);
+                    rects.swap_remove(j);
+                    changed = true;
+                    break 'outer;
+                }
+            }
+        }
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_ir::{RectCommand, RuleCommand};
+
+    fn rect(x: i32, y: i32, fill: bool) -> DrawCommand {
+        DrawCommand::Rect(RectCommand {
+            x,
+            y,
+            width: 10,
+            height: 10,
+            fill,
+            color: None,
+        })
+    }
+
+    fn page(commands: Vec<DrawCommand>) -> RenderPage {
+        let mut page = RenderPage::new(1);
+        for cmd in commands {
+            page.push_content_command(cmd);
+        }
+        page.sync_commands();
+        page
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_changed_commands() {
+        let rule = DrawCommand::Rule(RuleCommand {
+            x: 0,
+            y: 40,
+            length: 50,
+            thickness: 1,
+            horizontal: true,
+        });
+        let before = page(vec![rect(0, 0, false), rule.clone(), rect(100, 0, false)]);
+        let after = page(vec![rule, rect(0, 0, true), rect(0, 80, false)]);
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.removed, vec![rect(100, 0, false)]);
+        assert_eq!(diff.added, vec![rect(0, 80, false)]);
+        assert_eq!(
+            diff.changed,
+            vec![CommandChange {
+                before: rect(0, 0, false),
+                after: rect(0, 0, true),
+            }]
+        );
+        assert_eq!(diff.bounds.len(), 3);
+        assert!(before.diff(&before).is_empty());
+    }
+
+    #[test]
+    fn merge_rects_unions_touching_rectangles() {
+        let merged = merge_rects(vec![
+            PageRect::new(0, 0, 10, 10),
+            PageRect::new(10, 0, 10, 10),
+            PageRect::new(50, 50, 5, 5),
+            PageRect::new(0, 0, 0, 4),
+        ]);
+        assert_eq!(
+            merged,
+            vec![PageRect::new(0, 0, 20, 10), PageRect::new(50, 50, 5, 5)]
+        );
+    }
+}
@@ -0,0 +1,540 @@
+//! Pretty JSON dumps of render pages for layout bug reports.
+
+use core::fmt::Write as _;
+
+use mu_epub::Color;
+
+use crate::render_engine::RenderPhase;
+use crate::render_ir::{
+    DrawCommand, GrayBitmap, LinkTarget, OverlayContent, OverlayItem, OverlaySlot, PageAnnotation,
+    PageMetrics, PageRect, RenderPage, ResolvedTextStyle, TextCommand, TextPosition,
+};
+use crate::render_profile::{ChapterProfile, ProfileReport};
+
+impl RenderPage {
+    /// Pretty-printed JSON of this page's commands, metrics, and annotations.
+    ///
+    /// Meant for attaching to bug reports, not for round-tripping: the
+    /// legacy merged `commands` stream is omitted in favour of the three
+    /// layers, bitmaps are reduced to their dimensions, and enum values use
+    /// their Rust variant names. Key order is fixed, so dumps diff cleanly.
+    pub fn to_debug_json(&self) -> String {
+        let mut out = String::with_capacity(256 + self.commands.len() * 256);
+        page_json(self).write(&mut out, 0);
+        out
+    }
+}
+
+impl ProfileReport {
+    /// Pretty-printed JSON of this report, with per-phase totals.
+    ///
+    /// Key order is fixed, so reports from two builds diff cleanly.
+    pub fn to_json(&self) -> String {
+        let mut out = String::with_capacity(512 + self.chapters.len() * 256);
+        profile_json(self).write(&mut out, 0);
+        out
+    }
+}
+
+/// JSON value tree; objects keep insertion order.
+enum Json {
+    Null,
+    Bool(bool),
+    Num(String),
+    Str(String),
+    Arr(Vec<Json>),
+    Obj(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    fn num(value: impl core::fmt::Display) -> Self {
+        Self::Num(value.to_string())
+    }
+
+    fn float(value: f32) -> Self {
+        if value.is_finite() {
+            Self::num(value)
+        } else {
+            Self::Null
+        }
+    }
+
+    fn str(value: &str) -> Self {
+        Self::Str(value.to_string())
+    }
+
+    fn debug(value: impl core::fmt::Debug) -> Self {
+        Self::Str(format!("{:?}", value))
+    }
+
+    fn opt<T>(value: Option<T>, f: impl FnOnce(T) -> Json) -> Self {
+        value.map_or(Self::Null, f)
+    }
+
+    fn write(&self, out: &mut String, depth: usize) {
+        match self {
+            Self::Null => out.push_str("null"),
+            Self::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+            Self::Num(value) => out.push_str(value),
+            Self::Str(value) => write_string(out, value),
+            Self::Arr(items) if items.is_empty() => out.push_str("[]"),
+            Self::Obj(fields) if fields.is_empty() => out.push_str("{}"),
+            Self::Arr(items) => {
+                out.push('[');
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        out.push(',');
+                    }
+                    newline(out, depth + 1);
+                    item.write(out, depth + 1);
+                }
+                newline(out, depth);
+                out.push(']');
+            }
+            Self::Obj(fields) => {
+                out.push('{');
+                for (idx, (key, value)) in fields.iter().enumerate() {
+                    if idx > 0 {
+                        out.push(',');
+                    }
+                    newline(out, depth + 1);
+                    write_string(out, key);
+                    out.push_str(": ");
+                    value.write(out, depth + 1);
+                }
+                newline(out, depth);
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn newline(out: &mut String, depth: usize) {
+    out.push('\n');
+    for _ in 0..depth {
+        out.push_str("  ");
+    }
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c <= '\u{1f}' => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn page_json(page: &RenderPage) -> Json {
+    let commands = |cmds: &[DrawCommand]| Json::Arr(cmds.iter().map(command_json).collect());
+    Json::Obj(vec![
+        ("page_number", Json::num(page.page_number)),
+        (
+            "chapter_title",
+            Json::opt(page.chapter_title.as_deref(), Json::str),
+        ),
+        ("metrics", metrics_json(&page.metrics)),
+        (
+            "damage",
+            Json::opt(page.damage.as_deref(), |rects| {
+                Json::Arr(rects.iter().map(rect_json).collect())
+            }),
+        ),
+        ("content_commands", commands(&page.content_commands)),
+        ("chrome_commands", commands(&page.chrome_commands)),
+        ("overlay_commands", commands(&page.overlay_commands)),
+        (
+            "overlay_items",
+            Json::Arr(page.overlay_items.iter().map(overlay_item_json).collect()),
+        ),
+        (
+            "annotations",
+            Json::Arr(page.annotations.iter().map(annotation_json).collect()),
+        ),
+        (
+            "line_positions",
+            Json::Arr(page.line_positions.iter().map(position_json).collect()),
+        ),
+    ])
+}
+
+fn metrics_json(metrics: &PageMetrics) -> Json {
+    Json::Obj(vec![
+        ("chapter_index", Json::num(metrics.chapter_index)),
+        ("chapter_page_index", Json::num(metrics.chapter_page_index)),
+        (
+            "chapter_page_count",
+            Json::opt(metrics.chapter_page_count, Json::num),
+        ),
+        (
+            "global_page_index",
+            Json::opt(metrics.global_page_index, Json::num),
+        ),
+        (
+            "global_page_count_estimate",
+            Json::opt(metrics.global_page_count_estimate, Json::num),
+        ),
+        ("progress_chapter", Json::float(metrics.progress_chapter)),
+        (
+            "progress_book",
+            Json::opt(metrics.progress_book, Json::float),
+        ),
+        (
+            "physical_width_mm",
+            Json::opt(metrics.physical_width_mm, Json::float),
+        ),
+        (
+            "physical_height_mm",
+            Json::opt(metrics.physical_height_mm, Json::float),
+        ),
+        (
+            "pages_left_in_chapter",
+            Json::opt(metrics.pages_left_in_chapter, Json::num),
+        ),
+        (
+            "pages_left_in_book",
+            Json::opt(metrics.pages_left_in_book, Json::num),
+        ),
+        (
+            "start_position",
+            Json::opt(metrics.start_position.as_ref(), position_json),
+        ),
+        ("word_count", Json::num(metrics.word_count)),
+        ("image_count", Json::num(metrics.image_count)),
+    ])
+}
+
+fn position_json(position: &TextPosition) -> Json {
+    Json::Obj(vec![
+        ("block_index", Json::num(position.block_index)),
+        ("char_offset", Json::num(position.char_offset)),
+    ])
+}
+
+fn rect_json(rect: &PageRect) -> Json {
+    Json::Obj(vec![
+        ("x", Json::num(rect.x)),
+        ("y", Json::num(rect.y)),
+        ("width", Json::num(rect.width)),
+        ("height", Json::num(rect.height)),
+    ])
+}
+
+fn color_json(color: Option<Color>) -> Json {
+    Json::opt(color, |Color { r, g, b }| {
+        Json::Str(format!("#{:02x}{:02x}{:02x}", r, g, b))
+    })
+}
+
+fn bitmap_json(bitmap: Option<&GrayBitmap>) -> Json {
+    Json::opt(bitmap, |bitmap| {
+        Json::Obj(vec![
+            ("width", Json::num(bitmap.width)),
+            ("height", Json::num(bitmap.height)),
+        ])
+    })
+}
+
+fn command_json(cmd: &DrawCommand) -> Json {
+    let tagged = |kind: &'static str, mut fields: Vec<(&'static str, Json)>| {
+        fields.insert(0, ("type", Json::str(kind)));
+        Json::Obj(fields)
+    };
+    match cmd {
+        DrawCommand::Text(text) => tagged("text", text_fields(text)),
+        DrawCommand::Rule(rule) => tagged(
+            "rule",
+            vec![
+                ("x", Json::num(rule.x)),
+                ("y", Json::num(rule.y)),
+                ("length", Json::num(rule.length)),
+                ("thickness", Json::num(rule.thickness)),
+                ("horizontal", Json::Bool(rule.horizontal)),
+            ],
+        ),
+        DrawCommand::Rect(rect) => tagged(
+            "rect",
+            vec![
+                ("x", Json::num(rect.x)),
+                ("y", Json::num(rect.y)),
+                ("width", Json::num(rect.width)),
+                ("height", Json::num(rect.height)),
+                ("fill", Json::Bool(rect.fill)),
+                ("color", color_json(rect.color)),
+            ],
+        ),
+        DrawCommand::PageChrome(chrome) => tagged(
+            "page_chrome",
+            vec![
+                ("kind", Json::debug(chrome.kind)),
+                ("text", Json::opt(chrome.text.as_deref(), Json::str)),
+                ("current", Json::opt(chrome.current, Json::num)),
+                ("total", Json::opt(chrome.total, Json::num)),
+            ],
+        ),
+        DrawCommand::Image(image) => tagged(
+            "image",
+            vec![
+                ("x", Json::num(image.x)),
+                ("y", Json::num(image.y)),
+                ("width", Json::num(image.width)),
+                ("height", Json::num(image.height)),
+                ("src", Json::str(&image.src)),
+                ("alt", Json::str(&image.alt)),
+                ("bitmap", bitmap_json(image.bitmap.as_ref())),
+            ],
+        ),
+        DrawCommand::PushClip(rect) => tagged("push_clip", vec![("rect", rect_json(rect))]),
+        DrawCommand::PopClip => tagged("pop_clip", Vec::with_capacity(0)),
+        DrawCommand::Layered(layered) => tagged(
+            "layered",
+            vec![
+                ("z", Json::num(layered.z)),
+                ("command", command_json(&layered.command)),
+            ],
+        ),
+    }
+}
+
+fn text_fields(text: &TextCommand) -> Vec<(&'static str, Json)> {
+    vec![
+        ("x", Json::num(text.x)),
+        ("baseline_y", Json::num(text.baseline_y)),
+        ("text", Json::str(&text.text)),
+        ("font_id", Json::opt(text.font_id, Json::num)),
+        ("style", style_json(&text.style)),
+        (
+            "glyphs",
+            Json::opt(text.glyphs.as_deref(), |glyphs| {
+                Json::Arr(
+                    glyphs
+                        .iter()
+                        .map(|glyph| {
+                            Json::Obj(vec![
+                                ("x_advance", Json::num(glyph.x_advance)),
+                                ("x_offset", Json::num(glyph.x_offset)),
+                            ])
+                        })
+                        .collect(),
+                )
+            }),
+        ),
+        ("hint", Json::debug(text.hint)),
+    ]
+}
+
+fn style_json(style: &ResolvedTextStyle) -> Json {
+    Json::Obj(vec![
+        ("font_id", Json::opt(style.font_id, Json::num)),
+        ("family", Json::str(&style.family)),
+        ("weight", Json::num(style.weight)),
+        ("italic", Json::Bool(style.italic)),
+        ("size_px", Json::float(style.size_px)),
+        ("line_height", Json::float(style.line_height)),
+        ("letter_spacing", Json::float(style.letter_spacing)),
+        ("role", Json::debug(style.role)),
+        ("justify_mode", Json::debug(style.justify_mode)),
+        ("color", color_json(style.color)),
+        (
+            "metrics",
+            Json::Obj(vec![
+                ("ascent_px", Json::float(style.metrics.ascent_px)),
+                ("descent_px", Json::float(style.metrics.descent_px)),
+                ("x_height_px", Json::float(style.metrics.x_height_px)),
+                ("cap_height_px", Json::float(style.metrics.cap_height_px)),
+            ]),
+        ),
+    ])
+}
+
+fn overlay_item_json(item: &OverlayItem) -> Json {
+    let slot = match &item.slot {
+        OverlaySlot::Custom(rect) => Json::Obj(vec![(
+            "custom",
+            rect_json(&PageRect::new(rect.x, rect.y, rect.width, rect.height)),
+        )]),
+        OverlaySlot::At { x, y, anchor } => Json::Obj(vec![
+            ("x", Json::num(x)),
+            ("y", Json::num(y)),
+            ("anchor", Json::debug(anchor)),
+        ]),
+        OverlaySlot::Edge {
+            edge,
+            inset,
+            offset,
+            anchor,
+        } => Json::Obj(vec![
+            ("edge", Json::debug(edge)),
+            ("inset", Json::num(inset)),
+            ("offset", Json::num(offset)),
+            ("anchor", Json::debug(anchor)),
+        ]),
+        named => Json::debug(named),
+    };
+    let content = match &item.content {
+        OverlayContent::Text(text) => Json::Obj(vec![("text", Json::str(text))]),
+        OverlayContent::Command(cmd) => Json::Obj(vec![("command", command_json(cmd))]),
+        OverlayContent::Image(image) => Json::Obj(vec![(
+            "image",
+            Json::Obj(vec![
+                ("key", Json::str(&image.key)),
+                ("bitmap", bitmap_json(image.bitmap.as_ref())),
+                ("width", Json::num(image.size.width)),
+                ("height", Json::num(image.size.height)),
+            ]),
+        )]),
+    };
+    Json::Obj(vec![
+        ("slot", slot),
+        ("z", Json::num(item.z)),
+        ("content", content),
+    ])
+}
+
+fn annotation_json(annotation: &PageAnnotation) -> Json {
+    match annotation {
+        PageAnnotation::Tag { kind, value } => Json::Obj(vec![
+            ("type", Json::str("tag")),
+            ("kind", Json::str(kind)),
+            ("value", Json::opt(value.as_deref(), Json::str)),
+        ]),
+        PageAnnotation::Link { rect, target } => {
+            let target = match target {
+                LinkTarget::Internal(link) => Json::Obj(vec![
+                    ("href", Json::str(&link.href)),
+                    ("fragment", Json::opt(link.fragment.as_deref(), Json::str)),
+                    ("chapter_index", Json::opt(link.chapter_index, Json::num)),
+                    ("page_index", Json::opt(link.page_index, Json::num)),
+                ]),
+                LinkTarget::External(url) => Json::Obj(vec![("url", Json::str(url))]),
+            };
+            Json::Obj(vec![
+                ("type", Json::str("link")),
+                ("rect", rect_json(rect)),
+                ("target", target),
+            ])
+        }
+        PageAnnotation::ImageDescription {
+            rect,
+            src,
+            alt,
+            caption,
+        } => Json::Obj(vec![
+            ("type", Json::str("image_description")),
+            ("rect", rect_json(rect)),
+            ("src", Json::str(src)),
+            ("alt", Json::opt(alt.as_deref(), Json::str)),
+            ("caption", Json::opt(caption.as_deref(), Json::str)),
+        ]),
+        PageAnnotation::Heading { level, text, rect } => Json::Obj(vec![
+            ("type", Json::str("heading")),
+            ("level", Json::num(level)),
+            ("text", Json::str(text)),
+            ("rect", rect_json(rect)),
+        ]),
+        PageAnnotation::PendingImage { rect, src } => Json::Obj(vec![
+            ("type", Json::str("pending_image")),
+            ("rect", rect_json(rect)),
+            ("src", Json::str(src)),
+        ]),
+    }
+}
+
+fn profile_json(report: &ProfileReport) -> Json {
+    Json::Obj(vec![
+        ("chapters_prepared", Json::num(report.chapters_prepared)),
+        ("pages_emitted", Json::num(report.pages_emitted)),
+        ("total_micros", Json::num(report.total_micros())),
+        (
+            "phase_micros",
+            phase_micros_json(|phase| report.phase_micros(phase)),
+        ),
+        ("cache_hits", Json::num(report.cache_hits)),
+        ("cache_misses", Json::num(report.cache_misses)),
+        ("image_cache_hits", Json::num(report.image_cache_hits)),
+        ("image_cache_misses", Json::num(report.image_cache_misses)),
+        ("bytes_decompressed", Json::num(report.bytes_decompressed)),
+        ("images_decoded", Json::num(report.images_decoded)),
+        ("peak_page_bytes", Json::num(report.peak_page_bytes)),
+        (
+            "chapters",
+            Json::Arr(report.chapters.iter().map(chapter_profile_json).collect()),
+        ),
+    ])
+}
+
+fn chapter_profile_json(chapter: &ChapterProfile) -> Json {
+    Json::Obj(vec![
+        ("chapter_index", Json::num(chapter.chapter_index)),
+        ("runs", Json::num(chapter.runs)),
+        ("total_micros", Json::num(chapter.total_micros())),
+        (
+            "phase_micros",
+            phase_micros_json(|phase| chapter.phase_micros(phase)),
+        ),
+        ("peak_page_bytes", Json::num(chapter.peak_page_bytes)),
+    ])
+}
+
+fn phase_micros_json(micros: impl Fn(RenderPhase) -> u64) -> Json {
+    Json::Obj(vec![
+        ("tokenize", Json::num(micros(RenderPhase::Tokenize))),
+        ("style", Json::num(micros(RenderPhase::Style))),
+        ("line_break", Json::num(micros(RenderPhase::LineBreak))),
+        ("paginate", Json::num(micros(RenderPhase::Paginate))),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_ir::{RectCommand, RuleCommand};
+
+    #[test]
+    fn debug_json_lists_layers_metrics_and_annotations() {
+        let mut page = RenderPage::new(3);
+        page.push_content_command(DrawCommand::Rule(RuleCommand {
+            x: 4,
+            y: 10,
+            length: 20,
+            thickness: 1,
+            horizontal: true,
+        }));
+        page.push_chrome_command(DrawCommand::Rect(RectCommand {
+            x: 0,
+            y: 0,
+            width: 8,
+            height: 2,
+            fill: true,
+            color: Some(Color::BLACK),
+        }));
+        page.annotations.push(PageAnnotation::Tag {
+            kind: "note".to_string(),
+            value: Some("say \"hi\"\n".to_string()),
+        });
+        page.metrics.progress_chapter = f32::NAN;
+        page.sync_commands();
+
+        let json = page.to_debug_json();
+        assert!(json.starts_with("{\n  \"page_number\": 3,\n"));
+        assert!(json.contains("\"chapter_page_index\": 2"));
+        assert!(json.contains("\"progress_chapter\": null"));
+        assert!(json.contains("\"type\": \"rule\""));
+        assert!(json.contains("\"color\": \"#000000\""));
+        assert!(json.contains("\"value\": \"say \\\"hi\\\"\\n\""));
+        assert!(json.contains("\"overlay_commands\": []"));
+        assert!(!json.contains("\"commands\""));
+        let opens = json.matches(['{', '[']).count();
+        let closes = json.matches(['}', ']']).count();
+        assert_eq!(opens, closes);
+    }
+}
@@ -1,12 +1,43 @@
-use mu_epub::{EpubBook, RenderPrep, RenderPrepError, RenderPrepOptions, StyledEventOrRun};
+use mu_epub::memory::{MemoryGauge, MemorySubsystem};
+use mu_epub::navigation::NavPoint;
+use mu_epub::{
+    BlockRole, ChapterRef, ComputedTextStyle, EpubBook, ErrorLimitContext, ErrorPhase, PhaseError,
+    PhaseErrorContext, RenderPrep, RenderPrepError, RenderPrepOptions, StyledEvent,
+    StyledEventOrRun, StyledImage, StyledRun,
+};
+use std::borrow::Cow;
 use std::collections::VecDeque;
 use std::fmt;
+use std::ops::ControlFlow;
 use std::sync::mpsc::{sync_channel, Receiver};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use crate::render_ir::{OverlayContent, OverlaySize, PaginationProfileId, RenderPage};
+use crate::render_annotate::{Annotation, AnnotationStore};
+use crate::render_bookmark::{Bookmark, BookmarkStore};
+use crate::render_cache::{
+    BookFingerprint, CacheLedger, ChapterIr, PaginationProfile, ProfileChange, SessionSnapshot,
+};
+use crate::render_diff::{merge_rects, PageDiff};
+use crate::render_estimate::PageEstimator;
+use crate::render_image::{
+    attach_decoded_images, decode_gauge, fill_pending_images, mark_pending_images, resize,
+    DecodedImageCache, ImageCacheKey, ImageDecodeOptions, ImageDecoderRegistry,
+};
+use crate::render_ir::{
+    BookPageMap, ContentLocator, DrawCommand, GrayBitmap, InternalLink, LayeredCommand, LinkTarget,
+    OverlayContent, OverlaySize, PageAnnotation, PageChromeKind, PageChromeProvider, PageRect,
+    PaginationProfileId, RenderIntent, RenderPage, Rotation, StaticPageChrome, SvgMode,
+    TextPosition,
+};
 use crate::render_layout::{LayoutConfig, LayoutEngine, LayoutSession as CoreLayoutSession};
+use crate::render_profile::{ProfileReport, Profiler};
+use crate::render_reverse::ReverseFill;
+use crate::render_search::{may_contain, BookSearchHit, SearchOptions};
+use crate::render_spread::{
+    pair_pages, spine_spread_side, RenderSpread, SpreadOptions, SpreadStart,
+};
+use crate::render_thumb::ThumbnailConfig;
 
 /// Cancellation hook for long-running layout operations.
 pub trait CancelToken {
@@ -24,22 +55,446 @@ impl CancelToken for NeverCancel {
 }
 
 /// Runtime diagnostics from render preparation/layout.
+///
+/// Chapter-scoped variants are reported once per laid-out chapter; cached
+/// chapters report only their `CacheLookup`.
 #[derive(Clone, Debug, PartialEq)]
 pub enum RenderDiagnostic {
     ReflowTimeMs(u32),
     Cancelled,
+    /// Time spent in one pipeline stage while preparing a chapter.
+    PhaseTime {
+        chapter_index: usize,
+        phase: RenderPhase,
+        micros: u64,
+    },
+    /// Peak bytes of pages buffered by a chapter's layout session.
+    PeakScratchBytes {
+        chapter_index: usize,
+        bytes: usize,
+    },
+    /// Render-cache lookup for a chapter.
+    CacheLookup {
+        chapter_index: usize,
+        hit: bool,
+    },
+    /// Decoded-image cache lookup for a raster image resource.
+    ImageCacheLookup {
+        src: String,
+        hit: bool,
+    },
+    /// A text run resolved to a different family than it requested.
+    ///
+    /// Reported once per family pair and chapter, with the page being laid
+    /// out when it first occurred.
+    FontFallback {
+        chapter_index: usize,
+        page_index: usize,
+        requested: String,
+        resolved: String,
+    },
+    /// Content skipped or drawn as a placeholder during layout.
+    DroppedContent {
+        chapter_index: usize,
+        page_index: usize,
+        kind: DroppedContentKind,
+        src: String,
+    },
+    /// `RenderPage::to_debug_json` of a page selected by
+    /// `RenderEngine::set_page_dump_interval`.
+    PageDump {
+        chapter_index: usize,
+        page_index: usize,
+        json: String,
+    },
+}
+
+impl RenderDiagnostic {
+    /// Category used by `DiagnosticFilter`.
+    pub fn category(&self) -> DiagnosticCategory {
+        match self {
+            Self::ReflowTimeMs(_) | Self::PhaseTime { .. } | Self::PeakScratchBytes { .. } => {
+                DiagnosticCategory::Timing
+            }
+            Self::CacheLookup { .. } | Self::ImageCacheLookup { .. } => DiagnosticCategory::Cache,
+            Self::Cancelled => DiagnosticCategory::Lifecycle,
+            Self::FontFallback { .. } => DiagnosticCategory::Warning,
+            Self::DroppedContent { .. } => DiagnosticCategory::ContentLoss,
+            Self::PageDump { .. } => DiagnosticCategory::PageDump,
+        }
+    }
+
+    /// Severity, fixed per category.
+    pub fn level(&self) -> DiagnosticLevel {
+        self.category().level()
+    }
+}
+
+/// Compact RTT log form; page dumps log their size instead of the JSON.
+#[cfg(feature = "defmt")]
+impl defmt::Format for RenderDiagnostic {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self {
+            Self::ReflowTimeMs(ms) => defmt::write!(f, "reflow {=u32} ms", ms),
+            Self::Cancelled => defmt::write!(f, "cancelled"),
+            Self::PhaseTime {
+                chapter_index,
+                phase,
+                micros,
+            } => defmt::write!(
+                f,
+                "chapter {=usize} {} {=u64} us",
+                chapter_index,
+                phase,
+                micros
+            ),
+            Self::PeakScratchBytes {
+                chapter_index,
+                bytes,
+            } => defmt::write!(
+                f,
+                "chapter {=usize} peak page bytes {=usize}",
+                chapter_index,
+                bytes
+            ),
+            Self::CacheLookup { chapter_index, hit } => {
+                defmt::write!(f, "chapter {=usize} cache hit={=bool}", chapter_index, hit)
+            }
+            Self::ImageCacheLookup { src, hit } => {
+                defmt::write!(f, "image {=str} cache hit={=bool}", src.as_str(), hit)
+            }
+            Self::FontFallback {
+                chapter_index,
+                page_index,
+                requested,
+                resolved,
+            } => defmt::write!(
+                f,
+                "chapter {=usize} page {=usize} font {=str} -> {=str}",
+                chapter_index,
+                page_index,
+                requested.as_str(),
+                resolved.as_str()
+            ),
+            Self::DroppedContent {
+                chapter_index,
+                page_index,
+                kind,
+                src,
+            } => defmt::write!(
+                f,
+                "chapter {=usize} page {=usize} dropped {} {=str}",
+                chapter_index,
+                page_index,
+                kind,
+                src.as_str()
+            ),
+            Self::PageDump {
+                chapter_index,
+                page_index,
+                json,
+            } => defmt::write!(
+                f,
+                "chapter {=usize} page {=usize} dump ({=usize} bytes)",
+                chapter_index,
+                page_index,
+                json.len()
+            ),
+        }
+    }
+}
+
+/// Severity of a `RenderDiagnostic`, from least to most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiagnosticLevel {
+    /// Measurements for profiling.
+    Debug,
+    /// Run lifecycle events.
+    Info,
+    /// Output differs from what the book asked for.
+    Warn,
+}
+
+/// Kind of a `RenderDiagnostic`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagnosticCategory {
+    /// Phase timings, reflow time, and peak scratch bytes.
+    Timing,
+    /// Render-cache lookups.
+    Cache,
+    /// Cancellation.
+    Lifecycle,
+    /// Substitutions such as font fallback.
+    Warning,
+    /// Content skipped or replaced by a placeholder.
+    ContentLoss,
+    /// JSON page dumps.
+    PageDump,
+}
+
+impl DiagnosticCategory {
+    /// Severity of diagnostics in this category.
+    pub const fn level(self) -> DiagnosticLevel {
+        match self {
+            Self::Timing | Self::Cache | Self::PageDump => DiagnosticLevel::Debug,
+            Self::Lifecycle => DiagnosticLevel::Info,
+            Self::Warning | Self::ContentLoss => DiagnosticLevel::Warn,
+        }
+    }
+}
+
+/// Which diagnostics reach the sink, see `RenderEngine::set_diagnostic_filter`.
+///
+/// Filtered categories are not only dropped but never measured: without
+/// `timing` no clocks are read, and without `warnings`/`content_loss` styled
+/// items are not inspected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiagnosticFilter {
+    /// Drop diagnostics below this level.
+    pub min_level: DiagnosticLevel,
+    /// Pass `DiagnosticCategory::Timing`.
+    pub timing: bool,
+    /// Pass `DiagnosticCategory::Cache`.
+    pub cache: bool,
+    /// Pass `DiagnosticCategory::Lifecycle`.
+    pub lifecycle: bool,
+    /// Pass `DiagnosticCategory::Warning`.
+    pub warnings: bool,
+    /// Pass `DiagnosticCategory::ContentLoss`.
+    pub content_loss: bool,
+    /// Pass `DiagnosticCategory::PageDump`.
+    pub page_dumps: bool,
+}
+
+impl DiagnosticFilter {
+    /// Every diagnostic; the default.
+    pub const ALL: Self = Self {
+        min_level: DiagnosticLevel::Debug,
+        timing: true,
+        cache: true,
+        lifecycle: true,
+        warnings: true,
+        content_loss: true,
+        page_dumps: true,
+    };
+
+    /// Only diagnostics at or above `level`.
+    pub const fn at_least(level: DiagnosticLevel) -> Self {
+        Self {
+            min_level: level,
+            ..Self::ALL
+        }
+    }
+
+    /// Whether diagnostics in `category` pass.
+    pub fn allows_category(&self, category: DiagnosticCategory) -> bool {
+        let enabled = match category {
+            DiagnosticCategory::Timing => self.timing,
+            DiagnosticCategory::Cache => self.cache,
+            DiagnosticCategory::Lifecycle => self.lifecycle,
+            DiagnosticCategory::Warning => self.warnings,
+            DiagnosticCategory::ContentLoss => self.content_loss,
+            DiagnosticCategory::PageDump => self.page_dumps,
+        };
+        enabled && category.level() >= self.min_level
+    }
+
+    /// Whether `diagnostic` passes.
+    pub fn allows(&self, diagnostic: &RenderDiagnostic) -> bool {
+        self.allows_category(diagnostic.category())
+    }
+}
+
+impl Default for DiagnosticFilter {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// Pipeline stage timed by `RenderDiagnostic::PhaseTime`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RenderPhase {
+    /// Reading and tokenizing chapter markup.
+    Tokenize,
+    /// Stylesheets, cascade, and font resolution.
+    Style,
+    /// Line breaking and block placement.
+    LineBreak,
+    /// Closing pages: metrics, damage, and cache capture.
+    Paginate,
+}
+
+/// Cooperative yield callback, see `RenderEngineOptions::yield_hook`.
+///
+/// A plain function so options stay `Copy`; firmware keeps its watchdog
+/// and UI state in statics.
+#[derive(Clone, Copy, Debug)]
+pub struct YieldHook {
+    /// Called on the render thread; must not re-enter the engine.
+    pub callback: fn(YieldPoint),
+    /// Styled items laid out between calls; `0` behaves like `1`.
+    pub every_items: usize,
+}
+
+impl YieldHook {
+    /// Hook calling `callback` every `every_items` styled items.
+    pub const fn new(callback: fn(YieldPoint), every_items: usize) -> Self {
+        Self {
+            callback,
+            every_items,
+        }
+    }
+
+    fn call(&self, chapter_index: usize, phase: RenderPhase, items: usize) {
+        (self.callback)(YieldPoint {
+            chapter_index,
+            phase,
+            items,
+        });
+    }
+}
+
+impl PartialEq for YieldHook {
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::fn_addr_eq(self.callback, other.callback)
+            && self.every_items == other.every_items
+    }
+}
+
+/// Where a render was when it invoked the `YieldHook`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct YieldPoint {
+    /// Chapter being rendered.
+    pub chapter_index: usize,
+    /// `Tokenize` before the chapter is read, `LineBreak` between styled
+    /// items, `Paginate` after each page closes.
+    pub phase: RenderPhase,
+    /// Styled items laid out so far in this chapter.
+    pub items: usize,
+}
+
+/// Why content was reported by `RenderDiagnostic::DroppedContent`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DroppedContentKind {
+    /// SVG skipped under `SvgMode::Ignore`.
+    IgnoredSvg,
+    /// SVG markup over `svg_max_bytes`, or an inline island dropped for
+    /// exceeding `max_inline_svg_bytes`, drawn as a placeholder.
+    OversizedSvg,
+    /// SVG resource unreadable for rasterization, drawn as a placeholder.
+    MissingSvgPayload,
+}
+
+/// Cumulative engine counters, see `RenderEngine::telemetry`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EngineTelemetry {
+    /// Chapters laid out to completion; cache hits are not counted.
+    pub chapters_prepared: u64,
+    /// Pages handed to callers, cached or freshly laid out.
+    pub pages_emitted: u64,
+    /// Render-cache lookups that returned pages.
+    pub cache_hits: u64,
+    /// Render-cache lookups that missed.
+    pub cache_misses: u64,
+    /// Chapter markup and object resources read from the book.
+    pub bytes_decompressed: u64,
+    /// Raster images decoded to bitmaps.
+    pub images_decoded: u64,
+    /// Most bytes of pages one layout session held at once.
+    pub peak_page_bytes: usize,
 }
 
 type DiagnosticCallback = Arc<Mutex<Box<dyn FnMut(RenderDiagnostic) + Send + 'static>>>;
 type DiagnosticSink = Option<DiagnosticCallback>;
+/// Decoded raster images keyed by source href, in document order.
+type DecodedImages = VecDeque<(String, GrayBitmap)>;
+
+/// Outcome of preparing a raster image for layout.
+enum RasterPayload {
+    /// Pixels decoded into the content box.
+    Decoded(GrayBitmap),
+    /// Size known from the header; decoding left to the caller.
+    Deferred,
+}
+
+/// Progress of a whole-book pagination run, reported after each chapter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PaginationProgress {
+    /// Chapter just paginated (spine order).
+    pub chapter_index: usize,
+    /// Chapters in the spine.
+    pub chapter_count: usize,
+    /// Pages in the chapter just paginated.
+    pub chapter_pages: usize,
+    /// Pages paginated so far across the book.
+    pub pages_so_far: usize,
+}
+
+impl PaginationProgress {
+    /// Fraction of chapters done, in `0.0..=1.0`.
+    pub fn fraction(&self) -> f32 {
+        if self.chapter_count == 0 {
+            return 1.0;
+        }
+        (self.chapter_index + 1) as f32 / self.chapter_count as f32
+    }
+}
+
+/// Book-level navigation target for scrub bars and go-to dialogs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BookTarget {
+    /// Fraction of the book, clamped to `0.0..=1.0`.
+    Fraction(f32),
+    /// Zero-based global page index.
+    GlobalPage(usize),
+}
 
 /// Render-engine options.
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct RenderEngineOptions {
     /// Prep options passed to `RenderPrep`.
     pub prep: RenderPrepOptions,
-    /// Layout options used to produce pages.
+    /// Layout options used to produce pages, in physical panel terms.
     pub layout: LayoutConfig,
+    /// Panel rotation; layout runs on the rotated (reader-facing) viewport.
+    pub rotation: Rotation,
+    /// Panel density in pixels per inch.
+    ///
+    /// Overrides `prep.style.hints.dpi` so absolute CSS units match the panel,
+    /// and sizes `PageMetrics::physical_width_mm`/`physical_height_mm`.
+    pub dpi: f32,
+    /// Facing-page mode: pages are laid out at half the display width,
+    /// minus the gutter, for `prepare_chapter_spreads`.
+    pub spread: Option<SpreadOptions>,
+    /// Called at bounded intervals while a chapter renders, so
+    /// single-threaded firmware can pet its watchdog and pump the UI
+    /// without cancelling. Does not affect pagination.
+    pub yield_hook: Option<YieldHook>,
+    /// Golden-test mode: identical inputs give byte-identical pages.
+    ///
+    /// Layout itself uses only ordered collections and plain `f32`
+    /// arithmetic, so pages never depend on timing, threads, or platform.
+    /// This switch removes the remaining outside inputs: chrome providers
+    /// are replaced by `StaticPageChrome`, cache stores are written but
+    /// never read, and timing diagnostics report zero. Does not affect
+    /// pagination.
+    pub deterministic: bool,
+}
+
+impl Default for RenderEngineOptions {
+    fn default() -> Self {
+        Self {
+            prep: RenderPrepOptions::default(),
+            layout: LayoutConfig::default(),
+            rotation: Rotation::Deg0,
+            dpi: 96.0,
+            spread: None,
+            yield_hook: None,
+            deterministic: false,
+        }
+    }
 }
 
 impl RenderEngineOptions {
@@ -48,6 +503,7 @@ impl RenderEngineOptions {
         Self {
             prep: RenderPrepOptions::default(),
             layout: LayoutConfig::for_display(width, height),
+            ..Self::default()
         }
     }
 }
@@ -55,7 +511,50 @@ impl RenderEngineOptions {
 /// Alias used for chapter page slicing.
 pub type PageRange = core::ops::Range<usize>;
 
+/// Bounds a `RenderCacheStore` asks the engine to keep it within.
+///
+/// `None` leaves that dimension unbounded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheCapacity {
+    /// Most chapters kept per store.
+    pub max_chapters: Option<usize>,
+    /// Most page bytes kept per store, by `RenderPage::approx_bytes`.
+    pub max_bytes: Option<usize>,
+}
+
+impl CacheCapacity {
+    /// Whether one chapter of `bytes` fits at all.
+    pub fn admits(&self, bytes: usize) -> bool {
+        self.max_chapters != Some(0) && self.max_bytes.is_none_or(|max| bytes <= max)
+    }
+
+    /// Whether `chapters` totalling `bytes` exceed the bounds.
+    pub fn exceeded_by(&self, chapters: usize, bytes: usize) -> bool {
+        self.max_chapters.is_some_and(|max| chapters > max)
+            || self.max_bytes.is_some_and(|max| bytes > max)
+    }
+}
+
+/// When a `RenderCacheStore` commits stored pages to its medium.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CacheWritePolicy {
+    /// Every `store_chapter_pages` call is persisted immediately.
+    #[default]
+    WriteThrough,
+    /// Stores are buffered until `RenderCacheStore::flush`, which the
+    /// engine calls after `paginate_book` and which firmware should call
+    /// before sleep or power-off.
+    WriteBack,
+}
+
 /// Storage hooks for render-page caches.
+///
+/// Only the load and store hooks are required. Flash-backed stores can
+/// report a `capacity_hint`: the engine then remembers which chapters it
+/// stored or loaded and calls `evict_chapter_pages` for the least
+/// recently rendered ones once the hint is exceeded. Recency is tracked
+/// per engine (shared by its clones), so a store should be used by one
+/// engine.
 pub trait RenderCacheStore {
     /// Load cached pages for `chapter_index` and pagination profile, if available.
     fn load_chapter_pages(
@@ -74,6 +573,22 @@ pub trait RenderCacheStore {
         _pages: &[RenderPage],
     ) {
     }
+
+    /// Bounds the engine should keep this store within, if any.
+    fn capacity_hint(&self) -> Option<CacheCapacity> {
+        None
+    }
+
+    /// How stores reach the underlying medium.
+    fn write_policy(&self) -> CacheWritePolicy {
+        CacheWritePolicy::WriteThrough
+    }
+
+    /// Drop one chapter to stay within `capacity_hint`.
+    fn evict_chapter_pages(&self, _profile: PaginationProfileId, _chapter_index: usize) {}
+
+    /// Commit buffered stores under `CacheWritePolicy::WriteBack`.
+    fn flush(&self) {}
 }
 
 /// Per-run configuration used by `RenderEngine::begin`.
@@ -83,6 +598,12 @@ pub struct RenderConfig<'a> {
     cache: Option<&'a dyn RenderCacheStore>,
     cancel: Option<&'a dyn CancelToken>,
     embedded_fonts: bool,
+    book_page_counts: Option<&'a [usize]>,
+    annotations: Option<&'a dyn AnnotationStore>,
+    bookmarks: Option<&'a dyn BookmarkStore>,
+    stop_after_page: Option<usize>,
+    error_policy: ChapterErrorPolicy,
+    deferred_images: bool,
 }
 
 impl<'a> Default for RenderConfig<'a> {
@@ -92,6 +613,12 @@ impl<'a> Default for RenderConfig<'a> {
             cache: None,
             cancel: None,
             embedded_fonts: true,
+            book_page_counts: None,
+            annotations: None,
+            bookmarks: None,
+            stop_after_page: None,
+            error_policy: ChapterErrorPolicy::Fail,
+            deferred_images: false,
         }
     }
 }
@@ -123,14 +650,90 @@ impl<'a> RenderConfig<'a> {
         self.embedded_fonts = enabled;
         self
     }
+
+    /// Supply per-chapter page counts (spine order) from a prior pagination
+    /// pass, enabling book-level page metrics and book progress chrome.
+    pub fn with_book_page_counts(mut self, counts: &'a [usize]) -> Self {
+        self.book_page_counts = Some(counts);
+        self
+    }
+
+    /// Use a whole-book page map for global page numbers and book progress.
+    pub fn with_book_page_map(self, map: &'a BookPageMap) -> Self {
+        self.with_book_page_counts(map.chapter_page_counts())
+    }
+
+    /// Composite stored annotations onto rendered pages.
+    ///
+    /// Each chapter's annotations are loaded once and marked on the overlay
+    /// layer at z `0`, with note markers centred in the right margin.
+    pub fn with_annotations(mut self, store: &'a dyn AnnotationStore) -> Self {
+        self.annotations = Some(store);
+        self
+    }
+
+    /// Draw a bookmark ribbon in the chrome of pages holding a bookmark.
+    ///
+    /// The ribbon hangs from the top edge, centred in the right margin.
+    pub fn with_bookmarks(mut self, store: &'a dyn BookmarkStore) -> Self {
+        self.bookmarks = Some(store);
+        self
+    }
+
+    /// Interrupt a resumable run once chapter page `page_index` is laid out.
+    ///
+    /// Only `prepare_chapter_resumable` honors this; the run returns
+    /// `ChapterRun::Interrupted` as if cancelled. Resume with a config
+    /// without the stop (or with a later one) to lay out the rest.
+    pub fn with_stop_after_page(mut self, page_index: usize) -> Self {
+        self.stop_after_page = Some(page_index);
+        self
+    }
+
+    /// Choose what a failing chapter produces; see `ChapterErrorPolicy`.
+    pub fn with_error_policy(mut self, policy: ChapterErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// Lay out raster images from their header size alone and decode later.
+    ///
+    /// Pages are emitted without waiting on pixel decoding: each image not
+    /// already in the decoded-image cache keeps its rect reserved and is
+    /// marked with a `PageAnnotation::PendingImage`. Deliver pixels with
+    /// `RenderEngine::resolve_pending_images` or `fill_pending_image`.
+    pub fn with_deferred_images(mut self, enabled: bool) -> Self {
+        self.deferred_images = enabled;
+        self
+    }
+}
+
+/// Outcome of a chapter that fails to parse or exceeds memory limits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChapterErrorPolicy {
+    /// Return the error from the prepare call.
+    #[default]
+    Fail,
+    /// Emit a placeholder page with the error text after any pages already
+    /// produced, and succeed. Cancellation still fails.
+    Placeholder,
 }
 
 /// Render engine for chapter -> page conversion.
 #[derive(Clone)]
 pub struct RenderEngine {
     opts: RenderEngineOptions,
+    layout_cfg: LayoutConfig,
     layout: LayoutEngine,
     diagnostic_sink: DiagnosticSink,
+    diagnostic_filter: DiagnosticFilter,
+    page_dump_every: usize,
+    image_decoders: ImageDecoderRegistry,
+    cache_ledger: Arc<Mutex<CacheLedger>>,
+    page_start_memo: Arc<Mutex<PageStartMemo>>,
+    telemetry: Arc<Mutex<EngineTelemetry>>,
+    image_cache: Arc<Mutex<DecodedImageCache>>,
+    profiler: Option<Arc<Mutex<Profiler>>>,
 }
 
 impl fmt::Debug for RenderEngine {
@@ -144,11 +747,52 @@ impl fmt::Debug for RenderEngine {
 
 impl RenderEngine {
     /// Create a render engine.
-    pub fn new(opts: RenderEngineOptions) -> Self {
+    pub fn new(mut opts: RenderEngineOptions) -> Self {
+        opts.prep.style.hints.dpi = opts.dpi;
+        let mut layout_cfg = opts.layout.rotated(opts.rotation);
+        if let Some(spread) = opts.spread {
+            layout_cfg.display_width = spread.page_width(layout_cfg.display_width);
+        }
+        let layout_cfg = layout_cfg.effective();
         Self {
-            layout: LayoutEngine::new(opts.layout),
+            layout: LayoutEngine::new(layout_cfg),
+            layout_cfg,
             opts,
             diagnostic_sink: None,
+            diagnostic_filter: DiagnosticFilter::ALL,
+            page_dump_every: 0,
+            image_decoders: ImageDecoderRegistry::builtin(),
+            cache_ledger: Arc::default(),
+            page_start_memo: Arc::default(),
+            telemetry: Arc::default(),
+            image_cache: Arc::default(),
+            profiler: None,
+        }
+    }
+
+    /// Options the engine was built with.
+    pub fn options(&self) -> &RenderEngineOptions {
+        &self.opts
+    }
+
+    /// Replace the decoders used for raster images.
+    ///
+    /// Defaults to `ImageDecoderRegistry::builtin`. Decoded images are
+    /// scaled to layout size, mapped through the render intent, and stored
+    /// in `ImageCommand::bitmap`; pass an empty registry to leave decoding
+    /// to the backend.
+    pub fn set_image_decoders(&mut self, decoders: ImageDecoderRegistry) {
+        self.image_decoders = decoders;
+        self.image_cache = Arc::default();
+    }
+
+    /// Drop decoded images kept for reuse across layouts.
+    ///
+    /// The cache is bounded by `ObjectLayoutConfig::image_cache_bytes`;
+    /// clear it to return that memory early, e.g. when closing a book.
+    pub fn clear_image_cache(&self) {
+        if let Ok(mut cache) = self.image_cache.lock() {
+            cache.clear();
         }
     }
 
@@ -160,10 +804,144 @@ impl RenderEngine {
         self.diagnostic_sink = Some(Arc::new(Mutex::new(Box::new(sink))));
     }
 
-    fn emit_diagnostic(&self, diagnostic: RenderDiagnostic) {
+    /// Limit which diagnostics reach the sink.
+    ///
+    /// Defaults to `DiagnosticFilter::ALL`. Filtered categories are skipped
+    /// at the source, so e.g. `DiagnosticFilter::at_least(DiagnosticLevel::Warn)`
+    /// keeps timing out of the render loop.
+    pub fn set_diagnostic_filter(&mut self, filter: DiagnosticFilter) {
+        self.diagnostic_filter = filter;
+    }
+
+    /// Dump every `every`th page of each chapter to the diagnostics sink.
+    ///
+    /// Pages whose chapter page index is a multiple of `every` are reported
+    /// as `RenderDiagnostic::PageDump`, with links, titles, and annotations
+    /// already attached. `0`, the default, disables dumps; `1` dumps every
+    /// page. Pages are only serialized when a sink accepts
+    /// `DiagnosticCategory::PageDump`.
+    pub fn set_page_dump_interval(&mut self, every: usize) {
+        self.page_dump_every = every;
+    }
+
+    /// Counters accumulated since creation or the last `reset_telemetry`.
+    ///
+    /// Shared with clones of this engine and with sessions re-created by
+    /// `LayoutSession::relayout_with`.
+    pub fn telemetry(&self) -> EngineTelemetry {
+        self.telemetry
+            .lock()
+            .map(|telemetry| *telemetry)
+            .unwrap_or_default()
+    }
+
+    /// Zero every telemetry counter.
+    pub fn reset_telemetry(&self) {
+        self.record(|telemetry| *telemetry = EngineTelemetry::default());
+    }
+
+    /// Aggregate phase timings and counters for `profile_report`.
+    ///
+    /// Profiling measures timings even without a diagnostics sink, so leave
+    /// it off in production renders. Enabling starts a fresh profile shared
+    /// with clones made afterwards; disabling drops it.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profiler = enabled.then(|| Arc::new(Mutex::new(Profiler::new(self.telemetry()))));
+    }
+
+    /// Timings and counters gathered since profiling was enabled or last
+    /// reset; `None` while profiling is off.
+    ///
+    /// Typically read after `paginate_book` to see where a slow book spends
+    /// its time.
+    pub fn profile_report(&self) -> Option<ProfileReport> {
+        let profiler = self.profiler.as_ref()?.lock().ok()?;
+        Some(profiler.report(self.telemetry()))
+    }
+
+    /// Start a fresh profile without toggling profiling.
+    pub fn reset_profile(&self) {
+        if let Some(Ok(mut profiler)) = self.profiler.as_ref().map(|p| p.lock()) {
+            *profiler = Profiler::new(self.telemetry());
+        }
+    }
+
+    fn record(&self, update: impl FnOnce(&mut EngineTelemetry)) {
+        if let Ok(mut telemetry) = self.telemetry.lock() {
+            update(&mut telemetry);
+        }
+    }
+
+    /// Count the chapter markup `prep` just read.
+    fn record_chapter_read(&self, prep: &RenderPrep) {
+        let bytes = prep.chapter_bytes() as u64;
+        self.record(|telemetry| telemetry.bytes_decompressed += bytes);
+    }
+
+    /// Read an object resource of at most `cap` bytes.
+    fn read_object<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        src: &str,
+        cap: usize,
+    ) -> Option<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(cap.min(64 * 1024));
+        book.read_resource_into_with_hard_cap(src, &mut bytes, cap)
+            .ok()?;
+        let read = bytes.len() as u64;
+        self.record(|telemetry| telemetry.bytes_decompressed += read);
+        Some(bytes)
+    }
+
+    /// Whether diagnostics in `category` would reach a sink or the profiler.
+    fn wants_diagnostic(&self, category: DiagnosticCategory) -> bool {
+        let profiled = matches!(
+            category,
+            DiagnosticCategory::Timing | DiagnosticCategory::Cache
+        );
+        (self.diagnostic_sink.is_some() && self.diagnostic_filter.allows_category(category))
+            || (profiled && self.profiler.is_some())
+    }
+
+    /// Start time for timing diagnostics, when any would reach a sink.
+    ///
+    /// Always `None` on `wasm32-unknown-unknown`, where `Instant::now`
+    /// panics for lack of a platform clock.
+    fn timing_clock(&self) -> Option<Instant> {
+        if cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+            return None;
+        }
+        self.wants_diagnostic(DiagnosticCategory::Timing)
+            .then(Instant::now)
+    }
+
+    fn emit_reflow_time(&self, started: Option<Instant>) {
+        if let Some(started) = started {
+            let elapsed = started.elapsed().as_millis().min(u32::MAX as u128) as u32;
+            self.emit_diagnostic(RenderDiagnostic::ReflowTimeMs(elapsed));
+        }
+    }
+
+    fn emit_diagnostic(&self, mut diagnostic: RenderDiagnostic) {
+        if self.diagnostic_sink.is_none() && self.profiler.is_none() {
+            return;
+        }
+        if self.opts.deterministic {
+            match &mut diagnostic {
+                RenderDiagnostic::ReflowTimeMs(ms) => *ms = 0,
+                RenderDiagnostic::PhaseTime { micros, .. } => *micros = 0,
+                _ => {}
+            }
+        }
+        if let Some(Ok(mut profiler)) = self.profiler.as_ref().map(|p| p.lock()) {
+            profiler.observe(&diagnostic);
+        }
         let Some(sink) = &self.diagnostic_sink else {
             return;
         };
+        if !self.diagnostic_filter.allows(&diagnostic) {
+            return;
+        }
         if let Ok(mut sink) = sink.lock() {
             sink(diagnostic);
         }
@@ -171,8 +949,76 @@ impl RenderEngine {
 
     /// Stable fingerprint for all layout-affecting settings.
     pub fn pagination_profile_id(&self) -> PaginationProfileId {
-        let payload = format!("{:?}|{:?}", self.opts.prep, self.opts.layout);
-        PaginationProfileId::from_bytes(payload.as_bytes())
+        self.pagination_profile().id()
+    }
+
+    /// Settings that determine pagination, one named field each.
+    ///
+    /// Store it alongside cached pages; `pagination_profile_changes` later
+    /// names what changed, e.g. the base font size.
+    pub fn pagination_profile(&self) -> PaginationProfile {
+        let prep = &self.opts.prep;
+        let hints = &prep.style.hints;
+        let layout = &self.opts.layout;
+        let fields: [(&str, &dyn fmt::Debug); 35] = [
+            ("prep.style.limits", &prep.style.limits),
+            (
+                "prep.style.hints.base_font_size_px",
+                &hints.base_font_size_px,
+            ),
+            ("prep.style.hints.min_font_size_px", &hints.min_font_size_px),
+            ("prep.style.hints.max_font_size_px", &hints.max_font_size_px),
+            ("prep.style.hints.min_line_height", &hints.min_line_height),
+            ("prep.style.hints.max_line_height", &hints.max_line_height),
+            ("prep.style.hints.text_scale", &hints.text_scale),
+            ("prep.style.hints.dpi", &hints.dpi),
+            ("prep.fonts", &prep.fonts),
+            ("prep.layout_hints", &prep.layout_hints),
+            ("prep.memory", &prep.memory),
+            ("layout.display_width", &layout.display_width),
+            ("layout.display_height", &layout.display_height),
+            ("layout.margin_left", &layout.margin_left),
+            ("layout.margin_right", &layout.margin_right),
+            ("layout.margin_top", &layout.margin_top),
+            ("layout.margin_bottom", &layout.margin_bottom),
+            ("layout.line_gap_px", &layout.line_gap_px),
+            ("layout.paragraph_gap_px", &layout.paragraph_gap_px),
+            ("layout.heading_gap_px", &layout.heading_gap_px),
+            ("layout.list_indent_px", &layout.list_indent_px),
+            ("layout.first_line_indent_px", &layout.first_line_indent_px),
+            (
+                "layout.suppress_indent_after_heading",
+                &layout.suppress_indent_after_heading,
+            ),
+            ("layout.justify_min_words", &layout.justify_min_words),
+            (
+                "layout.justify_min_fill_ratio",
+                &layout.justify_min_fill_ratio,
+            ),
+            ("layout.min_line_height_px", &layout.min_line_height_px),
+            ("layout.max_line_height_px", &layout.max_line_height_px),
+            ("layout.soft_hyphen_policy", &layout.soft_hyphen_policy),
+            ("layout.page_chrome", &layout.page_chrome),
+            ("layout.typography", &layout.typography),
+            ("layout.object_layout", &layout.object_layout),
+            ("layout.render_intent", &layout.render_intent),
+            ("rotation", &self.opts.rotation),
+            ("dpi", &self.opts.dpi),
+            ("spread", &self.opts.spread),
+        ];
+        PaginationProfile::new(
+            fields
+                .iter()
+                .map(|(name, value)| (name.to_string(), format!("{:?}", value)))
+                .collect(),
+        )
+    }
+
+    /// Fields of `stored` that differ from this engine's configuration.
+    ///
+    /// Empty when pages cached under `stored` are still valid here.
+    pub fn pagination_profile_changes(&self, stored: &PaginationProfile) -> Vec<ProfileChange> {
+        self.pagination_profile().changes_from(stored)
     }
 
     /// Begin a chapter layout session for embedded/incremental integrations.
@@ -181,44 +1027,603 @@ impl RenderEngine {
         chapter_index: usize,
         config: RenderConfig<'a>,
     ) -> LayoutSession<'a> {
-        let profile = self.pagination_profile_id();
-        let mut pending = VecDeque::new();
-        let mut cached_hit = false;
-        if let Some(cache) = config.cache {
-            if let Some(pages) = cache.load_chapter_pages(profile, chapter_index) {
-                cached_hit = true;
-                let range = normalize_page_range(config.page_range.clone());
-                for (idx, mut page) in pages.into_iter().enumerate() {
-                    Self::annotate_page_for_chapter(&mut page, chapter_index);
-                    if page_in_range(idx, &range) {
-                        pending.push_back(page);
-                    }
-                }
-            }
-        }
-        LayoutSession {
-            engine: self,
-            chapter_index,
-            profile,
-            cfg: config,
-            inner: if cached_hit {
-                None
-            } else {
-                Some(self.layout.start_session())
-            },
-            pending_pages: pending,
-            rendered_pages: Vec::with_capacity(0),
-            page_index: 0,
-            completed: cached_hit,
+        LayoutSession::open(Cow::Borrowed(self), chapter_index, config)
+    }
+
+    /// Record that `chapter_index` was just stored or loaded, evicting
+    /// the least recently rendered chapters past the store's capacity hint.
+    fn touch_cached_chapter(
+        &self,
+        cache: &dyn RenderCacheStore,
+        profile: PaginationProfileId,
+        chapter_index: usize,
+        bytes: usize,
+    ) {
+        let Some(capacity) = cache.capacity_hint() else {
+            return;
+        };
+        let evicted = match self.cache_ledger.lock() {
+            Ok(mut ledger) => ledger.touch(profile, chapter_index, bytes, capacity),
+            Err(_) => return,
+        };
+        for (profile, chapter_index) in evicted {
+            cache.evict_chapter_pages(profile, chapter_index);
         }
     }
 
-    fn annotate_page_for_chapter(page: &mut RenderPage, chapter_index: usize) {
+    fn annotate_page_for_chapter(
+        &self,
+        page: &mut RenderPage,
+        chapter_index: usize,
+        book_page_counts: Option<&[usize]>,
+    ) {
         page.metrics.chapter_index = chapter_index;
         page.metrics.chapter_page_index = page.page_number.saturating_sub(1);
+        if self.opts.dpi > 0.0 {
+            let mm_per_px = 25.4 / self.opts.dpi;
+            page.metrics.physical_width_mm =
+                Some(self.layout_cfg.display_width.max(0) as f32 * mm_per_px);
+            page.metrics.physical_height_mm =
+                Some(self.layout_cfg.display_height.max(0) as f32 * mm_per_px);
+        }
+        if let Some(counts) = book_page_counts {
+            page.metrics.apply_book_page_counts(counts);
+            let chrome = &self.layout_cfg.page_chrome;
+            if chrome.book_progress_enabled
+                || chrome.header_template.is_some()
+                || chrome.footer_template.is_some()
+            {
+                page.recompose_chrome(chrome, &StaticPageChrome);
+            }
+        }
     }
 
-    /// Prepare and layout a chapter into render pages.
+    /// TOC label for a chapter, resolved only when chrome templates use it.
+    fn chapter_title_for<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+    ) -> Option<String> {
+        if !self.layout_cfg.page_chrome.uses_chapter_title() {
+            return None;
+        }
+        let href = book.chapter(chapter_index).ok()?.href;
+        let toc = &book.ensure_navigation().ok()??.toc;
+        toc_label_for_href(toc, &href)
+    }
+
+    fn page_decorations<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+        config: &RenderConfig<'_>,
+    ) -> PageDecorations {
+        let chapter_title = self.chapter_title_for(book, chapter_index);
+        self.decorations_with(
+            book.chapters().collect(),
+            chapter_title,
+            chapter_index,
+            config,
+        )
+    }
+
+    fn decorations_with(
+        &self,
+        chapters: Vec<ChapterRef>,
+        chapter_title: Option<String>,
+        chapter_index: usize,
+        config: &RenderConfig<'_>,
+    ) -> PageDecorations {
+        PageDecorations {
+            chapters,
+            chapter_title,
+            annotations: config
+                .annotations
+                .map(|store| store.load_annotations(chapter_index))
+                .unwrap_or_default(),
+            bookmarks: config
+                .bookmarks
+                .map(|store| store.bookmarks_for_chapter(chapter_index))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn decorate(&self, page: &mut RenderPage, decorations: &PageDecorations) {
+        resolve_page_links(page, &decorations.chapters);
+        self.attach_chapter_title(page, decorations.chapter_title.as_deref());
+        self.attach_annotations(page, &decorations.annotations);
+        self.attach_bookmark_indicator(page, &decorations.bookmarks);
+        self.dump_page(page);
+    }
+
+    fn dump_page(&self, page: &RenderPage) {
+        let every = self.page_dump_every;
+        let page_index = page.metrics.chapter_page_index;
+        if every == 0
+            || page_index % every != 0
+            || !self.wants_diagnostic(DiagnosticCategory::PageDump)
+        {
+            return;
+        }
+        self.emit_diagnostic(RenderDiagnostic::PageDump {
+            chapter_index: page.metrics.chapter_index,
+            page_index,
+            json: page.to_debug_json(),
+        });
+    }
+
+    fn attach_annotations(&self, page: &mut RenderPage, annotations: &[Annotation]) {
+        let marker_x = self.layout_cfg.display_width - self.layout_cfg.margin_right / 2;
+        for annotation in annotations {
+            page.push_annotation_overlay(annotation, marker_x, 0);
+        }
+    }
+
+    fn attach_bookmark_indicator(&self, page: &mut RenderPage, bookmarks: &[Bookmark]) {
+        if page.bookmarks_on_page(bookmarks).is_empty() {
+            return;
+        }
+        let cfg = &self.layout_cfg;
+        let width = 10;
+        let area = PageRect::new(
+            cfg.display_width - cfg.margin_right / 2 - width / 2,
+            0,
+            width as u32,
+            (cfg.margin_top * 2 / 3).max(8) as u32,
+        );
+        page.push_bookmark_indicator(area);
+    }
+
+    fn attach_chapter_title(&self, page: &mut RenderPage, title: Option<&str>) {
+        let Some(title) = title else {
+            return;
+        };
+        page.chapter_title = Some(title.to_string());
+        page.recompose_chrome(&self.layout_cfg.page_chrome, &StaticPageChrome);
+    }
+
+    /// Page counts for every chapter in spine order.
+    ///
+    /// Runs a full layout pass over the book; feed the result to
+    /// `RenderConfig::with_book_page_counts`.
+    pub fn book_page_counts<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+    ) -> Result<Vec<usize>, RenderEngineError> {
+        let mut counts = Vec::with_capacity(book.chapter_count());
+        for chapter_index in 0..book.chapter_count() {
+            let mut pages = 0usize;
+            self.prepare_chapter_with(book, chapter_index, |_| pages += 1)?;
+            counts.push(pages);
+        }
+        Ok(counts)
+    }
+
+    /// Approximate page count of one chapter, for progress bars shown
+    /// before pagination finishes.
+    ///
+    /// Styles the chapter but skips line breaking: block widths come from
+    /// char counts and average glyph widths, wrapped to the viewport.
+    /// Embedded fonts, SVG payloads, and page limits are not loaded or
+    /// checked, so the result can differ from `prepare_chapter` by a few
+    /// pages.
+    pub fn estimate_pages<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+    ) -> Result<usize, RenderEngineError> {
+        let mut estimator = PageEstimator::new(self.layout_cfg);
+        let mut prep = RenderPrep::new(self.opts.prep).with_serif_default();
+        prep.prepare_chapter_with(book, chapter_index, |item| estimator.push(item))?;
+        self.record_chapter_read(&prep);
+        Ok(estimator.finish())
+    }
+
+    /// Paginate the whole spine under this engine's profile.
+    ///
+    /// The map translates global page numbers ("page 214 of 502") to
+    /// chapter pages; pass it to `RenderConfig::with_book_page_map` or
+    /// `RenderEngine::prepare_book_page`. `cancel` is checked between
+    /// chapters and between styled items; `progress` runs after each chapter.
+    pub fn paginate_book<R, C, F>(
+        &self,
+        book: &mut EpubBook<R>,
+        cancel: &C,
+        progress: F,
+    ) -> Result<BookPageMap, RenderEngineError>
+    where
+        R: std::io::Read + std::io::Seek,
+        C: CancelToken,
+        F: FnMut(PaginationProgress),
+    {
+        let config = RenderConfig::default().with_cancel(cancel);
+        self.paginate_book_with_config(book, config, progress)
+    }
+
+    /// Paginate the whole spine with explicit run config.
+    ///
+    /// Intended for background pre-pagination after a book opens: with
+    /// `RenderConfig::with_cache`, every chapter is stored in the cache, and
+    /// chapters already cached are counted without re-layout, so a cancelled
+    /// run resumes where it stopped. Any page range on `config` is ignored.
+    pub fn paginate_book_with_config<R, F>(
+        &self,
+        book: &mut EpubBook<R>,
+        mut config: RenderConfig<'_>,
+        mut progress: F,
+    ) -> Result<BookPageMap, RenderEngineError>
+    where
+        R: std::io::Read + std::io::Seek,
+        F: FnMut(PaginationProgress),
+    {
+        config.page_range = None;
+        let chapter_count = book.chapter_count();
+        let _span = phase_span!("paginate", chapter_count = chapter_count);
+        let mut counts = Vec::with_capacity(chapter_count);
+        let mut pages_so_far = 0usize;
+        // Write-back stores commit whatever was paginated, even when the
+        // run stops early.
+        let flush = |config: &RenderConfig<'_>| {
+            if let Some(cache) = config.cache {
+                if cache.write_policy() == CacheWritePolicy::WriteBack {
+                    cache.flush();
+                }
+            }
+        };
+        for chapter_index in 0..chapter_count {
+            if config.cancel.is_some_and(|cancel| cancel.is_cancelled()) {
+                flush(&config);
+                self.emit_diagnostic(RenderDiagnostic::Cancelled);
+                return Err(RenderEngineError::Cancelled);
+            }
+            let mut chapter_pages = 0usize;
+            let result =
+                self.prepare_chapter_with_config(book, chapter_index, config.clone(), |_| {
+                    chapter_pages += 1
+                });
+            if let Err(err) = result {
+                flush(&config);
+                return Err(err);
+            }
+            counts.push(chapter_pages);
+            pages_so_far += chapter_pages;
+            progress(PaginationProgress {
+                chapter_index,
+                chapter_count,
+                chapter_pages,
+                pages_so_far,
+            });
+        }
+        flush(&config);
+        Ok(BookPageMap::new(self.pagination_profile_id(), counts))
+    }
+
+    /// Render one page by global page index (go-to-page).
+    ///
+    /// Returns `Ok(None)` when the index is past the end of the book and
+    /// `RenderEngineError::ProfileMismatch` when `map` was built under
+    /// different layout settings.
+    pub fn prepare_book_page<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        map: &BookPageMap,
+        global_page_index: usize,
+    ) -> Result<Option<RenderPage>, RenderEngineError> {
+        self.prepare_book_target(book, Some(map), BookTarget::GlobalPage(global_page_index))
+    }
+
+    /// Render the page at a book percentage or global page in one call.
+    ///
+    /// With `map`, the target is exact. Without it, a fraction is placed by
+    /// chapter byte size and then by page within the chapter, and a global
+    /// page is found by counting chapter pages from the start of the book.
+    /// Returns `Ok(None)` when the target is past the end of the book.
+    pub fn prepare_book_target<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        map: Option<&BookPageMap>,
+        target: BookTarget,
+    ) -> Result<Option<RenderPage>, RenderEngineError> {
+        self.prepare_book_target_with_config(book, map, target, RenderConfig::default())
+    }
+
+    /// Render a book target with explicit run config.
+    ///
+    /// With `RenderConfig::with_cache`, the chapters laid out to resolve an
+    /// unmapped target are stored and reused. Any page range on `config` is
+    /// ignored.
+    pub fn prepare_book_target_with_config<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        map: Option<&BookPageMap>,
+        target: BookTarget,
+        mut config: RenderConfig<'_>,
+    ) -> Result<Option<RenderPage>, RenderEngineError> {
+        config.page_range = None;
+        let Some(map) = map else {
+            return match target {
+                BookTarget::Fraction(fraction) => {
+                    self.prepare_estimated_fraction(book, fraction, config)
+                }
+                BookTarget::GlobalPage(index) => self.prepare_counted_page(book, index, config),
+            };
+        };
+        if map.profile() != self.pagination_profile_id() {
+            return Err(RenderEngineError::ProfileMismatch);
+        }
+        let global_page_index = match target {
+            BookTarget::Fraction(fraction) => match map.global_index_for_fraction(fraction) {
+                Some(index) => index,
+                None => return Ok(None),
+            },
+            BookTarget::GlobalPage(index) => index,
+        };
+        let Some((chapter_index, page_index)) = map.locate(global_page_index) else {
+            return Ok(None);
+        };
+        let config = config
+            .with_page_range(page_index..page_index + 1)
+            .with_book_page_map(map);
+        let pages = self.prepare_chapter_with_config_collect(book, chapter_index, config)?;
+        Ok(pages.into_iter().next())
+    }
+
+    /// Place `fraction` by chapter byte size, then by chapter page count.
+    fn prepare_estimated_fraction<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        fraction: f32,
+        config: RenderConfig<'_>,
+    ) -> Result<Option<RenderPage>, RenderEngineError> {
+        let fraction = if fraction.is_nan() {
+            0.0
+        } else {
+            fraction.clamp(0.0, 1.0)
+        };
+        let sizes: Vec<usize> = (0..book.chapter_count())
+            .map(|index| book.chapter_uncompressed_size(index).unwrap_or(0))
+            .collect();
+        let total: usize = sizes.iter().sum();
+        let target = fraction * total as f32;
+        let mut before = 0usize;
+        let mut start = (sizes.len().saturating_sub(1), 1.0f32);
+        for (index, size) in sizes.iter().enumerate() {
+            if *size > 0 && target < (before + size) as f32 {
+                start = (index, (target - before as f32) / *size as f32);
+                break;
+            }
+            before += size;
+        }
+        if total == 0 {
+            start = (0, fraction);
+        }
+        // Empty chapters defer to the first page of the next one.
+        let (first_chapter, mut within) = start;
+        for chapter_index in first_chapter..sizes.len() {
+            let pages =
+                self.prepare_chapter_with_config_collect(book, chapter_index, config.clone())?;
+            if let Some(last) = pages.len().checked_sub(1) {
+                let page_index = ((within * pages.len() as f32) as usize).min(last);
+                return Ok(pages.into_iter().nth(page_index));
+            }
+            within = 0.0;
+        }
+        Ok(None)
+    }
+
+    /// Find a global page by counting chapter pages from the book start.
+    fn prepare_counted_page<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        global_page_index: usize,
+        config: RenderConfig<'_>,
+    ) -> Result<Option<RenderPage>, RenderEngineError> {
+        let mut remaining = global_page_index;
+        for chapter_index in 0..book.chapter_count() {
+            let mut count = 0usize;
+            let mut found = None;
+            self.prepare_chapter_with_config(book, chapter_index, config.clone(), |page| {
+                if count == remaining {
+                    found = Some(page);
+                }
+                count += 1;
+            })?;
+            if found.is_some() {
+                return Ok(found);
+            }
+            remaining -= count;
+        }
+        Ok(None)
+    }
+
+    /// Search the whole book, streaming hits in reading order.
+    ///
+    /// `cancel` is checked between chapters and between styled items.
+    pub fn search<R, C, F>(
+        &self,
+        book: &mut EpubBook<R>,
+        query: &str,
+        options: SearchOptions,
+        cancel: &C,
+        on_hit: F,
+    ) -> Result<(), RenderEngineError>
+    where
+        R: std::io::Read + std::io::Seek,
+        C: CancelToken,
+        F: FnMut(BookSearchHit),
+    {
+        let config = RenderConfig::default().with_cancel(cancel);
+        self.search_with_config(book, query, options, config, on_hit)
+    }
+
+    /// Search the whole book with explicit run config.
+    ///
+    /// Each chapter's extracted text is checked first, so only chapters
+    /// that can match are laid out; with `RenderConfig::with_cache`, those
+    /// layouts are stored and reused. Hits carry page rectangles under this
+    /// engine's settings. Any page range on `config` is ignored.
+    pub fn search_with_config<R, F>(
+        &self,
+        book: &mut EpubBook<R>,
+        query: &str,
+        options: SearchOptions,
+        mut config: RenderConfig<'_>,
+        mut on_hit: F,
+    ) -> Result<(), RenderEngineError>
+    where
+        R: std::io::Read + std::io::Seek,
+        F: FnMut(BookSearchHit),
+    {
+        config.page_range = None;
+        let mut text = String::with_capacity(0);
+        for chapter_index in 0..book.chapter_count() {
+            if config.cancel.is_some_and(|cancel| cancel.is_cancelled()) {
+                self.emit_diagnostic(RenderDiagnostic::Cancelled);
+                return Err(RenderEngineError::Cancelled);
+            }
+            // Unreadable text falls through to layout, which reports errors.
+            if book.chapter_text_into(chapter_index, &mut text).is_ok()
+                && !may_contain(&text, query, options)
+            {
+                continue;
+            }
+            self.prepare_chapter_with_config(book, chapter_index, config.clone(), |page| {
+                let matches = page.find_text(query, options);
+                for hit in page.search_hits(&matches) {
+                    on_hit(BookSearchHit {
+                        chapter_index,
+                        chapter_page_index: page.metrics.chapter_page_index,
+                        hit,
+                    });
+                }
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Reflow-stable locator for the start of `page`.
+    ///
+    /// Pages without recorded content resolve to their chapter start.
+    /// `chapter_href` is left unset; attach it with
+    /// `ContentLocator::with_chapter_href` to survive spine reordering.
+    pub fn locate(&self, page: &RenderPage) -> ContentLocator {
+        ContentLocator::new(
+            page.metrics.chapter_index,
+            page.metrics.start_position.unwrap_or_default(),
+        )
+    }
+
+    /// Render the page containing `locator` under this engine's settings.
+    ///
+    /// The chapter is found by `chapter_href` when set, else by index.
+    /// Returns `Ok(None)` when the chapter no longer exists or is empty;
+    /// positions past the chapter end resolve to its last page.
+    pub fn page_for<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        locator: &ContentLocator,
+    ) -> Result<Option<RenderPage>, RenderEngineError> {
+        self.page_for_with_config(book, locator, RenderConfig::default())
+    }
+
+    /// Resolve a locator to a page with explicit run config.
+    ///
+    /// Any page range on `config` is ignored.
+    pub fn page_for_with_config<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        locator: &ContentLocator,
+        mut config: RenderConfig<'_>,
+    ) -> Result<Option<RenderPage>, RenderEngineError> {
+        let Some(chapter_index) = locator_chapter(book, locator) else {
+            return Ok(None);
+        };
+        config.page_range = None;
+        let mut found: Option<RenderPage> = None;
+        self.prepare_chapter_with_config(book, chapter_index, config, |page| {
+            let starts_before = page
+                .metrics
+                .start_position
+                .is_some_and(|start| start <= locator.position);
+            if found.is_none() || starts_before {
+                found = Some(page);
+            }
+        })?;
+        Ok(found)
+    }
+
+    /// Chapter page an internal link lands on.
+    ///
+    /// Links without a fragment land on the first page. For a fragment,
+    /// the target chapter is laid out under `config` (so a configured cache
+    /// is used) and the page holding the anchor id is returned. `None`
+    /// when `link.href` is not a spine item or the id never appears.
+    pub fn resolve_link<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        link: &InternalLink,
+        mut config: RenderConfig<'_>,
+    ) -> Result<Option<usize>, RenderEngineError> {
+        let Some(chapter_index) = book
+            .chapters()
+            .find(|chapter| chapter.href == link.href)
+            .map(|chapter| chapter.index)
+        else {
+            return Ok(None);
+        };
+        let Some(fragment) = link.fragment.as_deref() else {
+            return Ok(Some(0));
+        };
+        config.page_range = None;
+        let mut found = None;
+        self.prepare_chapter_with_config(book, chapter_index, config, |page| {
+            if found.is_none() && page.anchors().any(|id| id == fragment) {
+                found = Some(page.metrics.chapter_page_index);
+            }
+        })?;
+        Ok(found)
+    }
+
+    /// Prepare a chapter as low-fidelity page thumbnails.
+    ///
+    /// The chapter is laid out directly at thumbnail size, with panel
+    /// geometry and font sizes scaled by `ThumbnailConfig::scale_for`.
+    /// Images are deferred, so their boxes are sized from the header but
+    /// never decoded, and SVG is not rasterized. Text lines then become
+    /// bars via `RenderPage::thumbnail`. Page breaks
+    /// approximate, but need not match, the full-size layout. Cached pages
+    /// are stored under the thumbnail layout's own pagination profile.
+    pub fn prepare_chapter_thumbnails<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+        config: RenderConfig<'_>,
+        thumbnail: &ThumbnailConfig,
+    ) -> Result<Vec<RenderPage>, RenderEngineError> {
+        let scale = thumbnail.scale_for(
+            self.layout_cfg.display_width,
+            self.layout_cfg.display_height,
+        );
+        let scaled = RenderEngine::new(thumbnail.layout_options(&self.opts, scale));
+        let engine = RenderEngine {
+            opts: scaled.opts,
+            layout_cfg: scaled.layout_cfg,
+            layout: scaled.layout,
+            ..self.clone()
+        };
+        let (width, height) = (
+            engine.layout_cfg.display_width,
+            engine.layout_cfg.display_height,
+        );
+        let config = config.with_deferred_images(true);
+        let mut thumbs = Vec::with_capacity(0);
+        engine.prepare_chapter_with_config(book, chapter_index, config, |page| {
+            thumbs.push(page.thumbnail(width, height, thumbnail));
+        })?;
+        Ok(thumbs)
+    }
+
+    /// Prepare and layout a chapter into render pages.
     pub fn prepare_chapter<R: std::io::Read + std::io::Seek>(
         &self,
         book: &mut EpubBook<R>,
@@ -227,6 +1632,60 @@ impl RenderEngine {
         self.prepare_chapter_with_config_collect(book, chapter_index, RenderConfig::default())
     }
 
+    /// Prepare a chapter as facing-page spreads.
+    ///
+    /// The first page lands on the side named by the spine item's
+    /// `page-spread-left`/`page-spread-right` property, falling back to
+    /// `SpreadOptions::chapter_start`. Without `RenderEngineOptions::spread`,
+    /// full-width pages are paired with no gutter.
+    pub fn prepare_chapter_spreads<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+    ) -> Result<Vec<RenderSpread>, RenderEngineError> {
+        self.prepare_chapter_spreads_with_config(book, chapter_index, RenderConfig::default())
+    }
+
+    /// Prepare a chapter as facing-page spreads with explicit run config.
+    ///
+    /// See `prepare_chapter_spreads`.
+    pub fn prepare_chapter_spreads_with_config<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+        config: RenderConfig<'_>,
+    ) -> Result<Vec<RenderSpread>, RenderEngineError> {
+        let spread = self.opts.spread.unwrap_or_default();
+        let properties = book
+            .spine()
+            .items()
+            .get(chapter_index)
+            .and_then(|item| item.properties.as_deref());
+        let start_right =
+            spine_spread_side(properties).unwrap_or(spread.chapter_start == SpreadStart::Recto);
+        let mut pages = self.prepare_chapter_with_config_collect(book, chapter_index, config)?;
+        // Pages streamed before the chapter end carry no chrome markers;
+        // compose them now that the page count is known.
+        let total = pages.len();
+        for page in &mut pages {
+            let has_markers = page
+                .chrome_commands
+                .iter()
+                .any(|cmd| matches!(cmd, DrawCommand::PageChrome(_)));
+            if !has_markers {
+                let chrome = self
+                    .layout_cfg
+                    .page_chrome
+                    .compose(page, total, &StaticPageChrome);
+                for cmd in chrome {
+                    page.push_chrome_command(cmd);
+                }
+            }
+        }
+        let right_x = self.layout_cfg.display_width + spread.gutter;
+        Ok(pair_pages(pages, start_right, right_x))
+    }
+
     /// Prepare and layout a chapter into render pages with explicit run config.
     pub fn prepare_chapter_with_config_collect<R: std::io::Read + std::io::Seek>(
         &self,
@@ -234,16 +1693,42 @@ impl RenderEngine {
         chapter_index: usize,
         config: RenderConfig<'_>,
     ) -> Result<Vec<RenderPage>, RenderEngineError> {
+        let policy = config.error_policy;
+        match self.collect_pages(|on_page| {
+            self.prepare_chapter_with_config(book, chapter_index, config, on_page)
+        }) {
+            Err(err) if policy == ChapterErrorPolicy::Placeholder && err.is_chapter_failure() => {
+                Ok(self.error_pages(chapter_index, 0, &err))
+            }
+            other => other,
+        }
+    }
+
+    /// Collect the pages streamed by `run`, enforcing the prep memory limits.
+    pub(crate) fn collect_pages<F>(&self, run: F) -> Result<Vec<RenderPage>, RenderEngineError>
+    where
+        F: FnOnce(&mut dyn FnMut(RenderPage)) -> Result<(), RenderEngineError>,
+    {
         let page_limit = self.opts.prep.memory.max_pages_in_memory;
+        let byte_limit = self.opts.prep.memory.max_page_bytes_in_memory;
         let mut pages = Vec::with_capacity(page_limit.min(8));
         let mut dropped_pages = 0usize;
-        self.prepare_chapter_with_config(book, chapter_index, config, |page| {
-            if pages.len() < page_limit {
+        let mut total_bytes = 0usize;
+        run(&mut |page| {
+            total_bytes = total_bytes.saturating_add(page.approx_bytes());
+            if pages.len() < page_limit && total_bytes <= byte_limit {
                 pages.push(page);
             } else {
                 dropped_pages = dropped_pages.saturating_add(1);
             }
         })?;
+        if total_bytes > byte_limit {
+            return Err(RenderEngineError::LimitExceeded {
+                kind: "max_page_bytes_in_memory",
+                actual: total_bytes,
+                limit: byte_limit,
+            });
+        }
         if dropped_pages > 0 {
             return Err(RenderEngineError::LimitExceeded {
                 kind: "max_pages_in_memory",
@@ -251,6 +1736,7 @@ impl RenderEngine {
                 limit: page_limit,
             });
         }
+        resolve_fragment_links(&mut pages);
         Ok(pages)
     }
 
@@ -274,15 +1760,22 @@ impl RenderEngine {
         book: &mut EpubBook<R>,
         chapter_index: usize,
         config: RenderConfig<'_>,
-        mut on_page: F,
+        on_page: F,
     ) -> Result<(), RenderEngineError>
     where
         R: std::io::Read + std::io::Seek,
         F: FnMut(RenderPage),
     {
         let cancel = config.cancel.unwrap_or(&NeverCancel);
-        self.prepare_chapter_with_cancel_and_config(book, chapter_index, cancel, config, |page| {
-            on_page(page)
+        let policy = config.error_policy;
+        self.recover_chapter_errors(chapter_index, policy, on_page, |on_page| {
+            self.prepare_chapter_with_cancel_and_config(
+                book,
+                chapter_index,
+                cancel,
+                config,
+                on_page,
+            )
         })
     }
 
@@ -324,81 +1817,420 @@ impl RenderEngine {
         F: FnMut(RenderPage),
     {
         let cancel = config.cancel.unwrap_or(&NeverCancel);
-        self.prepare_chapter_bytes_with_cancel_and_config(
-            book,
-            chapter_index,
-            html,
-            cancel,
-            config,
-            on_page,
-        )
+        let policy = config.error_policy;
+        self.recover_chapter_errors(chapter_index, policy, on_page, |on_page| {
+            self.prepare_chapter_bytes_with_cancel_and_config(
+                book,
+                chapter_index,
+                html,
+                cancel,
+                config,
+                on_page,
+            )
+        })
     }
 
-    /// Prepare and layout a chapter while honoring cancellation.
-    pub fn prepare_chapter_with_cancel<R, C, F>(
+    /// Tokenize and style a chapter once, for `prepare_from_ir`.
+    ///
+    /// External SVG markup (up to `svg_max_bytes`) and raster intrinsic
+    /// sizes are read from the book so later layouts match this engine's.
+    pub fn prepare_chapter_ir<R: std::io::Read + std::io::Seek>(
         &self,
         book: &mut EpubBook<R>,
         chapter_index: usize,
-        cancel: &C,
+    ) -> Result<ChapterIr, RenderEngineError> {
+        let mut items = Vec::with_capacity(64);
+        let mut prep = RenderPrep::new(self.opts.prep)
+            .with_serif_default()
+            .with_embedded_fonts_from_book(book)?;
+        prep.prepare_chapter_with_resources(book, chapter_index, |item, book| {
+            items.push(self.capture_object(book, item));
+        })?;
+        self.record_chapter_read(&prep);
+        Ok(ChapterIr {
+            chapter_index,
+            hints: self.opts.prep.style.hints,
+            chapter_title: self.chapter_title_for(book, chapter_index),
+            spine: book.chapters().collect(),
+            items,
+        })
+    }
+
+    /// Lay out a chapter from IR captured by `prepare_chapter_ir`.
+    ///
+    /// Needs no book: run sizes are rescaled to this engine's style hints,
+    /// and raster images are laid out at their captured size without
+    /// decoded pixels.
+    pub fn prepare_from_ir(&self, ir: &ChapterIr) -> Result<Vec<RenderPage>, RenderEngineError> {
+        self.collect_pages(|on_page| {
+            self.prepare_from_ir_with_config(ir, RenderConfig::default(), on_page)
+        })
+    }
+
+    /// `prepare_from_ir` with explicit run config, streaming pages.
+    ///
+    /// `embedded_fonts` is ignored; font ids were fixed at capture.
+    pub fn prepare_from_ir_with_config<F>(
+        &self,
+        ir: &ChapterIr,
+        config: RenderConfig<'_>,
         on_page: F,
     ) -> Result<(), RenderEngineError>
     where
-        R: std::io::Read + std::io::Seek,
-        C: CancelToken,
         F: FnMut(RenderPage),
     {
-        let config = RenderConfig::default().with_cancel(cancel);
-        self.prepare_chapter_with_cancel_and_config(book, chapter_index, cancel, config, on_page)
+        let chapter_index = ir.chapter_index;
+        let policy = config.error_policy;
+        self.recover_chapter_errors(chapter_index, policy, on_page, |on_page| {
+            let _span = phase_span!("layout", chapter_index = chapter_index);
+            let started = self.timing_clock();
+            let decorations = self.decorations_with(
+                ir.spine.clone(),
+                ir.chapter_title.clone(),
+                chapter_index,
+                &config,
+            );
+            let mut on_page = |mut page: RenderPage| {
+                self.decorate(&mut page, &decorations);
+                on_page(page);
+            };
+            let mut session = self.begin(chapter_index, config);
+            if !session.is_complete() {
+                for item in ir.items_for(&self.opts.prep.style.hints) {
+                    session.push(item)?;
+                    session.drain_pages(&mut on_page);
+                }
+                session.finish()?;
+            }
+            session.drain_pages(&mut on_page);
+            self.emit_reflow_time(started);
+            Ok(())
+        })
     }
 
-    fn prepare_chapter_with_cancel_and_config<R, C, F>(
+    /// Run `layout`, replacing a chapter failure with placeholder pages
+    /// under `ChapterErrorPolicy::Placeholder`.
+    fn recover_chapter_errors<F, L>(
         &self,
-        book: &mut EpubBook<R>,
         chapter_index: usize,
-        cancel: &C,
-        config: RenderConfig<'_>,
+        policy: ChapterErrorPolicy,
         mut on_page: F,
+        layout: L,
     ) -> Result<(), RenderEngineError>
     where
-        R: std::io::Read + std::io::Seek,
-        C: CancelToken + ?Sized,
         F: FnMut(RenderPage),
+        L: FnOnce(&mut dyn FnMut(RenderPage)) -> Result<(), RenderEngineError>,
     {
-        let embedded_fonts = config.embedded_fonts;
-        let started = Instant::now();
-        if cancel.is_cancelled() {
-            self.emit_diagnostic(RenderDiagnostic::Cancelled);
-            return Err(RenderEngineError::Cancelled);
+        let mut emitted = 0usize;
+        let result = layout(&mut |page| {
+            emitted += 1;
+            on_page(page);
+        });
+        match result {
+            Err(err) if policy == ChapterErrorPolicy::Placeholder && err.is_chapter_failure() => {
+                for page in self.error_pages(chapter_index, emitted, &err) {
+                    on_page(page);
+                }
+                Ok(())
+            }
+            other => other,
         }
+    }
+
+    /// Pages describing `err`, numbered after `first_page_index` earlier
+    /// pages of the chapter.
+    fn error_pages(
+        &self,
+        chapter_index: usize,
+        first_page_index: usize,
+        err: &RenderEngineError,
+    ) -> Vec<RenderPage> {
+        let run = |text: String, size_px: f32, weight: u16, block_role: BlockRole| {
+            StyledEventOrRun::Run(StyledRun {
+                text,
+                style: ComputedTextStyle {
+                    family_stack: vec!["serif".to_string()],
+                    weight,
+                    italic: false,
+                    size_px,
+                    line_height: 1.4,
+                    letter_spacing: 0.0,
+                    block_role,
+                    color: None,
+                },
+                font_id: 0,
+                resolved_family: "serif".to_string(),
+                link: None,
+                anchors: Vec::with_capacity(0),
+            })
+        };
+        let base = self.opts.prep.style.hints.base_font_size_px;
+        let items = [
+            StyledEventOrRun::Event(StyledEvent::HeadingStart(2)),
+            run(
+                "This chapter could not be displayed".to_string(),
+                base * 1.25,
+                700,
+                BlockRole::Heading(2),
+            ),
+            StyledEventOrRun::Event(StyledEvent::HeadingEnd(2)),
+            StyledEventOrRun::Event(StyledEvent::ParagraphStart),
+            run(err.to_string(), base, 400, BlockRole::Paragraph),
+            StyledEventOrRun::Event(StyledEvent::ParagraphEnd),
+        ];
+        let mut pages = self.layout.layout_items(items);
+        for page in &mut pages {
+            page.page_number += first_page_index;
+            page.metrics.chapter_page_index = page.page_number - 1;
+            self.annotate_page_for_chapter(page, chapter_index, None);
+        }
+        pages
+    }
+
+    /// Prepare and layout a chapter while honoring cancellation.
+    pub fn prepare_chapter_with_cancel<R, C, F>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+        cancel: &C,
+        on_page: F,
+    ) -> Result<(), RenderEngineError>
+    where
+        R: std::io::Read + std::io::Seek,
+        C: CancelToken,
+        F: FnMut(RenderPage),
+    {
+        let config = RenderConfig::default().with_cancel(cancel);
+        self.prepare_chapter_with_cancel_and_config(book, chapter_index, cancel, config, on_page)
+    }
+
+    /// Prepare a chapter, returning a resumable checkpoint instead of
+    /// `Cancelled` when `config`'s cancel token fires mid-chapter.
+    ///
+    /// Pages emitted before the interruption travel with the checkpoint;
+    /// pass it to `resume_chapter` to continue laying out from where it
+    /// stopped.
+    pub fn prepare_chapter_resumable<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+        config: RenderConfig<'_>,
+    ) -> Result<ChapterRun, RenderEngineError> {
+        self.run_resumable(book, chapter_index, config, None)
+    }
+
+    /// Lay out a chapter only up to and including page `page_index`.
+    ///
+    /// Opening a book mid-chapter shows the target page without waiting on
+    /// the rest; pass an `Interrupted` checkpoint to `resume_chapter` in the
+    /// background to finish the chapter. A chapter shorter than
+    /// `page_index + 1` pages comes back `Complete`. Markup past the target
+    /// page is not tokenized. `config` carries cache, fonts and stores as in
+    /// `prepare_chapter_resumable`; its own stop page is replaced.
+    pub fn prepare_chapter_to_page<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+        page_index: usize,
+        config: RenderConfig<'_>,
+    ) -> Result<ChapterRun, RenderEngineError> {
+        let config = config.with_stop_after_page(page_index);
+        self.prepare_chapter_resumable(book, chapter_index, config)
+    }
+
+    /// Continue a chapter interrupted in `prepare_chapter_resumable`.
+    ///
+    /// Styled items are re-derived from the chapter source and skipped up
+    /// to the checkpoint cursor, so `book` and the prep options must match
+    /// the original run. Fails with `ProfileMismatch` when the engine's
+    /// pagination profile changed since the checkpoint was taken.
+    pub fn resume_chapter<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        checkpoint: LayoutCheckpoint,
+        config: RenderConfig<'_>,
+    ) -> Result<ChapterRun, RenderEngineError> {
+        if checkpoint.profile != self.pagination_profile_id() {
+            return Err(RenderEngineError::ProfileMismatch);
+        }
+        let chapter_index = checkpoint.chapter_index;
+        self.run_resumable(book, chapter_index, config, Some(checkpoint))
+    }
+
+    fn run_resumable<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+        config: RenderConfig<'_>,
+        resume: Option<LayoutCheckpoint>,
+    ) -> Result<ChapterRun, RenderEngineError> {
+        let _span = phase_span!("layout", chapter_index = chapter_index);
+        let embedded_fonts = config.embedded_fonts;
+        let stop_after_page = config.stop_after_page;
+        let decorations = self.page_decorations(book, chapter_index, &config);
         let mut session = self.begin(chapter_index, config);
+        let (mut pages, skip) = match resume {
+            Some(checkpoint) => {
+                let skip = checkpoint.items_consumed;
+                (session.restore_checkpoint(checkpoint), skip)
+            }
+            None => (Vec::with_capacity(0), 0),
+        };
+        let mut on_page = |mut page: RenderPage| {
+            self.decorate(&mut page, &decorations);
+            pages.push(page);
+        };
         if session.is_complete() {
             session.drain_pages(&mut on_page);
-            return Ok(());
+            self.check_page_limits(&pages)?;
+            return Ok(ChapterRun::Complete(pages));
         }
-        let mut prep = RenderPrep::new(self.opts.prep).with_serif_default();
+        let mut clock = PrepClock::start(self.timing_clock());
+        let mut prep = RenderPrep::new(self.opts.prep)
+            .with_serif_default()
+            .with_phase_timing(clock.enabled());
         if embedded_fonts {
             prep = prep.with_embedded_fonts_from_book(book)?;
         }
-        let mut saw_cancelled = false;
-        prep.prepare_chapter_with(book, chapter_index, |item| {
-            if saw_cancelled || cancel.is_cancelled() {
-                saw_cancelled = true;
-                return;
-            }
-            if session.push(item).is_err() {
-                saw_cancelled = true;
-                return;
+        let mut seen = 0usize;
+        let mut consumed = skip;
+        let mut push_error = None;
+        // Breaking stops the styler too, so a stop page or cancellation
+        // leaves the rest of the chapter untokenized.
+        prep.prepare_chapter_with_resources_until(book, chapter_index, |item, book| {
+            clock.time(|| {
+                seen += 1;
+                if seen <= skip {
+                    return ControlFlow::Continue(());
+                }
+                if stop_after_page.is_some_and(|stop| session.page_index > stop) {
+                    push_error = Some(RenderEngineError::Cancelled);
+                    return ControlFlow::Break(());
+                }
+                let item = self.load_object_payload(book, item, &mut session);
+                // `push` checks the cancel token before consuming the item.
+                if let Err(err) = session.push(item) {
+                    push_error = Some(err);
+                    return ControlFlow::Break(());
+                }
+                consumed += 1;
+                session.drain_pages(&mut on_page);
+                ControlFlow::Continue(())
+            })
+        })?;
+        clock.report(self, chapter_index, &prep);
+        self.record_chapter_read(&prep);
+        let mut interrupted = false;
+        match push_error {
+            Some(RenderEngineError::Cancelled) => interrupted = true,
+            Some(err) => return Err(err),
+            None => {}
+        }
+        if !interrupted {
+            match session.finish() {
+                Ok(()) => {}
+                Err(RenderEngineError::Cancelled) => interrupted = true,
+                Err(err) => return Err(err),
             }
+        }
+        session.drain_pages(&mut on_page);
+        self.check_page_limits(&pages)?;
+        if interrupted {
+            return Ok(ChapterRun::Interrupted(
+                session.into_checkpoint(consumed, pages),
+            ));
+        }
+        Ok(ChapterRun::Complete(pages))
+    }
+
+    /// Enforce the prep memory limits on pages held across a resumable run.
+    fn check_page_limits(&self, pages: &[RenderPage]) -> Result<(), RenderEngineError> {
+        let memory = self.opts.prep.memory;
+        if pages.len() > memory.max_pages_in_memory {
+            return Err(RenderEngineError::LimitExceeded {
+                kind: "max_pages_in_memory",
+                actual: pages.len(),
+                limit: memory.max_pages_in_memory,
+            });
+        }
+        let total_bytes: usize = pages.iter().map(RenderPage::approx_bytes).sum();
+        if total_bytes > memory.max_page_bytes_in_memory {
+            return Err(RenderEngineError::LimitExceeded {
+                kind: "max_page_bytes_in_memory",
+                actual: total_bytes,
+                limit: memory.max_page_bytes_in_memory,
+            });
+        }
+        Ok(())
+    }
+
+    fn prepare_chapter_with_cancel_and_config<R, C, F>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+        cancel: &C,
+        config: RenderConfig<'_>,
+        mut on_page: F,
+    ) -> Result<(), RenderEngineError>
+    where
+        R: std::io::Read + std::io::Seek,
+        C: CancelToken + ?Sized,
+        F: FnMut(RenderPage),
+    {
+        let embedded_fonts = config.embedded_fonts;
+        let _span = phase_span!("layout", chapter_index = chapter_index);
+        let started = self.timing_clock();
+        let decorations = self.page_decorations(book, chapter_index, &config);
+        let mut on_page = |mut page: RenderPage| {
+            self.decorate(&mut page, &decorations);
+            on_page(page);
+        };
+        if cancel.is_cancelled() {
+            self.emit_diagnostic(RenderDiagnostic::Cancelled);
+            return Err(RenderEngineError::Cancelled);
+        }
+        let mut session = self.begin(chapter_index, config);
+        if session.is_complete() {
             session.drain_pages(&mut on_page);
+            return Ok(());
+        }
+        let mut clock = PrepClock::start(self.timing_clock());
+        let mut prep = RenderPrep::new(self.opts.prep)
+            .with_serif_default()
+            .with_phase_timing(clock.enabled());
+        if embedded_fonts {
+            prep = prep.with_embedded_fonts_from_book(book)?;
+        }
+        let mut saw_cancelled = false;
+        let mut push_error = None;
+        prep.prepare_chapter_with_resources(book, chapter_index, |item, book| {
+            clock.time(|| {
+                if saw_cancelled || cancel.is_cancelled() {
+                    saw_cancelled = true;
+                    return;
+                }
+                let item = self.load_object_payload(book, item, &mut session);
+                if let Err(err) = session.push(item) {
+                    saw_cancelled = true;
+                    push_error = Some(err);
+                    return;
+                }
+                session.drain_pages(&mut on_page);
+            })
         })?;
+        clock.report(self, chapter_index, &prep);
+        self.record_chapter_read(&prep);
+        match push_error {
+            Some(RenderEngineError::Cancelled) | None => {}
+            Some(err) => return Err(err),
+        }
         if saw_cancelled || cancel.is_cancelled() {
             self.emit_diagnostic(RenderDiagnostic::Cancelled);
             return Err(RenderEngineError::Cancelled);
         }
         session.finish()?;
         session.drain_pages(&mut on_page);
-        let elapsed = started.elapsed().as_millis().min(u32::MAX as u128) as u32;
-        self.emit_diagnostic(RenderDiagnostic::ReflowTimeMs(elapsed));
+        self.emit_reflow_time(started);
         Ok(())
     }
 
@@ -417,7 +2249,13 @@ impl RenderEngine {
         F: FnMut(RenderPage),
     {
         let embedded_fonts = config.embedded_fonts;
-        let started = Instant::now();
+        let _span = phase_span!("layout", chapter_index = chapter_index);
+        let started = self.timing_clock();
+        let decorations = self.page_decorations(book, chapter_index, &config);
+        let mut on_page = |mut page: RenderPage| {
+            self.decorate(&mut page, &decorations);
+            on_page(page);
+        };
         if cancel.is_cancelled() {
             self.emit_diagnostic(RenderDiagnostic::Cancelled);
             return Err(RenderEngineError::Cancelled);
@@ -427,33 +2265,360 @@ impl RenderEngine {
             session.drain_pages(&mut on_page);
             return Ok(());
         }
-        let mut prep = RenderPrep::new(self.opts.prep).with_serif_default();
+        let mut clock = PrepClock::start(self.timing_clock());
+        let mut prep = RenderPrep::new(self.opts.prep)
+            .with_serif_default()
+            .with_phase_timing(clock.enabled());
         if embedded_fonts {
             prep = prep.with_embedded_fonts_from_book(book)?;
         }
         let mut saw_cancelled = false;
-        prep.prepare_chapter_bytes_with(book, chapter_index, html, |item| {
-            if saw_cancelled || cancel.is_cancelled() {
-                saw_cancelled = true;
-                return;
-            }
-            if session.push(item).is_err() {
-                saw_cancelled = true;
-                return;
-            }
-            session.drain_pages(&mut on_page);
+        let mut push_error = None;
+        prep.prepare_chapter_bytes_with_resources(book, chapter_index, html, |item, book| {
+            clock.time(|| {
+                if saw_cancelled || cancel.is_cancelled() {
+                    saw_cancelled = true;
+                    return;
+                }
+                let item = self.load_object_payload(book, item, &mut session);
+                if let Err(err) = session.push(item) {
+                    saw_cancelled = true;
+                    push_error = Some(err);
+                    return;
+                }
+                session.drain_pages(&mut on_page);
+            })
         })?;
+        clock.report(self, chapter_index, &prep);
+        self.record_chapter_read(&prep);
+        match push_error {
+            Some(RenderEngineError::Cancelled) | None => {}
+            Some(err) => return Err(err),
+        }
         if saw_cancelled || cancel.is_cancelled() {
             self.emit_diagnostic(RenderDiagnostic::Cancelled);
             return Err(RenderEngineError::Cancelled);
         }
         session.finish()?;
         session.drain_pages(&mut on_page);
-        let elapsed = started.elapsed().as_millis().min(u32::MAX as u128) as u32;
-        self.emit_diagnostic(RenderDiagnostic::ReflowTimeMs(elapsed));
+        self.emit_reflow_time(started);
         Ok(())
     }
 
+    /// Read the payload an image item needs before layout: SVG markup for
+    /// rasterization, or decoded pixels queued on `session` for raster
+    /// images.
+    ///
+    /// Draft intents never rasterize SVG, so no resource is read. Reads are
+    /// capped at `svg_max_bytes`; failures leave the item untouched so
+    /// layout falls back to placeholder output.
+    fn load_object_payload<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        item: StyledEventOrRun,
+        session: &mut LayoutSession<'_>,
+    ) -> StyledEventOrRun {
+        let objects = self.opts.layout.object_layout;
+        let StyledEventOrRun::Image(mut image) = item else {
+            return item;
+        };
+        if !image.is_svg() {
+            match self.decode_raster(book, &mut image, session.cfg.deferred_images) {
+                Some(RasterPayload::Decoded(bitmap)) => session
+                    .decoded_images
+                    .push_back((image.src.clone(), bitmap)),
+                Some(RasterPayload::Deferred) => {
+                    session.pending_images.push_back(image.src.clone())
+                }
+                None => {}
+            }
+            return StyledEventOrRun::Image(image);
+        }
+        if objects.svg_mode != SvgMode::Rasterize
+            || self.opts.layout.render_intent.draft
+            || image.inline_svg.is_some()
+            || image.src.is_empty()
+        {
+            return StyledEventOrRun::Image(image);
+        }
+        if let Some(bytes) = self.read_object(book, &image.src, objects.svg_max_bytes) {
+            image.inline_svg = String::from_utf8(bytes).ok();
+        }
+        StyledEventOrRun::Image(image)
+    }
+
+    /// Resolve an image item for `ChapterIr`: external SVG markup inlined,
+    /// raster intrinsic size filled in from the header.
+    fn capture_object<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        item: StyledEventOrRun,
+    ) -> StyledEventOrRun {
+        let objects = self.opts.layout.object_layout;
+        let StyledEventOrRun::Image(mut image) = item else {
+            return item;
+        };
+        if image.src.is_empty() || image.inline_svg.is_some() {
+            return StyledEventOrRun::Image(image);
+        }
+        let svg = image.is_svg();
+        if !svg && image.width_px.is_some() && image.height_px.is_some() {
+            return StyledEventOrRun::Image(image);
+        }
+        let cap = if svg {
+            objects.svg_max_bytes
+        } else {
+            objects.image_max_bytes
+        };
+        let Some(bytes) = self.read_object(book, &image.src, cap) else {
+            return StyledEventOrRun::Image(image);
+        };
+        if svg {
+            image.inline_svg = String::from_utf8(bytes).ok();
+        } else if let Some((width, height)) = self
+            .image_decoders
+            .find(&bytes)
+            .and_then(|decoder| decoder.dimensions(&bytes))
+        {
+            image.width_px = Some(width as f32);
+            image.height_px = Some(height as f32);
+        }
+        StyledEventOrRun::Image(image)
+    }
+
+    /// Decode a raster image within the object budgets.
+    ///
+    /// Fills in a missing intrinsic size from the header so layout keeps
+    /// the aspect ratio. The result is shrunk to the content box, the
+    /// largest size layout can give it, and kept in the image cache so
+    /// laying out the page again skips reading and decoding.
+    fn decode_raster<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        image: &mut StyledImage,
+        defer: bool,
+    ) -> Option<RasterPayload> {
+        let objects = self.opts.layout.object_layout;
+        if self.opts.layout.render_intent.draft
+            || image.src.is_empty()
+            || self.image_decoders.is_empty()
+        {
+            return None;
+        }
+        let capacity = objects.image_cache_bytes;
+        let key = (capacity > 0).then(|| ImageCacheKey {
+            book: BookFingerprint::from_book(book),
+            src: image.src.clone(),
+            target: (
+                self.layout_cfg.content_width(),
+                self.layout_cfg.content_height(),
+            ),
+            filter: objects.image_scale_filter,
+            intent: self.opts.layout.render_intent,
+        });
+        if let Some(key) = &key {
+            let cached = self
+                .image_cache
+                .lock()
+                .ok()
+                .and_then(|mut cache| cache.get(key));
+            self.emit_diagnostic(RenderDiagnostic::ImageCacheLookup {
+                src: image.src.clone(),
+                hit: cached.is_some(),
+            });
+            if let Some(((width, height), bitmap)) = cached {
+                if image.width_px.is_none() || image.height_px.is_none() {
+                    image.width_px = Some(width as f32);
+                    image.height_px = Some(height as f32);
+                }
+                return Some(RasterPayload::Decoded(bitmap));
+            }
+        }
+        let bytes = self.read_object(book, &image.src, objects.image_max_bytes)?;
+        let decoder = self.image_decoders.find(&bytes)?;
+        let (width, height) = decoder.dimensions(&bytes)?;
+        if image.width_px.is_none() || image.height_px.is_none() {
+            image.width_px = Some(width as f32);
+            image.height_px = Some(height as f32);
+        }
+        if !decoder.decodes_in_strips()
+            && (width as usize).saturating_mul(height as usize) > objects.image_max_decoded_pixels
+        {
+            return None;
+        }
+        if defer {
+            return Some(RasterPayload::Deferred);
+        }
+        let (max_width, max_height) = (
+            self.layout_cfg.content_width(),
+            self.layout_cfg.content_height(),
+        );
+        let _gauge = decode_gauge(decoder, (width, height), max_width, max_height);
+        let bitmap = decoder.decode_within(
+            &bytes,
+            max_width,
+            max_height,
+            ImageDecodeOptions {
+                filter: objects.image_scale_filter,
+                grayscale: self.opts.layout.render_intent.grayscale_mode,
+            },
+        )?;
+        self.record(|telemetry| telemetry.images_decoded += 1);
+        if let (Some(key), Ok(mut cache)) = (key, self.image_cache.lock()) {
+            cache.insert(key, (width, height), bitmap.clone(), capacity);
+        }
+        Some(RasterPayload::Decoded(bitmap))
+    }
+
+    /// Decode the image a `PageAnnotation::PendingImage` refers to, sized
+    /// for this engine's content box.
+    ///
+    /// Goes through the decoded-image cache, so later layouts of the same
+    /// book attach the image without deferring it again.
+    pub fn decode_pending_image<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        src: &str,
+    ) -> Option<GrayBitmap> {
+        let mut image = StyledImage {
+            src: src.to_string(),
+            alt: String::with_capacity(0),
+            width_px: None,
+            height_px: None,
+            inline_svg: None,
+            svg_oversize: false,
+            caption: None,
+        };
+        match self.decode_raster(book, &mut image, false)? {
+            RasterPayload::Decoded(bitmap) => Some(bitmap),
+            RasterPayload::Deferred => None,
+        }
+    }
+
+    /// Patch a decoded bitmap into the pending images of `page` showing
+    /// `src`, returning the damaged rects to refresh on screen.
+    ///
+    /// `bitmap` is a `decode_pending_image` result (or any decode of the
+    /// resource); it is resized to each image's layout size and mapped
+    /// through the render intent. Empty when nothing on the page was
+    /// waiting for `src`. Write patched pages back to a `RenderCacheStore`
+    /// to keep cached copies filled.
+    pub fn fill_pending_image(
+        &self,
+        page: &mut RenderPage,
+        src: &str,
+        bitmap: &GrayBitmap,
+    ) -> Vec<PageRect> {
+        fill_pending_images(page, src, bitmap, &self.opts.layout.render_intent)
+    }
+
+    /// Decode and patch every pending image on `page`, returning the merged
+    /// damage region. Images that fail to decode stay pending.
+    pub fn resolve_pending_images<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        page: &mut RenderPage,
+    ) -> Vec<PageRect> {
+        let mut sources: Vec<String> = Vec::with_capacity(0);
+        for annotation in &page.annotations {
+            if let PageAnnotation::PendingImage { src, .. } = annotation {
+                if !sources.contains(src) {
+                    sources.push(src.clone());
+                }
+            }
+        }
+        let mut damage = Vec::with_capacity(0);
+        for src in sources {
+            if let Some(bitmap) = self.decode_pending_image(book, &src) {
+                damage.extend(self.fill_pending_image(page, &src, &bitmap));
+            }
+        }
+        merge_rects(damage)
+    }
+
+    /// Decode an image resource at its full pixel size, for re-rendering
+    /// placed images at a larger scale.
+    pub(crate) fn decode_resource<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        src: &str,
+    ) -> Option<GrayBitmap> {
+        let objects = self.opts.layout.object_layout;
+        if src.is_empty() || self.image_decoders.is_empty() {
+            return None;
+        }
+        let bytes = self.read_object(book, src, objects.image_max_bytes)?;
+        let decoder = self.image_decoders.find(&bytes)?;
+        let (width, height) = decoder.dimensions(&bytes)?;
+        if (width as usize).saturating_mul(height as usize) > objects.image_max_decoded_pixels {
+            return None;
+        }
+        let options = ImageDecodeOptions {
+            grayscale: self.opts.layout.render_intent.grayscale_mode,
+            ..ImageDecodeOptions::default()
+        };
+        let _gauge = decode_gauge(decoder, (width, height), i32::MAX, i32::MAX);
+        let bitmap = decoder.decode_within(&bytes, i32::MAX, i32::MAX, options)?;
+        self.record(|telemetry| telemetry.images_decoded += 1);
+        Some(bitmap)
+    }
+
+    /// Cover image of `book` scaled to fit within `width`x`height` and
+    /// mapped through `intent`, ready to blit into a library grid cell.
+    ///
+    /// Covers are found with `EpubBook::cover_image_href`, decoded within
+    /// the object budgets, shrunk while decoding (small covers are grown),
+    /// and keep their aspect ratio. `None` when the book has no decodable
+    /// cover.
+    pub fn cover_thumbnail<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        width: u32,
+        height: u32,
+        intent: &RenderIntent,
+    ) -> Option<GrayBitmap> {
+        let objects = self.opts.layout.object_layout;
+        if width == 0 || height == 0 || self.image_decoders.is_empty() {
+            return None;
+        }
+        let href = book.cover_image_href()?;
+        let bytes = self.read_object(book, &href, objects.image_max_bytes)?;
+        let decoder = self.image_decoders.find(&bytes)?;
+        let (source_width, source_height) = decoder.dimensions(&bytes)?;
+        if !decoder.decodes_in_strips()
+            && (source_width as usize).saturating_mul(source_height as usize)
+                > objects.image_max_decoded_pixels
+        {
+            return None;
+        }
+        let options = ImageDecodeOptions {
+            filter: objects.image_scale_filter,
+            grayscale: intent.grayscale_mode,
+        };
+        let (max_width, max_height) = (
+            width.min(i32::MAX as u32) as i32,
+            height.min(i32::MAX as u32) as i32,
+        );
+        let _gauge = decode_gauge(
+            decoder,
+            (source_width, source_height),
+            max_width,
+            max_height,
+        );
+        let mut bitmap = decoder.decode_within(&bytes, max_width, max_height, options)?;
+        self.record(|telemetry| telemetry.images_decoded += 1);
+        let scale = (width as f32 / source_width as f32).min(height as f32 / source_height as f32);
+        let fitted = (
+            ((source_width as f32 * scale).round() as u32).clamp(1, width),
+            ((source_height as f32 * scale).round() as u32).clamp(1, height),
+        );
+        if (bitmap.width, bitmap.height) != fitted {
+            bitmap = resize(&bitmap, fitted.0, fitted.1);
+        }
+        intent.dither_bitmap(&mut bitmap);
+        Some(bitmap)
+    }
+
     /// Prepare and layout a chapter, returning pages within `[start, end)`.
     ///
     /// Range indices are zero-based over the emitted chapter page sequence.
@@ -499,6 +2664,115 @@ impl RenderEngine {
         })
     }
 
+    /// Lay out a chapter backwards from `before`, or from its end.
+    ///
+    /// The chapter is styled in full, but only pages the iterator reaches
+    /// are laid out, so a reader opened at a deep locator can show the
+    /// previous page without paginating the chapter prefix. Pages are
+    /// reverse-filled: each is as full as fits and ends exactly where the
+    /// page after it starts, so boundaries are stable for a given anchor but
+    /// differ from forward pagination. `page_number` counts back from 1 at
+    /// the anchor.
+    pub fn prepare_chapter_reverse<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+        before: Option<TextPosition>,
+    ) -> Result<ReversePageIter<'_>, RenderEngineError> {
+        let decorations = self.page_decorations(book, chapter_index, &RenderConfig::default());
+        let (items, decoded) = self.styled_items(book, chapter_index, true)?;
+        Ok(ReversePageIter {
+            engine: self,
+            chapter_index,
+            fill: ReverseFill::new(self.layout_cfg, items),
+            anchor: before,
+            decorations,
+            decoded,
+            emitted: 0,
+        })
+    }
+
+    /// Rebuild one page of a chapter, e.g. after an annotation was added or
+    /// its cache entry turned out corrupt.
+    ///
+    /// When this engine laid out the chapter recently, the page's start and
+    /// end positions are remembered and only that page is laid out. Otherwise
+    /// layout stops right after the page instead of finishing the chapter.
+    /// Returns `None` when the chapter has fewer pages.
+    pub fn prepare_single_page<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+        page_index: usize,
+    ) -> Result<Option<RenderPage>, RenderEngineError> {
+        self.prepare_single_page_with_config(
+            book,
+            chapter_index,
+            page_index,
+            RenderConfig::default(),
+        )
+    }
+
+    /// `prepare_single_page` with explicit run config.
+    ///
+    /// Annotations, bookmarks, and book page counts from `config` are applied.
+    /// The cache is consulted only when the page bounds are not known.
+    pub fn prepare_single_page_with_config<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+        page_index: usize,
+        mut config: RenderConfig<'_>,
+    ) -> Result<Option<RenderPage>, RenderEngineError> {
+        let profile = self.pagination_profile_id();
+        let bounds = self
+            .page_start_memo
+            .lock()
+            .ok()
+            .and_then(|memo| memo.bounds(profile, chapter_index, page_index));
+        let Some((start, end)) = bounds else {
+            config.page_range = Some(page_index..page_index + 1);
+            config.stop_after_page = Some(page_index);
+            let pages = match self.prepare_chapter_resumable(book, chapter_index, config)? {
+                ChapterRun::Complete(pages) => pages,
+                ChapterRun::Interrupted(checkpoint) => checkpoint.into_pages(),
+            };
+            return Ok(pages.into_iter().next());
+        };
+        let decorations = self.page_decorations(book, chapter_index, &config);
+        let (items, decoded) = self.styled_items(book, chapter_index, config.embedded_fonts)?;
+        let fill = ReverseFill::new(self.layout_cfg, items);
+        let Some(mut page) = fill.layout_range(start, end).into_iter().next() else {
+            return Ok(None);
+        };
+        page.page_number = page_index + 1;
+        self.annotate_page_for_chapter(&mut page, chapter_index, config.book_page_counts);
+        attach_page_images(&mut page, &decoded, &self.layout_cfg.render_intent);
+        self.decorate(&mut page, &decorations);
+        Ok(Some(page))
+    }
+
+    /// Every styled item of a chapter with object payloads loaded, plus
+    /// the decoded raster images they reference.
+    fn styled_items<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+        embedded_fonts: bool,
+    ) -> Result<(Vec<StyledEventOrRun>, DecodedImages), RenderEngineError> {
+        let mut scratch = self.begin(chapter_index, RenderConfig::default());
+        let mut items = Vec::with_capacity(64);
+        let mut prep = RenderPrep::new(self.opts.prep).with_serif_default();
+        if embedded_fonts {
+            prep = prep.with_embedded_fonts_from_book(book)?;
+        }
+        prep.prepare_chapter_with_resources(book, chapter_index, |item, book| {
+            items.push(self.load_object_payload(book, item, &mut scratch));
+        })?;
+        self.record_chapter_read(&prep);
+        Ok((items, core::mem::take(&mut scratch.decoded_images)))
+    }
+
     /// Prepare and layout a chapter as a streaming iterator.
     ///
     /// Unlike `prepare_chapter_iter`, this method streams pages incrementally from a
@@ -521,7 +2795,7 @@ impl RenderEngine {
                 if receiver_closed {
                     return;
                 }
-                if tx.send(StreamMessage::Page(page)).is_err() {
+                if tx.send(StreamMessage::Page(Box::new(page))).is_err() {
                     receiver_closed = true;
                 }
             });
@@ -546,6 +2820,9 @@ impl RenderEngine {
     }
 
     /// Prepare with an overlay composer that maps page metrics into overlay items.
+    ///
+    /// `viewport` is the physical panel size; composers receive the rotated
+    /// (reader-facing) viewport so slots track the configured rotation.
     pub fn prepare_chapter_with_overlay_composer<R, O, F>(
         &self,
         book: &mut EpubBook<R>,
@@ -559,112 +2836,1218 @@ impl RenderEngine {
         O: crate::render_ir::OverlayComposer,
         F: FnMut(RenderPage),
     {
+        let viewport = self.opts.rotation.logical_size(viewport);
         self.prepare_chapter_with(book, chapter_index, |mut page| {
             let overlays = composer.compose(&page.metrics, viewport);
             for item in overlays {
                 page.overlay_items.push(item.clone());
-                if let OverlayContent::Command(cmd) = item.content {
-                    page.push_overlay_command(cmd);
+                let cmd = match item.content {
+                    OverlayContent::Text(_) => None,
+                    OverlayContent::Command(cmd) => Some(cmd),
+                    OverlayContent::Image(image) => Some(image.to_command(&item.slot, viewport)),
+                };
+                if let Some(cmd) = cmd {
+                    // Overlays are not diffed; treat them as always damaged.
+                    page.damage = match (page.damage.take(), cmd.bounds()) {
+                        (Some(mut damage), Some(bounds)) => {
+                            damage.push(bounds);
+                            Some(damage)
+                        }
+                        _ => None,
+                    };
+                    page.push_overlay_command(DrawCommand::Layered(LayeredCommand {
+                        z: item.z,
+                        command: Box::new(cmd),
+                    }));
                 }
             }
-            page.sync_commands();
-            on_page(page);
+            page.sync_commands();
+            on_page(page);
+        })
+    }
+
+    /// Prepare with a chrome provider supplying dynamic header/footer fields.
+    pub fn prepare_chapter_with_chrome_provider<R, P, F>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+        provider: &P,
+        mut on_page: F,
+    ) -> Result<(), RenderEngineError>
+    where
+        R: std::io::Read + std::io::Seek,
+        P: PageChromeProvider,
+        F: FnMut(RenderPage),
+    {
+        let provider = self.chrome_provider(provider);
+        self.prepare_chapter_with(book, chapter_index, |mut page| {
+            page.recompose_chrome(&self.layout_cfg.page_chrome, provider);
+            on_page(page);
+        })
+    }
+
+    /// Re-compose only the chrome layer of an already-rendered page.
+    ///
+    /// Lets dynamic fields (clock, battery) tick without re-running layout.
+    /// When chrome changed, `damage` is replaced with the affected chrome
+    /// bands and `true` is returned; otherwise the page is left untouched.
+    pub fn recompose_chrome(
+        &self,
+        page: &mut RenderPage,
+        provider: &dyn PageChromeProvider,
+    ) -> bool {
+        let prev = page.chrome_commands.clone();
+        let provider = self.chrome_provider(provider);
+        if !page.recompose_chrome(&self.layout_cfg.page_chrome, provider) {
+            return false;
+        }
+        page.damage = page_damage(&prev, &page.chrome_commands, &self.layout_cfg);
+        true
+    }
+
+    /// `provider`, or the static chrome in deterministic mode.
+    fn chrome_provider<'p>(
+        &self,
+        provider: &'p dyn PageChromeProvider,
+    ) -> &'p dyn PageChromeProvider {
+        if self.opts.deterministic {
+            &StaticPageChrome
+        } else {
+            provider
+        }
+    }
+}
+
+/// Outcome of `LayoutSession::prefetch_idle`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrefetchStatus {
+    /// The cursor is not near a chapter boundary, or the neighbour cannot
+    /// be prefetched.
+    Idle,
+    /// The cancel token fired; call again on the next idle tick.
+    Interrupted,
+    /// The adjacent chapter is laid out and waiting for the page turn.
+    Ready,
+}
+
+/// Outcome of `RenderEngine::prepare_chapter_resumable`.
+#[derive(Debug)]
+pub enum ChapterRun {
+    /// Layout finished; holds every emitted page of the chapter.
+    Complete(Vec<RenderPage>),
+    /// The cancel token fired or the page stop was reached; resume with
+    /// `RenderEngine::resume_chapter`.
+    Interrupted(LayoutCheckpoint),
+}
+
+/// Layout state of a chapter run interrupted by its `CancelToken`.
+///
+/// Holds the pages emitted so far and the layout cursor, so a later call
+/// continues instead of starting over.
+pub struct LayoutCheckpoint {
+    chapter_index: usize,
+    profile: PaginationProfileId,
+    items_consumed: usize,
+    pages: Vec<RenderPage>,
+    inner: Option<Box<CoreLayoutSession>>,
+    page_index: usize,
+    page_starts: Vec<Option<TextPosition>>,
+    prev_commands: Option<Vec<DrawCommand>>,
+    rendered_pages: Vec<RenderPage>,
+    rendered_bytes: usize,
+}
+
+impl LayoutCheckpoint {
+    /// Chapter being laid out.
+    pub fn chapter_index(&self) -> usize {
+        self.chapter_index
+    }
+
+    /// Pages emitted before the interruption.
+    pub fn pages(&self) -> &[RenderPage] {
+        &self.pages
+    }
+
+    /// Styled items already laid out.
+    pub fn items_consumed(&self) -> usize {
+        self.items_consumed
+    }
+
+    /// Drop the layout cursor and keep the emitted pages.
+    pub fn into_pages(self) -> Vec<RenderPage> {
+        self.pages
+    }
+}
+
+impl fmt::Debug for LayoutCheckpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LayoutCheckpoint")
+            .field("chapter_index", &self.chapter_index)
+            .field("items_consumed", &self.items_consumed)
+            .field("pages", &self.pages.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Splits chapter prep wall time into tokenize and style phases by
+/// excluding time spent downstream in layout.
+struct PrepClock {
+    started: Option<Instant>,
+    downstream: Duration,
+}
+
+impl PrepClock {
+    fn start(started: Option<Instant>) -> Self {
+        Self {
+            started,
+            downstream: Duration::ZERO,
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// Run downstream work, excluding it from the prep phases.
+    fn time<T>(&mut self, work: impl FnOnce() -> T) -> T {
+        if self.started.is_none() {
+            return work();
+        }
+        let started = Instant::now();
+        let out = work();
+        self.downstream += started.elapsed();
+        out
+    }
+
+    fn report(&self, engine: &RenderEngine, chapter_index: usize, prep: &RenderPrep) {
+        let Some(started) = self.started else {
+            return;
+        };
+        let tokenize = prep.tokenize_time();
+        let style = started
+            .elapsed()
+            .saturating_sub(self.downstream)
+            .saturating_sub(tokenize);
+        for (phase, time) in [
+            (RenderPhase::Tokenize, tokenize),
+            (RenderPhase::Style, style),
+        ] {
+            engine.emit_diagnostic(RenderDiagnostic::PhaseTime {
+                chapter_index,
+                phase,
+                micros: time.as_micros().min(u64::MAX as u128) as u64,
+            });
+        }
+    }
+}
+
+/// Per-chapter data applied to every page as it leaves layout.
+struct PageDecorations {
+    chapters: Vec<ChapterRef>,
+    chapter_title: Option<String>,
+    annotations: Vec<Annotation>,
+    bookmarks: Vec<Bookmark>,
+}
+
+/// Reading position of the `LayoutSession` page cursor.
+///
+/// Holds the pages of the chapter under the cursor plus one neighbouring
+/// chapter: the one just left, or the next one once the last page is
+/// reached.
+#[derive(Default)]
+struct PageCursor {
+    chapter: Option<LoadedChapter>,
+    neighbor: Option<LoadedChapter>,
+    page_index: usize,
+    /// Partially laid out neighbour from an interrupted idle prefetch.
+    prefetching: Option<LayoutCheckpoint>,
+    /// Neighbour whose idle prefetch failed; left for the page turn.
+    prefetch_failed: Option<usize>,
+}
+
+/// Pages from a chapter boundary at which idle prefetch starts.
+const IDLE_PREFETCH_PAGES: usize = 2;
+
+struct LoadedChapter {
+    index: usize,
+    pages: Vec<RenderPage>,
+}
+
+/// Spine index a locator points at, by href first.
+pub(crate) fn locator_chapter<R: std::io::Read + std::io::Seek>(
+    book: &EpubBook<R>,
+    locator: &ContentLocator,
+) -> Option<usize> {
+    let by_href = locator.chapter_href.as_deref().and_then(|href| {
+        book.chapters()
+            .find(|chapter| chapter.href == href)
+            .map(|chapter| chapter.index)
+    });
+    match by_href {
+        Some(index) => Some(index),
+        None if locator.chapter_index < book.chapter_count() => Some(locator.chapter_index),
+        None => None,
+    }
+}
+
+/// Incremental wrapper session returned by `RenderEngine::begin`.
+pub struct LayoutSession<'a> {
+    engine: Cow<'a, RenderEngine>,
+    chapter_index: usize,
+    profile: PaginationProfileId,
+    cfg: RenderConfig<'a>,
+    inner: Option<CoreLayoutSession>,
+    pending_pages: VecDeque<RenderPage>,
+    pending_bytes: usize,
+    rendered_pages: Vec<RenderPage>,
+    rendered_bytes: usize,
+    /// `buffered_bytes` as last reported to the memory observer.
+    page_gauge: MemoryGauge,
+    prev_commands: Option<Vec<DrawCommand>>,
+    page_index: usize,
+    /// Start position of every laid-out page, for `relayout_with`.
+    page_starts: Vec<Option<TextPosition>>,
+    /// Decoded raster images awaiting their page, in layout order.
+    decoded_images: DecodedImages,
+    /// Deferred raster images awaiting their page's pending annotation.
+    pending_images: VecDeque<String>,
+    cursor: PageCursor,
+    /// Styled items pushed, for `YieldHook` intervals.
+    items_pushed: usize,
+    completed: bool,
+    diagnostics: Option<SessionDiagnostics>,
+}
+
+/// Layout observations reported through the diagnostic sink at `finish`.
+#[derive(Default)]
+struct SessionDiagnostics {
+    line_break: Duration,
+    paginate: Duration,
+    peak_bytes: usize,
+    /// `(requested, resolved)` family pairs already reported.
+    fallbacks: Vec<(String, String)>,
+}
+
+impl SessionDiagnostics {
+    /// Report font fallbacks and dropped content for an item about to be
+    /// laid out on page `page_index`.
+    fn observe(
+        &mut self,
+        engine: &RenderEngine,
+        chapter_index: usize,
+        page_index: usize,
+        item: &StyledEventOrRun,
+    ) {
+        match item {
+            StyledEventOrRun::Run(run) => {
+                if !engine.wants_diagnostic(DiagnosticCategory::Warning) {
+                    return;
+                }
+                let Some(requested) = run.style.family_stack.first() else {
+                    return;
+                };
+                let resolved = &run.resolved_family;
+                if requested.eq_ignore_ascii_case(resolved)
+                    || self
+                        .fallbacks
+                        .iter()
+                        .any(|(req, res)| req == requested && res == resolved)
+                {
+                    return;
+                }
+                self.fallbacks.push((requested.clone(), resolved.clone()));
+                engine.emit_diagnostic(RenderDiagnostic::FontFallback {
+                    chapter_index,
+                    page_index,
+                    requested: requested.clone(),
+                    resolved: resolved.clone(),
+                });
+            }
+            StyledEventOrRun::Image(image)
+                if image.is_svg() && engine.wants_diagnostic(DiagnosticCategory::ContentLoss) =>
+            {
+                let objects = engine.opts.layout.object_layout;
+                let kind = match (objects.svg_mode, image.inline_svg.as_deref()) {
+                    (SvgMode::Ignore, _) => DroppedContentKind::IgnoredSvg,
+                    (_, Some(markup)) if markup.len() > objects.svg_max_bytes => {
+                        DroppedContentKind::OversizedSvg
+                    }
+                    (_, None) if image.svg_oversize => DroppedContentKind::OversizedSvg,
+                    (SvgMode::Rasterize, None)
+                        if !engine.opts.layout.render_intent.draft && !image.src.is_empty() =>
+                    {
+                        DroppedContentKind::MissingSvgPayload
+                    }
+                    _ => return,
+                };
+                engine.emit_diagnostic(RenderDiagnostic::DroppedContent {
+                    chapter_index,
+                    page_index,
+                    kind,
+                    src: image.src.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn report(&self, engine: &RenderEngine, chapter_index: usize) {
+        for (phase, time) in [
+            (RenderPhase::LineBreak, self.line_break),
+            (RenderPhase::Paginate, self.paginate),
+        ] {
+            engine.emit_diagnostic(RenderDiagnostic::PhaseTime {
+                chapter_index,
+                phase,
+                micros: time.as_micros().min(u64::MAX as u128) as u64,
+            });
+        }
+        engine.emit_diagnostic(RenderDiagnostic::PeakScratchBytes {
+            chapter_index,
+            bytes: self.peak_bytes,
+        });
+    }
+}
+
+impl<'a> LayoutSession<'a> {
+    /// Start a session on `engine`, serving cached pages when available.
+    fn open(engine: Cow<'a, RenderEngine>, chapter_index: usize, config: RenderConfig<'a>) -> Self {
+        let profile = engine.pagination_profile_id();
+        let mut pending = VecDeque::new();
+        let mut page_starts = Vec::with_capacity(0);
+        let mut cached_hit = false;
+        if let Some(cache) = config.cache {
+            let cached = if engine.opts.deterministic {
+                None
+            } else {
+                cache.load_chapter_pages(profile, chapter_index)
+            };
+            let hit = cached.is_some();
+            engine.record(|telemetry| {
+                if hit {
+                    telemetry.cache_hits += 1;
+                } else {
+                    telemetry.cache_misses += 1;
+                }
+            });
+            engine.emit_diagnostic(RenderDiagnostic::CacheLookup { chapter_index, hit });
+            if let Some(pages) = cached {
+                cached_hit = true;
+                let bytes = pages.iter().map(RenderPage::approx_bytes).sum();
+                engine.touch_cached_chapter(cache, profile, chapter_index, bytes);
+                let range = normalize_page_range(config.page_range.clone());
+                for (idx, mut page) in pages.into_iter().enumerate() {
+                    engine.annotate_page_for_chapter(
+                        &mut page,
+                        chapter_index,
+                        config.book_page_counts,
+                    );
+                    page_starts.push(page.metrics.start_position);
+                    if page_in_range(idx, &range) {
+                        pending.push_back(page);
+                    }
+                }
+                if let Ok(mut memo) = engine.page_start_memo.lock() {
+                    memo.record(profile, chapter_index, &page_starts);
+                }
+            }
+        }
+        let inner = (!cached_hit).then(|| engine.layout.start_session());
+        if let (Some(hook), false) = (engine.opts.yield_hook, cached_hit) {
+            hook.call(chapter_index, RenderPhase::Tokenize, 0);
+        }
+        let diagnostics = [
+            DiagnosticCategory::Timing,
+            DiagnosticCategory::Warning,
+            DiagnosticCategory::ContentLoss,
+        ]
+        .into_iter()
+        .any(|category| engine.wants_diagnostic(category))
+        .then(SessionDiagnostics::default);
+        let pending_bytes = pending.iter().map(RenderPage::approx_bytes).sum();
+        LayoutSession {
+            engine,
+            chapter_index,
+            profile,
+            cfg: config,
+            inner,
+            pending_bytes,
+            pending_pages: pending,
+            rendered_pages: Vec::with_capacity(0),
+            rendered_bytes: 0,
+            page_gauge: MemoryGauge::with_bytes(MemorySubsystem::PageBuffers, pending_bytes),
+            prev_commands: None,
+            page_index: 0,
+            page_starts,
+            decoded_images: VecDeque::new(),
+            pending_images: VecDeque::new(),
+            cursor: PageCursor::default(),
+            items_pushed: 0,
+            completed: cached_hit,
+            diagnostics,
+        }
+    }
+
+    /// Push one styled item through layout and enqueue closed pages.
+    pub fn push(&mut self, item: StyledEventOrRun) -> Result<(), RenderEngineError> {
+        if self.completed {
+            return Ok(());
+        }
+        if self.cfg.cancel.is_some_and(|cancel| cancel.is_cancelled()) {
+            self.engine.emit_diagnostic(RenderDiagnostic::Cancelled);
+            return Err(RenderEngineError::Cancelled);
+        }
+        if let Some(diagnostics) = self.diagnostics.as_mut() {
+            diagnostics.observe(&self.engine, self.chapter_index, self.page_index, &item);
+        }
+        self.items_pushed += 1;
+        if let Some(hook) = self.engine.opts.yield_hook {
+            if self.items_pushed % hook.every_items.max(1) == 0 {
+                hook.call(
+                    self.chapter_index,
+                    RenderPhase::LineBreak,
+                    self.items_pushed,
+                );
+            }
+        }
+        self.step(|inner, on_page| inner.push_item_with_pages(item, on_page));
+        self.check_pending_bytes()
+    }
+
+    /// Run `step` on the core session, routing closed pages through chapter
+    /// annotation, damage, cache capture, and the page range.
+    fn step<S>(&mut self, step: S)
+    where
+        S: FnOnce(&mut CoreLayoutSession, &mut &mut dyn FnMut(RenderPage)),
+    {
+        let Some(inner) = self.inner.as_mut() else {
+            return;
+        };
+        let chapter = self.chapter_index;
+        let range = normalize_page_range(self.cfg.page_range.clone());
+        let rendered = &mut self.rendered_pages;
+        let rendered_bytes = &mut self.rendered_bytes;
+        let pending = &mut self.pending_pages;
+        let pending_bytes = &mut self.pending_bytes;
+        let page_index = &mut self.page_index;
+        let page_starts = &mut self.page_starts;
+        let decoded_images = &mut self.decoded_images;
+        let pending_images = &mut self.pending_images;
+        let capture_for_cache = self.cfg.cache.is_some();
+        let byte_limit = self.engine.opts.prep.memory.max_page_bytes_in_memory;
+        let prev_commands = &mut self.prev_commands;
+        let book_page_counts = self.cfg.book_page_counts;
+        let items_pushed = self.items_pushed;
+        let engine: &RenderEngine = &self.engine;
+        let layout_cfg = &engine.layout_cfg;
+        let started = self
+            .diagnostics
+            .is_some()
+            .then(|| self.engine.timing_clock())
+            .flatten();
+        let mut paginate = Duration::ZERO;
+        let mut on_page = |mut page: RenderPage| {
+            let page_started = started.map(|_| Instant::now());
+            engine.annotate_page_for_chapter(&mut page, chapter, book_page_counts);
+            attach_decoded_images(&mut page, decoded_images, &layout_cfg.render_intent);
+            mark_pending_images(&mut page, pending_images);
+            attach_damage(&mut page, prev_commands, layout_cfg);
+            if capture_for_cache {
+                capture_rendered(rendered, rendered_bytes, &page, byte_limit);
+            }
+            page_starts.push(page.metrics.start_position);
+            if page_in_range(*page_index, &range) {
+                *pending_bytes = pending_bytes.saturating_add(page.approx_bytes());
+                pending.push_back(page);
+            }
+            *page_index += 1;
+            if let Some(page_started) = page_started {
+                paginate += page_started.elapsed();
+            }
+            if let Some(hook) = engine.opts.yield_hook {
+                hook.call(chapter, RenderPhase::Paginate, items_pushed);
+            }
+        };
+        step(inner, &mut (&mut on_page as &mut dyn FnMut(RenderPage)));
+        self.page_gauge.set(self.buffered_bytes());
+        if let (Some(diagnostics), Some(started)) = (self.diagnostics.as_mut(), started) {
+            diagnostics.paginate += paginate;
+            diagnostics.line_break += started.elapsed().saturating_sub(paginate);
+            let buffered = self.pending_bytes.saturating_add(self.rendered_bytes);
+            diagnostics.peak_bytes = diagnostics.peak_bytes.max(buffered);
+        }
+    }
+
+    /// Drain currently available pages in FIFO order.
+    pub fn drain_pages<F>(&mut self, mut on_page: F)
+    where
+        F: FnMut(RenderPage),
+    {
+        if !self.pending_pages.is_empty() {
+            let pages = self.pending_pages.len() as u64;
+            let buffered = self.buffered_bytes();
+            self.engine.record(|telemetry| {
+                telemetry.pages_emitted += pages;
+                telemetry.peak_page_bytes = telemetry.peak_page_bytes.max(buffered);
+            });
+        }
+        while let Some(page) = self.pending_pages.pop_front() {
+            on_page(page);
+        }
+        self.pending_bytes = 0;
+        self.page_gauge.set(self.rendered_bytes);
+    }
+
+    /// Finish layout and enqueue any remaining pages.
+    pub fn finish(&mut self) -> Result<(), RenderEngineError> {
+        if self.completed {
+            return Ok(());
+        }
+        if self.cfg.cancel.is_some_and(|cancel| cancel.is_cancelled()) {
+            self.engine.emit_diagnostic(RenderDiagnostic::Cancelled);
+            return Err(RenderEngineError::Cancelled);
+        }
+        self.step(|inner, on_page| inner.finish(on_page));
+        self.decoded_images.clear();
+        self.pending_images.clear();
+        if let Ok(mut memo) = self.engine.page_start_memo.lock() {
+            memo.record(self.profile, self.chapter_index, &self.page_starts);
+        }
+        if let Some(diagnostics) = self.diagnostics.as_ref() {
+            diagnostics.report(&self.engine, self.chapter_index);
+        }
+        self.engine
+            .record(|telemetry| telemetry.chapters_prepared += 1);
+        if let Some(cache) = self.cfg.cache {
+            // An emptied capture after a budget overrun is skipped here, as
+            // is a chapter too large for the store's capacity hint.
+            let admitted = cache
+                .capacity_hint()
+                .is_none_or(|capacity| capacity.admits(self.rendered_bytes));
+            if !self.rendered_pages.is_empty() && admitted {
+                cache.store_chapter_pages(self.profile, self.chapter_index, &self.rendered_pages);
+                self.engine.touch_cached_chapter(
+                    cache,
+                    self.profile,
+                    self.chapter_index,
+                    self.rendered_bytes,
+                );
+            }
+        }
+        self.completed = true;
+        self.check_pending_bytes()
+    }
+
+    /// Approximate bytes of pages currently held by this session.
+    ///
+    /// Covers pages waiting in `drain_pages` plus pages captured for the
+    /// render cache.
+    pub fn buffered_bytes(&self) -> usize {
+        self.pending_bytes.saturating_add(self.rendered_bytes)
+    }
+
+    /// Fail once undrained pages exceed `max_page_bytes_in_memory`.
+    fn check_pending_bytes(&self) -> Result<(), RenderEngineError> {
+        let limit = self.engine.opts.prep.memory.max_page_bytes_in_memory;
+        if self.pending_bytes > limit {
+            return Err(RenderEngineError::LimitExceeded {
+                kind: "max_page_bytes_in_memory",
+                actual: self.pending_bytes,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    fn is_complete(&self) -> bool {
+        self.completed
+    }
+
+    /// Page under the cursor, laying out the session's chapter on first use.
+    ///
+    /// The cursor starts on the first page of the chapter passed to
+    /// `RenderEngine::begin`. Cursor pages come fully decorated from
+    /// `prepare_chapter_with_config_collect`, through the run config's
+    /// cache, and are independent of `push`/`drain_pages`. Returns `None`
+    /// for an empty chapter.
+    pub fn current_page<R: std::io::Read + std::io::Seek>(
+        &mut self,
+        book: &mut EpubBook<R>,
+    ) -> Result<Option<&RenderPage>, RenderEngineError> {
+        if self.cursor.chapter.is_none() {
+            let chapter = self.load_chapter(book, self.chapter_index)?;
+            self.install(chapter, 0);
+            self.prefetch(book);
+        }
+        Ok(self.cursor_page())
+    }
+
+    /// Advance one page, crossing into the next non-empty chapter at the
+    /// end of the current one.
+    ///
+    /// Returns `None` and stays put on the last page of the book. The next
+    /// chapter is prefetched once the cursor reaches a chapter's last page.
+    pub fn next_page<R: std::io::Read + std::io::Seek>(
+        &mut self,
+        book: &mut EpubBook<R>,
+    ) -> Result<Option<&RenderPage>, RenderEngineError> {
+        self.current_page(book)?;
+        let (chapter_index, pages) = self.cursor_chapter();
+        if self.cursor.page_index + 1 < pages {
+            self.cursor.page_index += 1;
+        } else {
+            let mut next = chapter_index + 1;
+            loop {
+                if next >= book.chapter_count() {
+                    return Ok(None);
+                }
+                let chapter = self.load_chapter(book, next)?;
+                if !chapter.pages.is_empty() {
+                    self.install(chapter, 0);
+                    break;
+                }
+                next += 1;
+            }
+        }
+        self.prefetch(book);
+        Ok(self.cursor_page())
+    }
+
+    /// Step back one page, crossing onto the last page of the previous
+    /// non-empty chapter at a chapter start.
+    ///
+    /// Returns `None` and stays put on the first page of the book.
+    pub fn prev_page<R: std::io::Read + std::io::Seek>(
+        &mut self,
+        book: &mut EpubBook<R>,
+    ) -> Result<Option<&RenderPage>, RenderEngineError> {
+        self.current_page(book)?;
+        if self.cursor.page_index > 0 {
+            self.cursor.page_index -= 1;
+            return Ok(self.cursor_page());
+        }
+        let (mut prev, _) = self.cursor_chapter();
+        loop {
+            if prev == 0 {
+                return Ok(None);
+            }
+            prev -= 1;
+            let chapter = self.load_chapter(book, prev)?;
+            if let Some(last) = chapter.pages.len().checked_sub(1) {
+                self.install(chapter, last);
+                return Ok(self.cursor_page());
+            }
+        }
+    }
+
+    /// Move the cursor to the page holding `locator`.
+    ///
+    /// Resolves like `RenderEngine::page_for`. Returns `None`, leaving the
+    /// cursor unchanged, when the chapter no longer exists; an empty target
+    /// chapter also yields `None` but moves the cursor there.
+    pub fn goto<R: std::io::Read + std::io::Seek>(
+        &mut self,
+        book: &mut EpubBook<R>,
+        locator: &ContentLocator,
+    ) -> Result<Option<&RenderPage>, RenderEngineError> {
+        let Some(chapter_index) = locator_chapter(book, locator) else {
+            return Ok(None);
+        };
+        let chapter = match self.cursor.chapter.take() {
+            Some(current) if current.index == chapter_index => current,
+            current => {
+                self.cursor.chapter = current;
+                self.load_chapter(book, chapter_index)?
+            }
+        };
+        let page_index = chapter
+            .pages
+            .iter()
+            .rposition(|page| {
+                page.metrics
+                    .start_position
+                    .is_some_and(|start| start <= locator.position)
+            })
+            .unwrap_or(0);
+        self.install(chapter, page_index);
+        self.prefetch(book);
+        Ok(self.cursor_page())
+    }
+
+    /// Reading state to persist before powering off.
+    ///
+    /// Records the cursor page's locator (the session chapter's start
+    /// before the first `current_page`), the run config's book page
+    /// counts, and the chapters held laid out.
+    pub fn snapshot(&self) -> SessionSnapshot {
+        let locator = match self.cursor_page() {
+            Some(page) => self.engine.locate(page),
+            None => ContentLocator::new(self.chapter_index, TextPosition::default()),
+        };
+        let warm_chapters = [&self.cursor.chapter, &self.cursor.neighbor]
+            .into_iter()
+            .flatten()
+            .map(|chapter| (chapter.index, chapter.pages.len()))
+            .collect();
+        SessionSnapshot {
+            locator,
+            profile: self.profile,
+            book_page_counts: self
+                .cfg
+                .book_page_counts
+                .map(<[usize]>::to_vec)
+                .unwrap_or_default(),
+            warm_chapters,
+        }
+    }
+
+    /// Resume a session from `snapshot` with the cursor on its page.
+    ///
+    /// While `engine`'s pagination profile matches the snapshot, its book
+    /// page counts stand in for a whole-book pagination pass (unless
+    /// `config` supplies its own) and warm chapters next to the cursor are
+    /// reloaded from `config`'s cache, so with a warm cache nothing is laid
+    /// out again. Otherwise the locator is resolved under the new layout.
+    pub fn restore<R: std::io::Read + std::io::Seek>(
+        engine: &'a RenderEngine,
+        book: &mut EpubBook<R>,
+        snapshot: &'a SessionSnapshot,
+        mut config: RenderConfig<'a>,
+    ) -> Result<Self, RenderEngineError> {
+        let current = snapshot.profile == engine.pagination_profile_id();
+        if current
+            && config.book_page_counts.is_none()
+            && !snapshot.book_page_counts.is_empty()
+            && snapshot.book_page_counts.len() == book.chapter_count()
+        {
+            config = config.with_book_page_counts(&snapshot.book_page_counts);
+        }
+        let chapter_index =
+            locator_chapter(book, &snapshot.locator).unwrap_or(snapshot.locator.chapter_index);
+        let warm = current && config.cache.is_some();
+        let mut session = engine.begin(chapter_index, config);
+        session.goto(book, &snapshot.locator)?;
+        if warm && session.cursor.neighbor.is_none() {
+            let (cursor_chapter, _) = session.cursor_chapter();
+            let neighbor = snapshot
+                .warm_chapters
+                .iter()
+                .map(|&(index, _)| index)
+                .find(|index| index.abs_diff(cursor_chapter) == 1 && *index < book.chapter_count());
+            if let Some(index) = neighbor {
+                if let Ok(chapter) = session.load_chapter(book, index) {
+                    session.cursor.neighbor = Some(chapter);
+                }
+            }
+        }
+        Ok(session)
+    }
+
+    /// Lay out the chapter adjacent to the cursor during idle time.
+    ///
+    /// Within a couple of pages of the chapter end the next chapter is
+    /// targeted, near the start the previous one. Work stops when `cancel`
+    /// fires and resumes from a checkpoint on the next call, so firmware can
+    /// call this from every idle callback and cancel on input. A failing
+    /// chapter is reported once and not retried; the page turn surfaces it
+    /// again.
+    pub fn prefetch_idle<R: std::io::Read + std::io::Seek>(
+        &mut self,
+        book: &mut EpubBook<R>,
+        cancel: &dyn CancelToken,
+    ) -> Result<PrefetchStatus, RenderEngineError> {
+        let Some(target) = self.idle_prefetch_target(book) else {
+            return Ok(PrefetchStatus::Idle);
+        };
+        if self
+            .cursor
+            .neighbor
+            .as_ref()
+            .is_some_and(|neighbor| neighbor.index == target)
+        {
+            return Ok(PrefetchStatus::Ready);
+        }
+        if self.cursor.prefetch_failed == Some(target) {
+            return Ok(PrefetchStatus::Idle);
+        }
+        let mut config = self.cfg.clone().with_cancel(cancel);
+        config.page_range = None;
+        config.stop_after_page = None;
+        let run = match self.cursor.prefetching.take() {
+            Some(checkpoint) if checkpoint.chapter_index() == target => {
+                self.engine.resume_chapter(book, checkpoint, config)
+            }
+            _ => self.engine.prepare_chapter_resumable(book, target, config),
+        };
+        match run {
+            Ok(ChapterRun::Complete(pages)) => {
+                self.cursor.neighbor = Some(LoadedChapter {
+                    index: target,
+                    pages,
+                });
+                Ok(PrefetchStatus::Ready)
+            }
+            Ok(ChapterRun::Interrupted(checkpoint)) => {
+                self.cursor.prefetching = Some(checkpoint);
+                Ok(PrefetchStatus::Interrupted)
+            }
+            Err(err) => {
+                self.cursor.prefetch_failed = Some(target);
+                Err(err)
+            }
+        }
+    }
+
+    /// Chapter worth prefetching from the cursor position, if any.
+    fn idle_prefetch_target<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &EpubBook<R>,
+    ) -> Option<usize> {
+        let chapter = self.cursor.chapter.as_ref()?;
+        let page = self.cursor.page_index;
+        if page + IDLE_PREFETCH_PAGES >= chapter.pages.len() {
+            let next = chapter.index + 1;
+            if next < book.chapter_count() {
+                return Some(next);
+            }
+        }
+        if page < IDLE_PREFETCH_PAGES {
+            return chapter.index.checked_sub(1);
+        }
+        None
+    }
+
+    fn cursor_page(&self) -> Option<&RenderPage> {
+        self.cursor
+            .chapter
+            .as_ref()?
+            .pages
+            .get(self.cursor.page_index)
+    }
+
+    /// Index and page count of the chapter under the cursor.
+    fn cursor_chapter(&self) -> (usize, usize) {
+        self.cursor
+            .chapter
+            .as_ref()
+            .map_or((self.chapter_index, 0), |chapter| {
+                (chapter.index, chapter.pages.len())
+            })
+    }
+
+    /// Pages of `chapter_index`, from the neighbour slot when prefetched.
+    fn load_chapter<R: std::io::Read + std::io::Seek>(
+        &mut self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+    ) -> Result<LoadedChapter, RenderEngineError> {
+        if let Some(neighbor) = self.cursor.neighbor.take() {
+            if neighbor.index == chapter_index {
+                return Ok(neighbor);
+            }
+            self.cursor.neighbor = Some(neighbor);
+        }
+        let mut config = self.cfg.clone();
+        config.page_range = None;
+        config.stop_after_page = None;
+        let pages = self
+            .engine
+            .prepare_chapter_with_config_collect(book, chapter_index, config)?;
+        Ok(LoadedChapter {
+            index: chapter_index,
+            pages,
         })
     }
-}
 
-/// Incremental wrapper session returned by `RenderEngine::begin`.
-pub struct LayoutSession<'a> {
-    engine: &'a RenderEngine,
-    chapter_index: usize,
-    profile: PaginationProfileId,
-    cfg: RenderConfig<'a>,
-    inner: Option<CoreLayoutSession>,
-    pending_pages: VecDeque<RenderPage>,
-    rendered_pages: Vec<RenderPage>,
-    page_index: usize,
-    completed: bool,
-}
+    /// Put `chapter` under the cursor, keeping the previous one as the
+    /// neighbour.
+    fn install(&mut self, chapter: LoadedChapter, page_index: usize) {
+        if let Some(previous) = self.cursor.chapter.replace(chapter) {
+            self.cursor.neighbor = Some(previous);
+        }
+        self.cursor.page_index = page_index;
+    }
 
-impl LayoutSession<'_> {
-    /// Push one styled item through layout and enqueue closed pages.
-    pub fn push(&mut self, item: StyledEventOrRun) -> Result<(), RenderEngineError> {
-        if self.completed {
-            return Ok(());
+    /// Lay out the next chapter ahead of time when on a chapter's last
+    /// page. Failures are left for the page turn to report.
+    fn prefetch<R: std::io::Read + std::io::Seek>(&mut self, book: &mut EpubBook<R>) {
+        let (chapter_index, pages) = self.cursor_chapter();
+        let next = chapter_index + 1;
+        if self.cursor.page_index + 1 < pages
+            || next >= book.chapter_count()
+            || self
+                .cursor
+                .neighbor
+                .as_ref()
+                .is_some_and(|neighbor| neighbor.index == next)
+        {
+            return;
         }
-        if self.cfg.cancel.is_some_and(|cancel| cancel.is_cancelled()) {
-            self.engine.emit_diagnostic(RenderDiagnostic::Cancelled);
-            return Err(RenderEngineError::Cancelled);
+        if let Ok(chapter) = self.load_chapter(book, next) {
+            self.cursor.neighbor = Some(chapter);
         }
-        if let Some(inner) = self.inner.as_mut() {
-            let chapter = self.chapter_index;
-            let range = normalize_page_range(self.cfg.page_range.clone());
-            let rendered = &mut self.rendered_pages;
-            let pending = &mut self.pending_pages;
-            let page_index = &mut self.page_index;
-            let capture_for_cache = self.cfg.cache.is_some();
-            inner.push_item_with_pages(item, &mut |mut page| {
-                RenderEngine::annotate_page_for_chapter(&mut page, chapter);
-                if capture_for_cache {
-                    rendered.push(page.clone());
-                }
-                if page_in_range(*page_index, &range) {
-                    pending.push_back(page);
-                }
-                *page_index += 1;
-            });
+    }
+
+    /// Re-paginate this session's chapter after a font-size, margin, or
+    /// other typography change, keeping the reader's place.
+    ///
+    /// `current_page` is the chapter page index on screen under the old
+    /// layout. Its start position is carried over like a `ContentLocator`
+    /// and the index of the new page holding it is returned. The chapter is
+    /// laid out again from `book` under `options`, replacing queued pages;
+    /// collect the new pages with `drain_pages`. The run config, including
+    /// its cache and cancel token, is kept.
+    pub fn relayout_with<R: std::io::Read + std::io::Seek>(
+        &mut self,
+        book: &mut EpubBook<R>,
+        options: RenderEngineOptions,
+        current_page: usize,
+    ) -> Result<usize, RenderEngineError> {
+        let position = self
+            .page_starts
+            .get(current_page)
+            .copied()
+            .flatten()
+            .unwrap_or_default();
+        let mut engine = RenderEngine::new(options);
+        engine.diagnostic_sink = self.engine.diagnostic_sink.clone();
+        engine.diagnostic_filter = self.engine.diagnostic_filter;
+        engine.cache_ledger = self.engine.cache_ledger.clone();
+        engine.page_start_memo = self.engine.page_start_memo.clone();
+        engine.telemetry = self.engine.telemetry.clone();
+        engine.image_cache = self.engine.image_cache.clone();
+        let chapter_index = self.chapter_index;
+        *self = LayoutSession::open(Cow::Owned(engine.clone()), chapter_index, self.cfg.clone());
+        if !self.completed {
+            let mut prep = RenderPrep::new(engine.opts.prep).with_serif_default();
+            if self.cfg.embedded_fonts {
+                prep = prep.with_embedded_fonts_from_book(book)?;
+            }
+            let mut push_error = None;
+            prep.prepare_chapter_with_resources(book, chapter_index, |item, book| {
+                if push_error.is_some() {
+                    return;
+                }
+                let item = engine.load_object_payload(book, item, self);
+                if let Err(err) = self.push(item) {
+                    push_error = Some(err);
+                }
+            })?;
+            engine.record_chapter_read(&prep);
+            if let Some(err) = push_error {
+                return Err(err);
+            }
+            self.finish()?;
         }
-        Ok(())
+        Ok(self
+            .page_starts
+            .iter()
+            .rposition(|start| start.is_some_and(|start| start <= position))
+            .unwrap_or(0))
     }
 
-    /// Drain currently available pages in FIFO order.
-    pub fn drain_pages<F>(&mut self, mut on_page: F)
-    where
-        F: FnMut(RenderPage),
-    {
-        while let Some(page) = self.pending_pages.pop_front() {
-            on_page(page);
+    /// Snapshot the layout cursor after `items_consumed` styled items.
+    fn into_checkpoint(self, items_consumed: usize, pages: Vec<RenderPage>) -> LayoutCheckpoint {
+        LayoutCheckpoint {
+            chapter_index: self.chapter_index,
+            profile: self.profile,
+            items_consumed,
+            pages,
+            inner: self.inner.map(Box::new),
+            page_index: self.page_index,
+            page_starts: self.page_starts,
+            prev_commands: self.prev_commands,
+            rendered_pages: self.rendered_pages,
+            rendered_bytes: self.rendered_bytes,
         }
     }
 
-    /// Finish layout and enqueue any remaining pages.
-    pub fn finish(&mut self) -> Result<(), RenderEngineError> {
-        if self.completed {
-            return Ok(());
+    /// Continue from `checkpoint`, returning its emitted pages.
+    ///
+    /// Replaces any cache hit taken by `begin`; the checkpoint already holds
+    /// the pages that hit would repeat.
+    fn restore_checkpoint(&mut self, checkpoint: LayoutCheckpoint) -> Vec<RenderPage> {
+        self.inner = checkpoint.inner.map(|inner| *inner);
+        self.page_index = checkpoint.page_index;
+        self.page_starts = checkpoint.page_starts;
+        self.prev_commands = checkpoint.prev_commands;
+        self.rendered_pages = checkpoint.rendered_pages;
+        self.rendered_bytes = checkpoint.rendered_bytes;
+        self.pending_pages.clear();
+        self.pending_bytes = 0;
+        self.page_gauge.set(self.rendered_bytes);
+        self.completed = false;
+        checkpoint.pages
+    }
+}
+
+/// Capture a page for cache storage unless the byte budget is exhausted.
+///
+/// Once the budget is exceeded the capture is dropped so the chapter is not
+/// stored partially.
+fn capture_rendered(
+    rendered: &mut Vec<RenderPage>,
+    rendered_bytes: &mut usize,
+    page: &RenderPage,
+    byte_limit: usize,
+) {
+    if *rendered_bytes > byte_limit {
+        return;
+    }
+    *rendered_bytes = rendered_bytes.saturating_add(page.approx_bytes());
+    if *rendered_bytes > byte_limit {
+        rendered.clear();
+        rendered.shrink_to_fit();
+        return;
+    }
+    rendered.push(page.clone());
+}
+
+/// Damage rectangles above this count collapse into their union.
+const MAX_DAMAGE_RECTS: usize = 8;
+
+fn attach_damage(
+    page: &mut RenderPage,
+    prev_commands: &mut Option<Vec<DrawCommand>>,
+    cfg: &LayoutConfig,
+) {
+    page.damage = prev_commands
+        .as_deref()
+        .and_then(|prev| page_damage(prev, &page.commands, cfg));
+    *prev_commands = Some(page.commands.clone());
+}
+
+/// Merged, display-clipped bounds of commands that differ between two pages.
+fn page_damage(
+    prev: &[DrawCommand],
+    next: &[DrawCommand],
+    cfg: &LayoutConfig,
+) -> Option<Vec<PageRect>> {
+    let diff = PageDiff::between(prev, next);
+    let display = PageRect::new(
+        0,
+        0,
+        cfg.display_width.max(0) as u32,
+        cfg.display_height.max(0) as u32,
+    );
+    let mut rects = Vec::with_capacity(16);
+    for cmd in diff.commands() {
+        if let Some(rect) = command_damage(cmd, cfg)?.clip(&display) {
+            rects.push(rect);
         }
-        if self.cfg.cancel.is_some_and(|cancel| cancel.is_cancelled()) {
-            self.engine.emit_diagnostic(RenderDiagnostic::Cancelled);
-            return Err(RenderEngineError::Cancelled);
+    }
+    let mut merged = merge_rects(rects);
+    if merged.len() > MAX_DAMAGE_RECTS {
+        let union = merged.iter().skip(1).fold(merged[0], |acc, r| acc.union(r));
+        merged.clear();
+        merged.push(union);
+    }
+    Some(merged)
+}
+
+/// Command bounds, mapping chrome markers onto their reserved margin bands.
+fn command_damage(cmd: &DrawCommand, cfg: &LayoutConfig) -> Option<PageRect> {
+    let DrawCommand::PageChrome(chrome) = cmd else {
+        return cmd.bounds();
+    };
+    let width = cfg.display_width.max(0) as u32;
+    Some(match chrome.kind {
+        PageChromeKind::Header => PageRect::new(0, 0, width, cfg.margin_top.max(0) as u32),
+        PageChromeKind::Footer | PageChromeKind::Progress | PageChromeKind::BookProgress => {
+            PageRect::new(
+                0,
+                cfg.display_height - cfg.margin_bottom,
+                width,
+                cfg.margin_bottom.max(0) as u32,
+            )
         }
-        if let Some(inner) = self.inner.as_mut() {
-            let chapter = self.chapter_index;
-            let range = normalize_page_range(self.cfg.page_range.clone());
-            let rendered = &mut self.rendered_pages;
-            let pending = &mut self.pending_pages;
-            let page_index = &mut self.page_index;
-            let capture_for_cache = self.cfg.cache.is_some();
-            inner.finish(&mut |mut page| {
-                RenderEngine::annotate_page_for_chapter(&mut page, chapter);
-                if capture_for_cache {
-                    rendered.push(page.clone());
-                }
-                if page_in_range(*page_index, &range) {
-                    pending.push_back(page);
-                }
-                *page_index += 1;
-            });
+    })
+}
+
+/// Label of the first TOC entry (depth-first) pointing into `href`.
+///
+/// TOC hrefs are relative to the navigation document, so a TOC path also
+/// matches when it is a trailing path segment of the chapter href.
+fn toc_label_for_href(points: &[NavPoint], href: &str) -> Option<String> {
+    for point in points {
+        let base = point.href.split('#').next().unwrap_or_default();
+        let base = base.trim_start_matches("../");
+        if !base.is_empty()
+            && (base == href
+                || href
+                    .strip_suffix(base)
+                    .is_some_and(|prefix| prefix.ends_with('/')))
+        {
+            return Some(point.label.clone());
         }
-        if let Some(cache) = self.cfg.cache {
-            if !self.rendered_pages.is_empty() {
-                cache.store_chapter_pages(self.profile, self.chapter_index, &self.rendered_pages);
+        if let Some(label) = toc_label_for_href(&point.children, href) {
+            return Some(label);
+        }
+    }
+    None
+}
+
+/// Fill spine and page indices of book-internal link targets.
+///
+/// Fragments resolve here only when their anchor is on the same page.
+fn resolve_page_links(page: &mut RenderPage, chapters: &[ChapterRef]) {
+    let (chapter_index, page_index) = (page.metrics.chapter_index, page.metrics.chapter_page_index);
+    let anchors: Vec<String> = page.anchors().map(str::to_string).collect();
+    for annotation in &mut page.annotations {
+        let PageAnnotation::Link {
+            target: LinkTarget::Internal(link),
+            ..
+        } = annotation
+        else {
+            continue;
+        };
+        let Some(chapter) = chapters.iter().find(|chapter| chapter.href == link.href) else {
+            continue;
+        };
+        link.chapter_index = Some(chapter.index);
+        match &link.fragment {
+            None => link.page_index = Some(0),
+            Some(fragment) if chapter.index == chapter_index && anchors.contains(fragment) => {
+                link.page_index = Some(page_index);
             }
+            Some(_) => {}
         }
-        self.completed = true;
-        Ok(())
     }
+}
 
-    fn is_complete(&self) -> bool {
-        self.completed
+/// Fill page indices of fragment links whose anchor is on one of `pages`.
+fn resolve_fragment_links(pages: &mut [RenderPage]) {
+    let mut anchors: Vec<(usize, String, usize)> = Vec::with_capacity(0);
+    for page in pages.iter() {
+        let metrics = page.metrics;
+        anchors.extend(page.anchors().map(|id| {
+            (
+                metrics.chapter_index,
+                id.to_string(),
+                metrics.chapter_page_index,
+            )
+        }));
+    }
+    if anchors.is_empty() {
+        return;
+    }
+    for annotation in pages.iter_mut().flat_map(|page| &mut page.annotations) {
+        let PageAnnotation::Link {
+            target: LinkTarget::Internal(link),
+            ..
+        } = annotation
+        else {
+            continue;
+        };
+        let (Some(chapter_index), None, Some(fragment)) =
+            (link.chapter_index, link.page_index, &link.fragment)
+        else {
+            continue;
+        };
+        link.page_index = anchors
+            .iter()
+            .find(|(chapter, id, _)| *chapter == chapter_index && id == fragment)
+            .map(|(_, _, page)| *page);
     }
 }
 
@@ -706,8 +4089,124 @@ impl ExactSizeIterator for RenderPageIter {
 
 impl std::iter::FusedIterator for RenderPageIter {}
 
+/// Pages of one chapter going backwards, returned by
+/// `RenderEngine::prepare_chapter_reverse`.
+pub struct ReversePageIter<'a> {
+    engine: &'a RenderEngine,
+    chapter_index: usize,
+    fill: ReverseFill,
+    /// Start of the page yielded last; `None` before the first page.
+    anchor: Option<TextPosition>,
+    decorations: PageDecorations,
+    decoded: DecodedImages,
+    emitted: usize,
+}
+
+impl ReversePageIter<'_> {
+    /// Start position of the page yielded last, i.e. where the next page
+    /// will end.
+    pub fn anchor(&self) -> Option<TextPosition> {
+        self.anchor
+    }
+}
+
+impl fmt::Debug for ReversePageIter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReversePageIter")
+            .field("chapter_index", &self.chapter_index)
+            .field("anchor", &self.anchor)
+            .field("emitted", &self.emitted)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Iterator for ReversePageIter<'_> {
+    type Item = RenderPage;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let end = self.anchor.unwrap_or(TextPosition {
+            block_index: usize::MAX,
+            char_offset: 0,
+        });
+        let (mut page, start) = self.fill.page_before(end)?;
+        self.anchor = Some(start);
+        self.emitted += 1;
+        page.page_number = self.emitted;
+        let engine = self.engine;
+        engine.annotate_page_for_chapter(&mut page, self.chapter_index, None);
+        attach_page_images(&mut page, &self.decoded, &engine.layout_cfg.render_intent);
+        engine.decorate(&mut page, &self.decorations);
+        Some(page)
+    }
+}
+
+impl std::iter::FusedIterator for ReversePageIter<'_> {}
+
+/// Attach the decoded images a page laid out out of order refers to.
+fn attach_page_images(page: &mut RenderPage, decoded: &DecodedImages, intent: &RenderIntent) {
+    let mut wanted: VecDeque<_> = decoded
+        .iter()
+        .filter(|(src, _)| {
+            page.content_commands
+                .iter()
+                .any(|cmd| matches!(cmd, DrawCommand::Image(image) if image.src == *src))
+        })
+        .cloned()
+        .collect();
+    attach_decoded_images(page, &mut wanted, intent);
+}
+
+/// Page start positions of recently laid out chapters, so single pages can
+/// be rebuilt without laying out the chapter prefix.
+#[derive(Debug, Default)]
+struct PageStartMemo {
+    chapters: VecDeque<(PaginationProfileId, usize, Vec<Option<TextPosition>>)>,
+}
+
+/// Chapters remembered by `PageStartMemo`.
+const PAGE_START_MEMO_CHAPTERS: usize = 16;
+
+impl PageStartMemo {
+    fn record(
+        &mut self,
+        profile: PaginationProfileId,
+        chapter_index: usize,
+        starts: &[Option<TextPosition>],
+    ) {
+        self.chapters
+            .retain(|(known, chapter, _)| *known != profile || *chapter != chapter_index);
+        if self.chapters.len() >= PAGE_START_MEMO_CHAPTERS {
+            self.chapters.pop_front();
+        }
+        self.chapters
+            .push_back((profile, chapter_index, starts.to_vec()));
+    }
+
+    /// Start and end positions of a page.
+    fn bounds(
+        &self,
+        profile: PaginationProfileId,
+        chapter_index: usize,
+        page_index: usize,
+    ) -> Option<(TextPosition, TextPosition)> {
+        let (_, _, starts) = self
+            .chapters
+            .iter()
+            .find(|(known, chapter, _)| *known == profile && *chapter == chapter_index)?;
+        let start = (*starts.get(page_index)?)?;
+        let end = match starts.get(page_index + 1) {
+            Some(next) => (*next)?,
+            None => TextPosition {
+                block_index: usize::MAX,
+                char_offset: 0,
+            },
+        };
+        Some((start, end))
+    }
+}
+
 enum StreamMessage {
-    Page(RenderPage),
+    Page(Box<RenderPage>),
     Error(RenderEngineError),
     Done,
 }
@@ -727,7 +4226,7 @@ impl Iterator for RenderPageStreamIter {
             return None;
         }
         match self.rx.recv() {
-            Ok(StreamMessage::Page(page)) => Some(Ok(page)),
+            Ok(StreamMessage::Page(page)) => Some(Ok(*page)),
             Ok(StreamMessage::Error(err)) => {
                 self.finished = true;
                 Some(Err(err))
@@ -753,6 +4252,8 @@ pub enum RenderEngineError {
         actual: usize,
         limit: usize,
     },
+    /// A book page map was built under a different pagination profile.
+    ProfileMismatch,
 }
 
 impl core::fmt::Display for RenderEngineError {
@@ -769,18 +4270,81 @@ impl core::fmt::Display for RenderEngineError {
                 "render memory limit exceeded: {} (actual={} limit={})",
                 kind, actual, limit
             ),
+            Self::ProfileMismatch => write!(f, "book page map profile mismatch"),
         }
     }
 }
 
 impl std::error::Error for RenderEngineError {}
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for RenderEngineError {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self {
+            Self::Prep(err) => defmt::write!(f, "render prep failed: {}", err),
+            Self::Cancelled => defmt::write!(f, "render cancelled"),
+            Self::LimitExceeded {
+                kind,
+                actual,
+                limit,
+            } => defmt::write!(
+                f,
+                "render memory limit exceeded: {=str} (actual={=usize} limit={=usize})",
+                kind,
+                actual,
+                limit
+            ),
+            Self::ProfileMismatch => defmt::write!(f, "book page map profile mismatch"),
+        }
+    }
+}
+
+impl RenderEngineError {
+    /// Whether the chapter itself failed, as opposed to the caller
+    /// cancelling or passing a stale page map.
+    fn is_chapter_failure(&self) -> bool {
+        matches!(self, Self::Prep(_) | Self::LimitExceeded { .. })
+    }
+
+    /// Stable machine-readable code, listed in `mu_epub::ERROR_CODES`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Prep(err) => err.code,
+            Self::Cancelled => "RENDER_CANCELLED",
+            Self::LimitExceeded { .. } => "RENDER_LIMIT_EXCEEDED",
+            Self::ProfileMismatch => "RENDER_PROFILE_MISMATCH",
+        }
+    }
+}
+
 impl From<RenderPrepError> for RenderEngineError {
     fn from(value: RenderPrepError) -> Self {
         Self::Prep(value)
     }
 }
 
+impl From<RenderEngineError> for PhaseError {
+    fn from(err: RenderEngineError) -> Self {
+        let code = err.code();
+        match err {
+            RenderEngineError::Prep(err) => err.into(),
+            RenderEngineError::LimitExceeded {
+                kind,
+                actual,
+                limit,
+            } => {
+                let mut phase = PhaseError::new(ErrorPhase::Layout, code, err.to_string());
+                phase.context = Some(Box::new(PhaseErrorContext {
+                    limit: Some(Box::new(ErrorLimitContext::new(kind, actual, limit))),
+                    ..PhaseErrorContext::default()
+                }));
+                phase
+            }
+            err => PhaseError::new(ErrorPhase::Render, code, err.to_string()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -797,9 +4361,12 @@ mod tests {
                 line_height: 1.4,
                 letter_spacing: 0.0,
                 block_role: BlockRole::Body,
+                color: None,
             },
             font_id: 0,
             resolved_family: "serif".to_string(),
+            link: None,
+            anchors: Vec::with_capacity(0),
         })
     }
 
@@ -810,7 +4377,7 @@ mod tests {
         opts.layout.margin_bottom = 8;
         let engine = RenderEngine::new(opts);
 
-        let mut items = Vec::new();
+        let mut items = Vec::with_capacity(8);
         for _ in 0..40 {
             items.push(StyledEventOrRun::Event(StyledEvent::ParagraphStart));
             items.push(body_run("one two three four five six seven eight nine ten"));
@@ -818,7 +4385,7 @@ mod tests {
         }
 
         let mut session = engine.begin(3, RenderConfig::default());
-        let mut streamed = Vec::new();
+        let mut streamed = Vec::with_capacity(8);
         for item in &items {
             session.push(item.clone()).expect("push should pass");
             session.drain_pages(|page| streamed.push(page));
@@ -828,9 +4395,466 @@ mod tests {
 
         let mut expected = engine.layout.layout_items(items);
         for page in &mut expected {
-            page.metrics.chapter_index = 3;
+            engine.annotate_page_for_chapter(page, 3, None);
         }
+        let damage: Vec<Option<Vec<PageRect>>> =
+            streamed.iter_mut().map(|page| page.damage.take()).collect();
         assert_eq!(streamed, expected);
+        assert!(damage[0].is_none());
+        // Repeated paragraphs may produce identical pages with empty damage.
+        assert!(damage[1..]
+            .iter()
+            .all(|rects| rects.as_ref().is_some_and(|r| r.len() <= MAX_DAMAGE_RECTS)));
         assert!(streamed.iter().all(|page| page.metrics.chapter_index == 3));
     }
+
+    #[test]
+    fn undrained_session_enforces_page_byte_budget() {
+        let mut opts = RenderEngineOptions::for_display(300, 120);
+        opts.prep.memory.max_page_bytes_in_memory = 4 * 1024;
+        let engine = RenderEngine::new(opts);
+        let paragraph = [
+            StyledEventOrRun::Event(StyledEvent::ParagraphStart),
+            body_run("one two three four five six seven eight nine ten"),
+            StyledEventOrRun::Event(StyledEvent::ParagraphEnd),
+        ];
+
+        let mut drained = engine.begin(0, RenderConfig::default());
+        for item in paragraph.iter().cycle().take(120) {
+            drained
+                .push(item.clone())
+                .expect("drained session stays in budget");
+            drained.drain_pages(|_| {});
+        }
+
+        let mut session = engine.begin(0, RenderConfig::default());
+        let err = paragraph
+            .iter()
+            .cycle()
+            .take(120)
+            .find_map(|item| session.push(item.clone()).err())
+            .expect("undrained pages should exceed the budget");
+        assert!(matches!(
+            err,
+            RenderEngineError::LimitExceeded {
+                kind: "max_page_bytes_in_memory",
+                limit: 4096,
+                ..
+            }
+        ));
+        let phase = PhaseError::from(err);
+        assert_eq!(phase.phase, ErrorPhase::Layout);
+        assert!(mu_epub::ErrorCodeInfo::lookup(phase.code).is_some());
+        let limit = phase
+            .context
+            .and_then(|ctx| ctx.limit)
+            .expect("limit context");
+        assert_eq!(limit.limit, 4096);
+        assert!(session.buffered_bytes() > 4 * 1024);
+        session.drain_pages(|_| {});
+        assert_eq!(session.buffered_bytes(), 0);
+    }
+
+    #[test]
+    fn session_reports_font_fallback_and_dropped_svg_once() {
+        let mut opts = RenderEngineOptions::for_display(300, 120);
+        opts.layout.object_layout.svg_mode = SvgMode::Ignore;
+        let mut engine = RenderEngine::new(opts);
+        let seen = Arc::new(Mutex::new(Vec::with_capacity(8)));
+        let sink = Arc::clone(&seen);
+        engine.set_diagnostic_sink(move |d| {
+            if let Ok(mut seen) = sink.lock() {
+                seen.push(d);
+            }
+        });
+        let StyledEventOrRun::Run(mut run) = body_run("fallback text") else {
+            unreachable!("body_run builds runs");
+        };
+        run.style.family_stack = vec!["Georgia".to_string(), "serif".to_string()];
+        let svg = StyledEventOrRun::Image(mu_epub::StyledImage {
+            src: "images/figure.svg".to_string(),
+            alt: String::with_capacity(0),
+            width_px: None,
+            height_px: None,
+            inline_svg: None,
+            svg_oversize: false,
+            caption: None,
+        });
+
+        let mut session = engine.begin(2, RenderConfig::default());
+        for item in [
+            StyledEventOrRun::Run(run.clone()),
+            StyledEventOrRun::Run(run),
+            svg,
+        ] {
+            session.push(item).expect("push should pass");
+        }
+        session.finish().expect("finish should pass");
+
+        let seen = seen.lock().expect("diag lock");
+        let reported: Vec<&RenderDiagnostic> = seen
+            .iter()
+            .filter(|d| {
+                matches!(
+                    d,
+                    RenderDiagnostic::FontFallback { .. } | RenderDiagnostic::DroppedContent { .. }
+                )
+            })
+            .collect();
+        assert_eq!(
+            reported,
+            [
+                &RenderDiagnostic::FontFallback {
+                    chapter_index: 2,
+                    page_index: 0,
+                    requested: "Georgia".to_string(),
+                    resolved: "serif".to_string(),
+                },
+                &RenderDiagnostic::DroppedContent {
+                    chapter_index: 2,
+                    page_index: 0,
+                    kind: DroppedContentKind::IgnoredSvg,
+                    src: "images/figure.svg".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn resolve_page_links_maps_internal_targets_to_spine() {
+        let chapters = vec![
+            ChapterRef {
+                index: 0,
+                idref: "c1".to_string(),
+                href: "text/c1.xhtml".to_string(),
+                media_type: "application/xhtml+xml".to_string(),
+            },
+            ChapterRef {
+                index: 1,
+                idref: "c2".to_string(),
+                href: "text/c2.xhtml".to_string(),
+                media_type: "application/xhtml+xml".to_string(),
+            },
+        ];
+        let rect = PageRect::new(0, 0, 10, 10);
+        let mut page = RenderPage::new(1);
+        page.metrics.chapter_page_index = 3;
+        page.annotations
+            .push(PageAnnotation::anchor("here".to_string()));
+        for href in [
+            "text/c2.xhtml",
+            "text/c1.xhtml#note",
+            "https://example.com",
+            "text/c1.xhtml#here",
+        ] {
+            page.annotations.push(PageAnnotation::Link {
+                rect,
+                target: LinkTarget::from_href(href),
+            });
+        }
+        resolve_page_links(&mut page, &chapters);
+
+        let targets: Vec<&LinkTarget> = page
+            .annotations
+            .iter()
+            .filter_map(|a| match a {
+                PageAnnotation::Link { target, .. } => Some(target),
+                _ => None,
+            })
+            .collect();
+        match targets[0] {
+            LinkTarget::Internal(link) => {
+                assert_eq!(link.chapter_index, Some(1));
+                assert_eq!(link.page_index, Some(0));
+            }
+            other => panic!("unexpected target {other:?}"),
+        }
+        match targets[1] {
+            LinkTarget::Internal(link) => {
+                assert_eq!(link.chapter_index, Some(0));
+                assert_eq!(link.fragment.as_deref(), Some("note"));
+                assert_eq!(link.page_index, None);
+            }
+            other => panic!("unexpected target {other:?}"),
+        }
+        assert!(matches!(targets[2], LinkTarget::External(_)));
+        match targets[3] {
+            LinkTarget::Internal(link) => assert_eq!(link.page_index, Some(3)),
+            other => panic!("unexpected target {other:?}"),
+        }
+    }
+
+    #[test]
+    fn resolve_fragment_links_finds_anchors_on_later_pages() {
+        let link = |href: &str, chapter_index: usize| {
+            let LinkTarget::Internal(mut link) = LinkTarget::from_href(href) else {
+                panic!("internal link expected");
+            };
+            link.chapter_index = Some(chapter_index);
+            PageAnnotation::Link {
+                rect: PageRect::new(0, 0, 10, 10),
+                target: LinkTarget::Internal(link),
+            }
+        };
+        let mut pages: Vec<RenderPage> = (0..3)
+            .map(|index| {
+                let mut page = RenderPage::new(index + 1);
+                page.metrics.chapter_page_index = index;
+                page
+            })
+            .collect();
+        pages[0].annotations.push(link("c1.xhtml#n1", 0));
+        pages[0].annotations.push(link("c2.xhtml#n1", 1));
+        pages[2]
+            .annotations
+            .push(PageAnnotation::anchor("n1".to_string()));
+        resolve_fragment_links(&mut pages);
+
+        let indices: Vec<Option<usize>> = pages[0]
+            .annotations
+            .iter()
+            .filter_map(|a| match a {
+                PageAnnotation::Link {
+                    target: LinkTarget::Internal(link),
+                    ..
+                } => Some(link.page_index),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(indices, vec![Some(2), None]);
+    }
+
+    #[test]
+    fn page_damage_skips_unchanged_commands_and_maps_chrome_bands() {
+        let cfg = LayoutConfig::for_display(200, 100);
+        let rule = DrawCommand::Rule(crate::render_ir::RuleCommand {
+            x: 10,
+            y: 50,
+            length: 20,
+            thickness: 1,
+            horizontal: true,
+        });
+        let progress = |current| {
+            DrawCommand::PageChrome(crate::render_ir::PageChromeCommand {
+                kind: PageChromeKind::Progress,
+                text: None,
+                current: Some(current),
+                total: Some(10),
+            })
+        };
+        let prev = vec![rule.clone(), progress(1)];
+
+        assert_eq!(page_damage(&prev, &prev, &cfg), Some(Vec::with_capacity(0)));
+        let next = vec![rule, progress(2)];
+        assert_eq!(
+            page_damage(&prev, &next, &cfg),
+            Some(vec![PageRect::new(0, 60, 200, 40)])
+        );
+    }
+
+    #[test]
+    fn recompose_chrome_refreshes_dynamic_fields_and_damages_band() {
+        struct Clock(&'static str);
+        impl PageChromeProvider for Clock {
+            fn header_text(&self, _metrics: &crate::render_ir::PageMetrics) -> Option<String> {
+                Some(self.0.to_string())
+            }
+        }
+
+        let mut opts = RenderEngineOptions::for_display(200, 100);
+        opts.layout.page_chrome.header_enabled = true;
+        let engine = RenderEngine::new(opts);
+        let mut page = RenderPage::new(1);
+        page.push_content_command(DrawCommand::Rule(crate::render_ir::RuleCommand {
+            x: 10,
+            y: 50,
+            length: 20,
+            thickness: 1,
+            horizontal: true,
+        }));
+        assert!(engine.recompose_chrome(&mut page, &Clock("12:00")));
+        let content = page.content_commands.clone();
+
+        assert!(!engine.recompose_chrome(&mut page, &Clock("12:00")));
+        assert!(engine.recompose_chrome(&mut page, &Clock("12:01")));
+        assert_eq!(page.content_commands, content);
+        assert!(matches!(
+            &page.chrome_commands[..],
+            [DrawCommand::PageChrome(chrome)] if chrome.text.as_deref() == Some("12:01")
+        ));
+        let top = engine.layout_cfg.margin_top.max(0) as u32;
+        assert_eq!(page.damage, Some(vec![PageRect::new(0, 0, 200, top)]));
+    }
+
+    #[test]
+    fn deterministic_mode_ignores_chrome_providers_and_timings() {
+        struct Clock(&'static str);
+        impl PageChromeProvider for Clock {
+            fn header_text(&self, _metrics: &crate::render_ir::PageMetrics) -> Option<String> {
+                Some(self.0.to_string())
+            }
+        }
+
+        let mut opts = RenderEngineOptions::for_display(200, 100);
+        opts.layout.page_chrome.header_enabled = true;
+        let live = RenderEngine::new(opts);
+        opts.deterministic = true;
+        let mut engine = RenderEngine::new(opts);
+        assert_eq!(engine.pagination_profile_id(), live.pagination_profile_id());
+
+        let mut page = RenderPage::new(1);
+        engine.recompose_chrome(&mut page, &Clock("12:00"));
+        let chrome = page.chrome_commands.clone();
+        assert!(!engine.recompose_chrome(&mut page, &Clock("12:01")));
+        assert_eq!(page.chrome_commands, chrome);
+
+        let seen = Arc::new(Mutex::new(Vec::with_capacity(2)));
+        let sink = seen.clone();
+        engine.set_diagnostic_sink(move |diagnostic| {
+            if let Ok(mut seen) = sink.lock() {
+                seen.push(diagnostic);
+            }
+        });
+        engine.emit_diagnostic(RenderDiagnostic::ReflowTimeMs(7));
+        engine.emit_diagnostic(RenderDiagnostic::PhaseTime {
+            chapter_index: 0,
+            phase: RenderPhase::Style,
+            micros: 42,
+        });
+        assert_eq!(
+            *seen.lock().expect("diagnostics"),
+            vec![
+                RenderDiagnostic::ReflowTimeMs(0),
+                RenderDiagnostic::PhaseTime {
+                    chapter_index: 0,
+                    phase: RenderPhase::Style,
+                    micros: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn stored_profile_names_the_settings_that_changed() {
+        let mut opts = RenderEngineOptions::for_display(300, 400);
+        let stored = RenderEngine::new(opts).pagination_profile();
+        let bytes = stored.encode();
+        let stored = PaginationProfile::decode(&bytes).expect("profile decodes");
+        let id = RenderEngine::new(opts).pagination_profile_id();
+        assert_eq!(stored.id(), id);
+        assert_eq!(PaginationProfileId::from_hex(&id.to_hex()), Some(id));
+
+        opts.prep.style.hints.base_font_size_px += 2.0;
+        opts.deterministic = true;
+        let engine = RenderEngine::new(opts);
+        let changes = engine.pagination_profile_changes(&stored);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "prep.style.hints.base_font_size_px");
+        assert_eq!(
+            changes[0].to_string(),
+            format!(
+                "prep.style.hints.base_font_size_px changed from {:?} to {:?}",
+                opts.prep.style.hints.base_font_size_px - 2.0,
+                opts.prep.style.hints.base_font_size_px
+            )
+        );
+        assert_ne!(engine.pagination_profile_id(), id);
+    }
+
+    #[test]
+    fn diagnostic_filter_drops_by_level_and_category() {
+        let mut engine = RenderEngine::new(RenderEngineOptions::for_display(300, 400));
+        let seen = Arc::new(Mutex::new(Vec::with_capacity(2)));
+        let sink = seen.clone();
+        engine.set_diagnostic_sink(move |diagnostic| {
+            if let Ok(mut seen) = sink.lock() {
+                seen.push(diagnostic);
+            }
+        });
+        let dropped = RenderDiagnostic::DroppedContent {
+            chapter_index: 0,
+            page_index: 0,
+            kind: DroppedContentKind::IgnoredSvg,
+            src: "a.svg".to_string(),
+        };
+        let fallback = RenderDiagnostic::FontFallback {
+            chapter_index: 0,
+            page_index: 0,
+            requested: "a".to_string(),
+            resolved: "b".to_string(),
+        };
+
+        engine.set_diagnostic_filter(DiagnosticFilter {
+            warnings: false,
+            ..DiagnosticFilter::at_least(DiagnosticLevel::Warn)
+        });
+        assert!(!engine.wants_diagnostic(DiagnosticCategory::Timing));
+        engine.emit_diagnostic(RenderDiagnostic::ReflowTimeMs(7));
+        engine.emit_diagnostic(RenderDiagnostic::Cancelled);
+        engine.emit_diagnostic(fallback);
+        engine.emit_diagnostic(dropped.clone());
+        assert_eq!(*seen.lock().expect("diagnostics"), vec![dropped]);
+    }
+
+    #[test]
+    fn page_dump_interval_reports_every_nth_page_as_json() {
+        let mut opts = RenderEngineOptions::for_display(300, 120);
+        opts.layout.margin_top = 8;
+        opts.layout.margin_bottom = 8;
+        let mut engine = RenderEngine::new(opts);
+        let seen = Arc::new(Mutex::new(Vec::with_capacity(4)));
+        let sink = seen.clone();
+        engine.set_diagnostic_sink(move |diagnostic| {
+            if let RenderDiagnostic::PageDump {
+                chapter_index,
+                page_index,
+                json,
+            } = diagnostic
+            {
+                if let Ok(mut seen) = sink.lock() {
+                    seen.push((chapter_index, page_index, json));
+                }
+            }
+        });
+
+        let mut items = Vec::with_capacity(60);
+        for _ in 0..20 {
+            items.push(StyledEventOrRun::Event(StyledEvent::ParagraphStart));
+            items.push(body_run("one two three four five six seven eight nine ten"));
+            items.push(StyledEventOrRun::Event(StyledEvent::ParagraphEnd));
+        }
+        let mut pages = engine.layout.layout_items(items);
+        assert!(pages.len() >= 4);
+        let decorations = PageDecorations {
+            chapters: Vec::with_capacity(0),
+            chapter_title: None,
+            annotations: Vec::with_capacity(0),
+            bookmarks: Vec::with_capacity(0),
+        };
+        engine.set_page_dump_interval(0);
+        for page in &mut pages {
+            engine.annotate_page_for_chapter(page, 2, None);
+            engine.decorate(page, &decorations);
+        }
+        assert!(seen.lock().expect("dumps").is_empty());
+
+        engine.set_page_dump_interval(3);
+        for page in &mut pages {
+            engine.decorate(page, &decorations);
+        }
+        let dumps = seen.lock().expect("dumps").clone();
+        let indices: Vec<usize> = dumps.iter().map(|(_, index, _)| *index).collect();
+        let expected: Vec<usize> = (0..pages.len()).step_by(3).collect();
+        assert_eq!(indices, expected);
+        assert!(dumps.iter().all(|(chapter, _, _)| *chapter == 2));
+        assert_eq!(dumps[1].2, pages[3].to_debug_json());
+        assert!(dumps[1].2.contains("\"text\": \"one two"));
+
+        engine.set_diagnostic_filter(DiagnosticFilter {
+            page_dumps: false,
+            ..DiagnosticFilter::ALL
+        });
+        engine.decorate(&mut pages[0], &decorations);
+        assert_eq!(seen.lock().expect("dumps").len(), dumps.len());
+    }
 }
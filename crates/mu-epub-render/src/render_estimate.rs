@@ -0,0 +1,222 @@
+//! Approximate page counts from styled items, without building pages.
+
+use mu_epub::{StyledEvent, StyledEventOrRun, StyledImage, StyledRun};
+
+use crate::render_ir::SvgMode;
+use crate::render_layout::{
+    line_height_px, measure_text, to_resolved_style, LayoutConfig, LINE_FIT_GUARD_PX,
+};
+
+/// Page counter mirroring the layout cursor at block granularity.
+///
+/// Each block's width is its char count times the average glyph width of
+/// its runs, wrapped into lines of the content width. Words are never
+/// placed, so ragged line ends are ignored and the count runs slightly low
+/// on narrow viewports.
+pub(crate) struct PageEstimator {
+    cfg: LayoutConfig,
+    cursor_y: i32,
+    pages: usize,
+    page_used: bool,
+    block_width: f32,
+    block_line_px: i32,
+    heading: bool,
+    in_list: bool,
+    pending_indent: bool,
+}
+
+impl PageEstimator {
+    pub(crate) fn new(cfg: LayoutConfig) -> Self {
+        Self {
+            cfg,
+            cursor_y: cfg.margin_top,
+            pages: 0,
+            page_used: false,
+            block_width: 0.0,
+            block_line_px: 0,
+            heading: false,
+            in_list: false,
+            pending_indent: false,
+        }
+    }
+
+    pub(crate) fn push(&mut self, item: StyledEventOrRun) {
+        match item {
+            StyledEventOrRun::Run(run) => self.push_run(&run),
+            StyledEventOrRun::Event(ev) => self.push_event(ev),
+            StyledEventOrRun::Image(image) => self.push_image(&image),
+        }
+    }
+
+    /// Estimated page count so far, including the open page.
+    pub(crate) fn finish(mut self) -> usize {
+        self.flush_block();
+        self.pages + usize::from(self.page_used)
+    }
+
+    fn push_run(&mut self, run: &StyledRun) {
+        let style = to_resolved_style(&run.style, &self.cfg.render_intent);
+        let words = run.text.split_whitespace().count();
+        if words == 0 {
+            return;
+        }
+        let chars = run
+            .text
+            .split_whitespace()
+            .map(|word| word.chars().count())
+            .sum::<usize>();
+        let spaces = words - 1 + usize::from(self.block_width > 0.0);
+        if self.pending_indent && !self.in_list && !self.heading {
+            self.block_width += self.cfg.first_line_indent_px.max(0) as f32;
+        }
+        self.pending_indent = false;
+        self.block_width += (chars + spaces) as f32 * measure_text("x", &style);
+        self.block_line_px = self.block_line_px.max(line_height_px(&style, &self.cfg));
+    }
+
+    fn push_event(&mut self, ev: StyledEvent) {
+        match ev {
+            StyledEvent::ParagraphStart => {
+                self.pending_indent = true;
+            }
+            StyledEvent::ParagraphEnd => {
+                self.flush_block();
+                self.add_gap(self.cfg.paragraph_gap_px);
+            }
+            StyledEvent::HeadingStart(_) => {
+                self.flush_block();
+                self.add_gap(self.cfg.heading_gap_px);
+                self.heading = true;
+            }
+            StyledEvent::HeadingEnd(_) => {
+                self.flush_block();
+                self.add_gap(self.cfg.heading_gap_px);
+                self.heading = false;
+            }
+            StyledEvent::ListItemStart => {
+                self.flush_block();
+                self.in_list = true;
+            }
+            StyledEvent::ListItemEnd => {
+                self.flush_block();
+                self.add_gap(self.cfg.paragraph_gap_px.saturating_sub(2));
+                self.in_list = false;
+            }
+            StyledEvent::LineBreak => self.flush_block(),
+        }
+    }
+
+    fn push_image(&mut self, image: &StyledImage) {
+        let objects = self.cfg.object_layout;
+        if image.is_svg() && objects.svg_mode == SvgMode::Ignore {
+            return;
+        }
+        let inline = self.block_width > 0.0;
+        self.flush_block();
+        let content_w = self.cfg.content_width() as f32;
+        let (w, h) = match (image.width_px, image.height_px) {
+            (Some(w), Some(h)) if w > 0.0 && h > 0.0 => (w, h),
+            _ => (content_w, content_w * 0.75),
+        };
+        let max_h = if inline {
+            self.cfg.content_height() as f32
+                * objects.max_inline_image_height_ratio.clamp(0.05, 1.0)
+        } else {
+            self.cfg.content_height() as f32
+        };
+        let scale = (content_w / w).min(max_h / h).min(1.0);
+        let height = (h * scale).round().max(1.0) as i32;
+        if self.page_used && self.cursor_y + height > self.cfg.content_bottom() {
+            self.next_page();
+        }
+        self.cursor_y += height;
+        self.page_used = true;
+        self.add_gap(self.cfg.paragraph_gap_px);
+    }
+
+    /// Wrap the open block into lines and advance the cursor past them.
+    fn flush_block(&mut self) {
+        let width = core::mem::take(&mut self.block_width);
+        let line_px = core::mem::take(&mut self.block_line_px);
+        if width <= 0.0 {
+            return;
+        }
+        let inset = if self.in_list {
+            self.cfg.list_indent_px
+        } else {
+            0
+        };
+        let max_width =
+            ((self.cfg.content_width() - inset).max(1) as f32 - LINE_FIT_GUARD_PX).max(1.0);
+        let lines = (width / max_width).ceil().max(1.0) as usize;
+        for _ in 0..lines {
+            if self.cursor_y + line_px > self.cfg.content_bottom() {
+                self.next_page();
+            }
+            self.page_used = true;
+            self.cursor_y += line_px + self.cfg.line_gap_px;
+        }
+    }
+
+    fn add_gap(&mut self, gap_px: i32) {
+        if gap_px <= 0 {
+            return;
+        }
+        self.cursor_y += gap_px;
+        if self.cursor_y >= self.cfg.content_bottom() {
+            self.next_page();
+        }
+    }
+
+    fn next_page(&mut self) {
+        if self.page_used {
+            self.pages += 1;
+        }
+        self.page_used = false;
+        self.cursor_y = self.cfg.margin_top;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_layout::LayoutEngine;
+    use mu_epub::{BlockRole, ComputedTextStyle};
+
+    fn run(text: &str) -> StyledEventOrRun {
+        StyledEventOrRun::Run(StyledRun {
+            text: text.to_string(),
+            style: ComputedTextStyle {
+                family_stack: vec!["serif".to_string()],
+                weight: 400,
+                italic: false,
+                size_px: 16.0,
+                line_height: 1.4,
+                letter_spacing: 0.0,
+                block_role: BlockRole::Body,
+                color: None,
+            },
+            font_id: 0,
+            resolved_family: "serif".to_string(),
+            link: None,
+            anchors: Vec::with_capacity(0),
+        })
+    }
+
+    #[test]
+    fn estimate_tracks_layout_for_uniform_paragraphs() {
+        let cfg = LayoutConfig::for_display(480, 800);
+        let mut items = Vec::with_capacity(0);
+        for _ in 0..60 {
+            items.push(StyledEventOrRun::Event(StyledEvent::ParagraphStart));
+            items.push(run(&"lorem ipsum dolor sit amet ".repeat(12)));
+            items.push(StyledEventOrRun::Event(StyledEvent::ParagraphEnd));
+        }
+        let laid_out = LayoutEngine::new(cfg).layout_items(items.clone()).len();
+        let mut estimator = PageEstimator::new(cfg.effective());
+        items.into_iter().for_each(|item| estimator.push(item));
+        let estimate = estimator.finish();
+        assert!(laid_out > 3);
+        assert!(estimate.abs_diff(laid_out) <= laid_out / 5 + 1);
+    }
+}
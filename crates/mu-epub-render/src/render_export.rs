@@ -0,0 +1,358 @@
+//! Supersampled page export at a multiple of the device resolution.
+
+use std::io::{Read, Seek};
+
+use mu_epub::EpubBook;
+
+use crate::render_engine::{RenderEngine, RenderEngineError};
+use crate::render_image::resize;
+use crate::render_ir::{
+    DrawCommand, GlyphPosition, GrayBitmap, ImageCommand, LayeredCommand, OverlayContent,
+    OverlayItem, OverlayRect, OverlaySize, OverlaySlot, PageAnnotation, PageRect, RectCommand,
+    RenderPage, RuleCommand, TextCommand, VerticalMetrics,
+};
+
+impl RenderPage {
+    /// Copy of this page with every coordinate multiplied by `scale`.
+    ///
+    /// Line breaks, page breaks, and metrics are unchanged, so the result
+    /// is the same page drawn on a `scale`x denser canvas. Text sizes grow
+    /// with the geometry; image bitmaps are upscaled nearest-neighbour
+    /// (see `RenderEngine::prepare_chapter_export` for sharp images).
+    /// A scale of `0` is treated as `1`.
+    pub fn scaled(&self, scale: u32) -> RenderPage {
+        let scale = scale.max(1);
+        let commands = |cmds: &[DrawCommand]| {
+            cmds.iter()
+                .map(|cmd| scale_command(cmd, scale))
+                .collect::<Vec<_>>()
+        };
+        let rect = |rect: &PageRect| scale_rect(rect, scale);
+        let mut page = RenderPage {
+            page_number: self.page_number,
+            commands: Vec::with_capacity(0),
+            content_commands: commands(&self.content_commands),
+            chrome_commands: commands(&self.chrome_commands),
+            overlay_commands: commands(&self.overlay_commands),
+            overlay_items: self
+                .overlay_items
+                .iter()
+                .map(|item| scale_overlay_item(item, scale))
+                .collect(),
+            annotations: self
+                .annotations
+                .iter()
+                .map(|annotation| scale_annotation(annotation, scale))
+                .collect(),
+            metrics: self.metrics,
+            damage: self
+                .damage
+                .as_ref()
+                .map(|damage| damage.iter().map(rect).collect()),
+            chapter_title: self.chapter_title.clone(),
+            line_positions: self.line_positions.clone(),
+        };
+        page.sync_commands();
+        page
+    }
+}
+
+impl RenderEngine {
+    /// Lay out a chapter for this device, then render it at `scale`x for
+    /// share images and print-quality exports.
+    ///
+    /// Pagination is exactly the device's: page count, line breaks, and
+    /// positions match `prepare_chapter`. Raster images are decoded again
+    /// from the book at their scaled size instead of being enlarged from
+    /// the device bitmap.
+    pub fn prepare_chapter_export<R: Read + Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+        scale: u32,
+    ) -> Result<Vec<RenderPage>, RenderEngineError> {
+        let pages = self.prepare_chapter(book, chapter_index)?;
+        let mut sources = Vec::with_capacity(4);
+        Ok(pages
+            .iter()
+            .map(|page| self.export_page(book, page, scale, &mut sources))
+            .collect())
+    }
+
+    /// One device page rendered at `scale`x; `None` when the chapter has
+    /// fewer pages.
+    pub fn prepare_page_export<R: Read + Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        chapter_index: usize,
+        page_index: usize,
+        scale: u32,
+    ) -> Result<Option<RenderPage>, RenderEngineError> {
+        let page = self.prepare_single_page(book, chapter_index, page_index)?;
+        let mut sources = Vec::with_capacity(1);
+        Ok(page.map(|page| self.export_page(book, &page, scale, &mut sources)))
+    }
+
+    /// Scale `page` and redraw its raster images from full-size sources,
+    /// decoding each resource once per export.
+    fn export_page<R: Read + Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+        page: &RenderPage,
+        scale: u32,
+        sources: &mut Vec<(String, Option<GrayBitmap>)>,
+    ) -> RenderPage {
+        let mut scaled = page.scaled(scale);
+        let intent = self.options().layout.render_intent;
+        if intent.draft || scale <= 1 {
+            return scaled;
+        }
+        let mut redrawn = false;
+        for cmd in &mut scaled.content_commands {
+            let DrawCommand::Image(image) = cmd else {
+                continue;
+            };
+            if image.bitmap.is_none() || image.src.is_empty() {
+                continue;
+            }
+            let index = match sources.iter().position(|(src, _)| *src == image.src) {
+                Some(index) => index,
+                None => {
+                    let bitmap = self.decode_resource(book, &image.src);
+                    sources.push((image.src.clone(), bitmap));
+                    sources.len() - 1
+                }
+            };
+            let Some(source) = sources[index].1.as_ref() else {
+                continue;
+            };
+            let mut bitmap = resize(source, image.width, image.height);
+            intent.dither_bitmap(&mut bitmap);
+            image.bitmap = Some(bitmap);
+            redrawn = true;
+        }
+        if redrawn {
+            scaled.sync_commands();
+        }
+        scaled
+    }
+}
+
+fn scale_rect(rect: &PageRect, scale: u32) -> PageRect {
+    PageRect::new(
+        rect.x * scale as i32,
+        rect.y * scale as i32,
+        rect.width * scale,
+        rect.height * scale,
+    )
+}
+
+fn scale_command(cmd: &DrawCommand, scale: u32) -> DrawCommand {
+    let px = |v: i32| v * scale as i32;
+    let len = |v: u32| v * scale;
+    let f = scale as f32;
+    match cmd {
+        DrawCommand::Text(text) => {
+            let mut style = text.style.clone();
+            style.size_px *= f;
+            style.letter_spacing *= f;
+            style.metrics = VerticalMetrics {
+                ascent_px: style.metrics.ascent_px * f,
+                descent_px: style.metrics.descent_px * f,
+                x_height_px: style.metrics.x_height_px * f,
+                cap_height_px: style.metrics.cap_height_px * f,
+            };
+            DrawCommand::Text(TextCommand {
+                x: px(text.x),
+                baseline_y: px(text.baseline_y),
+                text: text.text.clone(),
+                font_id: text.font_id,
+                style,
+                glyphs: text.glyphs.as_ref().map(|glyphs| {
+                    glyphs
+                        .iter()
+                        .map(|glyph| GlyphPosition {
+                            x_advance: px(glyph.x_advance),
+                            x_offset: px(glyph.x_offset),
+                        })
+                        .collect()
+                }),
+                hint: text.hint,
+            })
+        }
+        DrawCommand::Rule(rule) => DrawCommand::Rule(RuleCommand {
+            x: px(rule.x),
+            y: px(rule.y),
+            length: len(rule.length),
+            thickness: len(rule.thickness),
+            horizontal: rule.horizontal,
+        }),
+        DrawCommand::Rect(rect) => DrawCommand::Rect(RectCommand {
+            x: px(rect.x),
+            y: px(rect.y),
+            width: len(rect.width),
+            height: len(rect.height),
+            ..*rect
+        }),
+        DrawCommand::Image(image) => {
+            let width = len(image.width);
+            let height = len(image.height);
+            DrawCommand::Image(ImageCommand {
+                x: px(image.x),
+                y: px(image.y),
+                width,
+                height,
+                src: image.src.clone(),
+                alt: image.alt.clone(),
+                bitmap: image
+                    .bitmap
+                    .as_ref()
+                    .map(|bitmap| resize(bitmap, width, height)),
+            })
+        }
+        DrawCommand::PushClip(rect) => DrawCommand::PushClip(scale_rect(rect, scale)),
+        DrawCommand::PopClip => DrawCommand::PopClip,
+        DrawCommand::Layered(layered) => DrawCommand::Layered(LayeredCommand {
+            z: layered.z,
+            command: Box::new(scale_command(&layered.command, scale)),
+        }),
+        DrawCommand::PageChrome(chrome) => DrawCommand::PageChrome(chrome.clone()),
+    }
+}
+
+fn scale_overlay_item(item: &OverlayItem, scale: u32) -> OverlayItem {
+    let px = |v: i32| v * scale as i32;
+    let size = |size: OverlaySize| OverlaySize {
+        width: size.width * scale,
+        height: size.height * scale,
+    };
+    let slot = match item.slot {
+        OverlaySlot::Custom(rect) => OverlaySlot::Custom(OverlayRect {
+            x: px(rect.x),
+            y: px(rect.y),
+            width: rect.width * scale,
+            height: rect.height * scale,
+        }),
+        OverlaySlot::At { x, y, anchor } => OverlaySlot::At {
+            x: px(x),
+            y: px(y),
+            anchor,
+        },
+        OverlaySlot::Edge {
+            edge,
+            inset,
+            offset,
+            anchor,
+        } => OverlaySlot::Edge {
+            edge,
+            inset: px(inset),
+            offset: px(offset),
+            anchor,
+        },
+        ref slot => slot.clone(),
+    };
+    let content = match &item.content {
+        OverlayContent::Command(cmd) => OverlayContent::Command(scale_command(cmd, scale)),
+        OverlayContent::Image(image) => {
+            let mut image = image.clone();
+            image.size = size(image.size);
+            image.max_size = image.max_size.map(size);
+            OverlayContent::Image(image)
+        }
+        content => content.clone(),
+    };
+    OverlayItem {
+        slot,
+        z: item.z,
+        content,
+    }
+}
+
+fn scale_annotation(annotation: &PageAnnotation, scale: u32) -> PageAnnotation {
+    let mut annotation = annotation.clone();
+    match &mut annotation {
+        PageAnnotation::Link { rect, .. }
+        | PageAnnotation::ImageDescription { rect, .. }
+        | PageAnnotation::Heading { rect, .. }
+        | PageAnnotation::PendingImage { rect, .. } => *rect = scale_rect(rect, scale),
+        PageAnnotation::Tag { .. } => {}
+    }
+    annotation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_ir::{JustifyMode, ResolvedTextStyle, TextRenderHint};
+    use mu_epub::BlockRole;
+
+    #[test]
+    fn scaled_page_multiplies_geometry_and_keeps_text() {
+        let mut page = RenderPage::new(2);
+        page.push_content_command(DrawCommand::Text(TextCommand {
+            x: 12,
+            baseline_y: 40,
+            text: "hello".to_string(),
+            font_id: None,
+            style: ResolvedTextStyle {
+                font_id: None,
+                family: "serif".to_string(),
+                weight: 400,
+                italic: false,
+                size_px: 16.0,
+                line_height: 1.4,
+                letter_spacing: 0.5,
+                role: BlockRole::Body,
+                justify_mode: JustifyMode::None,
+                color: None,
+                metrics: VerticalMetrics::estimate(16.0),
+            },
+            glyphs: None,
+            hint: TextRenderHint::Aliased,
+        }));
+        page.push_content_command(DrawCommand::Image(ImageCommand {
+            x: 0,
+            y: 50,
+            width: 2,
+            height: 1,
+            src: String::with_capacity(0),
+            alt: String::with_capacity(0),
+            bitmap: Some(GrayBitmap {
+                width: 2,
+                height: 1,
+                pixels: vec![0, 255],
+            }),
+        }));
+        page.annotations.push(PageAnnotation::Heading {
+            level: 1,
+            text: "hello".to_string(),
+            rect: PageRect::new(12, 24, 50, 20),
+        });
+        page.sync_commands();
+
+        let scaled = page.scaled(3);
+        assert_eq!(scaled.page_number, 2);
+        assert_eq!(scaled.plain_text(), page.plain_text());
+        let DrawCommand::Text(text) = &scaled.commands[0] else {
+            panic!("text command expected");
+        };
+        assert_eq!((text.x, text.baseline_y), (36, 120));
+        assert_eq!(text.style.size_px, 48.0);
+        assert!((text.style.metrics.ascent_px - 38.4).abs() < 1e-3);
+        let DrawCommand::Image(image) = &scaled.commands[1] else {
+            panic!("image command expected");
+        };
+        let bitmap = image.bitmap.as_ref().expect("bitmap kept");
+        assert_eq!((image.width, bitmap.width, bitmap.height), (6, 6, 3));
+        assert_eq!(bitmap.pixel(2, 2), Some(0));
+        assert_eq!(bitmap.pixel(3, 0), Some(255));
+        assert_eq!(
+            scaled.annotations[0],
+            PageAnnotation::Heading {
+                level: 1,
+                text: "hello".to_string(),
+                rect: PageRect::new(36, 72, 150, 60),
+            }
+        );
+    }
+}
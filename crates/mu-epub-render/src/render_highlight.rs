@@ -0,0 +1,548 @@
+//! Text-range highlighting over laid-out pages.
+
+use core::ops::Range;
+
+use mu_epub::Color;
+
+use crate::render_ir::{
+    DrawCommand, GlyphPosition, LayeredCommand, OverlayContent, OverlayItem, OverlayRect,
+    OverlaySlot, PageRect, RectCommand, RenderPage, TextCommand,
+};
+use crate::render_layout::glyph_positions;
+
+/// Fill used for selection and search-hit highlights.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HighlightStyle {
+    /// Foreground fill with the covered text redrawn in white.
+    #[default]
+    Inverse,
+    /// Gray fill (`0` = black, `255` = white) under unchanged text.
+    Gray(u8),
+}
+
+/// Search match resolved to on-page geometry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchHit {
+    /// Char range of `RenderPage::plain_text` that matched.
+    pub range: Range<usize>,
+    /// Highlight rectangles, one per covered line span.
+    pub rects: Vec<PageRect>,
+}
+
+impl SearchHit {
+    /// Union of all hit rectangles (for scrolling or damage).
+    pub fn bounds(&self) -> Option<PageRect> {
+        self.rects
+            .iter()
+            .copied()
+            .reduce(|acc, rect| acc.union(&rect))
+    }
+}
+
+/// Text selected on a page, for copy, share, or annotate gestures.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextSelection {
+    /// Char range of `RenderPage::plain_text`.
+    pub range: Range<usize>,
+    /// Selected text, with `\n` between lines.
+    pub text: String,
+    /// Selection rectangles, one per covered line span.
+    pub rects: Vec<PageRect>,
+}
+
+impl RenderPage {
+    /// Plain text of the content layer, with `\n` between lines.
+    ///
+    /// Text commands sharing a line top are concatenated, so split runs read
+    /// as one line. Char offsets into this string address text for
+    /// `highlight_text` and `highlight_ranges`.
+    pub fn plain_text(&self) -> String {
+        let mut out = String::with_capacity(256);
+        let mut chars = 0usize;
+        for (start, text) in text_offsets(&self.content_commands) {
+            if chars < start {
+                out.push('\n');
+            }
+            out.push_str(&text.text);
+            chars = start + text.text.chars().count();
+        }
+        out
+    }
+
+    /// Highlight rectangles for char ranges of `plain_text`, without
+    /// modifying the page.
+    pub fn text_range_rects(&self, ranges: &[Range<usize>]) -> Vec<PageRect> {
+        let mut rects = Vec::with_capacity(ranges.len());
+        for (start, text) in text_offsets(&self.content_commands) {
+            let spans = local_spans(ranges, start, text.text.chars().count());
+            if !spans.is_empty() {
+                rects.extend(span_rects(text, &spans, &advances(text)));
+            }
+        }
+        rects
+    }
+
+    /// Caret offset into `plain_text` nearest to a page point.
+    ///
+    /// The nearest line is picked vertically, then the char boundary closest
+    /// to `x` on it. `None` when the page has no text.
+    pub fn text_offset_at(&self, x: i32, y: i32) -> Option<usize> {
+        let mut best: Option<(i32, i32)> = None;
+        for (_, text) in text_offsets(&self.content_commands) {
+            let bounds = text.approx_bounds();
+            let distance = if y < bounds.y {
+                bounds.y - y
+            } else {
+                (y - bounds.bottom() + 1).max(0)
+            };
+            if best.is_none_or(|(_, best_distance)| distance < best_distance) {
+                best = Some((text.baseline_y, distance));
+            }
+        }
+        let (line_top, _) = best?;
+        let mut caret = None;
+        for (start, text) in text_offsets(&self.content_commands) {
+            if text.baseline_y != line_top {
+                continue;
+            }
+            let prefix = prefix_advances(&advances(text));
+            let last = prefix.len() - 1;
+            if caret.is_none() || x >= text.x {
+                let local = prefix
+                    .windows(2)
+                    .position(|pair| x < text.x + (pair[0] + pair[1]) / 2)
+                    .unwrap_or(last);
+                caret = Some(start + local);
+            }
+        }
+        caret
+    }
+
+    /// Selection between two page points, in reading order.
+    ///
+    /// Points may be given in either order. `None` when they resolve to
+    /// the same caret.
+    pub fn select_between(&self, from: (i32, i32), to: (i32, i32)) -> Option<TextSelection> {
+        let a = self.text_offset_at(from.0, from.1)?;
+        let b = self.text_offset_at(to.0, to.1)?;
+        (a != b).then(|| self.text_selection(a.min(b)..a.max(b)))
+    }
+
+    /// Selection swept by a rectangle from its top-left to bottom-right.
+    pub fn select_rect(&self, rect: PageRect) -> Option<TextSelection> {
+        self.select_between(
+            (rect.x, rect.y),
+            (rect.right() - 1, rect.bottom().max(rect.y + 1) - 1),
+        )
+    }
+
+    /// Text and rectangles for a char range of `plain_text`.
+    pub fn text_selection(&self, range: Range<usize>) -> TextSelection {
+        TextSelection {
+            text: self
+                .plain_text()
+                .chars()
+                .skip(range.start)
+                .take(range.len())
+                .collect(),
+            rects: self.text_range_rects(core::slice::from_ref(&range)),
+            range,
+        }
+    }
+
+    /// Resolve search matches to hit geometry.
+    ///
+    /// Matches are char ranges of `plain_text`, as produced by a search over
+    /// it; hits keep input order and matches covering no text are dropped.
+    pub fn search_hits(&self, matches: &[Range<usize>]) -> Vec<SearchHit> {
+        matches
+            .iter()
+            .filter_map(|range| {
+                let rects = self.text_range_rects(core::slice::from_ref(range));
+                (!rects.is_empty()).then(|| SearchHit {
+                    range: range.clone(),
+                    rects,
+                })
+            })
+            .collect()
+    }
+
+    /// Copy of this page with search matches highlighted on the content layer.
+    pub fn with_search_highlights(
+        &self,
+        matches: &[Range<usize>],
+        style: HighlightStyle,
+    ) -> (RenderPage, Vec<SearchHit>) {
+        let hits = self.search_hits(matches);
+        let mut page = self.clone();
+        page.highlight_ranges(matches, style);
+        (page, hits)
+    }
+
+    /// Mark search matches on the overlay layer, leaving content untouched.
+    ///
+    /// Each hit rectangle becomes an outlined `RectCommand` overlay item at
+    /// `z`, so cached content pages can be reused across queries. Hit areas
+    /// are added to `damage`. Returns the resolved hits.
+    pub fn push_search_overlay(&mut self, matches: &[Range<usize>], z: i32) -> Vec<SearchHit> {
+        let hits = self.search_hits(matches);
+        for rect in hits.iter().flat_map(|hit| hit.rects.iter()) {
+            let cmd = DrawCommand::Rect(RectCommand {
+                x: rect.x,
+                y: rect.y,
+                width: rect.width,
+                height: rect.height,
+                fill: false,
+                color: None,
+            });
+            self.push_overlay_rect(*rect, z, cmd);
+        }
+        self.sync_commands();
+        hits
+    }
+
+    /// Attach `cmd` covering `rect` as an overlay item and layered overlay
+    /// command at `z`, adding `rect` to damage. Callers sync commands.
+    pub(crate) fn push_overlay_rect(&mut self, rect: PageRect, z: i32, cmd: DrawCommand) {
+        self.overlay_items.push(OverlayItem {
+            slot: OverlaySlot::Custom(OverlayRect {
+                x: rect.x,
+                y: rect.y,
+                width: rect.width,
+                height: rect.height,
+            }),
+            z,
+            content: OverlayContent::Command(cmd.clone()),
+        });
+        self.push_overlay_command(DrawCommand::Layered(LayeredCommand {
+            z,
+            command: Box::new(cmd),
+        }));
+        if let Some(damage) = self.damage.as_mut() {
+            damage.push(rect);
+        }
+    }
+
+    /// Highlight a char range of `plain_text`; see `highlight_ranges`.
+    pub fn highlight_text(&mut self, range: Range<usize>, style: HighlightStyle) -> Vec<PageRect> {
+        self.highlight_ranges(core::slice::from_ref(&range), style)
+    }
+
+    /// Highlight char ranges of `plain_text` on the content layer.
+    ///
+    /// Emits one filled `RectCommand` per covered line span directly before
+    /// its text command, so it draws underneath. With `Inverse`, covered text
+    /// is split out and recolored; split segments carry explicit glyph
+    /// positions to keep their laid-out placement. Returns the highlight
+    /// rectangles in page order.
+    pub fn highlight_ranges(
+        &mut self,
+        ranges: &[Range<usize>],
+        style: HighlightStyle,
+    ) -> Vec<PageRect> {
+        let starts: Vec<usize> = text_offsets(&self.content_commands)
+            .map(|(start, _)| start)
+            .collect();
+        let mut starts = starts.into_iter();
+        let mut rects = Vec::with_capacity(ranges.len());
+        let content = core::mem::take(&mut self.content_commands);
+        let mut out = Vec::with_capacity(content.len() + ranges.len() * 2);
+        for cmd in content {
+            let DrawCommand::Text(text) = cmd else {
+                out.push(cmd);
+                continue;
+            };
+            let start = starts.next().unwrap_or(0);
+            let spans = local_spans(ranges, start, text.text.chars().count());
+            if spans.is_empty() {
+                out.push(DrawCommand::Text(text));
+                continue;
+            }
+
+            let advances = advances(&text);
+            for rect in span_rects(&text, &spans, &advances) {
+                rects.push(rect);
+                out.push(DrawCommand::Rect(RectCommand {
+                    x: rect.x,
+                    y: rect.y,
+                    width: rect.width,
+                    height: rect.height,
+                    fill: true,
+                    color: match style {
+                        HighlightStyle::Inverse => None,
+                        HighlightStyle::Gray(level) => Some(Color::rgb(level, level, level)),
+                    },
+                }));
+            }
+            match style {
+                HighlightStyle::Inverse => split_inverse(&text, &spans, &advances, &mut out),
+                HighlightStyle::Gray(_) => out.push(DrawCommand::Text(text)),
+            }
+        }
+        self.content_commands = out;
+        self.sync_commands();
+        rects
+    }
+}
+
+/// Text commands paired with their start offset into `plain_text`.
+fn text_offsets(commands: &[DrawCommand]) -> impl Iterator<Item = (usize, &TextCommand)> {
+    let mut offset = 0usize;
+    let mut line_top = None;
+    commands.iter().filter_map(as_text).map(move |text| {
+        if line_top.is_some_and(|top| top != text.baseline_y) {
+            offset += 1;
+        }
+        line_top = Some(text.baseline_y);
+        let start = offset;
+        offset += text.text.chars().count();
+        (start, text)
+    })
+}
+
+fn as_text(cmd: &DrawCommand) -> Option<&TextCommand> {
+    match cmd {
+        DrawCommand::Text(text) => Some(text),
+        _ => None,
+    }
+}
+
+fn advances(text: &TextCommand) -> Vec<GlyphPosition> {
+    text.glyphs
+        .clone()
+        .unwrap_or_else(|| glyph_positions(&text.text, &text.style))
+}
+
+/// Pen offset before each char, plus the total advance.
+fn prefix_advances(advances: &[GlyphPosition]) -> Vec<i32> {
+    let mut prefix = Vec::with_capacity(advances.len() + 1);
+    prefix.push(0);
+    for glyph in advances {
+        let last = prefix.last().copied().unwrap_or(0);
+        prefix.push(last + glyph.x_advance);
+    }
+    prefix
+}
+
+/// Line-box rectangles covering `spans` of one text command.
+fn span_rects(
+    text: &TextCommand,
+    spans: &[(usize, usize)],
+    advances: &[GlyphPosition],
+) -> Vec<PageRect> {
+    let prefix = prefix_advances(advances);
+    let at = |idx: usize| prefix.get(idx).copied().unwrap_or(0);
+    let line_h = text.approx_bounds().height;
+    spans
+        .iter()
+        .map(|&(start, end)| {
+            PageRect::new(
+                text.x + at(start),
+                text.baseline_y,
+                (at(end) - at(start)).max(0) as u32,
+                line_h,
+            )
+        })
+        .collect()
+}
+
+/// Sorted, merged `[start, end)` char spans of `ranges` within one command.
+fn local_spans(ranges: &[Range<usize>], offset: usize, len: usize) -> Vec<(usize, usize)> {
+    let mut spans: Vec<(usize, usize)> = ranges
+        .iter()
+        .filter_map(|range| {
+            let start = range.start.max(offset);
+            let end = range.end.min(offset + len);
+            (start < end).then(|| (start - offset, end - offset))
+        })
+        .collect();
+    spans.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(spans.len());
+    for (start, end) in spans {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Split `text` at span edges, recoloring highlighted segments white.
+fn split_inverse(
+    text: &TextCommand,
+    spans: &[(usize, usize)],
+    advances: &[GlyphPosition],
+    out: &mut Vec<DrawCommand>,
+) {
+    let prefix = prefix_advances(advances);
+    let len = advances.len();
+    let mut edges = Vec::with_capacity(spans.len() * 2 + 2);
+    edges.push((0, false));
+    for &(start, end) in spans {
+        edges.push((start, true));
+        edges.push((end, false));
+    }
+    edges.push((len, false));
+    for pair in edges.windows(2) {
+        let ((start, selected), (end, _)) = (pair[0], pair[1]);
+        if start >= end {
+            continue;
+        }
+        let mut style = text.style.clone();
+        if selected {
+            style.color = Some(Color::WHITE);
+        }
+        out.push(DrawCommand::Text(TextCommand {
+            x: text.x + prefix.get(start).copied().unwrap_or(0),
+            baseline_y: text.baseline_y,
+            text: text.text.chars().skip(start).take(end - start).collect(),
+            font_id: text.font_id,
+            style,
+            glyphs: Some(advances[start..end].to_vec()),
+            hint: text.hint,
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_ir::{JustifyMode, ResolvedTextStyle, TextRenderHint, VerticalMetrics};
+    use mu_epub::BlockRole;
+
+    fn page_with_lines(lines: &[&str]) -> RenderPage {
+        let mut page = RenderPage::new(1);
+        for (idx, line) in lines.iter().enumerate() {
+            page.push_content_command(DrawCommand::Text(TextCommand {
+                x: 10,
+                baseline_y: 10 + idx as i32 * 30,
+                text: line.to_string(),
+                font_id: None,
+                style: ResolvedTextStyle {
+                    font_id: None,
+                    family: "serif".to_string(),
+                    weight: 400,
+                    italic: false,
+                    size_px: 20.0,
+                    line_height: 1.2,
+                    letter_spacing: 0.0,
+                    role: BlockRole::Body,
+                    justify_mode: JustifyMode::None,
+                    color: None,
+                    metrics: VerticalMetrics::estimate(20.0),
+                },
+                glyphs: None,
+                hint: TextRenderHint::Aliased,
+            }));
+        }
+        page.sync_commands();
+        page
+    }
+
+    #[test]
+    fn gray_highlight_spans_lines_under_text() {
+        let mut page = page_with_lines(&["alpha beta", "gamma"]);
+        assert_eq!(page.plain_text(), "alpha beta\ngamma");
+
+        let rects = page.highlight_text(6..13, HighlightStyle::Gray(192));
+        assert_eq!(rects.len(), 2);
+        assert!(rects[0].x > 10);
+        assert_eq!(rects[0].y, 10);
+        assert_eq!((rects[1].x, rects[1].y), (10, 40));
+
+        let kinds: Vec<&str> = page
+            .content_commands
+            .iter()
+            .map(|cmd| match cmd {
+                DrawCommand::Rect(_) => "rect",
+                DrawCommand::Text(_) => "text",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(kinds, vec!["rect", "text", "rect", "text"]);
+        assert_eq!(page.commands, page.content_commands);
+    }
+
+    #[test]
+    fn inverse_highlight_splits_and_recolors_selected_text() {
+        let mut page = page_with_lines(&["alpha beta gamma"]);
+        let rects = page.highlight_text(6..10, HighlightStyle::Inverse);
+        assert_eq!(rects.len(), 1);
+
+        let texts: Vec<&TextCommand> = page.content_commands.iter().filter_map(as_text).collect();
+        let parts: Vec<(&str, Option<Color>)> = texts
+            .iter()
+            .map(|t| (t.text.as_str(), t.style.color))
+            .collect();
+        assert_eq!(
+            parts,
+            vec![
+                ("alpha ", None),
+                ("beta", Some(Color::WHITE)),
+                (" gamma", None)
+            ]
+        );
+        assert_eq!(texts[1].x, rects[0].x);
+        assert_eq!(page.plain_text(), "alpha beta gamma");
+    }
+
+    #[test]
+    fn search_overlay_marks_hits_without_touching_content() {
+        let mut page = page_with_lines(&["find me", "and find me too"]);
+        page.damage = Some(Vec::with_capacity(0));
+        let content = page.content_commands.clone();
+        let text = page.plain_text();
+        let matches: Vec<Range<usize>> = text
+            .match_indices("find")
+            .map(|(byte, hit)| {
+                let start = text[..byte].chars().count();
+                start..start + hit.chars().count()
+            })
+            .chain(core::iter::once(200..210))
+            .collect();
+
+        let hits = page.push_search_overlay(&matches, 10);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].range, 0..4);
+        assert_eq!(hits[1].bounds().map(|r| r.y), Some(40));
+        assert_eq!(page.content_commands, content);
+        assert_eq!(page.overlay_items.len(), 2);
+        assert_eq!(page.damage.as_ref().map(Vec::len), Some(2));
+        assert!(matches!(
+            page.commands.last(),
+            Some(DrawCommand::Layered(LayeredCommand { z: 10, .. }))
+        ));
+
+        let (highlighted, copy_hits) =
+            page.with_search_highlights(&matches, HighlightStyle::Gray(200));
+        assert_eq!(copy_hits, hits);
+        assert_eq!(highlighted.content_commands.len(), content.len() + 2);
+    }
+
+    #[test]
+    fn points_and_sweeps_select_text_in_reading_order() {
+        let page = page_with_lines(&["alpha beta", "gamma delta"]);
+        // 20px serif estimates 9px per char.
+        let char_w = 9;
+        assert_eq!(page.text_offset_at(0, 0), Some(0));
+        assert_eq!(page.text_offset_at(500, 15), Some(10));
+        assert_eq!(page.text_offset_at(10 + 6 * char_w, 45), Some(17));
+
+        let forward = page
+            .select_between((10 + 6 * char_w, 15), (10 + 5 * char_w, 45))
+            .expect("selection");
+        let backward = page
+            .select_between((10 + 5 * char_w, 45), (10 + 6 * char_w, 15))
+            .expect("selection");
+        assert_eq!(forward, backward);
+        assert_eq!(forward.range, 6..16);
+        assert_eq!(forward.text, "beta\ngamma");
+        assert_eq!(forward.rects.len(), 2);
+        assert_eq!(page.select_between((0, 0), (0, 0)), None);
+
+        let swept = page
+            .select_rect(PageRect::new(10, 10, 5 * char_w as u32, 1))
+            .expect("sweep");
+        assert_eq!(swept.text, "alpha");
+    }
+}
@@ -0,0 +1,1226 @@
+//! Raster image decoding for page image commands.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+
+use mu_epub::memory::{MemoryGauge, MemorySubsystem};
+
+use crate::render_cache::BookFingerprint;
+use crate::render_ir::{
+    DrawCommand, GrayBitmap, GrayscaleMode, GrayscaleWeights, ImageScaleFilter, PageAnnotation,
+    PageRect, RenderIntent, RenderPage,
+};
+
+/// Raster format decoder plugged into an `ImageDecoderRegistry`.
+///
+/// Decoders produce 8-bit luma; color is converted by luminance and alpha
+/// is flattened onto white.
+pub trait ImageDecoder: Send + Sync {
+    /// Whether `bytes` start with this decoder's signature.
+    fn sniff(&self, bytes: &[u8]) -> bool;
+
+    /// Pixel size read from the header, without decoding pixels.
+    fn dimensions(&self, bytes: &[u8]) -> Option<(u32, u32)>;
+
+    /// Decode to a grayscale bitmap; `None` on malformed input.
+    fn decode(&self, bytes: &[u8]) -> Option<GrayBitmap>;
+
+    /// Decode shrunk to fit within `max_width`x`max_height`, keeping the
+    /// aspect ratio.
+    ///
+    /// Defaults to `decode` followed by `options.filter`, ignoring
+    /// `options.grayscale`; strip decoders override it so the full-size
+    /// image is never held.
+    fn decode_within(
+        &self,
+        bytes: &[u8],
+        max_width: i32,
+        max_height: i32,
+        options: ImageDecodeOptions,
+    ) -> Option<GrayBitmap> {
+        Some(fit_within(
+            self.decode(bytes)?,
+            max_width,
+            max_height,
+            options.filter,
+        ))
+    }
+
+    /// Whether `decode_within` holds only strips of the source image, so
+    /// `image_max_decoded_pixels` does not apply to it.
+    fn decodes_in_strips(&self) -> bool {
+        false
+    }
+}
+
+/// How `ImageDecoder::decode_within` scales and converts an image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageDecodeOptions {
+    /// Filter used to shrink the image.
+    pub filter: ImageScaleFilter,
+    /// Color to gray conversion; `Off` converts like `Luminosity`.
+    pub grayscale: GrayscaleMode,
+}
+
+impl Default for ImageDecodeOptions {
+    fn default() -> Self {
+        Self {
+            filter: ImageScaleFilter::Box,
+            grayscale: GrayscaleMode::Luminosity,
+        }
+    }
+}
+
+/// Ordered set of image decoders used by `RenderEngine`.
+///
+/// The first decoder whose `sniff` accepts a resource decodes it.
+#[derive(Clone, Default)]
+pub struct ImageDecoderRegistry {
+    decoders: Vec<Arc<dyn ImageDecoder>>,
+}
+
+impl ImageDecoderRegistry {
+    /// Registry with no decoders; images keep `bitmap: None`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decoders compiled into this build: binary PGM/PPM always, baseline
+    /// JPEG with `jpeg-baseline`, PNG in strips with `png-strip`, PNG and
+    /// JPEG with `image-decode`, WebP with `webp`.
+    pub fn builtin() -> Self {
+        let registry = Self::new().with_decoder(NetpbmDecoder);
+        #[cfg(feature = "jpeg-baseline")]
+        let registry = registry.with_decoder(crate::render_jpeg::StripJpegDecoder::default());
+        #[cfg(feature = "png-strip")]
+        let registry = registry.with_decoder(crate::render_png::StripPngDecoder::default());
+        #[cfg(feature = "image-decode")]
+        let registry = registry
+            .with_decoder(codecs::PngDecoder)
+            .with_decoder(codecs::JpegDecoder);
+        #[cfg(feature = "webp")]
+        let registry = registry.with_decoder(webp::WebpDecoder);
+        registry
+    }
+
+    /// Add a decoder, tried after the ones already registered.
+    pub fn with_decoder<D: ImageDecoder + 'static>(mut self, decoder: D) -> Self {
+        self.decoders.push(Arc::new(decoder));
+        self
+    }
+
+    /// Whether no decoders are registered.
+    pub fn is_empty(&self) -> bool {
+        self.decoders.is_empty()
+    }
+
+    /// Decoder accepting `bytes`, if any.
+    pub fn find(&self, bytes: &[u8]) -> Option<&dyn ImageDecoder> {
+        self.decoders
+            .iter()
+            .find(|decoder| decoder.sniff(bytes))
+            .map(|decoder| decoder.as_ref())
+    }
+}
+
+impl fmt::Debug for ImageDecoderRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImageDecoderRegistry")
+            .field("decoders", &self.decoders.len())
+            .finish()
+    }
+}
+
+/// Binary netpbm decoder: `P5` graymaps and `P6` pixmaps up to 8 bits.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NetpbmDecoder;
+
+impl NetpbmDecoder {
+    /// `(channels, width, height, maxval, data offset)` from the header.
+    fn header(bytes: &[u8]) -> Option<(usize, u32, u32, u32, usize)> {
+        let channels = match bytes.get(..2)? {
+            b"P5" => 1,
+            b"P6" => 3,
+            _ => return None,
+        };
+        let mut pos = 2;
+        let mut fields = [0u32; 3];
+        for field in &mut fields {
+            loop {
+                match bytes.get(pos)? {
+                    b'#' => {
+                        while *bytes.get(pos)? != b'\n' {
+                            pos += 1;
+                        }
+                    }
+                    ch if ch.is_ascii_whitespace() => pos += 1,
+                    _ => break,
+                }
+            }
+            let start = pos;
+            while bytes.get(pos).is_some_and(u8::is_ascii_digit) {
+                pos += 1;
+            }
+            *field = core::str::from_utf8(&bytes[start..pos])
+                .ok()?
+                .parse()
+                .ok()?;
+        }
+        let [width, height, maxval] = fields;
+        if width == 0 || height == 0 || maxval == 0 || maxval > 255 {
+            return None;
+        }
+        // One whitespace byte separates the header from the samples.
+        Some((channels, width, height, maxval, pos + 1))
+    }
+}
+
+impl ImageDecoder for NetpbmDecoder {
+    fn sniff(&self, bytes: &[u8]) -> bool {
+        matches!(bytes.get(..2), Some(b"P5" | b"P6"))
+    }
+
+    fn dimensions(&self, bytes: &[u8]) -> Option<(u32, u32)> {
+        let (_, width, height, _, _) = Self::header(bytes)?;
+        Some((width, height))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Option<GrayBitmap> {
+        self.decode_within(bytes, i32::MAX, i32::MAX, ImageDecodeOptions::default())
+    }
+
+    fn decode_within(
+        &self,
+        bytes: &[u8],
+        max_width: i32,
+        max_height: i32,
+        options: ImageDecodeOptions,
+    ) -> Option<GrayBitmap> {
+        let (channels, width, height, maxval, offset) = Self::header(bytes)?;
+        let stride = width as usize * channels;
+        let samples = bytes.get(offset..offset.checked_add(stride * height as usize)?)?;
+        let scale = |value: u8| (value as u32 * 255 / maxval).min(255) as u8;
+        let (out_width, out_height) = fit_size(width, height, max_width, max_height);
+        let mut scaler = RowDownscaler::new(width, height, out_width, out_height, options.filter);
+        let gray = LumaConverter::new(options.grayscale);
+        let mut row = Vec::with_capacity(width as usize);
+        for samples in samples.chunks_exact(stride) {
+            row.clear();
+            row.extend(samples.chunks_exact(channels).map(|px| match *px {
+                [r, g, b] => gray.luma(scale(r), scale(g), scale(b)),
+                _ => scale(px[0]),
+            }));
+            scaler.push_row(&row);
+        }
+        scaler.finish()
+    }
+
+    fn decodes_in_strips(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "image-decode")]
+mod codecs {
+    use super::{
+        fit_within, flatten_alpha, ExifOrientation, ImageDecodeOptions, ImageDecoder, LumaConverter,
+    };
+    use crate::render_ir::{GrayBitmap, GrayscaleMode};
+
+    /// PNG decoder (any color type, 16-bit reduced to 8).
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct PngDecoder;
+
+    impl PngDecoder {
+        fn decode_gray(bytes: &[u8], gray: &LumaConverter) -> Option<GrayBitmap> {
+            let mut decoder = png::Decoder::new(bytes);
+            decoder
+                .set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+            let mut reader = decoder.read_info().ok()?;
+            let mut buf = vec![0; reader.output_buffer_size()];
+            let frame = reader.next_frame(&mut buf).ok()?;
+            let samples = buf.get(..frame.buffer_size())?;
+            let pixels = match frame.color_type {
+                png::ColorType::Grayscale => samples.to_vec(),
+                png::ColorType::GrayscaleAlpha => samples
+                    .chunks_exact(2)
+                    .map(|px| flatten_alpha(px[0], px[1]))
+                    .collect(),
+                png::ColorType::Rgb => samples
+                    .chunks_exact(3)
+                    .map(|px| gray.luma(px[0], px[1], px[2]))
+                    .collect(),
+                png::ColorType::Rgba => samples
+                    .chunks_exact(4)
+                    .map(|px| flatten_alpha(gray.luma(px[0], px[1], px[2]), px[3]))
+                    .collect(),
+                png::ColorType::Indexed => return None,
+            };
+            Some(GrayBitmap {
+                width: frame.width,
+                height: frame.height,
+                pixels,
+            })
+        }
+    }
+
+    impl ImageDecoder for PngDecoder {
+        fn sniff(&self, bytes: &[u8]) -> bool {
+            bytes.starts_with(b"\x89PNG\r\n\x1a\n")
+        }
+
+        fn dimensions(&self, bytes: &[u8]) -> Option<(u32, u32)> {
+            let reader = png::Decoder::new(bytes).read_info().ok()?;
+            let info = reader.info();
+            Some((info.width, info.height))
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Option<GrayBitmap> {
+            Self::decode_gray(bytes, &LumaConverter::new(GrayscaleMode::Luminosity))
+        }
+
+        fn decode_within(
+            &self,
+            bytes: &[u8],
+            max_width: i32,
+            max_height: i32,
+            options: ImageDecodeOptions,
+        ) -> Option<GrayBitmap> {
+            let bitmap = Self::decode_gray(bytes, &LumaConverter::new(options.grayscale))?;
+            Some(fit_within(bitmap, max_width, max_height, options.filter))
+        }
+    }
+
+    /// Baseline and progressive JPEG decoder (8-bit gray, RGB, CMYK),
+    /// turned upright per its EXIF orientation.
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct JpegDecoder;
+
+    impl JpegDecoder {
+        fn decode_gray(bytes: &[u8], gray: &LumaConverter) -> Option<GrayBitmap> {
+            let mut decoder = jpeg_decoder::Decoder::new(bytes);
+            let samples = decoder.decode().ok()?;
+            let info = decoder.info()?;
+            let pixels = match info.pixel_format {
+                jpeg_decoder::PixelFormat::L8 => samples,
+                jpeg_decoder::PixelFormat::RGB24 => samples
+                    .chunks_exact(3)
+                    .map(|px| gray.luma(px[0], px[1], px[2]))
+                    .collect(),
+                jpeg_decoder::PixelFormat::CMYK32 => samples
+                    .chunks_exact(4)
+                    .map(|px| {
+                        let ink = |c: u8| ((255 - c as u32) * (255 - px[3] as u32) / 255) as u8;
+                        gray.luma(ink(px[0]), ink(px[1]), ink(px[2]))
+                    })
+                    .collect(),
+                jpeg_decoder::PixelFormat::L16 => return None,
+            };
+            let bitmap = GrayBitmap {
+                width: info.width as u32,
+                height: info.height as u32,
+                pixels,
+            };
+            Some(ExifOrientation::from_jpeg(bytes).apply(bitmap))
+        }
+    }
+
+    impl ImageDecoder for JpegDecoder {
+        fn sniff(&self, bytes: &[u8]) -> bool {
+            bytes.starts_with(&[0xFF, 0xD8, 0xFF])
+        }
+
+        fn dimensions(&self, bytes: &[u8]) -> Option<(u32, u32)> {
+            let mut decoder = jpeg_decoder::Decoder::new(bytes);
+            decoder.read_info().ok()?;
+            let info = decoder.info()?;
+            Some(
+                ExifOrientation::from_jpeg(bytes)
+                    .transpose_size(info.width as u32, info.height as u32),
+            )
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Option<GrayBitmap> {
+            Self::decode_gray(bytes, &LumaConverter::new(GrayscaleMode::Luminosity))
+        }
+
+        fn decode_within(
+            &self,
+            bytes: &[u8],
+            max_width: i32,
+            max_height: i32,
+            options: ImageDecodeOptions,
+        ) -> Option<GrayBitmap> {
+            let bitmap = Self::decode_gray(bytes, &LumaConverter::new(options.grayscale))?;
+            Some(fit_within(bitmap, max_width, max_height, options.filter))
+        }
+    }
+}
+
+#[cfg(feature = "image-decode")]
+pub use codecs::{JpegDecoder, PngDecoder};
+
+#[cfg(feature = "webp")]
+mod webp {
+    use std::io::Cursor;
+
+    use super::{fit_within, flatten_alpha, ImageDecodeOptions, ImageDecoder, LumaConverter};
+    use crate::render_ir::{GrayBitmap, GrayscaleMode};
+
+    /// Lossy (VP8) and lossless (VP8L) WebP decoder; animations decode
+    /// their first frame.
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct WebpDecoder;
+
+    impl WebpDecoder {
+        fn decode_gray(bytes: &[u8], gray: &LumaConverter) -> Option<GrayBitmap> {
+            let mut decoder = image_webp::WebPDecoder::new(Cursor::new(bytes)).ok()?;
+            let (width, height) = decoder.dimensions();
+            let mut buf = vec![0; decoder.output_buffer_size()?];
+            decoder.read_image(&mut buf).ok()?;
+            let pixels = if decoder.has_alpha() {
+                buf.chunks_exact(4)
+                    .map(|px| flatten_alpha(gray.luma(px[0], px[1], px[2]), px[3]))
+                    .collect()
+            } else {
+                buf.chunks_exact(3)
+                    .map(|px| gray.luma(px[0], px[1], px[2]))
+                    .collect()
+            };
+            Some(GrayBitmap {
+                width,
+                height,
+                pixels,
+            })
+        }
+    }
+
+    impl ImageDecoder for WebpDecoder {
+        fn sniff(&self, bytes: &[u8]) -> bool {
+            bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP"
+        }
+
+        fn dimensions(&self, bytes: &[u8]) -> Option<(u32, u32)> {
+            let decoder = image_webp::WebPDecoder::new(Cursor::new(bytes)).ok()?;
+            Some(decoder.dimensions())
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Option<GrayBitmap> {
+            Self::decode_gray(bytes, &LumaConverter::new(GrayscaleMode::Luminosity))
+        }
+
+        fn decode_within(
+            &self,
+            bytes: &[u8],
+            max_width: i32,
+            max_height: i32,
+            options: ImageDecodeOptions,
+        ) -> Option<GrayBitmap> {
+            let bitmap = Self::decode_gray(bytes, &LumaConverter::new(options.grayscale))?;
+            Some(fit_within(bitmap, max_width, max_height, options.filter))
+        }
+    }
+}
+
+#[cfg(feature = "webp")]
+pub use webp::WebpDecoder;
+
+pub(crate) fn luma(r: u8, g: u8, b: u8) -> u8 {
+    mu_epub::Color::rgb(r, g, b).luma()
+}
+
+/// RGB to gray conversion for one decode, with the transfer curve of
+/// weighted modes tabled per channel value.
+pub(crate) struct LumaConverter {
+    weights: Option<GrayscaleWeights>,
+    linear: [f32; 256],
+}
+
+impl LumaConverter {
+    pub(crate) fn new(mode: GrayscaleMode) -> Self {
+        let weights = match mode {
+            GrayscaleMode::Weighted(weights) => Some(weights),
+            GrayscaleMode::Off | GrayscaleMode::Luminosity => None,
+        };
+        let mut linear = [0.0; 256];
+        if let Some(weights) = weights {
+            for (value, slot) in linear.iter_mut().enumerate() {
+                *slot = weights.linearize(value as u8);
+            }
+        }
+        Self { weights, linear }
+    }
+
+    /// Same result as `GrayscaleWeights::luma`, or `Color::luma`.
+    pub(crate) fn luma(&self, r: u8, g: u8, b: u8) -> u8 {
+        match self.weights {
+            Some(_) if r == g && g == b => r,
+            Some(weights) => weights.mix([r, g, b].map(|channel| self.linear[channel as usize])),
+            None => luma(r, g, b),
+        }
+    }
+}
+
+/// Composite `value` at opacity `alpha` over white.
+#[cfg_attr(
+    not(any(feature = "image-decode", feature = "png-strip", feature = "webp")),
+    allow(dead_code)
+)]
+pub(crate) fn flatten_alpha(value: u8, alpha: u8) -> u8 {
+    ((value as u32 * alpha as u32 + 255 * (255 - alpha as u32)) / 255) as u8
+}
+
+/// Shrink `bitmap` to fit within `max_width`x`max_height`, keeping its
+/// aspect ratio.
+pub(crate) fn fit_within(
+    bitmap: GrayBitmap,
+    max_width: i32,
+    max_height: i32,
+    filter: ImageScaleFilter,
+) -> GrayBitmap {
+    let (width, height) = fit_size(bitmap.width, bitmap.height, max_width, max_height);
+    if (width, height) == (bitmap.width, bitmap.height) {
+        return bitmap;
+    }
+    match filter {
+        ImageScaleFilter::Box => bitmap.downsample(width, height),
+        ImageScaleFilter::Bilinear => {
+            let mut scaler = RowDownscaler::new(bitmap.width, bitmap.height, width, height, filter);
+            for row in bitmap.pixels.chunks_exact(bitmap.width as usize) {
+                scaler.push_row(row);
+            }
+            scaler.finish().unwrap_or(bitmap)
+        }
+    }
+}
+
+/// Size `fit_within` shrinks a `width`x`height` image to.
+pub(crate) fn fit_size(width: u32, height: u32, max_width: i32, max_height: i32) -> (u32, u32) {
+    let max_width = max_width.max(1) as f32;
+    let max_height = max_height.max(1) as f32;
+    let scale = (max_width / width as f32)
+        .min(max_height / height as f32)
+        .min(1.0);
+    if scale >= 1.0 {
+        return (width, height);
+    }
+    (
+        (width as f32 * scale).round().max(1.0) as u32,
+        (height as f32 * scale).round().max(1.0) as u32,
+    )
+}
+
+/// Downscaler fed one source row at a time.
+///
+/// `Box` produces the pixels of `GrayBitmap::downsample` while holding one
+/// row of column sums; `Bilinear` holds the last two source rows resampled
+/// to the output width. Neither keeps the full-size image.
+pub(crate) struct RowDownscaler {
+    src_width: u32,
+    src_height: u32,
+    filter: ImageScaleFilter,
+    out: GrayBitmap,
+    /// Box: column sums of the current band. Bilinear: the latest source
+    /// row resampled horizontally, in 1/256ths.
+    sums: Vec<u64>,
+    /// Bilinear: the source row before the one in `sums`.
+    prev: Vec<u64>,
+    /// Source rows summed into the output row being built.
+    rows: u32,
+    src_y: u32,
+}
+
+impl RowDownscaler {
+    /// Downscaler from `src_width`x`src_height` to `width`x`height`,
+    /// clamped like `GrayBitmap::downsample`.
+    pub(crate) fn new(
+        src_width: u32,
+        src_height: u32,
+        width: u32,
+        height: u32,
+        filter: ImageScaleFilter,
+    ) -> Self {
+        let width = width.min(src_width).max(1);
+        let height = height.min(src_height).max(1);
+        let prev = match filter {
+            ImageScaleFilter::Box => Vec::with_capacity(0),
+            ImageScaleFilter::Bilinear => vec![0; width as usize],
+        };
+        Self {
+            src_width,
+            src_height,
+            filter,
+            out: GrayBitmap {
+                width,
+                height,
+                pixels: Vec::with_capacity(width as usize * height as usize),
+            },
+            sums: vec![0; width as usize],
+            prev,
+            rows: 0,
+            src_y: 0,
+        }
+    }
+
+    /// Source columns averaged into output column `x`.
+    fn columns(&self, x: u32) -> (usize, usize) {
+        let (src, out) = (self.src_width as u64, self.out.width as u64);
+        let x0 = x as u64 * src / out;
+        let x1 = ((x as u64 + 1) * src / out).max(x0 + 1);
+        (x0 as usize, x1 as usize)
+    }
+
+    /// Add the next source row; rows past `src_height` are ignored.
+    pub(crate) fn push_row(&mut self, row: &[u8]) {
+        if self.src_y >= self.src_height {
+            return;
+        }
+        match self.filter {
+            ImageScaleFilter::Box => self.push_box_row(row),
+            ImageScaleFilter::Bilinear => self.push_bilinear_row(row),
+        }
+    }
+
+    fn push_box_row(&mut self, row: &[u8]) {
+        for x in 0..self.out.width {
+            let (x0, x1) = self.columns(x);
+            let sum: u64 = row
+                .get(x0..x1)
+                .map_or(0, |px| px.iter().map(|&v| v as u64).sum());
+            self.sums[x as usize] += sum;
+        }
+        self.rows += 1;
+        self.src_y += 1;
+        let out_y = self.out.pixels.len() as u64 / self.out.width as u64;
+        let (src, out) = (self.src_height as u64, self.out.height as u64);
+        let y0 = out_y * src / out;
+        let y1 = ((out_y + 1) * src / out).max(y0 + 1);
+        if (self.src_y as u64) < y1 {
+            return;
+        }
+        for x in 0..self.out.width {
+            let (x0, x1) = self.columns(x);
+            let count = (x1 - x0) as u64 * self.rows as u64;
+            let sum = core::mem::take(&mut self.sums[x as usize]);
+            self.out
+                .pixels
+                .push(sum.checked_div(count).unwrap_or(255) as u8);
+        }
+        self.rows = 0;
+    }
+
+    fn push_bilinear_row(&mut self, row: &[u8]) {
+        core::mem::swap(&mut self.prev, &mut self.sums);
+        for x in 0..self.out.width {
+            let (x0, x1, weight) = bilinear_taps(x, self.src_width, self.out.width);
+            let left = row.get(x0 as usize).copied().unwrap_or(255) as u64;
+            let right = row.get(x1 as usize).copied().unwrap_or(255) as u64;
+            self.sums[x as usize] = left * (256 - weight) + right * weight;
+        }
+        let current = self.src_y;
+        self.src_y += 1;
+        let len = self.out.width as usize * self.out.height as usize;
+        while self.out.pixels.len() < len {
+            let out_y = (self.out.pixels.len() / self.out.width as usize) as u32;
+            let (y0, y1, weight) = bilinear_taps(out_y, self.src_height, self.out.height);
+            if y1 > current {
+                break;
+            }
+            let top = if y0 < current { &self.prev } else { &self.sums };
+            for (&top, &bottom) in top.iter().zip(&self.sums) {
+                let value = (top * (256 - weight) + bottom * weight + (1 << 15)) >> 16;
+                self.out.pixels.push(value.min(255) as u8);
+            }
+        }
+    }
+
+    /// The downscaled image, once every source row was pushed.
+    pub(crate) fn finish(self) -> Option<GrayBitmap> {
+        (self.out.pixels.len() == self.out.width as usize * self.out.height as usize)
+            .then_some(self.out)
+    }
+}
+
+/// Source samples `(i0, i1, weight of i1 in 1/256ths)` interpolated for
+/// output sample `i` when resampling `src` samples to `out`, aligning
+/// pixel centers.
+fn bilinear_taps(i: u32, src: u32, out: u32) -> (u32, u32, u64) {
+    let center =
+        ((2 * i as u64 + 1) * src as u64 * 256 / (2 * out.max(1) as u64)).saturating_sub(128);
+    let i0 = ((center >> 8) as u32).min(src.saturating_sub(1));
+    let i1 = (i0 + 1).min(src.saturating_sub(1));
+    let weight = if i1 > i0 { center & 0xFF } else { 0 };
+    (i0, i1, weight)
+}
+
+/// EXIF orientation of a JPEG (TIFF tag `0x0112`), `1..=8`.
+///
+/// Describes how the stored pixels map onto the upright picture: `1` is
+/// upright, `2..=4` mirror or turn half way, `5..=8` swap the axes.
+#[cfg_attr(
+    not(any(feature = "image-decode", feature = "jpeg-baseline")),
+    allow(dead_code)
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ExifOrientation(u8);
+
+#[cfg_attr(
+    not(any(feature = "image-decode", feature = "jpeg-baseline")),
+    allow(dead_code)
+)]
+impl ExifOrientation {
+    /// Stored pixels are already upright.
+    pub(crate) const UPRIGHT: Self = Self(1);
+
+    /// Orientation declared in the EXIF `APP1` segment of JPEG `bytes`;
+    /// upright when absent or malformed.
+    pub(crate) fn from_jpeg(bytes: &[u8]) -> Self {
+        let mut pos = 2;
+        while let Some(&[0xFF, marker, hi, lo]) = bytes.get(pos..pos + 4) {
+            if matches!(marker, 0xD9 | 0xDA) {
+                break;
+            }
+            let len = u16::from_be_bytes([hi, lo]) as usize;
+            let Some(segment) = bytes.get(pos + 4..pos + 2 + len) else {
+                break;
+            };
+            if marker == 0xE1 {
+                if let Some(tiff) = segment.strip_prefix(b"Exif\0\0") {
+                    return tiff_orientation(tiff).map_or(Self::UPRIGHT, Self);
+                }
+            }
+            pos += 2 + len;
+        }
+        Self::UPRIGHT
+    }
+
+    /// Whether the upright picture is the stored image turned a quarter.
+    pub(crate) fn swaps_axes(self) -> bool {
+        matches!(self.0, 5..=8)
+    }
+
+    /// Upright size of a `width`x`height` stored image, and the stored
+    /// size of a `width`x`height` upright one.
+    pub(crate) fn transpose_size(self, width: u32, height: u32) -> (u32, u32) {
+        if self.swaps_axes() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Rotate and flip stored pixels upright.
+    pub(crate) fn apply(self, bitmap: GrayBitmap) -> GrayBitmap {
+        if self == Self::UPRIGHT {
+            return bitmap;
+        }
+        let (w, h) = (bitmap.width, bitmap.height);
+        let (width, height) = self.transpose_size(w, h);
+        let mut pixels = Vec::with_capacity(bitmap.pixels.len());
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = match self.0 {
+                    2 => (w - 1 - x, y),
+                    3 => (w - 1 - x, h - 1 - y),
+                    4 => (x, h - 1 - y),
+                    5 => (y, x),
+                    6 => (y, h - 1 - x),
+                    7 => (w - 1 - y, h - 1 - x),
+                    8 => (w - 1 - y, x),
+                    _ => (x, y),
+                };
+                pixels.push(bitmap.pixels[sy as usize * w as usize + sx as usize]);
+            }
+        }
+        GrayBitmap {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// Orientation tag of the first IFD of a TIFF header, when valid.
+#[cfg_attr(
+    not(any(feature = "image-decode", feature = "jpeg-baseline")),
+    allow(dead_code)
+)]
+fn tiff_orientation(tiff: &[u8]) -> Option<u8> {
+    let big_endian = match tiff.get(..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let u16_at = |pos: usize| {
+        let bytes = [*tiff.get(pos)?, *tiff.get(pos + 1)?];
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+    let u32_at = |pos: usize| {
+        let high = u16_at(pos)? as u32;
+        let low = u16_at(pos + 2)? as u32;
+        Some(if big_endian {
+            high << 16 | low
+        } else {
+            low << 16 | high
+        })
+    };
+    if u16_at(2)? != 42 {
+        return None;
+    }
+    let ifd = u32_at(4)? as usize;
+    let count = u16_at(ifd)? as usize;
+    (0..count.min(256)).find_map(|i| {
+        let entry = ifd + 2 + i * 12;
+        if u16_at(entry)? != 0x0112 || u16_at(entry + 2)? != 3 {
+            return None;
+        }
+        let value = u16_at(entry + 8)?;
+        (1..=8).contains(&value).then_some(value as u8)
+    })
+}
+
+/// Resample to exactly `width`x`height`: box filter when shrinking,
+/// nearest neighbour when growing.
+pub(crate) fn resize(bitmap: &GrayBitmap, width: u32, height: u32) -> GrayBitmap {
+    if width <= bitmap.width && height <= bitmap.height {
+        return bitmap.downsample(width, height);
+    }
+    let width = width.max(1);
+    let height = height.max(1);
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+    for y in 0..height {
+        let sy = (y as u64 * bitmap.height as u64 / height as u64) as u32;
+        for x in 0..width {
+            let sx = (x as u64 * bitmap.width as u64 / width as u64) as u32;
+            pixels.push(bitmap.pixel(sx, sy).unwrap_or(255));
+        }
+    }
+    GrayBitmap {
+        width,
+        height,
+        pixels,
+    }
+}
+
+/// Identity of a decoded image: the book and resource it came from and
+/// everything its pixels depend on.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ImageCacheKey {
+    pub(crate) book: BookFingerprint,
+    pub(crate) src: String,
+    /// Box the image was shrunk to fit.
+    pub(crate) target: (i32, i32),
+    pub(crate) filter: ImageScaleFilter,
+    pub(crate) intent: RenderIntent,
+}
+
+/// Decoded images kept across layouts within a byte budget, least
+/// recently used first.
+#[derive(Debug)]
+pub(crate) struct DecodedImageCache {
+    entries: VecDeque<CachedImage>,
+    bytes: usize,
+    gauge: MemoryGauge,
+}
+
+impl Default for DecodedImageCache {
+    fn default() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            bytes: 0,
+            gauge: MemoryGauge::new(MemorySubsystem::ImageDecode),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CachedImage {
+    key: ImageCacheKey,
+    /// Pixel size of the source before scaling.
+    source_size: (u32, u32),
+    bitmap: GrayBitmap,
+}
+
+impl DecodedImageCache {
+    /// Source size and a copy of the bitmap stored under `key`, marking it
+    /// most recently used.
+    pub(crate) fn get(&mut self, key: &ImageCacheKey) -> Option<((u32, u32), GrayBitmap)> {
+        let pos = self.entries.iter().position(|entry| entry.key == *key)?;
+        let entry = self.entries.remove(pos)?;
+        let found = (entry.source_size, entry.bitmap.clone());
+        self.entries.push_back(entry);
+        Some(found)
+    }
+
+    /// Store `bitmap`, evicting least recently used images to stay within
+    /// `capacity` bytes; images larger than `capacity` are not kept.
+    pub(crate) fn insert(
+        &mut self,
+        key: ImageCacheKey,
+        source_size: (u32, u32),
+        bitmap: GrayBitmap,
+        capacity: usize,
+    ) {
+        let bytes = bitmap.pixels.len();
+        if bytes > capacity {
+            return;
+        }
+        if let Some(pos) = self.entries.iter().position(|entry| entry.key == key) {
+            if let Some(old) = self.entries.remove(pos) {
+                self.bytes -= old.bitmap.pixels.len();
+            }
+        }
+        while self.bytes + bytes > capacity {
+            let Some(oldest) = self.entries.pop_front() else {
+                break;
+            };
+            self.bytes -= oldest.bitmap.pixels.len();
+        }
+        self.bytes += bytes;
+        self.gauge.set(self.bytes);
+        self.entries.push_back(CachedImage {
+            key,
+            source_size,
+            bitmap,
+        });
+    }
+
+    /// Drop every image.
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.bytes = 0;
+        self.gauge.set(0);
+    }
+}
+
+/// Memory charged while `decoder` shrinks a `source` sized image to fit
+/// `max_width`x`max_height`: the output bitmap, plus every source pixel for
+/// decoders that do not work in strips.
+pub(crate) fn decode_gauge(
+    decoder: &dyn ImageDecoder,
+    source: (u32, u32),
+    max_width: i32,
+    max_height: i32,
+) -> MemoryGauge {
+    let (out_width, out_height) = fit_size(source.0, source.1, max_width, max_height);
+    let mut bytes = (out_width as usize).saturating_mul(out_height as usize);
+    if !decoder.decodes_in_strips() {
+        bytes = bytes.saturating_add((source.0 as usize).saturating_mul(source.1 as usize));
+    }
+    MemoryGauge::with_bytes(MemorySubsystem::ImageDecode, bytes)
+}
+
+/// Give undecoded image commands on `page` their decoded pixels.
+///
+/// Each queued `(src, bitmap)` is used once, in layout order, then scaled
+/// to the command's layout size and mapped through `intent`.
+pub(crate) fn attach_decoded_images(
+    page: &mut RenderPage,
+    decoded: &mut VecDeque<(String, GrayBitmap)>,
+    intent: &RenderIntent,
+) {
+    if decoded.is_empty() {
+        return;
+    }
+    let mut attached = false;
+    for cmd in &mut page.content_commands {
+        let DrawCommand::Image(image) = cmd else {
+            continue;
+        };
+        if image.bitmap.is_some() {
+            continue;
+        }
+        let Some(index) = decoded.iter().position(|(src, _)| *src == image.src) else {
+            continue;
+        };
+        let Some((_, source)) = decoded.remove(index) else {
+            continue;
+        };
+        let mut bitmap = resize(&source, image.width, image.height);
+        intent.dither_bitmap(&mut bitmap);
+        image.bitmap = Some(bitmap);
+        attached = true;
+    }
+    if attached {
+        page.sync_commands();
+    }
+}
+
+/// Mark the undecoded image commands of `page` whose source is queued in
+/// `pending` with a `PageAnnotation::PendingImage` covering their rect.
+pub(crate) fn mark_pending_images(page: &mut RenderPage, pending: &mut VecDeque<String>) {
+    if pending.is_empty() {
+        return;
+    }
+    for cmd in &page.content_commands {
+        let DrawCommand::Image(image) = cmd else {
+            continue;
+        };
+        if image.bitmap.is_some() {
+            continue;
+        }
+        let Some(index) = pending.iter().position(|src| *src == image.src) else {
+            continue;
+        };
+        pending.remove(index);
+        page.annotations.push(PageAnnotation::PendingImage {
+            rect: PageRect::new(image.x, image.y, image.width, image.height),
+            src: image.src.clone(),
+        });
+    }
+}
+
+/// Attach `source` to every pending image of `page` showing `src`, at
+/// layout size and mapped through `intent`, and drop their pending
+/// annotations. Returns the rects whose pixels changed.
+pub(crate) fn fill_pending_images(
+    page: &mut RenderPage,
+    src: &str,
+    source: &GrayBitmap,
+    intent: &RenderIntent,
+) -> Vec<PageRect> {
+    let mut damage = Vec::with_capacity(0);
+    page.annotations.retain(|annotation| {
+        let PageAnnotation::PendingImage { rect, src: pending } = annotation else {
+            return true;
+        };
+        if pending != src {
+            return true;
+        }
+        damage.push(*rect);
+        false
+    });
+    if damage.is_empty() {
+        return damage;
+    }
+    for cmd in &mut page.content_commands {
+        let DrawCommand::Image(image) = cmd else {
+            continue;
+        };
+        let rect = PageRect::new(image.x, image.y, image.width, image.height);
+        if image.bitmap.is_some() || image.src != src || !damage.contains(&rect) {
+            continue;
+        }
+        let mut bitmap = resize(source, image.width, image.height);
+        intent.dither_bitmap(&mut bitmap);
+        image.bitmap = Some(bitmap);
+    }
+    page.sync_commands();
+    damage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_ir::ImageCommand;
+
+    #[test]
+    fn exif_orientation_reads_tiff_tag_and_turns_pixels_upright() {
+        assert_eq!(ExifOrientation(6).transpose_size(3, 2), (2, 3));
+        assert_eq!(
+            ExifOrientation::from_jpeg(&[0xFF, 0xD8, 0xFF, 0xDA, 0, 2]),
+            ExifOrientation::UPRIGHT
+        );
+        let little = [b"II\x2a\0\x08\0\0\0\x01\0".as_slice(), &[0x12, 0x01, 3, 0]]
+            .concat()
+            .into_iter()
+            .chain([1, 0, 0, 0, 8, 0, 0, 0])
+            .collect::<Vec<u8>>();
+        assert_eq!(tiff_orientation(&little), Some(8));
+
+        // 3x2 stored: a b c / d e f
+        let stored = GrayBitmap {
+            width: 3,
+            height: 2,
+            pixels: vec![1, 2, 3, 4, 5, 6],
+        };
+        let turned = |o: u8| ExifOrientation(o).apply(stored.clone()).pixels;
+        assert_eq!(turned(1), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(turned(2), [3, 2, 1, 6, 5, 4]);
+        assert_eq!(turned(3), [6, 5, 4, 3, 2, 1]);
+        assert_eq!(turned(4), [4, 5, 6, 1, 2, 3]);
+        assert_eq!(turned(5), [1, 4, 2, 5, 3, 6]);
+        assert_eq!(turned(6), [4, 1, 5, 2, 6, 3]);
+        assert_eq!(turned(7), [6, 3, 5, 2, 4, 1]);
+        assert_eq!(turned(8), [3, 6, 2, 5, 1, 4]);
+    }
+
+    #[test]
+    fn netpbm_decodes_and_attaches_at_layout_size() {
+        let mut pgm = b"P5\n# test\n4 2\n255\n".to_vec();
+        pgm.extend_from_slice(&[0, 0, 255, 255, 0, 0, 255, 255]);
+        let registry = ImageDecoderRegistry::builtin();
+        let decoder = registry.find(&pgm).expect("netpbm is built in");
+        assert_eq!(decoder.dimensions(&pgm), Some((4, 2)));
+        let bitmap = decoder.decode(&pgm).expect("valid graymap");
+        assert_eq!(bitmap.pixel(2, 1), Some(255));
+        assert!(registry.find(b"GIF89a").is_none());
+
+        let mut page = RenderPage::new(1);
+        page.push_content_command(DrawCommand::Image(ImageCommand {
+            x: 0,
+            y: 0,
+            width: 2,
+            height: 1,
+            src: "images/a.pgm".to_string(),
+            alt: String::with_capacity(0),
+            bitmap: None,
+        }));
+        let mut queue = VecDeque::from([("images/a.pgm".to_string(), bitmap)]);
+        attach_decoded_images(&mut page, &mut queue, &RenderIntent::default());
+        assert!(queue.is_empty());
+        let DrawCommand::Image(image) = &page.commands[0] else {
+            panic!("image command expected");
+        };
+        let bitmap = image.bitmap.as_ref().expect("bitmap attached");
+        assert_eq!((bitmap.width, bitmap.height), (2, 1));
+        assert_eq!(bitmap.pixels, [0, 255]);
+    }
+
+    #[test]
+    fn netpbm_streams_into_viewport_sized_bitmap() {
+        let (width, height) = (300u32, 400u32);
+        let mut pgm = format!("P5\n{width} {height}\n255\n").into_bytes();
+        pgm.extend((0..height).flat_map(|y| (0..width).map(move |x| ((x + y) % 256) as u8)));
+        let decoder = NetpbmDecoder;
+        assert!(decoder.decodes_in_strips());
+        let full = decoder.decode(&pgm).expect("valid graymap");
+
+        let boxed = decoder
+            .decode_within(&pgm, 60, 100, ImageDecodeOptions::default())
+            .expect("box decode");
+        assert_eq!((boxed.width, boxed.height), (60, 80));
+        assert_eq!(
+            boxed,
+            fit_within(full.clone(), 60, 100, ImageScaleFilter::Box)
+        );
+
+        let bilinear = decoder
+            .decode_within(
+                &pgm,
+                60,
+                100,
+                ImageDecodeOptions {
+                    filter: ImageScaleFilter::Bilinear,
+                    ..ImageDecodeOptions::default()
+                },
+            )
+            .expect("bilinear decode");
+        assert_eq!((bilinear.width, bilinear.height), (60, 80));
+        assert_eq!(
+            bilinear,
+            fit_within(full, 60, 100, ImageScaleFilter::Bilinear)
+        );
+    }
+
+    #[test]
+    fn decode_options_select_grayscale_conversion() {
+        let mut ppm = b"P6\n1 1\n255\n".to_vec();
+        ppm.extend_from_slice(&[255, 0, 0]);
+        let decoder = NetpbmDecoder;
+        assert_eq!(decoder.decode(&ppm).expect("valid pixmap").pixels, [76]);
+        let options = ImageDecodeOptions {
+            grayscale: GrayscaleMode::Weighted(GrayscaleWeights::REC709_LINEAR),
+            ..ImageDecodeOptions::default()
+        };
+        let bitmap = decoder
+            .decode_within(&ppm, 10, 10, options)
+            .expect("valid pixmap");
+        assert_eq!(bitmap.pixels, [126]);
+    }
+
+    #[test]
+    fn decoded_image_cache_evicts_least_recently_used_within_budget() {
+        let key = |src: &str| ImageCacheKey {
+            book: BookFingerprint([0; 32]),
+            src: src.to_string(),
+            target: (100, 100),
+            filter: ImageScaleFilter::Box,
+            intent: RenderIntent::default(),
+        };
+        let bitmap = |len: u32| GrayBitmap {
+            width: len,
+            height: 1,
+            pixels: vec![0; len as usize],
+        };
+        let mut cache = DecodedImageCache::default();
+        cache.insert(key("a"), (40, 1), bitmap(40), 100);
+        cache.insert(key("b"), (40, 1), bitmap(40), 100);
+        assert!(cache.get(&key("a")).is_some());
+        cache.insert(key("c"), (40, 1), bitmap(40), 100);
+        assert!(cache.get(&key("b")).is_none());
+        assert_eq!(cache.get(&key("a")).map(|(size, _)| size), Some((40, 1)));
+        assert!(cache.get(&key("c")).is_some());
+
+        cache.insert(key("huge"), (200, 1), bitmap(200), 100);
+        assert!(cache.get(&key("huge")).is_none());
+        let night = ImageCacheKey {
+            intent: RenderIntent {
+                inverted: true,
+                ..RenderIntent::default()
+            },
+            ..key("a")
+        };
+        assert!(cache.get(&night).is_none());
+    }
+
+    #[test]
+    fn bilinear_downscale_interpolates_between_centers() {
+        let mut scaler = RowDownscaler::new(4, 1, 2, 1, ImageScaleFilter::Bilinear);
+        scaler.push_row(&[0, 100, 200, 255]);
+        let bitmap = scaler.finish().expect("one row is enough");
+        assert_eq!(bitmap.pixels, [50, 228]);
+
+        let mut scaler = RowDownscaler::new(1, 4, 1, 2, ImageScaleFilter::Bilinear);
+        for value in [0, 100, 200, 255] {
+            scaler.push_row(&[value]);
+        }
+        assert_eq!(scaler.finish().expect("all rows").pixels, [50, 228]);
+    }
+
+    #[cfg(feature = "image-decode")]
+    #[test]
+    fn png_rgba_flattens_onto_white() {
+        let mut png_bytes = Vec::with_capacity(0);
+        {
+            let mut encoder = png::Encoder::new(&mut png_bytes, 2, 1);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().expect("header");
+            writer
+                .write_image_data(&[0, 0, 0, 255, 0, 0, 0, 0])
+                .expect("pixels");
+        }
+        let registry = ImageDecoderRegistry::builtin();
+        let bitmap = registry
+            .find(&png_bytes)
+            .and_then(|decoder| decoder.decode(&png_bytes))
+            .expect("png decodes");
+        assert_eq!(bitmap.pixels, [0, 255]);
+    }
+
+    #[cfg(feature = "webp")]
+    #[test]
+    fn webp_lossless_rgba_flattens_onto_white() {
+        let mut webp_bytes = Vec::with_capacity(0);
+        image_webp::WebPEncoder::new(&mut webp_bytes)
+            .encode(
+                &[0, 0, 0, 255, 255, 255, 255, 255, 0, 0, 0, 0],
+                3,
+                1,
+                image_webp::ColorType::Rgba8,
+            )
+            .expect("encodes");
+        let registry = ImageDecoderRegistry::builtin();
+        let decoder = registry.find(&webp_bytes).expect("webp is built in");
+        assert_eq!(decoder.dimensions(&webp_bytes), Some((3, 1)));
+        let bitmap = decoder.decode(&webp_bytes).expect("webp decodes");
+        assert_eq!(bitmap.pixels, [0, 255, 255]);
+        assert!(!decoder.sniff(b"RIFF\0\0\0\0WAVEfmt "));
+    }
+}
@@ -1,4 +1,4 @@
-use mu_epub::BlockRole;
+use mu_epub::{BlockRole, Color};
 
 /// Page represented as backend-agnostic draw commands.
 #[derive(Clone, Debug, Default, PartialEq)]
@@ -22,6 +22,16 @@ pub struct RenderPage {
     pub annotations: Vec<PageAnnotation>,
     /// Per-page metrics for navigation/progress consumers.
     pub metrics: PageMetrics,
+    /// Regions that changed since the previous page of the same chapter.
+    ///
+    /// `None` means a full refresh is required (first page, or damage could
+    /// not be bounded). `Some(vec![])` means the page is visually identical.
+    pub damage: Option<Vec<PageRect>>,
+    /// Chapter title from the book's table of contents, when known.
+    pub chapter_title: Option<String>,
+    /// Source position of each `plain_text` line start, when layout
+    /// recorded them.
+    pub line_positions: Vec<TextPosition>,
 }
 
 impl RenderPage {
@@ -39,6 +49,9 @@ impl RenderPage {
                 chapter_page_index: page_number.saturating_sub(1),
                 ..PageMetrics::default()
             },
+            damage: None,
+            chapter_title: None,
+            line_positions: Vec::with_capacity(0),
         }
     }
 
@@ -69,19 +82,229 @@ impl RenderPage {
     pub fn page_meta(&self) -> &PageMeta {
         &self.metrics
     }
+
+    /// Element ids laid out on this page, in source order.
+    pub fn anchors(&self) -> impl Iterator<Item = &str> {
+        self.annotations
+            .iter()
+            .filter_map(|annotation| match annotation {
+                PageAnnotation::Tag { kind, value } if kind == ANCHOR_TAG_KIND => value.as_deref(),
+                _ => None,
+            })
+    }
+
+    /// Approximate bytes held by this page, including heap payloads.
+    ///
+    /// Counts every command layer (the merged `commands` stream duplicates
+    /// the split layers), overlay items, annotations, and damage rects. Use
+    /// it for byte budgets; it is an estimate, not an allocator measurement.
+    pub fn approx_bytes(&self) -> usize {
+        let commands: usize = [
+            &self.commands,
+            &self.content_commands,
+            &self.chrome_commands,
+            &self.overlay_commands,
+        ]
+        .iter()
+        .flat_map(|layer| layer.iter())
+        .map(DrawCommand::approx_bytes)
+        .sum();
+        let overlays: usize = self
+            .overlay_items
+            .iter()
+            .map(|item| {
+                core::mem::size_of::<OverlayItem>()
+                    + match &item.content {
+                        OverlayContent::Text(text) => text.capacity(),
+                        OverlayContent::Command(cmd) => cmd.approx_bytes(),
+                        OverlayContent::Image(image) => {
+                            image.key.capacity()
+                                + image
+                                    .bitmap
+                                    .as_ref()
+                                    .map_or(0, |bitmap| bitmap.pixels.capacity())
+                        }
+                    }
+            })
+            .sum();
+        let annotations: usize = self
+            .annotations
+            .iter()
+            .map(|annotation| core::mem::size_of::<PageAnnotation>() + annotation.heap_bytes())
+            .sum();
+        let damage = self.damage.as_ref().map_or(0, |rects| {
+            rects.capacity() * core::mem::size_of::<PageRect>()
+        });
+        let title = self.chapter_title.as_ref().map_or(0, String::capacity);
+        let positions = self.line_positions.capacity() * core::mem::size_of::<TextPosition>();
+        core::mem::size_of::<Self>()
+            + commands
+            + overlays
+            + annotations
+            + damage
+            + title
+            + positions
+    }
+}
+
+/// Generates pages whose legacy `commands` stream matches the split layers.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for RenderPage {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut page = Self {
+            page_number: u.arbitrary()?,
+            commands: Vec::with_capacity(0),
+            content_commands: u.arbitrary()?,
+            chrome_commands: u.arbitrary()?,
+            overlay_commands: u.arbitrary()?,
+            overlay_items: u.arbitrary()?,
+            annotations: u.arbitrary()?,
+            metrics: u.arbitrary()?,
+            damage: u.arbitrary()?,
+            chapter_title: u.arbitrary()?,
+            line_positions: u.arbitrary()?,
+        };
+        page.sync_commands();
+        Ok(page)
+    }
 }
 
+/// `PageAnnotation::Tag` kind marking an element id laid out on the page.
+pub const ANCHOR_TAG_KIND: &str = "anchor";
+
 /// Structured page annotation.
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub struct PageAnnotation {
-    /// Stable annotation kind/tag.
-    pub kind: String,
-    /// Optional annotation payload.
-    pub value: Option<String>,
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum PageAnnotation {
+    /// Free-form tagged annotation.
+    ///
+    /// Layout records each element id that starts on the page as kind
+    /// `ANCHOR_TAG_KIND`, with the id as value.
+    Tag {
+        /// Stable annotation kind/tag.
+        kind: String,
+        /// Optional annotation payload.
+        value: Option<String>,
+    },
+    /// Tappable hyperlink region.
+    Link {
+        /// Hit area covering the laid-out link text on one line.
+        rect: PageRect,
+        /// Link destination.
+        target: LinkTarget,
+    },
+    /// Textual description of an image placed on the page.
+    ImageDescription {
+        /// Placed image (or placeholder) rectangle.
+        rect: PageRect,
+        /// Image resource path (empty for inline SVG islands).
+        src: String,
+        /// Alternative text, when non-empty.
+        alt: Option<String>,
+        /// Caption of the enclosing figure, when present.
+        caption: Option<String>,
+    },
+    /// Heading (or the part of one) laid out on the page.
+    Heading {
+        /// Heading level (`1..=6`).
+        level: u8,
+        /// Heading text placed on this page.
+        text: String,
+        /// Union of the heading's line boxes on this page.
+        rect: PageRect,
+    },
+    /// Raster image placed with its rect reserved but not yet decoded.
+    ///
+    /// Emitted under `RenderConfig::with_deferred_images`; deliver the
+    /// bitmap with `RenderEngine::fill_pending_image`.
+    PendingImage {
+        /// Reserved image rectangle.
+        rect: PageRect,
+        /// Image resource path, as on the `ImageCommand`.
+        src: String,
+    },
+}
+
+impl PageAnnotation {
+    pub(crate) fn anchor(id: String) -> Self {
+        Self::Tag {
+            kind: ANCHOR_TAG_KIND.to_string(),
+            value: Some(id),
+        }
+    }
+
+    fn heap_bytes(&self) -> usize {
+        let opt = |value: &Option<String>| value.as_ref().map_or(0, String::capacity);
+        match self {
+            Self::Tag { kind, value } => kind.capacity() + opt(value),
+            Self::Link { target, .. } => match target {
+                LinkTarget::Internal(link) => link.href.capacity() + opt(&link.fragment),
+                LinkTarget::External(url) => url.capacity(),
+            },
+            Self::ImageDescription {
+                src, alt, caption, ..
+            } => src.capacity() + opt(alt) + opt(caption),
+            Self::Heading { text, .. } => text.capacity(),
+            Self::PendingImage { src, .. } => src.capacity(),
+        }
+    }
+}
+
+/// Hyperlink destination.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum LinkTarget {
+    /// Location inside the book.
+    Internal(InternalLink),
+    /// External URL (`https:`, `mailto:`, ...).
+    External(String),
+}
+
+impl LinkTarget {
+    /// Classify a resolved link href.
+    pub fn from_href(href: &str) -> Self {
+        let has_scheme = href
+            .split_once(':')
+            .is_some_and(|(scheme, _)| !scheme.is_empty() && !scheme.contains(['/', '#']));
+        if has_scheme {
+            return Self::External(href.to_string());
+        }
+        let (path, fragment) = match href.split_once('#') {
+            Some((path, fragment)) => (path, Some(fragment.to_string())),
+            None => (href, None),
+        };
+        Self::Internal(InternalLink {
+            href: path.to_string(),
+            fragment: fragment.filter(|f| !f.is_empty()),
+            chapter_index: None,
+            page_index: None,
+        })
+    }
+}
+
+/// Book-internal link destination.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct InternalLink {
+    /// OPF-relative resource path.
+    pub href: String,
+    /// Fragment identifier without `#`.
+    pub fragment: Option<String>,
+    /// Spine index of `href`, when it is a spine item.
+    pub chapter_index: Option<usize>,
+    /// Chapter page index (0-based), when resolvable.
+    ///
+    /// Links without a fragment resolve to the first page of the chapter.
+    /// Fragment links resolve to the page holding the anchor id when that
+    /// page is known at render time: the same page when streaming, or any
+    /// page of a collected chapter. Otherwise this stays `None`; use
+    /// `RenderEngine::resolve_link` to lay out the target chapter.
+    pub page_index: Option<usize>,
 }
 
 /// Structured page metrics for progress and navigation.
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct PageMetrics {
     /// Chapter index in the spine (0-based), when known.
     pub chapter_index: usize,
@@ -97,11 +320,251 @@ pub struct PageMetrics {
     pub progress_chapter: f32,
     /// Book progress in range `[0.0, 1.0]`, when known.
     pub progress_book: Option<f32>,
+    /// Physical page width in millimetres, when the panel density is known.
+    pub physical_width_mm: Option<f32>,
+    /// Physical page height in millimetres, when the panel density is known.
+    pub physical_height_mm: Option<f32>,
+    /// Pages after this one in its chapter, when the chapter page count is known.
+    pub pages_left_in_chapter: Option<usize>,
+    /// Pages after this one in the book, when book pagination is known.
+    pub pages_left_in_book: Option<usize>,
+    /// Source position of the first content on this page, when layout
+    /// recorded it.
+    pub start_position: Option<TextPosition>,
+    /// Words laid out on this page. A word hyphenated across a page break
+    /// counts on both pages.
+    pub word_count: usize,
+    /// Images (or their placeholders) laid out on this page.
+    pub image_count: usize,
+}
+
+impl PageMetrics {
+    /// Typical adult silent-reading speed, for apps without a user setting.
+    pub const DEFAULT_WORDS_PER_MINUTE: u32 = 230;
+
+    /// Estimated seconds to read this page at `words_per_minute`.
+    pub fn reading_seconds(&self, words_per_minute: u32) -> u32 {
+        reading_seconds(self.word_count, words_per_minute)
+    }
+
+    /// Fill chapter and book-level fields from per-chapter page counts.
+    ///
+    /// `chapter_page_counts` is indexed by spine position and must cover
+    /// every chapter up to `chapter_index`; otherwise nothing changes.
+    pub fn apply_book_page_counts(&mut self, chapter_page_counts: &[usize]) {
+        let Some(&chapter_count) = chapter_page_counts.get(self.chapter_index) else {
+            return;
+        };
+        let chapter_count = chapter_count.max(self.chapter_page_index + 1);
+        let before: usize = chapter_page_counts[..self.chapter_index].iter().sum();
+        let total: usize = chapter_page_counts
+            .iter()
+            .sum::<usize>()
+            .max(before + chapter_count);
+        let global = before + self.chapter_page_index;
+        self.chapter_page_count = Some(chapter_count);
+        self.progress_chapter = (self.chapter_page_index + 1) as f32 / chapter_count as f32;
+        self.pages_left_in_chapter = Some(chapter_count - self.chapter_page_index - 1);
+        self.global_page_index = Some(global);
+        self.global_page_count_estimate = Some(total);
+        self.progress_book = Some((global + 1) as f32 / total as f32);
+        self.pages_left_in_book = Some(total - global - 1);
+    }
+
+    /// Whole-book progress as a rounded percentage, when known.
+    pub fn percent_complete(&self) -> Option<u8> {
+        self.progress_book
+            .map(|progress| (progress.clamp(0.0, 1.0) * 100.0).round() as u8)
+    }
 }
 
 /// Backward-compatible alias for page-level metadata.
 pub type PageMeta = PageMetrics;
 
+/// Reading statistics summed over laid-out pages.
+///
+/// Build it from a whole chapter for totals, or from `pages[i + 1..]` for
+/// what is left after page `i` ("3 min left in chapter").
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChapterStats {
+    /// Pages summed.
+    pub page_count: usize,
+    /// Words across those pages.
+    pub word_count: usize,
+    /// Images across those pages.
+    pub image_count: usize,
+}
+
+impl ChapterStats {
+    /// Sum the per-page counts recorded by layout.
+    pub fn from_pages<'a, I>(pages: I) -> Self
+    where
+        I: IntoIterator<Item = &'a RenderPage>,
+    {
+        pages.into_iter().fold(Self::default(), |acc, page| Self {
+            page_count: acc.page_count + 1,
+            word_count: acc.word_count + page.metrics.word_count,
+            image_count: acc.image_count + page.metrics.image_count,
+        })
+    }
+
+    /// Estimated seconds to read these pages at `words_per_minute`.
+    pub fn reading_seconds(&self, words_per_minute: u32) -> u32 {
+        reading_seconds(self.word_count, words_per_minute)
+    }
+}
+
+fn reading_seconds(words: usize, words_per_minute: u32) -> u32 {
+    let seconds = (words as u64 * 60).div_ceil(u64::from(words_per_minute.max(1)));
+    u32::try_from(seconds).unwrap_or(u32::MAX)
+}
+
+/// Whole-book page map: global page index <-> (chapter, chapter page).
+///
+/// Built by `RenderEngine::paginate_book`; only valid for the pagination
+/// profile it was built under.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BookPageMap {
+    profile: PaginationProfileId,
+    chapter_page_counts: Vec<usize>,
+    /// First global page index of each chapter, plus the total at the end.
+    starts: Vec<usize>,
+}
+
+impl BookPageMap {
+    /// Build a map from per-chapter page counts in spine order.
+    pub fn new(profile: PaginationProfileId, chapter_page_counts: Vec<usize>) -> Self {
+        let mut starts = Vec::with_capacity(chapter_page_counts.len() + 1);
+        let mut total = 0usize;
+        starts.push(total);
+        for count in &chapter_page_counts {
+            total += count;
+            starts.push(total);
+        }
+        Self {
+            profile,
+            chapter_page_counts,
+            starts,
+        }
+    }
+
+    /// Pagination profile the counts were produced under.
+    pub fn profile(&self) -> PaginationProfileId {
+        self.profile
+    }
+
+    /// Page count of every chapter in spine order.
+    pub fn chapter_page_counts(&self) -> &[usize] {
+        &self.chapter_page_counts
+    }
+
+    /// Total pages in the book.
+    pub fn total_pages(&self) -> usize {
+        self.starts.last().copied().unwrap_or(0)
+    }
+
+    /// Global index of a chapter's first page, when the chapter exists.
+    pub fn chapter_start(&self, chapter_index: usize) -> Option<usize> {
+        self.chapter_page_counts.get(chapter_index)?;
+        self.starts.get(chapter_index).copied()
+    }
+
+    /// Global page index for a zero-based chapter page.
+    pub fn global_index(&self, chapter_index: usize, chapter_page_index: usize) -> Option<usize> {
+        let count = *self.chapter_page_counts.get(chapter_index)?;
+        if chapter_page_index >= count {
+            return None;
+        }
+        Some(self.starts.get(chapter_index)? + chapter_page_index)
+    }
+
+    /// Global page index at a book fraction, clamped to `0.0..=1.0`.
+    ///
+    /// `None` for an empty book.
+    pub fn global_index_for_fraction(&self, fraction: f32) -> Option<usize> {
+        let last = self.total_pages().checked_sub(1)?;
+        let fraction = if fraction.is_nan() {
+            0.0
+        } else {
+            fraction.clamp(0.0, 1.0)
+        };
+        Some(((fraction * self.total_pages() as f32) as usize).min(last))
+    }
+
+    /// `(chapter_index, chapter_page_index)` for a global page index.
+    ///
+    /// Empty chapters are skipped; out-of-range indices return `None`.
+    pub fn locate(&self, global_page_index: usize) -> Option<(usize, usize)> {
+        if global_page_index >= self.total_pages() {
+            return None;
+        }
+        // Last chapter whose start is <= the index; empty chapters share a
+        // start with their successor, so take the last match.
+        let chapter = self
+            .starts
+            .partition_point(|start| *start <= global_page_index)
+            .checked_sub(1)?;
+        let start = self.starts.get(chapter)?;
+        Some((chapter, global_page_index - start))
+    }
+}
+
+/// Position in a chapter's styled content, independent of pagination.
+///
+/// Blocks are paragraphs, headings, list items, and images in stream order;
+/// `char_offset` counts chars of whitespace-collapsed text (one space
+/// between words, soft hyphens dropped) from the block start. Neither
+/// depends on fonts, margins, or display size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TextPosition {
+    /// Zero-based block index in the chapter.
+    pub block_index: usize,
+    /// Char offset within the block.
+    pub char_offset: usize,
+}
+
+/// Reflow-stable saved reading position.
+///
+/// Produced by `RenderEngine::locate` and resolved back to a page by
+/// `RenderEngine::page_for` under any layout settings.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ContentLocator {
+    /// Spine index of the chapter.
+    pub chapter_index: usize,
+    /// Chapter href; preferred over `chapter_index` when the spine changed.
+    pub chapter_href: Option<String>,
+    /// Position within the chapter.
+    pub position: TextPosition,
+    /// EPUB CFI for interop with other reading systems.
+    ///
+    /// Carried through unchanged; resolution uses `position`.
+    pub cfi: Option<String>,
+}
+
+impl ContentLocator {
+    /// Locator at `position` in a chapter.
+    pub fn new(chapter_index: usize, position: TextPosition) -> Self {
+        Self {
+            chapter_index,
+            position,
+            ..Self::default()
+        }
+    }
+
+    /// Attach the chapter href.
+    pub fn with_chapter_href(mut self, href: impl Into<String>) -> Self {
+        self.chapter_href = Some(href.into());
+        self
+    }
+
+    /// Attach an EPUB CFI.
+    pub fn with_cfi(mut self, cfi: impl Into<String>) -> Self {
+        self.cfi = Some(cfi.into());
+        self
+    }
+}
+
 /// Stable pagination profile id.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct PaginationProfileId(pub [u8; 32]);
@@ -128,10 +591,29 @@ impl PaginationProfileId {
         out[24..32].copy_from_slice(&h3);
         Self(out)
     }
+
+    /// Lowercase hex form, for file names and settings storage.
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Parse the form written by `to_hex`.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let mut out = [0u8; 32];
+        for (byte, pair) in out.iter_mut().zip(hex.as_bytes().chunks(2)) {
+            let pair = core::str::from_utf8(pair).ok()?;
+            *byte = u8::from_str_radix(pair, 16).ok()?;
+        }
+        Some(Self(out))
+    }
 }
 
 /// Logical overlay slots for app/UI composition.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum OverlaySlot {
     TopLeft,
     TopCenter,
@@ -140,17 +622,193 @@ pub enum OverlaySlot {
     BottomCenter,
     BottomRight,
     Custom(OverlayRect),
+    /// Absolute viewport point, with `anchor` choosing which point of the
+    /// item sits there.
+    At {
+        x: i32,
+        y: i32,
+        anchor: OverlayAnchor,
+    },
+    /// Point `inset` pixels in from a viewport `edge`, `offset` pixels along
+    /// it (from the left or top end).
+    Edge {
+        edge: OverlayEdge,
+        inset: i32,
+        offset: i32,
+        anchor: OverlayAnchor,
+    },
+}
+
+/// Point of an overlay item pinned to an `At`/`Edge` position.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum OverlayAnchor {
+    #[default]
+    TopLeft,
+    TopCenter,
+    TopRight,
+    CenterLeft,
+    Center,
+    CenterRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+impl OverlayAnchor {
+    /// Top-left origin of an item of `size` whose anchor lies at `(x, y)`.
+    pub fn origin(self, x: i32, y: i32, size: OverlaySize) -> (i32, i32) {
+        let (w, h) = (size.width as i32, size.height as i32);
+        let dx = match self {
+            Self::TopLeft | Self::CenterLeft | Self::BottomLeft => 0,
+            Self::TopCenter | Self::Center | Self::BottomCenter => w / 2,
+            Self::TopRight | Self::CenterRight | Self::BottomRight => w,
+        };
+        let dy = match self {
+            Self::TopLeft | Self::TopCenter | Self::TopRight => 0,
+            Self::CenterLeft | Self::Center | Self::CenterRight => h / 2,
+            Self::BottomLeft | Self::BottomCenter | Self::BottomRight => h,
+        };
+        (x - dx, y - dy)
+    }
+}
+
+/// Viewport edge for margin-anchored overlay placement.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum OverlayEdge {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+impl OverlaySlot {
+    /// Rectangle for content of `size` anchored to this slot in `viewport`.
+    ///
+    /// Custom slots place content at their origin; `At` and `Edge` slots
+    /// pin the item's anchor point to the resolved position.
+    pub fn place(&self, viewport: OverlaySize, size: OverlaySize) -> OverlayRect {
+        let (vw, vh) = (viewport.width as i32, viewport.height as i32);
+        let (w, h) = (size.width as i32, size.height as i32);
+        let (x, y) = match self {
+            Self::TopLeft => (0, 0),
+            Self::TopCenter => ((vw - w) / 2, 0),
+            Self::TopRight => (vw - w, 0),
+            Self::BottomLeft => (0, vh - h),
+            Self::BottomCenter => ((vw - w) / 2, vh - h),
+            Self::BottomRight => (vw - w, vh - h),
+            Self::Custom(rect) => (rect.x, rect.y),
+            Self::At { x, y, anchor } => anchor.origin(*x, *y, size),
+            Self::Edge {
+                edge,
+                inset,
+                offset,
+                anchor,
+            } => {
+                let (x, y) = match edge {
+                    OverlayEdge::Top => (*offset, *inset),
+                    OverlayEdge::Right => (vw - inset, *offset),
+                    OverlayEdge::Bottom => (*offset, vh - inset),
+                    OverlayEdge::Left => (*inset, *offset),
+                };
+                anchor.origin(x, y, size)
+            }
+        };
+        OverlayRect {
+            x,
+            y,
+            width: size.width,
+            height: size.height,
+        }
+    }
 }
 
 /// Logical viewport size for overlay composition.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct OverlaySize {
     pub width: u32,
     pub height: u32,
 }
 
+/// Panel rotation applied between logical (reader-facing) and physical axes.
+///
+/// Rotations are clockwise: with `Deg90`, the logical top edge lies along the
+/// physical right edge of the panel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Rotation {
+    /// Logical and physical axes coincide.
+    #[default]
+    Deg0,
+    /// Rotated a quarter turn clockwise.
+    Deg90,
+    /// Rotated a half turn.
+    Deg180,
+    /// Rotated three quarter turns clockwise.
+    Deg270,
+}
+
+impl Rotation {
+    /// Whether logical width/height are the physical height/width.
+    pub fn swaps_axes(self) -> bool {
+        matches!(self, Self::Deg90 | Self::Deg270)
+    }
+
+    /// Logical viewport size for a physical panel size.
+    pub fn logical_size(self, physical: OverlaySize) -> OverlaySize {
+        if self.swaps_axes() {
+            OverlaySize {
+                width: physical.height,
+                height: physical.width,
+            }
+        } else {
+            physical
+        }
+    }
+
+    /// Map `(top, right, bottom, left)` physical insets to logical insets.
+    pub fn logical_insets(self, physical: [i32; 4]) -> [i32; 4] {
+        let [top, right, bottom, left] = physical;
+        match self {
+            Self::Deg0 => [top, right, bottom, left],
+            Self::Deg90 => [right, bottom, left, top],
+            Self::Deg180 => [bottom, left, top, right],
+            Self::Deg270 => [left, top, right, bottom],
+        }
+    }
+
+    /// Map a logical rectangle onto the physical panel of size `physical`.
+    pub fn to_physical_rect(self, rect: PageRect, physical: OverlaySize) -> PageRect {
+        let (pw, ph) = (physical.width as i32, physical.height as i32);
+        let (w, h) = (rect.width as i32, rect.height as i32);
+        match self {
+            Self::Deg0 => rect,
+            Self::Deg90 => PageRect::new(pw - (rect.y + h), rect.x, rect.height, rect.width),
+            Self::Deg180 => PageRect::new(
+                pw - (rect.x + w),
+                ph - (rect.y + h),
+                rect.width,
+                rect.height,
+            ),
+            Self::Deg270 => PageRect::new(rect.y, ph - (rect.x + w), rect.height, rect.width),
+        }
+    }
+
+    /// Map a physical rectangle on a panel of size `physical` to logical space.
+    pub fn to_logical_rect(self, rect: PageRect, physical: OverlaySize) -> PageRect {
+        let inverse = match self {
+            Self::Deg90 => Self::Deg270,
+            Self::Deg270 => Self::Deg90,
+            other => other,
+        };
+        inverse.to_physical_rect(rect, self.logical_size(physical))
+    }
+}
+
 /// Rectangle for custom overlay slot coordinates.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct OverlayRect {
     pub x: i32,
     pub y: i32,
@@ -160,15 +818,93 @@ pub struct OverlayRect {
 
 /// Overlay content payload.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum OverlayContent {
     /// Text payload (resolved by the app/backend).
     Text(String),
     /// Backend-agnostic draw command payload.
     Command(DrawCommand),
+    /// Bitmap or icon payload placed within the slot.
+    Image(OverlayImage),
+}
+
+/// Bitmap/icon overlay payload (battery icons, status glyphs, logos).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct OverlayImage {
+    /// Stable icon/resource key (e.g. `"battery-75"`), used by backends to
+    /// resolve the icon when `bitmap` is `None`.
+    pub key: String,
+    /// Pre-rasterized pixels, when supplied by the composer.
+    pub bitmap: Option<GrayBitmap>,
+    /// Intrinsic size in pixels.
+    pub size: OverlaySize,
+    /// Largest placed size; larger images are scaled down, keeping aspect.
+    pub max_size: Option<OverlaySize>,
+}
+
+impl OverlayImage {
+    /// Image backed by `bitmap`, at its intrinsic size.
+    pub fn from_bitmap(key: impl Into<String>, bitmap: GrayBitmap) -> Self {
+        Self {
+            key: key.into(),
+            size: OverlaySize {
+                width: bitmap.width,
+                height: bitmap.height,
+            },
+            bitmap: Some(bitmap),
+            max_size: None,
+        }
+    }
+
+    /// Placed size: intrinsic size fitted within `max_size` and `bounds`.
+    ///
+    /// Never upscales; each side is at least one pixel.
+    pub fn placed_size(&self, bounds: Option<OverlaySize>) -> OverlaySize {
+        let mut scale = 1.0f32;
+        for limit in [self.max_size, bounds].into_iter().flatten() {
+            scale = scale
+                .min(limit.width as f32 / self.size.width.max(1) as f32)
+                .min(limit.height as f32 / self.size.height.max(1) as f32);
+        }
+        OverlaySize {
+            width: ((self.size.width as f32 * scale) as u32).max(1),
+            height: ((self.size.height as f32 * scale) as u32).max(1),
+        }
+    }
+
+    /// Image command placing this payload in `slot` of a `viewport`.
+    ///
+    /// Custom slots also bound the placed size. Bitmaps are downsampled to
+    /// the placed size.
+    pub fn to_command(&self, slot: &OverlaySlot, viewport: OverlaySize) -> DrawCommand {
+        let bounds = match slot {
+            OverlaySlot::Custom(rect) => Some(OverlaySize {
+                width: rect.width,
+                height: rect.height,
+            }),
+            _ => None,
+        };
+        let size = self.placed_size(bounds);
+        let rect = slot.place(viewport, size);
+        DrawCommand::Image(ImageCommand {
+            x: rect.x,
+            y: rect.y,
+            width: rect.width,
+            height: rect.height,
+            src: self.key.clone(),
+            alt: String::with_capacity(0),
+            bitmap: self
+                .bitmap
+                .as_ref()
+                .map(|bitmap| bitmap.downsample(size.width, size.height)),
+        })
+    }
 }
 
 /// Overlay item attached to a page.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct OverlayItem {
     /// Destination slot.
     pub slot: OverlaySlot,
@@ -183,8 +919,94 @@ pub trait OverlayComposer {
     fn compose(&self, metrics: &PageMetrics, viewport: OverlaySize) -> Vec<OverlayItem>;
 }
 
+/// How `OverlayComposerChain` resolves items from different composers that
+/// target the same slot.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverlayConflict {
+    /// Keep every item; on equal `z`, higher-priority composers draw on top.
+    #[default]
+    Stack,
+    /// Keep only the items of the highest-priority composer using the slot.
+    Suppress,
+}
+
+/// Several overlay composers merged into one, by priority.
+///
+/// Composers run in priority order, lowest first; equal priorities keep
+/// registration order, later ones ranking higher. Merged items are ordered
+/// by `z`, then rank, so draw order is deterministic.
+#[derive(Default)]
+pub struct OverlayComposerChain {
+    composers: Vec<(i32, Box<dyn OverlayComposer>)>,
+    conflict: OverlayConflict,
+}
+
+impl core::fmt::Debug for OverlayComposerChain {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OverlayComposerChain")
+            .field("composers", &self.composers.len())
+            .field("conflict", &self.conflict)
+            .finish()
+    }
+}
+
+impl OverlayComposerChain {
+    /// Empty chain stacking colliding items.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a composer at `priority`.
+    pub fn with<O: OverlayComposer + 'static>(mut self, priority: i32, composer: O) -> Self {
+        self.push(priority, composer);
+        self
+    }
+
+    /// Set how slot collisions are resolved.
+    pub fn with_conflict(mut self, conflict: OverlayConflict) -> Self {
+        self.conflict = conflict;
+        self
+    }
+
+    /// Add a composer at `priority`.
+    pub fn push<O: OverlayComposer + 'static>(&mut self, priority: i32, composer: O) {
+        let at = self
+            .composers
+            .partition_point(|(existing, _)| *existing <= priority);
+        self.composers.insert(at, (priority, Box::new(composer)));
+    }
+
+    /// Number of registered composers.
+    pub fn len(&self) -> usize {
+        self.composers.len()
+    }
+
+    /// Whether no composers are registered.
+    pub fn is_empty(&self) -> bool {
+        self.composers.is_empty()
+    }
+}
+
+impl OverlayComposer for OverlayComposerChain {
+    fn compose(&self, metrics: &PageMetrics, viewport: OverlaySize) -> Vec<OverlayItem> {
+        let mut ranked: Vec<(usize, OverlayItem)> = Vec::with_capacity(self.composers.len() * 2);
+        for (rank, (_, composer)) in self.composers.iter().enumerate() {
+            let items = composer.compose(metrics, viewport);
+            if self.conflict == OverlayConflict::Suppress {
+                // Lower ranks were composed first: drop their claims on
+                // slots this composer uses.
+                ranked.retain(|(_, kept)| !items.iter().any(|item| item.slot == kept.slot));
+            }
+            ranked.extend(items.into_iter().map(|item| (rank, item)));
+        }
+        ranked.sort_by_key(|(rank, item)| (item.z, *rank));
+        ranked.into_iter().map(|(_, item)| item).collect()
+    }
+}
+
 /// Layout output commands.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum DrawCommand {
     /// Draw text.
     Text(TextCommand),
@@ -194,10 +1016,232 @@ pub enum DrawCommand {
     Rect(RectCommand),
     /// Draw page metadata/chrome.
     PageChrome(PageChromeCommand),
+    /// Draw an image or rasterized SVG.
+    Image(ImageCommand),
+    /// Restrict following commands in the same layer to the intersection of
+    /// this rectangle and the current clip, until the matching `PopClip`.
+    PushClip(PageRect),
+    /// Restore the clip active before the matching `PushClip`.
+    PopClip,
+    /// Draw a command at an explicit stacking order.
+    Layered(LayeredCommand),
+}
+
+impl DrawCommand {
+    /// Stacking order of this command (`0` unless explicitly layered).
+    pub fn z_index(&self) -> i32 {
+        match self {
+            Self::Layered(layered) => layered.z,
+            _ => 0,
+        }
+    }
+
+    /// Approximate bytes held by this command, including heap payloads.
+    pub fn approx_bytes(&self) -> usize {
+        let heap = match self {
+            Self::Text(text) => {
+                text.text.capacity()
+                    + text.style.family.capacity()
+                    + text.glyphs.as_ref().map_or(0, |glyphs| {
+                        glyphs.capacity() * core::mem::size_of::<GlyphPosition>()
+                    })
+            }
+            Self::Image(image) => {
+                image.src.capacity()
+                    + image.alt.capacity()
+                    + image
+                        .bitmap
+                        .as_ref()
+                        .map_or(0, |bitmap| bitmap.pixels.capacity())
+            }
+            Self::PageChrome(chrome) => chrome.text.as_ref().map_or(0, String::capacity),
+            Self::Layered(layered) => layered.command.approx_bytes(),
+            Self::Rule(_) | Self::Rect(_) | Self::PushClip(_) | Self::PopClip => 0,
+        };
+        core::mem::size_of::<Self>() + heap
+    }
+
+    /// Conservative pixel bounds covered by this command.
+    ///
+    /// Text bounds are estimated from style metrics and may over-cover.
+    /// Returns `None` for page chrome markers, whose geometry is backend-owned,
+    /// and for clip pops. Clip pushes report the clip rectangle.
+    pub fn bounds(&self) -> Option<PageRect> {
+        match self {
+            Self::Text(text) => Some(text.approx_bounds()),
+            Self::Rule(rule) => Some(if rule.horizontal {
+                PageRect::new(rule.x, rule.y, rule.length, rule.thickness.max(1))
+            } else {
+                PageRect::new(rule.x, rule.y, rule.thickness.max(1), rule.length)
+            }),
+            Self::Rect(rect) => Some(PageRect::new(rect.x, rect.y, rect.width, rect.height)),
+            Self::Image(image) => Some(PageRect::new(image.x, image.y, image.width, image.height)),
+            Self::PushClip(rect) => Some(*rect),
+            Self::Layered(layered) => layered.command.bounds(),
+            Self::PageChrome(_) | Self::PopClip => None,
+        }
+    }
+}
+
+/// Draw command with an explicit z-index.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct LayeredCommand {
+    /// Stacking order; higher values draw later. Ties keep stream order.
+    pub z: i32,
+    /// Wrapped command.
+    pub command: Box<DrawCommand>,
+}
+
+/// One drawable command resolved for compositing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CompositeItem<'a> {
+    /// Effective stacking order.
+    pub z: i32,
+    /// Effective clip rectangle, if any; empty when fully clipped.
+    pub clip: Option<PageRect>,
+    /// Command to draw (never a clip or layered wrapper).
+    pub command: &'a DrawCommand,
+}
+
+impl RenderPage {
+    /// Flatten layers into draw order for simple backends.
+    ///
+    /// Content, chrome, and overlay layers are walked in that order (or the
+    /// legacy `commands` stream when all three are empty); see
+    /// [`composite_layers`].
+    pub fn composite(&self) -> Vec<CompositeItem<'_>> {
+        if self.content_commands.is_empty()
+            && self.chrome_commands.is_empty()
+            && self.overlay_commands.is_empty()
+        {
+            return composite_layers(&[&self.commands]);
+        }
+        composite_layers(&[
+            &self.content_commands,
+            &self.chrome_commands,
+            &self.overlay_commands,
+        ])
+    }
+}
+
+/// Resolve clips and z-order across command layers.
+///
+/// Each layer has its own clip stack. The result is stably sorted by z-index,
+/// so equal-z commands keep layer/stream order, and every item carries the
+/// clip that was active where it appeared.
+pub fn composite_layers<'a>(layers: &[&'a [DrawCommand]]) -> Vec<CompositeItem<'a>> {
+    let mut items = Vec::with_capacity(layers.iter().map(|layer| layer.len()).sum());
+    for layer in layers {
+        let mut clips: Vec<PageRect> = Vec::with_capacity(0);
+        for cmd in layer.iter() {
+            let clip = clips.last().copied();
+            match cmd {
+                DrawCommand::PushClip(rect) => {
+                    let next = match clip {
+                        Some(current) => rect.clip(&current).unwrap_or_default(),
+                        None => *rect,
+                    };
+                    clips.push(next);
+                }
+                DrawCommand::PopClip => {
+                    clips.pop();
+                }
+                _ => {
+                    let mut command = cmd;
+                    while let DrawCommand::Layered(layered) = command {
+                        command = &layered.command;
+                    }
+                    if matches!(command, DrawCommand::PushClip(_) | DrawCommand::PopClip) {
+                        continue;
+                    }
+                    items.push(CompositeItem {
+                        z: cmd.z_index(),
+                        clip,
+                        command,
+                    });
+                }
+            }
+        }
+    }
+    items.sort_by_key(|item| item.z);
+    items
+}
+
+/// Axis-aligned rectangle in page pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct PageRect {
+    /// Left x.
+    pub x: i32,
+    /// Top y.
+    pub y: i32,
+    /// Width.
+    pub width: u32,
+    /// Height.
+    pub height: u32,
+}
+
+impl PageRect {
+    /// Create a rectangle.
+    pub const fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Exclusive right edge.
+    pub fn right(&self) -> i32 {
+        self.x.saturating_add(self.width as i32)
+    }
+
+    /// Exclusive bottom edge.
+    pub fn bottom(&self) -> i32 {
+        self.y.saturating_add(self.height as i32)
+    }
+
+    /// True when the rectangle covers no pixels.
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Smallest rectangle covering both inputs.
+    pub fn union(&self, other: &PageRect) -> PageRect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        PageRect::new(
+            x,
+            y,
+            (self.right().max(other.right()) - x).max(0) as u32,
+            (self.bottom().max(other.bottom()) - y).max(0) as u32,
+        )
+    }
+
+    /// True when the rectangles overlap or share an edge.
+    pub fn touches(&self, other: &PageRect) -> bool {
+        self.x <= other.right()
+            && other.x <= self.right()
+            && self.y <= other.bottom()
+            && other.y <= self.bottom()
+    }
+
+    /// Intersection with `bounds`, or `None` when disjoint.
+    pub fn clip(&self, bounds: &PageRect) -> Option<PageRect> {
+        let x = self.x.max(bounds.x);
+        let y = self.y.max(bounds.y);
+        let right = self.right().min(bounds.right());
+        let bottom = self.bottom().min(bounds.bottom());
+        (right > x && bottom > y)
+            .then(|| PageRect::new(x, y, (right - x) as u32, (bottom - y) as u32))
+    }
 }
 
 /// Theme-aware render intent.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RenderIntent {
     /// Convert output to grayscale mode.
     pub grayscale_mode: GrayscaleMode,
@@ -205,6 +1249,19 @@ pub struct RenderIntent {
     pub dither: DitherMode,
     /// Contrast multiplier in percent (100 = neutral).
     pub contrast_boost: u8,
+    /// Pixel format of the output device.
+    pub color_target: ColorTarget,
+    /// Inverted (night mode) output: light content on a dark page.
+    ///
+    /// Tones are inverted before quantization, so dithered images keep their
+    /// detail instead of being bit-flipped after the fact.
+    pub inverted: bool,
+    /// Draft (low-power) output for previews and low-battery modes.
+    ///
+    /// Images are laid out as outlined bounding boxes without decoding,
+    /// anti-aliasing is not requested, and page chrome is reduced to the
+    /// plain footer.
+    pub draft: bool,
 }
 
 impl Default for RenderIntent {
@@ -213,25 +1270,453 @@ impl Default for RenderIntent {
             grayscale_mode: GrayscaleMode::Off,
             dither: DitherMode::None,
             contrast_boost: 100,
+            color_target: ColorTarget::Grayscale,
+            inverted: false,
+            draft: false,
         }
     }
 }
 
+impl RenderIntent {
+    /// Whether backends should anti-alias shape edges.
+    ///
+    /// Off for draft output and 1-bit targets, where gray edge pixels are
+    /// either wasted work or dithered into noise. Text runs carry their own
+    /// `TextRenderHint`; see `RenderIntent::text_hint`.
+    pub fn antialias(&self) -> bool {
+        !self.draft && self.color_target != ColorTarget::Mono
+    }
+
+    /// Rasterization hint for a text run at `size_px`.
+    ///
+    /// 1-bit targets threshold body text but let display-size runs be
+    /// rendered with coverage and dithered, where hard edges look jagged.
+    pub fn text_hint(&self, size_px: f32) -> TextRenderHint {
+        if self.draft {
+            return TextRenderHint::Aliased;
+        }
+        match self.color_target {
+            ColorTarget::Mono if size_px >= TextRenderHint::MONO_AA_MIN_SIZE_PX => {
+                TextRenderHint::Gray4
+            }
+            ColorTarget::Mono => TextRenderHint::Aliased,
+            ColorTarget::Gray4 => TextRenderHint::Gray4,
+            ColorTarget::Grayscale | ColorTarget::Rgb565 | ColorTarget::Rgb888 => {
+                TextRenderHint::Grayscale
+            }
+        }
+    }
+
+    /// Explicit color for content that would use the default foreground.
+    ///
+    /// `None` (backend default) unless output is inverted.
+    pub fn foreground(&self) -> Option<Color> {
+        self.inverted.then(|| self.map_color(Color::BLACK))
+    }
+
+    /// Page background color in the output target.
+    pub fn background(&self) -> Color {
+        self.map_color(Color::WHITE)
+    }
+
+    /// Map a source color into the output target without dithering.
+    ///
+    /// Applies grayscale conversion, contrast boost, and target quantization.
+    pub fn map_color(&self, color: Color) -> Color {
+        let channels = self.adjust(color);
+        let levels = self.color_target.channel_levels();
+        Color::rgb(
+            quantize_channel(channels[0] as i32, levels[0]),
+            quantize_channel(channels[1] as i32, levels[1]),
+            quantize_channel(channels[2] as i32, levels[2]),
+        )
+    }
+
+    /// Map a row-major pixel buffer into the output target in place.
+    ///
+    /// Dithering works per channel using the channel depth of the target, so
+    /// RGB565 green gets finer steps than red/blue and 1-bit targets get
+    /// full black/white thresholds.
+    pub fn dither_pixels(&self, pixels: &mut [Color], width: usize) {
+        if width == 0 {
+            return;
+        }
+        let levels = self.color_target.channel_levels();
+        match self.dither {
+            DitherMode::None => {
+                for px in pixels.iter_mut() {
+                    *px = self.map_color(*px);
+                }
+            }
+            DitherMode::Ordered | DitherMode::Bayer8x8 | DitherMode::BlueNoise => {
+                for (idx, px) in pixels.iter_mut().enumerate() {
+                    let (x, y) = (idx % width, idx / width);
+                    // Threshold in [0, 1) centered to (-0.5, 0.5) of a level step.
+                    let threshold = match self.dither {
+                        DitherMode::Ordered => (BAYER_4X4[y % 4][x % 4] as f32 + 0.5) / 16.0,
+                        DitherMode::Bayer8x8 => (bayer_8x8(x, y) as f32 + 0.5) / 64.0,
+                        _ => (BLUE_NOISE_16X16[y % 16][x % 16] as f32 + 0.5) / 256.0,
+                    } - 0.5;
+                    let channels = self.adjust(*px);
+                    let mut out = [0u8; 3];
+                    for c in 0..3 {
+                        let step = 255.0 / levels[c] as f32;
+                        let offset = (threshold * step) as i32;
+                        out[c] = quantize_channel(channels[c] as i32 + offset, levels[c]);
+                    }
+                    *px = Color::rgb(out[0], out[1], out[2]);
+                }
+            }
+            DitherMode::ErrorDiffusion => {
+                self.diffuse(pixels, width, &FLOYD_STEINBERG, 16);
+            }
+            DitherMode::Atkinson => {
+                self.diffuse(pixels, width, &ATKINSON, 8);
+            }
+        }
+    }
+
+    /// Map a grayscale bitmap into the output target in place.
+    ///
+    /// Weighted grayscale with `contrast_stretch` stretches it first.
+    pub fn dither_bitmap(&self, bitmap: &mut GrayBitmap) {
+        if let GrayscaleMode::Weighted(weights) = self.grayscale_mode {
+            if weights.contrast_stretch {
+                stretch_contrast(&mut bitmap.pixels);
+            }
+        }
+        let mut pixels: Vec<Color> = bitmap
+            .pixels
+            .iter()
+            .map(|&luma| Color::rgb(luma, luma, luma))
+            .collect();
+        self.dither_pixels(&mut pixels, bitmap.width as usize);
+        for (dst, px) in bitmap.pixels.iter_mut().zip(pixels) {
+            *dst = px.luma();
+        }
+    }
+
+    /// Error diffusion with a `(dx, dy, weight)` kernel over `divisor`.
+    ///
+    /// Kernels whose weights sum below `divisor` (Atkinson) intentionally
+    /// drop part of the error, which keeps highlights/shadows clean.
+    fn diffuse(&self, pixels: &mut [Color], width: usize, kernel: &[(i8, u8, i32)], divisor: i32) {
+        const ROWS: usize = 3;
+        const PAD: usize = 2;
+        let levels = self.color_target.channel_levels();
+        let stride = width + PAD * 2;
+        let mut errors = vec![[0i32; 3]; stride * ROWS];
+        for (y, row) in pixels.chunks_mut(width).enumerate() {
+            let base = (y % ROWS) * stride;
+            for (x, px) in row.iter_mut().enumerate() {
+                let channels = self.adjust(*px);
+                let acc = errors[base + x + PAD];
+                let mut out = [0u8; 3];
+                let mut err = [0i32; 3];
+                for c in 0..3 {
+                    let value = (channels[c] as i32 + acc[c] / divisor).clamp(0, 255);
+                    out[c] = quantize_channel(value, levels[c]);
+                    err[c] = value - out[c] as i32;
+                }
+                for &(dx, dy, weight) in kernel {
+                    let slot = ((y + dy as usize) % ROWS) * stride
+                        + (x + PAD).wrapping_add_signed(dx as isize);
+                    for c in 0..3 {
+                        errors[slot][c] += err[c] * weight;
+                    }
+                }
+                *px = Color::rgb(out[0], out[1], out[2]);
+            }
+            errors[base..base + stride].fill([0; 3]);
+        }
+    }
+
+    fn adjust(&self, color: Color) -> [u8; 3] {
+        let channels = match self.grayscale_mode {
+            GrayscaleMode::Weighted(weights) => {
+                let luma = weights.luma(color.r, color.g, color.b);
+                [luma, luma, luma]
+            }
+            GrayscaleMode::Luminosity => [color.luma(); 3],
+            GrayscaleMode::Off if self.color_target.is_grayscale() => [color.luma(); 3],
+            GrayscaleMode::Off => [color.r, color.g, color.b],
+        };
+        let boost = self.contrast_boost as i32;
+        channels.map(|ch| {
+            let ch = (128 + (ch as i32 - 128) * boost / 100).clamp(0, 255) as u8;
+            if self.inverted {
+                255 - ch
+            } else {
+                ch
+            }
+        })
+    }
+}
+
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// 8x8 Bayer index built from the 4x4 matrix.
+fn bayer_8x8(x: usize, y: usize) -> u8 {
+    let coarse = BAYER_4X4[(y / 2) % 4][(x / 2) % 4];
+    let fine = [[0, 2], [3, 1]][y % 2][x % 2];
+    fine * 16 + coarse
+}
+
+/// Void-and-cluster threshold ranks (0..=255), tileable.
+const BLUE_NOISE_16X16: [[u8; 16]; 16] = [
+    [
+        2, 86, 141, 232, 30, 122, 178, 134, 64, 1, 90, 128, 21, 72, 98, 42,
+    ],
+    [
+        243, 185, 20, 66, 188, 81, 244, 99, 218, 145, 195, 50, 181, 226, 194, 153,
+    ],
+    [
+        60, 127, 203, 97, 222, 138, 38, 18, 167, 110, 32, 239, 107, 10, 120, 82,
+    ],
+    [
+        214, 166, 46, 117, 7, 171, 202, 77, 255, 58, 207, 150, 65, 163, 251, 29,
+    ],
+    [
+        104, 12, 247, 156, 236, 56, 106, 154, 189, 130, 9, 84, 220, 36, 135, 182,
+    ],
+    [
+        225, 143, 68, 85, 34, 125, 221, 15, 45, 94, 227, 175, 114, 201, 80, 54,
+    ],
+    [
+        23, 190, 129, 210, 177, 197, 70, 142, 234, 165, 27, 137, 51, 3, 238, 160,
+    ],
+    [
+        91, 44, 231, 0, 103, 25, 248, 87, 113, 206, 69, 249, 187, 101, 211, 123,
+    ],
+    [
+        254, 169, 109, 62, 164, 149, 48, 180, 5, 41, 126, 83, 157, 33, 144, 67,
+    ],
+    [
+        199, 31, 140, 213, 242, 74, 124, 224, 193, 147, 233, 13, 217, 59, 179, 11,
+    ],
+    [
+        118, 79, 186, 14, 92, 196, 17, 95, 57, 168, 102, 200, 116, 246, 96, 223,
+    ],
+    [
+        159, 245, 55, 132, 40, 229, 158, 133, 253, 26, 73, 47, 152, 24, 136, 43,
+    ],
+    [
+        8, 93, 151, 219, 115, 174, 63, 35, 208, 119, 230, 176, 89, 191, 75, 204,
+    ],
+    [
+        228, 183, 22, 192, 76, 4, 241, 88, 184, 16, 139, 215, 6, 240, 170, 108,
+    ],
+    [
+        131, 71, 37, 252, 100, 148, 198, 111, 155, 78, 39, 105, 61, 121, 28, 52,
+    ],
+    [
+        173, 212, 112, 162, 53, 216, 19, 49, 237, 205, 172, 250, 161, 209, 146, 235,
+    ],
+];
+
+const FLOYD_STEINBERG: [(i8, u8, i32); 4] = [(1, 0, 7), (-1, 1, 3), (0, 1, 5), (1, 1, 1)];
+
+const ATKINSON: [(i8, u8, i32); 6] = [
+    (1, 0, 1),
+    (2, 0, 1),
+    (-1, 1, 1),
+    (0, 1, 1),
+    (1, 1, 1),
+    (0, 2, 1),
+];
+
+/// Snap `value` to the nearest of `levels + 1` evenly spaced channel values.
+fn quantize_channel(value: i32, levels: u8) -> u8 {
+    let value = value.clamp(0, 255) as u32;
+    if levels == u8::MAX {
+        return value as u8;
+    }
+    let levels = levels.max(1) as u32;
+    let level = (value * levels + 127) / 255;
+    (level * 255 / levels) as u8
+}
+
+/// Color to gray conversion for output colors and decoded images.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum GrayscaleMode {
+    /// Keep colors; grayscale targets and image decoders use `Luminosity`.
     Off,
+    /// Rec. 601 luma of the gamma-encoded channels (`Color::luma`).
     Luminosity,
+    /// Configurable coefficients mixed in linear light, with optional
+    /// contrast stretch of image bitmaps.
+    Weighted(GrayscaleWeights),
 }
 
+/// Coefficients and transfer curve of `GrayscaleMode::Weighted`.
+///
+/// Channels are decoded with `gamma_x100 / 100`, mixed by the weights
+/// (normalized by their sum), then re-encoded. Neutral grays map to
+/// themselves whatever the settings.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct GrayscaleWeights {
+    /// Red weight.
+    pub red: u16,
+    /// Green weight.
+    pub green: u16,
+    /// Blue weight.
+    pub blue: u16,
+    /// Transfer exponent in hundredths; `100` mixes encoded values.
+    pub gamma_x100: u16,
+    /// Stretch each image so its darkest and lightest percent reach black
+    /// and white; lifts faded scans and text-in-image pages.
+    pub contrast_stretch: bool,
+}
+
+impl GrayscaleWeights {
+    /// Rec. 601 weights on encoded values, matching `Luminosity`.
+    pub const REC601: Self = Self {
+        red: 299,
+        green: 587,
+        blue: 114,
+        gamma_x100: 100,
+        contrast_stretch: false,
+    };
+
+    /// Rec. 709 weights in linear light with a 2.2 transfer curve.
+    pub const REC709_LINEAR: Self = Self {
+        red: 2126,
+        green: 7152,
+        blue: 722,
+        gamma_x100: 220,
+        contrast_stretch: false,
+    };
+
+    /// Gray level of an sRGB color.
+    pub fn luma(&self, r: u8, g: u8, b: u8) -> u8 {
+        if r == g && g == b {
+            return r;
+        }
+        self.mix([r, g, b].map(|channel| self.linearize(channel)))
+    }
+
+    /// Channel value in linear light, `0.0..=1.0`.
+    pub(crate) fn linearize(&self, value: u8) -> f32 {
+        let value = value as f32 / 255.0;
+        match self.gamma_x100 {
+            100 => value,
+            gamma => value.powf(gamma.max(1) as f32 / 100.0),
+        }
+    }
+
+    /// Weighted mix of linear channels, re-encoded.
+    pub(crate) fn mix(&self, linear: [f32; 3]) -> u8 {
+        let weights = [self.red, self.green, self.blue].map(|weight| weight as f32);
+        let total = (weights[0] + weights[1] + weights[2]).max(1.0);
+        let mixed =
+            (linear[0] * weights[0] + linear[1] * weights[1] + linear[2] * weights[2]) / total;
+        let encoded = match self.gamma_x100 {
+            100 => mixed,
+            gamma => mixed.powf(100.0 / gamma.max(1) as f32),
+        };
+        (encoded * 255.0 + 0.5).clamp(0.0, 255.0) as u8
+    }
+}
+
+impl Default for GrayscaleWeights {
+    fn default() -> Self {
+        Self::REC709_LINEAR
+    }
+}
+
+/// Linearly stretch `pixels` so the darkest and lightest percent clip to
+/// black and white; near-uniform images are left alone.
+fn stretch_contrast(pixels: &mut [u8]) {
+    let mut histogram = [0usize; 256];
+    for &value in pixels.iter() {
+        histogram[value as usize] += 1;
+    }
+    let clip = pixels.len() / 100;
+    let low = clipped_level(&histogram, clip, 0..256);
+    let high = clipped_level(&histogram, clip, (0..256).rev());
+    if high <= low + 8 {
+        return;
+    }
+    let span = (high - low) as u32;
+    for value in pixels.iter_mut() {
+        let stretched = (*value as u32).saturating_sub(low as u32) * 255 / span;
+        *value = stretched.min(255) as u8;
+    }
+}
+
+/// First of `levels` where more than `clip` pixels have been passed.
+fn clipped_level(
+    histogram: &[usize; 256],
+    clip: usize,
+    levels: impl Iterator<Item = usize>,
+) -> usize {
+    let mut seen = 0;
+    let mut last = 0;
+    for level in levels {
+        seen += histogram[level];
+        last = level;
+        if seen > clip {
+            break;
+        }
+    }
+    last
+}
+
+/// Output pixel format targeted by render intent color mapping.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum ColorTarget {
+    /// 8-bit grayscale.
+    Grayscale,
+    /// 1-bit black/white e-ink.
+    Mono,
+    /// 4-bit (16-level) grayscale e-ink.
+    Gray4,
+    /// 16-bit RGB565 (LCD panels, color e-ink such as Kaleido).
+    Rgb565,
+    /// 24-bit RGB888.
+    Rgb888,
+}
+
+impl ColorTarget {
+    /// Highest quantized level per RGB channel.
+    fn channel_levels(self) -> [u8; 3] {
+        match self {
+            Self::Grayscale | Self::Rgb888 => [u8::MAX; 3],
+            Self::Mono => [1; 3],
+            Self::Gray4 => [15; 3],
+            Self::Rgb565 => [31, 63, 31],
+        }
+    }
+
+    fn is_grayscale(self) -> bool {
+        matches!(self, Self::Grayscale | Self::Mono | Self::Gray4)
+    }
+}
+
+/// Dithering algorithm applied when quantizing to the color target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum DitherMode {
     None,
+    /// 4x4 Bayer ordered dither.
     Ordered,
+    /// Floyd-Steinberg error diffusion.
     ErrorDiffusion,
+    /// Atkinson error diffusion (3/4 of the error, crisp on 1-bit panels).
+    Atkinson,
+    /// 8x8 Bayer ordered dither (smoother ramps on 4-bit panels).
+    Bayer8x8,
+    /// Blue-noise threshold mask; stable per pixel, so partial refreshes
+    /// do not shimmer.
+    BlueNoise,
 }
 
 /// Resolved style passed to renderer.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ResolvedTextStyle {
     /// Stable font identifier for this style.
     pub font_id: Option<u32>,
@@ -251,10 +1736,60 @@ pub struct ResolvedTextStyle {
     pub role: BlockRole,
     /// Justification mode from layout.
     pub justify_mode: JustifyMode,
+    /// Text color mapped to the render intent target (`None` = default foreground).
+    pub color: Option<Color>,
+    /// Vertical font metrics at `size_px`.
+    pub metrics: VerticalMetrics,
+}
+
+impl ResolvedTextStyle {
+    /// Line box height in pixels (`size_px * line_height`).
+    pub fn line_box_px(&self) -> f32 {
+        self.size_px * self.line_height
+    }
+
+    /// Distance from the top of the line box to the baseline.
+    ///
+    /// Text command `baseline_y` values are line-box tops; add this to get
+    /// the typographic baseline. Leading is split evenly above and below.
+    pub fn baseline_offset_px(&self) -> f32 {
+        let content = self.metrics.ascent_px + self.metrics.descent_px;
+        ((self.line_box_px() - content) / 2.0).max(0.0) + self.metrics.ascent_px
+    }
+}
+
+/// Vertical font metrics in pixels, measured from the baseline.
+///
+/// Layout fills these with `VerticalMetrics::estimate`; backends with real
+/// face data may substitute measured values.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct VerticalMetrics {
+    /// Height above the baseline.
+    pub ascent_px: f32,
+    /// Depth below the baseline (positive).
+    pub descent_px: f32,
+    /// Height of lowercase letters such as `x`.
+    pub x_height_px: f32,
+    /// Height of flat capital letters such as `H`.
+    pub cap_height_px: f32,
+}
+
+impl VerticalMetrics {
+    /// Typical text-face proportions for a font of `size_px`.
+    pub fn estimate(size_px: f32) -> Self {
+        Self {
+            ascent_px: size_px * 0.8,
+            descent_px: size_px * 0.2,
+            x_height_px: size_px * 0.48,
+            cap_height_px: size_px * 0.7,
+        }
+    }
 }
 
 /// Justification mode determined during layout.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum JustifyMode {
     /// Left/no justification.
     None,
@@ -264,6 +1799,7 @@ pub enum JustifyMode {
 
 /// Text draw command.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct TextCommand {
     /// Left x.
     pub x: i32,
@@ -275,10 +1811,93 @@ pub struct TextCommand {
     pub font_id: Option<u32>,
     /// Resolved style.
     pub style: ResolvedTextStyle,
+    /// Per-char placement decided by layout (one entry per `char` in `text`).
+    ///
+    /// Present when justification or letter spacing adjusted the line and
+    /// `TypographyConfig::emit_glyph_positions` is enabled. Backends should
+    /// place glyphs from these instead of re-measuring.
+    pub glyphs: Option<Vec<GlyphPosition>>,
+    /// Anti-aliasing hint derived from the render intent and font size.
+    pub hint: TextRenderHint,
+}
+
+/// Per-run text rasterization hint.
+///
+/// Backends may ignore it; 1-bit backends use it to choose between
+/// thresholding and dithering glyph coverage run by run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum TextRenderHint {
+    /// Hard-edged glyphs; threshold coverage to 1 bit.
+    #[default]
+    Aliased,
+    /// Anti-aliased with 16 coverage levels.
+    Gray4,
+    /// Anti-aliased with full 8-bit coverage.
+    Grayscale,
+}
+
+impl TextRenderHint {
+    /// Smallest font size anti-aliased on 1-bit targets.
+    pub const MONO_AA_MIN_SIZE_PX: f32 = 24.0;
+
+    /// Distinct coverage levels a backend should produce.
+    pub fn coverage_levels(self) -> u16 {
+        match self {
+            Self::Aliased => 2,
+            Self::Gray4 => 16,
+            Self::Grayscale => 256,
+        }
+    }
+}
+
+/// Layout-decided placement of one glyph.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct GlyphPosition {
+    /// Pen advance after this glyph, in pixels.
+    pub x_advance: i32,
+    /// Horizontal draw offset from the pen position, in pixels.
+    pub x_offset: i32,
+}
+
+impl TextCommand {
+    /// Conservative text box: top at `baseline_y`, wide enough for common
+    /// backend faces plus letter spacing and justification slack.
+    pub fn approx_bounds(&self) -> PageRect {
+        let chars = self.text.chars().count() as f32;
+        let style = &self.style;
+        let height = style.size_px * style.line_height.max(1.0);
+        if let Some(glyphs) = &self.glyphs {
+            let advance: i32 = glyphs.iter().map(|g| g.x_advance).sum();
+            // Glyph ink can overhang the final advance; pad by one em.
+            let width = advance.max(0) as f32 + style.size_px;
+            return PageRect::new(
+                self.x,
+                self.baseline_y,
+                width.ceil() as u32,
+                height.ceil().max(1.0) as u32,
+            );
+        }
+        let mut width = chars * style.size_px * 0.6;
+        if chars > 1.0 {
+            width += (chars - 1.0) * style.letter_spacing.max(0.0);
+        }
+        if let JustifyMode::InterWord { extra_px_total } = style.justify_mode {
+            width += extra_px_total.max(0) as f32;
+        }
+        PageRect::new(
+            self.x,
+            self.baseline_y,
+            width.ceil().max(0.0) as u32,
+            height.ceil().max(1.0) as u32,
+        )
+    }
 }
 
 /// Rule draw command.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RuleCommand {
     /// Start x.
     pub x: i32,
@@ -294,6 +1913,7 @@ pub struct RuleCommand {
 
 /// Rectangle command.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RectCommand {
     /// Left x.
     pub x: i32,
@@ -305,10 +1925,110 @@ pub struct RectCommand {
     pub height: u32,
     /// Fill rectangle when true.
     pub fill: bool,
+    /// Stroke/fill color (`None` = default foreground).
+    pub color: Option<Color>,
+}
+
+/// Image draw command.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct ImageCommand {
+    /// Left x.
+    pub x: i32,
+    /// Top y.
+    pub y: i32,
+    /// Layout width.
+    pub width: u32,
+    /// Layout height.
+    pub height: u32,
+    /// Archive path of the source resource (empty for inline SVG islands).
+    pub src: String,
+    /// Alternative text.
+    pub alt: String,
+    /// Pre-rasterized pixels at layout size, when available.
+    pub bitmap: Option<GrayBitmap>,
+}
+
+/// 8-bit grayscale bitmap (0 = black, 255 = white), row-major.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GrayBitmap {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Luma samples, `width * height` bytes.
+    pub pixels: Vec<u8>,
+}
+
+impl GrayBitmap {
+    /// Luma at `(x, y)`, or `None` when out of bounds.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<u8> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.pixels
+            .get(y as usize * self.width as usize + x as usize)
+            .copied()
+    }
+
+    /// Box-filter down to `width`x`height` (never upsamples).
+    pub fn downsample(&self, width: u32, height: u32) -> GrayBitmap {
+        let width = width.min(self.width).max(1);
+        let height = height.min(self.height).max(1);
+        let mut pixels = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height {
+            let y0 = y * self.height / height;
+            let y1 = ((y + 1) * self.height / height).max(y0 + 1);
+            for x in 0..width {
+                let x0 = x * self.width / width;
+                let x1 = ((x + 1) * self.width / width).max(x0 + 1);
+                let mut sum = 0u32;
+                let mut count = 0u32;
+                for sy in y0..y1 {
+                    for sx in x0..x1 {
+                        if let Some(luma) = self.pixel(sx, sy) {
+                            sum += luma as u32;
+                            count += 1;
+                        }
+                    }
+                }
+                pixels.push(sum.checked_div(count).unwrap_or(255) as u8);
+            }
+        }
+        GrayBitmap {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// Largest side generated for arbitrary bitmaps, keeping fuzz inputs cheap.
+#[cfg(feature = "arbitrary")]
+const ARBITRARY_BITMAP_MAX_SIDE: u32 = 64;
+
+/// Generates bitmaps whose `pixels` always hold `width * height` samples.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for GrayBitmap {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let width = u.int_in_range(0..=ARBITRARY_BITMAP_MAX_SIDE)?;
+        let height = u.int_in_range(0..=ARBITRARY_BITMAP_MAX_SIDE)?;
+        let len = width as usize * height as usize;
+        let mut pixels = Vec::with_capacity(len);
+        for _ in 0..len {
+            pixels.push(u8::arbitrary(u)?);
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
 }
 
 /// Page-level metadata/chrome marker.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct PageChromeCommand {
     /// Semantic chrome kind.
     pub kind: PageChromeKind,
@@ -322,6 +2042,7 @@ pub struct PageChromeCommand {
 
 /// Kind of page-level metadata/chrome.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum PageChromeKind {
     /// Header marker.
     Header,
@@ -329,6 +2050,8 @@ pub enum PageChromeKind {
     Footer,
     /// Progress marker.
     Progress,
+    /// Whole-book progress text (percent complete, pages left).
+    BookProgress,
 }
 
 /// Text style for header/footer chrome rendering.
@@ -349,6 +2072,12 @@ pub struct PageChromeConfig {
     pub footer_enabled: bool,
     /// Emit/draw page progress bar.
     pub progress_enabled: bool,
+    /// Emit/draw whole-book progress text, right-aligned on the footer line.
+    pub book_progress_enabled: bool,
+    /// Header text template; see `PageChromeConfig::render_template`.
+    pub header_template: Option<&'static str>,
+    /// Footer text template; see `PageChromeConfig::render_template`.
+    pub footer_template: Option<&'static str>,
     /// Header text left x.
     pub header_x: i32,
     /// Header text baseline y.
@@ -378,6 +2107,9 @@ impl PageChromeConfig {
             header_enabled: true,
             footer_enabled: true,
             progress_enabled: true,
+            book_progress_enabled: true,
+            header_template: None,
+            footer_template: None,
             header_x: 8,
             header_baseline_y: 16,
             header_style: PageChromeTextStyle::Bold,
@@ -397,6 +2129,22 @@ impl PageChromeConfig {
         cfg.header_enabled = false;
         cfg.footer_enabled = false;
         cfg.progress_enabled = false;
+        cfg.book_progress_enabled = false;
+        cfg
+    }
+
+    /// Reduced chrome for draft output: the footer alone, untemplated.
+    ///
+    /// Drops the header, progress bars, and templates so chrome never needs
+    /// table-of-contents lookups or whole-book page counts.
+    pub const fn simplified(self) -> Self {
+        let mut cfg = self;
+        cfg.header_enabled = false;
+        cfg.progress_enabled = false;
+        cfg.book_progress_enabled = false;
+        cfg.header_template = None;
+        cfg.footer_template = None;
+        cfg.footer_style = PageChromeTextStyle::Regular;
         cfg
     }
 }
@@ -407,8 +2155,183 @@ impl Default for PageChromeConfig {
     }
 }
 
+impl PageChromeConfig {
+    /// Chrome-layer commands for `page` of a `total`-page chapter.
+    ///
+    /// Header/footer text comes from `provider`, then the configured
+    /// template, falling back to `Page N`.
+    pub fn compose(
+        &self,
+        page: &RenderPage,
+        total: usize,
+        provider: &dyn PageChromeProvider,
+    ) -> Vec<DrawCommand> {
+        let metrics = &page.metrics;
+        let text = |custom: Option<String>, template: Option<&str>| {
+            custom
+                .or_else(|| template.map(|template| Self::render_template(template, page, total)))
+                .unwrap_or_else(|| format!("Page {}", page.page_number))
+        };
+        let mut out = Vec::with_capacity(3);
+        if self.header_enabled {
+            out.push(DrawCommand::PageChrome(PageChromeCommand {
+                kind: PageChromeKind::Header,
+                text: Some(text(provider.header_text(metrics), self.header_template)),
+                current: None,
+                total: None,
+            }));
+        }
+        if self.footer_enabled {
+            out.push(DrawCommand::PageChrome(PageChromeCommand {
+                kind: PageChromeKind::Footer,
+                text: Some(text(provider.footer_text(metrics), self.footer_template)),
+                current: None,
+                total: None,
+            }));
+        }
+        if self.progress_enabled {
+            out.push(DrawCommand::PageChrome(PageChromeCommand {
+                kind: PageChromeKind::Progress,
+                text: None,
+                current: Some(page.page_number),
+                total: Some(total),
+            }));
+        }
+        if self.book_progress_enabled {
+            out.push(DrawCommand::PageChrome(PageChromeCommand {
+                kind: PageChromeKind::BookProgress,
+                text: provider.book_progress_text(metrics),
+                current: metrics.global_page_index.map(|idx| idx + 1),
+                total: metrics.global_page_count_estimate,
+            }));
+        }
+        out
+    }
+
+    /// Whether a header or footer template references `{chapter_title}`.
+    pub fn uses_chapter_title(&self) -> bool {
+        [self.header_template, self.footer_template]
+            .into_iter()
+            .flatten()
+            .any(|template| template.contains("{chapter_title}"))
+    }
+
+    /// Expand a chrome text template for `page` of a `total`-page chapter.
+    ///
+    /// Placeholders: `{page}`, `{chapter_pages}`, `{chapter_pages_left}`,
+    /// `{chapter_title}`, `{book_page}`, `{book_pages}`, `{book_pages_left}`,
+    /// and `{percent}`. Values not known yet expand to nothing; unrecognized
+    /// placeholders are kept verbatim.
+    pub fn render_template(template: &str, page: &RenderPage, total: usize) -> String {
+        let metrics = &page.metrics;
+        let number = |value: Option<usize>| value.map(|v| v.to_string()).unwrap_or_default();
+        let mut out = String::with_capacity(template.len() + 16);
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            out.push_str(&rest[..open]);
+            let tail = &rest[open..];
+            let Some(close) = tail.find('}') else {
+                rest = tail;
+                break;
+            };
+            let value = match &tail[1..close] {
+                "page" => Some(page.page_number.to_string()),
+                "chapter_pages" => Some(metrics.chapter_page_count.unwrap_or(total).to_string()),
+                "chapter_pages_left" => Some(number(
+                    metrics
+                        .pages_left_in_chapter
+                        .or(total.checked_sub(page.page_number)),
+                )),
+                "chapter_title" => Some(page.chapter_title.clone().unwrap_or_default()),
+                "book_page" => Some(number(metrics.global_page_index.map(|idx| idx + 1))),
+                "book_pages" => Some(number(metrics.global_page_count_estimate)),
+                "book_pages_left" => Some(number(metrics.pages_left_in_book)),
+                "percent" => Some(number(metrics.percent_complete().map(usize::from))),
+                _ => None,
+            };
+            match value {
+                Some(value) => out.push_str(&value),
+                None => out.push_str(&tail[..=close]),
+            }
+            rest = &tail[close + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// Dynamic page-chrome fields (clock, battery, sync status).
+///
+/// Providers are invoked each time chrome is composed, so the chrome layer of
+/// an already-rendered page can be refreshed via
+/// `RenderPage::recompose_chrome` without re-running layout. Returning `None`
+/// keeps the default text.
+pub trait PageChromeProvider {
+    /// Header text for the page described by `metrics`.
+    fn header_text(&self, _metrics: &PageMetrics) -> Option<String> {
+        None
+    }
+
+    /// Footer text for the page described by `metrics`.
+    fn footer_text(&self, _metrics: &PageMetrics) -> Option<String> {
+        None
+    }
+
+    /// Whole-book progress text, e.g. `37% · 214 pages left`.
+    ///
+    /// Defaults to percent complete and pages left once book pagination is
+    /// known (see `PageMetrics::apply_book_page_counts`).
+    fn book_progress_text(&self, metrics: &PageMetrics) -> Option<String> {
+        let percent = metrics.percent_complete()?;
+        Some(match metrics.pages_left_in_book {
+            Some(1) => format!("{}% \u{b7} 1 page left", percent),
+            Some(left) => format!("{}% \u{b7} {} pages left", percent, left),
+            None => format!("{}%", percent),
+        })
+    }
+}
+
+/// Provider that keeps the default `Page N` chrome text.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StaticPageChrome;
+
+impl PageChromeProvider for StaticPageChrome {}
+
+impl RenderPage {
+    /// Rebuild the chrome layer from `cfg` and fresh `provider` values.
+    ///
+    /// Content and overlay layers are untouched. The progress total is kept
+    /// from the existing chrome (or page metrics). Returns whether the chrome
+    /// layer changed.
+    pub fn recompose_chrome(
+        &mut self,
+        cfg: &PageChromeConfig,
+        provider: &dyn PageChromeProvider,
+    ) -> bool {
+        let total = self
+            .chrome_commands
+            .iter()
+            .find_map(|cmd| match cmd {
+                DrawCommand::PageChrome(chrome) if chrome.kind == PageChromeKind::Progress => {
+                    chrome.total
+                }
+                _ => None,
+            })
+            .or(self.metrics.chapter_page_count)
+            .unwrap_or(self.page_number);
+        let chrome = cfg.compose(self, total, provider);
+        if chrome == self.chrome_commands {
+            return false;
+        }
+        self.chrome_commands = chrome;
+        self.sync_commands();
+        true
+    }
+}
+
 /// Typography policy knobs for layout behavior.
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct TypographyConfig {
     /// Hyphenation policy.
     pub hyphenation: HyphenationConfig,
@@ -418,10 +2341,13 @@ pub struct TypographyConfig {
     pub justification: JustificationConfig,
     /// Hanging punctuation policy.
     pub hanging_punctuation: HangingPunctuationConfig,
+    /// Emit per-glyph positions on text commands whose spacing was adjusted.
+    pub emit_glyph_positions: bool,
 }
 
 /// Hyphenation behavior.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct HyphenationConfig {
     /// Soft-hyphen handling policy.
     pub soft_hyphen_policy: HyphenationMode,
@@ -436,6 +2362,7 @@ impl Default for HyphenationConfig {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum HyphenationMode {
     Ignore,
     Discretionary,
@@ -443,6 +2370,7 @@ pub enum HyphenationMode {
 
 /// Widow/orphan policy.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct WidowOrphanControl {
     /// Keep at least this many lines at paragraph start/end when possible.
     pub min_lines: u8,
@@ -461,6 +2389,7 @@ impl Default for WidowOrphanControl {
 
 /// Justification policy.
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct JustificationConfig {
     /// Enable inter-word justification.
     pub enabled: bool,
@@ -482,6 +2411,7 @@ impl Default for JustificationConfig {
 
 /// Hanging punctuation policy.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct HangingPunctuationConfig {
     /// Enable hanging punctuation (currently informational).
     pub enabled: bool,
@@ -489,6 +2419,7 @@ pub struct HangingPunctuationConfig {
 
 /// Non-text object layout policy knobs.
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ObjectLayoutConfig {
     /// Max inline-image height ratio relative to content height.
     pub max_inline_image_height_ratio: f32,
@@ -498,6 +2429,20 @@ pub struct ObjectLayoutConfig {
     pub svg_mode: SvgMode,
     /// Emit alt-text fallback when object drawing is unavailable.
     pub alt_text_fallback: bool,
+    /// Max SVG markup bytes accepted for rasterization.
+    pub svg_max_bytes: usize,
+    /// Max output pixels for a single SVG rasterization.
+    pub svg_max_raster_pixels: usize,
+    /// Max compressed bytes read for a single raster image.
+    pub image_max_bytes: usize,
+    /// Max decoded pixels for a single raster image; larger images are
+    /// laid out without pixels unless their decoder works in strips.
+    pub image_max_decoded_pixels: usize,
+    /// Filter used to shrink decoded images to the content box.
+    pub image_scale_filter: ImageScaleFilter,
+    /// Byte budget for decoded images the engine keeps for later layouts
+    /// of the same book; `0` disables the cache.
+    pub image_cache_bytes: usize,
 }
 
 impl Default for ObjectLayoutConfig {
@@ -507,19 +2452,585 @@ impl Default for ObjectLayoutConfig {
             float_support: FloatSupport::None,
             svg_mode: SvgMode::RasterizeFallback,
             alt_text_fallback: true,
+            svg_max_bytes: 256 * 1024,
+            svg_max_raster_pixels: 1024 * 1024,
+            image_max_bytes: 2 * 1024 * 1024,
+            image_max_decoded_pixels: 4 * 1024 * 1024,
+            image_scale_filter: ImageScaleFilter::Box,
+            image_cache_bytes: 1024 * 1024,
         }
     }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum FloatSupport {
     None,
     Basic,
 }
 
+/// Resampling filter for shrinking decoded raster images.
+///
+/// Both run row by row alongside strip decoders.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum ImageScaleFilter {
+    /// Average every source pixel under each output pixel; sharpest for
+    /// large reductions.
+    #[default]
+    Box,
+    /// Interpolate the four nearest source pixels; cheaper and smoother
+    /// for reductions under 2x, aliases on larger ones.
+    Bilinear,
+}
+
+/// SVG handling policy.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum SvgMode {
+    /// Drop SVG content.
     Ignore,
+    /// Reserve the object box and emit placeholder/title text.
     RasterizeFallback,
+    /// Rasterize to a grayscale bitmap at layout size (`svg-raster` feature).
+    ///
+    /// Falls back to placeholder/title text when over budget or unavailable.
+    Rasterize,
+    /// Emit image commands for backends with native SVG support.
     Native,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intent(target: ColorTarget, dither: DitherMode) -> RenderIntent {
+        RenderIntent {
+            color_target: target,
+            dither,
+            ..RenderIntent::default()
+        }
+    }
+
+    #[test]
+    fn approx_bytes_counts_heap_payloads() {
+        let mut page = RenderPage::new(1);
+        let empty = page.approx_bytes();
+        page.push_content_command(DrawCommand::Image(ImageCommand {
+            x: 0,
+            y: 0,
+            width: 32,
+            height: 32,
+            src: String::with_capacity(0),
+            alt: String::with_capacity(0),
+            bitmap: Some(GrayBitmap {
+                width: 32,
+                height: 32,
+                pixels: vec![255; 32 * 32],
+            }),
+        }));
+        page.sync_commands();
+        // Image lives in both `content_commands` and the merged stream.
+        assert!(page.approx_bytes() >= empty + 2 * 32 * 32);
+    }
+
+    #[test]
+    fn composite_applies_nested_clips_and_stable_z_order() {
+        let rule = |y: i32| {
+            DrawCommand::Rule(RuleCommand {
+                x: 0,
+                y,
+                length: 10,
+                thickness: 1,
+                horizontal: true,
+            })
+        };
+        let mut page = RenderPage::new(1);
+        page.push_content_command(DrawCommand::PushClip(PageRect::new(0, 0, 50, 50)));
+        page.push_content_command(DrawCommand::PushClip(PageRect::new(40, 40, 20, 20)));
+        page.push_content_command(rule(1));
+        page.push_content_command(DrawCommand::PopClip);
+        page.push_content_command(rule(2));
+        page.push_content_command(DrawCommand::PopClip);
+        page.push_content_command(rule(3));
+        page.push_overlay_command(DrawCommand::Layered(LayeredCommand {
+            z: -1,
+            command: Box::new(rule(4)),
+        }));
+
+        let items = page.composite();
+        let ys: Vec<i32> = items
+            .iter()
+            .map(|item| match item.command {
+                DrawCommand::Rule(rule) => rule.y,
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(ys, vec![4, 1, 2, 3]);
+        assert_eq!(items[0].z, -1);
+        assert_eq!(items[1].clip, Some(PageRect::new(40, 40, 10, 10)));
+        assert_eq!(items[2].clip, Some(PageRect::new(0, 0, 50, 50)));
+        assert_eq!(items[3].clip, None);
+    }
+
+    #[test]
+    fn rotation_maps_rects_between_logical_and_physical() {
+        let panel = OverlaySize {
+            width: 800,
+            height: 480,
+        };
+        let rect = PageRect::new(10, 20, 30, 40);
+        assert_eq!(
+            Rotation::Deg90.to_physical_rect(rect, panel),
+            PageRect::new(740, 10, 40, 30)
+        );
+        assert_eq!(
+            Rotation::Deg180.to_physical_rect(rect, panel),
+            PageRect::new(760, 420, 30, 40)
+        );
+        for rotation in [
+            Rotation::Deg0,
+            Rotation::Deg90,
+            Rotation::Deg180,
+            Rotation::Deg270,
+        ] {
+            let physical = rotation.to_physical_rect(rect, panel);
+            assert_eq!(rotation.to_logical_rect(physical, panel), rect);
+        }
+        assert_eq!(Rotation::Deg270.logical_insets([1, 2, 3, 4]), [4, 1, 2, 3]);
+    }
+
+    #[test]
+    fn overlay_image_fits_constraints_and_anchors_to_slot() {
+        let mut icon = OverlayImage::from_bitmap(
+            "battery",
+            GrayBitmap {
+                width: 40,
+                height: 20,
+                pixels: vec![0; 40 * 20],
+            },
+        );
+        icon.max_size = Some(OverlaySize {
+            width: 20,
+            height: 20,
+        });
+        let viewport = OverlaySize {
+            width: 480,
+            height: 800,
+        };
+        let DrawCommand::Image(image) = icon.to_command(&OverlaySlot::BottomRight, viewport) else {
+            panic!("expected image command");
+        };
+        assert_eq!(
+            (image.x, image.y, image.width, image.height),
+            (460, 790, 20, 10)
+        );
+        assert_eq!(image.src, "battery");
+        assert_eq!(
+            image.bitmap.as_ref().map(|b| (b.width, b.height)),
+            Some((20, 10))
+        );
+
+        let slot = OverlaySlot::Custom(OverlayRect {
+            x: 5,
+            y: 6,
+            width: 8,
+            height: 8,
+        });
+        assert_eq!(
+            icon.to_command(&slot, viewport).bounds(),
+            Some(PageRect::new(5, 6, 8, 4))
+        );
+    }
+
+    #[test]
+    fn point_and_edge_slots_pin_item_anchor() {
+        let viewport = OverlaySize {
+            width: 480,
+            height: 800,
+        };
+        let dot = OverlaySize {
+            width: 6,
+            height: 6,
+        };
+        let at = OverlaySlot::At {
+            x: 100,
+            y: 200,
+            anchor: OverlayAnchor::Center,
+        };
+        assert_eq!(
+            at.place(viewport, dot),
+            OverlayRect {
+                x: 97,
+                y: 197,
+                width: 6,
+                height: 6
+            }
+        );
+        let column = OverlaySlot::Edge {
+            edge: OverlayEdge::Right,
+            inset: 4,
+            offset: 300,
+            anchor: OverlayAnchor::CenterRight,
+        };
+        assert_eq!(
+            (column.place(viewport, dot).x, column.place(viewport, dot).y),
+            (470, 297)
+        );
+        let bottom = OverlaySlot::Edge {
+            edge: OverlayEdge::Bottom,
+            inset: 10,
+            offset: 0,
+            anchor: OverlayAnchor::BottomLeft,
+        };
+        assert_eq!(bottom.place(viewport, dot).y, 784);
+    }
+
+    #[test]
+    fn book_page_counts_fill_progress_metrics() {
+        let mut metrics = PageMetrics {
+            chapter_index: 1,
+            chapter_page_index: 2,
+            ..PageMetrics::default()
+        };
+        metrics.apply_book_page_counts(&[10, 5, 85]);
+        assert_eq!(metrics.global_page_index, Some(12));
+        assert_eq!(metrics.global_page_count_estimate, Some(100));
+        assert_eq!(metrics.chapter_page_count, Some(5));
+        assert_eq!(metrics.pages_left_in_chapter, Some(2));
+        assert_eq!(metrics.pages_left_in_book, Some(87));
+        assert_eq!(metrics.percent_complete(), Some(13));
+        assert_eq!(
+            StaticPageChrome.book_progress_text(&metrics).as_deref(),
+            Some("13% \u{b7} 87 pages left")
+        );
+
+        let mut unknown = PageMetrics {
+            chapter_index: 3,
+            ..PageMetrics::default()
+        };
+        unknown.apply_book_page_counts(&[1, 2]);
+        assert_eq!(unknown.global_page_index, None);
+        assert_eq!(StaticPageChrome.book_progress_text(&unknown), None);
+    }
+
+    #[test]
+    fn chrome_templates_expand_known_placeholders() {
+        let mut page = RenderPage::new(4);
+        page.chapter_title = Some("Intro".to_string());
+        page.metrics.chapter_page_index = 3;
+        let cfg = PageChromeConfig {
+            footer_enabled: true,
+            footer_template: Some("{chapter_title} \u{2014} {page}/{chapter_pages} {book_page}{x}"),
+            ..PageChromeConfig::default()
+        };
+        assert!(cfg.uses_chapter_title());
+        let chrome = cfg.compose(&page, 9, &StaticPageChrome);
+        let [DrawCommand::PageChrome(footer)] = &chrome[..] else {
+            panic!("expected one footer, got {chrome:?}");
+        };
+        assert_eq!(footer.text.as_deref(), Some("Intro \u{2014} 4/9 {x}"));
+
+        page.metrics.apply_book_page_counts(&[9]);
+        assert_eq!(
+            PageChromeConfig::render_template("{percent}% {book_pages_left} left {", &page, 9),
+            "44% 5 left {"
+        );
+    }
+
+    #[test]
+    fn baseline_offset_splits_leading_around_ascent() {
+        let style = ResolvedTextStyle {
+            font_id: None,
+            family: "serif".to_string(),
+            weight: 400,
+            italic: false,
+            size_px: 20.0,
+            line_height: 1.5,
+            letter_spacing: 0.0,
+            role: BlockRole::Body,
+            justify_mode: JustifyMode::None,
+            color: None,
+            metrics: VerticalMetrics::estimate(20.0),
+        };
+        assert_eq!(style.metrics.ascent_px, 16.0);
+        assert!(style.metrics.x_height_px < style.metrics.cap_height_px);
+        assert_eq!(style.line_box_px(), 30.0);
+        assert_eq!(style.baseline_offset_px(), 21.0);
+    }
+
+    #[test]
+    fn map_color_quantizes_per_target() {
+        let color = Color::rgb(200, 30, 60);
+        let rgb888 = intent(ColorTarget::Rgb888, DitherMode::None);
+        assert_eq!(rgb888.map_color(color), color);
+        let gray = RenderIntent {
+            grayscale_mode: GrayscaleMode::Luminosity,
+            ..rgb888
+        };
+        assert_eq!(gray.map_color(color), Color::rgb(84, 84, 84));
+        let boosted = RenderIntent {
+            contrast_boost: 200,
+            ..rgb888
+        };
+        assert_eq!(boosted.map_color(color), Color::rgb(255, 0, 0));
+    }
+
+    #[test]
+    fn weighted_grayscale_mixes_in_linear_light_and_stretches_bitmaps() {
+        let linear = GrayscaleWeights::REC709_LINEAR;
+        assert_eq!(linear.luma(255, 0, 0), 126);
+        assert_eq!(linear.luma(0, 255, 0), 219);
+        assert_eq!(linear.luma(77, 77, 77), 77);
+        let weighted = RenderIntent {
+            grayscale_mode: GrayscaleMode::Weighted(linear),
+            ..intent(ColorTarget::Rgb888, DitherMode::None)
+        };
+        assert_eq!(
+            weighted.map_color(Color::rgb(200, 30, 60)),
+            Color::rgb(102, 102, 102)
+        );
+
+        let stretching = RenderIntent {
+            grayscale_mode: GrayscaleMode::Weighted(GrayscaleWeights {
+                contrast_stretch: true,
+                ..GrayscaleWeights::REC601
+            }),
+            ..intent(ColorTarget::Grayscale, DitherMode::None)
+        };
+        let mut faded = GrayBitmap {
+            width: 51,
+            height: 1,
+            pixels: (100..=150).collect(),
+        };
+        stretching.dither_bitmap(&mut faded);
+        assert_eq!(faded.pixels.first(), Some(&0));
+        assert_eq!(faded.pixels.last(), Some(&255));
+        let mut flat = GrayBitmap {
+            width: 4,
+            height: 1,
+            pixels: vec![120, 122, 121, 120],
+        };
+        stretching.dither_bitmap(&mut flat);
+        assert_eq!(flat.pixels, vec![120, 122, 121, 120]);
+    }
+
+    #[test]
+    fn book_page_map_round_trips_global_indices() {
+        let map = BookPageMap::new(PaginationProfileId([0; 32]), vec![2, 0, 3]);
+        assert_eq!(map.total_pages(), 5);
+        assert_eq!(map.locate(1), Some((0, 1)));
+        assert_eq!(map.locate(2), Some((2, 0)));
+        assert_eq!(map.locate(5), None);
+        assert_eq!(map.global_index(2, 2), Some(4));
+        assert_eq!(map.global_index(1, 0), None);
+        assert_eq!(map.chapter_start(2), Some(2));
+        assert_eq!(map.global_index_for_fraction(0.5), Some(2));
+        assert_eq!(map.global_index_for_fraction(1.0), Some(4));
+        assert_eq!(map.global_index_for_fraction(-1.0), Some(0));
+        let empty = BookPageMap::new(PaginationProfileId([0; 32]), vec![0]);
+        assert_eq!(empty.global_index_for_fraction(0.5), None);
+    }
+
+    #[test]
+    fn text_hint_follows_target_size_and_draft() {
+        let mono = intent(ColorTarget::Mono, DitherMode::None);
+        assert_eq!(mono.text_hint(16.0), TextRenderHint::Aliased);
+        assert_eq!(mono.text_hint(32.0), TextRenderHint::Gray4);
+        let gray = intent(ColorTarget::Grayscale, DitherMode::None);
+        assert_eq!(gray.text_hint(16.0), TextRenderHint::Grayscale);
+        let draft = RenderIntent {
+            draft: true,
+            ..gray
+        };
+        assert_eq!(draft.text_hint(32.0), TextRenderHint::Aliased);
+        assert_eq!(TextRenderHint::Gray4.coverage_levels(), 16);
+    }
+
+    #[test]
+    fn inverted_intent_swaps_foreground_and_background() {
+        let night = RenderIntent {
+            inverted: true,
+            ..intent(ColorTarget::Gray4, DitherMode::None)
+        };
+        assert_eq!(night.foreground(), Some(Color::WHITE));
+        assert_eq!(night.background(), Color::BLACK);
+        assert_eq!(RenderIntent::default().foreground(), None);
+
+        let mut bitmap = GrayBitmap {
+            width: 2,
+            height: 1,
+            pixels: vec![0, 255],
+        };
+        night.dither_bitmap(&mut bitmap);
+        assert_eq!(bitmap.pixels, vec![255, 0]);
+    }
+
+    #[test]
+    fn ordered_dither_mixes_neighbouring_rgb565_levels() {
+        // Red 4 sits between the 5-bit levels 0 and 8.
+        let mut pixels = vec![Color::rgb(4, 0, 0); 16];
+        intent(ColorTarget::Rgb565, DitherMode::Ordered).dither_pixels(&mut pixels, 4);
+        let lit = pixels.iter().filter(|px| px.r > 0).count();
+        assert!((4..=12).contains(&lit), "lit={lit}");
+        assert!(pixels.iter().all(|px| px.r == 0 || px.r == 8));
+    }
+
+    fn dithered_gray(target: ColorTarget, dither: DitherMode, luma: u8) -> Vec<u8> {
+        let mut bitmap = GrayBitmap {
+            width: 16,
+            height: 16,
+            pixels: vec![luma; 256],
+        };
+        intent(target, dither).dither_bitmap(&mut bitmap);
+        bitmap.pixels
+    }
+
+    #[test]
+    fn one_bit_dithers_track_mid_gray_coverage() {
+        for dither in [
+            DitherMode::Ordered,
+            DitherMode::Bayer8x8,
+            DitherMode::BlueNoise,
+            DitherMode::ErrorDiffusion,
+            DitherMode::Atkinson,
+        ] {
+            let pixels = dithered_gray(ColorTarget::Mono, dither, 64);
+            assert!(pixels.iter().all(|&px| px == 0 || px == 255), "{dither:?}");
+            let white = pixels.iter().filter(|&&px| px == 255).count();
+            assert!((40..=90).contains(&white), "{dither:?} white={white}");
+        }
+    }
+
+    #[test]
+    fn atkinson_keeps_near_white_clean_on_one_bit() {
+        // Atkinson drops 1/4 of the error, so light gray stays paper-white.
+        let atkinson = dithered_gray(ColorTarget::Mono, DitherMode::Atkinson, 240);
+        let floyd = dithered_gray(ColorTarget::Mono, DitherMode::ErrorDiffusion, 240);
+        let black = |px: &[u8]| px.iter().filter(|&&v| v == 0).count();
+        assert!(black(&atkinson) < black(&floyd));
+    }
+
+    #[test]
+    fn gray4_ordered_dither_uses_sixteen_levels() {
+        let pixels = dithered_gray(ColorTarget::Gray4, DitherMode::Bayer8x8, 100);
+        assert!(pixels.iter().all(|&px| px % 17 == 0));
+        assert!(pixels.contains(&85) && pixels.contains(&102));
+        let mut ranks: Vec<u8> = (0..64).map(|i| bayer_8x8(i % 8, i / 8)).collect();
+        ranks.sort_unstable();
+        assert!(ranks.iter().enumerate().all(|(i, &r)| i == r as usize));
+    }
+
+    #[test]
+    fn error_diffusion_preserves_average_intensity() {
+        let mut pixels = vec![Color::rgb(4, 2, 4); 64];
+        intent(ColorTarget::Rgb565, DitherMode::ErrorDiffusion).dither_pixels(&mut pixels, 8);
+        let avg_r = pixels.iter().map(|px| px.r as u32).sum::<u32>() as f32 / 64.0;
+        let avg_g = pixels.iter().map(|px| px.g as u32).sum::<u32>() as f32 / 64.0;
+        assert!((avg_r - 4.0).abs() < 1.0, "avg_r={avg_r}");
+        assert!((avg_g - 2.0).abs() < 1.0, "avg_g={avg_g}");
+    }
+
+    struct Fixed(Vec<(OverlaySlot, i32, &'static str)>);
+
+    impl OverlayComposer for Fixed {
+        fn compose(&self, _metrics: &PageMetrics, _viewport: OverlaySize) -> Vec<OverlayItem> {
+            self.0
+                .iter()
+                .map(|(slot, z, text)| OverlayItem {
+                    slot: slot.clone(),
+                    z: *z,
+                    content: OverlayContent::Text(text.to_string()),
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn composer_chain_orders_by_priority_and_suppresses_collisions() {
+        let progress = Fixed(vec![
+            (OverlaySlot::BottomCenter, 0, "42%"),
+            (OverlaySlot::BottomRight, 0, "p3"),
+        ]);
+        let sync = Fixed(vec![(OverlaySlot::BottomCenter, 0, "synced")]);
+        let texts = |items: Vec<OverlayItem>| {
+            items
+                .into_iter()
+                .map(|item| match item.content {
+                    OverlayContent::Text(text) => text,
+                    _ => String::with_capacity(0),
+                })
+                .collect::<Vec<_>>()
+        };
+        let viewport = OverlaySize {
+            width: 100,
+            height: 100,
+        };
+        let metrics = PageMetrics::default();
+
+        let chain = OverlayComposerChain::new()
+            .with(10, Fixed(sync.0.clone()))
+            .with(0, Fixed(progress.0.clone()));
+        assert_eq!(chain.len(), 2);
+        assert_eq!(
+            texts(chain.compose(&metrics, viewport)),
+            ["42%", "p3", "synced"]
+        );
+
+        let chain = chain.with_conflict(OverlayConflict::Suppress);
+        assert_eq!(texts(chain.compose(&metrics, viewport)), ["p3", "synced"]);
+        let chain = OverlayComposerChain::new()
+            .with(10, progress)
+            .with(0, sync)
+            .with_conflict(OverlayConflict::Suppress);
+        assert_eq!(texts(chain.compose(&metrics, viewport)), ["42%", "p3"]);
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary_pages_keep_layers_and_bitmaps_consistent() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        fn bitmaps(commands: &[DrawCommand]) -> Vec<&GrayBitmap> {
+            commands
+                .iter()
+                .filter_map(|cmd| match cmd {
+                    DrawCommand::Image(image) => image.bitmap.as_ref(),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        let mut seed = 0x2545_f491_u32;
+        let bytes: Vec<u8> = (0..16 * 1024)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect();
+        let mut u = Unstructured::new(&bytes);
+        let mut pages = 0;
+        while let Ok(page) = RenderPage::arbitrary(&mut u) {
+            let mut layers = page.content_commands.clone();
+            layers.extend(page.chrome_commands.iter().cloned());
+            layers.extend(page.overlay_commands.iter().cloned());
+            assert_eq!(page.commands, layers);
+            for bitmap in bitmaps(&page.commands) {
+                assert_eq!(
+                    bitmap.pixels.len(),
+                    bitmap.width as usize * bitmap.height as usize
+                );
+            }
+            pages += 1;
+            if u.is_empty() {
+                break;
+            }
+        }
+        assert!(pages > 0);
+        let mut u = Unstructured::new(&bytes);
+        assert!(TypographyConfig::arbitrary(&mut u).is_ok());
+        assert!(ObjectLayoutConfig::arbitrary(&mut u).is_ok());
+    }
+}
@@ -0,0 +1,798 @@
+//! Baseline JPEG decoding in MCU-row strips.
+//!
+//! Only the luma channel is reconstructed: chroma coefficients are entropy
+//! decoded to keep the bitstream in step and then dropped, and at most one
+//! MCU row of luma samples is held at a time.
+
+use crate::render_image::{
+    fit_size, ExifOrientation, ImageDecodeOptions, ImageDecoder, RowDownscaler,
+};
+use crate::render_ir::GrayBitmap;
+
+/// Default `StripJpegDecoder::max_scratch_bytes`.
+pub const DEFAULT_JPEG_SCRATCH_BYTES: usize = 96 * 1024;
+
+/// Natural-order index of each zigzag coefficient.
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+/// Baseline (SOF0/SOF1) JPEG decoder that works in MCU-row strips.
+///
+/// Grayscale and YCbCr images are supported; progressive, arithmetic,
+/// CMYK, and Adobe RGB files are not sniffed, leaving them to decoders
+/// registered later. Working memory is one MCU row of luma, reported by
+/// `scratch_len`, plus the output.
+///
+/// The `ImageDecoder` methods turn images upright per their EXIF
+/// orientation; `decode_strips` passes rows in stored order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StripJpegDecoder {
+    /// Images whose strip scratch would exceed this are not decoded.
+    pub max_scratch_bytes: usize,
+}
+
+impl Default for StripJpegDecoder {
+    fn default() -> Self {
+        Self {
+            max_scratch_bytes: DEFAULT_JPEG_SCRATCH_BYTES,
+        }
+    }
+}
+
+impl StripJpegDecoder {
+    /// Scratch bytes `decode_strips` needs for `bytes`, or `None` when the
+    /// image is not a supported baseline JPEG.
+    pub fn scratch_len(&self, bytes: &[u8]) -> Option<usize> {
+        Some(Jpeg::parse(bytes)?.geometry()?.scratch_len())
+    }
+
+    /// Decode `bytes` into `scratch` one MCU row at a time, passing each
+    /// luma row of the image to `on_row` with its y coordinate.
+    ///
+    /// `scratch` must hold at least `scratch_len` bytes. Returns the image
+    /// size, or `None` on unsupported or malformed input; rows already
+    /// passed to `on_row` are then incomplete.
+    pub fn decode_strips<F>(
+        &self,
+        bytes: &[u8],
+        scratch: &mut [u8],
+        on_row: F,
+    ) -> Option<(u32, u32)>
+    where
+        F: FnMut(u32, &[u8]),
+    {
+        let jpeg = Jpeg::parse(bytes)?;
+        jpeg.decode(scratch, on_row)?;
+        Some((jpeg.width, jpeg.height))
+    }
+
+    /// Run `decode_strips` with scratch allocated within `max_scratch_bytes`.
+    fn with_scratch<F>(&self, bytes: &[u8], on_row: F) -> Option<(u32, u32)>
+    where
+        F: FnMut(u32, &[u8]),
+    {
+        let len = self.scratch_len(bytes)?;
+        if len > self.max_scratch_bytes {
+            return None;
+        }
+        let mut scratch = vec![0; len];
+        self.decode_strips(bytes, &mut scratch, on_row)
+    }
+}
+
+impl ImageDecoder for StripJpegDecoder {
+    fn sniff(&self, bytes: &[u8]) -> bool {
+        bytes.starts_with(&[0xFF, 0xD8, 0xFF])
+            && Jpeg::parse(bytes).is_some_and(|jpeg| jpeg.geometry().is_some())
+    }
+
+    fn dimensions(&self, bytes: &[u8]) -> Option<(u32, u32)> {
+        let jpeg = Jpeg::parse(bytes)?;
+        Some(ExifOrientation::from_jpeg(bytes).transpose_size(jpeg.width, jpeg.height))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Option<GrayBitmap> {
+        let jpeg = Jpeg::parse(bytes)?;
+        let (width, height) = (jpeg.width, jpeg.height);
+        let mut pixels = Vec::with_capacity(width as usize * height as usize);
+        self.with_scratch(bytes, |_, row| pixels.extend_from_slice(row))?;
+        let bitmap = GrayBitmap {
+            width,
+            height,
+            pixels,
+        };
+        Some(ExifOrientation::from_jpeg(bytes).apply(bitmap))
+    }
+
+    fn decode_within(
+        &self,
+        bytes: &[u8],
+        max_width: i32,
+        max_height: i32,
+        options: ImageDecodeOptions,
+    ) -> Option<GrayBitmap> {
+        let jpeg = Jpeg::parse(bytes)?;
+        let (width, height) = (jpeg.width, jpeg.height);
+        // Fit the upright picture, downscale in stored order, then turn
+        // the (small) result upright.
+        let orientation = ExifOrientation::from_jpeg(bytes);
+        let (upright_width, upright_height) = orientation.transpose_size(width, height);
+        let (fit_width, fit_height) =
+            fit_size(upright_width, upright_height, max_width, max_height);
+        let (out_width, out_height) = orientation.transpose_size(fit_width, fit_height);
+        let mut scaler = RowDownscaler::new(width, height, out_width, out_height, options.filter);
+        self.with_scratch(bytes, |_, row| scaler.push_row(row))?;
+        Some(orientation.apply(scaler.finish()?))
+    }
+
+    fn decodes_in_strips(&self) -> bool {
+        true
+    }
+}
+
+/// Huffman table in canonical form (JPEG Annex C).
+struct Huffman {
+    /// Largest code of each length, `-1` when none.
+    max_code: [i32; 17],
+    /// Smallest code of each length.
+    min_code: [i32; 17],
+    /// Index into `values` of the first code of each length.
+    first_value: [i32; 17],
+    values: [u8; 256],
+}
+
+impl Huffman {
+    fn build(counts: &[u8], values: &[u8]) -> Option<Self> {
+        let mut table = Self {
+            max_code: [-1; 17],
+            min_code: [0; 17],
+            first_value: [0; 17],
+            values: [0; 256],
+        };
+        let mut code = 0i32;
+        let mut index = 0i32;
+        for (len, &count) in (1..=16).zip(counts) {
+            let count = count as i32;
+            table.first_value[len] = index;
+            table.min_code[len] = code;
+            code += count;
+            index += count;
+            if count > 0 {
+                table.max_code[len] = code - 1;
+            }
+            if code > 1 << len {
+                return None;
+            }
+            code <<= 1;
+        }
+        table
+            .values
+            .get_mut(..values.len())?
+            .copy_from_slice(values);
+        Some(table)
+    }
+
+    fn decode(&self, bits: &mut BitReader<'_>) -> Option<u8> {
+        let mut code = 0i32;
+        for len in 1..=16 {
+            code = (code << 1) | bits.bit() as i32;
+            if code <= self.max_code[len] {
+                let index = self.first_value[len] + code - self.min_code[len];
+                return self.values.get(index as usize).copied();
+            }
+        }
+        None
+    }
+}
+
+/// Entropy-coded segment reader that unstuffs `FF 00` and stops at markers.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    acc: u32,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            acc: 0,
+            count: 0,
+        }
+    }
+
+    /// Next data byte; zeros once a marker or the end is reached.
+    fn byte(&mut self) -> u8 {
+        match self.data.get(self.pos..) {
+            Some([0xFF, 0x00, ..]) => {
+                self.pos += 2;
+                0xFF
+            }
+            Some([0xFF, ..]) | Some([]) | None => 0,
+            Some([byte, ..]) => {
+                self.pos += 1;
+                *byte
+            }
+        }
+    }
+
+    fn bit(&mut self) -> u32 {
+        if self.count == 0 {
+            self.acc = self.byte() as u32;
+            self.count = 8;
+        }
+        self.count -= 1;
+        (self.acc >> self.count) & 1
+    }
+
+    /// `len` bits as a signed coefficient (JPEG `EXTEND`).
+    fn receive_extend(&mut self, len: u8) -> Option<i32> {
+        if len == 0 {
+            return Some(0);
+        }
+        if len > 16 {
+            return None;
+        }
+        let mut value = 0i32;
+        for _ in 0..len {
+            value = (value << 1) | self.bit() as i32;
+        }
+        if value < 1 << (len - 1) {
+            value += 1 - (1 << len);
+        }
+        Some(value)
+    }
+
+    /// Skip the `RSTn` marker at a restart interval boundary.
+    fn restart(&mut self) -> Option<()> {
+        self.acc = 0;
+        self.count = 0;
+        while self.data.get(self.pos..self.pos + 2) == Some(&[0xFF, 0xFF]) {
+            self.pos += 1;
+        }
+        match self.data.get(self.pos..self.pos + 2)? {
+            [0xFF, 0xD0..=0xD7] => {
+                self.pos += 2;
+                Some(())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Component {
+    id: u8,
+    h: u8,
+    v: u8,
+    quant: u8,
+    dc: u8,
+    ac: u8,
+}
+
+/// Headers up to the first scan plus its entropy-coded data.
+struct Jpeg<'a> {
+    width: u32,
+    height: u32,
+    components: [Component; 3],
+    component_count: usize,
+    quant: [[u16; 64]; 4],
+    dc: [Option<Box<Huffman>>; 4],
+    ac: [Option<Box<Huffman>>; 4],
+    restart_interval: u16,
+    scan: &'a [u8],
+}
+
+/// Block layout of the luma plane.
+struct Geometry {
+    mcu_width: usize,
+    mcu_height: usize,
+    mcus_x: usize,
+    mcus_y: usize,
+}
+
+impl Geometry {
+    fn stride(&self) -> usize {
+        self.mcu_width * self.mcus_x
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.stride() * self.mcu_height
+    }
+}
+
+impl<'a> Jpeg<'a> {
+    /// Parse markers through the first SOS; `None` for anything but a
+    /// single-scan 8-bit Huffman baseline image in gray or YCbCr.
+    fn parse(bytes: &'a [u8]) -> Option<Self> {
+        if !bytes.starts_with(&[0xFF, 0xD8]) {
+            return None;
+        }
+        let mut jpeg = Jpeg {
+            width: 0,
+            height: 0,
+            components: [Component::default(); 3],
+            component_count: 0,
+            quant: [[0; 64]; 4],
+            dc: [None, None, None, None],
+            ac: [None, None, None, None],
+            restart_interval: 0,
+            scan: &[],
+        };
+        let mut adobe_transform = None;
+        let mut pos = 2;
+        loop {
+            if *bytes.get(pos)? != 0xFF {
+                return None;
+            }
+            while *bytes.get(pos)? == 0xFF {
+                pos += 1;
+            }
+            let marker = bytes[pos];
+            pos += 1;
+            match marker {
+                0x01 | 0xD0..=0xD8 => continue,
+                0xD9 => return None,
+                _ => {}
+            }
+            let len = u16::from_be_bytes([*bytes.get(pos)?, *bytes.get(pos + 1)?]) as usize;
+            let segment = bytes.get(pos + 2..pos.checked_add(len)?)?;
+            pos += len;
+            match marker {
+                0xDB => jpeg.parse_quant(segment)?,
+                0xC4 => jpeg.parse_huffman(segment)?,
+                0xC0 | 0xC1 => jpeg.parse_frame(segment)?,
+                0xC2 | 0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => return None,
+                0xDD => {
+                    jpeg.restart_interval =
+                        u16::from_be_bytes([*segment.first()?, *segment.get(1)?]);
+                }
+                0xEE if segment.starts_with(b"Adobe") => adobe_transform = segment.get(11).copied(),
+                0xDA => {
+                    jpeg.parse_scan(segment)?;
+                    jpeg.scan = bytes.get(pos..)?;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let rgb_ids = jpeg.components.map(|c| c.id) == [b'R', b'G', b'B'];
+        if jpeg.component_count == 3 && (adobe_transform == Some(0) || rgb_ids) {
+            return None;
+        }
+        Some(jpeg)
+    }
+
+    fn parse_quant(&mut self, mut segment: &[u8]) -> Option<()> {
+        while let Some((&spec, rest)) = segment.split_first() {
+            let table = self.quant.get_mut((spec & 0x0F) as usize)?;
+            let wide = spec >> 4 != 0;
+            let len = if wide { 128 } else { 64 };
+            let values = rest.get(..len)?;
+            for (i, q) in table.iter_mut().enumerate() {
+                *q = if wide {
+                    u16::from_be_bytes([values[2 * i], values[2 * i + 1]])
+                } else {
+                    values[i] as u16
+                };
+            }
+            segment = &rest[len..];
+        }
+        Some(())
+    }
+
+    fn parse_huffman(&mut self, mut segment: &[u8]) -> Option<()> {
+        while let Some((&spec, rest)) = segment.split_first() {
+            let counts = rest.get(..16)?;
+            let total: usize = counts.iter().map(|&n| n as usize).sum();
+            let values = rest.get(16..16 + total)?;
+            let table = Some(Box::new(Huffman::build(counts, values)?));
+            let slot = (spec & 0x0F) as usize;
+            match spec >> 4 {
+                0 => *self.dc.get_mut(slot)? = table,
+                1 => *self.ac.get_mut(slot)? = table,
+                _ => return None,
+            }
+            segment = &rest[16 + total..];
+        }
+        Some(())
+    }
+
+    fn parse_frame(&mut self, segment: &[u8]) -> Option<()> {
+        let [precision, h1, h0, w1, w0, count, ref specs @ ..] = *segment else {
+            return None;
+        };
+        self.height = u16::from_be_bytes([h1, h0]) as u32;
+        self.width = u16::from_be_bytes([w1, w0]) as u32;
+        let count = count as usize;
+        if precision != 8 || self.width == 0 || self.height == 0 || !matches!(count, 1 | 3) {
+            return None;
+        }
+        for (component, spec) in self.components.iter_mut().zip(specs.chunks_exact(3)) {
+            let (h, v) = (spec[1] >> 4, spec[1] & 0x0F);
+            if !(1..=4).contains(&h) || !(1..=4).contains(&v) || spec[2] > 3 {
+                return None;
+            }
+            *component = Component {
+                id: spec[0],
+                h,
+                v,
+                quant: spec[2],
+                ..Component::default()
+            };
+        }
+        if specs.len() < count * 3 {
+            return None;
+        }
+        self.component_count = count;
+        Some(())
+    }
+
+    fn parse_scan(&mut self, segment: &[u8]) -> Option<()> {
+        let (&count, rest) = segment.split_first()?;
+        let count = count as usize;
+        if count != self.component_count || count == 0 {
+            return None;
+        }
+        for spec in rest.get(..count * 2)?.chunks_exact(2) {
+            let component = self.components[..count]
+                .iter_mut()
+                .find(|component| component.id == spec[0])?;
+            component.dc = spec[1] >> 4;
+            component.ac = spec[1] & 0x0F;
+        }
+        match rest.get(count * 2..count * 2 + 3)? {
+            [0, 63, 0] => Some(()),
+            _ => None,
+        }
+    }
+
+    /// Luma block layout; `None` when luma is subsampled relative to
+    /// chroma.
+    fn geometry(&self) -> Option<Geometry> {
+        let components = self.components.get(..self.component_count)?;
+        let (h, v) = if components.len() == 1 {
+            (1, 1)
+        } else {
+            let h = components.iter().map(|c| c.h).max()?;
+            let v = components.iter().map(|c| c.v).max()?;
+            if (components[0].h, components[0].v) != (h, v) {
+                return None;
+            }
+            (h as usize, v as usize)
+        };
+        let (mcu_width, mcu_height) = (8 * h, 8 * v);
+        Some(Geometry {
+            mcu_width,
+            mcu_height,
+            mcus_x: (self.width as usize).div_ceil(mcu_width),
+            mcus_y: (self.height as usize).div_ceil(mcu_height),
+        })
+    }
+
+    fn decode<F>(&self, scratch: &mut [u8], mut on_row: F) -> Option<()>
+    where
+        F: FnMut(u32, &[u8]),
+    {
+        let geometry = self.geometry()?;
+        let stride = geometry.stride();
+        let scratch = scratch.get_mut(..geometry.scratch_len())?;
+        let components = &self.components[..self.component_count];
+        let tables = components
+            .iter()
+            .map(|component| {
+                Some((
+                    self.dc.get(component.dc as usize)?.as_deref()?,
+                    self.ac.get(component.ac as usize)?.as_deref()?,
+                ))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let idct = IdctTable::new();
+        let mut bits = BitReader::new(self.scan);
+        let mut predictors = [0i32; 3];
+        let mut coefficients = [0i32; 64];
+        let restart_interval = self.restart_interval as usize;
+        let mut until_restart = restart_interval;
+        let width = self.width as usize;
+        for mcu_y in 0..geometry.mcus_y {
+            for mcu_x in 0..geometry.mcus_x {
+                if restart_interval > 0 {
+                    if until_restart == 0 {
+                        bits.restart()?;
+                        predictors = [0; 3];
+                        until_restart = restart_interval;
+                    }
+                    until_restart -= 1;
+                }
+                for (index, component) in components.iter().enumerate() {
+                    let (dc, ac) = tables[index];
+                    let (blocks_x, blocks_y) = if components.len() == 1 {
+                        (1, 1)
+                    } else {
+                        (component.h as usize, component.v as usize)
+                    };
+                    let quant = (index == 0).then(|| &self.quant[component.quant as usize]);
+                    for block_y in 0..blocks_y {
+                        for block_x in 0..blocks_x {
+                            decode_block(
+                                &mut bits,
+                                dc,
+                                ac,
+                                &mut predictors[index],
+                                quant,
+                                &mut coefficients,
+                            )?;
+                            if quant.is_some() {
+                                let x = mcu_x * geometry.mcu_width + block_x * 8;
+                                let offset = block_y * 8 * stride + x;
+                                idct.apply(&coefficients, &mut scratch[offset..], stride);
+                            }
+                        }
+                    }
+                }
+            }
+            let top = mcu_y * geometry.mcu_height;
+            let rows = geometry.mcu_height.min(self.height as usize - top);
+            for (row, samples) in scratch.chunks_exact(stride).take(rows).enumerate() {
+                on_row((top + row) as u32, &samples[..width]);
+            }
+        }
+        Some(())
+    }
+}
+
+/// Entropy decode one block, dequantizing into `coefficients` when `quant`
+/// is given; otherwise only the DC predictor is kept up to date.
+fn decode_block(
+    bits: &mut BitReader<'_>,
+    dc: &Huffman,
+    ac: &Huffman,
+    predictor: &mut i32,
+    quant: Option<&[u16; 64]>,
+    coefficients: &mut [i32; 64],
+) -> Option<()> {
+    let category = dc.decode(bits)?;
+    let diff = bits.receive_extend(category)?;
+    *predictor = predictor.wrapping_add(diff);
+    if let Some(quant) = quant {
+        *coefficients = [0; 64];
+        coefficients[0] = predictor.wrapping_mul(quant[0] as i32);
+    }
+    let mut k = 1;
+    while k < 64 {
+        let symbol = ac.decode(bits)?;
+        let (run, size) = ((symbol >> 4) as usize, symbol & 0x0F);
+        if size == 0 {
+            if run != 15 {
+                break;
+            }
+            k += 16;
+            continue;
+        }
+        k += run;
+        let value = bits.receive_extend(size)?;
+        if k > 63 {
+            return None;
+        }
+        if let Some(quant) = quant {
+            coefficients[ZIGZAG[k]] = value.wrapping_mul(quant[k] as i32);
+        }
+        k += 1;
+    }
+    Some(())
+}
+
+/// Separable floating-point inverse DCT.
+struct IdctTable {
+    /// `C(u) / 2 * cos((2x + 1) u pi / 16)` at `[x][u]`.
+    basis: [[f32; 8]; 8],
+}
+
+impl IdctTable {
+    fn new() -> Self {
+        let mut basis = [[0.0; 8]; 8];
+        for (x, row) in basis.iter_mut().enumerate() {
+            for (u, value) in row.iter_mut().enumerate() {
+                let scale = if u == 0 {
+                    core::f32::consts::FRAC_1_SQRT_2
+                } else {
+                    1.0
+                };
+                let angle = (2 * x + 1) as f32 * u as f32 * core::f32::consts::PI / 16.0;
+                *value = scale / 2.0 * angle.cos();
+            }
+        }
+        Self { basis }
+    }
+
+    /// Write the 8x8 samples of `coefficients` to `out` rows `stride` apart.
+    fn apply(&self, coefficients: &[i32; 64], out: &mut [u8], stride: usize) {
+        let mut rows = [[0.0f32; 8]; 8];
+        for (v, row) in rows.iter_mut().enumerate() {
+            let input = &coefficients[v * 8..v * 8 + 8];
+            for (x, value) in row.iter_mut().enumerate() {
+                *value = (0..8).map(|u| self.basis[x][u] * input[u] as f32).sum();
+            }
+        }
+        for y in 0..8 {
+            let Some(line) = out.get_mut(y * stride..y * stride + 8) else {
+                return;
+            };
+            for (x, sample) in line.iter_mut().enumerate() {
+                let value: f32 = (0..8).map(|v| self.basis[y][v] * rows[v][x]).sum();
+                *sample = (value + 128.0).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Append `len` low bits of `value`, MSB first, byte-stuffing `FF`.
+    fn put_bits(out: &mut Vec<u8>, acc: &mut (u32, u32), value: u32, len: u32) {
+        for i in (0..len).rev() {
+            acc.0 = (acc.0 << 1) | ((value >> i) & 1);
+            acc.1 += 1;
+            if acc.1 == 8 {
+                out.push(acc.0 as u8);
+                if acc.0 == 0xFF {
+                    out.push(0);
+                }
+                *acc = (0, 0);
+            }
+        }
+    }
+
+    /// 16x8 grayscale JPEG of two flat blocks, black then white.
+    fn two_block_jpeg() -> Vec<u8> {
+        let mut jpeg = vec![0xFF, 0xD8];
+        jpeg.extend_from_slice(&[0xFF, 0xDB, 0, 67, 0]);
+        jpeg.extend_from_slice(&[1; 64]);
+        jpeg.extend_from_slice(&[0xFF, 0xC0, 0, 11, 8, 0, 8, 0, 16, 1, 1, 0x11, 0]);
+        // One-symbol tables: DC category 11, AC end-of-block, each coded `0`.
+        let mut counts = [0u8; 16];
+        counts[0] = 1;
+        for (class, symbol) in [(0x00, 11u8), (0x10, 0x00)] {
+            jpeg.extend_from_slice(&[0xFF, 0xC4, 0, 20, class]);
+            jpeg.extend_from_slice(&counts);
+            jpeg.push(symbol);
+        }
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0, 8, 1, 1, 0x00, 0, 63, 0]);
+        let mut acc = (0, 0);
+        // DC -1024 (sample 0) then +2040 (sample 255), 11-bit magnitudes.
+        for diff in [-1024i32, 2040] {
+            put_bits(&mut jpeg, &mut acc, 0, 1);
+            let bits = if diff < 0 { diff + 2047 } else { diff };
+            put_bits(&mut jpeg, &mut acc, bits as u32, 11);
+            put_bits(&mut jpeg, &mut acc, 0, 1);
+        }
+        let pad = 8 - acc.1;
+        put_bits(&mut jpeg, &mut acc, 0x7F, pad);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]);
+        jpeg
+    }
+
+    #[test]
+    fn strips_decode_flat_blocks_and_downscale_while_streaming() {
+        let jpeg = two_block_jpeg();
+        let decoder = StripJpegDecoder::default();
+        assert!(decoder.sniff(&jpeg));
+        assert_eq!(decoder.dimensions(&jpeg), Some((16, 8)));
+        assert_eq!(decoder.scratch_len(&jpeg), Some(16 * 8));
+
+        let mut scratch = [0u8; 16 * 8];
+        let mut rows = Vec::with_capacity(8);
+        let size =
+            decoder.decode_strips(&jpeg, &mut scratch, |y, row| rows.push((y, row.to_vec())));
+        assert_eq!(size, Some((16, 8)));
+        assert_eq!(rows.len(), 8);
+        for (y, (row_y, row)) in rows.iter().enumerate() {
+            assert_eq!(*row_y, y as u32);
+            assert_eq!(row[..8], [0; 8]);
+            assert_eq!(row[8..], [255; 8]);
+        }
+
+        let full = decoder.decode(&jpeg).expect("full decode");
+        let scaled = decoder
+            .decode_within(&jpeg, 4, 100, ImageDecodeOptions::default())
+            .expect("scaled decode");
+        assert_eq!(
+            scaled,
+            crate::render_image::fit_within(full, 4, 100, crate::ImageScaleFilter::Box)
+        );
+        assert_eq!((scaled.width, scaled.height), (4, 2));
+        assert_eq!(scaled.pixels, [0, 0, 255, 255, 0, 0, 255, 255]);
+    }
+
+    /// `APP1` segment declaring EXIF `orientation` in big-endian TIFF.
+    fn exif_segment(orientation: u8) -> Vec<u8> {
+        let mut tiff = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01".to_vec();
+        tiff.extend_from_slice(&[0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, orientation, 0, 0]);
+        tiff.extend_from_slice(&[0, 0, 0, 0]);
+        let mut segment = vec![0xFF, 0xE1];
+        segment.extend_from_slice(&(tiff.len() as u16 + 2).to_be_bytes());
+        segment.extend_from_slice(&tiff);
+        segment
+    }
+
+    #[test]
+    fn exif_orientation_turns_strips_upright_after_downscaling() {
+        let plain = two_block_jpeg();
+        let mut jpeg = plain[..2].to_vec();
+        jpeg.extend_from_slice(&[0xFF, 0xE0, 0, 4, 0, 0]);
+        jpeg.extend_from_slice(&exif_segment(6));
+        jpeg.extend_from_slice(&plain[2..]);
+        let decoder = StripJpegDecoder::default();
+        assert_eq!(decoder.dimensions(&jpeg), Some((8, 16)));
+
+        let full = decoder.decode(&jpeg).expect("full decode");
+        assert_eq!((full.width, full.height), (8, 16));
+        assert!(full.pixels[..64].iter().all(|&p| p == 0));
+        assert!(full.pixels[64..].iter().all(|&p| p == 255));
+
+        let scaled = decoder
+            .decode_within(&jpeg, 4, 100, ImageDecodeOptions::default())
+            .expect("scaled decode");
+        assert_eq!((scaled.width, scaled.height), (4, 8));
+        assert_eq!(scaled.pixels[..16], [0; 16]);
+        assert_eq!(scaled.pixels[16..], [255; 16]);
+    }
+
+    #[test]
+    fn scratch_budget_and_progressive_files_are_rejected() {
+        let jpeg = two_block_jpeg();
+        let tight = StripJpegDecoder {
+            max_scratch_bytes: 64,
+        };
+        assert!(tight.decode(&jpeg).is_none());
+
+        let mut progressive = jpeg.clone();
+        let sof = progressive
+            .windows(2)
+            .position(|w| w == [0xFF, 0xC0])
+            .expect("frame marker");
+        progressive[sof + 1] = 0xC2;
+        assert!(!StripJpegDecoder::default().sniff(&progressive));
+        assert!(StripJpegDecoder::default().decode(&progressive).is_none());
+    }
+
+    #[cfg(feature = "image-decode")]
+    #[test]
+    fn subsampled_cover_with_restarts_matches_reference_luma() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../tests/fixtures/Fundamental-Accessibility-Tests-Basic-Functionality-v2.0.0.epub"
+        );
+        let mut book = mu_epub::EpubBook::open(path).expect("fixture opens");
+        let jpeg = book.read_resource("images/cover.jpg").expect("cover");
+        let decoder = StripJpegDecoder::default();
+        assert!(decoder
+            .scratch_len(&jpeg)
+            .is_some_and(|len| len < 16 * 1024));
+        let ours = decoder.decode(&jpeg).expect("baseline cover decodes");
+        let reference = crate::render_image::JpegDecoder
+            .decode(&jpeg)
+            .expect("reference decodes");
+        assert_eq!(
+            (ours.width, ours.height),
+            (reference.width, reference.height)
+        );
+        let diff: u64 = ours
+            .pixels
+            .iter()
+            .zip(&reference.pixels)
+            .map(|(&a, &b)| a.abs_diff(b) as u64)
+            .sum();
+        assert!(
+            diff < ours.pixels.len() as u64 * 2,
+            "mean diff too high: {diff}"
+        );
+    }
+}
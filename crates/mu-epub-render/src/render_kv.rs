@@ -0,0 +1,503 @@
+//! Key-value storage shared by the pagination cache, bookmarks, and
+//! annotations.
+//!
+//! Integrators implement [`KvStore`] once for their medium (flash
+//! partition, SD card, browser storage) and get `KvCacheStore`,
+//! `KvBookmarkStore`, and `KvAnnotationStore` on top of it. Keys are
+//! `/`-separated segments of ASCII letters, digits, `-`, `_`, and `.`
+//! (segments never start with `.`), so they map directly onto paths.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Size limits a `KvStore` enforces on `put`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KvLimits {
+    /// Longest accepted key, in bytes.
+    pub max_key_bytes: usize,
+    /// Largest accepted value, in bytes.
+    pub max_value_bytes: Option<usize>,
+    /// Largest combined size of all values, in bytes.
+    pub max_total_bytes: Option<usize>,
+}
+
+impl Default for KvLimits {
+    fn default() -> Self {
+        Self {
+            max_key_bytes: 255,
+            max_value_bytes: None,
+            max_total_bytes: None,
+        }
+    }
+}
+
+impl KvLimits {
+    /// Validate `key` and a value of `value_len` bytes against these limits
+    /// (the total is the caller's to check).
+    pub fn check(&self, key: &str, value_len: usize) -> Result<(), KvError> {
+        if !valid_key(key) {
+            return Err(KvError::InvalidKey);
+        }
+        if key.len() > self.max_key_bytes {
+            return Err(KvError::KeyTooLong {
+                len: key.len(),
+                max: self.max_key_bytes,
+            });
+        }
+        match self.max_value_bytes {
+            Some(max) if value_len > max => Err(KvError::ValueTooLarge {
+                len: value_len,
+                max,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Why a `KvStore` rejected a write.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum KvError {
+    /// Key is empty or has an empty, dot-leading, or non-portable segment.
+    InvalidKey,
+    /// Key exceeds `KvLimits::max_key_bytes`.
+    KeyTooLong {
+        /// Key length in bytes.
+        len: usize,
+        /// Configured maximum.
+        max: usize,
+    },
+    /// Value exceeds `KvLimits::max_value_bytes`.
+    ValueTooLarge {
+        /// Value length in bytes.
+        len: usize,
+        /// Configured maximum.
+        max: usize,
+    },
+    /// Storing the value would exceed `KvLimits::max_total_bytes`.
+    StoreFull {
+        /// Bytes the store would hold after the write.
+        needed: usize,
+        /// Configured maximum.
+        max: usize,
+    },
+    /// The underlying medium failed.
+    Io(io::ErrorKind),
+}
+
+impl fmt::Display for KvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidKey => write!(f, "invalid key"),
+            Self::KeyTooLong { len, max } => write!(f, "key of {} bytes exceeds {}", len, max),
+            Self::ValueTooLarge { len, max } => {
+                write!(f, "value of {} bytes exceeds {}", len, max)
+            }
+            Self::StoreFull { needed, max } => {
+                write!(f, "store would hold {} bytes, limit is {}", needed, max)
+            }
+            Self::Io(kind) => write!(f, "storage I/O error: {}", kind),
+        }
+    }
+}
+
+impl std::error::Error for KvError {}
+
+impl From<io::Error> for KvError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err.kind())
+    }
+}
+
+/// Byte-valued storage addressed by string keys.
+///
+/// Methods take `&self`; implementations use interior mutability so one
+/// store can back several adapters at once (see the `&S` and `Arc<S>`
+/// impls). Reads are best effort: a value that cannot be read is a miss.
+pub trait KvStore {
+    /// Value stored under `key`.
+    fn get(&self, key: &str) -> Option<Vec<u8>>;
+
+    /// Store `value` under `key`, replacing any previous value.
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), KvError>;
+
+    /// Remove `key`; missing keys are ignored.
+    fn delete(&self, key: &str);
+
+    /// Every stored key starting with `prefix`, in ascending order.
+    fn keys_with_prefix(&self, prefix: &str) -> Vec<String>;
+
+    /// Limits `put` enforces.
+    fn limits(&self) -> KvLimits {
+        KvLimits::default()
+    }
+}
+
+impl<S: KvStore + ?Sized> KvStore for &S {
+    fn get(&self, key: &str) -> Option<Vec<u8>> {
+        (**self).get(key)
+    }
+
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), KvError> {
+        (**self).put(key, value)
+    }
+
+    fn delete(&self, key: &str) {
+        (**self).delete(key)
+    }
+
+    fn keys_with_prefix(&self, prefix: &str) -> Vec<String> {
+        (**self).keys_with_prefix(prefix)
+    }
+
+    fn limits(&self) -> KvLimits {
+        (**self).limits()
+    }
+}
+
+impl<S: KvStore + ?Sized> KvStore for Arc<S> {
+    fn get(&self, key: &str) -> Option<Vec<u8>> {
+        (**self).get(key)
+    }
+
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), KvError> {
+        (**self).put(key, value)
+    }
+
+    fn delete(&self, key: &str) {
+        (**self).delete(key)
+    }
+
+    fn keys_with_prefix(&self, prefix: &str) -> Vec<String> {
+        (**self).keys_with_prefix(prefix)
+    }
+
+    fn limits(&self) -> KvLimits {
+        (**self).limits()
+    }
+}
+
+/// In-memory `KvStore`.
+#[derive(Debug, Default)]
+pub struct MemoryKvStore {
+    limits: KvLimits,
+    entries: Mutex<BTreeMap<String, Vec<u8>>>,
+}
+
+impl MemoryKvStore {
+    /// Empty store with default limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enforce `limits` on later writes.
+    pub fn with_limits(mut self, limits: KvLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Combined size of all values, in bytes.
+    pub fn total_bytes(&self) -> usize {
+        self.entries
+            .lock()
+            .map(|entries| entries.values().map(Vec::len).sum())
+            .unwrap_or(0)
+    }
+}
+
+impl KvStore for MemoryKvStore {
+    fn get(&self, key: &str) -> Option<Vec<u8>> {
+        self.entries.lock().ok()?.get(key).cloned()
+    }
+
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), KvError> {
+        self.limits.check(key, value.len())?;
+        let mut entries = self
+            .entries
+            .lock()
+            .map_err(|_| KvError::Io(io::ErrorKind::Other))?;
+        if let Some(max) = self.limits.max_total_bytes {
+            let replaced = entries.get(key).map_or(0, Vec::len);
+            let total: usize = entries.values().map(Vec::len).sum();
+            let needed = total - replaced + value.len();
+            if needed > max {
+                return Err(KvError::StoreFull { needed, max });
+            }
+        }
+        entries.insert(key.to_string(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(key);
+        }
+    }
+
+    fn keys_with_prefix(&self, prefix: &str) -> Vec<String> {
+        self.entries
+            .lock()
+            .map(|entries| {
+                entries
+                    .range(prefix.to_string()..)
+                    .map(|(key, _)| key)
+                    .take_while(|key| key.starts_with(prefix))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn limits(&self) -> KvLimits {
+        self.limits
+    }
+}
+
+/// `KvStore` keeping one file per key under a directory.
+///
+/// Key segments become path components. Writes go to a dot-prefixed
+/// temporary file and are renamed into place, so a power cut leaves
+/// either the old or the new value. `max_total_bytes` is not enforced.
+#[derive(Clone, Debug)]
+pub struct DirKvStore {
+    root: PathBuf,
+    limits: KvLimits,
+}
+
+impl DirKvStore {
+    /// Store rooted at `root` (created on first write).
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            limits: KvLimits::default(),
+        }
+    }
+
+    /// Enforce `limits` on later writes.
+    pub fn with_limits(mut self, limits: KvLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Root directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// File path for `key` (or directory path for a key prefix).
+    pub fn path_for(&self, key: &str) -> PathBuf {
+        let mut path = self.root.clone();
+        path.extend(key.split('/').filter(|segment| !segment.is_empty()));
+        path
+    }
+
+    fn collect_keys(dir: &Path, base: &str, prefix: &str, out: &mut Vec<String>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if name.starts_with('.') {
+                continue;
+            }
+            let key = format!("{}{}", base, name);
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => {
+                    let nested = format!("{}/", key);
+                    if nested.starts_with(prefix) || prefix.starts_with(&nested) {
+                        Self::collect_keys(&entry.path(), &nested, prefix, out);
+                    }
+                }
+                Ok(_) if key.starts_with(prefix) => out.push(key),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl KvStore for DirKvStore {
+    fn get(&self, key: &str) -> Option<Vec<u8>> {
+        if !valid_key(key) {
+            return None;
+        }
+        fs::read(self.path_for(key)).ok()
+    }
+
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), KvError> {
+        self.limits.check(key, value.len())?;
+        let path = self.path_for(key);
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            return Err(KvError::InvalidKey);
+        };
+        fs::create_dir_all(dir)?;
+        let tmp = dir.join(format!(".{}.tmp", name.to_string_lossy()));
+        fs::write(&tmp, value)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) {
+        if valid_key(key) {
+            let _ = fs::remove_file(self.path_for(key));
+        }
+    }
+
+    fn keys_with_prefix(&self, prefix: &str) -> Vec<String> {
+        // Start from the deepest directory the prefix fully names.
+        let base = match prefix.rfind('/') {
+            Some(end) => &prefix[..=end],
+            None => "",
+        };
+        // Directory segments must be valid keys, or `..` walks out of root.
+        if !base.is_empty() && !valid_key(&base[..base.len() - 1]) {
+            return Vec::with_capacity(0);
+        }
+        let mut keys = Vec::with_capacity(0);
+        Self::collect_keys(&self.path_for(base), base, prefix, &mut keys);
+        keys.sort();
+        keys
+    }
+
+    fn limits(&self) -> KvLimits {
+        self.limits
+    }
+}
+
+fn valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.split('/').all(|segment| {
+            !segment.is_empty()
+                && !segment.starts_with('.')
+                && segment
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+        })
+}
+
+/// Key segment for a record id, zero-padded so keys sort numerically.
+pub(crate) fn id_segment(id: u64) -> String {
+    format!("{:020}", id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_annotate::{Annotation, AnnotationStore, KvAnnotationStore};
+    use crate::render_bookmark::{Bookmark, BookmarkStore, KvBookmarkStore};
+    use crate::render_cache::{BookFingerprint, KvCacheStore};
+    use crate::render_engine::RenderCacheStore;
+    use crate::render_ir::{ContentLocator, PaginationProfileId, RenderPage, TextPosition};
+
+    fn at(chapter_index: usize, block_index: usize) -> ContentLocator {
+        ContentLocator::new(
+            chapter_index,
+            TextPosition {
+                block_index,
+                char_offset: 0,
+            },
+        )
+    }
+
+    #[test]
+    fn memory_store_enforces_limits_and_lists_by_prefix() {
+        let store = MemoryKvStore::new().with_limits(KvLimits {
+            max_key_bytes: 16,
+            max_value_bytes: Some(4),
+            max_total_bytes: Some(6),
+        });
+        assert_eq!(store.put("a/1", b"abcd"), Ok(()));
+        assert_eq!(store.put("a/2", b"ab"), Ok(()));
+        assert_eq!(
+            store.put("a/3", b"a"),
+            Err(KvError::StoreFull { needed: 7, max: 6 })
+        );
+        // Replacing a value only counts the difference.
+        assert_eq!(store.put("a/1", b"abc"), Ok(()));
+        assert_eq!(
+            store.put("b", b"abcde"),
+            Err(KvError::ValueTooLarge { len: 5, max: 4 })
+        );
+        assert!(matches!(
+            store.put("a/very-long-key-name", b""),
+            Err(KvError::KeyTooLong { .. })
+        ));
+        for key in ["", "a//b", "../x", "a/.tmp", "a b"] {
+            assert_eq!(store.put(key, b""), Err(KvError::InvalidKey), "{:?}", key);
+        }
+        assert_eq!(store.keys_with_prefix("a/"), ["a/1", "a/2"]);
+        store.delete("a/1");
+        assert_eq!(store.get("a/1"), None);
+        assert_eq!(store.get("a/2").as_deref(), Some(&b"ab"[..]));
+        assert_eq!(store.total_bytes(), 2);
+    }
+
+    #[test]
+    fn dir_store_round_trips_and_skips_temporary_files() {
+        let root = std::env::temp_dir().join(format!("mu-epub-kv-{}", std::process::id()));
+        let store = DirKvStore::new(&root);
+        store.put("x/a/1", b"one").expect("write");
+        store.put("x/b", b"two").expect("write");
+        store.put("y", b"three").expect("write");
+        fs::write(root.join("x").join(".b.tmp"), b"partial").expect("write temp");
+        assert_eq!(store.get("x/a/1").as_deref(), Some(&b"one"[..]));
+        assert_eq!(store.keys_with_prefix("x/"), ["x/a/1", "x/b"]);
+        assert_eq!(store.keys_with_prefix("x/a"), ["x/a/1"]);
+        assert_eq!(store.keys_with_prefix(""), ["x/a/1", "x/b", "y"]);
+        store.delete("x/b");
+        assert_eq!(store.get("x/b"), None);
+        assert_eq!(store.get("../etc/passwd"), None);
+        assert!(store.keys_with_prefix("../").is_empty());
+        assert!(store.keys_with_prefix("x/../").is_empty());
+        assert!(store.keys_with_prefix("/x/").is_empty());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn cache_bookmarks_and_annotations_share_one_store() {
+        let kv = MemoryKvStore::new();
+        let book = BookFingerprint([3; 32]);
+        let cache = KvCacheStore::new(&kv, book);
+        let bookmarks = KvBookmarkStore::new(&kv, book);
+        let annotations = KvAnnotationStore::new(&kv, book);
+
+        let profile = PaginationProfileId::from_bytes(b"kv");
+        let pages = vec![RenderPage::new(1)];
+        cache.store_chapter_pages(profile, 2, &pages);
+        assert_eq!(cache.load_chapter_pages(profile, 2), Some(pages));
+
+        bookmarks.save_bookmark(&Bookmark::new(10, at(1, 5), "later"));
+        bookmarks.save_bookmark(&Bookmark::new(2, at(1, 1), "earlier"));
+        let labels: Vec<String> = bookmarks
+            .list_bookmarks()
+            .into_iter()
+            .map(|bookmark| bookmark.label)
+            .collect();
+        assert_eq!(labels, ["earlier", "later"]);
+        bookmarks.remove_bookmark(10);
+        assert_eq!(bookmarks.list_bookmarks().len(), 1);
+
+        let note = Annotation::new(7, at(0, 1), at(1, 0)).with_note("see chapter 2");
+        annotations.save_annotation(&note);
+        assert_eq!(annotations.load_annotations(1), std::slice::from_ref(&note));
+        assert!(annotations.load_annotations(2).is_empty());
+
+        // A corrupt record is skipped, and a corrupt chapter is dropped.
+        kv.put("annotations/0303030303030303/x", b"junk")
+            .expect("write");
+        assert_eq!(annotations.load_annotations(0), [note]);
+        let key = cache.chapter_key(profile, 2);
+        kv.put(&key, b"MUPC junk").expect("write");
+        assert_eq!(cache.load_chapter_pages(profile, 2), None);
+        assert_eq!(kv.get(&key), None);
+
+        assert_eq!(kv.keys_with_prefix("").len(), 3);
+        let other = KvBookmarkStore::new(&kv, BookFingerprint([4; 32]));
+        assert!(other.list_bookmarks().is_empty());
+    }
+}
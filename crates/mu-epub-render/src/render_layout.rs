@@ -1,12 +1,17 @@
-use mu_epub::{BlockRole, ComputedTextStyle, StyledEvent, StyledEventOrRun, StyledRun};
+use mu_epub::{
+    BlockRole, ComputedTextStyle, StyledEvent, StyledEventOrRun, StyledImage, StyledRun,
+};
 
 use crate::render_ir::{
-    DrawCommand, JustifyMode, ObjectLayoutConfig, PageChromeCommand, PageChromeConfig,
-    PageChromeKind, RenderIntent, RenderPage, ResolvedTextStyle, TextCommand, TypographyConfig,
+    DrawCommand, GlyphPosition, ImageCommand, JustifyMode, LinkTarget, ObjectLayoutConfig,
+    OverlaySize, PageAnnotation, PageChromeConfig, PageRect, RectCommand, RenderIntent, RenderPage,
+    ResolvedTextStyle, Rotation, StaticPageChrome, SvgMode, TextCommand, TextPosition,
+    TypographyConfig, VerticalMetrics,
 };
+use crate::render_svg::{rasterize_svg, svg_intrinsic_size};
 
 const SOFT_HYPHEN: char = '\u{00AD}';
-const LINE_FIT_GUARD_PX: f32 = 4.0;
+pub(crate) const LINE_FIT_GUARD_PX: f32 = 4.0;
 
 /// Policy for discretionary soft-hyphen handling.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -74,13 +79,80 @@ impl LayoutConfig {
         }
     }
 
-    fn content_width(self) -> i32 {
+    /// Reader-facing config for a panel rotated by `rotation`.
+    ///
+    /// Display axes are swapped for quarter turns and margins follow the
+    /// physical panel edges they sit against.
+    pub fn rotated(self, rotation: Rotation) -> Self {
+        let physical = OverlaySize {
+            width: self.display_width.max(0) as u32,
+            height: self.display_height.max(0) as u32,
+        };
+        let logical = rotation.logical_size(physical);
+        let [margin_top, margin_right, margin_bottom, margin_left] = rotation.logical_insets([
+            self.margin_top,
+            self.margin_right,
+            self.margin_bottom,
+            self.margin_left,
+        ]);
+        Self {
+            display_width: logical.width as i32,
+            display_height: logical.height as i32,
+            margin_top,
+            margin_right,
+            margin_bottom,
+            margin_left,
+            ..self
+        }
+    }
+
+    /// Config with every pixel length multiplied by `scale`.
+    ///
+    /// Ratios, policies, and the render intent are kept.
+    pub(crate) fn scaled(self, scale: f32) -> Self {
+        let px = |v: i32| (v as f32 * scale).round() as i32;
+        Self {
+            display_width: px(self.display_width).max(1),
+            display_height: px(self.display_height).max(1),
+            margin_left: px(self.margin_left),
+            margin_right: px(self.margin_right),
+            margin_top: px(self.margin_top),
+            margin_bottom: px(self.margin_bottom),
+            line_gap_px: px(self.line_gap_px),
+            paragraph_gap_px: px(self.paragraph_gap_px),
+            heading_gap_px: px(self.heading_gap_px),
+            list_indent_px: px(self.list_indent_px),
+            first_line_indent_px: px(self.first_line_indent_px),
+            min_line_height_px: px(self.min_line_height_px).max(1),
+            max_line_height_px: px(self.max_line_height_px).max(1),
+            ..self
+        }
+    }
+
+    /// Config with render-intent policies folded in.
+    ///
+    /// Draft intents swap in simplified page chrome.
+    pub(crate) fn effective(self) -> Self {
+        if !self.render_intent.draft {
+            return self;
+        }
+        Self {
+            page_chrome: self.page_chrome.simplified(),
+            ..self
+        }
+    }
+
+    pub(crate) fn content_width(self) -> i32 {
         (self.display_width - self.margin_left - self.margin_right).max(1)
     }
 
-    fn content_bottom(self) -> i32 {
+    pub(crate) fn content_bottom(self) -> i32 {
         self.display_height - self.margin_bottom
     }
+
+    pub(crate) fn content_height(self) -> i32 {
+        (self.content_bottom() - self.margin_top).max(1)
+    }
 }
 
 impl Default for LayoutConfig {
@@ -127,7 +199,9 @@ pub struct LayoutSession {
 impl LayoutEngine {
     /// Create a layout engine.
     pub fn new(cfg: LayoutConfig) -> Self {
-        Self { cfg }
+        Self {
+            cfg: cfg.effective(),
+        }
     }
 
     /// Layout styled items into pages.
@@ -162,8 +236,8 @@ impl LayoutEngine {
         session.finish(&mut on_page);
     }
 
-    fn handle_run(&self, st: &mut LayoutState, ctx: &mut BlockCtx, run: StyledRun) {
-        let mut style = to_resolved_style(&run.style);
+    fn handle_run(&self, st: &mut LayoutState, ctx: &mut BlockCtx, mut run: StyledRun) {
+        let mut style = to_resolved_style(&run.style, &self.cfg.render_intent);
         style.font_id = Some(run.font_id);
         if !run.resolved_family.is_empty() {
             style.family = run.resolved_family.clone();
@@ -175,7 +249,9 @@ impl LayoutEngine {
             style.role = BlockRole::ListItem;
         }
 
+        st.pending_anchors.append(&mut run.anchors);
         for word in run.text.split_whitespace() {
+            st.advance_word(word);
             let mut extra_indent_px = 0;
             if ctx.pending_indent
                 && matches!(style.role, BlockRole::Body | BlockRole::Paragraph)
@@ -185,13 +261,125 @@ impl LayoutEngine {
                 extra_indent_px = self.cfg.first_line_indent_px.max(0);
                 ctx.pending_indent = false;
             }
-            st.push_word(word, style.clone(), extra_indent_px);
+            st.push_word(word, style.clone(), extra_indent_px, run.link.as_deref());
+            if let Some(line) = st.line.as_mut() {
+                line.anchors.append(&mut st.pending_anchors);
+            }
+        }
+    }
+
+    fn handle_image(&self, st: &mut LayoutState, image: StyledImage) {
+        let objects = self.cfg.object_layout;
+        let is_svg = image.is_svg();
+        if is_svg && objects.svg_mode == SvgMode::Ignore {
+            return;
+        }
+        let inline = st.line.is_some();
+        st.flush_line(true);
+        st.begin_block();
+        st.block_used = true;
+        let start = TextPosition {
+            block_index: st.block_index,
+            char_offset: 0,
+        };
+
+        let markup = image
+            .inline_svg
+            .as_deref()
+            .map(str::as_bytes)
+            .filter(|markup| markup.len() <= objects.svg_max_bytes);
+        let intrinsic = match (image.width_px, image.height_px) {
+            (Some(w), Some(h)) => Some((w, h)),
+            _ => markup.and_then(svg_intrinsic_size),
+        };
+        let content_w = self.cfg.content_width() as f32;
+        let (w, h) = intrinsic
+            .filter(|(w, h)| *w > 0.0 && *h > 0.0)
+            .unwrap_or((content_w, content_w * 0.75));
+        let max_h = if inline {
+            self.cfg.content_height() as f32
+                * objects.max_inline_image_height_ratio.clamp(0.05, 1.0)
+        } else {
+            self.cfg.content_height() as f32
+        };
+        let scale = (content_w / w).min(max_h / h).min(1.0);
+        let width = (w * scale).round().max(1.0) as u32;
+        let height = (h * scale).round().max(1.0) as u32;
+
+        let (x, y) = st.place_block(width, height);
+        st.page.metrics.start_position.get_or_insert(start);
+        st.page.metrics.image_count += 1;
+        let draft = self.cfg.render_intent.draft;
+        let bitmap = match objects.svg_mode {
+            _ if draft => None,
+            SvgMode::Rasterize if is_svg => markup.and_then(|markup| {
+                let mut bitmap =
+                    rasterize_svg(markup, width, height, objects.svg_max_raster_pixels)?;
+                self.cfg.render_intent.dither_bitmap(&mut bitmap);
+                Some(bitmap)
+            }),
+            _ => None,
+        };
+        let placeholder = draft
+            || image.svg_oversize
+            || (is_svg && bitmap.is_none() && objects.svg_mode != SvgMode::Native);
+        let alt = Some(image.alt.trim()).filter(|alt| !alt.is_empty());
+        if alt.is_some() || image.caption.is_some() {
+            st.page.annotations.push(PageAnnotation::ImageDescription {
+                rect: PageRect::new(x, y, width, height),
+                src: image.src.clone(),
+                alt: alt.map(str::to_string),
+                caption: image.caption.clone(),
+            });
+        }
+        if placeholder {
+            st.page.push_content_command(DrawCommand::Rect(RectCommand {
+                x,
+                y,
+                width,
+                height,
+                fill: false,
+                color: self.cfg.render_intent.foreground(),
+            }));
+            if let Some(alt) = alt.filter(|_| objects.alt_text_fallback && !draft) {
+                let mut style = placeholder_text_style();
+                style.color = self.cfg.render_intent.foreground();
+                let text = truncate_to_width(alt, &style, width as f32 - 8.0);
+                if !text.is_empty() {
+                    st.page.line_positions.push(start);
+                    st.page.push_content_command(DrawCommand::Text(TextCommand {
+                        x: x + 4,
+                        baseline_y: y + 4,
+                        text,
+                        font_id: None,
+                        hint: self.cfg.render_intent.text_hint(style.size_px),
+                        style,
+                        glyphs: None,
+                    }));
+                }
+            }
+        } else {
+            st.page
+                .push_content_command(DrawCommand::Image(ImageCommand {
+                    x,
+                    y,
+                    width,
+                    height,
+                    src: image.src,
+                    alt: image.alt,
+                    bitmap,
+                }));
         }
+        st.page.sync_commands();
+        st.add_vertical_gap(self.cfg.paragraph_gap_px);
+        // Text after an image starts a new block.
+        st.begin_block();
     }
 
     fn handle_event(&self, st: &mut LayoutState, ctx: &mut BlockCtx, ev: StyledEvent) {
         match ev {
             StyledEvent::ParagraphStart => {
+                st.begin_block();
                 if !ctx.suppress_next_indent {
                     ctx.pending_indent = true;
                 }
@@ -204,12 +392,15 @@ impl LayoutEngine {
             }
             StyledEvent::HeadingStart(level) => {
                 st.flush_line(true);
+                st.begin_block();
+                st.heading_open = false;
                 st.add_vertical_gap(self.cfg.heading_gap_px);
                 ctx.heading_level = Some(level.clamp(1, 6));
                 ctx.pending_indent = false;
             }
             StyledEvent::HeadingEnd(_) => {
                 st.flush_line(true);
+                st.heading_open = false;
                 st.add_vertical_gap(self.cfg.heading_gap_px);
                 ctx.heading_level = None;
                 ctx.pending_indent = false;
@@ -217,6 +408,7 @@ impl LayoutEngine {
             }
             StyledEvent::ListItemStart => {
                 st.flush_line(true);
+                st.begin_block();
                 ctx.in_list = true;
                 ctx.pending_indent = false;
             }
@@ -241,6 +433,7 @@ impl LayoutSession {
             StyledEventOrRun::Event(ev) => {
                 self.engine.handle_event(&mut self.st, &mut self.ctx, ev);
             }
+            StyledEventOrRun::Image(image) => self.engine.handle_image(&mut self.st, image),
         }
     }
 
@@ -260,12 +453,38 @@ impl LayoutSession {
         }
     }
 
+    /// Number positions from `pos` as if the content before it had been
+    /// laid out; a mid-block position continues the block without a
+    /// first-line indent.
+    pub(crate) fn resume_at(&mut self, pos: TextPosition) {
+        self.st.block_index = pos.block_index;
+        self.st.block_used = pos.char_offset > 0;
+        self.st.block_chars = pos.char_offset.saturating_sub(1);
+        if pos.char_offset > 0 {
+            self.ctx.pending_indent = false;
+        }
+    }
+
+    /// Treat the first page as continuing an earlier one: vertical gaps
+    /// before its first content are dropped, as at a forward page break.
+    pub(crate) fn continue_page(&mut self) {
+        self.st.continues_page = true;
+    }
+
+    /// Lay out the next paragraph as the first one after a heading.
+    pub(crate) fn suppress_next_indent(&mut self) {
+        self.ctx.suppress_next_indent = self.engine.cfg.suppress_indent_after_heading;
+    }
+
     /// Finish the session and stream resulting pages.
     pub fn finish<F>(&mut self, on_page: &mut F)
     where
         F: FnMut(RenderPage),
     {
         self.st.flush_line(true);
+        for id in self.st.pending_anchors.drain(..) {
+            self.st.page.annotations.push(PageAnnotation::anchor(id));
+        }
         let mut pages = core::mem::take(&mut self.st).into_pages();
         annotate_page_chrome(&mut pages, self.engine.cfg);
         for page in pages {
@@ -289,6 +508,62 @@ struct CurrentLine {
     width_px: f32,
     line_height_px: i32,
     left_inset_px: i32,
+    links: Vec<LineLink>,
+    /// Element ids starting on this line.
+    anchors: Vec<String>,
+    /// Source position of the first word.
+    start: TextPosition,
+}
+
+/// Char range of `CurrentLine::text` covered by one hyperlink.
+#[derive(Clone, Debug)]
+struct LineLink {
+    start: usize,
+    end: usize,
+    href: String,
+}
+
+impl CurrentLine {
+    fn new(
+        style: ResolvedTextStyle,
+        line_height_px: i32,
+        left_inset_px: i32,
+        start: TextPosition,
+    ) -> Self {
+        Self {
+            text: String::with_capacity(64),
+            style,
+            width_px: 0.0,
+            line_height_px,
+            left_inset_px,
+            links: Vec::with_capacity(0),
+            anchors: Vec::with_capacity(0),
+            start,
+        }
+    }
+
+    /// Append `word`, separated by a space when the line is non-empty.
+    fn append_word(&mut self, word: &str, word_w: f32, space_w: f32, link: Option<&str>) {
+        if !self.text.is_empty() {
+            self.text.push(' ');
+            self.width_px += space_w;
+        }
+        let start = self.text.chars().count();
+        self.text.push_str(word);
+        self.width_px += word_w;
+        let Some(href) = link else {
+            return;
+        };
+        let end = start + word.chars().count();
+        match self.links.last_mut() {
+            Some(prev) if prev.href == href && prev.end + 1 == start => prev.end = end,
+            _ => self.links.push(LineLink {
+                start,
+                end,
+                href: href.to_string(),
+            }),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -299,6 +574,21 @@ struct LayoutState {
     page: RenderPage,
     line: Option<CurrentLine>,
     emitted: Vec<RenderPage>,
+    /// A heading annotation on the current page is still receiving lines.
+    heading_open: bool,
+    /// Index of the block receiving text.
+    block_index: usize,
+    /// Chars consumed in the current block, excluding a trailing space.
+    block_chars: usize,
+    /// The current block has received a word or image.
+    block_used: bool,
+    /// Source position of the word being pushed.
+    word_start: TextPosition,
+    /// Element ids waiting for the next word to land on a line.
+    pending_anchors: Vec<String>,
+    /// The first page continues an earlier one, so gaps before its first
+    /// content are dropped as they would have been at the page break.
+    continues_page: bool,
 }
 
 impl Default for LayoutState {
@@ -316,10 +606,47 @@ impl LayoutState {
             page: RenderPage::new(1),
             line: None,
             emitted: Vec::with_capacity(2),
+            heading_open: false,
+            block_index: 0,
+            block_chars: 0,
+            block_used: false,
+            word_start: TextPosition::default(),
+            pending_anchors: Vec::with_capacity(0),
+            continues_page: false,
         }
     }
 
-    fn push_word(&mut self, word: &str, style: ResolvedTextStyle, extra_first_line_indent_px: i32) {
+    /// Move to a new block unless the current one is still empty.
+    fn begin_block(&mut self) {
+        if self.block_used {
+            self.block_index += 1;
+            self.block_chars = 0;
+            self.block_used = false;
+        }
+    }
+
+    /// Record the source position of the next word and advance past it.
+    fn advance_word(&mut self, word: &str) {
+        let start = if self.block_used {
+            self.block_chars + 1
+        } else {
+            0
+        };
+        self.word_start = TextPosition {
+            block_index: self.block_index,
+            char_offset: start,
+        };
+        self.block_chars = start + word.chars().filter(|ch| *ch != SOFT_HYPHEN).count();
+        self.block_used = true;
+    }
+
+    fn push_word(
+        &mut self,
+        word: &str,
+        style: ResolvedTextStyle,
+        extra_first_line_indent_px: i32,
+        link: Option<&str>,
+    ) {
         if word.is_empty() {
             return;
         }
@@ -332,13 +659,12 @@ impl LayoutState {
         left_inset_px += extra_first_line_indent_px.max(0);
 
         if self.line.is_none() {
-            self.line = Some(CurrentLine {
-                text: String::with_capacity(64),
-                style: style.clone(),
-                width_px: 0.0,
-                line_height_px: line_height_px(&style, &self.cfg),
+            self.line = Some(CurrentLine::new(
+                style.clone(),
+                line_height_px(&style, &self.cfg),
                 left_inset_px,
-            });
+                self.word_start,
+            ));
         }
 
         let Some(mut line) = self.line.take() else {
@@ -349,6 +675,7 @@ impl LayoutState {
             line.style = style.clone();
             line.left_inset_px = left_inset_px;
             line.line_height_px = line_height_px(&style, &self.cfg);
+            line.start = self.word_start;
         }
 
         let space_w = if line.text.is_empty() {
@@ -369,35 +696,32 @@ impl LayoutState {
                     crate::render_ir::HyphenationMode::Discretionary
                 ))
                 && word.contains(SOFT_HYPHEN)
-                && self.try_break_word_at_soft_hyphen(&mut line, word, &style, max_width, space_w)
+                && self.try_break_word_at_soft_hyphen(
+                    &mut line, word, &style, max_width, space_w, link,
+                )
             {
                 return;
             }
             if line.text.is_empty() {
-                line.text = sanitized_word;
-                line.width_px = word_w;
+                line.append_word(&sanitized_word, word_w, 0.0, link);
                 line.style = style;
                 self.line = Some(line);
                 return;
             }
             self.line = Some(line);
             self.flush_line(false);
-            self.line = Some(CurrentLine {
-                text: sanitized_word,
-                style: style.clone(),
-                width_px: word_w,
-                line_height_px: line_height_px(&style, &self.cfg),
+            let mut next = CurrentLine::new(
+                style.clone(),
+                line_height_px(&style, &self.cfg),
                 left_inset_px,
-            });
+                self.word_start,
+            );
+            next.append_word(&sanitized_word, word_w, 0.0, link);
+            self.line = Some(next);
             return;
         }
 
-        if !line.text.is_empty() {
-            line.text.push(' ');
-            line.width_px += space_w;
-        }
-        line.text.push_str(&sanitized_word);
-        line.width_px += word_w;
+        line.append_word(&sanitized_word, word_w, space_w, link);
         line.style = style;
         self.line = Some(line);
     }
@@ -409,6 +733,7 @@ impl LayoutState {
         style: &ResolvedTextStyle,
         max_width: f32,
         space_w: f32,
+        link: Option<&str>,
     ) -> bool {
         let parts: Vec<&str> = raw_word.split(SOFT_HYPHEN).collect();
         if parts.len() < 2 {
@@ -440,16 +765,14 @@ impl LayoutState {
             return false;
         };
 
-        if !line.text.is_empty() {
-            line.text.push(' ');
-            line.width_px += space_w;
-        }
-        line.text.push_str(&prefix_with_hyphen);
-        line.width_px += measure_text(&prefix_with_hyphen, style);
+        let prefix_w = measure_text(&prefix_with_hyphen, style);
+        line.append_word(&prefix_with_hyphen, prefix_w, space_w, link);
 
         self.line = Some(line.clone());
         self.flush_line(false);
-        self.push_word(&remainder, style.clone(), 0);
+        // The remainder starts after the prefix chars, minus the added `-`.
+        self.word_start.char_offset += prefix_with_hyphen.chars().count() - 1;
+        self.push_word(&remainder, style.clone(), 0, link);
         true
     }
 
@@ -464,10 +787,13 @@ impl LayoutState {
         if self.cursor_y + line.line_height_px > self.cfg.content_bottom() {
             self.start_next_page();
         }
+        self.page.metrics.start_position.get_or_insert(line.start);
+        self.page.line_positions.push(line.start);
 
         let available_width =
             ((self.cfg.content_width() - line.left_inset_px) as f32 - LINE_FIT_GUARD_PX) as i32;
         let words = line.text.split_whitespace().count();
+        self.page.metrics.word_count += words;
         let spaces = line.text.chars().filter(|c| *c == ' ').count() as i32;
         let fill_ratio = if available_width > 0 {
             line.width_px / available_width as f32
@@ -502,21 +828,110 @@ impl LayoutState {
             line.style.justify_mode = JustifyMode::None;
         }
 
+        let spacing_adjusted = matches!(line.style.justify_mode, JustifyMode::InterWord { .. })
+            || line.style.letter_spacing != 0.0;
+        let glyphs = (self.cfg.typography.emit_glyph_positions && spacing_adjusted)
+            .then(|| glyph_positions(&line.text, &line.style));
+        let x = self.cfg.margin_left + line.left_inset_px;
+        self.push_link_annotations(&line, x);
+        self.push_heading_annotation(&line, x);
+        for id in line.anchors.drain(..) {
+            self.page.annotations.push(PageAnnotation::anchor(id));
+        }
         self.page
             .push_content_command(DrawCommand::Text(TextCommand {
-                x: self.cfg.margin_left + line.left_inset_px,
+                x,
                 baseline_y: self.cursor_y,
                 text: line.text,
                 font_id: line.style.font_id,
+                hint: self.cfg.render_intent.text_hint(line.style.size_px),
                 style: line.style,
+                glyphs,
             }));
         self.page.sync_commands();
 
         self.cursor_y += line.line_height_px + self.cfg.line_gap_px;
     }
 
+    fn push_link_annotations(&mut self, line: &CurrentLine, x: i32) {
+        if line.links.is_empty() {
+            return;
+        }
+        let mut offsets = Vec::with_capacity(line.text.chars().count() + 1);
+        offsets.push(0);
+        for glyph in glyph_positions(&line.text, &line.style) {
+            let last = offsets.last().copied().unwrap_or(0);
+            offsets.push(last + glyph.x_advance);
+        }
+        for link in &line.links {
+            let start = offsets.get(link.start).copied().unwrap_or(0);
+            let end = offsets.get(link.end).copied().unwrap_or(start);
+            if end <= start {
+                continue;
+            }
+            self.page.annotations.push(PageAnnotation::Link {
+                rect: PageRect::new(
+                    x + start,
+                    self.cursor_y,
+                    (end - start) as u32,
+                    line.line_height_px.max(1) as u32,
+                ),
+                target: LinkTarget::from_href(&link.href),
+            });
+        }
+    }
+
+    /// Record a heading line, extending the open heading on this page.
+    fn push_heading_annotation(&mut self, line: &CurrentLine, x: i32) {
+        let BlockRole::Heading(level) = line.style.role else {
+            return;
+        };
+        let rect = PageRect::new(
+            x,
+            self.cursor_y,
+            measure_text(&line.text, &line.style).ceil() as u32,
+            line.line_height_px.max(1) as u32,
+        );
+        if self.heading_open {
+            let open = self
+                .page
+                .annotations
+                .iter_mut()
+                .rev()
+                .find_map(|a| match a {
+                    PageAnnotation::Heading {
+                        text, rect: area, ..
+                    } => Some((text, area)),
+                    _ => None,
+                });
+            if let Some((text, area)) = open {
+                text.push(' ');
+                text.push_str(&line.text);
+                *area = area.union(&rect);
+                return;
+            }
+        }
+        self.page.annotations.push(PageAnnotation::Heading {
+            level,
+            text: line.text.clone(),
+            rect,
+        });
+        self.heading_open = true;
+    }
+
+    fn place_block(&mut self, width: u32, height: u32) -> (i32, i32) {
+        let page_has_content = !self.page.content_commands.is_empty();
+        if page_has_content && self.cursor_y + height as i32 > self.cfg.content_bottom() {
+            self.start_next_page();
+        }
+        let x = self.cfg.margin_left + (self.cfg.content_width() - width as i32).max(0) / 2;
+        let y = self.cursor_y;
+        self.cursor_y += height as i32;
+        (x, y)
+    }
+
     fn add_vertical_gap(&mut self, gap_px: i32) {
-        if gap_px <= 0 {
+        if gap_px <= 0 || (self.continues_page && self.page.content_commands.is_empty()) {
             return;
         }
         self.cursor_y += gap_px;
@@ -527,6 +942,7 @@ impl LayoutState {
 
     fn start_next_page(&mut self) {
         self.flush_page_if_non_empty();
+        self.continues_page = false;
         self.page_no += 1;
         self.page = RenderPage::new(self.page_no);
         self.cursor_y = self.cfg.margin_top;
@@ -541,6 +957,20 @@ impl LayoutState {
         }
         let mut page = core::mem::replace(&mut self.page, RenderPage::new(self.page_no + 1));
         page.metrics.chapter_page_index = page.page_number.saturating_sub(1);
+        if self.cfg.render_intent.inverted {
+            // Dark page fill under all content.
+            page.content_commands.insert(
+                0,
+                DrawCommand::Rect(RectCommand {
+                    x: 0,
+                    y: 0,
+                    width: self.cfg.display_width.max(0) as u32,
+                    height: self.cfg.display_height.max(0) as u32,
+                    fill: true,
+                    color: Some(self.cfg.render_intent.background()),
+                }),
+            );
+        }
         page.sync_commands();
         self.emitted.push(page);
     }
@@ -555,7 +985,10 @@ impl LayoutState {
     }
 }
 
-fn to_resolved_style(style: &ComputedTextStyle) -> ResolvedTextStyle {
+pub(crate) fn to_resolved_style(
+    style: &ComputedTextStyle,
+    intent: &RenderIntent,
+) -> ResolvedTextStyle {
     let family = style
         .family_stack
         .first()
@@ -571,10 +1004,15 @@ fn to_resolved_style(style: &ComputedTextStyle) -> ResolvedTextStyle {
         letter_spacing: style.letter_spacing,
         role: style.block_role,
         justify_mode: JustifyMode::None,
+        color: style
+            .color
+            .map(|color| intent.map_color(color))
+            .or_else(|| intent.foreground()),
+        metrics: VerticalMetrics::estimate(style.size_px),
     }
 }
 
-fn measure_text(text: &str, style: &ResolvedTextStyle) -> f32 {
+pub(crate) fn measure_text(text: &str, style: &ResolvedTextStyle) -> f32 {
     let chars = text.chars().count() as f32;
     if chars == 0.0 {
         return 0.0;
@@ -606,7 +1044,72 @@ fn measure_text(text: &str, style: &ResolvedTextStyle) -> f32 {
     width
 }
 
-fn line_height_px(style: &ResolvedTextStyle, cfg: &LayoutConfig) -> i32 {
+/// Per-char advances matching `measure_text`, with justification slack spread
+/// over spaces (earlier spaces take the remainder). Positions are rounded
+/// cumulatively so the advances sum to the laid-out line width.
+pub(crate) fn glyph_positions(text: &str, style: &ResolvedTextStyle) -> Vec<GlyphPosition> {
+    let char_w = measure_text("x", style);
+    let chars = text.chars().count();
+    let spaces = text.chars().filter(|c| *c == ' ').count() as i32;
+    let (per_space, mut remainder) = match style.justify_mode {
+        JustifyMode::InterWord { extra_px_total } if spaces > 0 => (
+            extra_px_total.max(0) / spaces,
+            extra_px_total.max(0) % spaces,
+        ),
+        _ => (0, 0),
+    };
+    let mut glyphs = Vec::with_capacity(chars);
+    let mut pen = 0.0f32;
+    for (idx, ch) in text.chars().enumerate() {
+        let mut next = pen + char_w;
+        if idx + 1 < chars {
+            next += style.letter_spacing;
+        }
+        if ch == ' ' {
+            next += per_space as f32;
+            if remainder > 0 {
+                next += 1.0;
+                remainder -= 1;
+            }
+        }
+        glyphs.push(GlyphPosition {
+            x_advance: next.round() as i32 - pen.round() as i32,
+            x_offset: 0,
+        });
+        pen = next;
+    }
+    glyphs
+}
+
+fn placeholder_text_style() -> ResolvedTextStyle {
+    ResolvedTextStyle {
+        font_id: None,
+        family: "serif".to_string(),
+        weight: 400,
+        italic: true,
+        size_px: 16.0,
+        line_height: 1.4,
+        letter_spacing: 0.0,
+        role: BlockRole::Body,
+        justify_mode: JustifyMode::None,
+        color: None,
+        metrics: VerticalMetrics::estimate(16.0),
+    }
+}
+
+fn truncate_to_width(text: &str, style: &ResolvedTextStyle, max_width: f32) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        out.push(ch);
+        if measure_text(&out, style) > max_width {
+            out.pop();
+            break;
+        }
+    }
+    out
+}
+
+pub(crate) fn line_height_px(style: &ResolvedTextStyle, cfg: &LayoutConfig) -> i32 {
     let min_lh = cfg.min_line_height_px.min(cfg.max_line_height_px);
     let max_lh = cfg.max_line_height_px.max(cfg.min_line_height_px);
     (style.size_px * style.line_height)
@@ -628,29 +1131,9 @@ fn annotate_page_chrome(pages: &mut [RenderPage], cfg: LayoutConfig) {
     }
     let total = pages.len();
     for page in pages.iter_mut() {
-        if cfg.page_chrome.header_enabled {
-            page.push_chrome_command(DrawCommand::PageChrome(PageChromeCommand {
-                kind: PageChromeKind::Header,
-                text: Some(format!("Page {}", page.page_number)),
-                current: None,
-                total: None,
-            }));
-        }
-        if cfg.page_chrome.footer_enabled {
-            page.push_chrome_command(DrawCommand::PageChrome(PageChromeCommand {
-                kind: PageChromeKind::Footer,
-                text: Some(format!("Page {}", page.page_number)),
-                current: None,
-                total: None,
-            }));
-        }
-        if cfg.page_chrome.progress_enabled {
-            page.push_chrome_command(DrawCommand::PageChrome(PageChromeCommand {
-                kind: PageChromeKind::Progress,
-                text: None,
-                current: Some(page.page_number),
-                total: Some(total),
-            }));
+        let chrome = cfg.page_chrome.compose(page, total, &StaticPageChrome);
+        for cmd in chrome {
+            page.push_chrome_command(cmd);
         }
         page.sync_commands();
     }
@@ -659,6 +1142,8 @@ fn annotate_page_chrome(pages: &mut [RenderPage], cfg: LayoutConfig) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::render_ir::{ChapterStats, ColorTarget, PageChromeKind, PageMetrics};
+    use mu_epub::Color;
 
     fn body_run(text: &str) -> StyledEventOrRun {
         StyledEventOrRun::Run(StyledRun {
@@ -671,9 +1156,12 @@ mod tests {
                 line_height: 1.4,
                 letter_spacing: 0.0,
                 block_role: BlockRole::Body,
+                color: None,
             },
             font_id: 0,
             resolved_family: "serif".to_string(),
+            link: None,
+            anchors: Vec::with_capacity(0),
         })
     }
 
@@ -686,7 +1174,7 @@ mod tests {
             ..LayoutConfig::default()
         };
         let engine = LayoutEngine::new(cfg);
-        let mut items = Vec::new();
+        let mut items = Vec::with_capacity(8);
         for _ in 0..50 {
             items.push(StyledEventOrRun::Event(StyledEvent::ParagraphStart));
             items.push(body_run("hello world mu-epub renderer pipeline"));
@@ -721,6 +1209,144 @@ mod tests {
         assert!(saw_justified);
     }
 
+    #[test]
+    fn headings_emit_one_annotation_per_page() {
+        let cfg = LayoutConfig {
+            display_width: 200,
+            ..LayoutConfig::default()
+        };
+        let engine = LayoutEngine::new(cfg);
+        let items = vec![
+            StyledEventOrRun::Event(StyledEvent::HeadingStart(2)),
+            body_run("A heading long enough to wrap across several lines"),
+            StyledEventOrRun::Event(StyledEvent::HeadingEnd(2)),
+            StyledEventOrRun::Event(StyledEvent::ParagraphStart),
+            body_run("Body text."),
+            StyledEventOrRun::Event(StyledEvent::ParagraphEnd),
+            StyledEventOrRun::Event(StyledEvent::HeadingStart(3)),
+            body_run("Next"),
+            StyledEventOrRun::Event(StyledEvent::HeadingEnd(3)),
+        ];
+        let pages = engine.layout_items(items);
+        assert_eq!(pages.len(), 1);
+        let headings: Vec<(u8, &str, PageRect)> = pages[0]
+            .annotations
+            .iter()
+            .filter_map(|a| match a {
+                PageAnnotation::Heading { level, text, rect } => {
+                    Some((*level, text.as_str(), *rect))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(headings.len(), 2);
+        assert_eq!(
+            (headings[0].0, headings[0].1),
+            (2, "A heading long enough to wrap across several lines")
+        );
+        let heading_tops: Vec<i32> = pages[0]
+            .content_commands
+            .iter()
+            .filter_map(|cmd| match cmd {
+                DrawCommand::Text(t) if t.style.role == BlockRole::Heading(2) => Some(t.baseline_y),
+                _ => None,
+            })
+            .collect();
+        assert!(heading_tops.len() > 1);
+        assert_eq!(headings[0].2.y, heading_tops[0]);
+        assert!(headings[0].2.bottom() > heading_tops[heading_tops.len() - 1]);
+        assert_eq!((headings[1].0, headings[1].1), (3, "Next"));
+        assert!(headings[1].2.y > headings[0].2.y + headings[0].2.height as i32);
+    }
+
+    #[test]
+    fn link_runs_emit_hotspot_annotations() {
+        let engine = LayoutEngine::new(LayoutConfig::default());
+        let link = |text: &str, href: &str| match body_run(text) {
+            StyledEventOrRun::Run(mut run) => {
+                run.link = Some(href.to_string());
+                StyledEventOrRun::Run(run)
+            }
+            other => other,
+        };
+        let items = vec![
+            StyledEventOrRun::Event(StyledEvent::ParagraphStart),
+            body_run("see"),
+            link("the notes", "text/notes.xhtml#n1"),
+            body_run("or"),
+            link("site", "https://example.com"),
+            StyledEventOrRun::Event(StyledEvent::ParagraphEnd),
+        ];
+        let pages = engine.layout_items(items);
+        assert_eq!(pages.len(), 1);
+        let text = pages[0]
+            .commands
+            .iter()
+            .find_map(|cmd| match cmd {
+                DrawCommand::Text(t) => Some(t.clone()),
+                _ => None,
+            })
+            .expect("text command");
+        let links: Vec<(PageRect, LinkTarget)> = pages[0]
+            .annotations
+            .iter()
+            .filter_map(|a| match a {
+                PageAnnotation::Link { rect, target } => Some((*rect, target.clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(links.len(), 2);
+
+        let (notes_rect, notes_target) = &links[0];
+        assert!(notes_rect.x > text.x);
+        assert_eq!(notes_rect.y, text.baseline_y);
+        let line_w = measure_text(&text.text, &text.style);
+        assert!((notes_rect.width as f32) < line_w / 2.0);
+        match notes_target {
+            LinkTarget::Internal(link) => {
+                assert_eq!(link.href, "text/notes.xhtml");
+                assert_eq!(link.fragment.as_deref(), Some("n1"));
+            }
+            other => panic!("expected internal link, got {other:?}"),
+        }
+
+        let (site_rect, site_target) = &links[1];
+        assert!(site_rect.x > notes_rect.right());
+        assert_eq!(
+            site_target,
+            &LinkTarget::External("https://example.com".to_string())
+        );
+    }
+
+    #[test]
+    fn anchors_land_on_the_page_of_their_first_word() {
+        let engine = LayoutEngine::new(LayoutConfig {
+            display_height: 200,
+            ..LayoutConfig::default()
+        });
+        let anchored = |text: &str, id: &str| match body_run(text) {
+            StyledEventOrRun::Run(mut run) => {
+                run.anchors = vec![id.to_string()];
+                StyledEventOrRun::Run(run)
+            }
+            other => other,
+        };
+        let mut items = vec![anchored("start", "top")];
+        for _ in 0..12 {
+            items.push(StyledEventOrRun::Event(StyledEvent::ParagraphStart));
+            items.push(body_run("filler words to push the note onto a later page"));
+            items.push(StyledEventOrRun::Event(StyledEvent::ParagraphEnd));
+        }
+        items.push(anchored("the note", "n1"));
+        items.push(anchored(" ", "tail"));
+        let pages = engine.layout_items(items);
+        assert!(pages.len() > 1);
+        let anchors: Vec<Vec<&str>> = pages.iter().map(|page| page.anchors().collect()).collect();
+        assert_eq!(anchors[0], vec!["top"]);
+        assert_eq!(anchors.last(), Some(&vec!["n1", "tail"]));
+        assert!(anchors[1..pages.len() - 1].iter().all(Vec::is_empty));
+    }
+
     #[test]
     fn soft_hyphen_is_invisible_when_not_broken() {
         let engine = LayoutEngine::new(LayoutConfig {
@@ -852,6 +1478,269 @@ mod tests {
         );
     }
 
+    fn inline_svg_item(title: &str) -> StyledEventOrRun {
+        StyledEventOrRun::Image(StyledImage {
+            src: String::with_capacity(0),
+            alt: title.to_string(),
+            width_px: Some(200.0),
+            height_px: Some(100.0),
+            inline_svg: Some(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"200\" height=\"100\">\
+                 <rect width=\"100\" height=\"100\" fill=\"black\"/></svg>"
+                    .to_string(),
+            ),
+            svg_oversize: false,
+            caption: None,
+        })
+    }
+
+    fn svg_layout(svg_mode: SvgMode) -> Vec<DrawCommand> {
+        let engine = LayoutEngine::new(LayoutConfig {
+            object_layout: ObjectLayoutConfig {
+                svg_mode,
+                ..ObjectLayoutConfig::default()
+            },
+            ..LayoutConfig::default()
+        });
+        engine
+            .layout_items(vec![inline_svg_item("Chart")])
+            .into_iter()
+            .flat_map(|page| page.content_commands)
+            .collect()
+    }
+
+    #[test]
+    fn image_alt_and_caption_annotate_image_rect() {
+        let engine = LayoutEngine::new(LayoutConfig::default());
+        let image = StyledEventOrRun::Image(StyledImage {
+            src: "images/map.png".to_string(),
+            alt: " Route map ".to_string(),
+            width_px: Some(120.0),
+            height_px: Some(80.0),
+            inline_svg: None,
+            svg_oversize: false,
+            caption: Some("Figure 1. The route".to_string()),
+        });
+        let pages = engine.layout_items(vec![body_run("before"), image]);
+        let rect = pages[0]
+            .content_commands
+            .iter()
+            .find_map(|cmd| match cmd {
+                DrawCommand::Image(img) => Some(PageRect::new(img.x, img.y, img.width, img.height)),
+                _ => None,
+            })
+            .expect("image command");
+        assert_eq!(
+            pages[0].annotations,
+            vec![PageAnnotation::ImageDescription {
+                rect,
+                src: "images/map.png".to_string(),
+                alt: Some("Route map".to_string()),
+                caption: Some("Figure 1. The route".to_string()),
+            }]
+        );
+        assert_eq!(pages[0].metrics.word_count, 1);
+        assert_eq!(pages[0].metrics.image_count, 1);
+        let stats = ChapterStats::from_pages(&pages);
+        assert_eq!((stats.word_count, stats.image_count), (1, 1));
+        assert_eq!(
+            stats.reading_seconds(PageMetrics::DEFAULT_WORDS_PER_MINUTE),
+            1
+        );
+    }
+
+    #[test]
+    fn draft_intent_boxes_images_and_simplifies_chrome() {
+        let engine = LayoutEngine::new(LayoutConfig {
+            object_layout: ObjectLayoutConfig {
+                svg_mode: SvgMode::Rasterize,
+                ..ObjectLayoutConfig::default()
+            },
+            page_chrome: PageChromeConfig {
+                header_enabled: true,
+                footer_enabled: true,
+                progress_enabled: true,
+                ..PageChromeConfig::default()
+            },
+            render_intent: RenderIntent {
+                draft: true,
+                ..RenderIntent::default()
+            },
+            ..LayoutConfig::default()
+        });
+        let pages = engine.layout_items(vec![inline_svg_item("Chart")]);
+        assert!(!RenderIntent {
+            draft: true,
+            ..RenderIntent::default()
+        }
+        .antialias());
+        assert_eq!(
+            pages[0].content_commands,
+            vec![DrawCommand::Rect(RectCommand {
+                x: 140,
+                y: 48,
+                width: 200,
+                height: 100,
+                fill: false,
+                color: None,
+            })]
+        );
+        let chrome_kinds: Vec<PageChromeKind> = pages[0]
+            .chrome_commands
+            .iter()
+            .filter_map(|cmd| match cmd {
+                DrawCommand::PageChrome(c) => Some(c.kind),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(chrome_kinds, vec![PageChromeKind::Footer]);
+    }
+
+    #[test]
+    fn svg_ignore_mode_drops_svg_islands() {
+        assert!(svg_layout(SvgMode::Ignore).is_empty());
+    }
+
+    #[test]
+    fn svg_fallback_mode_emits_placeholder_and_title_text() {
+        let commands = svg_layout(SvgMode::RasterizeFallback);
+        assert!(matches!(
+            commands.first(),
+            Some(DrawCommand::Rect(RectCommand {
+                width: 200,
+                height: 100,
+                fill: false,
+                ..
+            }))
+        ));
+        assert!(commands
+            .iter()
+            .any(|cmd| matches!(cmd, DrawCommand::Text(t) if t.text == "Chart")));
+    }
+
+    #[cfg(feature = "svg-raster")]
+    #[test]
+    fn svg_rasterize_mode_emits_bitmap_at_layout_size() {
+        let commands = svg_layout(SvgMode::Rasterize);
+        let Some(DrawCommand::Image(image)) = commands.first() else {
+            panic!("expected image command, got {commands:?}");
+        };
+        let bitmap = image.bitmap.as_ref().expect("bitmap should be rasterized");
+        assert_eq!((bitmap.width, bitmap.height), (200, 100));
+        assert_eq!(bitmap.pixel(50, 50), Some(0));
+        assert_eq!(bitmap.pixel(150, 50), Some(255));
+    }
+
+    #[test]
+    fn oversized_svg_island_draws_placeholder_even_in_native_mode() {
+        let engine = LayoutEngine::new(LayoutConfig {
+            object_layout: ObjectLayoutConfig {
+                svg_mode: SvgMode::Native,
+                ..ObjectLayoutConfig::default()
+            },
+            ..LayoutConfig::default()
+        });
+        let StyledEventOrRun::Image(mut image) = inline_svg_item("Chart") else {
+            unreachable!("inline_svg_item builds images");
+        };
+        image.inline_svg = None;
+        image.svg_oversize = true;
+        let pages = engine.layout_items(vec![StyledEventOrRun::Image(image)]);
+        let commands = &pages[0].content_commands;
+        assert!(matches!(commands.first(), Some(DrawCommand::Rect(_))));
+        assert!(!commands
+            .iter()
+            .any(|cmd| matches!(cmd, DrawCommand::Image(_))));
+    }
+
+    #[test]
+    fn svg_rasterize_mode_falls_back_when_over_budget() {
+        let engine = LayoutEngine::new(LayoutConfig {
+            object_layout: ObjectLayoutConfig {
+                svg_mode: SvgMode::Rasterize,
+                svg_max_raster_pixels: 16,
+                ..ObjectLayoutConfig::default()
+            },
+            ..LayoutConfig::default()
+        });
+        let pages = engine.layout_items(vec![inline_svg_item("Chart")]);
+        assert!(matches!(
+            pages[0].content_commands.first(),
+            Some(DrawCommand::Rect(_))
+        ));
+    }
+
+    #[test]
+    fn run_color_is_mapped_through_render_intent_target() {
+        let colored = |target: ColorTarget| {
+            let mut item = body_run("Crimson");
+            if let StyledEventOrRun::Run(run) = &mut item {
+                run.style.color = Some(Color::rgb(200, 30, 60));
+            }
+            let engine = LayoutEngine::new(LayoutConfig {
+                render_intent: RenderIntent {
+                    color_target: target,
+                    ..RenderIntent::default()
+                },
+                ..LayoutConfig::default()
+            });
+            let pages = engine.layout_items(vec![item]);
+            match pages[0].content_commands.first() {
+                Some(DrawCommand::Text(text)) => text.style.color,
+                other => panic!("expected text command, got {other:?}"),
+            }
+        };
+
+        assert_eq!(colored(ColorTarget::Rgb888), Some(Color::rgb(200, 30, 60)));
+        assert_eq!(colored(ColorTarget::Rgb565), Some(Color::rgb(197, 28, 57)));
+        assert_eq!(
+            colored(ColorTarget::Grayscale),
+            Some(Color::rgb(84, 84, 84))
+        );
+        assert_eq!(engine_default_text_color(), None);
+    }
+
+    fn engine_default_text_color() -> Option<Color> {
+        let pages = LayoutEngine::new(LayoutConfig::default()).layout_items(vec![body_run("x")]);
+        match pages[0].content_commands.first() {
+            Some(DrawCommand::Text(text)) => text.style.color,
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn justified_lines_emit_glyph_advances_summing_to_line_width() {
+        let mut cfg = LayoutConfig::default();
+        cfg.typography.emit_glyph_positions = true;
+        let engine = LayoutEngine::new(cfg);
+        let pages = engine.layout_items(vec![
+            StyledEventOrRun::Event(StyledEvent::ParagraphStart),
+            body_run("one two three four five six seven eight nine ten eleven twelve"),
+            body_run("one two three four five six seven eight nine ten eleven twelve"),
+            StyledEventOrRun::Event(StyledEvent::ParagraphEnd),
+        ]);
+
+        let texts: Vec<&TextCommand> = pages[0]
+            .content_commands
+            .iter()
+            .filter_map(|cmd| match cmd {
+                DrawCommand::Text(text) => Some(text),
+                _ => None,
+            })
+            .collect();
+        let justified = texts[0];
+        let JustifyMode::InterWord { extra_px_total } = justified.style.justify_mode else {
+            panic!("first line should be justified");
+        };
+        let glyphs = justified.glyphs.as_ref().expect("glyph positions");
+        assert_eq!(glyphs.len(), justified.text.chars().count());
+        let natural = measure_text(&justified.text, &justified.style);
+        let total: i32 = glyphs.iter().map(|g| g.x_advance).sum();
+        assert_eq!(total, (natural + extra_px_total as f32).round() as i32);
+        // Last line is not justified, so it keeps backend measurement.
+        assert!(texts.last().is_some_and(|t| t.glyphs.is_none()));
+    }
+
     #[test]
     fn layout_invariants_are_deterministic_and_non_overlapping() {
         let cfg = LayoutConfig {
@@ -865,7 +1754,7 @@ mod tests {
             ..LayoutConfig::default()
         };
         let engine = LayoutEngine::new(cfg);
-        let mut items = Vec::new();
+        let mut items = Vec::with_capacity(8);
         for _ in 0..30 {
             items.push(StyledEventOrRun::Event(StyledEvent::ParagraphStart));
             items.push(body_run(
@@ -918,7 +1807,7 @@ mod tests {
         for item in items {
             session.push_item(item);
         }
-        let mut streamed = Vec::new();
+        let mut streamed = Vec::with_capacity(8);
         session.finish(&mut |page| streamed.push(page));
         assert_eq!(batch, streamed);
     }
@@ -932,7 +1821,7 @@ mod tests {
             ..LayoutConfig::default()
         };
         let engine = LayoutEngine::new(cfg);
-        let mut items = Vec::new();
+        let mut items = Vec::with_capacity(8);
         for _ in 0..40 {
             items.push(StyledEventOrRun::Event(StyledEvent::ParagraphStart));
             items.push(body_run("one two three four five six seven eight nine ten"));
@@ -943,8 +1832,8 @@ mod tests {
         assert!(batch.len() > 1);
 
         let mut session = engine.start_session();
-        let mut streamed = Vec::new();
-        let mut during_push = Vec::new();
+        let mut streamed = Vec::with_capacity(8);
+        let mut during_push = Vec::with_capacity(8);
         for item in items {
             session.push_item_with_pages(item, &mut |page| {
                 during_push.push(page.clone());
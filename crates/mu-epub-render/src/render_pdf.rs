@@ -0,0 +1,976 @@
+//! PDF export of laid-out pages behind the `pdf` feature.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::io::{Read, Seek};
+
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use mu_epub::{Color, EmbeddedFontStyle, EpubBook, EpubError};
+
+use crate::render_ir::{
+    DrawCommand, GlyphPosition, ImageCommand, PageChromeCommand, PageChromeConfig, PageChromeKind,
+    PageRect, RenderPage, TextCommand,
+};
+use crate::render_layout::glyph_positions;
+use crate::render_pdf_font::{PdfFontError, TrueTypeFace};
+
+/// Font size assumed for header and footer text.
+const CHROME_TEXT_PX: f32 = 13.0;
+
+/// zlib level for content streams, images, and font programs.
+const DEFLATE_LEVEL: u8 = 6;
+
+/// Entries per `beginbfchar` block (the CMap format caps it at 100).
+const BFCHAR_BLOCK: usize = 100;
+
+/// Base-14 fallback faces, indexed by `family * 4 + italic * 2 + bold`.
+const STANDARD_FONTS: [&str; 12] = [
+    "Times-Roman",
+    "Times-Bold",
+    "Times-Italic",
+    "Times-BoldItalic",
+    "Helvetica",
+    "Helvetica-Bold",
+    "Helvetica-Oblique",
+    "Helvetica-BoldOblique",
+    "Courier",
+    "Courier-Bold",
+    "Courier-Oblique",
+    "Courier-BoldOblique",
+];
+
+/// PDF page geometry and chrome settings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PdfConfig {
+    /// Page width in device pixels.
+    pub width_px: u32,
+    /// Page height in device pixels.
+    pub height_px: u32,
+    /// Device pixels per inch; one pixel maps to `72 / dpi` points.
+    pub dpi: f32,
+    /// Geometry for `PageChrome` markers; disabled kinds are skipped.
+    pub page_chrome: PageChromeConfig,
+    /// Document title stored in the PDF info dictionary.
+    pub title: Option<String>,
+}
+
+impl PdfConfig {
+    /// Pages of `width_px`x`height_px` at 96 dpi, matching
+    /// `RenderEngineOptions::for_display` defaults.
+    pub fn new(width_px: u32, height_px: u32) -> Self {
+        Self {
+            width_px,
+            height_px,
+            dpi: 96.0,
+            page_chrome: PageChromeConfig::geometry_defaults(),
+            title: None,
+        }
+    }
+
+    fn points_per_px(&self) -> f32 {
+        if self.dpi > 0.0 {
+            72.0 / self.dpi
+        } else {
+            0.75
+        }
+    }
+}
+
+/// TrueType face offered to the exporter for embedding.
+#[derive(Clone, Debug)]
+pub struct PdfFont {
+    family: String,
+    weight: u16,
+    italic: bool,
+    face: TrueTypeFace,
+}
+
+impl PdfFont {
+    /// Parse a TrueType font program for `family` at `weight`/`italic`.
+    ///
+    /// Only fonts with `glyf` outlines can be subset; CFF-flavoured
+    /// OpenType, WOFF, and obfuscated resources are rejected.
+    pub fn from_truetype(
+        family: impl Into<String>,
+        weight: u16,
+        italic: bool,
+        data: Vec<u8>,
+    ) -> Result<Self, PdfFontError> {
+        Ok(Self {
+            family: family.into(),
+            weight,
+            italic,
+            face: TrueTypeFace::parse(data)?,
+        })
+    }
+
+    /// CSS family this face serves.
+    pub fn family(&self) -> &str {
+        &self.family
+    }
+}
+
+/// Incremental PDF writer for a sequence of `RenderPage`s.
+///
+/// Each page keeps its device geometry: text is placed glyph by glyph at
+/// the advances layout decided, so line and page breaks match the reader
+/// exactly. Runs whose family matches a registered [`PdfFont`] embed a
+/// subset of that face holding only the glyphs used; everything else falls
+/// back to the non-embedded base-14 Times, Helvetica, or Courier faces.
+/// Rules and rectangles become vector paths and image bitmaps become
+/// grayscale image objects at their decoded resolution (see
+/// `RenderEngine::prepare_chapter_export` for sharper images).
+#[derive(Debug)]
+pub struct PdfWriter {
+    config: PdfConfig,
+    fonts: Vec<EmbeddedFont>,
+    standard_used: [bool; STANDARD_FONTS.len()],
+    images: Vec<PdfImage>,
+    pages: Vec<PdfPage>,
+}
+
+#[derive(Debug)]
+struct EmbeddedFont {
+    font: PdfFont,
+    /// Used glyphs and the first character each one was drawn for.
+    used: BTreeMap<u16, char>,
+}
+
+#[derive(Debug)]
+struct PdfImage {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct PdfPage {
+    content: Vec<u8>,
+    images: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FontRef {
+    Embedded(usize),
+    Standard(usize),
+}
+
+impl PdfWriter {
+    /// Empty document using `config` for every page.
+    pub fn new(config: PdfConfig) -> Self {
+        Self {
+            config,
+            fonts: Vec::with_capacity(0),
+            standard_used: [false; STANDARD_FONTS.len()],
+            images: Vec::with_capacity(0),
+            pages: Vec::with_capacity(0),
+        }
+    }
+
+    /// Register a face for embedding in pages added afterwards.
+    pub fn add_font(&mut self, font: PdfFont) {
+        self.fonts.push(EmbeddedFont {
+            font,
+            used: BTreeMap::new(),
+        });
+    }
+
+    /// Register the book's `@font-face` resources that are TrueType fonts.
+    ///
+    /// Returns how many faces were registered; unreadable or unsupported
+    /// resources are skipped.
+    pub fn add_book_fonts<R: Read + Seek>(
+        &mut self,
+        book: &mut EpubBook<R>,
+    ) -> Result<usize, EpubError> {
+        let mut added = 0;
+        for face in book.embedded_fonts()? {
+            let Ok(data) = book.read_resource(&face.href) else {
+                continue;
+            };
+            let italic = face.style != EmbeddedFontStyle::Normal;
+            if let Ok(font) = PdfFont::from_truetype(face.family, face.weight, italic, data) {
+                self.add_font(font);
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    /// Pages added so far.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Append `page`, composited like `RenderPage::composite`.
+    pub fn add_page(&mut self, page: &RenderPage) {
+        let mut out = PageWriter {
+            content: String::with_capacity(4096),
+            images: Vec::with_capacity(0),
+            height: self.config.height_px as f32,
+        };
+        let k = self.config.points_per_px();
+        let _ = writeln!(out.content, "{} 0 0 {} 0 0 cm", k, k);
+        let full = PageRect::new(0, 0, self.config.width_px, self.config.height_px);
+        for item in page.composite() {
+            let clip = match item.clip {
+                Some(clip) => match clip.clip(&full) {
+                    Some(clip) => Some(clip),
+                    None => continue,
+                },
+                None => None,
+            };
+            if let Some(clip) = clip {
+                out.content.push_str("q ");
+                out.rect_path(clip);
+                out.content.push_str("W n\n");
+            }
+            self.draw(&mut out, item.command);
+            if clip.is_some() {
+                out.content.push_str("Q\n");
+            }
+        }
+        self.pages.push(PdfPage {
+            content: compress_to_vec_zlib(out.content.as_bytes(), DEFLATE_LEVEL),
+            images: out.images,
+        });
+    }
+
+    fn draw(&mut self, out: &mut PageWriter, cmd: &DrawCommand) {
+        match cmd {
+            DrawCommand::Text(text) => self.draw_text(out, text),
+            DrawCommand::Rule(rule) => {
+                let (width, height) = if rule.horizontal {
+                    (rule.length, rule.thickness)
+                } else {
+                    (rule.thickness, rule.length)
+                };
+                out.fill(PageRect::new(rule.x, rule.y, width, height), None);
+            }
+            DrawCommand::Rect(rect) => {
+                let area = PageRect::new(rect.x, rect.y, rect.width, rect.height);
+                if rect.fill {
+                    out.fill(area, rect.color);
+                } else {
+                    out.stroke(area, rect.color);
+                }
+            }
+            DrawCommand::Image(image) => self.draw_image(out, image),
+            DrawCommand::PageChrome(chrome) => self.draw_chrome(out, chrome),
+            // Resolved by `RenderPage::composite`.
+            DrawCommand::Layered(_) | DrawCommand::PushClip(_) | DrawCommand::PopClip => {}
+        }
+    }
+
+    fn draw_image(&mut self, out: &mut PageWriter, image: &ImageCommand) {
+        let area = PageRect::new(image.x, image.y, image.width, image.height);
+        let Some(bitmap) = image
+            .bitmap
+            .as_ref()
+            .filter(|b| b.width > 0 && b.height > 0 && !area.is_empty())
+        else {
+            out.stroke(area, None);
+            return;
+        };
+        let index = self.images.len();
+        self.images.push(PdfImage {
+            width: bitmap.width,
+            height: bitmap.height,
+            data: compress_to_vec_zlib(&bitmap.pixels, DEFLATE_LEVEL),
+        });
+        out.images.push(index);
+        let _ = writeln!(
+            out.content,
+            "q {} 0 0 {} {} {} cm /Im{} Do Q",
+            area.width,
+            area.height,
+            area.x,
+            num(out.height - area.bottom() as f32),
+            index
+        );
+    }
+
+    fn draw_text(&mut self, out: &mut PageWriter, text: &TextCommand) {
+        let style = &text.style;
+        let glyphs = text
+            .glyphs
+            .clone()
+            .unwrap_or_else(|| glyph_positions(&text.text, style));
+        let baseline = text.baseline_y as f32 + style.metrics.ascent_px.round();
+        let embedded = self.match_font(&style.family, style.weight, style.italic);
+        let standard = standard_font(&style.family, style.weight, style.italic);
+        self.draw_run(
+            out,
+            &text.text,
+            &glyphs,
+            text.x as f32,
+            baseline,
+            style.size_px,
+            style.color,
+            embedded,
+            standard,
+        );
+    }
+
+    fn draw_chrome(&mut self, out: &mut PageWriter, chrome: &PageChromeCommand) {
+        let cfg = self.config.page_chrome;
+        let width = self.config.width_px as i32;
+        let height = self.config.height_px as i32;
+        let footer_y = height - cfg.footer_baseline_from_bottom;
+        let (enabled, x, y) = match chrome.kind {
+            PageChromeKind::Header => (cfg.header_enabled, cfg.header_x, cfg.header_baseline_y),
+            PageChromeKind::Footer => (cfg.footer_enabled, cfg.footer_x, footer_y),
+            PageChromeKind::BookProgress => {
+                (cfg.book_progress_enabled, width - cfg.footer_x, footer_y)
+            }
+            PageChromeKind::Progress => {
+                if !cfg.progress_enabled {
+                    return;
+                }
+                let current = chrome.current.unwrap_or(0);
+                let total = chrome.total.unwrap_or(1).max(1);
+                let bar_w = (width - cfg.progress_x_inset * 2).max(1) as u32;
+                let bar = PageRect::new(
+                    cfg.progress_x_inset,
+                    height - cfg.progress_y_from_bottom,
+                    bar_w,
+                    cfg.progress_height.max(1),
+                );
+                let filled = (bar_w as usize * current.min(total) / total) as u32;
+                let stroke = cfg.progress_stroke_width.max(1);
+                let _ = write!(out.content, "0 0 0 RG {} w ", stroke);
+                out.outline(bar, stroke as f32);
+                out.fill(PageRect::new(bar.x, bar.y, filled, bar.height), None);
+                return;
+            }
+        };
+        let Some(text) = chrome.text.as_deref().filter(|_| enabled) else {
+            return;
+        };
+        let advance = (CHROME_TEXT_PX * 0.6).round() as i32;
+        let glyphs = vec![
+            GlyphPosition {
+                x_advance: advance,
+                x_offset: 0,
+            };
+            text.chars().count()
+        ];
+        // Book progress is right-aligned on the footer line.
+        let x = if chrome.kind == PageChromeKind::BookProgress {
+            x - advance * glyphs.len() as i32
+        } else {
+            x
+        };
+        let standard = standard_font("sans-serif", 400, false);
+        self.draw_run(
+            out,
+            text,
+            &glyphs,
+            x as f32,
+            y as f32,
+            CHROME_TEXT_PX,
+            None,
+            None,
+            standard,
+        );
+    }
+
+    /// Place each glyph of `text` at its laid-out pen position.
+    ///
+    /// `Td` offsets are relative to the previous glyph origin, so the
+    /// font's own advance widths never affect placement.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_run(
+        &mut self,
+        out: &mut PageWriter,
+        text: &str,
+        glyphs: &[GlyphPosition],
+        x: f32,
+        baseline: f32,
+        size: f32,
+        color: Option<Color>,
+        embedded: Option<usize>,
+        standard: usize,
+    ) {
+        out.content.push_str("BT ");
+        out.fill_color(color);
+        let y = out.height - baseline;
+        let mut font = None;
+        let mut origin = (0.0f32, 0.0f32);
+        let mut pen = x;
+        for (ch, glyph) in text.chars().zip(glyphs) {
+            if !ch.is_whitespace() && !ch.is_control() {
+                let code = embedded.and_then(|index| {
+                    let entry = self.fonts.get_mut(index)?;
+                    let gid = entry.font.face.glyph_id(ch)?;
+                    entry.used.entry(gid).or_insert(ch);
+                    Some((FontRef::Embedded(index), format!("{:04X}", gid)))
+                });
+                let (next, code) = code.unwrap_or_else(|| {
+                    self.standard_used[standard] = true;
+                    (FontRef::Standard(standard), format!("{:02X}", win_ansi(ch)))
+                });
+                if font != Some(next) {
+                    let _ = write!(out.content, "/{} {} Tf ", font_name(next), num(size));
+                    font = Some(next);
+                }
+                let gx = pen + glyph.x_offset as f32;
+                let _ = write!(
+                    out.content,
+                    "{} {} Td <{}> Tj ",
+                    num(gx - origin.0),
+                    num(y - origin.1),
+                    code
+                );
+                origin = (gx, y);
+            }
+            pen += glyph.x_advance as f32;
+        }
+        out.content.push_str("ET\n");
+    }
+
+    /// Registered face for `family`, preferring the closest style then weight.
+    fn match_font(&self, family: &str, weight: u16, italic: bool) -> Option<usize> {
+        self.fonts
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.font.family.eq_ignore_ascii_case(family.trim()))
+            .min_by_key(|(_, entry)| {
+                (
+                    entry.font.italic != italic,
+                    entry.font.weight.abs_diff(weight),
+                )
+            })
+            .map(|(index, _)| index)
+    }
+
+    /// Serialize the document.
+    pub fn finish(self) -> Vec<u8> {
+        let mut doc = PdfObjects::default();
+        let catalog = doc.reserve();
+        let pages_id = doc.reserve();
+
+        let mut font_entries = String::with_capacity(256);
+        for (index, entry) in self.fonts.iter().enumerate() {
+            if entry.used.is_empty() {
+                continue;
+            }
+            let id = embed_font(&mut doc, index, entry);
+            let _ = write!(
+                font_entries,
+                "/{} {} 0 R ",
+                font_name(FontRef::Embedded(index)),
+                id
+            );
+        }
+        for (index, name) in STANDARD_FONTS.iter().enumerate() {
+            if !self.standard_used[index] {
+                continue;
+            }
+            let id = doc.add(format!(
+                "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                name
+            ));
+            let _ = write!(
+                font_entries,
+                "/{} {} 0 R ",
+                font_name(FontRef::Standard(index)),
+                id
+            );
+        }
+        let fonts_id = doc.add(format!("<< {}>>", font_entries));
+
+        let image_ids: Vec<usize> = self
+            .images
+            .iter()
+            .map(|image| {
+                doc.add_stream(
+                    &format!(
+                        "/Type /XObject /Subtype /Image /Width {} /Height {} \
+                         /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode",
+                        image.width, image.height
+                    ),
+                    &image.data,
+                )
+            })
+            .collect();
+
+        let k = self.config.points_per_px();
+        let media = format!(
+            "[0 0 {} {}]",
+            num(self.config.width_px as f32 * k),
+            num(self.config.height_px as f32 * k)
+        );
+        let mut kids = String::with_capacity(self.pages.len() * 8);
+        for page in &self.pages {
+            let content = doc.add_stream("/Filter /FlateDecode", &page.content);
+            let mut xobjects = String::with_capacity(page.images.len() * 12);
+            for &index in &page.images {
+                let _ = write!(xobjects, "/Im{} {} 0 R ", index, image_ids[index]);
+            }
+            let id = doc.add(format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox {} /Contents {} 0 R \
+                 /Resources << /Font {} 0 R /XObject << {}>> >> >>",
+                pages_id, media, content, fonts_id, xobjects
+            ));
+            let _ = write!(kids, "{} 0 R ", id);
+        }
+        doc.set(
+            pages_id,
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids,
+                self.pages.len()
+            ),
+        );
+        doc.set(
+            catalog,
+            format!("<< /Type /Catalog /Pages {} 0 R >>", pages_id),
+        );
+        let mut info = String::from("<< /Producer (mu-epub)");
+        if let Some(title) = &self.config.title {
+            let _ = write!(info, " /Title {}", text_string(title));
+        }
+        info.push_str(" >>");
+        let info_id = doc.add(info);
+        doc.serialize(catalog, info_id)
+    }
+}
+
+/// Write the Type0/CIDFontType2 object tree for an embedded subset.
+fn embed_font(doc: &mut PdfObjects, index: usize, entry: &EmbeddedFont) -> usize {
+    let face = &entry.font.face;
+    let used: BTreeSet<u16> = entry.used.keys().copied().collect();
+    let program = face.subset(&used);
+    let file = doc.add_stream(
+        &format!("/Length1 {} /Filter /FlateDecode", program.len()),
+        &compress_to_vec_zlib(&program, DEFLATE_LEVEL),
+    );
+
+    let base_font = format!(
+        "{}+{}",
+        subset_tag(index, &used),
+        postscript_name(&entry.font)
+    );
+    let bbox = face.bbox.map(|v| face.to_pdf_units(v as i32));
+    let flags = 32 | if entry.font.italic { 64 } else { 0 };
+    let descriptor = doc.add(format!(
+        "<< /Type /FontDescriptor /FontName /{} /Flags {} /FontBBox [{} {} {} {}] \
+         /ItalicAngle {} /Ascent {} /Descent {} /CapHeight {} /StemV {} /FontFile2 {} 0 R >>",
+        base_font,
+        flags,
+        bbox[0],
+        bbox[1],
+        bbox[2],
+        bbox[3],
+        if entry.font.italic { -12 } else { 0 },
+        face.to_pdf_units(face.ascent as i32),
+        face.to_pdf_units(face.descent as i32),
+        face.to_pdf_units(face.ascent as i32),
+        if entry.font.weight >= 600 { 140 } else { 80 },
+        file
+    ));
+
+    let mut widths = String::with_capacity(used.len() * 10);
+    for &gid in &used {
+        let width = face.to_pdf_units(face.advance(gid) as i32);
+        let _ = write!(widths, "{} [{}] ", gid, width);
+    }
+    let cid_font = doc.add(format!(
+        "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /{} \
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> \
+         /FontDescriptor {} 0 R /W [{}] /CIDToGIDMap /Identity >>",
+        base_font, descriptor, widths
+    ));
+
+    let to_unicode = doc.add_stream("/Filter /FlateDecode", &{
+        let cmap = to_unicode_cmap(&entry.used);
+        compress_to_vec_zlib(cmap.as_bytes(), DEFLATE_LEVEL)
+    });
+    doc.add(format!(
+        "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H \
+         /DescendantFonts [{} 0 R] /ToUnicode {} 0 R >>",
+        base_font, cid_font, to_unicode
+    ))
+}
+
+/// CMap mapping glyph ids back to text so copy and search work.
+fn to_unicode_cmap(used: &BTreeMap<u16, char>) -> String {
+    let mut cmap = String::with_capacity(256 + used.len() * 24);
+    cmap.push_str(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let entries: Vec<(&u16, &char)> = used.iter().collect();
+    for block in entries.chunks(BFCHAR_BLOCK) {
+        let _ = writeln!(cmap, "{} beginbfchar", block.len());
+        for (gid, ch) in block {
+            let mut units = [0u16; 2];
+            let hex: String = ch
+                .encode_utf16(&mut units)
+                .iter()
+                .map(|unit| format!("{:04X}", unit))
+                .collect();
+            let _ = writeln!(cmap, "<{:04X}> <{}>", gid, hex);
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    cmap
+}
+
+/// Six-letter subset prefix derived from the glyph set.
+fn subset_tag(index: usize, used: &BTreeSet<u16>) -> String {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64 ^ index as u64;
+    for &gid in used {
+        hash = (hash ^ gid as u64).wrapping_mul(0x100_0000_01b3);
+    }
+    (0..6)
+        .map(|i| (b'A' + ((hash >> (i * 8)) % 26) as u8) as char)
+        .collect()
+}
+
+fn postscript_name(font: &PdfFont) -> String {
+    let mut name: String = font
+        .family
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect();
+    if name.is_empty() {
+        name.push_str("Embedded");
+    }
+    match (font.weight >= 600, font.italic) {
+        (true, true) => name.push_str("-BoldItalic"),
+        (true, false) => name.push_str("-Bold"),
+        (false, true) => name.push_str("-Italic"),
+        (false, false) => {}
+    }
+    name
+}
+
+fn font_name(font: FontRef) -> String {
+    match font {
+        FontRef::Embedded(index) => format!("F{}", index),
+        FontRef::Standard(index) => format!("S{}", index),
+    }
+}
+
+/// Base-14 face approximating a CSS family.
+fn standard_font(family: &str, weight: u16, italic: bool) -> usize {
+    let family = family.to_ascii_lowercase();
+    let base = if ["mono", "courier", "fixed", "code"]
+        .iter()
+        .any(|key| family.contains(key))
+    {
+        2
+    } else if ["sans", "helvetica", "arial"]
+        .iter()
+        .any(|key| family.contains(key))
+    {
+        1
+    } else {
+        0
+    };
+    base * 4 + usize::from(italic) * 2 + usize::from(weight >= 600)
+}
+
+/// WinAnsiEncoding byte for `ch`, or `?` when it has none.
+fn win_ansi(ch: char) -> u8 {
+    match ch as u32 {
+        code @ (0x20..=0x7E | 0xA0..=0xFF) => code as u8,
+        _ => match ch {
+            '€' => 0x80,
+            '‚' => 0x82,
+            'ƒ' => 0x83,
+            '„' => 0x84,
+            '…' => 0x85,
+            '†' => 0x86,
+            '‡' => 0x87,
+            'ˆ' => 0x88,
+            '‰' => 0x89,
+            'Š' => 0x8A,
+            '‹' => 0x8B,
+            'Œ' => 0x8C,
+            'Ž' => 0x8E,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '˜' => 0x98,
+            '™' => 0x99,
+            'š' => 0x9A,
+            '›' => 0x9B,
+            'œ' => 0x9C,
+            'ž' => 0x9E,
+            'Ÿ' => 0x9F,
+            _ => b'?',
+        },
+    }
+}
+
+/// UTF-16BE hex string with byte-order mark.
+fn text_string(text: &str) -> String {
+    let mut out = String::with_capacity(6 + text.len() * 4);
+    out.push_str("<FEFF");
+    for unit in text.encode_utf16() {
+        let _ = write!(out, "{:04X}", unit);
+    }
+    out.push('>');
+    out
+}
+
+/// Compact decimal with at most two fractional digits.
+fn num(value: f32) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    if rounded == rounded.trunc() {
+        format!("{}", rounded as i64)
+    } else {
+        let mut text = format!("{:.2}", rounded);
+        while text.ends_with('0') {
+            text.pop();
+        }
+        text
+    }
+}
+
+/// Content stream under construction; coordinates are device pixels with
+/// the y axis flipped against `height`.
+struct PageWriter {
+    content: String,
+    images: Vec<usize>,
+    height: f32,
+}
+
+impl PageWriter {
+    fn rect_path(&mut self, rect: PageRect) {
+        let _ = write!(
+            self.content,
+            "{} {} {} {} re ",
+            rect.x,
+            num(self.height - rect.bottom() as f32),
+            rect.width,
+            rect.height
+        );
+    }
+
+    fn fill_color(&mut self, color: Option<Color>) {
+        let color = color.unwrap_or(Color::BLACK);
+        let _ = write!(
+            self.content,
+            "{} {} {} rg ",
+            num(color.r as f32 / 255.0),
+            num(color.g as f32 / 255.0),
+            num(color.b as f32 / 255.0)
+        );
+    }
+
+    fn fill(&mut self, rect: PageRect, color: Option<Color>) {
+        if rect.is_empty() {
+            return;
+        }
+        self.fill_color(color);
+        self.rect_path(rect);
+        self.content.push_str("f\n");
+    }
+
+    /// One-pixel outline drawn inside `rect`, like the raster backend.
+    fn stroke(&mut self, rect: PageRect, color: Option<Color>) {
+        if rect.is_empty() {
+            return;
+        }
+        let color = color.unwrap_or(Color::BLACK);
+        let _ = write!(
+            self.content,
+            "{} {} {} RG 1 w ",
+            num(color.r as f32 / 255.0),
+            num(color.g as f32 / 255.0),
+            num(color.b as f32 / 255.0)
+        );
+        self.outline(rect, 1.0);
+    }
+
+    /// Stroke `rect` inset by half of `width` so the ink stays inside.
+    fn outline(&mut self, rect: PageRect, width: f32) {
+        let half = width / 2.0;
+        let _ = writeln!(
+            self.content,
+            "{} {} {} {} re S",
+            num(rect.x as f32 + half),
+            num(self.height - rect.bottom() as f32 + half),
+            num((rect.width as f32 - width).max(0.0)),
+            num((rect.height as f32 - width).max(0.0))
+        );
+    }
+}
+
+/// Indirect objects numbered from 1 in insertion order.
+#[derive(Default)]
+struct PdfObjects {
+    bodies: Vec<Vec<u8>>,
+}
+
+impl PdfObjects {
+    fn reserve(&mut self) -> usize {
+        self.bodies.push(Vec::with_capacity(0));
+        self.bodies.len()
+    }
+
+    fn set(&mut self, id: usize, body: String) {
+        self.bodies[id - 1] = body.into_bytes();
+    }
+
+    fn add(&mut self, body: String) -> usize {
+        self.bodies.push(body.into_bytes());
+        self.bodies.len()
+    }
+
+    fn add_stream(&mut self, dict: &str, data: &[u8]) -> usize {
+        let mut body = format!("<< /Length {} {} >>\nstream\n", data.len(), dict).into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        self.bodies.push(body);
+        self.bodies.len()
+    }
+
+    fn serialize(self, root: usize, info: usize) -> Vec<u8> {
+        let total: usize = self.bodies.iter().map(|body| body.len() + 32).sum();
+        let mut out = Vec::with_capacity(total + 64 + self.bodies.len() * 20);
+        out.extend_from_slice(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n");
+        let mut offsets = Vec::with_capacity(self.bodies.len());
+        for (index, body) in self.bodies.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            out.extend_from_slice(body);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = out.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(table, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.bodies.len() + 1,
+            root,
+            info,
+            xref
+        );
+        out.extend_from_slice(table.as_bytes());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_ir::{
+        GrayBitmap, JustifyMode, ResolvedTextStyle, RuleCommand, TextRenderHint, VerticalMetrics,
+    };
+    use crate::render_pdf_font::test_font;
+    use miniz_oxide::inflate::decompress_to_vec_zlib;
+    use mu_epub::BlockRole;
+
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+    }
+
+    #[test]
+    fn pages_embed_font_subsets_vector_rules_and_images() {
+        let mut page = RenderPage::new(1);
+        page.push_content_command(DrawCommand::Text(TextCommand {
+            x: 10,
+            baseline_y: 20,
+            text: "AB C".to_string(),
+            font_id: None,
+            style: ResolvedTextStyle {
+                font_id: None,
+                family: "Test".to_string(),
+                weight: 400,
+                italic: false,
+                size_px: 20.0,
+                line_height: 1.2,
+                letter_spacing: 0.0,
+                role: BlockRole::Body,
+                justify_mode: JustifyMode::None,
+                color: None,
+                metrics: VerticalMetrics::estimate(20.0),
+            },
+            glyphs: Some(vec![
+                GlyphPosition {
+                    x_advance: 12,
+                    x_offset: 0,
+                };
+                4
+            ]),
+            hint: TextRenderHint::Aliased,
+        }));
+        page.push_content_command(DrawCommand::Rule(RuleCommand {
+            x: 10,
+            y: 50,
+            length: 100,
+            thickness: 2,
+            horizontal: true,
+        }));
+        page.push_content_command(DrawCommand::Image(ImageCommand {
+            x: 10,
+            y: 60,
+            width: 40,
+            height: 20,
+            src: "cover.png".to_string(),
+            alt: String::with_capacity(0),
+            bitmap: Some(GrayBitmap {
+                width: 2,
+                height: 1,
+                pixels: vec![0, 255],
+            }),
+        }));
+        page.sync_commands();
+
+        let mut writer = PdfWriter::new(PdfConfig::new(200, 100));
+        let font = PdfFont::from_truetype("test", 400, false, test_font()).expect("font parses");
+        writer.add_font(font);
+        writer.add_page(&page);
+        writer.add_page(&RenderPage::new(2));
+        assert_eq!(writer.page_count(), 2);
+
+        let content = decompress_to_vec_zlib(&writer.pages[0].content).expect("inflate");
+        let content = String::from_utf8(content).expect("ascii content");
+        // Baseline 20 + ascent 16 → y = 100 - 36; glyphs at their advances.
+        assert!(content.contains("/F0 20 Tf 10 64 Td <0001> Tj 12 0 Td <0002> Tj"));
+        // `C` is missing from the face and falls back to Times.
+        assert!(content.contains("/S0 20 Tf 24 0 Td <43> Tj"));
+        assert!(content.contains("10 48 100 2 re f"));
+        assert!(content.contains("q 40 0 0 20 10 20 cm /Im0 Do Q"));
+
+        let pdf = writer.finish();
+        assert!(pdf.starts_with(b"%PDF-1.4"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        for marker in [
+            &b"/Count 2"[..],
+            b"/MediaBox [0 0 150 75]",
+            b"/Subtype /CIDFontType2",
+            b"/FontFile2",
+            b"+test /Encoding /Identity-H",
+            b"/BaseFont /Times-Roman",
+            b"/Width 2 /Height 1 /ColorSpace /DeviceGray",
+            b"/W [1 [600] 2 [700] ]",
+        ] {
+            assert!(find(&pdf, marker).is_some(), "missing {:?}", marker);
+        }
+        let startxref = find(&pdf, b"startxref\n").expect("startxref") + 10;
+        let offset: usize = std::str::from_utf8(&pdf[startxref..pdf.len() - 7])
+            .expect("ascii offset")
+            .trim()
+            .parse()
+            .expect("numeric offset");
+        assert!(pdf[offset..].starts_with(b"xref\n0 "));
+    }
+}
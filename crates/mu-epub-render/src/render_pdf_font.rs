@@ -0,0 +1,450 @@
+//! TrueType parsing and glyph subsetting for PDF font embedding.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Tables copied into a subset font, sorted by tag.
+const SUBSET_TABLES: [&[u8; 4]; 9] = [
+    b"cvt ", b"fpgm", b"glyf", b"head", b"hhea", b"hmtx", b"loca", b"maxp", b"prep",
+];
+
+/// Why a font could not be used for PDF embedding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PdfFontError {
+    /// Not an sfnt with TrueType outlines (CFF OpenType, WOFF, obfuscated).
+    NotTrueType,
+    /// A required table is missing or truncated.
+    Malformed(&'static str),
+}
+
+impl fmt::Display for PdfFontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotTrueType => write!(f, "font is not a TrueType (glyf) font"),
+            Self::Malformed(table) => write!(f, "font table `{}` is malformed", table),
+        }
+    }
+}
+
+impl std::error::Error for PdfFontError {}
+
+/// Parsed TrueType font program.
+#[derive(Clone, Debug)]
+pub(crate) struct TrueTypeFace {
+    data: Vec<u8>,
+    tables: BTreeMap<[u8; 4], (usize, usize)>,
+    pub(crate) units_per_em: u16,
+    pub(crate) bbox: [i16; 4],
+    pub(crate) ascent: i16,
+    pub(crate) descent: i16,
+    num_glyphs: u16,
+    num_h_metrics: u16,
+    long_loca: bool,
+    cmap: Cmap,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Cmap {
+    /// Offset of a format 4 subtable.
+    Segmented(usize),
+    /// Offset of a format 12 subtable.
+    Groups(usize),
+}
+
+impl TrueTypeFace {
+    pub(crate) fn parse(data: Vec<u8>) -> Result<Self, PdfFontError> {
+        let version = data.get(..4).ok_or(PdfFontError::NotTrueType)?;
+        if version != [0, 1, 0, 0] && version != b"true" {
+            return Err(PdfFontError::NotTrueType);
+        }
+        let count = read_u16(&data, 4).ok_or(PdfFontError::Malformed("sfnt"))?;
+        let mut tables = BTreeMap::new();
+        for i in 0..count as usize {
+            let record = 12 + i * 16;
+            let tag = data
+                .get(record..record + 4)
+                .and_then(|tag| <[u8; 4]>::try_from(tag).ok())
+                .ok_or(PdfFontError::Malformed("sfnt"))?;
+            let offset = read_u32(&data, record + 8).ok_or(PdfFontError::Malformed("sfnt"))?;
+            let len = read_u32(&data, record + 12).ok_or(PdfFontError::Malformed("sfnt"))?;
+            let (offset, len) = (offset as usize, len as usize);
+            if offset.checked_add(len).is_none_or(|end| end > data.len()) {
+                return Err(PdfFontError::Malformed("sfnt"));
+            }
+            tables.insert(tag, (offset, len));
+        }
+        if !tables.contains_key(b"glyf") {
+            return Err(PdfFontError::NotTrueType);
+        }
+        let table = |tag: &[u8; 4], name: &'static str, min: usize| {
+            tables
+                .get(tag)
+                .copied()
+                .filter(|&(_, len)| len >= min)
+                .map(|(offset, _)| offset)
+                .ok_or(PdfFontError::Malformed(name))
+        };
+        let head = table(b"head", "head", 54)?;
+        let hhea = table(b"hhea", "hhea", 36)?;
+        let maxp = table(b"maxp", "maxp", 6)?;
+        table(b"hmtx", "hmtx", 4)?;
+        table(b"loca", "loca", 2)?;
+        let cmap_offset = table(b"cmap", "cmap", 4)?;
+
+        let head_i16 = |at: usize| read_u16(&data, head + at).map(|v| v as i16);
+        let units_per_em = read_u16(&data, head + 18)
+            .filter(|&units| units > 0)
+            .ok_or(PdfFontError::Malformed("head"))?;
+        let bbox = [
+            head_i16(36).unwrap_or(0),
+            head_i16(38).unwrap_or(0),
+            head_i16(40).unwrap_or(0),
+            head_i16(42).unwrap_or(0),
+        ];
+        let long_loca = head_i16(50) == Some(1);
+        let num_glyphs = read_u16(&data, maxp + 4).ok_or(PdfFontError::Malformed("maxp"))?;
+        let num_h_metrics = read_u16(&data, hhea + 34)
+            .filter(|&n| n > 0)
+            .ok_or(PdfFontError::Malformed("hhea"))?;
+        let ascent = read_u16(&data, hhea + 4).unwrap_or(0) as i16;
+        let descent = read_u16(&data, hhea + 6).unwrap_or(0) as i16;
+        let cmap = select_cmap(&data, cmap_offset).ok_or(PdfFontError::Malformed("cmap"))?;
+        Ok(Self {
+            data,
+            tables,
+            units_per_em,
+            bbox,
+            ascent,
+            descent,
+            num_glyphs,
+            num_h_metrics,
+            long_loca,
+            cmap,
+        })
+    }
+
+    /// Glyph for `ch`, or `None` when the font does not cover it.
+    pub(crate) fn glyph_id(&self, ch: char) -> Option<u16> {
+        let code = ch as u32;
+        let data = &self.data;
+        let gid = match self.cmap {
+            Cmap::Segmented(at) => {
+                let code = u16::try_from(code).ok()?;
+                let seg_x2 = read_u16(data, at + 6)? as usize;
+                let ends = at + 14;
+                let starts = ends + seg_x2 + 2;
+                let deltas = starts + seg_x2;
+                let ranges = deltas + seg_x2;
+                let mut gid = None;
+                for seg in (0..seg_x2).step_by(2) {
+                    if read_u16(data, ends + seg)? < code {
+                        continue;
+                    }
+                    let start = read_u16(data, starts + seg)?;
+                    if start > code {
+                        break;
+                    }
+                    let delta = read_u16(data, deltas + seg)?;
+                    let range = read_u16(data, ranges + seg)? as usize;
+                    gid = if range == 0 {
+                        Some(code.wrapping_add(delta))
+                    } else {
+                        let addr = ranges + seg + range + 2 * (code - start) as usize;
+                        read_u16(data, addr)
+                            .filter(|&g| g != 0)
+                            .map(|g| g.wrapping_add(delta))
+                    };
+                    break;
+                }
+                gid?
+            }
+            Cmap::Groups(at) => {
+                let groups = read_u32(data, at + 12)? as usize;
+                let mut gid = None;
+                for group in 0..groups {
+                    let record = at + 16 + group * 12;
+                    let start = read_u32(data, record)?;
+                    let end = read_u32(data, record + 4)?;
+                    if (start..=end).contains(&code) {
+                        gid = u16::try_from(read_u32(data, record + 8)? + (code - start)).ok();
+                        break;
+                    }
+                }
+                gid?
+            }
+        };
+        (gid != 0 && gid < self.num_glyphs).then_some(gid)
+    }
+
+    /// Advance width of `gid` in font units.
+    pub(crate) fn advance(&self, gid: u16) -> u16 {
+        let Some(&(hmtx, _)) = self.tables.get(b"hmtx") else {
+            return 0;
+        };
+        let index = gid.min(self.num_h_metrics - 1) as usize;
+        read_u16(&self.data, hmtx + index * 4).unwrap_or(0)
+    }
+
+    /// Scale font units to PDF glyph space (1/1000 em).
+    pub(crate) fn to_pdf_units(&self, value: i32) -> i32 {
+        value * 1000 / self.units_per_em as i32
+    }
+
+    /// Byte range of `gid` within `glyf`.
+    fn glyph_range(&self, gid: u16) -> Option<(usize, usize)> {
+        let (loca, _) = *self.tables.get(b"loca")?;
+        let (glyf, glyf_len) = *self.tables.get(b"glyf")?;
+        let gid = gid as usize;
+        let (start, end) = if self.long_loca {
+            (
+                read_u32(&self.data, loca + gid * 4)? as usize,
+                read_u32(&self.data, loca + gid * 4 + 4)? as usize,
+            )
+        } else {
+            (
+                read_u16(&self.data, loca + gid * 2)? as usize * 2,
+                read_u16(&self.data, loca + gid * 2 + 2)? as usize * 2,
+            )
+        };
+        (start <= end && end <= glyf_len).then_some((glyf + start, glyf + end))
+    }
+
+    /// Components referenced by a composite glyph.
+    fn components(&self, gid: u16) -> Vec<u16> {
+        let mut out = Vec::with_capacity(0);
+        let Some((start, end)) = self.glyph_range(gid) else {
+            return out;
+        };
+        let glyph = &self.data[start..end];
+        if read_u16(glyph, 0).is_none_or(|contours| contours as i16 >= 0) {
+            return out;
+        }
+        let mut at = 10;
+        while let (Some(flags), Some(component)) = (read_u16(glyph, at), read_u16(glyph, at + 2)) {
+            out.push(component);
+            at += 4 + if flags & 0x0001 != 0 { 4 } else { 2 };
+            at += if flags & 0x0008 != 0 {
+                2
+            } else if flags & 0x0040 != 0 {
+                4
+            } else if flags & 0x0080 != 0 {
+                8
+            } else {
+                0
+            };
+            if flags & 0x0020 == 0 {
+                break;
+            }
+        }
+        out
+    }
+
+    /// Build a font program keeping only `used` glyphs (plus `.notdef` and
+    /// composite components).
+    ///
+    /// Glyph ids are preserved so content streams can address glyphs before
+    /// the subset is known; unused glyphs become empty outlines.
+    pub(crate) fn subset(&self, used: &BTreeSet<u16>) -> Vec<u8> {
+        let mut keep: BTreeSet<u16> = used.clone();
+        keep.insert(0);
+        let mut pending: Vec<u16> = keep.iter().copied().collect();
+        while let Some(gid) = pending.pop() {
+            for component in self.components(gid) {
+                if component < self.num_glyphs && keep.insert(component) {
+                    pending.push(component);
+                }
+            }
+        }
+
+        let mut glyf = Vec::with_capacity(self.tables.get(b"glyf").map_or(0, |&(_, len)| len));
+        let mut loca = Vec::with_capacity((self.num_glyphs as usize + 1) * 4);
+        for gid in 0..self.num_glyphs {
+            loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+            if keep.contains(&gid) {
+                if let Some((start, end)) = self.glyph_range(gid) {
+                    glyf.extend_from_slice(&self.data[start..end]);
+                    glyf.resize(glyf.len().next_multiple_of(4), 0);
+                }
+            }
+        }
+        loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+
+        let mut tables: Vec<([u8; 4], Vec<u8>)> = Vec::with_capacity(SUBSET_TABLES.len());
+        for tag in SUBSET_TABLES {
+            let body = match tag {
+                b"glyf" => glyf.clone(),
+                b"loca" => loca.clone(),
+                _ => match self.tables.get(tag) {
+                    Some(&(offset, len)) => self.data[offset..offset + len].to_vec(),
+                    None => continue,
+                },
+            };
+            tables.push((*tag, body));
+        }
+        for (tag, body) in &mut tables {
+            if tag == b"head" {
+                // Zero checkSumAdjustment; switch to long loca offsets.
+                body[8..12].fill(0);
+                body[50..52].copy_from_slice(&1u16.to_be_bytes());
+            }
+        }
+        write_sfnt(&tables)
+    }
+}
+
+/// Prefer a full-repertoire format 12 subtable, then a BMP format 4 one.
+fn select_cmap(data: &[u8], cmap: usize) -> Option<Cmap> {
+    let count = read_u16(data, cmap + 2)? as usize;
+    let mut segmented = None;
+    for i in 0..count {
+        let record = cmap + 4 + i * 8;
+        let platform = read_u16(data, record)?;
+        let encoding = read_u16(data, record + 2)?;
+        let at = cmap + read_u32(data, record + 4)? as usize;
+        let unicode = platform == 0 || (platform == 3 && matches!(encoding, 1 | 10));
+        if !unicode {
+            continue;
+        }
+        match read_u16(data, at)? {
+            12 => return Some(Cmap::Groups(at)),
+            4 => segmented = segmented.or(Some(Cmap::Segmented(at))),
+            _ => {}
+        }
+    }
+    segmented
+}
+
+fn write_sfnt(tables: &[([u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let count = tables.len() as u16;
+    let selector = 15 - count.max(1).leading_zeros() as u16;
+    let search_range = (1u16 << selector) * 16;
+    let body_len: usize = tables.iter().map(|(_, body)| body.len() + 3).sum();
+    let mut out = Vec::with_capacity(12 + tables.len() * 16 + body_len);
+    out.extend_from_slice(&[0, 1, 0, 0]);
+    out.extend_from_slice(&count.to_be_bytes());
+    out.extend_from_slice(&search_range.to_be_bytes());
+    out.extend_from_slice(&selector.to_be_bytes());
+    out.extend_from_slice(&(count * 16 - search_range).to_be_bytes());
+    let mut offset = 12 + tables.len() * 16;
+    for (tag, body) in tables {
+        out.extend_from_slice(tag);
+        out.extend_from_slice(&checksum(body).to_be_bytes());
+        out.extend_from_slice(&(offset as u32).to_be_bytes());
+        out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        offset += body.len().next_multiple_of(4);
+    }
+    let mut head_at = None;
+    for (tag, body) in tables {
+        if tag == b"head" {
+            head_at = Some(out.len());
+        }
+        out.extend_from_slice(body);
+        out.resize(out.len().next_multiple_of(4), 0);
+    }
+    if let Some(at) = head_at {
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&out));
+        out[at + 8..at + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    out
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    let bytes = data.get(at..at.checked_add(2)?)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at.checked_add(4)?)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Five-glyph TrueType font mapping `A` and `B`; `B` is a composite of
+/// glyph 3 and glyph 4 is never referenced.
+#[cfg(test)]
+pub(crate) fn test_font() -> Vec<u8> {
+    let mut head = vec![0u8; 54];
+    head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+    head[36..44].copy_from_slice(&[0, 0, 0, 0, 0x03, 0xE8, 0x03, 0xE8]);
+    let mut hhea = vec![0u8; 36];
+    hhea[4..6].copy_from_slice(&800u16.to_be_bytes());
+    hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes());
+    hhea[34..36].copy_from_slice(&5u16.to_be_bytes());
+    let maxp = vec![0, 0, 0x50, 0, 0, 5];
+    let hmtx: Vec<u8> = [500u16, 600, 700, 300, 400]
+        .iter()
+        .flat_map(|adv| [adv.to_be_bytes(), [0, 0]].concat())
+        .collect();
+    let simple = [0u8, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    let composite = [0xFFu8, 0xFF, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x02, 0, 3, 0, 0];
+    let glyf = [&simple[..], &composite, &simple, &simple].concat();
+    let loca: Vec<u8> = [0u16, 0, 6, 14, 20, 26]
+        .iter()
+        .flat_map(|offset| offset.to_be_bytes())
+        .collect();
+    let cmap: Vec<u8> = [
+        0u16, 1, 3, 1, 0, 12, // header and one (3, 1) record at offset 12
+        4, 32, 0, 4, 4, 1, 0, // format 4, two segments
+        0x42, 0xFFFF, 0, 0x41, 0xFFFF, 0xFFC0, 1, 0, 0,
+    ]
+    .iter()
+    .flat_map(|word| word.to_be_bytes())
+    .collect();
+    write_sfnt(&[
+        (*b"cmap", cmap),
+        (*b"glyf", glyf),
+        (*b"head", head),
+        (*b"hhea", hhea),
+        (*b"hmtx", hmtx),
+        (*b"loca", loca),
+        (*b"maxp", maxp),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subset_keeps_used_glyphs_and_composite_components() {
+        let face = TrueTypeFace::parse(test_font()).expect("test font should parse");
+        assert_eq!(face.glyph_id('A'), Some(1));
+        assert_eq!(face.glyph_id('B'), Some(2));
+        assert_eq!(face.glyph_id('C'), None);
+        assert_eq!(face.advance(2), 700);
+        assert_eq!(face.to_pdf_units(-200), -200);
+        assert_eq!(
+            TrueTypeFace::parse(b"OTTO\0\0\0\0".to_vec()).err(),
+            Some(PdfFontError::NotTrueType)
+        );
+
+        let subset = face.subset(&BTreeSet::from([2]));
+        assert_eq!(checksum(&subset), 0xB1B0_AFBA);
+        let tables = read_u16(&subset, 4).expect("table count") as usize;
+        let offset_of = |tag: &[u8; 4]| {
+            (0..tables)
+                .map(|i| 12 + i * 16)
+                .find(|&record| &subset[record..record + 4] == tag)
+                .and_then(|record| read_u32(&subset, record + 8))
+                .expect("table should exist") as usize
+        };
+        assert_eq!(read_u16(&subset, offset_of(b"head") + 50), Some(1));
+        let loca = offset_of(b"loca");
+        let glyph_len = |gid: usize| {
+            read_u32(&subset, loca + gid * 4 + 4).expect("loca")
+                - read_u32(&subset, loca + gid * 4).expect("loca")
+        };
+        assert_eq!(glyph_len(1), 0);
+        assert_eq!(glyph_len(2), 16);
+        assert_eq!(glyph_len(3), 12);
+        assert_eq!(glyph_len(4), 0);
+    }
+}
@@ -0,0 +1,625 @@
+//! PNG decoding one scanline at a time.
+//!
+//! IDAT data is inflated straight into a pair of scanline buffers, so only
+//! the current and previous filtered lines are held. Adam7 images also
+//! need one byte per pixel to reassemble the passes.
+
+use miniz_oxide::inflate::stream::{inflate, InflateState};
+use miniz_oxide::{DataFormat, MZError, MZFlush, MZStatus};
+
+use crate::render_image::{
+    fit_size, flatten_alpha, ImageDecodeOptions, ImageDecoder, LumaConverter, RowDownscaler,
+};
+use crate::render_ir::{GrayBitmap, GrayscaleMode};
+
+/// Default `StripPngDecoder::max_scratch_bytes`.
+pub const DEFAULT_PNG_SCRATCH_BYTES: usize = 96 * 1024;
+
+const SIGNATURE: [u8; 8] = *b"\x89PNG\r\n\x1a\n";
+
+/// Adam7 passes as `(x0, y0, dx, dy)`.
+const ADAM7: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// PNG decoder streaming scanlines into a bounded scratch buffer.
+///
+/// Decodes every color type and bit depth, palettes and `tRNS`
+/// transparency included, and Adam7 interlacing. Images whose scratch,
+/// reported by `scratch_len`, exceeds the budget are not sniffed, leaving
+/// them to decoders registered later. Inflating adds a fixed ~45 KB of
+/// state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StripPngDecoder {
+    /// Images whose scratch would exceed this are not decoded.
+    pub max_scratch_bytes: usize,
+}
+
+impl Default for StripPngDecoder {
+    fn default() -> Self {
+        Self {
+            max_scratch_bytes: DEFAULT_PNG_SCRATCH_BYTES,
+        }
+    }
+}
+
+impl StripPngDecoder {
+    /// Scratch bytes `decode_strips` needs for `bytes`: two filtered
+    /// scanlines and a luma row, plus a luma plane when interlaced.
+    pub fn scratch_len(&self, bytes: &[u8]) -> Option<usize> {
+        Png::parse(bytes, GrayscaleMode::Luminosity)?.scratch_len()
+    }
+
+    /// Decode `bytes` into `scratch`, passing each luma row of the image
+    /// to `on_row` with its y coordinate. Color converts with `Color::luma`.
+    ///
+    /// `scratch` must hold at least `scratch_len` bytes. Returns the image
+    /// size, or `None` on malformed input; rows already passed to `on_row`
+    /// are then incomplete.
+    pub fn decode_strips<F>(
+        &self,
+        bytes: &[u8],
+        scratch: &mut [u8],
+        on_row: F,
+    ) -> Option<(u32, u32)>
+    where
+        F: FnMut(u32, &[u8]),
+    {
+        let png = Png::parse(bytes, GrayscaleMode::Luminosity)?;
+        png.decode(scratch, on_row)?;
+        Some((png.width as u32, png.height as u32))
+    }
+
+    /// Decode with `grayscale` conversion and scratch allocated within
+    /// `max_scratch_bytes`.
+    fn with_scratch<F>(&self, bytes: &[u8], grayscale: GrayscaleMode, on_row: F) -> Option<()>
+    where
+        F: FnMut(u32, &[u8]),
+    {
+        let png = Png::parse(bytes, grayscale)?;
+        let len = png
+            .scratch_len()
+            .filter(|&len| len <= self.max_scratch_bytes)?;
+        let mut scratch = vec![0; len];
+        png.decode(&mut scratch, on_row)
+    }
+}
+
+impl ImageDecoder for StripPngDecoder {
+    fn sniff(&self, bytes: &[u8]) -> bool {
+        bytes.starts_with(&SIGNATURE)
+            && self
+                .scratch_len(bytes)
+                .is_some_and(|len| len <= self.max_scratch_bytes)
+    }
+
+    fn dimensions(&self, bytes: &[u8]) -> Option<(u32, u32)> {
+        let png = Png::parse(bytes, GrayscaleMode::Luminosity)?;
+        Some((png.width as u32, png.height as u32))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Option<GrayBitmap> {
+        let (width, height) = self.dimensions(bytes)?;
+        let mut pixels = Vec::with_capacity(width as usize * height as usize);
+        self.with_scratch(bytes, GrayscaleMode::Luminosity, |_, row| {
+            pixels.extend_from_slice(row)
+        })?;
+        Some(GrayBitmap {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    fn decode_within(
+        &self,
+        bytes: &[u8],
+        max_width: i32,
+        max_height: i32,
+        options: ImageDecodeOptions,
+    ) -> Option<GrayBitmap> {
+        let (width, height) = self.dimensions(bytes)?;
+        let (out_width, out_height) = fit_size(width, height, max_width, max_height);
+        let mut scaler = RowDownscaler::new(width, height, out_width, out_height, options.filter);
+        self.with_scratch(bytes, options.grayscale, |_, row| scaler.push_row(row))?;
+        scaler.finish()
+    }
+
+    fn decodes_in_strips(&self) -> bool {
+        true
+    }
+}
+
+/// `(type, data)` of each chunk after the signature, up to `IEND`.
+struct Chunks<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = ([u8; 4], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let [l0, l1, l2, l3, t0, t1, t2, t3, ref rest @ ..] = *self.rest else {
+            return None;
+        };
+        let len = u32::from_be_bytes([l0, l1, l2, l3]) as usize;
+        let data = rest.get(..len)?;
+        self.rest = rest.get(len.checked_add(4)?..)?;
+        let kind = [t0, t1, t2, t3];
+        (&kind != b"IEND").then_some((kind, data))
+    }
+}
+
+/// Header fields plus the palette folded to luma.
+struct Png<'a> {
+    width: usize,
+    height: usize,
+    depth: u8,
+    color: u8,
+    interlaced: bool,
+    /// Luma of each palette entry over white.
+    palette: [u8; 256],
+    /// `tRNS` color key of gray and RGB images.
+    transparent: Option<[u16; 3]>,
+    gray: LumaConverter,
+    chunks: &'a [u8],
+}
+
+impl<'a> Png<'a> {
+    fn parse(bytes: &'a [u8], grayscale: GrayscaleMode) -> Option<Self> {
+        let chunks = bytes.strip_prefix(&SIGNATURE)?;
+        let mut iter = Chunks { rest: chunks };
+        let (kind, header) = iter.next()?;
+        let [w0, w1, w2, w3, h0, h1, h2, h3, depth, color, 0, 0, interlace] = *header else {
+            return None;
+        };
+        if &kind != b"IHDR" || interlace > 1 {
+            return None;
+        }
+        let valid = match color {
+            0 => matches!(depth, 1 | 2 | 4 | 8 | 16),
+            3 => matches!(depth, 1 | 2 | 4 | 8),
+            2 | 4 | 6 => matches!(depth, 8 | 16),
+            _ => false,
+        };
+        let width = u32::from_be_bytes([w0, w1, w2, w3]) as usize;
+        let height = u32::from_be_bytes([h0, h1, h2, h3]) as usize;
+        if !valid || width == 0 || height == 0 {
+            return None;
+        }
+        let mut png = Png {
+            width,
+            height,
+            depth,
+            color,
+            interlaced: interlace == 1,
+            palette: [0; 256],
+            transparent: None,
+            gray: LumaConverter::new(grayscale),
+            chunks,
+        };
+        let mut palette_len = 0;
+        for (kind, data) in iter {
+            match &kind {
+                b"PLTE" => {
+                    for (entry, rgb) in png.palette.iter_mut().zip(data.chunks_exact(3)) {
+                        *entry = png.gray.luma(rgb[0], rgb[1], rgb[2]);
+                        palette_len += 1;
+                    }
+                }
+                b"tRNS" if color == 3 => {
+                    for (entry, &alpha) in png.palette[..palette_len].iter_mut().zip(data) {
+                        *entry = flatten_alpha(*entry, alpha);
+                    }
+                }
+                b"tRNS" => {
+                    let mut key = [0u16; 3];
+                    for (value, sample) in key.iter_mut().zip(data.chunks_exact(2)) {
+                        *value = u16::from_be_bytes([sample[0], sample[1]]);
+                    }
+                    png.transparent = Some(key);
+                }
+                b"IDAT" => break,
+                _ => {}
+            }
+        }
+        (color != 3 || palette_len > 0).then_some(png)
+    }
+
+    fn channels(&self) -> usize {
+        match self.color {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1,
+        }
+    }
+
+    /// Filtered bytes of a `width`-pixel scanline, without the filter byte.
+    fn line_bytes(&self, width: usize) -> Option<usize> {
+        width
+            .checked_mul(self.channels() * self.depth as usize)?
+            .checked_add(7)
+            .map(|bits| bits / 8)
+    }
+
+    fn scratch_len(&self) -> Option<usize> {
+        let lines = (self.line_bytes(self.width)? + 1).checked_mul(2)?;
+        let plane = if self.interlaced {
+            self.width.checked_mul(self.height)?
+        } else {
+            0
+        };
+        lines.checked_add(self.width)?.checked_add(plane)
+    }
+
+    fn decode<F>(&self, scratch: &mut [u8], mut on_row: F) -> Option<()>
+    where
+        F: FnMut(u32, &[u8]),
+    {
+        let line = self.line_bytes(self.width)? + 1;
+        let scratch = scratch.get_mut(..self.scratch_len()?)?;
+        let (mut prev, rest) = scratch.split_at_mut(line);
+        let (mut cur, rest) = rest.split_at_mut(line);
+        let (gray, plane) = rest.split_at_mut(self.width);
+        let filter_bpp = (self.channels() * self.depth as usize / 8).max(1);
+        let mut inflater = Inflater::new(self.chunks);
+        if !self.interlaced {
+            prev.fill(0);
+            for y in 0..self.height {
+                inflater.fill(cur)?;
+                unfilter(cur, prev, filter_bpp)?;
+                self.gray_row(&cur[1..], gray);
+                on_row(y as u32, gray);
+                core::mem::swap(&mut prev, &mut cur);
+            }
+            return Some(());
+        }
+        for (x0, y0, dx, dy) in ADAM7 {
+            let pass_width = self.width.saturating_sub(x0).div_ceil(dx);
+            let pass_height = self.height.saturating_sub(y0).div_ceil(dy);
+            if pass_width == 0 || pass_height == 0 {
+                continue;
+            }
+            let len = self.line_bytes(pass_width)? + 1;
+            prev[..len].fill(0);
+            for pass_y in 0..pass_height {
+                inflater.fill(&mut cur[..len])?;
+                unfilter(&mut cur[..len], &prev[..len], filter_bpp)?;
+                self.gray_row(&cur[1..len], &mut gray[..pass_width]);
+                let row = (y0 + pass_y * dy) * self.width;
+                for (pass_x, &value) in gray[..pass_width].iter().enumerate() {
+                    plane[row + x0 + pass_x * dx] = value;
+                }
+                core::mem::swap(&mut prev, &mut cur);
+            }
+        }
+        for (y, row) in plane.chunks_exact(self.width).enumerate() {
+            on_row(y as u32, row);
+        }
+        Some(())
+    }
+
+    /// Convert one unfiltered scanline to luma, one sample per `out` byte.
+    fn gray_row(&self, raw: &[u8], out: &mut [u8]) {
+        let depth = self.depth as usize;
+        if depth < 8 {
+            let max = (1u16 << depth) - 1;
+            for (x, value) in out.iter_mut().enumerate() {
+                let bit = x * depth;
+                let sample = (raw[bit / 8] >> (8 - depth - bit % 8)) as u16 & max;
+                *value = if self.color == 3 {
+                    self.palette[sample as usize]
+                } else if self.transparent.is_some_and(|key| key[0] == sample) {
+                    255
+                } else {
+                    (sample * 255 / max) as u8
+                };
+            }
+            return;
+        }
+        let bytes = depth / 8;
+        let channels = self.channels();
+        for (value, px) in out.iter_mut().zip(raw.chunks_exact(channels * bytes)) {
+            let sample = |channel: usize| px[channel * bytes];
+            let full = |channel: usize| match bytes {
+                2 => u16::from_be_bytes([px[channel * 2], px[channel * 2 + 1]]),
+                _ => px[channel] as u16,
+            };
+            *value = match self.color {
+                0 if self.transparent.is_some_and(|key| key[0] == full(0)) => 255,
+                0 => sample(0),
+                2 if self
+                    .transparent
+                    .is_some_and(|key| key == [full(0), full(1), full(2)]) =>
+                {
+                    255
+                }
+                2 => self.gray.luma(sample(0), sample(1), sample(2)),
+                3 => self.palette[sample(0) as usize],
+                4 => flatten_alpha(sample(0), sample(1)),
+                _ => flatten_alpha(self.gray.luma(sample(0), sample(1), sample(2)), sample(3)),
+            };
+        }
+    }
+}
+
+/// Reverse the scanline filter in place; `line[0]` is the filter type.
+fn unfilter(line: &mut [u8], prev: &[u8], bpp: usize) -> Option<()> {
+    let (&mut filter, cur) = line.split_first_mut()?;
+    let prev = prev.get(1..cur.len() + 1)?;
+    match filter {
+        0 => {}
+        1 => {
+            for i in bpp..cur.len() {
+                cur[i] = cur[i].wrapping_add(cur[i - bpp]);
+            }
+        }
+        2 => {
+            for (value, &up) in cur.iter_mut().zip(prev) {
+                *value = value.wrapping_add(up);
+            }
+        }
+        3 => {
+            for i in 0..cur.len() {
+                let left = if i >= bpp { cur[i - bpp] } else { 0 };
+                let average = ((left as u16 + prev[i] as u16) / 2) as u8;
+                cur[i] = cur[i].wrapping_add(average);
+            }
+        }
+        4 => {
+            for i in 0..cur.len() {
+                let (left, up_left) = if i >= bpp {
+                    (cur[i - bpp], prev[i - bpp])
+                } else {
+                    (0, 0)
+                };
+                cur[i] = cur[i].wrapping_add(paeth(left, prev[i], up_left));
+            }
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let (da, db, dc) = (
+        (estimate - left as i16).abs(),
+        (estimate - up as i16).abs(),
+        (estimate - up_left as i16).abs(),
+    );
+    if da <= db && da <= dc {
+        left
+    } else if db <= dc {
+        up
+    } else {
+        up_left
+    }
+}
+
+/// Zlib stream spread over the IDAT chunks, inflated on demand.
+struct Inflater<'a> {
+    chunks: Chunks<'a>,
+    input: &'a [u8],
+    exhausted: bool,
+    state: Box<InflateState>,
+}
+
+impl<'a> Inflater<'a> {
+    fn new(chunks: &'a [u8]) -> Self {
+        Self {
+            chunks: Chunks { rest: chunks },
+            input: &[],
+            exhausted: false,
+            state: InflateState::new_boxed(DataFormat::Zlib),
+        }
+    }
+
+    /// Fill `out` completely with inflated bytes.
+    fn fill(&mut self, out: &mut [u8]) -> Option<()> {
+        let mut filled = 0;
+        while filled < out.len() {
+            if self.input.is_empty() && !self.exhausted {
+                match self.chunks.find(|(kind, _)| kind == b"IDAT") {
+                    Some((_, data)) => self.input = data,
+                    None => self.exhausted = true,
+                }
+            }
+            let result = inflate(
+                &mut self.state,
+                self.input,
+                &mut out[filled..],
+                MZFlush::None,
+            );
+            self.input = self.input.get(result.bytes_consumed..)?;
+            filled += result.bytes_written;
+            let progressed = result.bytes_consumed > 0 || result.bytes_written > 0;
+            match result.status {
+                Ok(MZStatus::Ok) if progressed || !self.exhausted => {}
+                Err(MZError::Buf) if !self.exhausted => {}
+                Ok(MZStatus::StreamEnd) if filled == out.len() => {}
+                _ => return None,
+            }
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_image::luma;
+
+    fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = out.len();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(data);
+        let crc = crc32fast::hash(&out[start..]);
+        out.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    /// Filter `rows` cycling through all five filter types.
+    fn filtered(rows: &[Vec<u8>], bpp: usize) -> Vec<u8> {
+        let mut out = Vec::with_capacity(rows.len() * (rows[0].len() + 1));
+        let empty = vec![0; rows[0].len()];
+        for (y, row) in rows.iter().enumerate() {
+            let prev = if y == 0 { &empty } else { &rows[y - 1] };
+            let filter = (y % 5) as u8;
+            out.push(filter);
+            for i in 0..row.len() {
+                let left = if i >= bpp { row[i - bpp] } else { 0 };
+                let up_left = if i >= bpp { prev[i - bpp] } else { 0 };
+                let predicted = match filter {
+                    0 => 0,
+                    1 => left,
+                    2 => prev[i],
+                    3 => ((left as u16 + prev[i] as u16) / 2) as u8,
+                    _ => paeth(left, prev[i], up_left),
+                };
+                out.push(row[i].wrapping_sub(predicted));
+            }
+        }
+        out
+    }
+
+    fn encode(
+        width: u32,
+        height: u32,
+        depth_color: [u8; 2],
+        interlaced: bool,
+        extra: &[(&[u8; 4], &[u8])],
+        scanlines: &[u8],
+    ) -> Vec<u8> {
+        let mut png = SIGNATURE.to_vec();
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&width.to_be_bytes());
+        header.extend_from_slice(&height.to_be_bytes());
+        header.extend_from_slice(&[depth_color[0], depth_color[1], 0, 0, interlaced as u8]);
+        chunk(&mut png, b"IHDR", &header);
+        for (kind, data) in extra {
+            chunk(&mut png, kind, data);
+        }
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(scanlines, 6);
+        // Split the stream across IDAT chunks to exercise chunk boundaries.
+        for part in compressed.chunks(7) {
+            chunk(&mut png, b"IDAT", part);
+        }
+        chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    #[test]
+    fn palette_with_transparency_streams_rows() {
+        // 2-bit indices: black, white, opaque mid-gray, fully transparent.
+        let plte = [0, 0, 0, 255, 255, 255, 128, 128, 128, 0, 0, 0];
+        let trns = [255, 255, 255, 0];
+        let rows: Vec<Vec<u8>> = (0..3).map(|_| vec![0b0001_1011, 0b1000_0000]).collect();
+        let png = encode(
+            5,
+            3,
+            [2, 3],
+            false,
+            &[(b"PLTE", &plte), (b"tRNS", &trns)],
+            &filtered(&rows, 1),
+        );
+        let decoder = StripPngDecoder::default();
+        assert!(decoder.sniff(&png));
+        assert_eq!(decoder.dimensions(&png), Some((5, 3)));
+        let mut scratch = vec![0; decoder.scratch_len(&png).expect("scratch")];
+        let mut seen = Vec::with_capacity(3);
+        let size = decoder.decode_strips(&png, &mut scratch, |y, row| seen.push((y, row.to_vec())));
+        assert_eq!(size, Some((5, 3)));
+        let gray = luma(128, 128, 128);
+        for (y, (row_y, row)) in seen.iter().enumerate() {
+            assert_eq!(*row_y, y as u32);
+            assert_eq!(*row, [0, 255, gray, 255, gray]);
+        }
+    }
+
+    #[test]
+    fn adam7_matches_non_interlaced_encoding() {
+        let (width, height) = (11usize, 9usize);
+        let rgb: Vec<Vec<u8>> = (0..height)
+            .map(|y| {
+                (0..width)
+                    .flat_map(|x| [(x * 23) as u8, (y * 29) as u8, (x * y * 7) as u8])
+                    .collect()
+            })
+            .collect();
+        let plain = encode(
+            width as u32,
+            height as u32,
+            [8, 2],
+            false,
+            &[],
+            &filtered(&rgb, 3),
+        );
+        let mut passes = Vec::with_capacity(rgb.len() * rgb[0].len() * 2);
+        for (x0, y0, dx, dy) in ADAM7 {
+            let pass: Vec<Vec<u8>> = (y0..height)
+                .step_by(dy)
+                .map(|y| {
+                    (x0..width)
+                        .step_by(dx)
+                        .flat_map(|x| rgb[y][x * 3..x * 3 + 3].to_vec())
+                        .collect()
+                })
+                .filter(|row: &Vec<u8>| !row.is_empty())
+                .collect();
+            if !pass.is_empty() {
+                passes.extend(filtered(&pass, 3));
+            }
+        }
+        let interlaced = encode(width as u32, height as u32, [8, 2], true, &[], &passes);
+
+        let decoder = StripPngDecoder::default();
+        let expected = decoder.decode(&plain).expect("plain decodes");
+        assert_eq!(expected.pixel(3, 2), Some(luma(69, 58, 42)));
+        assert_eq!(decoder.decode(&interlaced), Some(expected.clone()));
+        #[cfg(feature = "image-decode")]
+        assert_eq!(
+            crate::render_image::PngDecoder.decode(&interlaced),
+            Some(expected.clone())
+        );
+        let scaled = decoder
+            .decode_within(&interlaced, 4, 4, ImageDecodeOptions::default())
+            .expect("scaled decode");
+        assert_eq!(
+            scaled,
+            crate::render_image::fit_within(expected, 4, 4, crate::ImageScaleFilter::Box)
+        );
+        assert!(decoder
+            .scratch_len(&interlaced)
+            .is_some_and(|len| len >= width * height));
+    }
+
+    #[test]
+    fn sixteen_bit_gray_alpha_flattens_and_budget_is_enforced() {
+        let row = [0x00, 0x10, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00];
+        let png = encode(2, 1, [16, 4], false, &[], &filtered(&[row.to_vec()], 4));
+        let decoder = StripPngDecoder::default();
+        let bitmap = decoder.decode(&png).expect("gray-alpha decodes");
+        assert_eq!(bitmap.pixels, [0, 255]);
+
+        let tight = StripPngDecoder {
+            max_scratch_bytes: 8,
+        };
+        assert!(!tight.sniff(&png));
+        assert!(tight.decode(&png).is_none());
+        let mut corrupt = png.clone();
+        let idat = corrupt
+            .windows(4)
+            .position(|w| w == b"IDAT")
+            .expect("data chunk");
+        corrupt[idat + 4] ^= 0xFF;
+        assert!(decoder.decode(&corrupt).is_none());
+    }
+}
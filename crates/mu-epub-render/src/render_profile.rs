@@ -0,0 +1,244 @@
+//! Opt-in whole-book profiling, see `RenderEngine::set_profiling`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::render_engine::{EngineTelemetry, RenderDiagnostic, RenderPhase};
+
+/// Phase timings for one chapter laid out while profiling.
+///
+/// A chapter laid out more than once accumulates into one entry; cached
+/// chapters never get one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChapterProfile {
+    /// Chapter index (spine order).
+    pub chapter_index: usize,
+    /// Layout runs folded into this entry.
+    pub runs: u32,
+    /// Reading and tokenizing chapter markup.
+    pub tokenize_micros: u64,
+    /// Stylesheets, cascade, and font resolution.
+    pub style_micros: u64,
+    /// Line breaking and block placement.
+    pub line_break_micros: u64,
+    /// Closing pages: metrics, damage, and cache capture.
+    pub paginate_micros: u64,
+    /// Most bytes of pages one layout session held at once.
+    pub peak_page_bytes: usize,
+}
+
+impl ChapterProfile {
+    /// Time spent in `phase`.
+    pub fn phase_micros(&self, phase: RenderPhase) -> u64 {
+        match phase {
+            RenderPhase::Tokenize => self.tokenize_micros,
+            RenderPhase::Style => self.style_micros,
+            RenderPhase::LineBreak => self.line_break_micros,
+            RenderPhase::Paginate => self.paginate_micros,
+        }
+    }
+
+    /// Time spent across all phases.
+    pub fn total_micros(&self) -> u64 {
+        PHASES
+            .iter()
+            .map(|&phase| self.phase_micros(phase))
+            .fold(0, u64::saturating_add)
+    }
+
+    fn phase_micros_mut(&mut self, phase: RenderPhase) -> &mut u64 {
+        match phase {
+            RenderPhase::Tokenize => &mut self.tokenize_micros,
+            RenderPhase::Style => &mut self.style_micros,
+            RenderPhase::LineBreak => &mut self.line_break_micros,
+            RenderPhase::Paginate => &mut self.paginate_micros,
+        }
+    }
+}
+
+/// Aggregated timings and counters from `RenderEngine::profile_report`.
+///
+/// Prints as a plain-text table and serializes with `to_json`. Timings are
+/// zero on targets without a clock (`wasm32-unknown-unknown`) and under
+/// `RenderEngineOptions::deterministic`; counters are always filled.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProfileReport {
+    /// Per-chapter timings, in chapter order.
+    pub chapters: Vec<ChapterProfile>,
+    /// Chapters laid out to completion; cache hits are not counted.
+    pub chapters_prepared: u64,
+    /// Pages handed to callers, cached or freshly laid out.
+    pub pages_emitted: u64,
+    /// Render-cache lookups that returned pages.
+    pub cache_hits: u64,
+    /// Render-cache lookups that missed.
+    pub cache_misses: u64,
+    /// Decoded-image cache lookups that returned a bitmap.
+    pub image_cache_hits: u64,
+    /// Decoded-image cache lookups that missed.
+    pub image_cache_misses: u64,
+    /// Chapter markup and object resources read from the book.
+    pub bytes_decompressed: u64,
+    /// Raster images decoded to bitmaps.
+    pub images_decoded: u64,
+    /// Most bytes of pages any layout session held at once.
+    pub peak_page_bytes: usize,
+}
+
+impl ProfileReport {
+    /// Time spent in `phase` across all chapters.
+    pub fn phase_micros(&self, phase: RenderPhase) -> u64 {
+        self.chapters
+            .iter()
+            .map(|chapter| chapter.phase_micros(phase))
+            .fold(0, u64::saturating_add)
+    }
+
+    /// Time spent across all phases and chapters.
+    pub fn total_micros(&self) -> u64 {
+        self.chapters
+            .iter()
+            .map(ChapterProfile::total_micros)
+            .fold(0, u64::saturating_add)
+    }
+
+    /// Up to `count` chapters, slowest first.
+    pub fn slowest_chapters(&self, count: usize) -> Vec<&ChapterProfile> {
+        let mut chapters: Vec<&ChapterProfile> = self.chapters.iter().collect();
+        chapters.sort_by_key(|chapter| core::cmp::Reverse(chapter.total_micros()));
+        chapters.truncate(count);
+        chapters
+    }
+}
+
+impl fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "chapters {} pages {} total {:.1} ms",
+            self.chapters_prepared,
+            self.pages_emitted,
+            millis(self.total_micros())
+        )?;
+        writeln!(
+            f,
+            "cache {}/{} hit, images {}/{} hit, {} decoded",
+            self.cache_hits,
+            self.cache_hits + self.cache_misses,
+            self.image_cache_hits,
+            self.image_cache_hits + self.image_cache_misses,
+            self.images_decoded
+        )?;
+        writeln!(
+            f,
+            "read {} bytes, peak pages {} bytes",
+            self.bytes_decompressed, self.peak_page_bytes
+        )?;
+        writeln!(
+            f,
+            "{:>7} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            "chapter", "tokenize", "style", "linebreak", "paginate", "total", "peak"
+        )?;
+        for chapter in &self.chapters {
+            writeln!(
+                f,
+                "{:>7} {:>10.1} {:>10.1} {:>10.1} {:>10.1} {:>10.1} {:>10}",
+                chapter.chapter_index,
+                millis(chapter.tokenize_micros),
+                millis(chapter.style_micros),
+                millis(chapter.line_break_micros),
+                millis(chapter.paginate_micros),
+                millis(chapter.total_micros()),
+                chapter.peak_page_bytes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+const PHASES: [RenderPhase; 4] = [
+    RenderPhase::Tokenize,
+    RenderPhase::Style,
+    RenderPhase::LineBreak,
+    RenderPhase::Paginate,
+];
+
+fn millis(micros: u64) -> f64 {
+    micros as f64 / 1000.0
+}
+
+/// Accumulator fed from engine diagnostics while profiling is on.
+#[derive(Debug, Default)]
+pub(crate) struct Profiler {
+    baseline: EngineTelemetry,
+    chapters: BTreeMap<usize, ChapterProfile>,
+    image_cache_hits: u64,
+    image_cache_misses: u64,
+    peak_page_bytes: usize,
+}
+
+impl Profiler {
+    /// Start counting from the engine's current telemetry.
+    pub(crate) fn new(baseline: EngineTelemetry) -> Self {
+        Self {
+            baseline,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn observe(&mut self, diagnostic: &RenderDiagnostic) {
+        match diagnostic {
+            RenderDiagnostic::PhaseTime {
+                chapter_index,
+                phase,
+                micros,
+            } => {
+                let chapter = self.chapter(*chapter_index);
+                if *phase == RenderPhase::LineBreak {
+                    chapter.runs += 1;
+                }
+                let total = chapter.phase_micros_mut(*phase);
+                *total = total.saturating_add(*micros);
+            }
+            RenderDiagnostic::PeakScratchBytes {
+                chapter_index,
+                bytes,
+            } => {
+                let chapter = self.chapter(*chapter_index);
+                chapter.peak_page_bytes = chapter.peak_page_bytes.max(*bytes);
+                self.peak_page_bytes = self.peak_page_bytes.max(*bytes);
+            }
+            RenderDiagnostic::ImageCacheLookup { hit: true, .. } => self.image_cache_hits += 1,
+            RenderDiagnostic::ImageCacheLookup { hit: false, .. } => self.image_cache_misses += 1,
+            _ => {}
+        }
+    }
+
+    fn chapter(&mut self, chapter_index: usize) -> &mut ChapterProfile {
+        self.chapters
+            .entry(chapter_index)
+            .or_insert_with(|| ChapterProfile {
+                chapter_index,
+                ..ChapterProfile::default()
+            })
+    }
+
+    /// Report against the engine's current telemetry.
+    pub(crate) fn report(&self, now: EngineTelemetry) -> ProfileReport {
+        let base = self.baseline;
+        ProfileReport {
+            chapters: self.chapters.values().copied().collect(),
+            chapters_prepared: now.chapters_prepared.saturating_sub(base.chapters_prepared),
+            pages_emitted: now.pages_emitted.saturating_sub(base.pages_emitted),
+            cache_hits: now.cache_hits.saturating_sub(base.cache_hits),
+            cache_misses: now.cache_misses.saturating_sub(base.cache_misses),
+            image_cache_hits: self.image_cache_hits,
+            image_cache_misses: self.image_cache_misses,
+            bytes_decompressed: now
+                .bytes_decompressed
+                .saturating_sub(base.bytes_decompressed),
+            images_decoded: now.images_decoded.saturating_sub(base.images_decoded),
+            peak_page_bytes: self.peak_page_bytes,
+        }
+    }
+}
@@ -0,0 +1,444 @@
+//! Reference software rasterizer behind the `raster` feature.
+
+use std::io::{self, Write};
+
+use mu_epub::Color;
+
+use crate::render_ir::{
+    DrawCommand, GlyphPosition, GrayBitmap, ImageCommand, PageChromeCommand, PageChromeConfig,
+    PageChromeKind, PageRect, RenderPage, TextCommand, VerticalMetrics,
+};
+use crate::render_layout::glyph_positions;
+
+/// Font size assumed for greeked header and footer text.
+const CHROME_TEXT_PX: f32 = 13.0;
+
+/// Largest stored deflate block.
+const STORED_BLOCK_MAX: usize = 0xFFFF;
+
+/// Software rasterizer settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RasterConfig {
+    /// Paper luma.
+    pub paper: u8,
+    /// Luma for commands without an explicit color.
+    pub ink: u8,
+    /// Geometry for `PageChrome` markers; disabled kinds are skipped.
+    pub page_chrome: PageChromeConfig,
+}
+
+impl Default for RasterConfig {
+    fn default() -> Self {
+        Self {
+            paper: 255,
+            ink: 0,
+            page_chrome: PageChromeConfig::geometry_defaults(),
+        }
+    }
+}
+
+impl RenderPage {
+    /// Rasterize to a `width`x`height` grayscale bitmap.
+    ///
+    /// Layers are composited via `composite`, honoring z-order and clips.
+    /// Text is greeked: each glyph becomes a box over its x-height,
+    /// ascender, or descender band at its laid-out advance. Output depends
+    /// only on the page, not on fonts or platform, so it suits golden tests
+    /// and layout previews.
+    pub fn rasterize(&self, width: u32, height: u32, cfg: &RasterConfig) -> GrayBitmap {
+        let full = PageRect::new(0, 0, width, height);
+        let mut canvas = Canvas {
+            bitmap: GrayBitmap {
+                width,
+                height,
+                pixels: vec![cfg.paper; width as usize * height as usize],
+            },
+            clip: full,
+            cfg,
+        };
+        for item in self.composite() {
+            canvas.clip = match item.clip {
+                Some(clip) => match clip.clip(&full) {
+                    Some(clip) => clip,
+                    None => continue,
+                },
+                None => full,
+            };
+            canvas.draw(item.command);
+        }
+        canvas.bitmap
+    }
+}
+
+impl GrayBitmap {
+    /// Write as a binary PGM (`P5`) image.
+    pub fn write_pgm<W: Write>(&self, mut out: W) -> io::Result<()> {
+        write!(out, "P5\n{} {}\n255\n", self.width, self.height)?;
+        out.write_all(&self.pixels)
+    }
+
+    /// Write as an 8-bit grayscale PNG.
+    ///
+    /// Image data is stored uncompressed; run the file through an optimizer
+    /// when size matters.
+    pub fn write_png<W: Write>(&self, mut out: W) -> io::Result<()> {
+        out.write_all(b"\x89PNG\r\n\x1a\n")?;
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        // Bit depth 8, grayscale, deflate, adaptive filtering, no interlace.
+        header.extend_from_slice(&[8, 0, 0, 0, 0]);
+        write_png_chunk(&mut out, b"IHDR", &header)?;
+
+        let row = self.width as usize;
+        let mut raw = Vec::with_capacity((row + 1) * self.height as usize);
+        for line in self.pixels.chunks(row.max(1)).take(self.height as usize) {
+            // Filter type 0 (none).
+            raw.push(0);
+            raw.extend_from_slice(line);
+        }
+        write_png_chunk(&mut out, b"IDAT", &zlib_stored(&raw))?;
+        write_png_chunk(&mut out, b"IEND", &[])
+    }
+}
+
+struct Canvas<'c> {
+    bitmap: GrayBitmap,
+    clip: PageRect,
+    cfg: &'c RasterConfig,
+}
+
+impl Canvas<'_> {
+    fn ink(&self, color: Option<Color>) -> u8 {
+        color.map_or(self.cfg.ink, Color::luma)
+    }
+
+    fn draw(&mut self, cmd: &DrawCommand) {
+        match cmd {
+            DrawCommand::Text(text) => self.draw_text(text),
+            DrawCommand::Rule(rule) => {
+                let (width, height) = if rule.horizontal {
+                    (rule.length, rule.thickness)
+                } else {
+                    (rule.thickness, rule.length)
+                };
+                let ink = self.cfg.ink;
+                self.fill(PageRect::new(rule.x, rule.y, width, height), ink);
+            }
+            DrawCommand::Rect(rect) => {
+                let area = PageRect::new(rect.x, rect.y, rect.width, rect.height);
+                let ink = self.ink(rect.color);
+                if rect.fill {
+                    self.fill(area, ink);
+                } else {
+                    self.stroke(area, ink);
+                }
+            }
+            DrawCommand::Image(image) => self.draw_image(image),
+            DrawCommand::PageChrome(chrome) => self.draw_chrome(chrome),
+            // Resolved by `RenderPage::composite`.
+            DrawCommand::Layered(_) | DrawCommand::PushClip(_) | DrawCommand::PopClip => {}
+        }
+    }
+
+    fn fill(&mut self, rect: PageRect, luma: u8) {
+        let Some(area) = rect.clip(&self.clip) else {
+            return;
+        };
+        let stride = self.bitmap.width as usize;
+        for y in area.y..area.bottom() {
+            let row = y as usize * stride;
+            if let Some(span) = self
+                .bitmap
+                .pixels
+                .get_mut(row + area.x as usize..row + area.right() as usize)
+            {
+                span.fill(luma);
+            }
+        }
+    }
+
+    fn stroke(&mut self, rect: PageRect, luma: u8) {
+        if rect.is_empty() {
+            return;
+        }
+        let (w, h) = (rect.width, rect.height);
+        self.fill(PageRect::new(rect.x, rect.y, w, 1), luma);
+        self.fill(PageRect::new(rect.x, rect.bottom() - 1, w, 1), luma);
+        self.fill(PageRect::new(rect.x, rect.y, 1, h), luma);
+        self.fill(PageRect::new(rect.right() - 1, rect.y, 1, h), luma);
+    }
+
+    fn draw_image(&mut self, image: &ImageCommand) {
+        let area = PageRect::new(image.x, image.y, image.width, image.height);
+        let Some(bitmap) = image
+            .bitmap
+            .as_ref()
+            .filter(|b| b.width > 0 && b.height > 0)
+        else {
+            let ink = self.cfg.ink;
+            self.stroke(area, ink);
+            return;
+        };
+        let Some(visible) = area.clip(&self.clip) else {
+            return;
+        };
+        // Nearest-neighbour scale from bitmap to layout size.
+        for y in visible.y..visible.bottom() {
+            let sy = (y - area.y) as u32 * bitmap.height / area.height;
+            for x in visible.x..visible.right() {
+                let sx = (x - area.x) as u32 * bitmap.width / area.width;
+                if let Some(luma) = bitmap.pixel(sx, sy) {
+                    self.fill(PageRect::new(x, y, 1, 1), luma);
+                }
+            }
+        }
+    }
+
+    fn draw_text(&mut self, text: &TextCommand) {
+        let metrics = text.style.metrics;
+        let glyphs = text
+            .glyphs
+            .clone()
+            .unwrap_or_else(|| glyph_positions(&text.text, &text.style));
+        let baseline = text.baseline_y + metrics.ascent_px.round() as i32;
+        let ink = self.ink(text.style.color);
+        self.draw_greeked(&text.text, &glyphs, text.x, baseline, &metrics, ink);
+    }
+
+    fn draw_chrome(&mut self, chrome: &PageChromeCommand) {
+        let cfg = self.cfg.page_chrome;
+        let width = self.bitmap.width as i32;
+        let height = self.bitmap.height as i32;
+        let footer_y = height - cfg.footer_baseline_from_bottom;
+        let ink = self.cfg.ink;
+        let (enabled, x, y) = match chrome.kind {
+            PageChromeKind::Header => (cfg.header_enabled, cfg.header_x, cfg.header_baseline_y),
+            PageChromeKind::Footer => (cfg.footer_enabled, cfg.footer_x, footer_y),
+            PageChromeKind::BookProgress => {
+                (cfg.book_progress_enabled, width - cfg.footer_x, footer_y)
+            }
+            PageChromeKind::Progress => {
+                if !cfg.progress_enabled {
+                    return;
+                }
+                let current = chrome.current.unwrap_or(0);
+                let total = chrome.total.unwrap_or(1).max(1);
+                let bar_w = (width - cfg.progress_x_inset * 2).max(1) as u32;
+                let bar = PageRect::new(
+                    cfg.progress_x_inset,
+                    height - cfg.progress_y_from_bottom,
+                    bar_w,
+                    cfg.progress_height.max(1),
+                );
+                let filled = (bar_w as usize * current.min(total) / total) as u32;
+                for inset in 0..cfg.progress_stroke_width.max(1) {
+                    let ring = PageRect::new(
+                        bar.x + inset as i32,
+                        bar.y + inset as i32,
+                        bar.width.saturating_sub(inset * 2),
+                        bar.height.saturating_sub(inset * 2),
+                    );
+                    self.stroke(ring, ink);
+                }
+                self.fill(PageRect::new(bar.x, bar.y, filled, bar.height), ink);
+                return;
+            }
+        };
+        let Some(text) = chrome.text.as_deref().filter(|_| enabled) else {
+            return;
+        };
+        let advance = (CHROME_TEXT_PX * 0.6).round() as i32;
+        let glyphs = vec![
+            GlyphPosition {
+                x_advance: advance,
+                x_offset: 0,
+            };
+            text.chars().count()
+        ];
+        // Book progress is right-aligned on the footer line.
+        let x = if chrome.kind == PageChromeKind::BookProgress {
+            x - advance * glyphs.len() as i32
+        } else {
+            x
+        };
+        let metrics = VerticalMetrics::estimate(CHROME_TEXT_PX);
+        self.draw_greeked(text, &glyphs, x, y, &metrics, ink);
+    }
+
+    fn draw_greeked(
+        &mut self,
+        text: &str,
+        glyphs: &[GlyphPosition],
+        x: i32,
+        baseline: i32,
+        metrics: &VerticalMetrics,
+        luma: u8,
+    ) {
+        let mut pen = x;
+        for (ch, glyph) in text.chars().zip(glyphs) {
+            if !ch.is_whitespace() {
+                let (above, below) = glyph_band(ch, metrics);
+                let advance = glyph.x_advance.max(1);
+                // Leave a sidebearing gap so words read as glyph runs.
+                let gap = if advance > 2 { (advance / 6).max(1) } else { 0 };
+                let top = baseline - above.round() as i32;
+                let bottom = baseline + below.round() as i32;
+                self.fill(
+                    PageRect::new(
+                        pen + glyph.x_offset,
+                        top,
+                        (advance - gap) as u32,
+                        (bottom - top).max(1) as u32,
+                    ),
+                    luma,
+                );
+            }
+            pen += glyph.x_advance;
+        }
+    }
+}
+
+/// Ink extent of a greeked glyph above and below the baseline.
+fn glyph_band(ch: char, metrics: &VerticalMetrics) -> (f32, f32) {
+    let above = if ch.is_uppercase() || ch.is_ascii_digit() {
+        metrics.cap_height_px
+    } else if matches!(ch, 'b' | 'd' | 'f' | 'h' | 'k' | 'l' | 't') {
+        metrics.ascent_px * 0.9
+    } else if matches!(ch, '.' | ',' | '_') {
+        metrics.x_height_px / 4.0
+    } else {
+        metrics.x_height_px
+    };
+    let below = if matches!(ch, 'g' | 'j' | 'p' | 'q' | 'y' | ',') {
+        metrics.descent_px * 0.8
+    } else {
+        0.0
+    };
+    (above, below)
+}
+
+fn write_png_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    out.write_all(&crc.finalize().to_be_bytes())
+}
+
+/// zlib stream holding `data` in stored (uncompressed) deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(STORED_BLOCK_MAX).max(1);
+    let mut out = Vec::with_capacity(data.len() + blocks * 5 + 6);
+    out.extend_from_slice(&[0x78, 0x01]);
+    let mut chunks = data.chunks(STORED_BLOCK_MAX).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = u8::from(chunks.peek().is_none());
+        let len = chunk.len() as u16;
+        out.push(last);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65_521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 bytes is the longest run that cannot overflow before reducing.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_ir::{
+        JustifyMode, RectCommand, ResolvedTextStyle, TextRenderHint, VerticalMetrics,
+    };
+    use mu_epub::BlockRole;
+
+    fn text(x: i32, y: i32, text: &str) -> DrawCommand {
+        DrawCommand::Text(TextCommand {
+            x,
+            baseline_y: y,
+            text: text.to_string(),
+            font_id: None,
+            style: ResolvedTextStyle {
+                font_id: None,
+                family: "serif".to_string(),
+                weight: 400,
+                italic: false,
+                size_px: 20.0,
+                line_height: 1.2,
+                letter_spacing: 0.0,
+                role: BlockRole::Body,
+                justify_mode: JustifyMode::None,
+                color: None,
+                metrics: VerticalMetrics::estimate(20.0),
+            },
+            glyphs: Some(vec![
+                GlyphPosition {
+                    x_advance: 12,
+                    x_offset: 0,
+                };
+                text.chars().count()
+            ]),
+            hint: TextRenderHint::Aliased,
+        })
+    }
+
+    #[test]
+    fn greeked_text_and_gray_rects_rasterize_to_png_and_pgm() {
+        let mut page = RenderPage::new(1);
+        page.push_content_command(DrawCommand::Rect(RectCommand {
+            x: 50,
+            y: 0,
+            width: 10,
+            height: 10,
+            fill: true,
+            color: Some(Color::rgb(128, 128, 128)),
+        }));
+        page.push_content_command(text(0, 10, "hy x"));
+        page.sync_commands();
+
+        let cfg = RasterConfig::default();
+        let bitmap = page.rasterize(64, 48, &cfg);
+        assert_eq!(bitmap.pixels.len(), 64 * 48);
+        assert_eq!(bitmap.pixel(55, 5), Some(128));
+        // Baseline at y = 26: `h` rises above the x-height, `y` descends.
+        assert_eq!(bitmap.pixel(2, 13), Some(0));
+        assert_eq!(bitmap.pixel(14, 13), Some(255));
+        assert_eq!(bitmap.pixel(14, 28), Some(0));
+        assert_eq!(bitmap.pixel(2, 28), Some(255));
+        // Space and sidebearing stay paper.
+        assert_eq!(bitmap.pixel(30, 20), Some(255));
+        assert_eq!(bitmap.pixel(11, 20), Some(255));
+        assert_eq!(bitmap.pixel(40, 20), Some(0));
+
+        let mut pgm = Vec::with_capacity(0);
+        bitmap.write_pgm(&mut pgm).expect("in-memory write");
+        assert!(pgm.starts_with(b"P5\n64 48\n255\n"));
+        assert_eq!(pgm.len(), 13 + 64 * 48);
+
+        let mut png = Vec::with_capacity(0);
+        bitmap.write_png(&mut png).expect("in-memory write");
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert!(png.ends_with(&[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]));
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+}
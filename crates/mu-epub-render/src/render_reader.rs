@@ -0,0 +1,324 @@
+//! Reading state for several open books on one engine.
+
+use std::io::{Read, Seek};
+
+use mu_epub::EpubBook;
+
+use crate::render_engine::{
+    locator_chapter, RenderCacheStore, RenderConfig, RenderEngine, RenderEngineError,
+};
+use crate::render_ir::{ContentLocator, RenderPage};
+
+/// Book opened in a `ReaderSession`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BookHandle(u32);
+
+/// Several open books sharing one engine, each with its reading position.
+///
+/// The current chapter of every book is kept laid out so switching back is
+/// instant. Held pages stay within a byte budget: past it, the least
+/// recently read books drop their pages first and re-render from their
+/// saved position when read again. The book being read keeps its chapter
+/// even when that alone exceeds the budget.
+pub struct ReaderSession<R: Read + Seek> {
+    engine: RenderEngine,
+    /// Least recently read first; the last entry is the active book.
+    books: Vec<OpenBook<R>>,
+    page_budget_bytes: usize,
+    next_handle: u32,
+}
+
+struct OpenBook<R: Read + Seek> {
+    handle: BookHandle,
+    book: EpubBook<R>,
+    cache: Option<Box<dyn RenderCacheStore>>,
+    position: ContentLocator,
+    /// Laid-out chapter holding `position`, and the page index within it.
+    chapter: Option<(usize, Vec<RenderPage>)>,
+    page_index: usize,
+}
+
+impl<R: Read + Seek> core::fmt::Debug for ReaderSession<R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ReaderSession")
+            .field("books", &self.books.len())
+            .field("page_budget_bytes", &self.page_budget_bytes)
+            .field("held_bytes", &self.held_bytes())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R: Read + Seek> ReaderSession<R> {
+    /// Session on `engine`, budgeting held pages by the engine's
+    /// `max_page_bytes_in_memory`.
+    pub fn new(engine: RenderEngine) -> Self {
+        let page_budget_bytes = engine.options().prep.memory.max_page_bytes_in_memory;
+        Self {
+            engine,
+            books: Vec::with_capacity(4),
+            page_budget_bytes,
+            next_handle: 0,
+        }
+    }
+
+    /// Limit the bytes of pages held across all books.
+    pub fn with_page_budget(mut self, bytes: usize) -> Self {
+        self.page_budget_bytes = bytes;
+        self.enforce_budget();
+        self
+    }
+
+    /// Engine used for every book.
+    pub fn engine(&self) -> &RenderEngine {
+        &self.engine
+    }
+
+    /// Open `book` at `position` (the start when `None`) and make it active.
+    pub fn open(&mut self, book: EpubBook<R>, position: Option<ContentLocator>) -> BookHandle {
+        self.open_book(book, position, None)
+    }
+
+    /// `open` with a render cache used for this book's chapters.
+    pub fn open_with_cache(
+        &mut self,
+        book: EpubBook<R>,
+        position: Option<ContentLocator>,
+        cache: Box<dyn RenderCacheStore>,
+    ) -> BookHandle {
+        self.open_book(book, position, Some(cache))
+    }
+
+    fn open_book(
+        &mut self,
+        book: EpubBook<R>,
+        position: Option<ContentLocator>,
+        cache: Option<Box<dyn RenderCacheStore>>,
+    ) -> BookHandle {
+        let handle = BookHandle(self.next_handle);
+        self.next_handle = self.next_handle.wrapping_add(1);
+        self.books.push(OpenBook {
+            handle,
+            book,
+            cache,
+            position: position.unwrap_or_default(),
+            chapter: None,
+            page_index: 0,
+        });
+        handle
+    }
+
+    /// Close a book, returning it with its reading position.
+    pub fn close(&mut self, handle: BookHandle) -> Option<(EpubBook<R>, ContentLocator)> {
+        let index = self.index_of(handle)?;
+        let open = self.books.remove(index);
+        Some((open.book, open.position))
+    }
+
+    /// Make `handle` the active book; `false` when it is not open.
+    pub fn switch_to(&mut self, handle: BookHandle) -> bool {
+        let Some(index) = self.index_of(handle) else {
+            return false;
+        };
+        let open = self.books.remove(index);
+        self.books.push(open);
+        true
+    }
+
+    /// The book being read.
+    pub fn active(&self) -> Option<BookHandle> {
+        self.books.last().map(|open| open.handle)
+    }
+
+    /// Open books, most recently read first.
+    pub fn books(&self) -> impl Iterator<Item = BookHandle> + '_ {
+        self.books.iter().rev().map(|open| open.handle)
+    }
+
+    /// Saved reading position of a book.
+    pub fn position(&self, handle: BookHandle) -> Option<&ContentLocator> {
+        self.index_of(handle)
+            .map(|index| &self.books[index].position)
+    }
+
+    /// Mutable access to an open book, e.g. for metadata or navigation.
+    pub fn book_mut(&mut self, handle: BookHandle) -> Option<&mut EpubBook<R>> {
+        let index = self.index_of(handle)?;
+        Some(&mut self.books[index].book)
+    }
+
+    /// Bytes of laid-out pages held across all books.
+    pub fn held_bytes(&self) -> usize {
+        self.books.iter().map(OpenBook::held_bytes).sum()
+    }
+
+    /// Page at the active book's position, laying out its chapter if needed.
+    ///
+    /// `Ok(None)` when no book is open or the position's chapter no longer
+    /// exists.
+    pub fn current_page(&mut self) -> Result<Option<&RenderPage>, RenderEngineError> {
+        if !self.ensure_loaded()? {
+            return Ok(None);
+        }
+        Ok(self.books.last().and_then(OpenBook::page))
+    }
+
+    /// Advance the active book one page, crossing into later chapters.
+    ///
+    /// Stays on the last page of the book and returns `Ok(None)` there.
+    pub fn next_page(&mut self) -> Result<Option<&RenderPage>, RenderEngineError> {
+        if !self.ensure_loaded()? {
+            return Ok(None);
+        }
+        let moved = match self.books.last_mut() {
+            Some(open) => open.step(&self.engine, true)?,
+            None => false,
+        };
+        self.enforce_budget();
+        Ok(self.books.last().filter(|_| moved).and_then(OpenBook::page))
+    }
+
+    /// Step the active book back one page, crossing into earlier chapters.
+    ///
+    /// Stays on the first page of the book and returns `Ok(None)` there.
+    pub fn prev_page(&mut self) -> Result<Option<&RenderPage>, RenderEngineError> {
+        if !self.ensure_loaded()? {
+            return Ok(None);
+        }
+        let moved = match self.books.last_mut() {
+            Some(open) => open.step(&self.engine, false)?,
+            None => false,
+        };
+        self.enforce_budget();
+        Ok(self.books.last().filter(|_| moved).and_then(OpenBook::page))
+    }
+
+    /// Move the active book to `locator` and return the page holding it.
+    pub fn goto(
+        &mut self,
+        locator: &ContentLocator,
+    ) -> Result<Option<&RenderPage>, RenderEngineError> {
+        if let Some(open) = self.books.last_mut() {
+            open.position = locator.clone();
+            open.chapter = None;
+        }
+        self.current_page()
+    }
+
+    fn index_of(&self, handle: BookHandle) -> Option<usize> {
+        self.books.iter().position(|open| open.handle == handle)
+    }
+
+    /// Lay out the active book's chapter when its pages were dropped;
+    /// `false` when there is nothing to show.
+    fn ensure_loaded(&mut self) -> Result<bool, RenderEngineError> {
+        let Some(open) = self.books.last_mut() else {
+            return Ok(false);
+        };
+        if open.chapter.is_none() {
+            let Some(chapter_index) = locator_chapter(&open.book, &open.position) else {
+                return Ok(false);
+            };
+            let pages = open.layout(&self.engine, chapter_index)?;
+            let target = open.position.position;
+            open.page_index = pages
+                .iter()
+                .rposition(|page| {
+                    page.metrics
+                        .start_position
+                        .is_some_and(|start| start <= target)
+                })
+                .unwrap_or(0);
+            open.chapter = Some((chapter_index, pages));
+            self.enforce_budget();
+        }
+        Ok(self.books.last().and_then(OpenBook::page).is_some())
+    }
+
+    /// Drop pages of the least recently read books until within budget.
+    fn enforce_budget(&mut self) {
+        let mut held = self.held_bytes();
+        let inactive = self.books.len().saturating_sub(1);
+        for open in self.books.iter_mut().take(inactive) {
+            if held <= self.page_budget_bytes {
+                break;
+            }
+            held -= open.held_bytes();
+            open.chapter = None;
+        }
+    }
+}
+
+impl<R: Read + Seek> OpenBook<R> {
+    fn held_bytes(&self) -> usize {
+        self.chapter.as_ref().map_or(0, |(_, pages)| {
+            pages.iter().map(RenderPage::approx_bytes).sum()
+        })
+    }
+
+    fn page(&self) -> Option<&RenderPage> {
+        self.chapter
+            .as_ref()
+            .and_then(|(_, pages)| pages.get(self.page_index))
+    }
+
+    fn layout(
+        &mut self,
+        engine: &RenderEngine,
+        chapter_index: usize,
+    ) -> Result<Vec<RenderPage>, RenderEngineError> {
+        let mut config = RenderConfig::default();
+        if let Some(cache) = self.cache.as_deref() {
+            config = config.with_cache(cache);
+        }
+        engine.prepare_chapter_with_config_collect(&mut self.book, chapter_index, config)
+    }
+
+    /// Move one page forward or back, skipping empty chapters; returns
+    /// whether the position changed.
+    fn step(&mut self, engine: &RenderEngine, forward: bool) -> Result<bool, RenderEngineError> {
+        let Some((chapter_index, pages)) = self.chapter.as_ref() else {
+            return Ok(false);
+        };
+        let chapter_index = *chapter_index;
+        if forward && self.page_index + 1 < pages.len() {
+            self.page_index += 1;
+        } else if !forward && self.page_index > 0 {
+            self.page_index -= 1;
+        } else {
+            let mut next = chapter_index;
+            loop {
+                next = match forward {
+                    true if next + 1 < self.book.chapter_count() => next + 1,
+                    false if next > 0 => next - 1,
+                    _ => return Ok(false),
+                };
+                let pages = self.layout(engine, next)?;
+                if pages.is_empty() {
+                    continue;
+                }
+                self.page_index = if forward { 0 } else { pages.len() - 1 };
+                self.chapter = Some((next, pages));
+                break;
+            }
+        }
+        self.remember_position();
+        Ok(true)
+    }
+
+    fn remember_position(&mut self) {
+        let Some(page) = self.page() else {
+            return;
+        };
+        let chapter_index = page.metrics.chapter_index;
+        let mut position = ContentLocator::new(
+            chapter_index,
+            page.metrics.start_position.unwrap_or_default(),
+        );
+        position.chapter_href = self
+            .book
+            .chapters()
+            .find(|chapter| chapter.index == chapter_index)
+            .map(|chapter| chapter.href);
+        self.position = position;
+    }
+}
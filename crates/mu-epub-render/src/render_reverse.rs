@@ -0,0 +1,404 @@
+//! Reverse-fill pagination: pages laid out backwards from a position.
+
+use mu_epub::{StyledEvent, StyledEventOrRun, StyledRun};
+
+use crate::render_ir::{RenderPage, SvgMode, TextPosition};
+use crate::render_layout::{LayoutConfig, LayoutEngine, LayoutSession};
+
+const SOFT_HYPHEN: char = '\u{00AD}';
+
+/// Block and char counter mirroring `TextPosition` numbering in layout.
+#[derive(Clone, Copy, Debug, Default)]
+struct PositionCursor {
+    block_index: usize,
+    block_chars: usize,
+    block_used: bool,
+    heading_level: Option<u8>,
+    in_list: bool,
+    /// A heading just ended, so the next paragraph may skip its indent.
+    after_heading: bool,
+}
+
+impl PositionCursor {
+    /// Move to a new block unless the current one is still empty; returns
+    /// whether a block was opened.
+    fn begin_block(&mut self) -> bool {
+        if !self.block_used {
+            return false;
+        }
+        self.block_index += 1;
+        self.block_chars = 0;
+        self.block_used = false;
+        true
+    }
+
+    fn word(&mut self, word: &str) -> TextPosition {
+        let start = if self.block_used {
+            self.block_chars + 1
+        } else {
+            0
+        };
+        self.block_chars = start + word.chars().filter(|ch| *ch != SOFT_HYPHEN).count();
+        self.block_used = true;
+        TextPosition {
+            block_index: self.block_index,
+            char_offset: start,
+        }
+    }
+
+    /// Position of an image block, leaving the cursor on the next block.
+    fn image(&mut self) -> TextPosition {
+        self.begin_block();
+        let pos = TextPosition {
+            block_index: self.block_index,
+            char_offset: 0,
+        };
+        self.block_used = true;
+        self.begin_block();
+        pos
+    }
+
+    /// Apply an event; returns whether it opened a block.
+    fn event(&mut self, ev: &StyledEvent) -> bool {
+        match ev {
+            StyledEvent::ParagraphStart => {
+                self.after_heading = false;
+                self.begin_block()
+            }
+            StyledEvent::HeadingStart(level) => {
+                let opened = self.begin_block();
+                self.heading_level = Some((*level).clamp(1, 6));
+                opened
+            }
+            StyledEvent::HeadingEnd(_) => {
+                self.heading_level = None;
+                self.after_heading = true;
+                false
+            }
+            StyledEvent::ListItemStart => {
+                let opened = self.begin_block();
+                self.in_list = true;
+                opened
+            }
+            StyledEvent::ListItemEnd => {
+                self.in_list = false;
+                false
+            }
+            StyledEvent::ParagraphEnd | StyledEvent::LineBreak => false,
+        }
+    }
+}
+
+/// First item of a block and the cursor just before it.
+#[derive(Clone, Copy, Debug)]
+struct BlockOpen {
+    item: usize,
+    cursor: PositionCursor,
+}
+
+/// Lays out a chapter's styled items backwards from an anchor.
+///
+/// Each page is the longest run of content that ends right before the
+/// anchor and still fits one page, found by laying out candidate starts
+/// forward: first block by block, then word by word (binary search) in the
+/// block holding the break. Pages chain exactly, each ending where the
+/// following one starts, but the boundaries generally differ from forward
+/// pagination.
+pub(crate) struct ReverseFill {
+    layout: LayoutEngine,
+    items: Vec<StyledEventOrRun>,
+    blocks: Vec<BlockOpen>,
+    svg_ignored: bool,
+}
+
+impl ReverseFill {
+    pub(crate) fn new(cfg: LayoutConfig, items: Vec<StyledEventOrRun>) -> Self {
+        let svg_ignored = cfg.object_layout.svg_mode == SvgMode::Ignore;
+        let mut cursor = PositionCursor::default();
+        let mut blocks = vec![BlockOpen { item: 0, cursor }];
+        for (index, item) in items.iter().enumerate() {
+            let before = cursor;
+            match item {
+                StyledEventOrRun::Run(run) => {
+                    run.text.split_whitespace().for_each(|word| {
+                        cursor.word(word);
+                    });
+                }
+                StyledEventOrRun::Event(ev) => {
+                    if cursor.event(ev) {
+                        blocks.push(BlockOpen {
+                            item: index,
+                            cursor: before,
+                        });
+                    }
+                }
+                StyledEventOrRun::Image(image) => {
+                    if image.is_svg() && svg_ignored {
+                        continue;
+                    }
+                    if cursor.block_used {
+                        blocks.push(BlockOpen {
+                            item: index,
+                            cursor: before,
+                        });
+                    }
+                    cursor.image();
+                    // Text after an image starts a new block.
+                    blocks.push(BlockOpen {
+                        item: index + 1,
+                        cursor,
+                    });
+                }
+            }
+        }
+        Self {
+            layout: LayoutEngine::new(cfg),
+            items,
+            blocks,
+            svg_ignored,
+        }
+    }
+
+    /// The page ending right before `anchor` and its start position.
+    ///
+    /// `None` once `anchor` is at or before the first content.
+    pub(crate) fn page_before(&self, anchor: TextPosition) -> Option<(RenderPage, TextPosition)> {
+        let last_block = self.blocks.len().checked_sub(1)?;
+        let mut block = anchor.block_index.min(last_block);
+        if anchor.block_index <= last_block && anchor.char_offset == 0 {
+            block = block.checked_sub(1)?;
+        }
+        let mut later_start = None;
+        loop {
+            let start = block_start(block);
+            if start < anchor {
+                let pages = self.layout_range(start, anchor);
+                if pages.len() > 1 {
+                    break;
+                }
+                if block == 0 {
+                    let page = pages.into_iter().next()?;
+                    return Some((page, start));
+                }
+                if !pages.is_empty() {
+                    later_start = Some(start);
+                }
+            }
+            block = block.checked_sub(1)?;
+        }
+        // The page break falls inside `block`: find the earliest word from
+        // which the rest still fits one page.
+        let starts = self.content_starts(block, anchor);
+        let (mut lo, mut hi) = (1usize, starts.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.layout_range(starts[mid], anchor).len() > 1 {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        let start = starts.get(lo).copied().or(later_start)?;
+        let page = self.layout_range(start, anchor).into_iter().next()?;
+        Some((page, start))
+    }
+
+    /// Word and image positions of `block` before `anchor`.
+    fn content_starts(&self, block: usize, anchor: TextPosition) -> Vec<TextPosition> {
+        let mut starts = Vec::with_capacity(16);
+        self.walk(block, |pos| {
+            if pos.block_index != block || pos >= anchor {
+                return false;
+            }
+            starts.push(pos);
+            true
+        });
+        starts
+    }
+
+    /// Feed items from the opener of `block` to `visit` with each content
+    /// position, stopping when it returns `false`.
+    fn walk(&self, block: usize, mut visit: impl FnMut(TextPosition) -> bool) {
+        let Some(open) = self.blocks.get(block) else {
+            return;
+        };
+        let mut cursor = open.cursor;
+        for item in &self.items[open.item..] {
+            match item {
+                StyledEventOrRun::Run(run) => {
+                    for word in run.text.split_whitespace() {
+                        if !visit(cursor.word(word)) {
+                            return;
+                        }
+                    }
+                }
+                StyledEventOrRun::Event(ev) => {
+                    cursor.event(ev);
+                }
+                StyledEventOrRun::Image(image) => {
+                    if image.is_svg() && self.svg_ignored {
+                        continue;
+                    }
+                    if !visit(cursor.image()) {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Lay out content in `[from, to)` as a fresh page sequence.
+    pub(crate) fn layout_range(&self, from: TextPosition, to: TextPosition) -> Vec<RenderPage> {
+        let mut pages = Vec::with_capacity(2);
+        let Some(open) = self.blocks.get(from.block_index) else {
+            return pages;
+        };
+        let mut session = self.layout.start_session();
+        if from > TextPosition::default() {
+            session.continue_page();
+        }
+        if open.cursor.after_heading {
+            session.suppress_next_indent();
+        }
+        let opener = self.items.get(open.item);
+        if open.cursor.in_list
+            && !matches!(
+                opener,
+                Some(StyledEventOrRun::Event(StyledEvent::ListItemStart))
+            )
+        {
+            session.push_item(StyledEventOrRun::Event(StyledEvent::ListItemStart));
+        }
+        if let Some(level) = open.cursor.heading_level {
+            if !matches!(
+                opener,
+                Some(StyledEventOrRun::Event(StyledEvent::HeadingStart(_)))
+            ) {
+                session.push_item(StyledEventOrRun::Event(StyledEvent::HeadingStart(level)));
+            }
+        }
+        let mut cursor = open.cursor;
+        let mut resumed = false;
+        let mut resume = |session: &mut LayoutSession| {
+            if !resumed {
+                session.resume_at(from);
+                resumed = true;
+            }
+        };
+        'items: for item in &self.items[open.item..] {
+            match item {
+                StyledEventOrRun::Run(run) => {
+                    let mut kept = String::with_capacity(run.text.len());
+                    let mut reached_end = false;
+                    for word in run.text.split_whitespace() {
+                        let pos = cursor.word(word);
+                        if pos >= to {
+                            reached_end = true;
+                            break;
+                        }
+                        if pos >= from {
+                            if !kept.is_empty() {
+                                kept.push(' ');
+                            }
+                            kept.push_str(word);
+                        }
+                    }
+                    if !kept.is_empty() {
+                        resume(&mut session);
+                        session.push_item(StyledEventOrRun::Run(StyledRun {
+                            text: kept,
+                            ..run.clone()
+                        }));
+                    }
+                    if reached_end {
+                        break 'items;
+                    }
+                }
+                StyledEventOrRun::Event(ev) => {
+                    cursor.event(ev);
+                    session.push_item(item.clone());
+                }
+                StyledEventOrRun::Image(image) => {
+                    if image.is_svg() && self.svg_ignored {
+                        continue;
+                    }
+                    let pos = cursor.image();
+                    if pos >= to {
+                        break 'items;
+                    }
+                    if pos >= from {
+                        resume(&mut session);
+                        session.push_item(item.clone());
+                    }
+                }
+            }
+        }
+        session.finish(&mut |page| pages.push(page));
+        pages
+    }
+}
+
+fn block_start(block: usize) -> TextPosition {
+    TextPosition {
+        block_index: block,
+        char_offset: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mu_epub::{BlockRole, ComputedTextStyle};
+
+    fn run(text: &str) -> StyledEventOrRun {
+        StyledEventOrRun::Run(StyledRun {
+            text: text.to_string(),
+            style: ComputedTextStyle {
+                family_stack: vec!["serif".to_string()],
+                weight: 400,
+                italic: false,
+                size_px: 16.0,
+                line_height: 1.4,
+                letter_spacing: 0.0,
+                block_role: BlockRole::Body,
+                color: None,
+            },
+            font_id: 0,
+            resolved_family: "serif".to_string(),
+            link: None,
+            anchors: Vec::with_capacity(0),
+        })
+    }
+
+    #[test]
+    fn reverse_pages_chain_back_to_chapter_start_without_losing_words() {
+        let cfg = LayoutConfig::for_display(320, 240);
+        let layout = LayoutEngine::new(cfg);
+        let mut items = Vec::with_capacity(0);
+        for paragraph in 0..12 {
+            items.push(StyledEventOrRun::Event(StyledEvent::ParagraphStart));
+            items.push(run(&format!("p{} ", paragraph).repeat(20 + paragraph * 7)));
+            items.push(StyledEventOrRun::Event(StyledEvent::ParagraphEnd));
+        }
+        let forward = layout.layout_items(items.clone());
+        let fill = ReverseFill::new(cfg, items);
+
+        let mut anchor = TextPosition {
+            block_index: usize::MAX,
+            char_offset: 0,
+        };
+        let mut words = 0usize;
+        let mut pages = 0usize;
+        while let Some((page, start)) = fill.page_before(anchor) {
+            assert!(start < anchor);
+            assert_eq!(page.metrics.start_position, Some(start));
+            words += page.metrics.word_count;
+            pages += 1;
+            anchor = start;
+        }
+        assert_eq!(anchor, TextPosition::default());
+        let forward_words: usize = forward.iter().map(|page| page.metrics.word_count).sum();
+        assert_eq!(words, forward_words);
+        assert!(pages.abs_diff(forward.len()) <= 1);
+    }
+}
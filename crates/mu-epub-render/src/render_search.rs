@@ -0,0 +1,209 @@
+//! Case- and diacritic-folding text search over laid-out pages.
+
+use core::ops::Range;
+
+use crate::render_highlight::SearchHit;
+use crate::render_ir::RenderPage;
+
+/// Matching policy for text search.
+///
+/// The default ignores both letter case and accents.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Match letter case exactly.
+    pub case_sensitive: bool,
+    /// Match accents exactly (`é` only matches `é`).
+    pub diacritic_sensitive: bool,
+}
+
+/// Search hit located in the book.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BookSearchHit {
+    /// Spine index of the chapter.
+    pub chapter_index: usize,
+    /// Zero-based page index within the chapter.
+    pub chapter_page_index: usize,
+    /// Matched range and rectangles on that page.
+    pub hit: SearchHit,
+}
+
+impl RenderPage {
+    /// Char ranges of `plain_text` matching `query`.
+    ///
+    /// Whitespace runs in the query match any whitespace run, including line
+    /// breaks. Matches do not overlap. Words hyphenated across lines and
+    /// phrases spanning pages are not found.
+    pub fn find_text(&self, query: &str, options: SearchOptions) -> Vec<Range<usize>> {
+        find_matches(&self.plain_text(), query, options)
+    }
+}
+
+/// Whether `text` can contain `query`, ignoring all whitespace.
+///
+/// Used as a cheap pre-layout filter: extracted chapter text and laid-out
+/// lines disagree on whitespace, but never on the non-space chars.
+pub(crate) fn may_contain(text: &str, query: &str, options: SearchOptions) -> bool {
+    let needle: Vec<char> = fold(query, options, false)
+        .into_iter()
+        .map(|(ch, _)| ch)
+        .collect();
+    if needle.is_empty() {
+        return false;
+    }
+    let hay: Vec<char> = fold(text, options, false)
+        .into_iter()
+        .map(|(ch, _)| ch)
+        .collect();
+    hay.windows(needle.len()).any(|window| window == needle)
+}
+
+fn find_matches(text: &str, query: &str, options: SearchOptions) -> Vec<Range<usize>> {
+    let needle: Vec<char> = fold(query, options, true)
+        .into_iter()
+        .map(|(ch, _)| ch)
+        .collect();
+    let mut matches = Vec::with_capacity(0);
+    if needle.is_empty() {
+        return matches;
+    }
+    let hay = fold(text, options, true);
+    let mut idx = 0usize;
+    while idx + needle.len() <= hay.len() {
+        let window = &hay[idx..idx + needle.len()];
+        if window.iter().map(|(ch, _)| ch).eq(needle.iter()) {
+            let start = window[0].1;
+            let end = window[needle.len() - 1].1 + 1;
+            matches.push(start..end);
+            idx += needle.len();
+        } else {
+            idx += 1;
+        }
+    }
+    matches
+}
+
+/// Folded chars paired with the char index they came from.
+///
+/// With `spaces`, whitespace runs collapse to one `' '` and are trimmed;
+/// otherwise whitespace is dropped.
+fn fold(text: &str, options: SearchOptions, spaces: bool) -> Vec<(char, usize)> {
+    let mut out: Vec<(char, usize)> = Vec::with_capacity(text.len());
+    for (idx, ch) in text.chars().enumerate() {
+        if ch.is_whitespace() {
+            if spaces && out.last().is_some_and(|(prev, _)| *prev != ' ') {
+                out.push((' ', idx));
+            }
+            continue;
+        }
+        if let Some(ch) = fold_char(ch, options) {
+            out.push((ch, idx));
+        }
+    }
+    if out.last().is_some_and(|(ch, _)| *ch == ' ') {
+        out.pop();
+    }
+    out
+}
+
+fn fold_char(ch: char, options: SearchOptions) -> Option<char> {
+    if ch == '\u{00AD}' {
+        return None;
+    }
+    let mut ch = ch;
+    if !options.diacritic_sensitive {
+        if ('\u{0300}'..='\u{036F}').contains(&ch) {
+            return None;
+        }
+        ch = strip_diacritic(ch);
+    }
+    if !options.case_sensitive {
+        let mut lower = ch.to_lowercase();
+        if let (Some(single), None) = (lower.next(), lower.next()) {
+            ch = single;
+        }
+    }
+    Some(ch)
+}
+
+/// Base letters for U+0100..=U+017F; `*` keeps the char (ligatures, kra,
+/// eng, long s).
+const LATIN_EXTENDED_A: &[u8] = concat!(
+    "AaAaAa",
+    "CcCcCcCc",
+    "DdDd",
+    "EeEeEeEeEe",
+    "GgGgGgGg",
+    "HhHh",
+    "IiIiIiIiIi",
+    "**",
+    "Jj",
+    "Kk*",
+    "LlLlLlLlLl",
+    "NnNnNn***",
+    "OoOoOo",
+    "**",
+    "RrRrRr",
+    "SsSsSsSs",
+    "TtTtTt",
+    "UuUuUuUuUuUu",
+    "Ww",
+    "YyY",
+    "ZzZzZz",
+    "*",
+)
+.as_bytes();
+
+const _: () = assert!(LATIN_EXTENDED_A.len() == 0x80);
+
+/// Accented Latin letter to its base letter; other chars are unchanged.
+fn strip_diacritic(ch: char) -> char {
+    match ch {
+        'À'..='Å' => 'A',
+        'Ç' => 'C',
+        'È'..='Ë' => 'E',
+        'Ì'..='Ï' => 'I',
+        'Ñ' => 'N',
+        'Ò'..='Ö' | 'Ø' => 'O',
+        'Ù'..='Ü' => 'U',
+        'Ý' => 'Y',
+        'à'..='å' => 'a',
+        'ç' => 'c',
+        'è'..='ë' => 'e',
+        'ì'..='ï' => 'i',
+        'ñ' => 'n',
+        'ò'..='ö' | 'ø' => 'o',
+        'ù'..='ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        '\u{0100}'..='\u{017F}' => match LATIN_EXTENDED_A.get(ch as usize - 0x100) {
+            Some(b'*') | None => ch,
+            Some(base) => *base as char,
+        },
+        _ => ch,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_fold_case_diacritics_and_line_breaks() {
+        let text = "Café au lait\nwith CAFE creme, Łódź";
+        let loose = SearchOptions::default();
+        assert_eq!(find_matches(text, "cafe", loose), vec![0..4, 18..22]);
+        assert_eq!(find_matches(text, "lait  with", loose), vec![8..17]);
+        assert_eq!(find_matches(text, "lodz", loose), vec![30..34]);
+        assert_eq!(find_matches("Cafe\u{0301}!", "café", loose), vec![0..4]);
+
+        let strict = SearchOptions {
+            case_sensitive: true,
+            diacritic_sensitive: true,
+        };
+        assert_eq!(find_matches(text, "Café", strict), vec![0..4]);
+        assert!(find_matches(text, "cafe", strict).is_empty());
+        assert!(find_matches(text, "  ", loose).is_empty());
+
+        assert!(may_contain("au laitwith", "lait with", loose));
+        assert!(!may_contain("lait", "lait with", loose));
+    }
+}
@@ -0,0 +1,249 @@
+//! Sentence segmentation of laid-out pages for text-to-speech.
+
+use core::ops::Range;
+
+use crate::render_ir::{PageRect, RenderPage, TextPosition};
+
+/// Chars that end a sentence.
+const TERMINATORS: &[char] = &['.', '!', '?', '…'];
+
+/// Closing punctuation that may trail a terminator (`"Stop!"`).
+const CLOSERS: &[char] = &['"', '\'', '”', '’', '»', ')', ']'];
+
+/// Lowercase words whose trailing `.` does not end a sentence.
+const ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "st", "prof", "jr", "sr", "vs", "no", "e.g", "i.e", "cf",
+];
+
+/// Sentence of page text, ready to be spoken.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpeechSegment {
+    /// Char range of `RenderPage::plain_text`, for `highlight_text`.
+    pub range: Range<usize>,
+    /// Text to speak: whitespace collapsed, words hyphenated across lines
+    /// rejoined.
+    pub text: String,
+    /// Highlight rectangles, one per covered line span.
+    pub rects: Vec<PageRect>,
+    /// Source position of the first char, when line positions were recorded.
+    pub start: Option<TextPosition>,
+    /// True for a trailing sentence without closing punctuation, which may
+    /// continue on the next page.
+    pub open_end: bool,
+}
+
+/// How a page line joins the next one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LineJoin {
+    /// Wrapped between words.
+    Space,
+    /// Word broken at a soft hyphen; the `-` was added by layout.
+    Hyphen,
+    /// Next line starts a new block.
+    Block,
+}
+
+impl RenderPage {
+    /// Sentences on this page, in reading order.
+    ///
+    /// Block boundaries always end a sentence. Line breaks are classified
+    /// from recorded line positions; without them every break reads as a
+    /// space. A sentence cut by the page edge is split, with the first part
+    /// marked `open_end`.
+    pub fn speech_segments(&self) -> Vec<SpeechSegment> {
+        let joins = self.line_joins();
+        let mut segments = Vec::with_capacity(0);
+        let mut builder = SegmentBuilder::default();
+        let mut line = 0usize;
+        for (idx, ch) in self.plain_text().chars().enumerate() {
+            if ch == '\n' {
+                let join = joins.get(line).copied().unwrap_or(LineJoin::Space);
+                line += 1;
+                match join {
+                    LineJoin::Hyphen => {
+                        builder.text.pop();
+                        continue;
+                    }
+                    LineJoin::Block => {
+                        segments.extend(builder.finish(self, false));
+                        continue;
+                    }
+                    LineJoin::Space => {}
+                }
+            }
+            if ch.is_whitespace() {
+                if ends_sentence(&builder.text) {
+                    segments.extend(builder.finish(self, false));
+                }
+                builder.space = true;
+                continue;
+            }
+            builder.push(idx, ch);
+        }
+        let open_end = !ends_sentence(&builder.text);
+        segments.extend(builder.finish(self, open_end));
+        segments
+    }
+
+    /// Join kind after each line that has a recorded successor.
+    fn line_joins(&self) -> Vec<LineJoin> {
+        let spans: Vec<_> = self.line_spans().collect();
+        let text = self.plain_text();
+        let lines: Vec<&str> = text.split('\n').collect();
+        spans
+            .windows(2)
+            .zip(&lines)
+            .map(|(pair, line)| {
+                let (_, len, here) = pair[0];
+                let (_, _, next) = pair[1];
+                if next.block_index != here.block_index {
+                    LineJoin::Block
+                } else if line.ends_with('-') && next.char_offset + 1 == here.char_offset + len {
+                    LineJoin::Hyphen
+                } else {
+                    LineJoin::Space
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct SegmentBuilder {
+    range: Option<Range<usize>>,
+    text: String,
+    space: bool,
+}
+
+impl SegmentBuilder {
+    fn push(&mut self, idx: usize, ch: char) {
+        match self.range.as_mut() {
+            Some(range) => {
+                if self.space {
+                    self.text.push(' ');
+                }
+                range.end = idx + 1;
+            }
+            None => self.range = Some(idx..idx + 1),
+        }
+        self.space = false;
+        self.text.push(ch);
+    }
+
+    fn finish(&mut self, page: &RenderPage, open_end: bool) -> Option<SpeechSegment> {
+        let builder = core::mem::take(self);
+        let range = builder.range?;
+        Some(SpeechSegment {
+            rects: page.text_range_rects(core::slice::from_ref(&range)),
+            start: page.position_at(range.start),
+            range,
+            text: builder.text,
+            open_end,
+        })
+    }
+}
+
+/// Whether `text` ends with sentence-final punctuation that is not part of
+/// an abbreviation or initial.
+fn ends_sentence(text: &str) -> bool {
+    let trimmed = text.trim_end_matches(CLOSERS);
+    let Some(last) = trimmed.chars().last() else {
+        return false;
+    };
+    if !TERMINATORS.contains(&last) {
+        return false;
+    }
+    if last != '.' {
+        return true;
+    }
+    let word = trimmed[..trimmed.len() - 1]
+        .rsplit(' ')
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches(|ch: char| !ch.is_alphanumeric());
+    let is_initial = word.chars().count() == 1 && word.chars().all(char::is_uppercase);
+    !is_initial && !ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_ir::{
+        DrawCommand, JustifyMode, ResolvedTextStyle, TextCommand, TextRenderHint, VerticalMetrics,
+    };
+    use mu_epub::BlockRole;
+
+    fn line(y: i32, text: &str) -> DrawCommand {
+        DrawCommand::Text(TextCommand {
+            x: 10,
+            baseline_y: y,
+            text: text.to_string(),
+            font_id: None,
+            style: ResolvedTextStyle {
+                font_id: None,
+                family: "serif".to_string(),
+                weight: 400,
+                italic: false,
+                size_px: 20.0,
+                line_height: 1.2,
+                letter_spacing: 0.0,
+                role: BlockRole::Body,
+                justify_mode: JustifyMode::None,
+                color: None,
+                metrics: VerticalMetrics::estimate(20.0),
+            },
+            glyphs: None,
+            hint: TextRenderHint::Aliased,
+        })
+    }
+
+    fn position(block_index: usize, char_offset: usize) -> TextPosition {
+        TextPosition {
+            block_index,
+            char_offset,
+        }
+    }
+
+    #[test]
+    fn sentences_rejoin_hyphenated_words_and_break_at_blocks() {
+        let mut page = RenderPage::new(1);
+        for (idx, text) in [
+            "Chapter One",
+            "Mr. Hyde met Dr. Je-",
+            "kyll. \"Why?\" he",
+            "asked. Pre-",
+            "and post-war",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            page.push_content_command(line(30 * idx as i32, text));
+        }
+        page.line_positions = vec![
+            position(0, 0),
+            position(1, 0),
+            position(1, 19),
+            position(1, 35),
+            position(1, 47),
+        ];
+        page.sync_commands();
+
+        let segments = page.speech_segments();
+        let texts: Vec<&str> = segments.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            [
+                "Chapter One",
+                "Mr. Hyde met Dr. Jekyll.",
+                "\"Why?\"",
+                "he asked.",
+                "Pre- and post-war",
+            ]
+        );
+        assert_eq!(segments[1].range, 12..38);
+        assert_eq!(segments[1].rects.len(), 2);
+        assert_eq!(segments[1].start, Some(position(1, 0)));
+        assert!(segments.iter().rev().skip(1).all(|s| !s.open_end));
+        assert!(segments[4].open_end);
+    }
+}
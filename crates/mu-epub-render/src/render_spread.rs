@@ -0,0 +1,182 @@
+//! Two-page spreads for landscape and dual-screen displays.
+
+use crate::render_ir::{DrawCommand, PageChromeKind, RenderPage};
+
+/// Facing-page layout settings; see `RenderEngineOptions::spread`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SpreadOptions {
+    /// Horizontal gap between the two pages, in pixels.
+    pub gutter: i32,
+    /// Side each chapter starts on unless its spine item sets
+    /// `page-spread-left` or `page-spread-right`.
+    pub chapter_start: SpreadStart,
+}
+
+impl SpreadOptions {
+    /// Width of one page within a spread `spread_width` pixels wide.
+    pub fn page_width(&self, spread_width: i32) -> i32 {
+        ((spread_width - self.gutter) / 2).max(1)
+    }
+}
+
+/// Where a chapter's first page lands in a left-to-right spread.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpreadStart {
+    /// Right-hand page, leaving the left blank when needed.
+    #[default]
+    Recto,
+    /// Left-hand page.
+    Verso,
+}
+
+/// Pair of facing pages with chrome shared across both.
+///
+/// Page-chrome markers are moved off the pages: the header comes from the
+/// earlier page, footer and progress from the later one. Other chrome
+/// drawing (e.g. bookmark ribbons) stays on its page.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderSpread {
+    /// Left-hand page, drawn at x = 0.
+    pub left: Option<RenderPage>,
+    /// Right-hand page, drawn at x = `right_x`.
+    pub right: Option<RenderPage>,
+    /// x offset of the right-hand page.
+    pub right_x: i32,
+    /// Shared `PageChrome` markers for the whole spread.
+    pub chrome_commands: Vec<DrawCommand>,
+}
+
+impl RenderSpread {
+    /// Present pages, left first.
+    pub fn pages(&self) -> impl Iterator<Item = &RenderPage> {
+        self.left.iter().chain(self.right.iter())
+    }
+}
+
+/// Side forced by a spine item's `properties`, if any.
+///
+/// `true` means the right-hand page.
+pub(crate) fn spine_spread_side(properties: Option<&str>) -> Option<bool> {
+    properties?.split_ascii_whitespace().find_map(|token| {
+        match token.trim_start_matches("rendition:") {
+            "page-spread-left" => Some(false),
+            "page-spread-right" => Some(true),
+            _ => None,
+        }
+    })
+}
+
+/// Pair chapter pages into spreads, starting on the right when
+/// `start_right`.
+pub(crate) fn pair_pages(
+    pages: Vec<RenderPage>,
+    start_right: bool,
+    right_x: i32,
+) -> Vec<RenderSpread> {
+    let mut spreads = Vec::with_capacity(pages.len() / 2 + 1);
+    let mut pages = pages.into_iter();
+    let mut first = true;
+    loop {
+        let left = if first && start_right {
+            None
+        } else {
+            pages.next()
+        };
+        first = false;
+        let right = pages.next();
+        if left.is_none() && right.is_none() {
+            break;
+        }
+        spreads.push(share_chrome(left, right, right_x));
+    }
+    spreads
+}
+
+fn share_chrome(
+    mut left: Option<RenderPage>,
+    mut right: Option<RenderPage>,
+    right_x: i32,
+) -> RenderSpread {
+    let earlier = take_page_chrome(left.as_mut());
+    let later = take_page_chrome(right.as_mut());
+    let (earlier, later) = match (earlier, later) {
+        (Some(earlier), Some(later)) => (earlier, later),
+        (Some(only), None) | (None, Some(only)) => (only.clone(), only),
+        (None, None) => (Vec::with_capacity(0), Vec::with_capacity(0)),
+    };
+    let chrome_commands = earlier
+        .into_iter()
+        .filter(is_header)
+        .chain(later.into_iter().filter(|cmd| !is_header(cmd)))
+        .collect();
+    RenderSpread {
+        left,
+        right,
+        right_x,
+        chrome_commands,
+    }
+}
+
+fn is_header(cmd: &DrawCommand) -> bool {
+    matches!(cmd, DrawCommand::PageChrome(chrome) if chrome.kind == PageChromeKind::Header)
+}
+
+/// Remove and return a page's `PageChrome` markers.
+fn take_page_chrome(page: Option<&mut RenderPage>) -> Option<Vec<DrawCommand>> {
+    let page = page?;
+    let (markers, rest) = core::mem::take(&mut page.chrome_commands)
+        .into_iter()
+        .partition(|cmd| matches!(cmd, DrawCommand::PageChrome(_)));
+    page.chrome_commands = rest;
+    page.sync_commands();
+    Some(markers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_ir::PageChromeCommand;
+
+    fn page(number: usize) -> RenderPage {
+        let mut page = RenderPage::new(number);
+        for kind in [PageChromeKind::Header, PageChromeKind::Footer] {
+            page.push_chrome_command(DrawCommand::PageChrome(PageChromeCommand {
+                kind,
+                text: None,
+                current: Some(number),
+                total: Some(3),
+            }));
+        }
+        page.sync_commands();
+        page
+    }
+
+    #[test]
+    fn recto_start_leaves_left_blank_and_shares_chrome() {
+        let pages = vec![page(1), page(2), page(3)];
+        let spreads = pair_pages(pages, true, 310);
+        assert_eq!(spreads.len(), 2);
+        assert!(spreads[0].left.is_none());
+        assert_eq!(spreads[0].right.as_ref().map(|p| p.page_number), Some(1));
+        assert_eq!(spreads[1].pages().count(), 2);
+
+        let current: Vec<Option<usize>> = spreads[1]
+            .chrome_commands
+            .iter()
+            .map(|cmd| match cmd {
+                DrawCommand::PageChrome(chrome) => chrome.current,
+                _ => None,
+            })
+            .collect();
+        assert_eq!(current, [Some(2), Some(3)]);
+        assert!(spreads[1].pages().all(|p| p.chrome_commands.is_empty()));
+        assert!(spreads[1].pages().all(|p| p.commands.is_empty()));
+
+        assert_eq!(
+            spine_spread_side(Some("rendition:page-spread-left")),
+            Some(false)
+        );
+        assert_eq!(spine_spread_side(Some("svg page-spread-right")), Some(true));
+        assert_eq!(spine_spread_side(None), None);
+    }
+}
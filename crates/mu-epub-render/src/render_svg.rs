@@ -0,0 +1,99 @@
+//! SVG rasterization behind the `svg-raster` feature.
+
+use crate::render_ir::GrayBitmap;
+
+/// Intrinsic SVG size in CSS pixels, when the markup can be parsed.
+#[cfg(feature = "svg-raster")]
+pub(crate) fn svg_intrinsic_size(markup: &[u8]) -> Option<(f32, f32)> {
+    let tree = resvg::usvg::Tree::from_data(markup, &resvg::usvg::Options::default()).ok()?;
+    let size = tree.size();
+    Some((size.width(), size.height()))
+}
+
+/// Intrinsic SVG size in CSS pixels, when the markup can be parsed.
+#[cfg(not(feature = "svg-raster"))]
+pub(crate) fn svg_intrinsic_size(_markup: &[u8]) -> Option<(f32, f32)> {
+    None
+}
+
+/// Rasterize SVG markup into a `width x height` grayscale bitmap.
+///
+/// The image is scaled to fit, centered, and composited over white. Returns
+/// `None` when the output exceeds `max_pixels` or the markup cannot be parsed.
+#[cfg(feature = "svg-raster")]
+pub(crate) fn rasterize_svg(
+    markup: &[u8],
+    width: u32,
+    height: u32,
+    max_pixels: usize,
+) -> Option<GrayBitmap> {
+    use resvg::tiny_skia::{Pixmap, Transform};
+
+    let pixel_count = (width as usize).checked_mul(height as usize)?;
+    if pixel_count == 0 || pixel_count > max_pixels {
+        return None;
+    }
+    let tree = resvg::usvg::Tree::from_data(markup, &resvg::usvg::Options::default()).ok()?;
+    let size = tree.size();
+    let scale = (width as f32 / size.width()).min(height as f32 / size.height());
+    if !scale.is_finite() || scale <= 0.0 {
+        return None;
+    }
+    let dx = (width as f32 - size.width() * scale) / 2.0;
+    let dy = (height as f32 - size.height() * scale) / 2.0;
+    let mut pixmap = Pixmap::new(width, height)?;
+    resvg::render(
+        &tree,
+        Transform::from_row(scale, 0.0, 0.0, scale, dx, dy),
+        &mut pixmap.as_mut(),
+    );
+
+    let mut pixels = Vec::with_capacity(pixel_count);
+    for px in pixmap.pixels() {
+        // Premultiplied RGBA over a white page.
+        let backdrop = 255 - px.alpha() as u32;
+        let r = px.red() as u32 + backdrop;
+        let g = px.green() as u32 + backdrop;
+        let b = px.blue() as u32 + backdrop;
+        pixels.push(((r * 299 + g * 587 + b * 114) / 1000).min(255) as u8);
+    }
+    Some(GrayBitmap {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// Rasterize SVG markup into a `width x height` grayscale bitmap.
+///
+/// Always `None` without the `svg-raster` feature.
+#[cfg(not(feature = "svg-raster"))]
+pub(crate) fn rasterize_svg(
+    _markup: &[u8],
+    _width: u32,
+    _height: u32,
+    _max_pixels: usize,
+) -> Option<GrayBitmap> {
+    None
+}
+
+#[cfg(all(test, feature = "svg-raster"))]
+mod tests {
+    use super::*;
+
+    const SQUARE: &[u8] = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect x="0" y="0" width="5" height="10" fill="black"/></svg>"#;
+
+    #[test]
+    fn rasterize_svg_composites_over_white() {
+        let bitmap = rasterize_svg(SQUARE, 10, 10, 1024).expect("svg should rasterize");
+        assert_eq!(bitmap.pixels.len(), 100);
+        assert_eq!(bitmap.pixel(1, 5), Some(0));
+        assert_eq!(bitmap.pixel(8, 5), Some(255));
+        assert_eq!(svg_intrinsic_size(SQUARE), Some((10.0, 10.0)));
+    }
+
+    #[test]
+    fn rasterize_svg_respects_pixel_budget() {
+        assert!(rasterize_svg(SQUARE, 100, 100, 1024).is_none());
+    }
+}
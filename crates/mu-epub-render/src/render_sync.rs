@@ -0,0 +1,65 @@
+//! Portable reading positions for device/companion-app sync.
+
+use crate::render_bookmark::{unix_now_secs, Bookmark};
+use crate::render_cache::{
+    decode_sync_position, encode_sync_position, BookFingerprint, CacheDecodeError,
+};
+use crate::render_ir::ContentLocator;
+
+/// Portable reading position for syncing between a device and a companion
+/// app.
+///
+/// Pairs a reflow-stable [`ContentLocator`] (with its optional EPUB CFI)
+/// with the book it belongs to and when it was recorded, so either side can
+/// resolve it under its own layout settings and keep the newest. Bookmarks
+/// travel as one position each ([`SyncPosition::for_bookmark`]);
+/// highlights as a start/end pair.
+///
+/// ```text
+/// magic "MURP" | version u16 | book [u8; 32] | locator | updated u64 | crc32 u32
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SyncPosition {
+    /// Book the locator points into.
+    pub book: BookFingerprint,
+    /// Position within the book; `locator.cfi` carries the CFI, if known.
+    pub locator: ContentLocator,
+    /// When the position was recorded, in seconds since the Unix epoch.
+    pub updated_unix_secs: u64,
+}
+
+impl SyncPosition {
+    /// Position recorded now.
+    pub fn new(book: BookFingerprint, locator: ContentLocator) -> Self {
+        Self {
+            book,
+            locator,
+            updated_unix_secs: unix_now_secs(),
+        }
+    }
+
+    /// Position of `bookmark`, timestamped with its creation time.
+    pub fn for_bookmark(book: BookFingerprint, bookmark: &Bookmark) -> Self {
+        Self {
+            book,
+            locator: bookmark.locator.clone(),
+            updated_unix_secs: bookmark.created_unix_secs,
+        }
+    }
+
+    /// Whether this position belongs to the same book as `other` and was
+    /// recorded after it; the rule sync uses to pick a winner.
+    pub fn supersedes(&self, other: &SyncPosition) -> bool {
+        self.book == other.book && self.updated_unix_secs > other.updated_unix_secs
+    }
+
+    /// Encode with a trailing CRC-32.
+    pub fn encode(&self) -> Vec<u8> {
+        encode_sync_position(self)
+    }
+
+    /// Decode a position written by `encode`.
+    pub fn decode(bytes: &[u8]) -> Result<Self, CacheDecodeError> {
+        decode_sync_position(bytes)
+    }
+}
@@ -0,0 +1,255 @@
+//! Low-fidelity page thumbnails for page-grid navigation.
+
+use mu_epub::Color;
+
+use crate::render_engine::RenderEngineOptions;
+use crate::render_ir::{
+    DrawCommand, ImageCommand, LayeredCommand, PageRect, RectCommand, RenderPage, RuleCommand,
+    SvgMode,
+};
+
+/// Thumbnail output settings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ThumbnailConfig {
+    /// Maximum thumbnail width in pixels.
+    pub max_width: u32,
+    /// Maximum thumbnail height in pixels.
+    pub max_height: u32,
+    /// Fill color of the bars standing in for text lines.
+    pub text_color: Color,
+    /// Keep page chrome markers (header/footer/progress).
+    pub include_chrome: bool,
+}
+
+impl Default for ThumbnailConfig {
+    fn default() -> Self {
+        Self {
+            max_width: 120,
+            max_height: 160,
+            text_color: Color::rgb(96, 96, 96),
+            include_chrome: false,
+        }
+    }
+}
+
+impl ThumbnailConfig {
+    /// Uniform scale that fits a `width`x`height` viewport into the thumbnail box.
+    pub fn scale_for(&self, width: i32, height: i32) -> f32 {
+        let sx = self.max_width as f32 / width.max(1) as f32;
+        let sy = self.max_height as f32 / height.max(1) as f32;
+        sx.min(sy).min(1.0)
+    }
+
+    /// Engine options that lay out directly at thumbnail size.
+    ///
+    /// Panel geometry and font-size hints shrink by `scale`, and SVG
+    /// rasterization falls back to reserved boxes.
+    pub(crate) fn layout_options(
+        &self,
+        opts: &RenderEngineOptions,
+        scale: f32,
+    ) -> RenderEngineOptions {
+        let mut opts = *opts;
+        opts.layout = opts.layout.scaled(scale);
+        if opts.layout.object_layout.svg_mode == SvgMode::Rasterize {
+            opts.layout.object_layout.svg_mode = SvgMode::RasterizeFallback;
+        }
+        opts.dpi *= scale;
+        let hints = &mut opts.prep.style.hints;
+        hints.base_font_size_px *= scale;
+        hints.min_font_size_px *= scale;
+        hints.max_font_size_px *= scale;
+        hints.text_scale *= scale;
+        opts
+    }
+}
+
+impl RenderPage {
+    /// Build a thumbnail of a page laid out on a `width`x`height` viewport.
+    ///
+    /// Text lines become filled bars, geometry is scaled, and image bitmaps are
+    /// box-downsampled, so backends can draw a page grid without glyph
+    /// rasterization. Overlays, annotations, and damage are dropped.
+    pub fn thumbnail(&self, width: i32, height: i32, cfg: &ThumbnailConfig) -> RenderPage {
+        let scale = cfg.scale_for(width, height);
+        let mut thumb = RenderPage::new(self.page_number);
+        thumb.metrics = self.metrics;
+        for cmd in &self.content_commands {
+            if let Some(cmd) = thumbnail_command(cmd, scale, cfg) {
+                thumb.push_content_command(cmd);
+            }
+        }
+        if cfg.include_chrome {
+            for cmd in &self.chrome_commands {
+                thumb.push_chrome_command(cmd.clone());
+            }
+        }
+        thumb.sync_commands();
+        thumb
+    }
+}
+
+fn thumbnail_command(cmd: &DrawCommand, scale: f32, cfg: &ThumbnailConfig) -> Option<DrawCommand> {
+    let px = |v: i32| (v as f32 * scale).round() as i32;
+    let len = |v: u32| ((v as f32 * scale).round() as u32).max(1);
+    match cmd {
+        DrawCommand::Text(text) => {
+            let bounds = text.approx_bounds();
+            if bounds.is_empty() {
+                return None;
+            }
+            // Bar over the x-height band of the line box.
+            Some(DrawCommand::Rect(RectCommand {
+                x: px(bounds.x),
+                y: px(bounds.y + bounds.height as i32 / 4),
+                width: len(bounds.width),
+                height: len(bounds.height / 2),
+                fill: true,
+                color: Some(text.style.color.unwrap_or(cfg.text_color)),
+            }))
+        }
+        DrawCommand::Rule(rule) => Some(DrawCommand::Rule(RuleCommand {
+            x: px(rule.x),
+            y: px(rule.y),
+            length: len(rule.length),
+            thickness: len(rule.thickness),
+            horizontal: rule.horizontal,
+        })),
+        DrawCommand::Rect(rect) => Some(DrawCommand::Rect(RectCommand {
+            x: px(rect.x),
+            y: px(rect.y),
+            width: len(rect.width),
+            height: len(rect.height),
+            ..*rect
+        })),
+        DrawCommand::Image(image) => {
+            let width = len(image.width);
+            let height = len(image.height);
+            Some(DrawCommand::Image(ImageCommand {
+                x: px(image.x),
+                y: px(image.y),
+                width,
+                height,
+                src: image.src.clone(),
+                alt: image.alt.clone(),
+                bitmap: image
+                    .bitmap
+                    .as_ref()
+                    .map(|bitmap| bitmap.downsample(width, height)),
+            }))
+        }
+        DrawCommand::PushClip(rect) => Some(DrawCommand::PushClip(PageRect::new(
+            px(rect.x),
+            px(rect.y),
+            len(rect.width),
+            len(rect.height),
+        ))),
+        DrawCommand::PopClip => Some(DrawCommand::PopClip),
+        DrawCommand::Layered(layered) => {
+            thumbnail_command(&layered.command, scale, cfg).map(|command| {
+                DrawCommand::Layered(LayeredCommand {
+                    z: layered.z,
+                    command: Box::new(command),
+                })
+            })
+        }
+        DrawCommand::PageChrome(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_ir::{
+        GrayBitmap, JustifyMode, PageChromeCommand, PageChromeKind, ResolvedTextStyle, TextCommand,
+        TextRenderHint, VerticalMetrics,
+    };
+    use mu_epub::BlockRole;
+
+    fn text(x: i32, y: i32, text: &str) -> DrawCommand {
+        DrawCommand::Text(TextCommand {
+            x,
+            baseline_y: y,
+            text: text.to_string(),
+            font_id: None,
+            style: ResolvedTextStyle {
+                font_id: None,
+                family: "serif".to_string(),
+                weight: 400,
+                italic: false,
+                size_px: 20.0,
+                line_height: 1.2,
+                letter_spacing: 0.0,
+                role: BlockRole::Body,
+                justify_mode: JustifyMode::None,
+                color: None,
+                metrics: VerticalMetrics::estimate(20.0),
+            },
+            glyphs: None,
+            hint: TextRenderHint::Aliased,
+        })
+    }
+
+    #[test]
+    fn thumbnail_replaces_text_with_bars_and_drops_chrome() {
+        let mut page = RenderPage::new(3);
+        page.push_content_command(text(40, 100, "hello world"));
+        page.push_chrome_command(DrawCommand::PageChrome(PageChromeCommand {
+            kind: PageChromeKind::Footer,
+            text: Some("Page 3".to_string()),
+            current: None,
+            total: None,
+        }));
+        page.sync_commands();
+
+        let cfg = ThumbnailConfig::default();
+        let thumb = page.thumbnail(480, 640, &cfg);
+        assert_eq!(thumb.page_number, 3);
+        assert_eq!(thumb.commands.len(), 1);
+        let DrawCommand::Rect(bar) = &thumb.commands[0] else {
+            panic!("expected text bar, got {:?}", thumb.commands);
+        };
+        assert_eq!((bar.x, bar.y), (10, 27));
+        assert!(bar.fill);
+        assert_eq!(bar.height, 3);
+        assert_eq!(bar.color, Some(cfg.text_color));
+        assert!(bar.x + bar.width as i32 <= cfg.max_width as i32);
+    }
+
+    #[test]
+    fn thumbnail_downsamples_image_bitmaps() {
+        let mut pixels = vec![255u8; 8 * 8];
+        for px in pixels.iter_mut().take(8 * 4) {
+            *px = 0;
+        }
+        let mut page = RenderPage::new(1);
+        page.push_content_command(DrawCommand::Image(ImageCommand {
+            x: 0,
+            y: 0,
+            width: 8,
+            height: 8,
+            src: "a.png".to_string(),
+            alt: String::with_capacity(0),
+            bitmap: Some(GrayBitmap {
+                width: 8,
+                height: 8,
+                pixels,
+            }),
+        }));
+        page.sync_commands();
+
+        let cfg = ThumbnailConfig {
+            max_width: 4,
+            max_height: 4,
+            ..ThumbnailConfig::default()
+        };
+        let thumb = page.thumbnail(8, 8, &cfg);
+        let Some(DrawCommand::Image(image)) = thumb.commands.first() else {
+            panic!("expected image");
+        };
+        let bitmap = image.bitmap.as_ref().expect("bitmap");
+        assert_eq!((bitmap.width, bitmap.height), (4, 4));
+        assert_eq!(bitmap.pixel(0, 0), Some(0));
+        assert_eq!(bitmap.pixel(3, 3), Some(255));
+    }
+}
@@ -0,0 +1,20 @@
+//! Phase spans for the layout and pagination pipeline.
+//!
+//! With the `tracing` feature, `phase_span!` enters a `tracing` span (nested
+//! under the core crate's open, ZIP read, and style spans); otherwise it
+//! compiles to a zero-sized guard and field expressions are never evaluated.
+
+/// Guard returned by `phase_span!` when the `tracing` feature is disabled.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
+
+/// Enter a debug-level span named after a pipeline phase.
+macro_rules! phase_span {
+    ($name:literal $(, $field:ident = $value:expr)* $(,)?) => {{
+        #[cfg(feature = "tracing")]
+        let guard = tracing::debug_span!($name $(, $field = $value)*).entered();
+        #[cfg(not(feature = "tracing"))]
+        let guard = $crate::render_trace::NoSpan;
+        guard
+    }};
+}
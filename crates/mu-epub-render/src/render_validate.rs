@@ -0,0 +1,386 @@
+//! Internal-consistency checks for rendered pages.
+
+use crate::render_compact::CompactPage;
+use crate::render_ir::{DrawCommand, OverlaySize, PageMetrics, PageRect, RenderPage, TextCommand};
+
+/// Command layer a finding refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PageLayer {
+    /// `RenderPage::content_commands`.
+    Content,
+    /// `RenderPage::chrome_commands`.
+    Chrome,
+    /// `RenderPage::overlay_commands`.
+    Overlay,
+}
+
+/// One inconsistency reported by `RenderPage::validate`.
+///
+/// `index` fields are positions within the named layer.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PageFinding {
+    /// A command's geometry leaves the viewport.
+    OutOfViewport {
+        /// Layer holding the command.
+        layer: PageLayer,
+        /// Command index.
+        index: usize,
+        /// Checked command bounds.
+        bounds: PageRect,
+    },
+    /// A content text line starts above the previous line.
+    BaselineRegression {
+        /// Command index.
+        index: usize,
+        /// Line top of this command.
+        baseline_y: i32,
+        /// Line top of the preceding content line.
+        previous: i32,
+    },
+    /// Consecutive content lines overlap vertically and horizontally.
+    OverlappingLines {
+        /// Index of the earlier line.
+        first: usize,
+        /// Index of the later line.
+        second: usize,
+    },
+    /// `TextCommand::glyphs` does not have one entry per `char`.
+    GlyphCountMismatch {
+        /// Layer holding the command.
+        layer: PageLayer,
+        /// Command index.
+        index: usize,
+        /// Chars in the text.
+        chars: usize,
+        /// Glyph positions supplied.
+        glyphs: usize,
+    },
+    /// `PushClip`/`PopClip` do not pair up within a layer.
+    UnbalancedClip {
+        /// Layer holding the commands.
+        layer: PageLayer,
+    },
+    /// The merged `commands` stream is out of sync with the split layers.
+    StaleMergedCommands,
+    /// A compact text run references a missing string or style entry.
+    UnresolvedIndex {
+        /// Layer holding the command.
+        layer: PageLayer,
+        /// Command index.
+        index: usize,
+    },
+    /// `page_number` disagrees with `metrics.chapter_page_index`.
+    PageIndexMismatch {
+        /// 1-based page number on the page.
+        page_number: usize,
+        /// Zero-based index from the metrics.
+        chapter_page_index: usize,
+    },
+    /// Page index is not below the chapter page count.
+    PageBeyondChapter {
+        /// Zero-based index from the metrics.
+        chapter_page_index: usize,
+        /// Chapter page count from the metrics.
+        chapter_page_count: usize,
+    },
+    /// A progress fraction lies outside `0.0..=1.0`.
+    ProgressOutOfRange {
+        /// Offending value.
+        value: f32,
+    },
+}
+
+impl RenderPage {
+    /// Check this page for internal consistency.
+    ///
+    /// `metrics` is usually `&self.metrics`; pass expected metrics to
+    /// cross-check a cached or deserialized page. Text extents are only
+    /// checked horizontally when layout supplied glyph positions, since
+    /// width estimates over-cover. Returns an empty list for a clean page.
+    pub fn validate(&self, metrics: &PageMetrics, viewport: OverlaySize) -> Vec<PageFinding> {
+        let mut findings = Vec::with_capacity(0);
+        let viewport_rect = PageRect::new(0, 0, viewport.width, viewport.height);
+        let layers = [
+            (PageLayer::Content, &self.content_commands),
+            (PageLayer::Chrome, &self.chrome_commands),
+            (PageLayer::Overlay, &self.overlay_commands),
+        ];
+        for (layer, commands) in layers {
+            check_layer(layer, commands, viewport_rect, &mut findings);
+        }
+        check_lines(&self.content_commands, &mut findings);
+
+        let split =
+            self.content_commands.len() + self.chrome_commands.len() + self.overlay_commands.len();
+        let in_sync = self.commands.len() == split
+            && self
+                .content_commands
+                .iter()
+                .chain(&self.chrome_commands)
+                .chain(&self.overlay_commands)
+                .eq(self.commands.iter());
+        // Pages built before the layer split only fill `commands`.
+        if split > 0 && !in_sync {
+            findings.push(PageFinding::StaleMergedCommands);
+        }
+
+        if self.page_number != metrics.chapter_page_index + 1 {
+            findings.push(PageFinding::PageIndexMismatch {
+                page_number: self.page_number,
+                chapter_page_index: metrics.chapter_page_index,
+            });
+        }
+        if let Some(count) = metrics.chapter_page_count {
+            if metrics.chapter_page_index >= count {
+                findings.push(PageFinding::PageBeyondChapter {
+                    chapter_page_index: metrics.chapter_page_index,
+                    chapter_page_count: count,
+                });
+            }
+        }
+        for value in core::iter::once(metrics.progress_chapter).chain(metrics.progress_book) {
+            if !(0.0..=1.0).contains(&value) {
+                findings.push(PageFinding::ProgressOutOfRange { value });
+            }
+        }
+        findings
+    }
+}
+
+impl CompactPage {
+    /// Check this page for internal consistency, including that every
+    /// interned text run resolves.
+    ///
+    /// See `RenderPage::validate`.
+    pub fn validate(&self, metrics: &PageMetrics, viewport: OverlaySize) -> Vec<PageFinding> {
+        let layers = [
+            (PageLayer::Content, self.content_commands()),
+            (PageLayer::Chrome, self.chrome_commands()),
+            (PageLayer::Overlay, self.overlay_commands()),
+        ];
+        let mut findings: Vec<PageFinding> = layers
+            .iter()
+            .flat_map(|(layer, commands)| {
+                commands
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, cmd)| self.expand(cmd).is_none())
+                    .map(|(index, _)| PageFinding::UnresolvedIndex {
+                        layer: *layer,
+                        index,
+                    })
+            })
+            .collect();
+        if findings.is_empty() {
+            findings = self.to_page().validate(metrics, viewport);
+        }
+        findings
+    }
+}
+
+fn check_layer(
+    layer: PageLayer,
+    commands: &[DrawCommand],
+    viewport: PageRect,
+    findings: &mut Vec<PageFinding>,
+) {
+    let mut clip_depth = 0usize;
+    let mut balanced = true;
+    for (index, cmd) in commands.iter().enumerate() {
+        match cmd {
+            DrawCommand::PushClip(_) => clip_depth += 1,
+            DrawCommand::PopClip => match clip_depth.checked_sub(1) {
+                Some(depth) => clip_depth = depth,
+                None => balanced = false,
+            },
+            _ => {}
+        }
+        let text = match cmd {
+            DrawCommand::Text(text) => Some(text),
+            DrawCommand::Layered(layered) => match &*layered.command {
+                DrawCommand::Text(text) => Some(text),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(text) = text {
+            let chars = text.text.chars().count();
+            if let Some(glyphs) = text.glyphs.as_ref().filter(|g| g.len() != chars) {
+                findings.push(PageFinding::GlyphCountMismatch {
+                    layer,
+                    index,
+                    chars,
+                    glyphs: glyphs.len(),
+                });
+            }
+        }
+        let bounds = match (text, cmd) {
+            (Some(text), _) => Some(checked_text_bounds(text)),
+            (None, DrawCommand::PushClip(_) | DrawCommand::PopClip) => None,
+            (None, cmd) => cmd.bounds(),
+        };
+        if let Some(bounds) = bounds.filter(|bounds| !within(bounds, &viewport)) {
+            findings.push(PageFinding::OutOfViewport {
+                layer,
+                index,
+                bounds,
+            });
+        }
+    }
+    if !balanced || clip_depth != 0 {
+        findings.push(PageFinding::UnbalancedClip { layer });
+    }
+}
+
+fn check_lines(commands: &[DrawCommand], findings: &mut Vec<PageFinding>) {
+    let mut previous: Option<(usize, &TextCommand)> = None;
+    for (index, cmd) in commands.iter().enumerate() {
+        let DrawCommand::Text(text) = cmd else {
+            continue;
+        };
+        if let Some((prev_index, prev)) = previous {
+            if text.baseline_y < prev.baseline_y {
+                findings.push(PageFinding::BaselineRegression {
+                    index,
+                    baseline_y: text.baseline_y,
+                    previous: prev.baseline_y,
+                });
+            } else if text.baseline_y > prev.baseline_y {
+                let prev_bottom = prev.baseline_y + prev.style.size_px.ceil() as i32;
+                let prev_bounds = checked_text_bounds(prev);
+                let bounds = checked_text_bounds(text);
+                let horizontal = bounds.x < prev_bounds.right().max(prev_bounds.x + 1)
+                    && prev_bounds.x < bounds.right().max(bounds.x + 1);
+                if text.baseline_y < prev_bottom && horizontal {
+                    findings.push(PageFinding::OverlappingLines {
+                        first: prev_index,
+                        second: index,
+                    });
+                }
+            } else {
+                // Runs sharing a line top belong to the same line.
+                continue;
+            }
+        }
+        previous = Some((index, text));
+    }
+}
+
+/// Text bounds without estimated widths: exact when glyph positions are
+/// present, zero-width otherwise.
+fn checked_text_bounds(text: &TextCommand) -> PageRect {
+    let width: i32 = text
+        .glyphs
+        .as_ref()
+        .map_or(0, |glyphs| glyphs.iter().map(|g| g.x_advance).sum());
+    PageRect::new(
+        text.x,
+        text.baseline_y,
+        width.max(0) as u32,
+        text.style.size_px.ceil().max(1.0) as u32,
+    )
+}
+
+fn within(rect: &PageRect, viewport: &PageRect) -> bool {
+    rect.x >= viewport.x
+        && rect.y >= viewport.y
+        && rect.right() <= viewport.right()
+        && rect.bottom() <= viewport.bottom()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_ir::{
+        JustifyMode, ResolvedTextStyle, RuleCommand, TextRenderHint, VerticalMetrics,
+    };
+    use mu_epub::BlockRole;
+
+    fn line(y: i32) -> DrawCommand {
+        DrawCommand::Text(TextCommand {
+            x: 10,
+            baseline_y: y,
+            text: "line".to_string(),
+            font_id: None,
+            style: ResolvedTextStyle {
+                font_id: None,
+                family: "serif".to_string(),
+                weight: 400,
+                italic: false,
+                size_px: 20.0,
+                line_height: 1.2,
+                letter_spacing: 0.0,
+                role: BlockRole::Body,
+                justify_mode: JustifyMode::None,
+                color: None,
+                metrics: VerticalMetrics::estimate(20.0),
+            },
+            glyphs: None,
+            hint: TextRenderHint::Aliased,
+        })
+    }
+
+    const VIEWPORT: OverlaySize = OverlaySize {
+        width: 200,
+        height: 100,
+    };
+
+    #[test]
+    fn clean_page_has_no_findings() {
+        let mut page = RenderPage::new(1);
+        page.push_content_command(line(0));
+        page.push_content_command(line(24));
+        page.sync_commands();
+        assert!(page.validate(&page.metrics, VIEWPORT).is_empty());
+        assert!(page.compact().validate(&page.metrics, VIEWPORT).is_empty());
+    }
+
+    #[test]
+    fn validate_reports_typed_findings() {
+        let mut page = RenderPage::new(2);
+        page.push_content_command(line(40));
+        page.push_content_command(line(50));
+        page.push_content_command(line(30));
+        page.push_content_command(DrawCommand::Rule(RuleCommand {
+            x: 150,
+            y: 90,
+            length: 80,
+            thickness: 1,
+            horizontal: true,
+        }));
+        page.push_content_command(DrawCommand::PopClip);
+
+        let metrics = PageMetrics {
+            chapter_page_count: Some(1),
+            ..page.metrics
+        };
+        let findings = page.validate(&metrics, VIEWPORT);
+        assert_eq!(
+            findings,
+            vec![
+                PageFinding::OutOfViewport {
+                    layer: PageLayer::Content,
+                    index: 3,
+                    bounds: PageRect::new(150, 90, 80, 1),
+                },
+                PageFinding::UnbalancedClip {
+                    layer: PageLayer::Content,
+                },
+                PageFinding::OverlappingLines {
+                    first: 0,
+                    second: 1,
+                },
+                PageFinding::BaselineRegression {
+                    index: 2,
+                    baseline_y: 30,
+                    previous: 50,
+                },
+                PageFinding::StaleMergedCommands,
+                PageFinding::PageBeyondChapter {
+                    chapter_page_index: 1,
+                    chapter_page_count: 1,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,598 @@
+//! Golden-page test harness behind the `testkit` feature.
+//!
+//! Renders a book under a named [`GoldenProfile`] in deterministic mode,
+//! stores the pages as goldens, and reports later differences as readable
+//! command diffs (see `PageDiff`). Goldens live under
+//! `<root>/<book>/<profile>/`:
+//!
+//! - `chapter_NNN.pages`: pages in the pagination cache format, compared
+//!   command by command.
+//! - `chapter_NNN.json`: `RenderPage::to_debug_json` of every page, for
+//!   reviewing blessed changes in a pull request.
+//! - `profile.bin`: the `PaginationProfile`, so a report can name the
+//!   layout settings that changed since the goldens were blessed.
+//!
+//! Set `MU_EPUB_BLESS=1` to rewrite goldens instead of comparing:
+//!
+//! ```no_run
+//! use mu_epub::EpubBook;
+//! use mu_epub_render::testkit::{GoldenProfile, GoldenStore};
+//!
+//! let profile = GoldenProfile::for_display("small", 480, 800);
+//! let mut book = EpubBook::open("tests/fixtures/book.epub")?;
+//! let pages = profile.render_book(&mut book)?;
+//! GoldenStore::new("tests/goldens").assert_matches("book", &profile, &pages);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use mu_epub::EpubBook;
+
+use crate::render_cache::{
+    decode_chapter_pages, encode_chapter_pages, BookFingerprint, CacheDecodeError, CacheKey,
+    PaginationProfile, ProfileChange,
+};
+use crate::render_diff::PageDiff;
+use crate::render_engine::{RenderEngine, RenderEngineError, RenderEngineOptions};
+use crate::render_ir::{DrawCommand, PaginationProfileId, RenderPage};
+
+/// Environment variable that switches `GoldenStore::check` to blessing.
+pub const BLESS_ENV: &str = "MU_EPUB_BLESS";
+
+const PROFILE_FILE: &str = "profile.bin";
+
+/// Commands listed per mismatched page before the report elides the rest;
+/// the `.json` goldens hold the full pages.
+const MAX_REPORTED_COMMANDS: usize = 12;
+
+/// Render settings a set of goldens is recorded under.
+#[derive(Clone, Debug)]
+pub struct GoldenProfile {
+    /// Directory name for this profile's goldens.
+    pub name: String,
+    /// Engine options; `deterministic` is always on.
+    pub options: RenderEngineOptions,
+}
+
+impl GoldenProfile {
+    /// Profile named `name` rendering with `options`.
+    pub fn new(name: impl Into<String>, mut options: RenderEngineOptions) -> Self {
+        options.deterministic = true;
+        Self {
+            name: name.into(),
+            options,
+        }
+    }
+
+    /// Profile with `RenderEngineOptions::for_display` defaults.
+    pub fn for_display(name: impl Into<String>, width: i32, height: i32) -> Self {
+        Self::new(name, RenderEngineOptions::for_display(width, height))
+    }
+
+    /// Deterministic engine for this profile.
+    pub fn engine(&self) -> RenderEngine {
+        RenderEngine::new(self.options)
+    }
+
+    /// Pages of every spine chapter, in spine order.
+    pub fn render_book<R: std::io::Read + std::io::Seek>(
+        &self,
+        book: &mut EpubBook<R>,
+    ) -> Result<Vec<Vec<RenderPage>>, RenderEngineError> {
+        let engine = self.engine();
+        (0..book.chapter_count())
+            .map(|chapter_index| engine.prepare_chapter(book, chapter_index))
+            .collect()
+    }
+}
+
+/// One difference between goldens and freshly rendered pages.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GoldenMismatch {
+    /// The book has a different number of chapters than was blessed.
+    ChapterCount { golden: usize, current: usize },
+    /// A chapter paginated to a different number of pages.
+    PageCount {
+        chapter_index: usize,
+        golden: usize,
+        current: usize,
+    },
+    /// A page draws different commands.
+    Commands {
+        chapter_index: usize,
+        page_index: usize,
+        diff: PageDiff,
+    },
+    /// A page draws the same commands but its metrics, annotations, or
+    /// line positions differ.
+    PageFields {
+        chapter_index: usize,
+        page_index: usize,
+    },
+}
+
+/// Every difference found by `GoldenStore::compare`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GoldenReport {
+    /// Book directory name.
+    pub book: String,
+    /// Profile directory name.
+    pub profile: String,
+    /// Layout settings changed since the goldens were blessed.
+    pub profile_changes: Vec<ProfileChange>,
+    /// Page differences in chapter and page order.
+    pub mismatches: Vec<GoldenMismatch>,
+}
+
+impl GoldenReport {
+    /// True when the rendered pages match the goldens.
+    pub fn is_match(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl fmt::Display for GoldenReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "goldens for '{}' under profile '{}' differ:",
+            self.book, self.profile
+        )?;
+        if !self.profile_changes.is_empty() {
+            writeln!(f, "  layout settings changed since bless:")?;
+            for change in &self.profile_changes {
+                writeln!(f, "    {}", change)?;
+            }
+        }
+        for mismatch in &self.mismatches {
+            match mismatch {
+                GoldenMismatch::ChapterCount { golden, current } => {
+                    writeln!(f, "  {} chapters blessed, {} now", golden, current)?;
+                }
+                GoldenMismatch::PageCount {
+                    chapter_index,
+                    golden,
+                    current,
+                } => writeln!(
+                    f,
+                    "  chapter {}: {} pages blessed, {} now",
+                    chapter_index, golden, current
+                )?,
+                GoldenMismatch::Commands {
+                    chapter_index,
+                    page_index,
+                    diff,
+                } => {
+                    writeln!(
+                        f,
+                        "  chapter {} page {}: {} added, {} removed, {} changed",
+                        chapter_index,
+                        page_index,
+                        diff.added.len(),
+                        diff.removed.len(),
+                        diff.changed.len()
+                    )?;
+                    let lines = diff
+                        .removed
+                        .iter()
+                        .map(|cmd| format!("- {}", describe(cmd)))
+                        .chain(diff.added.iter().map(|cmd| format!("+ {}", describe(cmd))))
+                        .chain(diff.changed.iter().map(|change| {
+                            format!(
+                                "~ {}\n      {}",
+                                describe(&change.before),
+                                describe(&change.after)
+                            )
+                        }));
+                    let total = diff.added.len() + diff.removed.len() + diff.changed.len();
+                    for line in lines.take(MAX_REPORTED_COMMANDS) {
+                        writeln!(f, "    {}", line)?;
+                    }
+                    if total > MAX_REPORTED_COMMANDS {
+                        writeln!(f, "    ... {} more", total - MAX_REPORTED_COMMANDS)?;
+                    }
+                }
+                GoldenMismatch::PageFields {
+                    chapter_index,
+                    page_index,
+                } => writeln!(
+                    f,
+                    "  chapter {} page {}: same commands, different metrics or annotations",
+                    chapter_index, page_index
+                )?,
+            }
+        }
+        write!(f, "re-run with {}=1 to bless the new pages", BLESS_ENV)
+    }
+}
+
+/// Failure to read, write, or match goldens.
+#[derive(Debug)]
+pub enum GoldenError {
+    /// Golden directory or file I/O failed.
+    Io { path: PathBuf, source: io::Error },
+    /// A golden file is corrupt or from another cache format version.
+    Decode {
+        path: PathBuf,
+        source: CacheDecodeError,
+    },
+    /// Rendered pages differ from the goldens.
+    Mismatch(GoldenReport),
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Self::Decode { path, source } => write!(
+                f,
+                "{}: {} (re-bless with {}=1)",
+                path.display(),
+                source,
+                BLESS_ENV
+            ),
+            Self::Mismatch(report) => write!(f, "{}", report),
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {}
+
+/// Golden files under one root directory.
+#[derive(Clone, Debug)]
+pub struct GoldenStore {
+    root: PathBuf,
+}
+
+impl GoldenStore {
+    /// Store rooted at `root`, usually a directory checked into the repo.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Directory holding `book`'s goldens for `profile`.
+    pub fn dir(&self, book: &str, profile: &GoldenProfile) -> PathBuf {
+        self.root.join(book).join(&profile.name)
+    }
+
+    /// Replace `book`'s goldens for `profile` with `chapters`.
+    pub fn bless(
+        &self,
+        book: &str,
+        profile: &GoldenProfile,
+        chapters: &[Vec<RenderPage>],
+    ) -> Result<(), GoldenError> {
+        let dir = self.dir(book, profile);
+        fs::create_dir_all(&dir).map_err(|source| io_error(&dir, source))?;
+        for pages in chapter_files(&dir)? {
+            for stale in [pages.with_extension("json"), pages] {
+                if stale.exists() {
+                    fs::remove_file(&stale).map_err(|source| io_error(&stale, source))?;
+                }
+            }
+        }
+        for (chapter_index, pages) in chapters.iter().enumerate() {
+            let bytes = encode_chapter_pages(&golden_key(chapter_index), pages);
+            write_file(&chapter_path(&dir, chapter_index, "pages"), &bytes)?;
+            write_file(
+                &chapter_path(&dir, chapter_index, "json"),
+                serialize_pages(pages).as_bytes(),
+            )?;
+        }
+        let profile_bytes = profile.engine().pagination_profile().encode();
+        write_file(&dir.join(PROFILE_FILE), &profile_bytes)
+    }
+
+    /// Compare `chapters` with `book`'s goldens for `profile`.
+    ///
+    /// Missing goldens are reported as an I/O error naming the directory.
+    pub fn compare(
+        &self,
+        book: &str,
+        profile: &GoldenProfile,
+        chapters: &[Vec<RenderPage>],
+    ) -> Result<GoldenReport, GoldenError> {
+        let dir = self.dir(book, profile);
+        let mut report = GoldenReport {
+            book: book.to_string(),
+            profile: profile.name.clone(),
+            ..GoldenReport::default()
+        };
+
+        let profile_path = dir.join(PROFILE_FILE);
+        let stored = fs::read(&profile_path).map_err(|source| io_error(&profile_path, source))?;
+        let stored = PaginationProfile::decode(&stored).map_err(|source| GoldenError::Decode {
+            path: profile_path,
+            source,
+        })?;
+        report.profile_changes = profile.engine().pagination_profile_changes(&stored);
+
+        let golden_chapters = chapter_files(&dir)?.len();
+        if golden_chapters != chapters.len() {
+            report.mismatches.push(GoldenMismatch::ChapterCount {
+                golden: golden_chapters,
+                current: chapters.len(),
+            });
+        }
+        for (chapter_index, current) in chapters.iter().enumerate().take(golden_chapters) {
+            let path = chapter_path(&dir, chapter_index, "pages");
+            let bytes = fs::read(&path).map_err(|source| io_error(&path, source))?;
+            let golden = decode_chapter_pages(&bytes, &golden_key(chapter_index))
+                .map_err(|source| GoldenError::Decode { path, source })?;
+            if golden.len() != current.len() {
+                report.mismatches.push(GoldenMismatch::PageCount {
+                    chapter_index,
+                    golden: golden.len(),
+                    current: current.len(),
+                });
+            }
+            for (page_index, (before, after)) in golden.iter().zip(current).enumerate() {
+                if before == after {
+                    continue;
+                }
+                let diff = before.diff(after);
+                report.mismatches.push(if diff.is_empty() {
+                    GoldenMismatch::PageFields {
+                        chapter_index,
+                        page_index,
+                    }
+                } else {
+                    GoldenMismatch::Commands {
+                        chapter_index,
+                        page_index,
+                        diff,
+                    }
+                });
+            }
+        }
+        Ok(report)
+    }
+
+    /// Compare with the goldens, or bless when `MU_EPUB_BLESS` is set.
+    pub fn check(
+        &self,
+        book: &str,
+        profile: &GoldenProfile,
+        chapters: &[Vec<RenderPage>],
+    ) -> Result<(), GoldenError> {
+        if std::env::var_os(BLESS_ENV).is_some_and(|value| !value.is_empty() && value != "0") {
+            return self.bless(book, profile, chapters);
+        }
+        let report = self.compare(book, profile, chapters)?;
+        if report.is_match() {
+            Ok(())
+        } else {
+            Err(GoldenError::Mismatch(report))
+        }
+    }
+
+    /// `check`, for use in tests.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the readable report when the pages differ or the
+    /// goldens cannot be read.
+    #[allow(clippy::panic)]
+    pub fn assert_matches(
+        &self,
+        book: &str,
+        profile: &GoldenProfile,
+        chapters: &[Vec<RenderPage>],
+    ) {
+        if let Err(err) = self.check(book, profile, chapters) {
+            panic!("{}", err);
+        }
+    }
+}
+
+/// Pages as one deterministic JSON array of `RenderPage::to_debug_json`.
+pub fn serialize_pages(pages: &[RenderPage]) -> String {
+    let mut out = String::with_capacity(2 + pages.len() * 4096);
+    out.push('[');
+    for (idx, page) in pages.iter().enumerate() {
+        if idx > 0 {
+            out.push(',');
+        }
+        out.push('\n');
+        out.push_str(&page.to_debug_json());
+    }
+    out.push_str("\n]\n");
+    out
+}
+
+/// Key stored in golden files; goldens are addressed by path instead.
+fn golden_key(chapter_index: usize) -> CacheKey {
+    CacheKey {
+        profile: PaginationProfileId([0; 32]),
+        book: BookFingerprint([0; 32]),
+        chapter_index,
+    }
+}
+
+fn chapter_path(dir: &Path, chapter_index: usize, ext: &str) -> PathBuf {
+    dir.join(format!("chapter_{:03}.{}", chapter_index, ext))
+}
+
+/// `chapter_NNN.pages` files in `dir`.
+fn chapter_files(dir: &Path) -> Result<Vec<PathBuf>, GoldenError> {
+    let entries = fs::read_dir(dir).map_err(|source| io_error(dir, source))?;
+    let mut files = Vec::with_capacity(16);
+    for entry in entries {
+        let path = entry.map_err(|source| io_error(dir, source))?.path();
+        let is_chapter = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("chapter_") && name.ends_with(".pages"));
+        if is_chapter {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), GoldenError> {
+    fs::write(path, bytes).map_err(|source| io_error(path, source))
+}
+
+fn io_error(path: &Path, source: io::Error) -> GoldenError {
+    GoldenError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// One-line summary of a command for mismatch reports.
+fn describe(cmd: &DrawCommand) -> String {
+    match cmd {
+        DrawCommand::Text(text) => format!(
+            "text at ({}, {}) {:?} {} {}{}px",
+            text.x,
+            text.baseline_y,
+            text.text,
+            text.style.family,
+            if text.style.italic { "italic " } else { "" },
+            text.style.size_px
+        ),
+        DrawCommand::Rule(rule) => format!(
+            "{} rule at ({}, {}) length {} thickness {}",
+            if rule.horizontal {
+                "horizontal"
+            } else {
+                "vertical"
+            },
+            rule.x,
+            rule.y,
+            rule.length,
+            rule.thickness
+        ),
+        DrawCommand::Rect(rect) => format!(
+            "{} rect at ({}, {}) {}x{}",
+            if rect.fill { "filled" } else { "stroked" },
+            rect.x,
+            rect.y,
+            rect.width,
+            rect.height
+        ),
+        DrawCommand::PageChrome(chrome) => format!(
+            "{:?} chrome {:?} {:?}/{:?}",
+            chrome.kind, chrome.text, chrome.current, chrome.total
+        ),
+        DrawCommand::Image(image) => format!(
+            "image {:?} at ({}, {}) {}x{}",
+            image.src, image.x, image.y, image.width, image.height
+        ),
+        DrawCommand::PushClip(rect) => format!(
+            "push clip ({}, {}) {}x{}",
+            rect.x, rect.y, rect.width, rect.height
+        ),
+        DrawCommand::PopClip => "pop clip".to_string(),
+        DrawCommand::Layered(layered) => {
+            format!("z {} {}", layered.z, describe(&layered.command))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_ir::RuleCommand;
+
+    fn rule_page(page_number: usize, y: i32, length: u32) -> RenderPage {
+        let mut page = RenderPage::new(page_number);
+        page.push_content_command(DrawCommand::Rule(RuleCommand {
+            x: 10,
+            y,
+            length,
+            thickness: 1,
+            horizontal: true,
+        }));
+        page.sync_commands();
+        page
+    }
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("mu-epub-testkit-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        root
+    }
+
+    #[test]
+    fn blessed_goldens_match_and_report_readable_diffs() {
+        let root = temp_root("diff");
+        let store = GoldenStore::new(&root);
+        let profile = GoldenProfile::for_display("small", 300, 400);
+        let chapters = vec![
+            vec![rule_page(1, 20, 40), rule_page(2, 20, 40)],
+            vec![rule_page(1, 5, 40)],
+        ];
+
+        store
+            .bless("book", &profile, &chapters)
+            .expect("bless should write goldens");
+        let dir = store.dir("book", &profile);
+        assert!(dir.join("chapter_001.json").exists());
+        let report = store
+            .compare("book", &profile, &chapters)
+            .expect("goldens should load");
+        assert!(report.is_match(), "{}", report);
+
+        let mut moved = chapters.clone();
+        moved[0][1] = rule_page(2, 20, 44);
+        moved[1].push(rule_page(2, 5, 40));
+        moved[1][0].metrics.word_count = 7;
+        let wider = GoldenProfile::for_display("small", 320, 400);
+        let report = store
+            .compare("book", &wider, &moved)
+            .expect("goldens should load");
+        assert_eq!(report.mismatches.len(), 3);
+        assert!(matches!(
+            report.mismatches[0],
+            GoldenMismatch::Commands {
+                chapter_index: 0,
+                page_index: 1,
+                ..
+            }
+        ));
+        assert_eq!(
+            report.mismatches[1],
+            GoldenMismatch::PageCount {
+                chapter_index: 1,
+                golden: 1,
+                current: 2
+            }
+        );
+        assert_eq!(
+            report.mismatches[2],
+            GoldenMismatch::PageFields {
+                chapter_index: 1,
+                page_index: 0
+            }
+        );
+        let text = report.to_string();
+        assert!(text.contains("layout.display_width changed from 300 to 320"));
+        assert!(text.contains("~ horizontal rule at (10, 20) length 40 thickness 1"));
+        assert!(text.contains("  horizontal rule at (10, 20) length 44 thickness 1"));
+        assert!(text.contains(BLESS_ENV));
+
+        store
+            .bless("book", &profile, &chapters[..1])
+            .expect("re-bless should replace goldens");
+        assert!(!dir.join("chapter_001.pages").exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn missing_goldens_are_io_errors() {
+        let store = GoldenStore::new(temp_root("missing"));
+        let profile = GoldenProfile::for_display("small", 300, 400);
+        let err = store
+            .compare("book", &profile, &[])
+            .expect_err("no goldens were blessed");
+        assert!(matches!(err, GoldenError::Io { .. }));
+    }
+}
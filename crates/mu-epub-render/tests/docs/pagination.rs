@@ -1,11 +1,20 @@
+use std::io::Cursor;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
+use mu_epub::memory::{set_memory_observer, MemorySubsystem, MemoryTally};
 use mu_epub::{EpubBook, MemoryBudget, RenderPrepOptions};
 use mu_epub_render::{
-    CancelToken, OverlayComposer, OverlayContent, OverlayItem, OverlaySize, OverlaySlot,
-    PageChromeConfig, PaginationProfileId, RenderCacheStore, RenderConfig, RenderDiagnostic,
-    RenderEngine, RenderEngineError, RenderEngineOptions, RenderPage,
+    Annotation, AnnotationStore, BookFingerprint, BookTarget, Bookmark, BookmarkStore,
+    CacheCapacity, CancelToken, ChapterErrorPolicy, ChapterIr, ChapterRun, ColorTarget,
+    ContentLocator, DirCacheStore, DrawCommand, EngineTelemetry, InternalLink, LayoutSession,
+    LinkTarget, LruCacheStore, MemoryAnnotationStore, MemoryBookmarkStore, NeverCancel,
+    OverlayComposer, OverlayContent, OverlayItem, OverlaySize, OverlaySlot, PageAnnotation,
+    PageChromeConfig, PageChromeKind, PageRect, PaginationProfileId, PrefetchStatus, ReaderSession,
+    RenderCacheStore, RenderConfig, RenderDiagnostic, RenderEngine, RenderEngineError,
+    RenderEngineOptions, RenderIntent, RenderPage, RenderPhase, RenderSpread, Rotation,
+    SearchOptions, SessionSnapshot, SpreadOptions, SpreadStart, SyncPosition, ThumbnailConfig,
+    YieldHook, YieldPoint,
 };
 
 fn fixture_path() -> PathBuf {
@@ -46,6 +55,114 @@ fn chapter_with_min_pages(
     None
 }
 
+/// Uncompressed ZIP archive holding `files`.
+fn stored_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut zip = Vec::with_capacity(4096);
+    let mut central = Vec::with_capacity(1024);
+    for (name, content) in files {
+        let crc = crc32fast::hash(content);
+        let offset = zip.len() as u32;
+        let mut header = Vec::with_capacity(46);
+        header.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        header.extend_from_slice(&[0; 8]); // flags, stored, time, date
+        header.extend_from_slice(&crc.to_le_bytes());
+        header.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // extra length
+        zip.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        zip.extend_from_slice(&header);
+        zip.extend_from_slice(name.as_bytes());
+        zip.extend_from_slice(content);
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        central.extend_from_slice(&header);
+        central.extend_from_slice(&[0; 10]); // comment, disk, attributes
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+    let central_offset = zip.len() as u32;
+    zip.extend_from_slice(&central);
+    zip.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    zip.extend_from_slice(&[0; 4]); // disk numbers
+    zip.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    zip.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    zip.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    zip.extend_from_slice(&central_offset.to_le_bytes());
+    zip.extend_from_slice(&0u16.to_le_bytes()); // comment length
+    zip
+}
+
+const SYNTHETIC_CONTAINER: &[u8] = br#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="EPUB/package.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#;
+
+/// One-chapter EPUB whose chapter shows `images/gray.pgm`, an 8x8
+/// horizontal gradient that is also the cover.
+fn image_book() -> EpubBook<Cursor<Vec<u8>>> {
+    let opf = br#"<?xml version="1.0" encoding="UTF-8"?>
+<package version="3.0" xmlns="http://www.idpf.org/2007/opf">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>Gradients</dc:title>
+    <dc:identifier>urn:test:gradients</dc:identifier>
+    <dc:language>en</dc:language>
+  </metadata>
+  <manifest>
+    <item id="c1" href="ch1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="gray" href="images/gray.pgm" media-type="image/x-portable-graymap" properties="cover-image"/>
+  </manifest>
+  <spine>
+    <itemref idref="c1"/>
+  </spine>
+</package>"#;
+    let chapter = br#"<html xmlns="http://www.w3.org/1999/xhtml"><body>
+<p>Before the picture.</p>
+<img src="images/gray.pgm" alt="A gradient"/>
+<p>After the picture.</p>
+</body></html>"#;
+    let mut pgm = b"P5\n8 8\n255\n".to_vec();
+    pgm.extend((0..64u32).map(|i| (i % 8 * 32) as u8));
+    let zip = stored_zip(&[
+        ("mimetype", b"application/epub+zip"),
+        ("META-INF/container.xml", SYNTHETIC_CONTAINER),
+        ("EPUB/package.opf", opf),
+        ("EPUB/ch1.xhtml", chapter),
+        ("EPUB/images/gray.pgm", &pgm),
+    ]);
+    EpubBook::from_reader(Cursor::new(zip)).expect("image book should open")
+}
+
+/// One-chapter EPUB with no images and no cover declaration at all.
+fn coverless_book() -> EpubBook<Cursor<Vec<u8>>> {
+    let opf = br#"<?xml version="1.0" encoding="UTF-8"?>
+<package version="3.0" xmlns="http://www.idpf.org/2007/opf">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>Plain</dc:title>
+    <dc:identifier>urn:test:plain</dc:identifier>
+    <dc:language>en</dc:language>
+  </metadata>
+  <manifest>
+    <item id="c1" href="ch1.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine>
+    <itemref idref="c1"/>
+  </spine>
+</package>"#;
+    let chapter = br#"<html xmlns="http://www.w3.org/1999/xhtml"><body>
+<p>Only words here.</p>
+</body></html>"#;
+    let zip = stored_zip(&[
+        ("mimetype", b"application/epub+zip"),
+        ("META-INF/container.xml", SYNTHETIC_CONTAINER),
+        ("EPUB/package.opf", opf),
+        ("EPUB/ch1.xhtml", chapter),
+    ]);
+    EpubBook::from_reader(Cursor::new(zip)).expect("coverless book should open")
+}
+
 #[test]
 fn prepare_chapter_page_range_matches_full_slice() {
     let engine = build_engine();
@@ -117,7 +234,7 @@ fn prepare_chapter_bytes_with_matches_full_render() {
     )
     .expect("chapter bytes should load");
 
-    let mut actual = Vec::new();
+    let mut actual = Vec::with_capacity(8);
     engine
         .prepare_chapter_bytes_with(&mut book, chapter, &chapter_buf, |page| actual.push(page))
         .expect("chapter-bytes render should succeed");
@@ -170,7 +287,7 @@ fn prepare_chapter_with_config_can_disable_embedded_fonts() {
     let mut book = open_fixture_book();
     let (chapter, _) = chapter_with_min_pages(&engine, &mut book, 1)
         .expect("fixture should contain at least one renderable chapter");
-    let mut pages = Vec::new();
+    let mut pages = Vec::with_capacity(8);
     engine
         .prepare_chapter_with_config(
             &mut book,
@@ -205,7 +322,7 @@ fn overlay_composer_attaches_overlay_items() {
     let mut book = open_fixture_book();
     let (chapter, _) = chapter_with_min_pages(&engine, &mut book, 1)
         .expect("fixture should contain at least one renderable chapter");
-    let mut pages = Vec::new();
+    let mut pages = Vec::with_capacity(8);
     engine
         .prepare_chapter_with_overlay_composer(
             &mut book,
@@ -222,10 +339,94 @@ fn overlay_composer_attaches_overlay_items() {
     assert!(pages.iter().all(|p| !p.overlay_items.is_empty()));
 }
 
+#[derive(Clone, Debug, Default)]
+struct ViewportProbe(Arc<Mutex<Option<OverlaySize>>>);
+
+impl OverlayComposer for ViewportProbe {
+    fn compose(
+        &self,
+        _metrics: &mu_epub_render::PageMetrics,
+        viewport: OverlaySize,
+    ) -> Vec<OverlayItem> {
+        if let Ok(mut seen) = self.0.lock() {
+            *seen = Some(viewport);
+        }
+        Vec::with_capacity(0)
+    }
+}
+
+#[test]
+fn rotation_swaps_viewport_and_changes_profile() {
+    let mut opts = RenderEngineOptions::for_display(420, 180);
+    opts.layout.margin_left = 4;
+    opts.layout.margin_right = 4;
+    opts.rotation = Rotation::Deg90;
+    let rotated = RenderEngine::new(opts);
+    assert_ne!(
+        rotated.pagination_profile_id(),
+        RenderEngine::new(RenderEngineOptions::for_display(420, 180)).pagination_profile_id()
+    );
+
+    let mut book = open_fixture_book();
+    let (chapter, _) = chapter_with_min_pages(&rotated, &mut book, 1)
+        .expect("fixture should contain at least one renderable chapter");
+    let probe = ViewportProbe::default();
+    let mut pages = Vec::with_capacity(8);
+    rotated
+        .prepare_chapter_with_overlay_composer(
+            &mut book,
+            chapter,
+            OverlaySize {
+                width: 420,
+                height: 180,
+            },
+            &probe,
+            |p| pages.push(p),
+        )
+        .expect("rotated render should succeed");
+    assert_eq!(
+        *probe.0.lock().expect("probe lock"),
+        Some(OverlaySize {
+            width: 180,
+            height: 420
+        })
+    );
+    for page in &pages {
+        for cmd in &page.content_commands {
+            if let Some(bounds) = cmd.bounds() {
+                assert!(bounds.x < 180, "{cmd:?} outside rotated width");
+                assert!(bounds.y < 420, "{cmd:?} outside rotated height");
+            }
+        }
+    }
+}
+
+#[test]
+fn dpi_sets_physical_page_metrics() {
+    let mut opts = RenderEngineOptions::for_display(420, 180);
+    opts.dpi = 300.0;
+    let engine = RenderEngine::new(opts);
+    assert_ne!(
+        engine.pagination_profile_id(),
+        RenderEngine::new(RenderEngineOptions::for_display(420, 180)).pagination_profile_id()
+    );
+    let mut book = open_fixture_book();
+    let (chapter, _) = chapter_with_min_pages(&engine, &mut book, 1)
+        .expect("fixture should contain at least one renderable chapter");
+    let pages = engine
+        .prepare_chapter(&mut book, chapter)
+        .expect("render should succeed");
+    let metrics = pages[0].metrics;
+    let width = metrics.physical_width_mm.expect("width known");
+    let height = metrics.physical_height_mm.expect("height known");
+    assert!((width - 35.56).abs() < 0.01);
+    assert!((height - 15.24).abs() < 0.01);
+}
+
 #[test]
 fn diagnostic_sink_receives_reflow_timing() {
     let mut engine = build_engine();
-    let seen = Arc::new(Mutex::new(Vec::<RenderDiagnostic>::new()));
+    let seen = Arc::new(Mutex::new(Vec::<RenderDiagnostic>::with_capacity(4)));
     let seen_clone = Arc::clone(&seen);
     engine.set_diagnostic_sink(move |d| {
         if let Ok(mut sink) = seen_clone.lock() {
@@ -242,6 +443,67 @@ fn diagnostic_sink_receives_reflow_timing() {
         .any(|d| matches!(d, RenderDiagnostic::ReflowTimeMs(_))));
 }
 
+#[test]
+fn diagnostic_sink_receives_phase_timing_scratch_and_cache_lookups() {
+    let mut engine = build_engine();
+    let seen = Arc::new(Mutex::new(Vec::<RenderDiagnostic>::with_capacity(16)));
+    let seen_clone = Arc::clone(&seen);
+    engine.set_diagnostic_sink(move |d| {
+        if let Ok(mut sink) = seen_clone.lock() {
+            sink.push(d);
+        }
+    });
+    let mut book = open_fixture_book();
+    let (chapter, _) = chapter_with_min_pages(&engine, &mut book, 1)
+        .expect("fixture should contain a chapter with pages");
+    seen.lock().expect("diag lock").clear();
+
+    let cache = ChapterCache::default();
+    for _ in 0..2 {
+        engine
+            .prepare_chapter_with_config_collect(
+                &mut book,
+                chapter,
+                RenderConfig::default().with_cache(&cache),
+            )
+            .expect("prepare should pass");
+    }
+    let diagnostics = seen.lock().expect("diag lock").clone();
+    let phases: Vec<RenderPhase> = diagnostics
+        .iter()
+        .filter_map(|d| match d {
+            RenderDiagnostic::PhaseTime {
+                chapter_index,
+                phase,
+                ..
+            } if *chapter_index == chapter => Some(*phase),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        phases,
+        [
+            RenderPhase::Tokenize,
+            RenderPhase::Style,
+            RenderPhase::LineBreak,
+            RenderPhase::Paginate
+        ],
+        "only the uncached run is timed"
+    );
+    assert!(diagnostics.iter().any(|d| matches!(
+        d,
+        RenderDiagnostic::PeakScratchBytes { bytes, .. } if *bytes > 0
+    )));
+    let lookups: Vec<bool> = diagnostics
+        .iter()
+        .filter_map(|d| match d {
+            RenderDiagnostic::CacheLookup { hit, .. } => Some(*hit),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(lookups, [false, true]);
+}
+
 #[derive(Default)]
 struct CacheSpy {
     loads: Mutex<usize>,
@@ -363,3 +625,1616 @@ fn prepare_chapter_collect_enforces_max_pages_in_memory() {
         }
     ));
 }
+
+#[test]
+fn prepare_chapter_collect_enforces_page_byte_budget() {
+    let baseline_engine = build_engine();
+    let mut baseline_book = open_fixture_book();
+    let (chapter, pages) = chapter_with_min_pages(&baseline_engine, &mut baseline_book, 2)
+        .expect("fixture should contain a chapter with at least 2 pages");
+    let first_bytes = pages[0].approx_bytes();
+    assert!(first_bytes > core::mem::size_of::<RenderPage>());
+
+    let mut opts = RenderEngineOptions::for_display(420, 180);
+    opts.layout.page_chrome = PageChromeConfig {
+        progress_enabled: true,
+        footer_enabled: true,
+        ..PageChromeConfig::default()
+    };
+    opts.prep.memory.max_page_bytes_in_memory = first_bytes;
+    let engine = RenderEngine::new(opts);
+    let mut book = open_fixture_book();
+
+    let err = engine
+        .prepare_chapter(&mut book, chapter)
+        .expect_err("collect path should enforce max_page_bytes_in_memory");
+    assert!(matches!(
+        err,
+        RenderEngineError::LimitExceeded {
+            kind: "max_page_bytes_in_memory",
+            limit,
+            ..
+        } if limit == first_bytes
+    ));
+}
+
+#[test]
+fn consecutive_pages_carry_damage_for_text_and_progress_band() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let (_, pages) = chapter_with_min_pages(&engine, &mut book, 2)
+        .expect("fixture should contain a chapter with at least 2 pages");
+
+    assert!(pages[0].damage.is_none());
+    let damage = pages[1]
+        .damage
+        .as_ref()
+        .expect("second page should have bounded damage");
+    assert!(!damage.is_empty());
+    // Progress advances between pages, so the footer band must be dirty.
+    assert!(damage.iter().any(|rect| rect.bottom() == 180));
+}
+
+#[test]
+fn book_page_counts_fill_book_metrics_and_progress_chrome() {
+    let mut opts = RenderEngineOptions::for_display(420, 180);
+    opts.layout.page_chrome = PageChromeConfig {
+        book_progress_enabled: true,
+        ..PageChromeConfig::default()
+    };
+    let engine = RenderEngine::new(opts);
+    let mut book = open_fixture_book();
+    let counts = engine
+        .book_page_counts(&mut book)
+        .expect("book pagination should succeed");
+    assert_eq!(counts.len(), book.chapter_count());
+    let total: usize = counts.iter().sum();
+    let chapter = counts
+        .iter()
+        .position(|&count| count >= 2)
+        .expect("fixture should contain a multi-page chapter");
+
+    let pages = engine
+        .prepare_chapter_with_config_collect(
+            &mut book,
+            chapter,
+            RenderConfig::default().with_book_page_counts(&counts),
+        )
+        .expect("render should succeed");
+    let before: usize = counts[..chapter].iter().sum();
+    let first = &pages[0].metrics;
+    assert_eq!(first.global_page_index, Some(before));
+    assert_eq!(first.global_page_count_estimate, Some(total));
+    assert_eq!(first.pages_left_in_chapter, Some(counts[chapter] - 1));
+    assert_eq!(first.pages_left_in_book, Some(total - before - 1));
+
+    let chrome = pages[0]
+        .chrome_commands
+        .iter()
+        .find_map(|cmd| match cmd {
+            DrawCommand::PageChrome(chrome) if chrome.kind == PageChromeKind::BookProgress => {
+                chrome.text.clone()
+            }
+            _ => None,
+        })
+        .expect("book progress chrome should carry text");
+    let percent = first.percent_complete().expect("percent should be known");
+    assert!(chrome.starts_with(&format!("{}% ", percent)));
+    assert!(chrome.ends_with("left"));
+}
+
+#[test]
+fn footer_template_expands_chapter_title_and_page_counts() {
+    let mut opts = RenderEngineOptions::for_display(420, 180);
+    opts.layout.page_chrome = PageChromeConfig {
+        footer_enabled: true,
+        footer_template: Some("{chapter_title}|{page}/{chapter_pages}"),
+        ..PageChromeConfig::default()
+    };
+    let engine = RenderEngine::new(opts);
+    let mut book = open_fixture_book();
+    book.ensure_navigation().expect("navigation should load");
+    let (chapter, label) = book
+        .chapters()
+        .find_map(|chapter| {
+            let toc = book.toc()?;
+            let point = toc.iter().find(|point| {
+                let base = point.href.split('#').next().unwrap_or_default();
+                !base.is_empty() && chapter.href.ends_with(base)
+            })?;
+            Some((chapter.index, point.label.clone()))
+        })
+        .expect("fixture TOC should point at a spine chapter");
+
+    let pages = engine
+        .prepare_chapter(&mut book, chapter)
+        .expect("render should succeed");
+    let footer = pages[0]
+        .chrome_commands
+        .iter()
+        .find_map(|cmd| match cmd {
+            DrawCommand::PageChrome(chrome) if chrome.kind == PageChromeKind::Footer => {
+                chrome.text.clone()
+            }
+            _ => None,
+        })
+        .expect("footer chrome should carry text");
+    assert_eq!(footer, format!("{}|1/{}", label, pages.len()));
+    assert_eq!(pages[0].chapter_title.as_deref(), Some(label.as_str()));
+}
+
+#[test]
+fn rendered_pages_validate_clean() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let viewport = OverlaySize {
+        width: 420,
+        height: 180,
+    };
+    for chapter in 0..book.chapter_count() {
+        let pages = engine
+            .prepare_chapter(&mut book, chapter)
+            .expect("render should succeed");
+        for page in &pages {
+            let findings = page.validate(&page.metrics, viewport);
+            assert!(
+                findings.is_empty(),
+                "chapter {chapter} page {}: {findings:?}",
+                page.page_number
+            );
+        }
+    }
+}
+
+#[test]
+fn book_page_map_supports_go_to_global_page() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let map = engine
+        .paginate_book(&mut book, &NeverCancel, |_| {})
+        .expect("book pagination should succeed");
+    assert_eq!(
+        map.total_pages(),
+        map.chapter_page_counts().iter().sum::<usize>()
+    );
+    let target = map.total_pages() / 2;
+    let (chapter, chapter_page) = map.locate(target).expect("target should be in range");
+
+    let page = engine
+        .prepare_book_page(&mut book, &map, target)
+        .expect("render should succeed")
+        .expect("page should exist");
+    assert_eq!(page.metrics.chapter_index, chapter);
+    assert_eq!(page.metrics.chapter_page_index, chapter_page);
+    assert_eq!(page.metrics.global_page_index, Some(target));
+    assert_eq!(
+        page.metrics.global_page_count_estimate,
+        Some(map.total_pages())
+    );
+    assert!(engine
+        .prepare_book_page(&mut book, &map, map.total_pages())
+        .expect("render should succeed")
+        .is_none());
+}
+
+#[test]
+fn book_targets_resolve_with_and_without_map() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let map = engine
+        .paginate_book(&mut book, &NeverCancel, |_| {})
+        .expect("book pagination should succeed");
+    let target = map.total_pages() / 2;
+    let (chapter, chapter_page) = map.locate(target).expect("target should be in range");
+
+    let counted = engine
+        .prepare_book_target(&mut book, None, BookTarget::GlobalPage(target))
+        .expect("render should succeed")
+        .expect("page should exist");
+    assert_eq!(counted.metrics.chapter_index, chapter);
+    assert_eq!(counted.metrics.chapter_page_index, chapter_page);
+
+    let exact = engine
+        .prepare_book_target(&mut book, Some(&map), BookTarget::Fraction(0.5))
+        .expect("render should succeed")
+        .expect("page should exist");
+    assert_eq!(exact.metrics.global_page_index, Some(target));
+
+    let (last_chapter, last_page) = map
+        .locate(map.total_pages() - 1)
+        .expect("book should have pages");
+    let end = engine
+        .prepare_book_target(&mut book, None, BookTarget::Fraction(1.0))
+        .expect("render should succeed")
+        .expect("page should exist");
+    assert_eq!(end.metrics.chapter_index, last_chapter);
+    assert_eq!(end.metrics.chapter_page_index, last_page);
+    let estimated = engine
+        .prepare_book_target(&mut book, None, BookTarget::Fraction(0.5))
+        .expect("render should succeed")
+        .expect("page should exist");
+    assert!(estimated.metrics.chapter_index <= last_chapter);
+    assert!(engine
+        .prepare_book_target(&mut book, None, BookTarget::GlobalPage(map.total_pages()))
+        .expect("render should succeed")
+        .is_none());
+}
+
+#[test]
+fn locator_survives_layout_changes() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let (chapter, pages) =
+        chapter_with_min_pages(&engine, &mut book, 4).expect("fixture should have long chapter");
+    let locator = engine
+        .locate(&pages[2])
+        .with_chapter_href(book.chapter(chapter).expect("chapter").href);
+    assert_eq!(locator.chapter_index, chapter);
+    assert!(pages[2].metrics.start_position.is_some());
+    assert!(pages[1].metrics.start_position < pages[2].metrics.start_position);
+
+    let mut opts = RenderEngineOptions::for_display(300, 260);
+    opts.layout.margin_left = 12;
+    opts.layout.margin_right = 20;
+    let reflowed = RenderEngine::new(opts);
+    let page = reflowed
+        .page_for(&mut book, &locator)
+        .expect("resolve should succeed")
+        .expect("page should exist");
+    assert_eq!(page.metrics.chapter_index, chapter);
+    let all = reflowed
+        .prepare_chapter(&mut book, chapter)
+        .expect("reflowed chapter should render");
+    let index = page.metrics.chapter_page_index;
+    assert_eq!(all[index], page);
+    assert!(page.metrics.start_position <= Some(locator.position));
+    if let Some(next) = all.get(index + 1) {
+        assert!(next.metrics.start_position > Some(locator.position));
+    }
+
+    let missing = ContentLocator::new(usize::MAX, locator.position);
+    assert!(reflowed
+        .page_for(&mut book, &missing)
+        .expect("resolve should succeed")
+        .is_none());
+}
+
+#[derive(Default)]
+struct ChapterCache {
+    pages: Mutex<std::collections::HashMap<usize, Vec<RenderPage>>>,
+    stores: Mutex<usize>,
+}
+
+impl RenderCacheStore for ChapterCache {
+    fn load_chapter_pages(
+        &self,
+        _profile: PaginationProfileId,
+        chapter_index: usize,
+    ) -> Option<Vec<RenderPage>> {
+        self.pages
+            .lock()
+            .expect("pages lock")
+            .get(&chapter_index)
+            .cloned()
+    }
+
+    fn store_chapter_pages(
+        &self,
+        _profile: PaginationProfileId,
+        chapter_index: usize,
+        pages: &[RenderPage],
+    ) {
+        *self.stores.lock().expect("store lock") += 1;
+        self.pages
+            .lock()
+            .expect("pages lock")
+            .insert(chapter_index, pages.to_vec());
+    }
+}
+
+struct CancelFlag(std::sync::atomic::AtomicBool);
+
+impl CancelToken for CancelFlag {
+    fn is_cancelled(&self) -> bool {
+        self.0.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+#[test]
+fn paginate_book_fills_cache_reports_progress_and_resumes() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let cache = ChapterCache::default();
+    let cancel = CancelFlag(std::sync::atomic::AtomicBool::new(false));
+
+    // Cancel after the first chapter; the cached chapter survives.
+    let config = RenderConfig::default()
+        .with_cache(&cache)
+        .with_cancel(&cancel);
+    let result = engine.paginate_book_with_config(&mut book, config, |_| {
+        cancel.0.store(true, std::sync::atomic::Ordering::Relaxed)
+    });
+    assert!(matches!(result, Err(RenderEngineError::Cancelled)));
+    let stored_before_resume = *cache.stores.lock().expect("store lock");
+    assert!(stored_before_resume <= 1);
+
+    let mut reports = Vec::with_capacity(book.chapter_count());
+    let map = engine
+        .paginate_book_with_config(&mut book, RenderConfig::default().with_cache(&cache), |p| {
+            reports.push(p)
+        })
+        .expect("resumed pagination should succeed");
+    assert_eq!(reports.len(), book.chapter_count());
+    let last = reports.last().expect("fixture has chapters");
+    assert_eq!(last.pages_so_far, map.total_pages());
+    assert_eq!(last.fraction(), 1.0);
+    let non_empty = map.chapter_page_counts().iter().filter(|c| **c > 0).count();
+    assert_eq!(
+        *cache.stores.lock().expect("store lock"),
+        non_empty,
+        "each non-empty chapter is stored exactly once"
+    );
+    assert_eq!(
+        map,
+        engine
+            .paginate_book(&mut book, &NeverCancel, |_| {})
+            .expect("uncached pagination should succeed")
+    );
+}
+
+#[test]
+fn profiling_aggregates_whole_book_pagination() {
+    let mut engine = build_engine();
+    let mut book = open_fixture_book();
+    assert!(engine.profile_report().is_none());
+    engine.set_profiling(true);
+    let map = engine
+        .paginate_book(&mut book, &NeverCancel, |_| {})
+        .expect("pagination should succeed");
+
+    let report = engine.profile_report().expect("profiling is on");
+    assert_eq!(report.pages_emitted, map.total_pages() as u64);
+    assert_eq!(report.chapters_prepared, book.chapter_count() as u64);
+    assert_eq!(report.chapters.len(), book.chapter_count());
+    assert!(report.chapters.iter().all(|chapter| chapter.runs == 1));
+    assert!(report.bytes_decompressed > 0);
+    assert!(report.peak_page_bytes > 0);
+    assert!(report.phase_micros(RenderPhase::LineBreak) > 0);
+    assert_eq!(report.slowest_chapters(2).len(), 2);
+    assert!(report.to_string().contains("linebreak"));
+    assert!(report.to_json().contains("\"pages_emitted\": "));
+
+    engine.reset_profile();
+    let empty = engine.profile_report().expect("profiling is on");
+    assert!(empty.chapters.is_empty());
+    assert_eq!(empty.pages_emitted, 0);
+
+    let mut opts = RenderEngineOptions::for_display(420, 180);
+    opts.deterministic = true;
+    let mut deterministic = RenderEngine::new(opts);
+    deterministic.set_profiling(true);
+    deterministic
+        .paginate_book(&mut book, &NeverCancel, |_| {})
+        .expect("pagination should succeed");
+    let report = deterministic.profile_report().expect("profiling is on");
+    assert_eq!(report.total_micros(), 0);
+    assert!(report.pages_emitted > 0);
+
+    deterministic.set_profiling(false);
+    assert!(deterministic.profile_report().is_none());
+}
+
+#[test]
+fn memory_observer_sees_page_buffers_and_image_decode() {
+    static TALLY: MemoryTally = MemoryTally::new();
+    set_memory_observer(&TALLY);
+    let engine = build_engine();
+    let mut book = image_book();
+    let pages = engine
+        .prepare_chapter(&mut book, 0)
+        .expect("image chapter should render");
+    assert!(!pages.is_empty());
+
+    // Other tests in this binary report to the same observer, so only
+    // peaks are stable here.
+    for subsystem in [MemorySubsystem::ZipScratch, MemorySubsystem::PageBuffers] {
+        assert!(TALLY.peak(subsystem) > 0, "{}", subsystem.name());
+    }
+    assert!(TALLY.peak(MemorySubsystem::ImageDecode) >= 8 * 8);
+}
+
+#[test]
+fn search_streams_page_hits_with_rects() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let (chapter, pages) =
+        chapter_with_min_pages(&engine, &mut book, 2).expect("fixture should have long chapter");
+    let text = pages[1].plain_text();
+    let word = text
+        .split(|ch: char| !ch.is_alphabetic())
+        .find(|word| word.chars().count() >= 6)
+        .expect("page should have a long word")
+        .to_uppercase();
+
+    let mut hits = Vec::with_capacity(4);
+    engine
+        .search(
+            &mut book,
+            &word,
+            SearchOptions::default(),
+            &NeverCancel,
+            |hit| hits.push(hit),
+        )
+        .expect("search should succeed");
+    let hit = hits
+        .iter()
+        .find(|hit| hit.chapter_index == chapter && hit.chapter_page_index == 1)
+        .expect("hit on source page");
+    assert!(!hit.hit.rects.is_empty());
+    let matched: String = text
+        .chars()
+        .skip(hit.hit.range.start)
+        .take(hit.hit.range.len())
+        .collect();
+    assert_eq!(matched.to_uppercase(), word);
+    assert!(hits
+        .windows(2)
+        .all(|pair| (pair[0].chapter_index, pair[0].chapter_page_index)
+            <= (pair[1].chapter_index, pair[1].chapter_page_index)));
+
+    let cancel = CancelFlag(std::sync::atomic::AtomicBool::new(true));
+    let result = engine.search(&mut book, &word, SearchOptions::default(), &cancel, |_| {});
+    assert!(matches!(result, Err(RenderEngineError::Cancelled)));
+}
+
+#[test]
+fn annotations_follow_text_across_reflow() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let (chapter, pages) =
+        chapter_with_min_pages(&engine, &mut book, 2).expect("fixture should have long chapter");
+    let page = &pages[1];
+    let len = page.plain_text().chars().count();
+    let selection = page.text_selection(len / 4..len / 2);
+    let annotation = Annotation::from_selection(1, page, selection.range.clone())
+        .expect("layout should record line positions")
+        .with_note("reflow");
+    let store = MemoryAnnotationStore::new();
+    store.save_annotation(&annotation);
+
+    let mut opts = RenderEngineOptions::for_display(300, 260);
+    opts.layout.margin_left = 12;
+    let reflowed = RenderEngine::new(opts);
+    let config = RenderConfig::default().with_annotations(&store);
+    let pages = reflowed
+        .prepare_chapter_with_config_collect(&mut book, chapter, config)
+        .expect("reflowed chapter should render");
+    let mut covered = String::with_capacity(selection.text.len());
+    let mut marked_pages = 0;
+    for page in &pages {
+        let text: Vec<char> = page.plain_text().chars().collect();
+        for range in page.annotation_ranges(&annotation) {
+            covered.extend(&text[range]);
+            covered.push(' ');
+        }
+        marked_pages += usize::from(!page.overlay_items.is_empty());
+    }
+    let words = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+    assert_eq!(words(&covered), words(&selection.text));
+    assert!(marked_pages >= 1);
+}
+
+#[test]
+fn bookmark_indicator_marks_only_bookmarked_page() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let (chapter, pages) =
+        chapter_with_min_pages(&engine, &mut book, 2).expect("fixture should have long chapter");
+    let store = MemoryBookmarkStore::new();
+    store.save_bookmark(&Bookmark::new(1, engine.locate(&pages[1]), "second page"));
+    assert_eq!(store.list_bookmarks().len(), 1);
+
+    let config = RenderConfig::default().with_bookmarks(&store);
+    let marked = engine
+        .prepare_chapter_with_config_collect(&mut book, chapter, config)
+        .expect("chapter should render");
+    assert_eq!(marked.len(), pages.len());
+    for (idx, (plain, page)) in pages.iter().zip(&marked).enumerate() {
+        let extra = page.chrome_commands.len() - plain.chrome_commands.len();
+        assert_eq!(extra, usize::from(idx == 1), "page {idx}");
+    }
+}
+
+#[test]
+fn spread_mode_pairs_half_width_pages_with_shared_chrome() {
+    let mut opts = RenderEngineOptions::for_display(840, 180);
+    opts.layout.page_chrome = PageChromeConfig {
+        progress_enabled: true,
+        footer_enabled: true,
+        ..PageChromeConfig::default()
+    };
+    opts.spread = Some(SpreadOptions {
+        gutter: 20,
+        chapter_start: SpreadStart::Recto,
+    });
+    let engine = RenderEngine::new(opts);
+    let mut book = open_fixture_book();
+    let (chapter, pages) =
+        chapter_with_min_pages(&engine, &mut book, 3).expect("fixture should have long chapter");
+    let spreads: Vec<RenderSpread> = engine
+        .prepare_chapter_spreads(&mut book, chapter)
+        .expect("spreads should render");
+
+    assert!(spreads[0].left.is_none());
+    assert_eq!(spreads[0].right_x, 430);
+    let paired: Vec<usize> = spreads
+        .iter()
+        .flat_map(|spread| spread.pages().map(|page| page.page_number))
+        .collect();
+    let expected: Vec<usize> = pages.iter().map(|page| page.page_number).collect();
+    assert_eq!(paired, expected);
+    for spread in &spreads {
+        assert!(!spread.chrome_commands.is_empty());
+        for page in spread.pages() {
+            assert!(!page
+                .chrome_commands
+                .iter()
+                .any(|cmd| matches!(cmd, DrawCommand::PageChrome(_))));
+            let viewport = OverlaySize {
+                width: 410,
+                height: 180,
+            };
+            assert!(page.validate(&page.metrics, viewport).is_empty());
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_render {
+    use super::*;
+    use core::future::Future;
+    use core::pin::{pin, Pin};
+    use core::task::{Context, Poll, Waker};
+    use futures_core::Stream;
+    use mu_epub::EpubError;
+    use mu_epub_render::{AsyncBookSource, AsyncRenderError};
+
+    /// Future that is pending once before resolving, like a storage read.
+    struct Delayed<T>(Option<T>, bool);
+
+    impl<T: Unpin> Future for Delayed<T> {
+        type Output = T;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+            if !self.1 {
+                self.1 = true;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            match self.0.take() {
+                Some(value) => Poll::Ready(value),
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    struct FixtureSource {
+        book: EpubBook<std::fs::File>,
+    }
+
+    impl AsyncBookSource for FixtureSource {
+        type Reader = std::fs::File;
+        type Error = EpubError;
+
+        fn load_chapter(
+            &mut self,
+            chapter_index: usize,
+        ) -> impl Future<Output = Result<Vec<u8>, EpubError>> {
+            let html = self
+                .book
+                .chapter_html(chapter_index)
+                .map(String::into_bytes);
+            Delayed(Some(html), false)
+        }
+
+        fn book(&mut self) -> &mut EpubBook<std::fs::File> {
+            &mut self.book
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn async_prepare_and_stream_match_sync_render() {
+        let engine = build_engine();
+        let mut book = open_fixture_book();
+        let (chapter, expected) = chapter_with_min_pages(&engine, &mut book, 2)
+            .expect("fixture should contain a chapter with at least 2 pages");
+        let mut source = FixtureSource { book };
+
+        let pages = block_on(engine.prepare_chapter_async(&mut source, chapter))
+            .expect("async render should succeed");
+        assert_eq!(pages, expected);
+
+        let missing = block_on(engine.prepare_chapter_async(&mut source, usize::MAX));
+        assert!(matches!(missing, Err(AsyncRenderError::Source(_))));
+
+        let mut stream =
+            pin!(engine.prepare_chapter_stream(&mut source, chapter, RenderConfig::default()));
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(stream.as_mut().poll_next(&mut cx).is_pending());
+        let mut streamed = Vec::with_capacity(expected.len());
+        while let Poll::Ready(Some(page)) = stream.as_mut().poll_next(&mut cx) {
+            streamed.push(page.expect("streamed page"));
+        }
+        assert_eq!(streamed, expected);
+    }
+}
+
+/// Fires on every `period`-th check, like a background task that keeps
+/// getting preempted.
+struct PeriodicCancel {
+    calls: std::sync::atomic::AtomicUsize,
+    period: usize,
+}
+
+impl CancelToken for PeriodicCancel {
+    fn is_cancelled(&self) -> bool {
+        let calls = self
+            .calls
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+            + 1;
+        calls % self.period == 0
+    }
+}
+
+#[test]
+fn resumable_prepare_continues_from_checkpoint_after_cancel() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let (chapter, expected) = chapter_with_min_pages(&engine, &mut book, 3)
+        .expect("fixture should contain a chapter with at least 3 pages");
+    let cancel = PeriodicCancel {
+        calls: std::sync::atomic::AtomicUsize::new(0),
+        period: 25,
+    };
+
+    let mut run = engine
+        .prepare_chapter_resumable(
+            &mut book,
+            chapter,
+            RenderConfig::default().with_cancel(&cancel),
+        )
+        .expect("resumable run should start");
+    let mut interruptions = 0usize;
+    let mut consumed = 0usize;
+    let pages = loop {
+        match run {
+            ChapterRun::Complete(pages) => break pages,
+            ChapterRun::Interrupted(checkpoint) => {
+                interruptions += 1;
+                assert!(interruptions < 10_000, "resume must make progress");
+                assert_eq!(checkpoint.chapter_index(), chapter);
+                assert!(checkpoint.items_consumed() >= consumed);
+                consumed = checkpoint.items_consumed();
+                run = engine
+                    .resume_chapter(
+                        &mut book,
+                        checkpoint,
+                        RenderConfig::default().with_cancel(&cancel),
+                    )
+                    .expect("resume should succeed");
+            }
+        }
+    };
+    assert!(interruptions > 0);
+    assert_eq!(pages, expected);
+
+    let cancel_all = AlreadyCancelled;
+    let ChapterRun::Interrupted(checkpoint) = engine
+        .prepare_chapter_resumable(
+            &mut book,
+            chapter,
+            RenderConfig::default().with_cancel(&cancel_all),
+        )
+        .expect("cancelled run still returns a checkpoint")
+    else {
+        panic!("an always-cancelled run cannot complete");
+    };
+    assert!(checkpoint.pages().is_empty());
+    let other = RenderEngine::new(RenderEngineOptions::for_display(300, 180));
+    assert!(matches!(
+        other.resume_chapter(&mut book, checkpoint, RenderConfig::default()),
+        Err(RenderEngineError::ProfileMismatch)
+    ));
+}
+
+#[test]
+fn prepare_chapter_to_page_stops_after_target_and_resumes_remainder() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let (chapter, expected) = chapter_with_min_pages(&engine, &mut book, 3)
+        .expect("fixture should contain a chapter with at least 3 pages");
+
+    let ChapterRun::Interrupted(checkpoint) = engine
+        .prepare_chapter_to_page(&mut book, chapter, 1, RenderConfig::default())
+        .expect("priority run should succeed")
+    else {
+        panic!("a chapter longer than the target page must be interrupted");
+    };
+    assert_eq!(checkpoint.pages(), &expected[..2]);
+
+    let ChapterRun::Complete(pages) = engine
+        .resume_chapter(&mut book, checkpoint, RenderConfig::default())
+        .expect("resume should succeed")
+    else {
+        panic!("resume without a page stop must complete");
+    };
+    assert_eq!(pages, expected);
+}
+
+#[test]
+fn prepare_chapter_to_page_stops_tokenizing_at_target() {
+    let engine = build_engine();
+    let mut book = broken_tail_book();
+    assert!(engine.prepare_chapter(&mut book, 0).is_err());
+
+    let ChapterRun::Interrupted(checkpoint) = engine
+        .prepare_chapter_to_page(&mut book, 0, 0, RenderConfig::default())
+        .expect("markup past the target page must not be parsed")
+    else {
+        panic!("a chapter longer than the target page must be interrupted");
+    };
+    assert_eq!(checkpoint.pages().len(), 1);
+}
+
+#[test]
+fn relayout_with_new_margins_keeps_reading_position() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let (chapter, _) = chapter_with_min_pages(&engine, &mut book, 4)
+        .expect("fixture should contain a chapter with at least 4 pages");
+    let mut opts = RenderEngineOptions::for_display(420, 180);
+    let mut session = engine.begin(chapter, RenderConfig::default());
+    let index = session
+        .relayout_with(&mut book, opts, 0)
+        .expect("initial layout should succeed");
+    assert_eq!(index, 0);
+    let mut before = Vec::with_capacity(8);
+    session.drain_pages(|page| before.push(page));
+    let reading = before[2].metrics.start_position.expect("page start");
+
+    opts.layout.margin_left += 60;
+    opts.layout.margin_right += 60;
+    let index = session
+        .relayout_with(&mut book, opts, 2)
+        .expect("relayout should succeed");
+    let mut after = Vec::with_capacity(8);
+    session.drain_pages(|page| after.push(page));
+    assert!(after.len() > before.len());
+    assert!(index >= 2);
+    let start = after[index].metrics.start_position.expect("page start");
+    assert!(start <= reading);
+    assert!(after
+        .get(index + 1)
+        .and_then(|page| page.metrics.start_position)
+        .is_none_or(|next| next > reading));
+}
+
+#[test]
+fn placeholder_policy_replaces_failed_chapter_with_error_page() {
+    let mut opts = RenderEngineOptions::for_display(420, 600);
+    opts.prep.memory.max_entry_bytes = 16;
+    let engine = RenderEngine::new(opts);
+    let mut book = open_fixture_book();
+    let chapter = book.chapter_count() - 1;
+
+    assert!(engine.prepare_chapter(&mut book, chapter).is_err());
+
+    let config = RenderConfig::default().with_error_policy(ChapterErrorPolicy::Placeholder);
+    let pages = engine
+        .prepare_chapter_with_config_collect(&mut book, chapter, config)
+        .expect("placeholder policy should recover");
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].metrics.chapter_index, chapter);
+    let text = pages[0].plain_text();
+    assert!(text.contains("could not be displayed"));
+    assert!(text.contains("render prep failed"));
+}
+
+#[test]
+fn page_cursor_walks_the_book_across_chapters() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let total: usize = engine
+        .book_page_counts(&mut book)
+        .expect("page counts should succeed")
+        .iter()
+        .sum();
+    let mut session = engine.begin(0, RenderConfig::default());
+
+    let mut seen = Vec::with_capacity(total);
+    let mut page = session
+        .current_page(&mut book)
+        .expect("first page should load")
+        .cloned();
+    while let Some(current) = page {
+        seen.push(current);
+        page = session
+            .next_page(&mut book)
+            .expect("page turn should succeed")
+            .cloned();
+    }
+    assert_eq!(seen.len(), total);
+    let last = seen.last().expect("book has pages");
+    let current = session.current_page(&mut book).expect("cursor stays put");
+    assert_eq!(current.map(|page| page.page_number), Some(last.page_number));
+
+    let prev = session
+        .prev_page(&mut book)
+        .expect("page turn back should succeed")
+        .expect("previous page");
+    assert_eq!(prev.page_number, seen[seen.len() - 2].page_number);
+    assert_eq!(
+        prev.metrics.chapter_index,
+        seen[seen.len() - 2].metrics.chapter_index
+    );
+
+    let target = &seen[total / 2];
+    let page = session
+        .goto(&mut book, &engine.locate(target))
+        .expect("goto should succeed")
+        .expect("locator should resolve");
+    assert_eq!(page.metrics.chapter_index, target.metrics.chapter_index);
+    assert_eq!(
+        page.metrics.chapter_page_index,
+        target.metrics.chapter_page_index
+    );
+}
+
+#[test]
+fn prefetch_idle_lays_out_next_chapter_across_interruptions() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let counts = engine
+        .book_page_counts(&mut book)
+        .expect("page counts should succeed");
+    let chapter = (0..counts.len() - 1)
+        .find(|&chapter| counts[chapter] >= 3 && counts[chapter + 1] >= 2)
+        .expect("fixture should contain consecutive multi-page chapters");
+    let pages = engine
+        .prepare_chapter(&mut book, chapter)
+        .expect("chapter render should succeed");
+    let mut session = engine.begin(chapter, RenderConfig::default());
+    session
+        .goto(&mut book, &engine.locate(&pages[pages.len() - 2]))
+        .expect("goto should succeed")
+        .expect("page should exist");
+
+    let cancel = PeriodicCancel {
+        calls: std::sync::atomic::AtomicUsize::new(0),
+        period: 5,
+    };
+    let mut interruptions = 0usize;
+    for _ in 0..1000 {
+        match session
+            .prefetch_idle(&mut book, &cancel)
+            .expect("prefetch should succeed")
+        {
+            PrefetchStatus::Interrupted => interruptions += 1,
+            status => {
+                assert_eq!(status, PrefetchStatus::Ready);
+                break;
+            }
+        }
+    }
+    assert!(interruptions > 0);
+
+    let next = engine
+        .prepare_chapter(&mut book, chapter + 1)
+        .expect("next chapter render should succeed");
+    session.next_page(&mut book).expect("page turn");
+    let page = session
+        .next_page(&mut book)
+        .expect("page turn into next chapter")
+        .expect("next chapter page");
+    assert_eq!(page.metrics.chapter_index, chapter + 1);
+    assert_eq!(page.commands, next[0].commands);
+}
+
+static YIELD_CALLS: [std::sync::atomic::AtomicUsize; 3] = [
+    std::sync::atomic::AtomicUsize::new(0),
+    std::sync::atomic::AtomicUsize::new(0),
+    std::sync::atomic::AtomicUsize::new(0),
+];
+
+fn count_yield(point: YieldPoint) {
+    let slot = match point.phase {
+        RenderPhase::Tokenize => 0,
+        RenderPhase::Paginate => 2,
+        _ => {
+            assert_eq!(point.items % 4, 0);
+            1
+        }
+    };
+    YIELD_CALLS[slot].fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+}
+
+#[test]
+fn yield_hook_runs_between_items_and_pages_without_changing_output() {
+    let mut book = open_fixture_book();
+    let mut opts = RenderEngineOptions::for_display(420, 180);
+    let (chapter, expected) = chapter_with_min_pages(&RenderEngine::new(opts), &mut book, 3)
+        .expect("fixture should contain a chapter with at least 3 pages");
+    opts.yield_hook = Some(YieldHook::new(count_yield, 4));
+    let engine = RenderEngine::new(opts);
+    let pages = engine
+        .prepare_chapter(&mut book, chapter)
+        .expect("chapter render should succeed");
+
+    assert_eq!(pages, expected);
+    let calls = YIELD_CALLS
+        .each_ref()
+        .map(|calls| calls.load(std::sync::atomic::Ordering::Relaxed));
+    assert_eq!(calls[0], 1);
+    assert!(calls[1] > 0);
+    assert_eq!(calls[2], pages.len());
+}
+
+#[test]
+fn capacity_hint_evicts_least_recently_rendered_chapter_files() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let root = std::env::temp_dir().join(format!("mu-epub-evict-{}", std::process::id()));
+    let store =
+        DirCacheStore::new(&root, BookFingerprint::from_book(&book)).with_capacity(CacheCapacity {
+            max_chapters: Some(2),
+            max_bytes: None,
+        });
+    let profile = engine.pagination_profile_id();
+    let config = RenderConfig::default().with_cache(&store);
+    engine
+        .paginate_book_with_config(&mut book, config.clone(), |_| {})
+        .expect("pagination should succeed");
+    let cached: Vec<usize> = (0..book.chapter_count())
+        .filter(|&chapter| store.chapter_path(profile, chapter).exists())
+        .collect();
+    let last = book.chapter_count() - 1;
+    assert_eq!(cached, vec![last - 1, last]);
+
+    // A cache hit counts as a render, so chapter `last - 1` outlives `last`.
+    engine
+        .prepare_chapter_with_config_collect(&mut book, last - 1, config.clone())
+        .expect("cached chapter should load");
+    engine
+        .prepare_chapter_with_config_collect(&mut book, 0, config)
+        .expect("chapter render should succeed");
+    assert!(store.chapter_path(profile, 0).exists());
+    assert!(store.chapter_path(profile, last - 1).exists());
+    assert!(!store.chapter_path(profile, last).exists());
+    let _ = std::fs::remove_dir_all(root);
+}
+
+#[test]
+fn reverse_pagination_fills_back_from_a_deep_locator() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let (chapter, forward) = chapter_with_min_pages(&engine, &mut book, 4)
+        .expect("fixture should contain a chapter with at least 4 pages");
+    let target = forward.len() - 1;
+    let anchor = forward[target].metrics.start_position;
+
+    let reverse: Vec<RenderPage> = engine
+        .prepare_chapter_reverse(&mut book, chapter, anchor)
+        .expect("reverse layout should succeed")
+        .collect();
+    assert!(!reverse.is_empty());
+    let starts: Vec<_> = reverse
+        .iter()
+        .map(|page| page.metrics.start_position.expect("page start"))
+        .collect();
+    assert!(starts.windows(2).all(|pair| pair[1] < pair[0]));
+    assert!(starts[0] < anchor.expect("anchor"));
+    assert_eq!(reverse[0].metrics.chapter_index, chapter);
+    assert_eq!(reverse[0].page_number, 1);
+    let reverse_words: usize = reverse.iter().map(|page| page.metrics.word_count).sum();
+    let prefix_words: usize = forward[..target]
+        .iter()
+        .map(|page| page.metrics.word_count)
+        .sum();
+    assert_eq!(reverse_words, prefix_words);
+}
+
+#[test]
+fn single_page_rebuild_matches_forward_layout() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let (chapter, forward) = chapter_with_min_pages(&engine, &mut book, 3)
+        .expect("fixture should contain a chapter with at least 3 pages");
+    let target = forward.len() / 2;
+
+    // The chapter was just laid out, so only the target page is rebuilt.
+    let page = engine
+        .prepare_single_page(&mut book, chapter, target)
+        .expect("single page render should succeed")
+        .expect("page should exist");
+    assert_eq!(page.content_commands, forward[target].content_commands);
+    assert_eq!(page.metrics, forward[target].metrics);
+    assert_eq!(page.page_number, forward[target].page_number);
+
+    // A fresh engine has no page starts and stops right after the page.
+    let fresh = build_engine();
+    let page = fresh
+        .prepare_single_page(&mut book, chapter, target)
+        .expect("single page render should succeed");
+    assert_eq!(page.as_ref(), Some(&forward[target]));
+    assert_eq!(
+        fresh
+            .prepare_single_page(&mut book, chapter, forward.len())
+            .expect("render should succeed"),
+        None
+    );
+}
+
+#[test]
+fn chapter_ir_relayouts_without_the_book() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let (chapter, forward) = chapter_with_min_pages(&engine, &mut book, 3)
+        .expect("fixture should contain a chapter with at least 3 pages");
+
+    let ir = engine
+        .prepare_chapter_ir(&mut book, chapter)
+        .expect("IR capture should succeed");
+    let ir = ChapterIr::decode(&ir.encode()).expect("IR should round-trip");
+    assert_eq!(engine.prepare_from_ir(&ir).expect("IR layout"), forward);
+
+    // A larger text scale rescales the captured runs.
+    let mut opts = RenderEngineOptions::for_display(420, 180);
+    opts.layout.page_chrome = PageChromeConfig {
+        progress_enabled: true,
+        footer_enabled: true,
+        ..PageChromeConfig::default()
+    };
+    opts.prep.style.hints.text_scale = 1.25;
+    let larger = RenderEngine::new(opts);
+    let expected = larger
+        .prepare_chapter(&mut book, chapter)
+        .expect("direct layout");
+    let from_ir = larger.prepare_from_ir(&ir).expect("IR layout");
+    assert!(from_ir.len() > forward.len());
+    assert_eq!(from_ir, expected);
+}
+
+#[test]
+fn reader_session_switches_books_within_budget_and_restores_position() {
+    let mut session = ReaderSession::new(build_engine()).with_page_budget(1);
+    let first = session.open(open_fixture_book(), None);
+    let mut pages_read = 1usize;
+    let mut at = session
+        .current_page()
+        .expect("first page")
+        .cloned()
+        .expect("book has pages");
+    for _ in 0..3 {
+        match session.next_page().expect("next page") {
+            Some(page) => {
+                assert_ne!(
+                    (page.metrics.chapter_index, page.metrics.start_position),
+                    (at.metrics.chapter_index, at.metrics.start_position)
+                );
+                at = page.clone();
+                pages_read += 1;
+            }
+            None => break,
+        }
+    }
+    assert!(pages_read > 1);
+
+    let second = session.open(open_fixture_book(), None);
+    assert_eq!(session.active(), Some(second));
+    let other = session
+        .current_page()
+        .expect("second book")
+        .cloned()
+        .expect("book has pages");
+    assert_eq!(other.metrics.chapter_index, 0);
+    // Only the active book keeps pages under the tiny budget.
+    let held = session.held_bytes();
+    assert!(session.switch_to(first));
+    let back = session
+        .current_page()
+        .expect("restored page")
+        .cloned()
+        .expect("position resolves");
+    assert_eq!(back.metrics.chapter_index, at.metrics.chapter_index);
+    assert_eq!(back.metrics.start_position, at.metrics.start_position);
+    assert_ne!(session.held_bytes(), held);
+    assert_eq!(session.books().collect::<Vec<_>>(), vec![first, second]);
+
+    let (_, position) = session.close(first).expect("open book");
+    assert_eq!(position.chapter_index, at.metrics.chapter_index);
+    assert_eq!(session.active(), Some(second));
+}
+
+#[test]
+fn export_at_double_scale_keeps_device_pagination() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let (chapter, device) = chapter_with_min_pages(&engine, &mut book, 2)
+        .expect("fixture should contain a chapter with at least 2 pages");
+    let export = engine
+        .prepare_chapter_export(&mut book, chapter, 2)
+        .expect("export should succeed");
+    assert_eq!(export.len(), device.len());
+    for (big, small) in export.iter().zip(&device) {
+        assert_eq!(big.metrics, small.metrics);
+        assert_eq!(big.plain_text(), small.plain_text());
+        assert_eq!(big.content_commands.len(), small.content_commands.len());
+    }
+    let texts = |page: &RenderPage| {
+        page.content_commands
+            .iter()
+            .filter_map(|cmd| match cmd {
+                DrawCommand::Text(text) => Some((text.x, text.baseline_y, text.style.size_px)),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    let doubled = texts(&device[1])
+        .into_iter()
+        .map(|(x, y, size)| (x * 2, y * 2, size * 2.0))
+        .collect::<Vec<_>>();
+    assert!(!doubled.is_empty());
+    assert_eq!(texts(&export[1]), doubled);
+    let page = engine
+        .prepare_page_export(&mut book, chapter, 1, 2)
+        .expect("page export should succeed")
+        .expect("page should exist");
+    assert_eq!(page.content_commands, export[1].content_commands);
+}
+
+#[test]
+fn session_snapshot_resumes_from_warm_cache_without_relayout() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let counts = engine
+        .book_page_counts(&mut book)
+        .expect("page counts should succeed");
+    let chapter = counts
+        .iter()
+        .position(|&pages| pages >= 3)
+        .expect("fixture should contain a chapter with at least 3 pages");
+    let store = LruCacheStore::new(CacheCapacity {
+        max_chapters: Some(8),
+        max_bytes: None,
+    });
+    let config = RenderConfig::default()
+        .with_cache(&store)
+        .with_book_page_counts(&counts);
+    let mut session = engine.begin(chapter, config);
+    session.current_page(&mut book).expect("first page");
+    let reading = session
+        .next_page(&mut book)
+        .expect("page turn should succeed")
+        .cloned()
+        .expect("second page");
+    let snapshot = session.snapshot();
+    assert_eq!(snapshot.locator, engine.locate(&reading));
+    assert!(snapshot.warm_chapters.contains(&(chapter, counts[chapter])));
+
+    let mut bytes = snapshot.encode();
+    let decoded = SessionSnapshot::decode(&bytes).expect("snapshot should decode");
+    assert_eq!(decoded, snapshot);
+    let last = bytes.len() - 5;
+    bytes[last] ^= 0xff;
+    assert!(SessionSnapshot::decode(&bytes).is_err());
+
+    let mut resumed = build_engine();
+    let misses = Arc::new(Mutex::new(0usize));
+    let misses_clone = Arc::clone(&misses);
+    resumed.set_diagnostic_sink(move |d| {
+        if let RenderDiagnostic::CacheLookup { hit: false, .. } = d {
+            if let Ok(mut misses) = misses_clone.lock() {
+                *misses += 1;
+            }
+        }
+    });
+    let mut restored = LayoutSession::restore(
+        &resumed,
+        &mut book,
+        &decoded,
+        RenderConfig::default().with_cache(&store),
+    )
+    .expect("restore should succeed");
+    let page = restored
+        .current_page(&mut book)
+        .expect("restored page")
+        .expect("locator resolves");
+    assert_eq!(page.metrics, reading.metrics);
+    assert_eq!(page.content_commands, reading.content_commands);
+    assert_eq!(misses.lock().map(|misses| *misses).ok(), Some(0));
+}
+
+#[test]
+fn sync_position_round_trips_and_resolves_on_another_profile() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let (chapter, pages) =
+        chapter_with_min_pages(&engine, &mut book, 2).expect("fixture should have 2+ pages");
+    let book_id = BookFingerprint::from_book(&book);
+    let locator = engine.locate(&pages[1]).with_cfi("/6/4!/4/2/1:0");
+    let position = SyncPosition::new(book_id, locator.clone());
+
+    let mut bytes = position.encode();
+    let decoded = SyncPosition::decode(&bytes).expect("position should decode");
+    assert_eq!(decoded, position);
+    let last = bytes.len() - 5;
+    bytes[last] ^= 0xff;
+    assert!(SyncPosition::decode(&bytes).is_err());
+
+    let older = SyncPosition::for_bookmark(book_id, &Bookmark::new(1, locator.clone(), "x"));
+    let older = SyncPosition {
+        updated_unix_secs: older.updated_unix_secs.saturating_sub(60),
+        ..older
+    };
+    assert!(position.supersedes(&older));
+    assert!(!older.supersedes(&position));
+    let other_book = SyncPosition {
+        book: BookFingerprint([7; 32]),
+        ..position.clone()
+    };
+    assert!(!other_book.supersedes(&older));
+
+    // A companion laying out at another size lands on the same content.
+    let mut opts = RenderEngineOptions::for_display(300, 500);
+    opts.layout.margin_left = 12;
+    let companion = RenderEngine::new(opts);
+    let page = companion
+        .page_for(&mut book, &decoded.locator)
+        .expect("locator should resolve")
+        .expect("page should exist");
+    assert_eq!(page.metrics.chapter_index, chapter);
+}
+
+#[test]
+fn engine_telemetry_counts_layout_and_cache_until_reset() {
+    let engine = build_engine();
+    let mut book = open_fixture_book();
+    let (chapter, pages) =
+        chapter_with_min_pages(&engine, &mut book, 2).expect("fixture should have 2+ pages");
+    engine.reset_telemetry();
+    assert_eq!(engine.telemetry(), EngineTelemetry::default());
+
+    let store = LruCacheStore::new(CacheCapacity {
+        max_chapters: Some(4),
+        max_bytes: None,
+    });
+    for _ in 0..2 {
+        let rendered = engine
+            .prepare_chapter_with_config_collect(
+                &mut book,
+                chapter,
+                RenderConfig::default().with_cache(&store),
+            )
+            .expect("render should succeed");
+        assert_eq!(rendered.len(), pages.len());
+    }
+    let telemetry = engine.telemetry();
+    assert_eq!(telemetry.chapters_prepared, 1);
+    assert_eq!(telemetry.cache_misses, 1);
+    assert_eq!(telemetry.cache_hits, 1);
+    assert_eq!(telemetry.pages_emitted, 2 * pages.len() as u64);
+    assert!(telemetry.bytes_decompressed > 0);
+    assert!(telemetry.peak_page_bytes > 0);
+    assert_eq!(engine.clone().telemetry(), telemetry);
+
+    engine.reset_telemetry();
+    assert_eq!(engine.telemetry(), EngineTelemetry::default());
+}
+
+#[test]
+fn decoded_images_are_reused_across_layouts_within_budget() {
+    let mut engine = build_engine();
+    let lookups = Arc::new(Mutex::new(Vec::with_capacity(4)));
+    let lookups_clone = Arc::clone(&lookups);
+    engine.set_diagnostic_sink(move |d| {
+        if let RenderDiagnostic::ImageCacheLookup { src, hit } = d {
+            if let Ok(mut lookups) = lookups_clone.lock() {
+                lookups.push((src, hit));
+            }
+        }
+    });
+    let mut book = image_book();
+    let bitmaps = |pages: &[RenderPage]| -> Vec<_> {
+        pages
+            .iter()
+            .flat_map(|page| &page.content_commands)
+            .filter_map(|cmd| match cmd {
+                DrawCommand::Image(image) => image.bitmap.clone(),
+                _ => None,
+            })
+            .collect()
+    };
+
+    let first = engine.prepare_chapter(&mut book, 0).expect("first layout");
+    let second = engine.prepare_chapter(&mut book, 0).expect("second layout");
+    assert_eq!(bitmaps(&first).len(), 1);
+    assert_eq!(bitmaps(&first), bitmaps(&second));
+    assert_eq!(engine.telemetry().images_decoded, 1);
+    let src = "images/gray.pgm".to_string();
+    assert_eq!(
+        *lookups.lock().expect("lookups"),
+        vec![(src.clone(), false), (src, true)]
+    );
+
+    engine.clear_image_cache();
+    engine.prepare_chapter(&mut book, 0).expect("after clear");
+    assert_eq!(engine.telemetry().images_decoded, 2);
+
+    let mut opts = *engine.options();
+    opts.layout.object_layout.image_cache_bytes = 0;
+    let uncached = RenderEngine::new(opts);
+    for _ in 0..2 {
+        uncached
+            .prepare_chapter(&mut book, 0)
+            .expect("uncached layout");
+    }
+    assert_eq!(uncached.telemetry().images_decoded, 2);
+}
+
+#[test]
+fn deferred_images_reserve_rects_and_patch_in_later() {
+    let engine = build_engine();
+    let mut book = image_book();
+    let layout = |book: &mut EpubBook<Cursor<Vec<u8>>>| {
+        let mut pages = Vec::with_capacity(1);
+        engine
+            .prepare_chapter_with_config(
+                book,
+                0,
+                RenderConfig::default().with_deferred_images(true),
+                |page| pages.push(page),
+            )
+            .expect("deferred layout");
+        pages
+    };
+    let image = |page: &RenderPage| {
+        page.content_commands.iter().find_map(|cmd| match cmd {
+            DrawCommand::Image(image) => Some(image.clone()),
+            _ => None,
+        })
+    };
+
+    let mut page = layout(&mut book).remove(0);
+    assert_eq!(engine.telemetry().images_decoded, 0);
+    let placed = image(&page).expect("image placed");
+    assert!(placed.bitmap.is_none());
+    let rect = PageRect::new(placed.x, placed.y, placed.width, placed.height);
+    assert!(page.annotations.contains(&PageAnnotation::PendingImage {
+        rect,
+        src: "images/gray.pgm".to_string(),
+    }));
+
+    assert_eq!(
+        engine.resolve_pending_images(&mut book, &mut page),
+        vec![rect]
+    );
+    assert_eq!(engine.telemetry().images_decoded, 1);
+    let filled = image(&page).and_then(|image| image.bitmap);
+    assert!(filled.is_some());
+    assert!(!page
+        .annotations
+        .iter()
+        .any(|a| matches!(a, PageAnnotation::PendingImage { .. })));
+    assert!(engine
+        .resolve_pending_images(&mut book, &mut page)
+        .is_empty());
+
+    // Cached pixels attach immediately on the next deferred layout.
+    let again = layout(&mut book).remove(0);
+    assert_eq!(image(&again).and_then(|image| image.bitmap), filled);
+    assert!(!again
+        .annotations
+        .iter()
+        .any(|a| matches!(a, PageAnnotation::PendingImage { .. })));
+    assert_eq!(engine.telemetry().images_decoded, 1);
+}
+
+/// Two chapters whose footnotes sit several pages after their references.
+fn footnote_book() -> EpubBook<Cursor<Vec<u8>>> {
+    let opf = br#"<?xml version="1.0" encoding="UTF-8"?>
+<package version="3.0" xmlns="http://www.idpf.org/2007/opf">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>Notes</dc:title>
+    <dc:identifier>urn:test:notes</dc:identifier>
+    <dc:language>en</dc:language>
+  </metadata>
+  <manifest>
+    <item id="c1" href="ch1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c2" href="ch2.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine>
+    <itemref idref="c1"/>
+    <itemref idref="c2"/>
+  </spine>
+</package>"#;
+    let filler = "<p>Filler prose that takes up room on the page.</p>".repeat(20);
+    let ch1 = format!(
+        r##"<html xmlns="http://www.w3.org/1999/xhtml"><body>
+<p>See <a href="#n1">note one</a> and <a href="ch2.xhtml#n2">note two</a>.</p>
+{filler}<p id="n1">First note.</p></body></html>"##
+    );
+    let ch2 = format!(
+        r#"<html xmlns="http://www.w3.org/1999/xhtml"><body>
+{filler}<aside><p><a id="n2"/>Second note.</p></aside></body></html>"#
+    );
+    let zip = stored_zip(&[
+        ("mimetype", b"application/epub+zip"),
+        ("META-INF/container.xml", SYNTHETIC_CONTAINER),
+        ("EPUB/package.opf", opf),
+        ("EPUB/ch1.xhtml", ch1.as_bytes()),
+        ("EPUB/ch2.xhtml", ch2.as_bytes()),
+    ]);
+    EpubBook::from_reader(Cursor::new(zip)).expect("footnote book should open")
+}
+
+/// One long chapter whose closing markup is ill-formed.
+fn broken_tail_book() -> EpubBook<Cursor<Vec<u8>>> {
+    let opf = br#"<?xml version="1.0" encoding="UTF-8"?>
+<package version="3.0" xmlns="http://www.idpf.org/2007/opf">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>Broken tail</dc:title>
+    <dc:identifier>urn:test:broken-tail</dc:identifier>
+    <dc:language>en</dc:language>
+  </metadata>
+  <manifest>
+    <item id="c1" href="ch1.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine>
+    <itemref idref="c1"/>
+  </spine>
+</package>"#;
+    let filler = "<p>Filler prose that takes up room on the page.</p>".repeat(40);
+    let ch1 = format!(
+        r#"<html xmlns="http://www.w3.org/1999/xhtml"><body>
+{filler}<div></span></body></html>"#
+    );
+    let zip = stored_zip(&[
+        ("mimetype", b"application/epub+zip"),
+        ("META-INF/container.xml", SYNTHETIC_CONTAINER),
+        ("EPUB/package.opf", opf),
+        ("EPUB/ch1.xhtml", ch1.as_bytes()),
+    ]);
+    EpubBook::from_reader(Cursor::new(zip)).expect("broken-tail book should open")
+}
+
+fn internal_links(page: &RenderPage) -> Vec<InternalLink> {
+    page.annotations
+        .iter()
+        .filter_map(|annotation| match annotation {
+            PageAnnotation::Link {
+                target: LinkTarget::Internal(link),
+                ..
+            } => Some(link.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn fragment_links_resolve_to_the_anchor_page() {
+    let engine = build_engine();
+    let mut book = footnote_book();
+    let pages = engine.prepare_chapter(&mut book, 0).expect("chapter 1");
+    let note_page = pages
+        .iter()
+        .position(|page| page.anchors().any(|id| id == "n1"))
+        .expect("n1 is laid out");
+    assert!(note_page > 0);
+
+    let links = internal_links(&pages[0]);
+    assert_eq!(links.len(), 2);
+    assert_eq!(links[0].fragment.as_deref(), Some("n1"));
+    assert_eq!(links[0].chapter_index, Some(0));
+    assert_eq!(links[0].page_index, Some(note_page));
+    assert_eq!(links[1].chapter_index, Some(1));
+    assert_eq!(links[1].page_index, None, "other chapters are not laid out");
+
+    let resolved = engine
+        .resolve_link(&mut book, &links[1], RenderConfig::default())
+        .expect("resolve")
+        .expect("n2 is laid out");
+    let ch2 = engine.prepare_chapter(&mut book, 1).expect("chapter 2");
+    assert!(resolved > 0);
+    assert!(ch2[resolved].anchors().any(|id| id == "n2"));
+    assert_eq!(
+        engine
+            .resolve_link(&mut book, &links[0], RenderConfig::default())
+            .expect("resolve"),
+        Some(note_page)
+    );
+
+    let mut missing = links[1].clone();
+    missing.fragment = Some("nowhere".to_string());
+    assert_eq!(
+        engine
+            .resolve_link(&mut book, &missing, RenderConfig::default())
+            .expect("resolve"),
+        None
+    );
+}
+
+#[test]
+fn chapter_thumbnails_lay_out_at_thumbnail_size_without_decoding() {
+    let engine = build_engine();
+    let mut book = image_book();
+    let cfg = ThumbnailConfig {
+        max_width: 84,
+        max_height: 36,
+        ..ThumbnailConfig::default()
+    };
+    let thumbs = engine
+        .prepare_chapter_thumbnails(&mut book, 0, RenderConfig::default(), &cfg)
+        .expect("thumbnails should render");
+    assert!(!thumbs.is_empty());
+    let mut images = 0;
+    for cmd in thumbs.iter().flat_map(|page| &page.content_commands) {
+        let (x, y, width, height) = match cmd {
+            DrawCommand::Text(_) => panic!("text should become bars: {:?}", cmd),
+            DrawCommand::Rect(rect) => (rect.x, rect.y, rect.width, rect.height),
+            DrawCommand::Image(image) => {
+                images += 1;
+                assert!(image.bitmap.is_none(), "thumbnails skip image decode");
+                (image.x, image.y, image.width, image.height)
+            }
+            _ => continue,
+        };
+        assert!(x >= 0 && x + width as i32 <= 84, "{:?}", cmd);
+        assert!(y >= 0 && y + height as i32 <= 36, "{:?}", cmd);
+    }
+    assert_eq!(images, 1);
+    assert_eq!(engine.telemetry().images_decoded, 0);
+}
+
+#[test]
+fn cover_thumbnail_fits_box_and_applies_intent() {
+    let engine = build_engine();
+    let mut book = image_book();
+    assert_eq!(book.cover_image_href().as_deref(), Some("images/gray.pgm"));
+
+    let thumb = engine
+        .cover_thumbnail(&mut book, 4, 8, &RenderIntent::default())
+        .expect("cover should decode");
+    assert_eq!((thumb.width, thumb.height), (4, 4));
+    assert_eq!(thumb.pixel(0, 0), Some(16));
+    assert_eq!(thumb.pixel(3, 3), Some(208));
+
+    let grown = engine
+        .cover_thumbnail(&mut book, 16, 16, &RenderIntent::default())
+        .expect("small covers grow");
+    assert_eq!((grown.width, grown.height), (16, 16));
+
+    let mono = RenderIntent {
+        color_target: ColorTarget::Mono,
+        ..RenderIntent::default()
+    };
+    let thumb = engine
+        .cover_thumbnail(&mut book, 4, 4, &mono)
+        .expect("cover should decode");
+    assert!(thumb.pixels.iter().all(|&px| px == 0 || px == 255));
+
+    let mut text_only = coverless_book();
+    assert_eq!(text_only.cover_image_href(), None);
+    assert!(engine
+        .cover_thumbnail(&mut text_only, 4, 4, &RenderIntent::default())
+        .is_none());
+}
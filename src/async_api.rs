@@ -11,6 +11,7 @@ use std::path::Path;
 
 use crate::book::{EpubBook, EpubBookOptions};
 use crate::error::EpubError;
+use crate::io::{IoErrorInfo, IoOperation};
 
 /// Read an EPUB file asynchronously and open it as an `EpubBook`.
 ///
@@ -28,6 +29,6 @@ pub async fn open_epub_file_async_with_options<P: AsRef<Path>>(
 ) -> Result<EpubBook<Cursor<Vec<u8>>>, EpubError> {
     let bytes = tokio::fs::read(path)
         .await
-        .map_err(|e| EpubError::Io(e.to_string()))?;
+        .map_err(|e| EpubError::Io(IoErrorInfo::from_std(&e, IoOperation::Read)))?;
     EpubBook::from_reader_with_options(Cursor::new(bytes), options)
 }
@@ -6,21 +6,35 @@
 
 extern crate alloc;
 
+use alloc::boxed::Box;
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use core::str;
 use quick_xml::events::Event;
 use quick_xml::reader::Reader;
+#[cfg(feature = "std")]
 use std::fs::File;
-use std::io::{Read, Seek, Write};
+#[cfg(feature = "std")]
 use std::path::Path;
 
+use crate::conformance::PackageDocuments;
 use crate::error::{
-    EpubError, ErrorLimitContext, ErrorPhase, LimitKind, PhaseError, PhaseErrorContext, ZipError,
+    EpubError, ErrorLimitContext, ErrorPhase, ErrorScope, LimitKind, PhaseError, PhaseErrorContext,
+    ZipError,
 };
-use crate::metadata::{extract_metadata, EpubMetadata};
+#[cfg(feature = "std")]
+use crate::export::{
+    export_markup, inline_styles, sniff_image_type, ExportFormat, ExportImage, HtmlBundle,
+    HtmlExportLimits,
+};
+#[cfg(feature = "std")]
+use crate::io::{IoErrorInfo, IoOperation};
+use crate::io::{Read, Seek, Write};
+use crate::metadata::{extract_metadata, EpubMetadata, ManifestItem};
 use crate::navigation::{parse_nav_xhtml, parse_ncx, NavPoint, Navigation};
+#[cfg(feature = "std")]
+use crate::render_prep::parse_stylesheet_links_bytes;
 use crate::render_prep::{
     parse_font_faces_from_css, parse_stylesheet_links, ChapterStylesheets, EmbeddedFontFace,
     FontLimits, RenderPrep, RenderPrepOptions, StyleLimits, StyledChapter, StyledEventOrRun,
@@ -33,17 +47,24 @@ use crate::zip::{CdEntry, StreamingZip, ZipLimits};
 
 /// Validation strictness for high-level open/parse flows.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum ValidationMode {
     /// Best-effort behavior for partial/quirky EPUBs.
     #[default]
     Lenient,
-    /// Fail early for structural inconsistencies.
+    /// Check OCF/OPF conformance on open and fail on the first violation.
+    ///
+    /// Covers the `mimetype` entry (first, stored), `container.xml` and
+    /// package document structure, duplicate ids, manifest resources present
+    /// with media types matching their extensions, and spine references.
+    /// Violations are `EpubError::Phase` errors in `ErrorPhase::Open`.
     Strict,
 }
 
 /// High-level configuration for opening EPUB books.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EpubBookOptions {
     /// Optional ZIP safety limits used while reading archive entries.
     ///
@@ -67,6 +88,7 @@ impl Default for EpubBookOptions {
 
 /// Compatibility open configuration for embedded-facing APIs.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OpenConfig {
     /// Baseline high-level open options.
     pub options: EpubBookOptions,
@@ -85,6 +107,7 @@ impl From<EpubBookOptions> for OpenConfig {
 
 /// Streaming chapter-event options for bounded extraction.
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChapterEventsOptions {
     /// Render-prep options used to produce event/run stream.
     pub render: RenderPrepOptions,
@@ -203,6 +226,7 @@ impl EpubBookBuilder {
     }
 
     /// Open an EPUB from a file path.
+    #[cfg(feature = "std")]
     pub fn open<P: AsRef<Path>>(self, path: P) -> Result<EpubBook<File>, EpubError> {
         EpubBook::open_with_options(path, self.options)
     }
@@ -213,6 +237,7 @@ impl EpubBookBuilder {
     }
 
     /// Parse summary metadata from a file path.
+    #[cfg(feature = "std")]
     pub fn parse_file<P: AsRef<Path>>(self, path: P) -> Result<EpubSummary, EpubError> {
         parse_epub_file_with_options(path, self.options)
     }
@@ -225,6 +250,7 @@ impl EpubBookBuilder {
 
 /// Parsed top-level EPUB data for lightweight usage.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EpubSummary {
     metadata: EpubMetadata,
     spine: Spine,
@@ -264,16 +290,19 @@ pub fn parse_epub_reader_with_options<R: Read + Seek>(
 }
 
 /// Parse an EPUB from a file path.
+#[cfg(feature = "std")]
 pub fn parse_epub_file<P: AsRef<Path>>(path: P) -> Result<EpubSummary, EpubError> {
     parse_epub_file_with_options(path, EpubBookOptions::default())
 }
 
 /// Parse an EPUB from a file path with explicit options.
+#[cfg(feature = "std")]
 pub fn parse_epub_file_with_options<P: AsRef<Path>>(
     path: P,
     options: EpubBookOptions,
 ) -> Result<EpubSummary, EpubError> {
-    let file = File::open(path).map_err(|e| EpubError::Io(e.to_string()))?;
+    let file = File::open(path)
+        .map_err(|e| EpubError::Io(IoErrorInfo::from_std(&e, IoOperation::Open)))?;
     parse_epub_reader_with_options(file, options)
 }
 
@@ -292,6 +321,7 @@ pub struct EpubBook<R: Read + Seek> {
 
 /// Lightweight chapter descriptor in spine order.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChapterRef {
     /// Spine position index.
     pub index: usize,
@@ -305,6 +335,7 @@ pub struct ChapterRef {
 
 /// Stable reading position with anchor + fallback offset information.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ReadingPosition {
     /// 0-based chapter index in spine order.
     pub chapter_index: usize,
@@ -318,6 +349,7 @@ pub struct ReadingPosition {
 
 /// Semantic navigation primitive for seeking/resolve operations.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Locator {
     /// Resolve by chapter index.
     Chapter(usize),
@@ -641,6 +673,7 @@ fn find_toc_href(nav: &Navigation, id: &str) -> Option<String> {
     visit(&nav.toc, id)
 }
 
+#[cfg(feature = "std")]
 impl EpubBook<File> {
     /// Open an EPUB from disk and parse core structures.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, EpubError> {
@@ -652,7 +685,8 @@ impl EpubBook<File> {
         path: P,
         options: EpubBookOptions,
     ) -> Result<Self, EpubError> {
-        let file = File::open(path).map_err(|e| EpubError::Io(e.to_string()))?;
+        let file = File::open(path)
+            .map_err(|e| EpubError::Io(IoErrorInfo::from_std(&e, IoOperation::Open)))?;
         Self::from_reader_with_options(file, options)
     }
 
@@ -661,7 +695,8 @@ impl EpubBook<File> {
         path: P,
         config: OpenConfig,
     ) -> Result<Self, EpubError> {
-        let file = File::open(path).map_err(|e| EpubError::Io(e.to_string()))?;
+        let file = File::open(path)
+            .map_err(|e| EpubError::Io(IoErrorInfo::from_std(&e, IoOperation::Open)))?;
         Self::from_reader_with_config(file, config)
     }
 
@@ -705,7 +740,8 @@ impl EpubBook<File> {
 
         let options = config.options;
         let mut zip = StreamingZip::new_with_limits(
-            File::open(&epub_path).map_err(|e| EpubError::Io(e.to_string()))?,
+            File::open(&epub_path)
+                .map_err(|e| EpubError::Io(IoErrorInfo::from_std(&e, IoOperation::Open)))?,
             options.zip_limits,
         )
         .map_err(EpubError::Zip)?;
@@ -719,7 +755,7 @@ impl EpubBook<File> {
 
         // Stream container.xml to temp file instead of loading into RAM
         let mut container_file = File::create(&container_temp)
-            .map_err(|e| EpubError::Io(format!("Failed to create temp file: {}", e)))?;
+            .map_err(|e| EpubError::Io(IoErrorInfo::from_std(&e, IoOperation::Create)))?;
         read_entry_into(&mut zip, "META-INF/container.xml", &mut container_file)?;
         drop(container_file);
 
@@ -742,12 +778,13 @@ impl EpubBook<File> {
             uncompressed_size: opf_entry.uncompressed_size,
             local_header_offset: opf_entry.local_header_offset,
             crc32: opf_entry.crc32,
+            flags: opf_entry.flags,
             filename: String::with_capacity(0),
         };
 
         // Stream OPF to temp file instead of loading into RAM
         let mut opf_file = File::create(&opf_temp)
-            .map_err(|e| EpubError::Io(format!("Failed to create temp file: {}", e)))?;
+            .map_err(|e| EpubError::Io(IoErrorInfo::from_std(&e, IoOperation::Create)))?;
         zip.read_file_to_writer(&opf_entry_data, &mut opf_file)
             .map_err(EpubError::Zip)?;
         drop(opf_file);
@@ -765,7 +802,14 @@ impl EpubBook<File> {
         // Clean up OPF temp file
         let _ = std::fs::remove_file(&opf_temp);
 
-        validate_open_invariants(&metadata, &spine, options.validation_mode)?;
+        // The package documents were streamed to disk, so schema checks are
+        // skipped here.
+        let docs = PackageDocuments {
+            container: None,
+            opf: None,
+            opf_path: &opf_path,
+        };
+        validate_open_invariants(&zip, docs, &metadata, &spine, options.validation_mode)?;
 
         // Navigation is deferred if lazy_navigation is enabled
         let (navigation, navigation_loaded) = if config.lazy_navigation {
@@ -829,6 +873,7 @@ impl<R: Read + Seek> EpubBook<R> {
     /// - Supports lazy navigation loading to defer allocation
     /// - Caller buffer required: No
     pub fn from_reader_with_config(reader: R, config: OpenConfig) -> Result<Self, EpubError> {
+        let _span = phase_span!("epub.open");
         let options = config.options;
         let mut zip =
             StreamingZip::new_with_limits(reader, options.zip_limits).map_err(EpubError::Zip)?;
@@ -839,7 +884,12 @@ impl<R: Read + Seek> EpubBook<R> {
         let opf = read_entry(&mut zip, &opf_path)?;
         let metadata = extract_metadata(&container, &opf)?;
         let spine = crate::spine::parse_spine(&opf)?;
-        validate_open_invariants(&metadata, &spine, options.validation_mode)?;
+        let docs = PackageDocuments {
+            container: Some(&container),
+            opf: Some(&opf),
+            opf_path: &opf_path,
+        };
+        validate_open_invariants(&zip, docs, &metadata, &spine, options.validation_mode)?;
         let (navigation, navigation_loaded) = if config.lazy_navigation {
             (None, false)
         } else {
@@ -930,13 +980,16 @@ impl<R: Read + Seek> EpubBook<R> {
     /// This enables embedded callers to pre-size reusable chapter buffers
     /// before invoking chapter streaming/render flows.
     pub fn chapter_uncompressed_size(&mut self, index: usize) -> Result<usize, EpubError> {
-        let chapter = self.chapter(index)?;
-        let zip_path = resolve_opf_relative_path(&self.opf_path, &chapter.href);
-        let entry = self
-            .zip
-            .get_entry(&zip_path)
-            .ok_or(EpubError::Zip(ZipError::FileNotFound))?;
-        usize::try_from(entry.uncompressed_size).map_err(|_| EpubError::Zip(ZipError::FileTooLarge))
+        self.in_chapter(index, |book| {
+            let chapter = book.chapter(index)?;
+            let zip_path = resolve_opf_relative_path(&book.opf_path, &chapter.href);
+            let entry = book
+                .zip
+                .get_entry(&zip_path)
+                .ok_or(EpubError::Zip(ZipError::FileNotFound))?;
+            usize::try_from(entry.uncompressed_size)
+                .map_err(|_| EpubError::Zip(ZipError::FileTooLarge))
+        })
     }
 
     /// Create a detached reading session for locator/progress operations.
@@ -962,6 +1015,22 @@ impl<R: Read + Seek> EpubBook<R> {
             })
     }
 
+    /// Run a chapter operation, scoping any error it returns to the
+    /// chapter's index and href.
+    fn in_chapter<T>(
+        &mut self,
+        index: usize,
+        op: impl FnOnce(&mut Self) -> Result<T, EpubError>,
+    ) -> Result<T, EpubError> {
+        op(self).map_err(|err| {
+            let mut scope = ErrorScope::new(ErrorPhase::Parse).with_chapter_index(index);
+            if let Ok(chapter) = self.chapter(index) {
+                scope = scope.with_href(chapter.href);
+            }
+            scope.apply(err)
+        })
+    }
+
     /// Get a chapter descriptor by spine index.
     pub fn chapter(&self, index: usize) -> Result<ChapterRef, EpubError> {
         let spine_item = self
@@ -1054,14 +1123,67 @@ impl<R: Read + Seek> EpubBook<R> {
         hard_cap_bytes: usize,
     ) -> Result<usize, EpubError> {
         let zip_path = resolve_opf_relative_path(&self.opf_path, href);
-        read_entry_into_with_limit(&mut self.zip, &zip_path, writer, hard_cap_bytes)
+        read_entry_into_with_limit(&mut self.zip, &zip_path, writer, hard_cap_bytes).map_err(
+            |err| {
+                ErrorScope::new(ErrorPhase::Parse)
+                    .with_path(zip_path.as_str())
+                    .with_href(href)
+                    .apply(err)
+            },
+        )
+    }
+
+    /// OPF-relative href of the cover image, if the book declares or
+    /// names one.
+    ///
+    /// Tries the package cover (EPUB 3 `cover-image` item or EPUB 2
+    /// `<meta name="cover">`), then the guide `cover` reference, taking the
+    /// first image of an XHTML cover page, then an image manifest item
+    /// whose id or href mentions "cover".
+    pub fn cover_image_href(&mut self) -> Option<String> {
+        let is_image = |item: &&ManifestItem| item.media_type.starts_with("image/");
+        if let Some(item) = self.metadata.get_cover_item().filter(is_image) {
+            return Some(item.href.clone());
+        }
+        let guide_page = self
+            .metadata
+            .guide
+            .iter()
+            .find(|reference| reference.guide_type.eq_ignore_ascii_case("cover"))
+            .map(|reference| split_href_fragment(&reference.href).0);
+        if let Some(page) = guide_page {
+            let page_item = self.metadata.manifest.iter().find(|item| item.href == page);
+            if page_item.is_some_and(|item| is_image(&item)) {
+                return Some(page);
+            }
+            let mut html = Vec::with_capacity(0);
+            if self
+                .read_resource_into_with_hard_cap(&page, &mut html, MAX_COVER_PAGE_BYTES)
+                .is_ok()
+            {
+                if let Some(src) = first_image_src(&html) {
+                    return Some(resolve_opf_relative_path(&page, &src));
+                }
+            }
+        }
+        self.metadata
+            .manifest
+            .iter()
+            .filter(is_image)
+            .find(|item| {
+                item.id.to_ascii_lowercase().contains("cover")
+                    || item.href.to_ascii_lowercase().contains("cover")
+            })
+            .map(|item| item.href.clone())
     }
 
     /// Read spine item content bytes by index.
     pub fn read_spine_item_bytes(&mut self, index: usize) -> Result<Vec<u8>, EpubError> {
-        let href = self.chapter(index)?.href;
+        self.in_chapter(index, |book| {
+            let href = book.chapter(index)?.href;
 
-        self.read_resource(&href)
+            book.read_resource(&href)
+        })
     }
 
     /// Read a spine chapter as UTF-8 HTML/XHTML text by index.
@@ -1096,14 +1218,16 @@ impl<R: Read + Seek> EpubBook<R> {
         max_bytes: usize,
         out: &mut String,
     ) -> Result<(), EpubError> {
-        out.clear();
-        let chapter = self.chapter(index)?;
-        let mut bytes = Vec::with_capacity(0);
-        self.read_resource_into_with_hard_cap(&chapter.href, &mut bytes, max_bytes)?;
-        let mut html = String::from_utf8(bytes)
-            .map_err(|_| EpubError::ChapterNotUtf8 { href: chapter.href })?;
-        core::mem::swap(out, &mut html);
-        Ok(())
+        self.in_chapter(index, |book| {
+            out.clear();
+            let chapter = book.chapter(index)?;
+            let mut bytes = Vec::with_capacity(0);
+            book.read_resource_into_with_hard_cap(&chapter.href, &mut bytes, max_bytes)?;
+            let mut html = String::from_utf8(bytes)
+                .map_err(|_| EpubError::ChapterNotUtf8 { href: chapter.href })?;
+            core::mem::swap(out, &mut html);
+            Ok(())
+        })
     }
 
     /// Resolve chapter stylesheet sources in cascade order.
@@ -1117,27 +1241,29 @@ impl<R: Read + Seek> EpubBook<R> {
         index: usize,
         limits: StyleLimits,
     ) -> Result<ChapterStylesheets, EpubError> {
-        let chapter = self.chapter(index)?;
-        let html = self.chapter_html(index)?;
-        let links = parse_stylesheet_links(&chapter.href, &html);
-        let mut sources = Vec::with_capacity(0);
-
-        for href in links {
-            let bytes = self.read_resource(&href)?;
-            if bytes.len() > limits.max_css_bytes {
-                return Err(EpubError::Parse(format!(
-                    "Stylesheet exceeds max_css_bytes ({} > {}) at '{}'",
-                    bytes.len(),
-                    limits.max_css_bytes,
-                    href
-                )));
+        self.in_chapter(index, |book| {
+            let chapter = book.chapter(index)?;
+            let html = book.chapter_html(index)?;
+            let links = parse_stylesheet_links(&chapter.href, &html);
+            let mut sources = Vec::with_capacity(0);
+
+            for href in links {
+                let bytes = book.read_resource(&href)?;
+                if bytes.len() > limits.max_css_bytes {
+                    return Err(EpubError::Parse(format!(
+                        "Stylesheet exceeds max_css_bytes ({} > {}) at '{}'",
+                        bytes.len(),
+                        limits.max_css_bytes,
+                        href
+                    )));
+                }
+                let css = String::from_utf8(bytes)
+                    .map_err(|_| EpubError::Parse(format!("Stylesheet is not UTF-8: {}", href)))?;
+                sources.push(StylesheetSource { href, css });
             }
-            let css = String::from_utf8(bytes)
-                .map_err(|_| EpubError::Parse(format!("Stylesheet is not UTF-8: {}", href)))?;
-            sources.push(StylesheetSource { href, css });
-        }
 
-        Ok(ChapterStylesheets { sources })
+            Ok(ChapterStylesheets { sources })
+        })
     }
 
     /// Backward-compatible alias for chapter stylesheet discovery with explicit limits.
@@ -1164,32 +1290,34 @@ impl<R: Read + Seek> EpubBook<R> {
         limits: StyleLimits,
         scratch_buf: &mut Vec<u8>,
     ) -> Result<ChapterStylesheets, EpubError> {
-        let chapter = self.chapter(index)?;
-        let html = self.chapter_html(index)?;
-        let links = parse_stylesheet_links(&chapter.href, &html);
-        let mut sources = Vec::with_capacity(links.len());
+        self.in_chapter(index, |book| {
+            let chapter = book.chapter(index)?;
+            let html = book.chapter_html(index)?;
+            let links = parse_stylesheet_links(&chapter.href, &html);
+            let mut sources = Vec::with_capacity(links.len());
+
+            for href in links {
+                scratch_buf.clear();
+                book.read_resource_into(&href, scratch_buf)
+                    .map_err(|_| EpubError::Zip(ZipError::FileNotFound))?;
+
+                if scratch_buf.len() > limits.max_css_bytes {
+                    return Err(EpubError::LimitExceeded {
+                        kind: LimitKind::CssSize,
+                        actual: scratch_buf.len(),
+                        limit: limits.max_css_bytes,
+                        path: Some(href.clone()),
+                    });
+                }
 
-        for href in links {
-            scratch_buf.clear();
-            self.read_resource_into(&href, scratch_buf)
-                .map_err(|_| EpubError::Zip(ZipError::FileNotFound))?;
+                let css = String::from_utf8(scratch_buf.clone())
+                    .map_err(|_| EpubError::ChapterNotUtf8 { href: href.clone() })?;
 
-            if scratch_buf.len() > limits.max_css_bytes {
-                return Err(EpubError::LimitExceeded {
-                    kind: LimitKind::CssSize,
-                    actual: scratch_buf.len(),
-                    limit: limits.max_css_bytes,
-                    path: Some(href.clone()),
-                });
+                sources.push(StylesheetSource { href, css });
             }
 
-            let css = String::from_utf8(scratch_buf.clone())
-                .map_err(|_| EpubError::ChapterNotUtf8 { href: href.clone() })?;
-
-            sources.push(StylesheetSource { href, css });
-        }
-
-        Ok(ChapterStylesheets { sources })
+            Ok(ChapterStylesheets { sources })
+        })
     }
 
     /// Enumerate embedded font-face metadata from EPUB CSS resources.
@@ -1303,13 +1431,15 @@ impl<R: Read + Seek> EpubBook<R> {
         index: usize,
         options: RenderPrepOptions,
     ) -> Result<StyledChapter, EpubError> {
-        let mut prep = RenderPrep::new(options).with_serif_default();
-        let prepared = prep.prepare_chapter(self, index).map_err(EpubError::from)?;
-        let mut items = Vec::with_capacity(0);
-        for item in prepared.iter() {
-            items.push(item.clone());
-        }
-        Ok(StyledChapter::from_items(items))
+        self.in_chapter(index, |book| {
+            let mut prep = RenderPrep::new(options).with_serif_default();
+            let prepared = prep.prepare_chapter(book, index).map_err(EpubError::from)?;
+            let mut items = Vec::with_capacity(0);
+            for item in prepared.iter() {
+                items.push(item.clone());
+            }
+            Ok(StyledChapter::from_items(items))
+        })
     }
 
     /// Stream chapter style events/runs via callback with bounded item emission.
@@ -1328,39 +1458,41 @@ impl<R: Read + Seek> EpubBook<R> {
     where
         F: FnMut(StyledEventOrRun) -> Result<(), EpubError>,
     {
-        let mut prep = RenderPrep::new(opts.render).with_serif_default();
-        let mut emitted = 0usize;
-        let mut callback_error: Option<EpubError> = None;
-        let mut hit_cap = false;
-
-        prep.prepare_chapter_with(self, index, |item| {
-            if callback_error.is_some() || hit_cap {
-                return;
-            }
-            if emitted >= opts.max_items {
-                hit_cap = true;
-                return;
+        self.in_chapter(index, |book| {
+            let mut prep = RenderPrep::new(opts.render).with_serif_default();
+            let mut emitted = 0usize;
+            let mut callback_error: Option<EpubError> = None;
+            let mut hit_cap = false;
+
+            prep.prepare_chapter_with(book, index, |item| {
+                if callback_error.is_some() || hit_cap {
+                    return;
+                }
+                if emitted >= opts.max_items {
+                    hit_cap = true;
+                    return;
+                }
+                if let Err(err) = on_item(item) {
+                    callback_error = Some(err);
+                    return;
+                }
+                emitted += 1;
+            })
+            .map_err(EpubError::from)?;
+
+            if let Some(err) = callback_error {
+                return Err(err);
             }
-            if let Err(err) = on_item(item) {
-                callback_error = Some(err);
-                return;
+            if hit_cap {
+                // TODO: RenderPrep callbacks cannot currently short-circuit parsing.
+                // This cap bounds emitted output, but upstream tokenization keeps scanning.
+                return Err(EpubError::Parse(format!(
+                    "Chapter event count exceeded max_items ({})",
+                    opts.max_items
+                )));
             }
-            emitted += 1;
+            Ok(emitted)
         })
-        .map_err(EpubError::from)?;
-
-        if let Some(err) = callback_error {
-            return Err(err);
-        }
-        if hit_cap {
-            // TODO: RenderPrep callbacks cannot currently short-circuit parsing.
-            // This cap bounds emitted output, but upstream tokenization keeps scanning.
-            return Err(EpubError::Parse(format!(
-                "Chapter event count exceeded max_items ({})",
-                opts.max_items
-            )));
-        }
-        Ok(emitted)
     }
 
     /// Stream chapter events with caller-provided scratch buffers.
@@ -1390,122 +1522,126 @@ impl<R: Read + Seek> EpubBook<R> {
     where
         F: FnMut(StyledEventOrRun) -> Result<(), EpubError>,
     {
-        use crate::zip::CdEntry;
-
-        // Clear buffers for reuse
-        chapter_buf.clear();
-        scratch.clear();
-
-        let chapter = self.chapter(index)?;
-        let href = chapter.href;
-        let zip_path = resolve_opf_relative_path(&self.opf_path, &href);
-
-        // Get ZIP entry
-        let entry = self
-            .zip
-            .get_entry(&zip_path)
-            .ok_or(EpubError::Zip(ZipError::FileNotFound))?
-            .clone();
-
-        // Check hard caps before reading
-        let uncompressed = usize::try_from(entry.uncompressed_size)
-            .map_err(|_| EpubError::Zip(ZipError::FileTooLarge))?;
-
-        // Check ZIP limits
-        if let Some(limits) = self.zip.limits() {
-            if uncompressed > limits.max_file_read_size {
+        self.in_chapter(index, |book| {
+            use crate::zip::CdEntry;
+
+            // Clear buffers for reuse
+            chapter_buf.clear();
+            scratch.clear();
+
+            let chapter = book.chapter(index)?;
+            let href = chapter.href;
+            let _span = phase_span!("style", chapter_index = index, href = href.as_str());
+            let zip_path = resolve_opf_relative_path(&book.opf_path, &href);
+
+            // Get ZIP entry
+            let entry = book
+                .zip
+                .get_entry(&zip_path)
+                .ok_or(EpubError::Zip(ZipError::FileNotFound))?
+                .clone();
+
+            // Check hard caps before reading
+            let uncompressed = usize::try_from(entry.uncompressed_size)
+                .map_err(|_| EpubError::Zip(ZipError::FileTooLarge))?;
+
+            // Check ZIP limits
+            if let Some(limits) = book.zip.limits() {
+                if uncompressed > limits.max_file_read_size {
+                    return Err(EpubError::LimitExceeded {
+                        kind: LimitKind::FileSize,
+                        actual: uncompressed,
+                        limit: limits.max_file_read_size,
+                        path: Some(zip_path),
+                    });
+                }
+            }
+
+            // Check memory budget
+            if uncompressed > opts.render.memory.max_entry_bytes {
                 return Err(EpubError::LimitExceeded {
-                    kind: LimitKind::FileSize,
+                    kind: LimitKind::MemoryBudget,
                     actual: uncompressed,
-                    limit: limits.max_file_read_size,
+                    limit: opts.render.memory.max_entry_bytes,
                     path: Some(zip_path),
                 });
             }
-        }
-
-        // Check memory budget
-        if uncompressed > opts.render.memory.max_entry_bytes {
-            return Err(EpubError::LimitExceeded {
-                kind: LimitKind::MemoryBudget,
-                actual: uncompressed,
-                limit: opts.render.memory.max_entry_bytes,
-                path: Some(zip_path),
-            });
-        }
 
-        // Check if chapter fits in provided buffer
-        if uncompressed > chapter_buf.capacity() {
-            return Err(EpubError::BufferTooSmall {
-                required: uncompressed,
-                provided: chapter_buf.capacity(),
-                context: "chapter_buf".to_string(),
-            });
-        }
+            // Check if chapter fits in provided buffer
+            if uncompressed > chapter_buf.capacity() {
+                return Err(EpubError::BufferTooSmall {
+                    required: uncompressed,
+                    provided: chapter_buf.capacity(),
+                    context: "chapter_buf".to_string(),
+                });
+            }
 
-        // Read chapter into caller-provided buffer using scratch for I/O
-        let use_entry = CdEntry {
-            filename: String::with_capacity(0),
-            method: entry.method,
-            compressed_size: entry.compressed_size,
-            uncompressed_size: entry.uncompressed_size,
-            local_header_offset: entry.local_header_offset,
-            crc32: entry.crc32,
-        };
+            // Read chapter into caller-provided buffer using scratch for I/O
+            let use_entry = CdEntry {
+                filename: String::with_capacity(0),
+                method: entry.method,
+                compressed_size: entry.compressed_size,
+                uncompressed_size: entry.uncompressed_size,
+                local_header_offset: entry.local_header_offset,
+                crc32: entry.crc32,
+                flags: entry.flags,
+            };
 
-        // `ZipArchive::read_file_with_scratch` consumes `&mut [u8]` and therefore
-        // uses slice length (not Vec capacity) as the writable output window.
-        // Ensure the Vec length matches the entry size before decompression while
-        // staying within the pre-validated capacity budget.
-        if chapter_buf.len() < uncompressed {
-            chapter_buf.resize(uncompressed, 0);
-        }
-        if scratch.read_buf.is_empty() {
-            // `read_file_with_scratch` requires a non-empty input slice and uses
-            // slice length as the compressed read chunk size.
-            let read_chunk = scratch.read_buf.capacity().max(1024);
-            scratch.read_buf.resize(read_chunk, 0);
-        }
-        let bytes_read = self
-            .zip
-            .read_file_with_scratch(
-                &use_entry,
-                chapter_buf.as_mut_slice(),
-                &mut scratch.read_buf,
-            )
-            .map_err(EpubError::Zip)?;
-        chapter_buf.truncate(bytes_read);
-
-        let mut emitted = 0usize;
-        let mut callback_err: Option<EpubError> = None;
-        let mut prep = RenderPrep::new(opts.render).with_serif_default();
-        prep.prepare_chapter_bytes_with(self, index, chapter_buf, |item| {
-            if callback_err.is_some() || emitted >= opts.max_items {
-                return;
+            // `ZipArchive::read_file_with_scratch` consumes `&mut [u8]` and therefore
+            // uses slice length (not Vec capacity) as the writable output window.
+            // Ensure the Vec length matches the entry size before decompression while
+            // staying within the pre-validated capacity budget.
+            if chapter_buf.len() < uncompressed {
+                chapter_buf.resize(uncompressed, 0);
             }
-            if let Err(e) = on_item(item) {
-                callback_err = Some(e);
-                return;
+            if scratch.read_buf.is_empty() {
+                // `read_file_with_scratch` requires a non-empty input slice and uses
+                // slice length as the compressed read chunk size.
+                let read_chunk = scratch.read_buf.capacity().max(1024);
+                scratch.read_buf.resize(read_chunk, 0);
             }
-            emitted += 1;
-        })
-        .map_err(EpubError::from)?;
+            let bytes_read = book
+                .zip
+                .read_file_with_scratch(
+                    &use_entry,
+                    chapter_buf.as_mut_slice(),
+                    &mut scratch.read_buf,
+                )
+                .map_err(EpubError::Zip)?;
+            chapter_buf.truncate(bytes_read);
+
+            let mut emitted = 0usize;
+            let mut callback_err: Option<EpubError> = None;
+            let mut prep = RenderPrep::new(opts.render).with_serif_default();
+            prep.prepare_chapter_bytes_with(book, index, chapter_buf, |item| {
+                if callback_err.is_some() || emitted >= opts.max_items {
+                    return;
+                }
+                if let Err(e) = on_item(item) {
+                    callback_err = Some(e);
+                    return;
+                }
+                emitted += 1;
+            })
+            .map_err(EpubError::from)?;
 
-        if let Some(err) = callback_err {
-            return Err(err);
-        }
-        if emitted >= opts.max_items {
-            return Err(EpubError::LimitExceeded {
-                kind: LimitKind::EventCount,
-                actual: emitted,
-                limit: opts.max_items,
-                path: Some(zip_path),
-            });
-        }
+            if let Some(err) = callback_err {
+                return Err(err);
+            }
+            if emitted >= opts.max_items {
+                return Err(EpubError::LimitExceeded {
+                    kind: LimitKind::EventCount,
+                    actual: emitted,
+                    limit: opts.max_items,
+                    path: Some(zip_path),
+                });
+            }
 
-        Ok(ChapterStreamResult {
-            items_emitted: emitted,
-            bytes_read: chapter_buf.len(),
-            complete: true,
+            Ok(ChapterStreamResult {
+                items_emitted: emitted,
+                bytes_read: chapter_buf.len(),
+                complete: true,
+            })
         })
     }
 
@@ -1559,14 +1695,152 @@ impl<R: Read + Seek> EpubBook<R> {
         max_bytes: usize,
         out: &mut String,
     ) -> Result<(), EpubError> {
-        out.clear();
-        if max_bytes == 0 {
-            return Ok(());
+        self.in_chapter(index, |book| {
+            out.clear();
+            if max_bytes == 0 {
+                return Ok(());
+            }
+
+            let chapter = book.chapter(index)?;
+            let bytes = book.read_resource(&chapter.href)?;
+            extract_plain_text_limited(&bytes, max_bytes, out)
+        })
+    }
+
+    /// Export a chapter as plain text or Markdown into `out`.
+    ///
+    /// Headings, lists, block quotes, emphasis, code, links, and images map
+    /// to their Markdown forms (plain text keeps only the block layout).
+    /// Output is written through a small fixed buffer as the chapter is
+    /// parsed.
+    #[cfg(feature = "std")]
+    pub fn export_chapter<W: std::io::Write>(
+        &mut self,
+        index: usize,
+        format: ExportFormat,
+        out: &mut W,
+    ) -> Result<(), EpubError> {
+        self.in_chapter(index, |book| {
+            let chapter = book.chapter(index)?;
+            let bytes = book.read_resource(&chapter.href)?;
+            export_markup(&bytes, format, out)
+        })
+    }
+
+    /// Export a chapter as one self-contained HTML document.
+    ///
+    /// Markup is reduced to an allow-list of elements and attributes
+    /// (scripts, forms, embedded objects, and event handlers are dropped),
+    /// the chapter's stylesheets are inlined without external references,
+    /// and images are embedded as base64 `data:` URIs within `limits`;
+    /// images over the limits keep only their alt text. Links to other
+    /// chapters are dropped.
+    #[cfg(feature = "std")]
+    pub fn export_chapter_html<W: std::io::Write>(
+        &mut self,
+        index: usize,
+        limits: HtmlExportLimits,
+        out: &mut W,
+    ) -> Result<(), EpubError> {
+        self.in_chapter(index, |book| {
+            book.chapter(index)?;
+            book.export_html(index..index + 1, limits, out)
+        })
+    }
+
+    /// Export every spine chapter into one self-contained HTML document.
+    ///
+    /// Each chapter becomes a `<section id="chapter-{index}">`; links
+    /// between chapters are rewritten to in-document anchors. Sanitizing
+    /// and limits are as for `export_chapter_html`, with
+    /// `max_total_image_bytes` shared by the whole book.
+    #[cfg(feature = "std")]
+    pub fn export_book_html<W: std::io::Write>(
+        &mut self,
+        limits: HtmlExportLimits,
+        out: &mut W,
+    ) -> Result<(), EpubError> {
+        self.export_html(0..self.chapter_count(), limits, out)
+    }
+
+    #[cfg(feature = "std")]
+    fn export_html<W: std::io::Write>(
+        &mut self,
+        chapters: core::ops::Range<usize>,
+        limits: HtmlExportLimits,
+        out: &mut W,
+    ) -> Result<(), EpubError> {
+        let mut bundle = HtmlBundle::new(out, limits);
+        bundle.begin(self.title(), self.language())?;
+        let mut linked: Vec<String> = Vec::with_capacity(0);
+        let mut paths = Vec::with_capacity(chapters.len());
+        for index in chapters.clone() {
+            let chapter = self.chapter(index)?;
+            let html = self.read_resource(&chapter.href)?;
+            for href in parse_stylesheet_links_bytes(&chapter.href, &html) {
+                if linked.contains(&href) {
+                    continue;
+                }
+                let mut css = Vec::with_capacity(0);
+                if self
+                    .read_resource_into_with_limit(&href, &mut css, limits.max_css_bytes)
+                    .is_ok()
+                {
+                    bundle.stylesheet(&String::from_utf8_lossy(&css))?;
+                }
+                linked.push(href);
+            }
+            for css in inline_styles(&html, limits.max_css_bytes) {
+                bundle.stylesheet(&css)?;
+            }
+            paths.push((normalize_path(&chapter.href), index));
+        }
+        for index in chapters {
+            let chapter = self.chapter(index)?;
+            let html = self.read_resource(&chapter.href)?;
+            let link = |href: &str| -> Option<String> {
+                if href.starts_with('#') {
+                    return Some(href.to_string());
+                }
+                if let Some(scheme) = url_scheme(href) {
+                    let external = ["http", "https", "mailto"]
+                        .iter()
+                        .any(|allowed| scheme.eq_ignore_ascii_case(allowed));
+                    return external.then(|| href.to_string());
+                }
+                let path = resolve_opf_relative_path(&chapter.href, href);
+                let (_, target) = paths.iter().find(|(chapter, _)| *chapter == path)?;
+                Some(match href.split_once('#') {
+                    Some((_, fragment)) if !fragment.is_empty() => format!("#{fragment}"),
+                    _ => format!("#chapter-{target}"),
+                })
+            };
+            let image = |src: &str, cap: usize| self.export_image(&chapter.href, src, cap);
+            bundle.chapter(index, &html, image, link)?;
         }
+        bundle.finish()
+    }
 
-        let chapter = self.chapter(index)?;
-        let bytes = self.read_resource(&chapter.href)?;
-        extract_plain_text_limited(&bytes, max_bytes, out)
+    /// Read an image referenced from `base` for embedding, with its media
+    /// type from the manifest or its signature.
+    #[cfg(feature = "std")]
+    fn export_image(&mut self, base: &str, src: &str, cap: usize) -> Option<ExportImage> {
+        if url_scheme(src).is_some() {
+            return None;
+        }
+        let path = resolve_opf_relative_path(base, src);
+        let mut bytes = Vec::with_capacity(0);
+        self.read_resource_into_with_limit(&path, &mut bytes, cap)
+            .ok()?;
+        let media_type = self
+            .metadata
+            .manifest
+            .iter()
+            .find(|item| normalize_path(&item.href) == path)
+            .map(|item| item.media_type.clone())
+            .filter(|media_type| media_type.starts_with("image/"))
+            .or_else(|| sniff_image_type(&bytes).map(str::to_string))?;
+        Some((media_type, bytes))
     }
 
     /// Tokenize spine item content by index.
@@ -1580,11 +1854,18 @@ impl<R: Read + Seek> EpubBook<R> {
     /// Prefer `chapter_text_into` for low-memory extraction paths.
     /// For bounded tokenization, use `tokenize_html_limited` from the tokenizer module.
     pub fn tokenize_spine_item(&mut self, index: usize) -> Result<Vec<Token>, EpubError> {
-        let chapter = self.chapter(index)?;
-        let bytes = self.read_resource(&chapter.href)?;
-        let html =
-            str::from_utf8(&bytes).map_err(|_| EpubError::ChapterNotUtf8 { href: chapter.href })?;
-        tokenize_html(html).map_err(EpubError::from)
+        self.in_chapter(index, |book| {
+            let chapter = book.chapter(index)?;
+            let _span = phase_span!(
+                "tokenize",
+                chapter_index = index,
+                href = chapter.href.as_str()
+            );
+            let bytes = book.read_resource(&chapter.href)?;
+            let html = str::from_utf8(&bytes)
+                .map_err(|_| EpubError::ChapterNotUtf8 { href: chapter.href })?;
+            tokenize_html(html).map_err(EpubError::from)
+        })
     }
 
     /// Backward-compatible alias for `read_spine_item_bytes`.
@@ -1621,6 +1902,7 @@ impl<R: Read + Seek> EpubBook<R> {
     }
 }
 
+#[cfg(feature = "std")]
 impl EpubBook<File> {
     /// Create a high-level builder for opening/parsing EPUBs.
     pub fn builder() -> EpubBookBuilder {
@@ -1638,7 +1920,12 @@ fn load_summary_from_zip<R: Read + Seek>(
     let opf = read_entry(zip, &opf_path)?;
     let metadata = extract_metadata(&container, &opf)?;
     let spine = crate::spine::parse_spine(&opf)?;
-    validate_open_invariants(&metadata, &spine, options.validation_mode)?;
+    let docs = PackageDocuments {
+        container: Some(&container),
+        opf: Some(&opf),
+        opf_path: &opf_path,
+    };
+    validate_open_invariants(zip, docs, &metadata, &spine, options.validation_mode)?;
     let navigation = parse_navigation(
         zip,
         &metadata,
@@ -1691,7 +1978,12 @@ fn parse_navigation<R: Read + Seek>(
             if matches!(validation_mode, ValidationMode::Strict) {
                 return Err(err);
             }
-            log::warn!("Failed to read navigation document '{}': {}", nav_path, err);
+            log_event!(
+                warn,
+                "Failed to read navigation document '{}': {}",
+                nav_path,
+                err
+            );
             return Ok(None);
         }
     };
@@ -1723,6 +2015,7 @@ fn parse_navigation<R: Read + Seek>(
                         limit,
                     ))),
                 })),
+                cause: None,
             }));
         }
     }
@@ -1741,7 +2034,8 @@ fn parse_navigation<R: Read + Seek>(
             if matches!(validation_mode, ValidationMode::Strict) {
                 Err(EpubError::Navigation(err.to_string()))
             } else {
-                log::warn!(
+                log_event!(
+                    warn,
                     "Failed to parse navigation document '{}': {}",
                     nav_path,
                     err
@@ -1752,24 +2046,17 @@ fn parse_navigation<R: Read + Seek>(
     }
 }
 
-fn validate_open_invariants(
+fn validate_open_invariants<R: Read + Seek>(
+    zip: &StreamingZip<R>,
+    docs: PackageDocuments<'_>,
     metadata: &EpubMetadata,
     spine: &Spine,
     validation_mode: ValidationMode,
 ) -> Result<(), EpubError> {
-    if matches!(validation_mode, ValidationMode::Lenient) {
-        return Ok(());
+    match validation_mode {
+        ValidationMode::Lenient => Ok(()),
+        ValidationMode::Strict => crate::conformance::check_package(zip, docs, metadata, spine),
     }
-
-    for item in spine.items() {
-        if metadata.get_item(&item.idref).is_none() {
-            return Err(EpubError::ManifestItemMissing {
-                idref: item.idref.clone(),
-            });
-        }
-    }
-
-    Ok(())
 }
 
 fn read_entry<R: Read + Seek>(zip: &mut StreamingZip<R>, path: &str) -> Result<Vec<u8>, EpubError> {
@@ -1792,7 +2079,8 @@ fn read_entry_into_with_limit<R: Read + Seek, W: Write>(
     writer: &mut W,
     max_bytes: usize,
 ) -> Result<usize, EpubError> {
-    let (method, compressed_size, uncompressed_size, local_header_offset, crc32) = {
+    let _span = phase_span!("zip.read", path = path);
+    let (method, compressed_size, uncompressed_size, local_header_offset, crc32, flags) = {
         let entry = zip
             .get_entry(path)
             .ok_or(EpubError::Zip(ZipError::FileNotFound))?;
@@ -1802,11 +2090,16 @@ fn read_entry_into_with_limit<R: Read + Seek, W: Write>(
             entry.uncompressed_size,
             entry.local_header_offset,
             entry.crc32,
+            entry.flags,
         )
     };
 
     if uncompressed_size > max_bytes as u64 || compressed_size > max_bytes as u64 {
-        return Err(EpubError::Zip(ZipError::FileTooLarge));
+        let actual = usize::try_from(uncompressed_size.max(compressed_size)).unwrap_or(usize::MAX);
+        return Err(ErrorScope::new(ErrorPhase::Parse)
+            .with_path(path)
+            .with_limit("max_bytes", actual, max_bytes)
+            .apply(EpubError::Zip(ZipError::FileTooLarge)));
     }
     let entry = CdEntry {
         method,
@@ -1814,13 +2107,14 @@ fn read_entry_into_with_limit<R: Read + Seek, W: Write>(
         uncompressed_size,
         local_header_offset,
         crc32,
+        flags,
         filename: String::with_capacity(0),
     };
     zip.read_file_to_writer(&entry, writer)
         .map_err(EpubError::Zip)
 }
 
-fn resolve_opf_relative_path(opf_path: &str, href: &str) -> String {
+pub(crate) fn resolve_opf_relative_path(opf_path: &str, href: &str) -> String {
     let href = href.split('#').next().unwrap_or(href);
     if href.is_empty() {
         return normalize_path(opf_path);
@@ -1840,6 +2134,17 @@ fn resolve_opf_relative_path(opf_path: &str, href: &str) -> String {
     }
 }
 
+/// Scheme of an absolute URL (`https` in `https://...`), if `href` has one.
+#[cfg(feature = "std")]
+fn url_scheme(href: &str) -> Option<&str> {
+    let (scheme, _) = href.split_once(':')?;
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then_some(scheme)
+}
+
 fn normalize_path(path: &str) -> String {
     let mut parts: Vec<&str> = Vec::with_capacity(0);
     for part in path.split('/') {
@@ -1854,7 +2159,43 @@ fn normalize_path(path: &str) -> String {
     parts.join("/")
 }
 
-fn should_skip_text_tag(name: &str) -> bool {
+/// Max bytes of an XHTML cover page scanned for its image.
+const MAX_COVER_PAGE_BYTES: usize = 64 * 1024;
+
+/// `src` of the first `<img>`, or `href` of the first SVG `<image>`.
+fn first_image_src(html: &[u8]) -> Option<String> {
+    let mut reader = Reader::from_reader(html);
+    let mut buf = Vec::with_capacity(0);
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e) | Event::Empty(e)) => {
+                let wanted: &[u8] = match e.local_name().as_ref() {
+                    b"img" => b"src",
+                    b"image" => b"href",
+                    _ => {
+                        buf.clear();
+                        continue;
+                    }
+                };
+                let src = e
+                    .attributes()
+                    .flatten()
+                    .find(|attr| attr.key.local_name().as_ref() == wanted)
+                    .and_then(|attr| attr.unescape_value().ok())
+                    .map(|value| value.trim().to_string())
+                    .filter(|value| !value.is_empty());
+                if src.is_some() {
+                    return src;
+                }
+            }
+            Ok(Event::Eof) | Err(_) => return None,
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+pub(crate) fn should_skip_text_tag(name: &str) -> bool {
     matches!(
         name,
         "script" | "style" | "head" | "nav" | "header" | "footer" | "aside" | "noscript"
@@ -2042,6 +2383,14 @@ mod tests {
     use super::*;
     use crate::render_prep::{RenderPrep, RenderPrepOptions, RenderPrepTrace, StyledEventOrRun};
 
+    #[test]
+    fn test_url_scheme_detects_absolute_urls() {
+        assert_eq!(url_scheme("https://example.com/a"), Some("https"));
+        assert_eq!(url_scheme("javascript:alert(1)"), Some("javascript"));
+        assert_eq!(url_scheme("ch2.xhtml#p:1"), None);
+        assert_eq!(url_scheme("../images/a.png"), None);
+    }
+
     #[test]
     fn test_resolve_opf_relative_path() {
         assert_eq!(
@@ -2062,6 +2411,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_first_image_src_reads_img_and_svg_image() {
+        assert_eq!(
+            first_image_src(
+                br#"<html><body><p>Cover</p><img alt="" src=" images/c.jpg "/></body></html>"#
+            ),
+            Some("images/c.jpg".to_string())
+        );
+        assert_eq!(
+            first_image_src(
+                br#"<html xmlns:xlink="http://www.w3.org/1999/xlink"><body><svg><image width="600" xlink:href="../img/c.png"></image></svg></body></html>"#
+            ),
+            Some("../img/c.png".to_string())
+        );
+        assert_eq!(
+            first_image_src(b"<html><body><p>No art</p></body></html>"),
+            None
+        );
+        assert_eq!(
+            resolve_opf_relative_path("Text/cover.xhtml", "../img/c.png"),
+            "img/c.png"
+        );
+    }
+
     #[test]
     fn test_read_resource_into_streams_to_writer() {
         let file = std::fs::File::open(
@@ -2090,7 +2463,18 @@ mod tests {
         let err = book
             .read_resource_into_with_hard_cap("xhtml/nav.xhtml", &mut out, 8)
             .expect_err("hard cap should fail");
-        assert!(matches!(err, EpubError::Zip(ZipError::FileTooLarge)));
+        assert!(matches!(
+            err.cause(),
+            EpubError::Zip(ZipError::FileTooLarge)
+        ));
+        let ctx = err
+            .phase_context()
+            .expect("phase context should be present");
+        assert_eq!(ctx.href.as_deref(), Some("xhtml/nav.xhtml"));
+        let limit = ctx.limit.as_ref().expect("limit context should be present");
+        assert_eq!(limit.kind.as_ref(), "max_bytes");
+        assert_eq!(limit.limit, 8);
+        assert!(limit.actual > 8);
     }
 
     #[test]
@@ -2199,7 +2583,15 @@ mod tests {
         let err = book
             .chapter_html_into_with_limit(0, 8, &mut out)
             .expect_err("hard cap should fail");
-        assert!(matches!(err, EpubError::Zip(ZipError::FileTooLarge)));
+        assert!(matches!(
+            err.cause(),
+            EpubError::Zip(ZipError::FileTooLarge)
+        ));
+        let ctx = err
+            .phase_context()
+            .expect("phase context should be present");
+        assert_eq!(ctx.chapter_index, Some(0));
+        assert!(ctx.limit.is_some());
     }
 
     #[test]
@@ -2408,7 +2800,12 @@ mod tests {
                 |_| Ok(()),
             )
             .expect_err("max_items cap should fail");
-        assert!(matches!(err, EpubError::Parse(_)));
+        assert!(matches!(err.cause(), EpubError::Parse(_)));
+        assert_eq!(err.code(), "PARSE_ERROR");
+        let ctx = err
+            .phase_context()
+            .expect("phase context should be present");
+        assert_eq!(ctx.chapter_index, Some(index));
     }
 
     #[test]
@@ -2499,7 +2896,7 @@ mod tests {
                     RenderPrepTrace::Event => panic!("run item should produce run trace context"),
                 }
             }
-            StyledEventOrRun::Event(_) => {
+            StyledEventOrRun::Event(_) | StyledEventOrRun::Image(_) => {
                 assert!(matches!(trace, RenderPrepTrace::Event));
             }
         })
@@ -2560,4 +2957,39 @@ mod tests {
             .expect_err("seek should fail");
         assert!(matches!(err, EpubError::ChapterOutOfBounds { .. }));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_roundtrips_positions_and_options() {
+        let locator = Locator::Position(ReadingPosition {
+            chapter_index: 3,
+            chapter_href: Some("text/ch3.xhtml".to_string()),
+            anchor: Some("p12".to_string()),
+            fallback_offset: 420,
+        });
+        let json = serde_json::to_string(&locator).expect("locator should serialize");
+        let back: Locator = serde_json::from_str(&json).expect("locator should deserialize");
+        assert_eq!(back, locator);
+
+        let options = ChapterEventsOptions {
+            max_items: 512,
+            ..ChapterEventsOptions::default()
+        };
+        let json = serde_json::to_string(&options).expect("options should serialize");
+        let back: ChapterEventsOptions =
+            serde_json::from_str(&json).expect("options should deserialize");
+        assert_eq!(back, options);
+
+        let open = OpenConfig {
+            options: EpubBookOptions {
+                zip_limits: Some(ZipLimits::new(1024, 64)),
+                validation_mode: ValidationMode::Strict,
+                max_nav_bytes: Some(4096),
+            },
+            lazy_navigation: true,
+        };
+        let json = serde_json::to_string(&open).expect("open config should serialize");
+        let back: OpenConfig = serde_json::from_str(&json).expect("open config should deserialize");
+        assert_eq!(back, open);
+    }
 }
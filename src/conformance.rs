@@ -0,0 +1,482 @@
+//! OCF/OPF conformance checks run on open under `ValidationMode::Strict`.
+//!
+//! Each violation is an `EpubError::Phase` in `ErrorPhase::Open` with a
+//! stable code (shared with `validate` where the check overlaps) and the
+//! offending archive path. The first violation found is returned; use
+//! `validate::validate_epub_file` for a full report.
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader;
+
+use crate::book::resolve_opf_relative_path;
+use crate::error::{EpubError, ErrorPhase, PhaseError, PhaseErrorContext};
+use crate::io::{Read, Seek};
+use crate::metadata::EpubMetadata;
+use crate::spine::Spine;
+use crate::zip::{StreamingZip, METHOD_STORED};
+
+const CONTAINER_PATH: &str = "META-INF/container.xml";
+const CONTAINER_NAMESPACE: &str = "urn:oasis:names:tc:opendocument:xmlns:container";
+const OPF_MEDIA_TYPE: &str = "application/oebps-package+xml";
+
+/// Raw package documents, when the open path kept them in memory.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PackageDocuments<'a> {
+    pub(crate) container: Option<&'a [u8]>,
+    pub(crate) opf: Option<&'a [u8]>,
+    pub(crate) opf_path: &'a str,
+}
+
+/// Run every strict check; schema checks are skipped for documents not in
+/// `docs`.
+pub(crate) fn check_package<R: Read + Seek>(
+    zip: &StreamingZip<R>,
+    docs: PackageDocuments<'_>,
+    metadata: &EpubMetadata,
+    spine: &Spine,
+) -> Result<(), EpubError> {
+    check_mimetype_entry(zip)?;
+    if let Some(container) = docs.container {
+        check_container(container)?;
+    }
+    if let Some(opf) = docs.opf {
+        check_opf(opf, docs.opf_path)?;
+    }
+    check_manifest(zip, metadata, docs.opf_path)?;
+    check_spine(metadata, spine, docs.opf_path)
+}
+
+fn violation(code: &'static str, message: String, path: &str, href: Option<&str>) -> EpubError {
+    EpubError::Phase(PhaseError {
+        phase: ErrorPhase::Open,
+        code,
+        message: message.into_boxed_str(),
+        context: Some(Box::new(PhaseErrorContext {
+            path: Some(path.into()),
+            href: href.map(Into::into),
+            ..PhaseErrorContext::default()
+        })),
+        cause: None,
+    })
+}
+
+/// `mimetype` must be the first entry in the archive and stored.
+fn check_mimetype_entry<R: Read + Seek>(zip: &StreamingZip<R>) -> Result<(), EpubError> {
+    let Some(entry) = zip.get_entry("mimetype") else {
+        return Err(violation(
+            "OCF_INVALID_MIMETYPE",
+            "mimetype entry is missing".to_string(),
+            "mimetype",
+            None,
+        ));
+    };
+    if entry.filename != "mimetype" {
+        return Err(violation(
+            "OCF_INVALID_MIMETYPE",
+            format!("mimetype entry is named '{}'", entry.filename),
+            &entry.filename,
+            None,
+        ));
+    }
+    if entry.local_header_offset != 0 {
+        return Err(violation(
+            "OCF_MIMETYPE_NOT_FIRST",
+            format!(
+                "mimetype must be the first archive entry (found at offset {})",
+                entry.local_header_offset
+            ),
+            "mimetype",
+            None,
+        ));
+    }
+    if entry.method != METHOD_STORED {
+        return Err(violation(
+            "OCF_MIMETYPE_COMPRESSED",
+            format!(
+                "mimetype must be stored uncompressed (method {})",
+                entry.method
+            ),
+            "mimetype",
+            None,
+        ));
+    }
+    Ok(())
+}
+
+/// One element of a scanned XML document.
+struct XmlElement {
+    /// Local name, prefix stripped.
+    name: String,
+    /// Local name of the parent element, empty for the root.
+    parent: String,
+    /// Attributes by local name.
+    attrs: Vec<(String, String)>,
+    /// Text directly inside the element, trimmed.
+    text: String,
+}
+
+impl XmlElement {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn local_name(name: &[u8]) -> String {
+    let name = String::from_utf8_lossy(name);
+    name.rsplit(':').next().unwrap_or(&name).to_string()
+}
+
+/// Flatten `bytes` into elements in document order.
+fn scan_xml(bytes: &[u8], path: &str, code: &'static str) -> Result<Vec<XmlElement>, EpubError> {
+    let malformed = |err: &dyn core::fmt::Debug| {
+        violation(
+            code,
+            format!("{} is not well-formed XML: {:?}", path, err),
+            path,
+            None,
+        )
+    };
+    let mut reader = Reader::from_reader(bytes);
+    reader.config_mut().trim_text(true);
+    let mut buf = Vec::with_capacity(0);
+    let mut elements: Vec<XmlElement> = Vec::with_capacity(0);
+    // Indices into `elements` of the open ancestors.
+    let mut open: Vec<usize> = Vec::with_capacity(8);
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => {
+                let element = read_element(&e, &elements, &open).map_err(|err| malformed(&err))?;
+                elements.push(element);
+                open.push(elements.len() - 1);
+            }
+            Ok(Event::Empty(e)) => {
+                let element = read_element(&e, &elements, &open).map_err(|err| malformed(&err))?;
+                elements.push(element);
+            }
+            Ok(Event::Text(t)) => {
+                let text = reader.decoder().decode(&t).map_err(|err| malformed(&err))?;
+                if let Some(element) = open.last().and_then(|&index| elements.get_mut(index)) {
+                    element.text.push_str(text.trim());
+                }
+            }
+            Ok(Event::End(_)) => {
+                open.pop();
+            }
+            Ok(Event::Eof) => break,
+            Err(err) => return Err(malformed(&err)),
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(elements)
+}
+
+fn read_element(
+    start: &BytesStart<'_>,
+    elements: &[XmlElement],
+    open: &[usize],
+) -> Result<XmlElement, quick_xml::Error> {
+    let mut attrs = Vec::with_capacity(0);
+    for attr in start.attributes() {
+        let attr = attr.map_err(quick_xml::Error::from)?;
+        let value = attr.unescape_value()?.into_owned();
+        attrs.push((local_name(attr.key.as_ref()), value));
+    }
+    let parent = open
+        .last()
+        .and_then(|&index| elements.get(index))
+        .map(|parent| parent.name.clone())
+        .unwrap_or_default();
+    Ok(XmlElement {
+        name: local_name(start.name().as_ref()),
+        parent,
+        attrs,
+        text: String::with_capacity(0),
+    })
+}
+
+/// `container.xml`: a version 1.0 `container` root in the OCF namespace
+/// whose `rootfiles` list at least one package document.
+fn check_container(bytes: &[u8]) -> Result<(), EpubError> {
+    let invalid =
+        |message: String| violation("OCF_CONTAINER_INVALID", message, CONTAINER_PATH, None);
+    let elements = scan_xml(bytes, CONTAINER_PATH, "OCF_CONTAINER_INVALID")?;
+    let Some(root) = elements.first().filter(|root| root.name == "container") else {
+        return Err(invalid("root element must be <container>".to_string()));
+    };
+    if root.attr("version") != Some("1.0") {
+        return Err(invalid(format!(
+            "<container> version must be \"1.0\", got {:?}",
+            root.attr("version").unwrap_or("")
+        )));
+    }
+    if root.attr("xmlns") != Some(CONTAINER_NAMESPACE) {
+        return Err(invalid(format!(
+            "<container> must be in namespace {}",
+            CONTAINER_NAMESPACE
+        )));
+    }
+    let mut package_rootfiles = 0usize;
+    for rootfile in elements.iter().filter(|e| e.name == "rootfile") {
+        if rootfile.parent != "rootfiles" {
+            return Err(invalid("<rootfile> must be inside <rootfiles>".to_string()));
+        }
+        let full_path = rootfile.attr("full-path").unwrap_or("");
+        if full_path.is_empty() || full_path.starts_with('/') {
+            return Err(invalid(format!(
+                "<rootfile> full-path must be a relative archive path, got {:?}",
+                full_path
+            )));
+        }
+        match rootfile.attr("media-type") {
+            Some(OPF_MEDIA_TYPE) => package_rootfiles += 1,
+            Some(_) => {}
+            None => return Err(invalid("<rootfile> is missing media-type".to_string())),
+        }
+    }
+    if package_rootfiles == 0 {
+        return Err(invalid(format!(
+            "no <rootfile> with media-type {}",
+            OPF_MEDIA_TYPE
+        )));
+    }
+    Ok(())
+}
+
+/// Package document: `package` root with a 2.x/3.x `version` and a
+/// `unique-identifier` naming a `dc:identifier`; `metadata`, `manifest`, and
+/// `spine` present; required Dublin Core fields non-empty; complete manifest
+/// items and spine itemrefs; `id` values unique across the document.
+fn check_opf(bytes: &[u8], opf_path: &str) -> Result<(), EpubError> {
+    let invalid = |message: String| violation("OPF_PACKAGE_INVALID", message, opf_path, None);
+    let elements = scan_xml(bytes, opf_path, "OPF_PACKAGE_INVALID")?;
+    let Some(package) = elements.first().filter(|root| root.name == "package") else {
+        return Err(invalid("root element must be <package>".to_string()));
+    };
+    let version = package.attr("version").unwrap_or("");
+    if !(version.starts_with("2.") || version.starts_with("3.")) {
+        return Err(invalid(format!(
+            "<package> version must be 2.x or 3.x, got {:?}",
+            version
+        )));
+    }
+    for section in ["metadata", "manifest", "spine"] {
+        if !elements
+            .iter()
+            .any(|e| e.name == section && e.parent == "package")
+        {
+            return Err(invalid(format!("<package> is missing <{}>", section)));
+        }
+    }
+    for field in ["title", "identifier", "language"] {
+        if !elements
+            .iter()
+            .any(|e| e.name == field && e.parent == "metadata" && !e.text.is_empty())
+        {
+            return Err(violation(
+                "OPF_METADATA_MISSING",
+                format!("<metadata> has no non-empty dc:{}", field),
+                opf_path,
+                None,
+            ));
+        }
+    }
+    let unique_identifier = package.attr("unique-identifier").unwrap_or("");
+    let names_identifier = elements.iter().any(|e| {
+        e.name == "identifier" && e.parent == "metadata" && e.attr("id") == Some(unique_identifier)
+    });
+    if unique_identifier.is_empty() || !names_identifier {
+        return Err(invalid(format!(
+            "<package> unique-identifier {:?} does not name a dc:identifier",
+            unique_identifier
+        )));
+    }
+    for item in elements
+        .iter()
+        .filter(|e| e.name == "item" && e.parent == "manifest")
+    {
+        for attr in ["id", "href", "media-type"] {
+            if item.attr(attr).is_none_or(|value| value.trim().is_empty()) {
+                return Err(violation(
+                    "MANIFEST_ITEM_INVALID",
+                    format!(
+                        "manifest item {:?} is missing {}",
+                        item.attr("id").unwrap_or(""),
+                        attr
+                    ),
+                    opf_path,
+                    item.attr("href"),
+                ));
+            }
+        }
+    }
+    for itemref in elements
+        .iter()
+        .filter(|e| e.name == "itemref" && e.parent == "spine")
+    {
+        if itemref
+            .attr("idref")
+            .is_none_or(|value| value.trim().is_empty())
+        {
+            return Err(violation(
+                "SPINE_ITEMREF_INVALID",
+                "spine itemref is missing idref".to_string(),
+                opf_path,
+                None,
+            ));
+        }
+    }
+    let mut ids = BTreeSet::new();
+    for id in elements.iter().filter_map(|e| e.attr("id")) {
+        if !ids.insert(id) {
+            return Err(violation(
+                "OPF_ID_DUPLICATE",
+                format!("id {:?} is used more than once", id),
+                opf_path,
+                None,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Manifest: unique ids and hrefs, local resources present in the archive,
+/// and media types that agree with well-known file extensions.
+fn check_manifest<R: Read + Seek>(
+    zip: &StreamingZip<R>,
+    metadata: &EpubMetadata,
+    opf_path: &str,
+) -> Result<(), EpubError> {
+    let mut ids = BTreeSet::new();
+    let mut paths = BTreeSet::new();
+    for item in &metadata.manifest {
+        if !ids.insert(item.id.as_str()) {
+            return Err(violation(
+                "MANIFEST_ID_DUPLICATE",
+                format!("duplicate manifest id {:?}", item.id),
+                opf_path,
+                Some(&item.href),
+            ));
+        }
+        if item.href.contains("://") {
+            continue;
+        }
+        let path = resolve_opf_relative_path(opf_path, &item.href);
+        if zip.get_entry(&path).is_none() {
+            return Err(violation(
+                "MANIFEST_RESOURCE_MISSING",
+                format!("manifest item {:?} points to missing {}", item.id, path),
+                &path,
+                Some(&item.href),
+            ));
+        }
+        if !media_type_fits_extension(&path, &item.media_type) {
+            return Err(violation(
+                "MANIFEST_MEDIA_TYPE_MISMATCH",
+                format!(
+                    "manifest item {:?} declares {} for {}",
+                    item.id, item.media_type, path
+                ),
+                &path,
+                Some(&item.href),
+            ));
+        }
+        if !paths.insert(path.to_ascii_lowercase()) {
+            return Err(violation(
+                "MANIFEST_HREF_DUPLICATE",
+                format!("{} is listed in the manifest more than once", path),
+                &path,
+                Some(&item.href),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Whether `media_type` is plausible for `path`; unknown extensions pass.
+fn media_type_fits_extension(path: &str, media_type: &str) -> bool {
+    let Some((_, ext)) = path.rsplit_once('.') else {
+        return true;
+    };
+    let ext = ext.to_ascii_lowercase();
+    let media_type = media_type
+        .split(';')
+        .next()
+        .unwrap_or(media_type)
+        .trim()
+        .to_ascii_lowercase();
+    let allowed: &[&str] = match ext.as_str() {
+        "xhtml" | "xht" => &["application/xhtml+xml"],
+        "html" | "htm" => &["application/xhtml+xml", "text/html"],
+        "css" => &["text/css"],
+        "ncx" => &["application/x-dtbncx+xml"],
+        "jpg" | "jpeg" => &["image/jpeg"],
+        "png" => &["image/png"],
+        "gif" => &["image/gif"],
+        "webp" => &["image/webp"],
+        "svg" => &["image/svg+xml"],
+        "smil" => &["application/smil+xml"],
+        "js" => &[
+            "application/javascript",
+            "application/ecmascript",
+            "text/javascript",
+        ],
+        "mp3" => &["audio/mpeg"],
+        "otf" | "ttf" | "woff" | "woff2" => {
+            return media_type.starts_with("font/")
+                || media_type.starts_with("application/font-")
+                || media_type.starts_with("application/x-font")
+                || media_type == "application/vnd.ms-opentype"
+                || media_type == "application/octet-stream";
+        }
+        _ => return true,
+    };
+    allowed.contains(&media_type.as_str())
+}
+
+/// Spine: at least one itemref, every idref and the `toc` attribute naming
+/// a manifest item.
+fn check_spine(metadata: &EpubMetadata, spine: &Spine, opf_path: &str) -> Result<(), EpubError> {
+    if spine.items().is_empty() {
+        return Err(violation(
+            "SPINE_EMPTY",
+            "spine has no itemref".to_string(),
+            opf_path,
+            None,
+        ));
+    }
+    for (index, item) in spine.items().iter().enumerate() {
+        if metadata.get_item(&item.idref).is_none() {
+            return Err(violation(
+                "SPINE_IDREF_NOT_IN_MANIFEST",
+                format!(
+                    "spine item {} references unknown manifest id {:?}",
+                    index, item.idref
+                ),
+                opf_path,
+                None,
+            ));
+        }
+    }
+    if let Some(toc) = spine.toc_id() {
+        if metadata.get_item(toc).is_none() {
+            return Err(violation(
+                "SPINE_TOC_NOT_IN_MANIFEST",
+                format!("spine toc references unknown manifest id {:?}", toc),
+                opf_path,
+                None,
+            ));
+        }
+    }
+    Ok(())
+}
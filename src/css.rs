@@ -2,7 +2,7 @@
 //!
 //! Parses a minimal subset of CSS sufficient for EPUB rendering:
 //! - Font properties: `font-size`, `font-family`, `font-weight`, `font-style`
-//! - Text: `text-align`, `line-height`
+//! - Text: `text-align`, `line-height`, `color`
 //! - Spacing: `margin-top`, `margin-bottom`
 //! - Selectors: tag, class, and inline `style` attributes
 //!
@@ -23,6 +23,8 @@ pub enum LineHeight {
     Px(f32),
     /// Multiplier relative to font size (e.g., 1.5 = 1.5x)
     Multiplier(f32),
+    /// Physical height in points (`pt`, `pc`, `in`, `cm`, `mm` are normalized)
+    Pt(f32),
 }
 
 /// Font size value
@@ -33,6 +35,8 @@ pub enum FontSize {
     Px(f32),
     /// Relative size in em units
     Em(f32),
+    /// Physical size in points (`pt`, `pc`, `in`, `cm`, `mm` are normalized)
+    Pt(f32),
 }
 
 /// Font weight
@@ -72,6 +76,35 @@ pub enum TextAlign {
     Justify,
 }
 
+/// sRGB color value
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Color {
+    /// Red channel
+    pub r: u8,
+    /// Green channel
+    pub g: u8,
+    /// Blue channel
+    pub b: u8,
+}
+
+impl Color {
+    /// Opaque black
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    /// Opaque white
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+
+    /// Build a color from 8-bit channels
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Rec. 601 luma (0 = black, 255 = white)
+    pub fn luma(self) -> u8 {
+        ((self.r as u32 * 299 + self.g as u32 * 587 + self.b as u32 * 114) / 1000) as u8
+    }
+}
+
 /// A set of CSS property values
 ///
 /// All fields are optional — `None` means "not specified" (inherit from parent
@@ -94,6 +127,8 @@ pub struct CssStyle {
     pub margin_top: Option<f32>,
     /// Bottom margin in pixels
     pub margin_bottom: Option<f32>,
+    /// Foreground (text) color
+    pub color: Option<Color>,
 }
 
 impl CssStyle {
@@ -112,6 +147,7 @@ impl CssStyle {
             && self.line_height.is_none()
             && self.margin_top.is_none()
             && self.margin_bottom.is_none()
+            && self.color.is_none()
     }
 
     /// Merge another style into this one (other's values take precedence)
@@ -140,6 +176,9 @@ impl CssStyle {
         if other.margin_bottom.is_some() {
             self.margin_bottom = other.margin_bottom;
         }
+        if other.color.is_some() {
+            self.color = other.color;
+        }
     }
 }
 
@@ -378,6 +417,9 @@ fn parse_declarations(declarations: &str) -> Result<CssStyle, EpubError> {
             "margin-bottom" => {
                 style.margin_bottom = parse_px_value(value);
             }
+            "color" => {
+                style.color = parse_color(value);
+            }
             "margin" => {
                 // Shorthand: only handle single-value case for now
                 if let Some(val) = parse_px_value(value) {
@@ -394,7 +436,7 @@ fn parse_declarations(declarations: &str) -> Result<CssStyle, EpubError> {
     Ok(style)
 }
 
-/// Parse a font-size value (px or em)
+/// Parse a font-size value (px, em, or an absolute physical unit)
 fn parse_font_size(value: &str) -> Option<FontSize> {
     let value = value.trim().to_lowercase();
     if let Some(px_str) = value.strip_suffix("px") {
@@ -402,15 +444,34 @@ fn parse_font_size(value: &str) -> Option<FontSize> {
     } else if let Some(em_str) = value.strip_suffix("em") {
         em_str.trim().parse::<f32>().ok().map(FontSize::Em)
     } else {
-        None
+        parse_absolute_pt(&value).map(FontSize::Pt)
     }
 }
 
+/// Parse an absolute physical length into points (1/72 inch)
+fn parse_absolute_pt(value: &str) -> Option<f32> {
+    const UNITS: [(&str, f32); 5] = [
+        ("pt", 1.0),
+        ("pc", 12.0),
+        ("in", 72.0),
+        ("cm", 72.0 / 2.54),
+        ("mm", 72.0 / 25.4),
+    ];
+    UNITS.iter().find_map(|(suffix, scale)| {
+        value
+            .strip_suffix(suffix)
+            .and_then(|num| num.trim().parse::<f32>().ok())
+            .map(|num| num * scale)
+    })
+}
+
 /// Parse a line-height value (px or unitless multiplier)
 fn parse_line_height(value: &str) -> Option<LineHeight> {
     let value = value.trim().to_lowercase();
     if let Some(px_str) = value.strip_suffix("px") {
         px_str.trim().parse::<f32>().ok().map(LineHeight::Px)
+    } else if let Some(pt) = parse_absolute_pt(&value) {
+        Some(LineHeight::Pt(pt))
     } else if value == "normal" {
         None // Use default
     } else {
@@ -419,6 +480,64 @@ fn parse_line_height(value: &str) -> Option<LineHeight> {
     }
 }
 
+/// Parse a color value (`#rgb`, `#rrggbb`, `rgb(r, g, b)`, or a basic keyword)
+fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim().to_lowercase();
+    if let Some(hex) = value.strip_prefix('#') {
+        let digit = |i: usize| u8::from_str_radix(hex.get(i..i + 1)?, 16).ok();
+        let pair = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return match hex.len() {
+            3 => Some(Color::rgb(digit(0)? * 17, digit(1)? * 17, digit(2)? * 17)),
+            6 => Some(Color::rgb(pair(0)?, pair(2)?, pair(4)?)),
+            _ => None,
+        };
+    }
+    if let Some(args) = value
+        .strip_prefix("rgb(")
+        .or_else(|| value.strip_prefix("rgba("))
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        let mut channels = args.split(',').map(|part| {
+            let part = part.trim();
+            match part.strip_suffix('%') {
+                Some(pct) => pct
+                    .trim()
+                    .parse::<f32>()
+                    .ok()
+                    .map(|pct| (pct.clamp(0.0, 100.0) * 2.55).round() as u8),
+                None => part
+                    .parse::<f32>()
+                    .ok()
+                    .map(|v| v.clamp(0.0, 255.0).round() as u8),
+            }
+        });
+        return Some(Color::rgb(
+            channels.next()??,
+            channels.next()??,
+            channels.next()??,
+        ));
+    }
+    match value.as_str() {
+        "black" => Some(Color::BLACK),
+        "white" => Some(Color::WHITE),
+        "gray" | "grey" => Some(Color::rgb(128, 128, 128)),
+        "silver" => Some(Color::rgb(192, 192, 192)),
+        "red" => Some(Color::rgb(255, 0, 0)),
+        "maroon" => Some(Color::rgb(128, 0, 0)),
+        "green" => Some(Color::rgb(0, 128, 0)),
+        "lime" => Some(Color::rgb(0, 255, 0)),
+        "blue" => Some(Color::rgb(0, 0, 255)),
+        "navy" => Some(Color::rgb(0, 0, 128)),
+        "yellow" => Some(Color::rgb(255, 255, 0)),
+        "olive" => Some(Color::rgb(128, 128, 0)),
+        "purple" => Some(Color::rgb(128, 0, 128)),
+        "teal" => Some(Color::rgb(0, 128, 128)),
+        "aqua" | "cyan" => Some(Color::rgb(0, 255, 255)),
+        "fuchsia" | "magenta" => Some(Color::rgb(255, 0, 255)),
+        _ => None,
+    }
+}
+
 /// Parse a pixel value (e.g., "10px" -> Some(10.0))
 fn parse_px_value(value: &str) -> Option<f32> {
     let value = value.trim().to_lowercase();
@@ -555,6 +674,18 @@ mod tests {
         assert_eq!(ss.rules[0].style.font_size, Some(FontSize::Em(1.5)));
     }
 
+    #[test]
+    fn test_parse_absolute_units_as_points() {
+        let css = "p { font-size: 10pt; line-height: 5mm; } h1 { font-size: 1pc; }";
+        let ss = parse_stylesheet(css).unwrap();
+        assert_eq!(ss.rules[0].style.font_size, Some(FontSize::Pt(10.0)));
+        let Some(LineHeight::Pt(pt)) = ss.rules[0].style.line_height else {
+            panic!("expected pt line-height");
+        };
+        assert!((pt - 14.173).abs() < 0.01);
+        assert_eq!(ss.rules[1].style.font_size, Some(FontSize::Pt(12.0)));
+    }
+
     #[test]
     fn test_parse_font_family() {
         let css = "p { font-family: 'Georgia'; }";
@@ -591,6 +722,23 @@ mod tests {
         assert_eq!(style.font_size, Some(FontSize::Px(14.0)));
     }
 
+    #[test]
+    fn test_parse_color_values() {
+        for (value, expected) in [
+            ("#fff", Color::WHITE),
+            ("#1A2b3C", Color::rgb(0x1a, 0x2b, 0x3c)),
+            ("rgb(255, 0, 128)", Color::rgb(255, 0, 128)),
+            ("rgb(100%, 0%, 50%)", Color::rgb(255, 0, 128)),
+            ("Navy", Color::rgb(0, 0, 128)),
+        ] {
+            let style = parse_inline_style(&alloc::format!("color: {}", value)).unwrap();
+            assert_eq!(style.color, Some(expected), "{}", value);
+        }
+        let style = parse_inline_style("color: #12").unwrap();
+        assert!(style.color.is_none());
+        assert_eq!(Color::rgb(255, 0, 0).luma(), 76);
+    }
+
     #[test]
     fn test_css_comments_skipped() {
         let css = "/* comment */ p { font-weight: bold; } /* another */";
@@ -601,10 +749,10 @@ mod tests {
 
     #[test]
     fn test_unknown_properties_ignored() {
-        let css = "p { color: red; font-weight: bold; display: flex; }";
+        let css = "p { float: left; font-weight: bold; display: flex; }";
         let ss = parse_stylesheet(css).unwrap();
         assert_eq!(ss.rules[0].style.font_weight, Some(FontWeight::Bold));
-        // color and display are silently ignored
+        // float and display are silently ignored
     }
 
     #[test]
@@ -785,6 +933,7 @@ mod tests {
             font_family: Some("Arial".into()),
             line_height: Some(LineHeight::Px(20.0)),
             margin_bottom: Some(5.0),
+            color: Some(Color::BLACK),
         };
         let overlay = CssStyle {
             font_weight: Some(FontWeight::Normal),
@@ -795,6 +944,7 @@ mod tests {
             font_family: Some("Georgia".into()),
             line_height: Some(LineHeight::Multiplier(1.5)),
             margin_bottom: Some(15.0),
+            color: Some(Color::rgb(0, 0, 128)),
         };
         base.merge(&overlay);
 
@@ -807,6 +957,7 @@ mod tests {
         assert_eq!(base.font_family, Some("Georgia".into()));
         assert_eq!(base.line_height, Some(LineHeight::Multiplier(1.5)));
         assert_eq!(base.margin_bottom, Some(15.0));
+        assert_eq!(base.color, Some(Color::rgb(0, 0, 128)));
     }
 
     #[test]
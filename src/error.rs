@@ -10,6 +10,8 @@ use alloc::format;
 use alloc::string::{String, ToString};
 use core::fmt;
 
+use crate::io::IoErrorInfo;
+
 /// Stable processing phases for typed EPUB failures.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[non_exhaustive]
@@ -96,6 +98,9 @@ pub struct PhaseError {
     pub message: Box<str>,
     /// Optional rich context.
     pub context: Option<Box<PhaseErrorContext>>,
+    /// Untyped error this one was raised from, when an `ErrorScope` lifted
+    /// it into a phase error.
+    pub cause: Option<Box<EpubError>>,
 }
 
 impl PhaseError {
@@ -106,7 +111,88 @@ impl PhaseError {
             code,
             message: message.into().into_boxed_str(),
             context: None,
+            cause: None,
+        }
+    }
+}
+
+/// Context shared by every error raised inside one chapter or resource
+/// operation.
+///
+/// `apply` lifts any `EpubError` into a `PhaseError` (keeping the original
+/// as `cause`) and fills the context fields the error does not already set,
+/// so inner scopes win over outer ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorScope {
+    phase: ErrorPhase,
+    path: Option<Box<str>>,
+    href: Option<Box<str>>,
+    chapter_index: Option<usize>,
+    limit: Option<Box<ErrorLimitContext>>,
+}
+
+impl ErrorScope {
+    /// Scope for work in `phase`, with no context yet.
+    pub fn new(phase: ErrorPhase) -> Self {
+        Self {
+            phase,
+            path: None,
+            href: None,
+            chapter_index: None,
+            limit: None,
+        }
+    }
+
+    /// Archive path being read.
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into().into_boxed_str());
+        self
+    }
+
+    /// OPF-relative href being worked on.
+    pub fn with_href(mut self, href: impl Into<String>) -> Self {
+        self.href = Some(href.into().into_boxed_str());
+        self
+    }
+
+    /// Spine index of the chapter being worked on.
+    pub fn with_chapter_index(mut self, chapter_index: usize) -> Self {
+        self.chapter_index = Some(chapter_index);
+        self
+    }
+
+    /// Limit in force, with the value observed against it.
+    pub fn with_limit(mut self, kind: impl Into<String>, actual: usize, limit: usize) -> Self {
+        self.limit = Some(Box::new(ErrorLimitContext::new(kind, actual, limit)));
+        self
+    }
+
+    /// Lift `err` into a phase error carrying this scope's context.
+    pub fn apply(&self, err: EpubError) -> EpubError {
+        let mut err = match err {
+            EpubError::Phase(err) => err,
+            other => PhaseError {
+                phase: self.phase,
+                code: other.code(),
+                message: other.to_string().into_boxed_str(),
+                context: Some(Box::new(other.intrinsic_context())),
+                cause: Some(Box::new(other)),
+            },
+        };
+        let ctx = err.context.get_or_insert_with(Box::default);
+        if ctx.path.is_none() {
+            ctx.path = self.path.clone();
+        }
+        if ctx.href.is_none() {
+            ctx.href = self.href.clone();
         }
+        if ctx.chapter_index.is_none() {
+            ctx.chapter_index = self.chapter_index;
+        }
+        if ctx.limit.is_none() {
+            ctx.limit = self.limit.clone();
+        }
+        EpubError::Phase(err)
     }
 }
 
@@ -126,8 +212,8 @@ pub enum EpubError {
     Navigation(String),
     /// CSS parsing error
     Css(String),
-    /// I/O error (description only, since `std::io::Error` is not `Clone`)
-    Io(String),
+    /// I/O error outside the ZIP layer, e.g. opening the book file
+    Io(IoErrorInfo),
     /// Chapter index requested is out of bounds
     ChapterOutOfBounds {
         /// Requested chapter index.
@@ -200,7 +286,7 @@ impl fmt::Display for EpubError {
             EpubError::InvalidEpub(msg) => write!(f, "Invalid EPUB: {}", msg),
             EpubError::Navigation(msg) => write!(f, "Navigation error: {}", msg),
             EpubError::Css(msg) => write!(f, "CSS error: {}", msg),
-            EpubError::Io(msg) => write!(f, "I/O error: {}", msg),
+            EpubError::Io(info) => write!(f, "I/O error: {}", info),
             EpubError::ChapterOutOfBounds {
                 index,
                 chapter_count,
@@ -275,7 +361,7 @@ pub enum ZipErrorKind {
     /// CRC32 mismatch
     CrcMismatch,
     /// I/O error during ZIP operations
-    IoError,
+    IoError(IoErrorInfo),
     /// Central directory full (exceeded max entries)
     CentralDirFull,
     /// Buffer too small for decompressed content
@@ -286,6 +372,10 @@ pub enum ZipErrorKind {
     InvalidMimetype(String),
     /// ZIP64 structures are present but unsupported
     UnsupportedZip64,
+    /// Entry inflates beyond the allowed compression ratio
+    CompressionRatioExceeded,
+    /// Archive-wide decompressed-bytes budget exhausted
+    DecompressBudgetExceeded,
 }
 
 /// Public ZIP error type alias used across the crate API.
@@ -299,25 +389,330 @@ impl fmt::Display for ZipErrorKind {
             ZipErrorKind::UnsupportedCompression => write!(f, "unsupported compression method"),
             ZipErrorKind::DecompressError => write!(f, "decompression failed"),
             ZipErrorKind::CrcMismatch => write!(f, "CRC32 checksum mismatch"),
-            ZipErrorKind::IoError => write!(f, "I/O error"),
+            ZipErrorKind::IoError(info) => write!(f, "I/O error: {}", info),
             ZipErrorKind::CentralDirFull => write!(f, "central directory full"),
             ZipErrorKind::BufferTooSmall => write!(f, "buffer too small"),
             ZipErrorKind::FileTooLarge => write!(f, "file too large"),
             ZipErrorKind::InvalidMimetype(msg) => write!(f, "invalid mimetype: {}", msg),
             ZipErrorKind::UnsupportedZip64 => write!(f, "ZIP64 is not supported"),
+            ZipErrorKind::CompressionRatioExceeded => {
+                write!(f, "compression ratio exceeds limit")
+            }
+            ZipErrorKind::DecompressBudgetExceeded => {
+                write!(f, "decompressed bytes budget exceeded")
+            }
+        }
+    }
+}
+
+// `defmt::Format` mirrors `Display` so bare-metal targets can log errors over
+// RTT without `core::fmt` machinery.
+#[cfg(feature = "defmt")]
+impl defmt::Format for ErrorPhase {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        let name = match self {
+            Self::Open => "open",
+            Self::Parse => "parse",
+            Self::Style => "style",
+            Self::Layout => "layout",
+            Self::Render => "render",
+        };
+        defmt::write!(f, "{=str}", name)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for LimitKind {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        let name = match self {
+            Self::FileSize => "File size",
+            Self::MemoryBudget => "Memory budget",
+            Self::EventCount => "Event count",
+            Self::NestingDepth => "Nesting depth",
+            Self::CssSize => "CSS size",
+            Self::FontLimit => "Font limit",
+        };
+        defmt::write!(f, "{=str}", name)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ErrorLimitContext {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(
+            f,
+            "{=str} {=usize} > {=usize}",
+            &self.kind,
+            self.actual,
+            self.limit
+        )
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for PhaseError {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(
+            f,
+            "{} error [{=str}]: {=str}",
+            self.phase,
+            self.code,
+            &self.message
+        );
+        if let Some(limit) = self.context.as_ref().and_then(|ctx| ctx.limit.as_deref()) {
+            defmt::write!(f, " ({})", limit);
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for EpubError {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self {
+            Self::Phase(err) => defmt::write!(f, "{}", err),
+            Self::Zip(kind) => defmt::write!(f, "ZIP error: {}", kind),
+            Self::Parse(msg) => defmt::write!(f, "Parse error: {=str}", msg.as_str()),
+            Self::InvalidEpub(msg) => defmt::write!(f, "Invalid EPUB: {=str}", msg.as_str()),
+            Self::Navigation(msg) => {
+                defmt::write!(f, "Navigation error: {=str}", msg.as_str())
+            }
+            Self::Css(msg) => defmt::write!(f, "CSS error: {=str}", msg.as_str()),
+            Self::Io(info) => defmt::write!(f, "I/O error: {}", info),
+            Self::ChapterOutOfBounds {
+                index,
+                chapter_count,
+            } => defmt::write!(
+                f,
+                "Chapter index {=usize} out of bounds (chapter count: {=usize})",
+                index,
+                chapter_count
+            ),
+            Self::ManifestItemMissing { idref } => defmt::write!(
+                f,
+                "Spine item '{=str}' does not exist in manifest",
+                idref.as_str()
+            ),
+            Self::ChapterNotUtf8 { href } => defmt::write!(
+                f,
+                "Chapter content is not valid UTF-8: {=str}",
+                href.as_str()
+            ),
+            Self::LimitExceeded {
+                kind,
+                actual,
+                limit,
+                path,
+            } => {
+                defmt::write!(
+                    f,
+                    "{} limit exceeded: {=usize} > {=usize}",
+                    kind,
+                    actual,
+                    limit
+                );
+                if let Some(path) = path {
+                    defmt::write!(f, " at {=str}", path.as_str());
+                }
+            }
+            Self::BufferTooSmall {
+                required,
+                provided,
+                context,
+            } => defmt::write!(
+                f,
+                "Buffer too small for {=str}: required {=usize} bytes, provided {=usize}",
+                context.as_str(),
+                required,
+                provided
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ZipErrorKind {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        let text = match self {
+            Self::FileNotFound => "file not found in archive",
+            Self::InvalidFormat => "invalid ZIP format",
+            Self::UnsupportedCompression => "unsupported compression method",
+            Self::DecompressError => "decompression failed",
+            Self::CrcMismatch => "CRC32 checksum mismatch",
+            Self::IoError(info) => return defmt::write!(f, "I/O error: {}", info),
+            Self::CentralDirFull => "central directory full",
+            Self::BufferTooSmall => "buffer too small",
+            Self::FileTooLarge => "file too large",
+            Self::InvalidMimetype(msg) => {
+                return defmt::write!(f, "invalid mimetype: {=str}", msg.as_str());
+            }
+            Self::UnsupportedZip64 => "ZIP64 is not supported",
+            Self::CompressionRatioExceeded => "compression ratio exceeds limit",
+            Self::DecompressBudgetExceeded => "decompressed bytes budget exceeded",
+        };
+        defmt::write!(f, "{=str}", text)
+    }
+}
+
+impl EpubError {
+    /// Underlying I/O failure, whether raised directly or by the ZIP layer.
+    pub fn io_error(&self) -> Option<&IoErrorInfo> {
+        match self.cause() {
+            EpubError::Io(info) => Some(info),
+            EpubError::Zip(err) => err.io_error(),
+            _ => None,
+        }
+    }
+
+    /// Stable machine-readable code, listed in [`ERROR_CODES`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            EpubError::Phase(err) => err.code,
+            EpubError::Zip(kind) => kind.code(),
+            EpubError::Parse(_) => "PARSE_ERROR",
+            EpubError::InvalidEpub(_) => "INVALID_EPUB",
+            EpubError::Navigation(_) => "NAVIGATION_ERROR",
+            EpubError::Css(_) => "CSS_ERROR",
+            EpubError::Io(_) => "IO_ERROR",
+            EpubError::ChapterOutOfBounds { .. } => "CHAPTER_OUT_OF_BOUNDS",
+            EpubError::ManifestItemMissing { .. } => "MANIFEST_ITEM_MISSING",
+            EpubError::ChapterNotUtf8 { .. } => "CHAPTER_NOT_UTF8",
+            EpubError::LimitExceeded { .. } => "LIMIT_EXCEEDED",
+            EpubError::BufferTooSmall { .. } => "BUFFER_TOO_SMALL",
+        }
+    }
+
+    /// Rich context, present on phase errors.
+    pub fn phase_context(&self) -> Option<&PhaseErrorContext> {
+        match self {
+            EpubError::Phase(err) => err.context.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The untyped error a phase error was lifted from, or `self`.
+    pub fn cause(&self) -> &EpubError {
+        match self {
+            EpubError::Phase(PhaseError {
+                cause: Some(cause), ..
+            }) => cause.cause(),
+            _ => self,
+        }
+    }
+
+    /// Context the error itself knows, used when lifting it into a phase
+    /// error.
+    fn intrinsic_context(&self) -> PhaseErrorContext {
+        let mut ctx = PhaseErrorContext {
+            token_offset: self
+                .io_error()
+                .and_then(|info| info.offset)
+                .and_then(|offset| usize::try_from(offset).ok()),
+            ..PhaseErrorContext::default()
+        };
+        match self {
+            EpubError::ChapterOutOfBounds {
+                index,
+                chapter_count,
+            } => {
+                ctx.chapter_index = Some(*index);
+                ctx.limit = Some(Box::new(ErrorLimitContext::new(
+                    "chapter_count",
+                    *index,
+                    *chapter_count,
+                )));
+            }
+            EpubError::ChapterNotUtf8 { href } => ctx.href = Some(href.as_str().into()),
+            EpubError::LimitExceeded {
+                kind,
+                actual,
+                limit,
+                path,
+            } => {
+                ctx.path = path.as_deref().map(Into::into);
+                ctx.limit = Some(Box::new(ErrorLimitContext::new(
+                    kind.to_string(),
+                    *actual,
+                    *limit,
+                )));
+            }
+            EpubError::BufferTooSmall {
+                required, provided, ..
+            } => {
+                ctx.limit = Some(Box::new(ErrorLimitContext::new(
+                    "buffer_bytes",
+                    *required,
+                    *provided,
+                )));
+            }
+            _ => {}
+        }
+        ctx
+    }
+}
+
+impl ZipErrorKind {
+    /// Underlying I/O failure, if this is `IoError`.
+    pub fn io_error(&self) -> Option<&IoErrorInfo> {
+        match self {
+            ZipErrorKind::IoError(info) => Some(info),
+            _ => None,
+        }
+    }
+
+    /// Stable machine-readable code, listed in [`ERROR_CODES`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            ZipErrorKind::FileNotFound => "ZIP_FILE_NOT_FOUND",
+            ZipErrorKind::InvalidFormat => "ZIP_INVALID_FORMAT",
+            ZipErrorKind::UnsupportedCompression => "ZIP_UNSUPPORTED_COMPRESSION",
+            ZipErrorKind::DecompressError => "ZIP_DECOMPRESS_ERROR",
+            ZipErrorKind::CrcMismatch => "ZIP_CRC_MISMATCH",
+            ZipErrorKind::IoError(_) => "ZIP_IO_ERROR",
+            ZipErrorKind::CentralDirFull => "ZIP_CENTRAL_DIR_FULL",
+            ZipErrorKind::BufferTooSmall => "ZIP_BUFFER_TOO_SMALL",
+            ZipErrorKind::FileTooLarge => "ZIP_FILE_TOO_LARGE",
+            ZipErrorKind::InvalidMimetype(_) => "ZIP_INVALID_MIMETYPE",
+            ZipErrorKind::UnsupportedZip64 => "ZIP_UNSUPPORTED_ZIP64",
+            ZipErrorKind::CompressionRatioExceeded => "ZIP_COMPRESSION_RATIO_EXCEEDED",
+            ZipErrorKind::DecompressBudgetExceeded => "ZIP_DECOMPRESS_BUDGET_EXCEEDED",
         }
     }
 }
 
 #[cfg(feature = "std")]
-impl std::error::Error for EpubError {}
+impl std::error::Error for EpubError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EpubError::Phase(PhaseError {
+                cause: Some(cause), ..
+            }) => Some(cause.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 #[cfg(feature = "std")]
 impl std::error::Error for ZipErrorKind {}
 
 impl From<crate::tokenizer::TokenizeError> for EpubError {
     fn from(err: crate::tokenizer::TokenizeError) -> Self {
-        EpubError::Parse(err.to_string())
+        let message = err.to_string();
+        EpubError::Phase(PhaseError {
+            phase: ErrorPhase::Parse,
+            code: err.code(),
+            message: message.clone().into_boxed_str(),
+            context: Some(Box::new(PhaseErrorContext {
+                token_offset: err.offset(),
+                ..PhaseErrorContext::default()
+            })),
+            cause: Some(Box::new(EpubError::Parse(message))),
+        })
+    }
+}
+
+impl From<IoErrorInfo> for EpubError {
+    fn from(info: IoErrorInfo) -> Self {
+        EpubError::Io(info)
     }
 }
 
@@ -327,6 +722,192 @@ impl From<PhaseError> for EpubError {
     }
 }
 
+/// A stable error code and what it means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCodeInfo {
+    /// Code as it appears in `PhaseError::code`.
+    pub code: &'static str,
+    /// One-line meaning.
+    pub summary: &'static str,
+}
+
+impl ErrorCodeInfo {
+    const fn new(code: &'static str, summary: &'static str) -> Self {
+        Self { code, summary }
+    }
+
+    /// Registry entry for `code`.
+    pub fn lookup(code: &str) -> Option<&'static ErrorCodeInfo> {
+        ERROR_CODES.iter().find(|info| info.code == code)
+    }
+}
+
+/// Every code `PhaseError::code` and `EpubError::code` can return.
+///
+/// Codes are stable: they are never renamed or reused, only added.
+pub const ERROR_CODES: &[ErrorCodeInfo] = &[
+    // Open: package conformance (`ValidationMode::Strict`)
+    ErrorCodeInfo::new(
+        "OCF_INVALID_MIMETYPE",
+        "`mimetype` entry is missing or misnamed.",
+    ),
+    ErrorCodeInfo::new(
+        "OCF_MIMETYPE_NOT_FIRST",
+        "`mimetype` is not the first archive entry.",
+    ),
+    ErrorCodeInfo::new(
+        "OCF_MIMETYPE_COMPRESSED",
+        "`mimetype` is compressed instead of stored.",
+    ),
+    ErrorCodeInfo::new(
+        "OCF_CONTAINER_INVALID",
+        "`META-INF/container.xml` is malformed or names no package document.",
+    ),
+    ErrorCodeInfo::new(
+        "OPF_PACKAGE_INVALID",
+        "Package document root, version, or unique identifier is invalid.",
+    ),
+    ErrorCodeInfo::new(
+        "OPF_METADATA_MISSING",
+        "Package metadata lacks a title, identifier, or language.",
+    ),
+    ErrorCodeInfo::new(
+        "OPF_ID_DUPLICATE",
+        "An `id` is used more than once in the package document.",
+    ),
+    ErrorCodeInfo::new(
+        "MANIFEST_ITEM_INVALID",
+        "A manifest item lacks `id`, `href`, or `media-type`.",
+    ),
+    ErrorCodeInfo::new("MANIFEST_ID_DUPLICATE", "Two manifest items share an id."),
+    ErrorCodeInfo::new(
+        "MANIFEST_HREF_DUPLICATE",
+        "Two manifest items point at the same resource.",
+    ),
+    ErrorCodeInfo::new(
+        "MANIFEST_RESOURCE_MISSING",
+        "A manifest item points at a resource missing from the archive.",
+    ),
+    ErrorCodeInfo::new(
+        "MANIFEST_MEDIA_TYPE_MISMATCH",
+        "A manifest media type disagrees with the file extension.",
+    ),
+    ErrorCodeInfo::new("SPINE_ITEMREF_INVALID", "A spine itemref lacks `idref`."),
+    ErrorCodeInfo::new("SPINE_EMPTY", "The spine has no itemref."),
+    ErrorCodeInfo::new(
+        "SPINE_IDREF_NOT_IN_MANIFEST",
+        "A spine itemref names an unknown manifest id.",
+    ),
+    ErrorCodeInfo::new(
+        "SPINE_TOC_NOT_IN_MANIFEST",
+        "The spine `toc` attribute names an unknown manifest id.",
+    ),
+    ErrorCodeInfo::new(
+        "NAV_BYTES_LIMIT",
+        "The navigation document exceeds `max_nav_bytes`.",
+    ),
+    // Book and render prep
+    ErrorCodeInfo::new(
+        "BOOK_CHAPTER_REF",
+        "The chapter index does not resolve to a manifest item.",
+    ),
+    ErrorCodeInfo::new("BOOK_CHAPTER_HTML", "Chapter markup could not be read."),
+    ErrorCodeInfo::new(
+        "BOOK_CHAPTER_STYLESHEET_READ",
+        "A linked stylesheet could not be read.",
+    ),
+    ErrorCodeInfo::new(
+        "BOOK_EMBEDDED_FONTS",
+        "Embedded font faces could not be discovered.",
+    ),
+    ErrorCodeInfo::new(
+        "ENTRY_BYTES_LIMIT",
+        "Chapter markup exceeds `max_entry_bytes`.",
+    ),
+    // Tokenizer
+    ErrorCodeInfo::new("TOKENIZE_PARSE_ERROR", "Chapter markup is not well-formed."),
+    ErrorCodeInfo::new(
+        "TOKENIZE_INVALID_STRUCTURE",
+        "Chapter markup exceeds a tokenizer limit or nests badly.",
+    ),
+    // Style
+    ErrorCodeInfo::new(
+        "STYLE_CSS_TOO_LARGE",
+        "A stylesheet exceeds `max_css_bytes`.",
+    ),
+    ErrorCodeInfo::new("STYLE_CSS_NOT_UTF8", "A stylesheet is not UTF-8."),
+    ErrorCodeInfo::new("STYLE_PARSE_ERROR", "A stylesheet could not be parsed."),
+    ErrorCodeInfo::new(
+        "STYLE_SELECTOR_LIMIT",
+        "A stylesheet exceeds `max_selectors`.",
+    ),
+    ErrorCodeInfo::new(
+        "STYLE_INLINE_BYTES_LIMIT",
+        "An inline `style` attribute exceeds `max_inline_style_bytes`.",
+    ),
+    ErrorCodeInfo::new(
+        "STYLE_INLINE_PARSE_ERROR",
+        "An inline `style` attribute could not be parsed.",
+    ),
+    ErrorCodeInfo::new(
+        "STYLE_TOKENIZE_ERROR",
+        "Chapter markup failed to tokenize while styling.",
+    ),
+    ErrorCodeInfo::new("FONT_FACE_LIMIT", "Embedded font faces exceed `max_faces`."),
+    ErrorCodeInfo::new("FONT_LOAD_ERROR", "An embedded font could not be read."),
+    ErrorCodeInfo::new(
+        "FONT_BYTES_PER_FACE_LIMIT",
+        "An embedded font exceeds `max_bytes_per_font`.",
+    ),
+    ErrorCodeInfo::new(
+        "FONT_TOTAL_BYTES_LIMIT",
+        "Embedded fonts exceed `max_total_font_bytes`.",
+    ),
+    // Render engine (`mu-epub-render`)
+    ErrorCodeInfo::new("RENDER_CANCELLED", "The layout run was cancelled."),
+    ErrorCodeInfo::new(
+        "RENDER_LIMIT_EXCEEDED",
+        "Buffered pages exceed a render memory limit.",
+    ),
+    ErrorCodeInfo::new(
+        "RENDER_PROFILE_MISMATCH",
+        "A page map was built under a different pagination profile.",
+    ),
+    // Lifted from untyped `EpubError` variants by `ErrorScope`
+    ErrorCodeInfo::new("PARSE_ERROR", "`EpubError::Parse`."),
+    ErrorCodeInfo::new("INVALID_EPUB", "`EpubError::InvalidEpub`."),
+    ErrorCodeInfo::new("NAVIGATION_ERROR", "`EpubError::Navigation`."),
+    ErrorCodeInfo::new("CSS_ERROR", "`EpubError::Css`."),
+    ErrorCodeInfo::new("IO_ERROR", "`EpubError::Io`."),
+    ErrorCodeInfo::new("CHAPTER_OUT_OF_BOUNDS", "`EpubError::ChapterOutOfBounds`."),
+    ErrorCodeInfo::new("MANIFEST_ITEM_MISSING", "`EpubError::ManifestItemMissing`."),
+    ErrorCodeInfo::new("CHAPTER_NOT_UTF8", "`EpubError::ChapterNotUtf8`."),
+    ErrorCodeInfo::new("LIMIT_EXCEEDED", "`EpubError::LimitExceeded`."),
+    ErrorCodeInfo::new("BUFFER_TOO_SMALL", "`EpubError::BufferTooSmall`."),
+    ErrorCodeInfo::new("ZIP_FILE_NOT_FOUND", "`ZipError::FileNotFound`."),
+    ErrorCodeInfo::new("ZIP_INVALID_FORMAT", "`ZipError::InvalidFormat`."),
+    ErrorCodeInfo::new(
+        "ZIP_UNSUPPORTED_COMPRESSION",
+        "`ZipError::UnsupportedCompression`.",
+    ),
+    ErrorCodeInfo::new("ZIP_DECOMPRESS_ERROR", "`ZipError::DecompressError`."),
+    ErrorCodeInfo::new("ZIP_CRC_MISMATCH", "`ZipError::CrcMismatch`."),
+    ErrorCodeInfo::new("ZIP_IO_ERROR", "`ZipError::IoError`."),
+    ErrorCodeInfo::new("ZIP_CENTRAL_DIR_FULL", "`ZipError::CentralDirFull`."),
+    ErrorCodeInfo::new("ZIP_BUFFER_TOO_SMALL", "`ZipError::BufferTooSmall`."),
+    ErrorCodeInfo::new("ZIP_FILE_TOO_LARGE", "`ZipError::FileTooLarge`."),
+    ErrorCodeInfo::new("ZIP_INVALID_MIMETYPE", "`ZipError::InvalidMimetype`."),
+    ErrorCodeInfo::new("ZIP_UNSUPPORTED_ZIP64", "`ZipError::UnsupportedZip64`."),
+    ErrorCodeInfo::new(
+        "ZIP_COMPRESSION_RATIO_EXCEEDED",
+        "`ZipError::CompressionRatioExceeded`.",
+    ),
+    ErrorCodeInfo::new(
+        "ZIP_DECOMPRESS_BUDGET_EXCEEDED",
+        "`ZipError::DecompressBudgetExceeded`.",
+    ),
+];
+
 #[cfg(test)]
 mod tests {
     use super::*;
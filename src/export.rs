@@ -0,0 +1,1279 @@
+//! Chapter export to plain text, Markdown, and self-contained HTML.
+//!
+//! Chapter XHTML is walked once with a pull parser; block structure is
+//! mapped onto line prefixes (quote markers, list indentation) and output
+//! is written through a small buffer, so memory stays bounded by nesting
+//! depth rather than chapter size.
+//!
+//! HTML bundles keep an allow-list of elements and attributes, inline
+//! stylesheets with external references removed, and embed images as
+//! base64 `data:` URIs within `HtmlExportLimits`.
+
+use std::io::Write;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::book::should_skip_text_tag;
+use crate::error::EpubError;
+use crate::io::{IoErrorInfo, IoOperation};
+
+/// Output format for `EpubBook::export_chapter`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExportFormat {
+    /// Plain text: blocks separated by blank lines, list items bulleted,
+    /// block quotes indented, inline markup dropped.
+    #[default]
+    Text,
+    /// CommonMark: headings, lists, block quotes, emphasis, code, links,
+    /// and images.
+    Markdown,
+}
+
+/// Output bytes buffered before they are written out.
+const EXPORT_FLUSH_BYTES: usize = 4 * 1024;
+
+/// Deepest list or link nesting tracked; deeper levels share the last.
+const MAX_EXPORT_NESTING: usize = 32;
+
+/// Separation owed before the next block's first line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Gap {
+    None,
+    Line,
+    Blank,
+}
+
+/// Where the output cursor is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Line {
+    /// Nothing written yet.
+    Empty,
+    /// At the start of a line; its prefix is not written yet.
+    Start,
+    /// Inside a line.
+    Mid,
+}
+
+/// One open `<ul>`/`<ol>`.
+#[derive(Clone, Copy, Debug)]
+struct ListLevel {
+    /// Next item number of an ordered list.
+    next: Option<u32>,
+    /// Width of the current item's marker, indenting its continuation.
+    indent: usize,
+}
+
+/// Streaming XHTML to text/Markdown writer.
+struct Exporter<'w, W: Write> {
+    out: &'w mut W,
+    markdown: bool,
+    buf: String,
+    line: Line,
+    gap: Gap,
+    /// Quote depth of the blank line owed by `gap`.
+    gap_quotes: usize,
+    space: bool,
+    quotes: usize,
+    lists: Vec<ListLevel>,
+    marker: Option<String>,
+    heading: Option<u8>,
+    links: Vec<Option<String>>,
+    /// Lists and links opened past `MAX_EXPORT_NESTING`.
+    overflow: usize,
+    pre: usize,
+    code: usize,
+    skip: usize,
+}
+
+/// Export chapter XHTML `html` as `format` into `out`.
+pub(crate) fn export_markup<W: Write>(
+    html: &[u8],
+    format: ExportFormat,
+    out: &mut W,
+) -> Result<(), EpubError> {
+    let mut exporter = Exporter {
+        out,
+        markdown: format == ExportFormat::Markdown,
+        buf: String::with_capacity(EXPORT_FLUSH_BYTES),
+        line: Line::Empty,
+        gap: Gap::None,
+        gap_quotes: 0,
+        space: false,
+        quotes: 0,
+        lists: Vec::with_capacity(0),
+        marker: None,
+        heading: None,
+        links: Vec::with_capacity(0),
+        overflow: 0,
+        pre: 0,
+        code: 0,
+        skip: 0,
+    };
+    let mut reader = Reader::from_reader(html);
+    reader.config_mut().trim_text(false);
+    let mut buf = Vec::with_capacity(0);
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => {
+                let name = e.local_name();
+                let name = String::from_utf8_lossy(name.as_ref()).to_ascii_lowercase();
+                if should_skip_text_tag(&name) {
+                    exporter.skip += 1;
+                } else if exporter.skip == 0 {
+                    exporter.open(&name, &e);
+                }
+            }
+            Ok(Event::Empty(e)) => {
+                let name = e.local_name();
+                let name = String::from_utf8_lossy(name.as_ref()).to_ascii_lowercase();
+                if exporter.skip == 0 && !should_skip_text_tag(&name) {
+                    exporter.empty(&name, &e);
+                }
+            }
+            Ok(Event::End(e)) => {
+                let name = e.local_name();
+                let name = String::from_utf8_lossy(name.as_ref()).to_ascii_lowercase();
+                if should_skip_text_tag(&name) {
+                    exporter.skip = exporter.skip.saturating_sub(1);
+                } else if exporter.skip == 0 {
+                    exporter.close(&name);
+                }
+            }
+            Ok(Event::Text(e)) if exporter.skip == 0 => {
+                let text = e
+                    .decode()
+                    .map_err(|err| EpubError::Parse(format!("Decode error: {:?}", err)))?;
+                exporter.text(&text);
+            }
+            Ok(Event::CData(e)) if exporter.skip == 0 => {
+                let text = reader
+                    .decoder()
+                    .decode(&e)
+                    .map_err(|err| EpubError::Parse(format!("Decode error: {:?}", err)))?;
+                exporter.text(&text);
+            }
+            Ok(Event::GeneralRef(e)) if exporter.skip == 0 => {
+                let entity_name = e
+                    .decode()
+                    .map_err(|err| EpubError::Parse(format!("Decode error: {:?}", err)))?;
+                let entity = format!("&{};", entity_name);
+                let resolved = quick_xml::escape::unescape(&entity)
+                    .map_err(|err| EpubError::Parse(format!("Unescape error: {:?}", err)))?;
+                exporter.text(&resolved);
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(err) => return Err(EpubError::Parse(format!("XML error: {:?}", err))),
+        }
+        buf.clear();
+        if exporter.buf.len() >= EXPORT_FLUSH_BYTES {
+            exporter.flush()?;
+        }
+    }
+    if exporter.line == Line::Mid {
+        exporter.buf.push('\n');
+    }
+    exporter.flush()
+}
+
+impl<W: Write> Exporter<'_, W> {
+    fn open(&mut self, name: &str, e: &BytesStart<'_>) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.block(Gap::Blank);
+                if self.markdown {
+                    self.heading = Some(name.as_bytes()[1] - b'0');
+                }
+            }
+            "blockquote" => {
+                self.block(Gap::Blank);
+                self.quotes += 1;
+            }
+            "ul" | "ol" => {
+                self.block(Gap::Blank);
+                if self.lists.len() < MAX_EXPORT_NESTING {
+                    let next = (name == "ol").then(|| {
+                        attr(e, b"start")
+                            .and_then(|start| start.trim().parse().ok())
+                            .unwrap_or(1)
+                    });
+                    self.lists.push(ListLevel { next, indent: 0 });
+                } else {
+                    self.overflow += 1;
+                }
+            }
+            "li" => {
+                self.block(Gap::Line);
+                let marker = match self.lists.last_mut() {
+                    Some(ListLevel {
+                        next: Some(next), ..
+                    }) => {
+                        let marker = format!("{next}. ");
+                        *next = next.saturating_add(1);
+                        marker
+                    }
+                    _ => "- ".to_string(),
+                };
+                if let Some(level) = self.lists.last_mut() {
+                    level.indent = marker.len();
+                }
+                self.marker = Some(marker);
+            }
+            "pre" => {
+                self.block(Gap::Blank);
+                if self.markdown {
+                    self.begin_content();
+                    self.buf.push_str("```");
+                    self.newline();
+                }
+                self.pre += 1;
+            }
+            "em" | "i" | "cite" | "dfn" => self.inline_marker("*"),
+            "strong" | "b" => self.inline_marker("**"),
+            "code" | "kbd" | "samp" => {
+                if self.pre == 0 {
+                    self.inline_marker("`");
+                }
+                self.code += 1;
+            }
+            "a" => {
+                let href = attr(e, b"href").filter(|href| !href.is_empty());
+                if self.markdown && href.is_some() {
+                    self.inline_marker("[");
+                }
+                if self.links.len() < MAX_EXPORT_NESTING {
+                    self.links.push(href);
+                } else {
+                    self.overflow += 1;
+                }
+            }
+            "td" | "th" => self.space = true,
+            _ if is_block(name) => self.block(Gap::Blank),
+            _ => {}
+        }
+    }
+
+    fn empty(&mut self, name: &str, e: &BytesStart<'_>) {
+        match name {
+            "br" => {
+                if self.markdown && self.line == Line::Mid {
+                    self.buf.push('\\');
+                }
+                self.newline();
+            }
+            "hr" => {
+                self.block(Gap::Blank);
+                if self.markdown {
+                    self.begin_content();
+                    self.buf.push_str("---");
+                }
+                self.block(Gap::Blank);
+            }
+            "img" => {
+                let alt = attr(e, b"alt").unwrap_or_default();
+                if self.markdown {
+                    let Some(src) = attr(e, b"src") else {
+                        return;
+                    };
+                    self.inline_marker("![");
+                    self.words(&alt);
+                    self.buf.push_str("](");
+                    self.buf.push_str(&src);
+                    self.buf.push(')');
+                } else {
+                    self.text(&alt);
+                }
+            }
+            "td" | "th" => self.space = true,
+            _ if is_block(name) => self.block(Gap::Blank),
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.block(Gap::Blank);
+                self.heading = None;
+            }
+            "blockquote" => {
+                self.block(Gap::Blank);
+                self.quotes = self.quotes.saturating_sub(1);
+            }
+            "ul" | "ol" => {
+                self.block(Gap::Blank);
+                if self.overflow > 0 {
+                    self.overflow -= 1;
+                } else {
+                    self.lists.pop();
+                }
+                self.marker = None;
+            }
+            "li" => {
+                self.block(Gap::Line);
+                self.marker = None;
+            }
+            "pre" => {
+                self.pre = self.pre.saturating_sub(1);
+                if self.markdown {
+                    if self.line == Line::Mid {
+                        self.newline();
+                    }
+                    self.begin_content();
+                    self.buf.push_str("```");
+                }
+                self.block(Gap::Blank);
+            }
+            "em" | "i" | "cite" | "dfn" => self.close_marker("*"),
+            "strong" | "b" => self.close_marker("**"),
+            "code" | "kbd" | "samp" => {
+                self.code = self.code.saturating_sub(1);
+                if self.pre == 0 {
+                    self.close_marker("`");
+                }
+            }
+            "a" if self.overflow > 0 => self.overflow -= 1,
+            "a" => {
+                if let Some(Some(href)) = self.links.pop() {
+                    if self.markdown {
+                        self.buf.push_str("](");
+                        self.buf.push_str(&href);
+                        self.buf.push(')');
+                    }
+                }
+            }
+            _ if is_block(name) => self.block(Gap::Blank),
+            _ => {}
+        }
+    }
+
+    /// End the current line and owe at least `gap` before the next one.
+    fn block(&mut self, gap: Gap) {
+        if self.line == Line::Mid {
+            self.buf.push('\n');
+            self.line = Line::Start;
+        }
+        self.space = false;
+        self.gap_quotes = if self.gap == Gap::None {
+            self.quotes
+        } else {
+            self.gap_quotes.min(self.quotes)
+        };
+        self.gap = self.gap.max(gap);
+    }
+
+    /// Hard line break inside a block.
+    fn newline(&mut self) {
+        if self.line == Line::Empty {
+            return;
+        }
+        if self.line == Line::Start {
+            self.prefix();
+        }
+        self.buf.push('\n');
+        self.line = Line::Start;
+        self.space = false;
+    }
+
+    /// Write owed separation and the line prefix before content.
+    fn begin_content(&mut self) {
+        if self.line == Line::Mid {
+            return;
+        }
+        if self.line == Line::Start && self.gap == Gap::Blank {
+            let start = self.buf.len();
+            self.quote_prefix(self.gap_quotes.min(self.quotes));
+            let trimmed = self.buf[start..].trim_end().len();
+            self.buf.truncate(start + trimmed);
+            self.buf.push('\n');
+        }
+        self.prefix();
+        if let Some(level) = self.heading.take() {
+            for _ in 0..level {
+                self.buf.push('#');
+            }
+            self.buf.push(' ');
+        }
+        self.line = Line::Mid;
+        self.gap = Gap::None;
+    }
+
+    fn quote_prefix(&mut self, depth: usize) {
+        let quote = if self.markdown { "> " } else { "  " };
+        for _ in 0..depth {
+            self.buf.push_str(quote);
+        }
+    }
+
+    fn prefix(&mut self) {
+        self.quote_prefix(self.quotes);
+        let depth = self.lists.len();
+        for (i, level) in self.lists.iter().enumerate() {
+            if i + 1 == depth {
+                if let Some(marker) = self.marker.take() {
+                    self.buf.push_str(&marker);
+                    continue;
+                }
+            }
+            for _ in 0..level.indent {
+                self.buf.push(' ');
+            }
+        }
+    }
+
+    fn inline_marker(&mut self, marker: &str) {
+        if !self.markdown {
+            return;
+        }
+        if self.space && self.line == Line::Mid {
+            self.buf.push(' ');
+        }
+        self.space = false;
+        self.begin_content();
+        self.buf.push_str(marker);
+    }
+
+    fn close_marker(&mut self, marker: &str) {
+        if self.markdown && self.line == Line::Mid {
+            self.buf.push_str(marker);
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.pre == 0 {
+            self.words(text);
+            return;
+        }
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.newline();
+            }
+            if !line.is_empty() {
+                self.begin_content();
+                self.buf.push_str(line);
+            }
+        }
+    }
+
+    /// Append `text` with whitespace collapsed, escaped for Markdown.
+    fn words(&mut self, text: &str) {
+        if text.starts_with(char::is_whitespace) {
+            self.space = true;
+        }
+        for word in text.split_whitespace() {
+            if self.space && self.line == Line::Mid {
+                self.buf.push(' ');
+            }
+            let line_start = self.line != Line::Mid;
+            self.begin_content();
+            if self.markdown && self.code == 0 {
+                self.escaped(word, line_start);
+            } else {
+                self.buf.push_str(word);
+            }
+            self.space = true;
+        }
+        if !text.ends_with(char::is_whitespace) {
+            self.space = false;
+        }
+    }
+
+    fn escaped(&mut self, word: &str, line_start: bool) {
+        if line_start && word.starts_with(['#', '+', '-', '=']) {
+            self.buf.push('\\');
+        }
+        for ch in word.chars() {
+            if matches!(ch, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>') {
+                self.buf.push('\\');
+            }
+            self.buf.push(ch);
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), EpubError> {
+        self.out
+            .write_all(self.buf.as_bytes())
+            .map_err(|err| EpubError::Io(IoErrorInfo::from_std(&err, IoOperation::Write)))?;
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+/// Elements that start and end a paragraph-like block.
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "div"
+            | "section"
+            | "article"
+            | "main"
+            | "body"
+            | "figure"
+            | "figcaption"
+            | "address"
+            | "dl"
+            | "dt"
+            | "dd"
+            | "table"
+            | "tr"
+            | "caption"
+    )
+}
+
+/// Unescaped value of attribute `name`.
+fn attr(e: &BytesStart<'_>, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|attr| attr.key.local_name().as_ref() == name)
+        .and_then(|attr| attr.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+/// Resource limits for HTML bundle export.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HtmlExportLimits {
+    /// Largest image embedded; bigger images keep only their alt text.
+    pub max_image_bytes: usize,
+    /// Image bytes embedded across the whole document.
+    pub max_total_image_bytes: usize,
+    /// Largest stylesheet inlined; bigger ones are left out.
+    pub max_css_bytes: usize,
+}
+
+impl Default for HtmlExportLimits {
+    fn default() -> Self {
+        Self {
+            max_image_bytes: 1024 * 1024,
+            max_total_image_bytes: 16 * 1024 * 1024,
+            max_css_bytes: 256 * 1024,
+        }
+    }
+}
+
+/// Elements kept in HTML bundles; others are unwrapped to their content.
+const HTML_ELEMENTS: &[&str] = &[
+    "a",
+    "abbr",
+    "address",
+    "article",
+    "aside",
+    "b",
+    "bdi",
+    "bdo",
+    "blockquote",
+    "br",
+    "caption",
+    "cite",
+    "code",
+    "col",
+    "colgroup",
+    "dd",
+    "del",
+    "details",
+    "dfn",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "main",
+    "mark",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "rp",
+    "rt",
+    "ruby",
+    "s",
+    "samp",
+    "section",
+    "small",
+    "span",
+    "strong",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "time",
+    "tr",
+    "u",
+    "ul",
+    "var",
+    "wbr",
+    // SVG islands.
+    "svg",
+    "g",
+    "image",
+    "path",
+    "rect",
+    "circle",
+    "ellipse",
+    "line",
+    "polyline",
+    "polygon",
+    "text",
+    "tspan",
+    "title",
+    "desc",
+];
+
+/// Elements dropped together with their content.
+const HTML_DROPPED: &[&str] = &[
+    "head",
+    "script",
+    "style",
+    "iframe",
+    "object",
+    "embed",
+    "applet",
+    "form",
+    "input",
+    "button",
+    "select",
+    "textarea",
+    "noscript",
+    "template",
+    "link",
+    "meta",
+    "base",
+    "audio",
+    "video",
+    "canvas",
+    "foreignobject",
+];
+
+/// Attributes kept verbatim on allowed elements.
+const HTML_ATTRIBUTES: &[&str] = &[
+    "id",
+    "class",
+    "title",
+    "alt",
+    "dir",
+    "colspan",
+    "rowspan",
+    "scope",
+    "headers",
+    "span",
+    "start",
+    "reversed",
+    "width",
+    "height",
+    "datetime",
+    "abbr",
+    "viewBox",
+    "preserveAspectRatio",
+    "d",
+    "fill",
+    "stroke",
+    "stroke-width",
+    "opacity",
+    "fill-opacity",
+    "points",
+    "x",
+    "y",
+    "x1",
+    "y1",
+    "x2",
+    "y2",
+    "cx",
+    "cy",
+    "r",
+    "rx",
+    "ry",
+    "transform",
+    "font-size",
+    "text-anchor",
+];
+
+/// Elements written without an end tag.
+const HTML_VOID: &[&str] = &["br", "col", "hr", "img", "wbr"];
+
+/// Image resolved for embedding: media type and bytes.
+pub(crate) type ExportImage = (String, Vec<u8>);
+
+/// Writer of one self-contained HTML document.
+///
+/// Call `begin`, `stylesheet` for each sheet, `chapter` for each chapter,
+/// then `finish`.
+pub(crate) struct HtmlBundle<'w, W: Write> {
+    out: &'w mut W,
+    limits: HtmlExportLimits,
+    buf: String,
+    in_head: bool,
+    image_bytes: usize,
+}
+
+impl<'w, W: Write> HtmlBundle<'w, W> {
+    pub(crate) fn new(out: &'w mut W, limits: HtmlExportLimits) -> Self {
+        Self {
+            out,
+            limits,
+            buf: String::with_capacity(EXPORT_FLUSH_BYTES),
+            in_head: false,
+            image_bytes: 0,
+        }
+    }
+
+    /// Open the document and its inline `<style>` element.
+    pub(crate) fn begin(&mut self, title: &str, language: &str) -> Result<(), EpubError> {
+        self.buf.push_str("<!DOCTYPE html>\n<html");
+        if !language.is_empty() {
+            self.buf.push_str(" lang=\"");
+            escape_html(&mut self.buf, language, true);
+            self.buf.push('"');
+        }
+        self.buf
+            .push_str(">\n<head>\n<meta charset=\"utf-8\">\n<title>");
+        escape_html(&mut self.buf, title, false);
+        self.buf.push_str("</title>\n<style>\n");
+        self.in_head = true;
+        self.flush()
+    }
+
+    /// Inline a stylesheet, dropping `@import` rules and `url()` references.
+    pub(crate) fn stylesheet(&mut self, css: &str) -> Result<(), EpubError> {
+        if !self.in_head || css.len() > self.limits.max_css_bytes {
+            return Ok(());
+        }
+        sanitize_css(css.trim_start_matches('\u{feff}'), &mut self.buf);
+        self.buf.push('\n');
+        self.flush()
+    }
+
+    /// Write a chapter as `<section id="chapter-{index}">`.
+    ///
+    /// `image` loads an archive image given its `src` and a byte cap;
+    /// `link` maps an `href` to the value to keep, or `None` to drop it.
+    pub(crate) fn chapter<I, L>(
+        &mut self,
+        index: usize,
+        html: &[u8],
+        mut image: I,
+        link: L,
+    ) -> Result<(), EpubError>
+    where
+        I: FnMut(&str, usize) -> Option<ExportImage>,
+        L: Fn(&str) -> Option<String>,
+    {
+        self.end_head();
+        self.buf.push_str("<section id=\"chapter-");
+        self.buf.push_str(&index.to_string());
+        self.buf.push_str("\">\n");
+        let mut reader = Reader::from_reader(html);
+        reader.config_mut().trim_text(false);
+        let mut buf = Vec::with_capacity(0);
+        let mut open: Vec<Option<String>> = Vec::with_capacity(0);
+        let mut skip = 0usize;
+        loop {
+            match reader.read_event_into(&mut buf) {
+                Ok(Event::Start(e)) => {
+                    let name = e.local_name();
+                    let name = String::from_utf8_lossy(name.as_ref()).to_ascii_lowercase();
+                    if skip > 0 || HTML_DROPPED.contains(&name.as_str()) {
+                        skip += 1;
+                    } else if HTML_ELEMENTS.contains(&name.as_str()) {
+                        self.start_tag(&name, &e, &mut image, &link)?;
+                        open.push(Some(name));
+                    } else {
+                        open.push(None);
+                    }
+                }
+                Ok(Event::Empty(e)) if skip == 0 => {
+                    let name = e.local_name();
+                    let name = String::from_utf8_lossy(name.as_ref()).to_ascii_lowercase();
+                    if HTML_ELEMENTS.contains(&name.as_str()) {
+                        self.start_tag(&name, &e, &mut image, &link)?;
+                        if !HTML_VOID.contains(&name.as_str()) {
+                            self.end_tag(&name);
+                        }
+                    }
+                }
+                Ok(Event::End(_)) => {
+                    if skip > 0 {
+                        skip -= 1;
+                    } else if let Some(Some(name)) = open.pop() {
+                        if !HTML_VOID.contains(&name.as_str()) {
+                            self.end_tag(&name);
+                        }
+                    }
+                }
+                Ok(Event::Text(e)) if skip == 0 => {
+                    let text = e
+                        .decode()
+                        .map_err(|err| EpubError::Parse(format!("Decode error: {:?}", err)))?;
+                    escape_html(&mut self.buf, &text, false);
+                }
+                Ok(Event::CData(e)) if skip == 0 => {
+                    let text = reader
+                        .decoder()
+                        .decode(&e)
+                        .map_err(|err| EpubError::Parse(format!("Decode error: {:?}", err)))?;
+                    escape_html(&mut self.buf, &text, false);
+                }
+                Ok(Event::GeneralRef(e)) if skip == 0 => {
+                    let entity_name = e
+                        .decode()
+                        .map_err(|err| EpubError::Parse(format!("Decode error: {:?}", err)))?;
+                    let entity = format!("&{};", entity_name);
+                    match quick_xml::escape::unescape(&entity) {
+                        Ok(resolved) => escape_html(&mut self.buf, &resolved, false),
+                        // HTML named entities XML does not know, e.g. `&nbsp;`.
+                        Err(_) if entity_name.chars().all(|c| c.is_ascii_alphanumeric()) => {
+                            self.buf.push_str(&entity)
+                        }
+                        Err(_) => {}
+                    }
+                }
+                Ok(Event::Eof) => break,
+                Ok(_) => {}
+                Err(err) => return Err(EpubError::Parse(format!("XML error: {:?}", err))),
+            }
+            buf.clear();
+            if self.buf.len() >= EXPORT_FLUSH_BYTES {
+                self.flush()?;
+            }
+        }
+        while let Some(name) = open.pop() {
+            if let Some(name) = name.filter(|name| !HTML_VOID.contains(&name.as_str())) {
+                self.end_tag(&name);
+            }
+        }
+        self.buf.push_str("\n</section>\n");
+        self.flush()
+    }
+
+    /// Close the document.
+    pub(crate) fn finish(mut self) -> Result<(), EpubError> {
+        self.end_head();
+        self.buf.push_str("</body>\n</html>\n");
+        self.flush()
+    }
+
+    fn end_head(&mut self) {
+        if self.in_head {
+            self.buf.push_str("</style>\n</head>\n<body>\n");
+            self.in_head = false;
+        }
+    }
+
+    fn start_tag<I, L>(
+        &mut self,
+        name: &str,
+        e: &BytesStart<'_>,
+        image: &mut I,
+        link: &L,
+    ) -> Result<(), EpubError>
+    where
+        I: FnMut(&str, usize) -> Option<ExportImage>,
+        L: Fn(&str) -> Option<String>,
+    {
+        self.buf.push('<');
+        self.buf.push_str(name);
+        for attr in e.attributes().flatten() {
+            let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+            let Ok(value) = attr.unescape_value() else {
+                continue;
+            };
+            match (name, key.as_str()) {
+                ("a", "href") => {
+                    if let Some(href) = link(value.trim()) {
+                        self.attribute("href", &href);
+                    }
+                }
+                ("img", "src") | ("image", "href" | "xlink:href") => {
+                    let attr_name = if name == "img" { "src" } else { "href" };
+                    self.image(attr_name, value.trim(), image)?;
+                }
+                (_, "lang" | "xml:lang") => self.attribute("lang", &value),
+                (_, "style") => {
+                    let mut style = String::with_capacity(value.len());
+                    sanitize_css(&value, &mut style);
+                    self.attribute("style", &style);
+                }
+                (_, key) if HTML_ATTRIBUTES.contains(&key) => self.attribute(key, &value),
+                _ => {}
+            }
+        }
+        self.buf.push('>');
+        Ok(())
+    }
+
+    fn end_tag(&mut self, name: &str) {
+        self.buf.push_str("</");
+        self.buf.push_str(name);
+        self.buf.push('>');
+    }
+
+    fn attribute(&mut self, key: &str, value: &str) {
+        self.buf.push(' ');
+        self.buf.push_str(key);
+        self.buf.push_str("=\"");
+        escape_html(&mut self.buf, value, true);
+        self.buf.push('"');
+    }
+
+    /// Embed an image reference as a `data:` URI within the byte budgets;
+    /// unresolvable or oversized images lose the attribute.
+    fn image<I>(&mut self, key: &str, src: &str, image: &mut I) -> Result<(), EpubError>
+    where
+        I: FnMut(&str, usize) -> Option<ExportImage>,
+    {
+        let remaining = self
+            .limits
+            .max_total_image_bytes
+            .saturating_sub(self.image_bytes);
+        let cap = self.limits.max_image_bytes.min(remaining);
+        if src.starts_with("data:image/") {
+            if src.len() <= cap {
+                self.image_bytes += src.len();
+                self.attribute(key, src);
+            }
+            return Ok(());
+        }
+        let Some((media_type, bytes)) = image(src, cap).filter(|(_, bytes)| bytes.len() <= cap)
+        else {
+            return Ok(());
+        };
+        self.image_bytes += bytes.len();
+        self.buf.push(' ');
+        self.buf.push_str(key);
+        self.buf.push_str("=\"data:");
+        escape_html(&mut self.buf, &media_type, true);
+        self.buf.push_str(";base64,");
+        for chunk in bytes.chunks(3 * 1024) {
+            base64_into(&mut self.buf, chunk);
+            if self.buf.len() >= EXPORT_FLUSH_BYTES {
+                self.flush()?;
+            }
+        }
+        self.buf.push('"');
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), EpubError> {
+        self.out
+            .write_all(self.buf.as_bytes())
+            .map_err(|err| EpubError::Io(IoErrorInfo::from_std(&err, IoOperation::Write)))?;
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+/// Text of the `<style>` elements of chapter XHTML, skipping any larger
+/// than `max_bytes`.
+pub(crate) fn inline_styles(html: &[u8], max_bytes: usize) -> Vec<String> {
+    let mut styles = Vec::with_capacity(0);
+    let mut reader = Reader::from_reader(html);
+    let mut buf = Vec::with_capacity(0);
+    let mut current: Option<String> = None;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) if e.local_name().as_ref() == b"style" => {
+                current = Some(String::with_capacity(0));
+            }
+            Ok(Event::Text(e)) => {
+                if let (Some(css), Ok(text)) = (current.as_mut(), e.decode()) {
+                    css.push_str(&text);
+                }
+            }
+            Ok(Event::CData(e)) => {
+                if let (Some(css), Ok(text)) = (current.as_mut(), reader.decoder().decode(&e)) {
+                    css.push_str(&text);
+                }
+            }
+            Ok(Event::GeneralRef(e)) => {
+                if let (Some(css), Ok(name)) = (current.as_mut(), e.decode()) {
+                    if let Ok(resolved) = quick_xml::escape::unescape(&format!("&{};", name)) {
+                        css.push_str(&resolved);
+                    }
+                }
+            }
+            Ok(Event::End(e)) if e.local_name().as_ref() == b"style" => {
+                if let Some(css) = current.take().filter(|css| css.len() <= max_bytes) {
+                    styles.push(css);
+                }
+            }
+            Ok(Event::End(e)) if e.local_name().as_ref() == b"head" => break,
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    styles
+}
+
+/// Media type of image `bytes` from their signature.
+pub(crate) fn sniff_image_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        Some("image/webp")
+    } else if bytes
+        .get(..bytes.len().min(512))
+        .is_some_and(|head| head.windows(4).any(|w| w == b"<svg"))
+    {
+        Some("image/svg+xml")
+    } else {
+        None
+    }
+}
+
+/// Copy `css` into `out` without `@import` rules or `url()` references,
+/// and with `</` escaped so it cannot close the `<style>` element.
+fn sanitize_css(css: &str, out: &mut String) {
+    let lower = css.to_ascii_lowercase();
+    let mut pos = 0;
+    while let Some(ch) = css[pos..].chars().next() {
+        let rest = &lower[pos..];
+        if rest.starts_with("@import") {
+            pos = rest.find(';').map_or(css.len(), |end| pos + end + 1);
+        } else if rest.starts_with("url(") {
+            out.push_str("none");
+            pos = rest.find(')').map_or(css.len(), |end| pos + end + 1);
+        } else if rest.starts_with("</") {
+            out.push_str("<\\/");
+            pos += 2;
+        } else {
+            out.push(ch);
+            pos += ch.len_utf8();
+        }
+    }
+}
+
+/// Append `text` with HTML special characters escaped (and `"` in
+/// attribute values).
+fn escape_html(out: &mut String, text: &str, attribute: bool) {
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if attribute => out.push_str("&quot;"),
+            _ => out.push(ch),
+        }
+    }
+}
+
+/// Append the standard base64 encoding of `bytes`.
+fn base64_into(out: &mut String, bytes: &[u8]) {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(html: &str, format: ExportFormat) -> String {
+        let mut out = Vec::with_capacity(1024);
+        export_markup(html.as_bytes(), format, &mut out).expect("export should succeed");
+        String::from_utf8(out).expect("utf-8 output")
+    }
+
+    const CHAPTER: &str = r#"<html xmlns="http://www.w3.org/1999/xhtml">
+<head><title>Skipped</title><style>p { color: red }</style></head>
+<body>
+  <h2>Chapter <em>One</em></h2>
+  <p>Some   <strong>bold</strong> and <i>italic</i> text with a
+     <a href="ch2.xhtml#n1">link</a>, 2*3 &amp; <code>x_y</code>.</p>
+  <blockquote><p>Quoted line one<br/>line two</p><p>Second</p></blockquote>
+  <ul>
+    <li>First</li>
+    <li>Second
+      <ol start="3"><li>Nested</li><li>More</li></ol>
+    </li>
+  </ul>
+  <p><img src="images/fig.png" alt="A figure"/></p>
+  <pre>fn main() {
+    println!();
+}</pre>
+  <hr/>
+  <p>- not a list</p>
+</body>
+</html>"#;
+
+    #[test]
+    fn markdown_maps_block_roles_and_inline_emphasis() {
+        let markdown = export(CHAPTER, ExportFormat::Markdown);
+        assert_eq!(
+            markdown,
+            "## Chapter *One*\n\
+             \n\
+             Some **bold** and *italic* text with a [link](ch2.xhtml#n1), 2\\*3 & `x_y`.\n\
+             \n\
+             > Quoted line one\\\n\
+             > line two\n\
+             >\n\
+             > Second\n\
+             \n\
+             - First\n\
+             - Second\n\
+             \n  3. Nested\n  4. More\n\
+             \n\
+             ![A figure](images/fig.png)\n\
+             \n\
+             ```\nfn main() {\n    println!();\n}\n```\n\
+             \n\
+             ---\n\
+             \n\
+             \\- not a list\n"
+        );
+    }
+
+    #[test]
+    fn text_drops_markup_but_keeps_structure() {
+        let text = export(CHAPTER, ExportFormat::Text);
+        assert_eq!(
+            text,
+            "Chapter One\n\
+             \n\
+             Some bold and italic text with a link, 2*3 & x_y.\n\
+             \n  Quoted line one\n  line two\n\
+             \n  Second\n\
+             \n\
+             - First\n\
+             - Second\n\
+             \n  3. Nested\n  4. More\n\
+             \n\
+             A figure\n\
+             \n\
+             fn main() {\n    println!();\n}\n\
+             \n\
+             - not a list\n"
+        );
+    }
+
+    fn bundle(html: &str, limits: HtmlExportLimits) -> String {
+        let mut out = Vec::with_capacity(1024);
+        let mut bundle = HtmlBundle::new(&mut out, limits);
+        bundle.begin("A <Book>", "en").expect("begin");
+        bundle
+            .stylesheet("@import \"x.css\";\np { background: url(bg.png) } </style>")
+            .expect("stylesheet");
+        let image = |src: &str, cap: usize| {
+            let bytes = b"abcd".to_vec();
+            (src == "fig.png" && bytes.len() <= cap).then(|| ("image/png".to_string(), bytes))
+        };
+        let link = |href: &str| href.starts_with('#').then(|| href.to_string());
+        bundle
+            .chapter(3, html.as_bytes(), image, link)
+            .expect("chapter");
+        bundle.finish().expect("finish");
+        String::from_utf8(out).expect("utf-8 output")
+    }
+
+    #[test]
+    fn html_bundle_sanitizes_markup_and_inlines_resources() {
+        let html = r##"<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head><title>T</title><script>alert(1)</script></head>
+<body onload="evil()">
+  <p class="x" onclick="evil()" epub:type="z">A&amp;B &lt;tag&gt;&nbsp;<a href="#n1">note</a>
+     <a href="ch2.xhtml">next</a></p>
+  <form><input/>gone</form>
+  <custom>kept text</custom>
+  <img src="fig.png" alt="Fig &quot;1&quot;"/><img src="missing.png" alt="M"/>
+  <p style="color: red; background: url(x.png)"/>
+</body></html>"##;
+        let out = bundle(html, HtmlExportLimits::default());
+        assert_eq!(
+            out,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>A &lt;Book&gt;</title>\n<style>\n\n\
+             p { background: none } <\\/style>\n</style>\n</head>\n<body>\n\
+             <section id=\"chapter-3\">\n\n\n\n  \
+             <p class=\"x\">A&amp;B &lt;tag&gt;&nbsp;<a href=\"#n1\">note</a>\n     \
+             <a>next</a></p>\n  \n  kept text\n  \
+             <img src=\"data:image/png;base64,YWJjZA==\" alt=\"Fig &quot;1&quot;\">\
+             <img alt=\"M\">\n  <p style=\"color: red; background: none\"></p>\n\n\
+             </section>\n</body>\n</html>\n"
+        );
+
+        let tight = HtmlExportLimits {
+            max_total_image_bytes: 3,
+            ..HtmlExportLimits::default()
+        };
+        assert!(bundle(html, tight).contains("<img alt=\"Fig &quot;1&quot;\">"));
+    }
+
+    #[test]
+    fn base64_pads_partial_groups() {
+        let encode = |bytes: &[u8]| {
+            let mut out = String::with_capacity(8);
+            base64_into(&mut out, bytes);
+            out
+        };
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"f"), "Zg==");
+        assert_eq!(encode(b"fo"), "Zm8=");
+        assert_eq!(encode(b"foo"), "Zm9v");
+        assert_eq!(encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(sniff_image_type(b"GIF89a"), Some("image/gif"));
+        assert_eq!(
+            inline_styles(b"<html><head><style>p{}</style></head></html>", 16),
+            vec!["p{}".to_string()]
+        );
+    }
+
+    #[test]
+    fn output_is_flushed_in_bounded_chunks() {
+        struct Chunks(Vec<usize>);
+        impl Write for Chunks {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.push(buf.len());
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let body = "<p>word word word word</p>".repeat(1_000);
+        let html = format!("<html><body>{body}</body></html>");
+        let mut out = Chunks(Vec::with_capacity(8));
+        export_markup(html.as_bytes(), ExportFormat::Text, &mut out).expect("export");
+        assert!(out.0.len() > 1);
+        assert!(out.0.iter().all(|&len| len < EXPORT_FLUSH_BYTES + 64));
+    }
+}
@@ -0,0 +1,502 @@
+//! Minimal byte I/O traits for the ZIP, book, and render-prep layers.
+//!
+//! These mirror the subset of `std::io` the archive reader needs, so the
+//! open → chapter events → styled runs path builds without the `std`
+//! feature. With `std`, every `std::io::{Read, Seek, Write}` type implements
+//! them through blanket impls (`File`, `Cursor`, `Vec<u8>`, ...). Without
+//! it, implement [`Read`] + [`Seek`] for the storage driver (SD card, flash
+//! partition) or wrap archive bytes in [`MemoryReader`].
+
+extern crate alloc;
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Failure reported by an I/O source or sink.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum IoError {
+    /// The source ended before the requested bytes were read.
+    UnexpectedEof,
+    /// The sink accepted no bytes.
+    WriteZero,
+    /// A seek targeted a position before the start of the stream.
+    InvalidSeek,
+    /// The device or OS rejected an argument, e.g. `EINVAL`.
+    InvalidInput,
+    /// The file or device is missing, e.g. a removed SD card.
+    NotFound,
+    /// The device refused access.
+    PermissionDenied,
+    /// The operation was interrupted and may be retried.
+    Interrupted,
+    /// The device did not answer in time.
+    TimedOut,
+    /// The device returned corrupt data.
+    InvalidData,
+    /// The device has no space left.
+    StorageFull,
+    /// The device is no longer attached, e.g. a card pulled mid-read.
+    NotConnected,
+    /// The other end of the stream went away.
+    BrokenPipe,
+    /// Raw OS or driver error code: `errno` on Unix, a Win32 code on
+    /// Windows, driver-defined without `std`.
+    Os(i32),
+    /// Any other device or OS failure.
+    Other,
+}
+
+impl IoError {
+    /// Error kind, with `Os` codes resolved to a named kind where `std`
+    /// knows one (`Other` otherwise).
+    pub fn kind(self) -> IoError {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Os(code) => Self::from_std_kind(std::io::Error::from_raw_os_error(code).kind()),
+            #[cfg(not(feature = "std"))]
+            Self::Os(_) => Self::Other,
+            other => other,
+        }
+    }
+
+    /// Raw OS or driver error code, if one was reported.
+    pub fn raw_os_error(self) -> Option<i32> {
+        match self {
+            Self::Os(code) => Some(code),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn from_std(err: &std::io::Error) -> Self {
+        match err.raw_os_error() {
+            Some(code) => Self::Os(code),
+            None => Self::from_std_kind(err.kind()),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn from_std_kind(kind: std::io::ErrorKind) -> Self {
+        match kind {
+            std::io::ErrorKind::UnexpectedEof => Self::UnexpectedEof,
+            std::io::ErrorKind::WriteZero => Self::WriteZero,
+            std::io::ErrorKind::InvalidInput => Self::InvalidInput,
+            std::io::ErrorKind::NotFound => Self::NotFound,
+            std::io::ErrorKind::PermissionDenied => Self::PermissionDenied,
+            std::io::ErrorKind::Interrupted => Self::Interrupted,
+            std::io::ErrorKind::TimedOut => Self::TimedOut,
+            std::io::ErrorKind::InvalidData => Self::InvalidData,
+            std::io::ErrorKind::StorageFull => Self::StorageFull,
+            std::io::ErrorKind::NotConnected => Self::NotConnected,
+            std::io::ErrorKind::BrokenPipe => Self::BrokenPipe,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEof => write!(f, "unexpected end of stream"),
+            Self::WriteZero => write!(f, "write accepted zero bytes"),
+            Self::InvalidSeek => write!(f, "seek before start of stream"),
+            Self::InvalidInput => write!(f, "invalid input"),
+            Self::NotFound => write!(f, "not found"),
+            Self::PermissionDenied => write!(f, "permission denied"),
+            Self::Interrupted => write!(f, "interrupted"),
+            Self::TimedOut => write!(f, "timed out"),
+            Self::InvalidData => write!(f, "invalid data"),
+            Self::StorageFull => write!(f, "no storage space"),
+            Self::NotConnected => write!(f, "device not connected"),
+            Self::BrokenPipe => write!(f, "broken pipe"),
+            #[cfg(feature = "std")]
+            Self::Os(code) => write!(f, "{}", std::io::Error::from_raw_os_error(*code)),
+            #[cfg(not(feature = "std"))]
+            Self::Os(code) => write!(f, "os error {}", code),
+            Self::Other => write!(f, "I/O failure"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for IoError {}
+
+/// Keeps the OS error code when there is one, so `kind` and `raw_os_error`
+/// both survive.
+#[cfg(feature = "std")]
+impl From<std::io::Error> for IoError {
+    fn from(err: std::io::Error) -> Self {
+        Self::from_std(&err)
+    }
+}
+
+/// Rebuilds a `std` error, keeping the OS error code when there is one.
+#[cfg(feature = "std")]
+impl From<IoError> for std::io::Error {
+    fn from(err: IoError) -> Self {
+        let kind = match err {
+            IoError::Os(code) => return std::io::Error::from_raw_os_error(code),
+            IoError::UnexpectedEof => std::io::ErrorKind::UnexpectedEof,
+            IoError::WriteZero => std::io::ErrorKind::WriteZero,
+            IoError::InvalidSeek | IoError::InvalidInput => std::io::ErrorKind::InvalidInput,
+            IoError::NotFound => std::io::ErrorKind::NotFound,
+            IoError::PermissionDenied => std::io::ErrorKind::PermissionDenied,
+            IoError::Interrupted => std::io::ErrorKind::Interrupted,
+            IoError::TimedOut => std::io::ErrorKind::TimedOut,
+            IoError::InvalidData => std::io::ErrorKind::InvalidData,
+            IoError::StorageFull => std::io::ErrorKind::StorageFull,
+            IoError::NotConnected => std::io::ErrorKind::NotConnected,
+            IoError::BrokenPipe => std::io::ErrorKind::BrokenPipe,
+            IoError::Other => std::io::ErrorKind::Other,
+        };
+        kind.into()
+    }
+}
+
+/// Operation an [`IoErrorInfo`] failed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum IoOperation {
+    /// Opening a file.
+    Open,
+    /// Creating a file.
+    Create,
+    /// Reading bytes.
+    Read,
+    /// Writing bytes.
+    Write,
+    /// Moving the cursor.
+    Seek,
+    /// Flushing a sink.
+    Flush,
+}
+
+impl fmt::Display for IoOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Open => write!(f, "open"),
+            Self::Create => write!(f, "create"),
+            Self::Read => write!(f, "read"),
+            Self::Write => write!(f, "write"),
+            Self::Seek => write!(f, "seek"),
+            Self::Flush => write!(f, "flush"),
+        }
+    }
+}
+
+/// Structured I/O failure carried by `ZipError::IoError` and
+/// `EpubError::Io`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct IoErrorInfo {
+    /// What went wrong; never `IoError::Os`, see `raw_os_error`.
+    pub kind: IoError,
+    /// OS or driver error code, when the source reported one.
+    pub raw_os_error: Option<i32>,
+    /// Operation that failed.
+    pub operation: IoOperation,
+    /// Stream offset the operation started at, when known.
+    pub offset: Option<u64>,
+}
+
+impl IoErrorInfo {
+    /// Describe `err` raised by `operation` at `offset`.
+    pub fn new(err: IoError, operation: IoOperation, offset: Option<u64>) -> Self {
+        Self {
+            kind: err.kind(),
+            raw_os_error: err.raw_os_error(),
+            operation,
+            offset,
+        }
+    }
+
+    /// The error as a source would report it, with the OS code folded back
+    /// into `IoError::Os`.
+    pub fn error(&self) -> IoError {
+        self.raw_os_error.map_or(self.kind, IoError::Os)
+    }
+
+    /// Describe a `std` error raised by `operation`.
+    #[cfg(feature = "std")]
+    pub fn from_std(err: &std::io::Error, operation: IoOperation) -> Self {
+        Self::new(IoError::from_std(err), operation, None)
+    }
+}
+
+impl fmt::Display for IoErrorInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed", self.operation)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {}", offset)?;
+        }
+        write!(f, ": {}", self.kind)?;
+        if let Some(code) = self.raw_os_error {
+            write!(f, " (os error {})", code)?;
+        }
+        Ok(())
+    }
+}
+
+/// Seek origin, as in `std::io::SeekFrom`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeekFrom {
+    /// Absolute offset from the start.
+    Start(u64),
+    /// Signed offset from the end.
+    End(i64),
+    /// Signed offset from the current position.
+    Current(i64),
+}
+
+/// Byte source.
+pub trait Read {
+    /// Read up to `buf.len()` bytes, returning how many were read (`0` at end).
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError>;
+
+    /// Fill `buf` completely or fail with [`IoError::UnexpectedEof`].
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<(), IoError> {
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => return Err(IoError::UnexpectedEof),
+                n => buf = &mut buf[n..],
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Random-access cursor over a byte source.
+pub trait Seek {
+    /// Move the cursor, returning the new absolute position.
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, IoError>;
+
+    /// Current absolute position.
+    fn stream_position(&mut self) -> Result<u64, IoError> {
+        self.seek(SeekFrom::Current(0))
+    }
+}
+
+/// Byte sink.
+pub trait Write {
+    /// Write up to `buf.len()` bytes, returning how many were accepted.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IoError>;
+
+    /// Write all of `buf` or fail with [`IoError::WriteZero`].
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<(), IoError> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(IoError::WriteZero),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Flush buffered bytes to the device.
+    fn flush(&mut self) -> Result<(), IoError> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: std::io::Read + ?Sized> Read for T {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        std::io::Read::read(self, buf).map_err(IoError::from)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), IoError> {
+        std::io::Read::read_exact(self, buf).map_err(IoError::from)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: std::io::Seek + ?Sized> Seek for T {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, IoError> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => std::io::SeekFrom::Start(offset),
+            SeekFrom::End(offset) => std::io::SeekFrom::End(offset),
+            SeekFrom::Current(offset) => std::io::SeekFrom::Current(offset),
+        };
+        std::io::Seek::seek(self, pos).map_err(IoError::from)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: std::io::Write + ?Sized> Write for T {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        std::io::Write::write(self, buf).map_err(IoError::from)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), IoError> {
+        std::io::Write::write_all(self, buf).map_err(IoError::from)
+    }
+
+    fn flush(&mut self) -> Result<(), IoError> {
+        std::io::Write::flush(self).map_err(IoError::from)
+    }
+}
+
+// Without `std` the blanket impls above are absent; cover the same common
+// types so `&mut reader` and `Vec<u8>` sinks behave identically.
+
+#[cfg(not(feature = "std"))]
+impl<R: Read + ?Sized> Read for &mut R {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        (**self).read(buf)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<S: Seek + ?Sized> Seek for &mut S {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, IoError> {
+        (**self).seek(pos)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        (**self).write(buf)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Read for &[u8] {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        let n = buf.len().min(self.len());
+        let (head, tail) = self.split_at(n);
+        buf[..n].copy_from_slice(head);
+        *self = tail;
+        Ok(n)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Write for Vec<u8> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+}
+
+/// Seekable reader over in-memory archive bytes.
+///
+/// Available with or without `std`; with `std`, `std::io::Cursor` works too.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryReader<T> {
+    inner: T,
+    pos: u64,
+}
+
+impl<T: AsRef<[u8]>> MemoryReader<T> {
+    /// Wrap `inner`, positioned at its start.
+    pub fn new(inner: T) -> Self {
+        Self { inner, pos: 0 }
+    }
+
+    /// Current absolute position.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Unwrap the underlying bytes.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: AsRef<[u8]>> Read for MemoryReader<T> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        let bytes = self.inner.as_ref();
+        let start = usize::try_from(self.pos)
+            .unwrap_or(usize::MAX)
+            .min(bytes.len());
+        let n = buf.len().min(bytes.len() - start);
+        buf[..n].copy_from_slice(&bytes[start..start + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<T: AsRef<[u8]>> Seek for MemoryReader<T> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, IoError> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => {
+                self.pos = offset;
+                return Ok(offset);
+            }
+            SeekFrom::End(offset) => (self.inner.as_ref().len() as u64, offset),
+            SeekFrom::Current(offset) => (self.pos, offset),
+        };
+        self.pos = base
+            .checked_add_signed(offset)
+            .ok_or(IoError::InvalidSeek)?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_reader_reads_and_seeks_like_a_cursor() {
+        let mut reader = MemoryReader::new(b"mimetype".as_slice());
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf).expect("read should succeed");
+        assert_eq!(&buf, b"mime");
+        assert_eq!(reader.seek(SeekFrom::End(-2)), Ok(6));
+        assert_eq!(reader.read(&mut buf), Ok(2));
+        assert_eq!(&buf[..2], b"pe");
+        assert_eq!(reader.read(&mut buf), Ok(0));
+        assert_eq!(reader.read_exact(&mut buf), Err(IoError::UnexpectedEof));
+        assert_eq!(
+            reader.seek(SeekFrom::Current(-9)),
+            Err(IoError::InvalidSeek)
+        );
+        assert_eq!(reader.seek(SeekFrom::Start(20)), Ok(20));
+        assert_eq!(reader.read(&mut buf), Ok(0));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn std_kinds_map_to_matching_io_errors() {
+        use std::io::ErrorKind;
+        let cases = [
+            (ErrorKind::UnexpectedEof, IoError::UnexpectedEof),
+            (ErrorKind::WriteZero, IoError::WriteZero),
+            (ErrorKind::InvalidInput, IoError::InvalidInput),
+            (ErrorKind::NotFound, IoError::NotFound),
+            (ErrorKind::PermissionDenied, IoError::PermissionDenied),
+            (ErrorKind::Interrupted, IoError::Interrupted),
+            (ErrorKind::TimedOut, IoError::TimedOut),
+            (ErrorKind::InvalidData, IoError::InvalidData),
+            (ErrorKind::StorageFull, IoError::StorageFull),
+            (ErrorKind::NotConnected, IoError::NotConnected),
+            (ErrorKind::BrokenPipe, IoError::BrokenPipe),
+            (ErrorKind::AddrInUse, IoError::Other),
+        ];
+        for (kind, expected) in cases {
+            assert_eq!(IoError::from_std_kind(kind), expected, "{kind:?}");
+            if expected != IoError::Other {
+                assert_eq!(std::io::Error::from(expected).kind(), kind);
+            }
+        }
+        assert_eq!(
+            std::io::Error::from(IoError::InvalidSeek).kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+
+    #[cfg(all(feature = "std", unix))]
+    #[test]
+    fn einval_is_invalid_input_not_a_seek_underflow() {
+        let err = IoError::from(std::io::Error::from_raw_os_error(22));
+        assert_eq!(err, IoError::Os(22));
+        assert_eq!(err.kind(), IoError::InvalidInput);
+    }
+}
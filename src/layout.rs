@@ -764,7 +764,7 @@ mod tests {
 
     #[test]
     fn test_empty_input() {
-        let tokens: Vec<Token> = vec![];
+        let tokens: Vec<Token> = Vec::with_capacity(0);
         let mut engine = LayoutEngine::new(460.0, 650.0, 20.0);
         let pages = engine.layout_tokens(&tokens);
 
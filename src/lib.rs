@@ -5,8 +5,21 @@
 //!
 //! # Features
 //!
-//! - `std` (default) -- enables streaming ZIP reader and file I/O
+//! - `std` (default) -- file I/O, validation, export, and `std::io` interop;
+//!   implies `book`
+//! - `book` -- streaming ZIP reader, `EpubBook`, and render prep over the
+//!   [`io`] traits, with no `std` types in the API; the XML parser still
+//!   links `std`, so this is not bare-metal support
 //! - `layout` -- text layout engine for pagination
+//! - `serde` -- `Serialize`/`Deserialize` for metadata, navigation, chapter
+//!   descriptors, reading positions, and limit/config types
+//! - `tracing` -- `tracing` spans around open, ZIP entry reads, tokenize, and
+//!   style phases (with chapter index and href fields); `log` events are
+//!   mirrored as `tracing` events. Implies `book`
+//! - `arbitrary` -- `arbitrary::Arbitrary` for [`Color`], [`BlockRole`], and
+//!   [`ZipLimits`], for fuzzers and property tests; implies `std`
+//! - `defmt` -- `defmt::Format` for [`EpubError`], [`ZipError`], and
+//!   [`RenderPrepError`], for logging over RTT without `core::fmt`
 //!
 //! # Allocation Behavior
 //!
@@ -14,7 +27,8 @@
 //! expensive entrypoints. The primary APIs require caller-provided buffers
 //! or scratch space (`*_with_scratch`, `*_into`). Convenience APIs that
 //! allocate (`read_resource() -> Vec<u8>`) are available only with the `std`
-//! feature and are clearly marked as non-embedded-fast-path.
+//! feature and are clearly marked as non-embedded-fast-path. Install a
+//! [`memory::MemoryObserver`] to attribute buffers to subsystems at runtime.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_docs)]
@@ -44,8 +58,14 @@
 )]
 extern crate alloc;
 
+#[cfg(feature = "book")]
+#[macro_use]
+mod trace;
+
 pub mod css;
 pub mod error;
+pub mod io;
+pub mod memory;
 pub mod metadata;
 pub mod navigation;
 pub mod spine;
@@ -55,45 +75,54 @@ pub mod tokenizer;
 #[cfg(feature = "layout")]
 pub mod layout;
 
-#[cfg(feature = "std")]
+#[cfg(feature = "book")]
 pub mod book;
 
+#[cfg(feature = "book")]
+mod conformance;
+
 #[cfg(feature = "std")]
 pub mod validate;
 
 #[cfg(feature = "std")]
+pub mod export;
+
+#[cfg(feature = "book")]
 pub mod render_prep;
 
 #[cfg(feature = "async")]
 pub mod async_api;
 
-#[cfg(feature = "std")]
+#[cfg(feature = "book")]
 pub mod zip;
 
 // Re-export key types for convenience
 #[cfg(feature = "async")]
 pub use async_api::{open_epub_file_async, open_epub_file_async_with_options};
 #[cfg(feature = "std")]
+pub use book::{parse_epub_file, parse_epub_file_with_options};
+#[cfg(feature = "book")]
 pub use book::{
-    parse_epub_file, parse_epub_file_with_options, parse_epub_reader,
-    parse_epub_reader_with_options, ChapterRef, ChapterStreamResult, EpubBook, EpubBookBuilder,
-    EpubBookOptions, EpubSummary, Locator, PaginationSession, ReadingPosition, ReadingSession,
-    ResolvedLocation, ValidationMode,
+    parse_epub_reader, parse_epub_reader_with_options, ChapterRef, ChapterStreamResult, EpubBook,
+    EpubBookBuilder, EpubBookOptions, EpubSummary, Locator, PaginationSession, ReadingPosition,
+    ReadingSession, ResolvedLocation, ValidationMode,
 };
-pub use css::{CssStyle, Stylesheet};
+pub use css::{Color, CssStyle, Stylesheet};
 pub use error::{
-    EpubError, ErrorLimitContext, ErrorPhase, LimitKind, PhaseError, PhaseErrorContext, ZipError,
-    ZipErrorKind,
+    EpubError, ErrorCodeInfo, ErrorLimitContext, ErrorPhase, ErrorScope, LimitKind, PhaseError,
+    PhaseErrorContext, ZipError, ZipErrorKind, ERROR_CODES,
 };
+#[cfg(feature = "std")]
+pub use export::{ExportFormat, HtmlExportLimits};
 pub use metadata::EpubMetadata;
 pub use navigation::Navigation;
-#[cfg(feature = "std")]
+#[cfg(feature = "book")]
 pub use render_prep::{
     BlockRole, ChapterStylesheets, ComputedTextStyle, EmbeddedFontFace, EmbeddedFontStyle,
     FontFallbackPolicy, FontLimits, FontPolicy, FontResolutionTrace, FontResolver, LayoutHints,
     MemoryBudget, PreparedChapter, RenderPrep, RenderPrepError, RenderPrepOptions, RenderPrepTrace,
     ResolvedFontFace, StyleConfig, StyleLimits, StyledChapter, StyledEvent, StyledEventOrRun,
-    StyledRun, Styler, StylesheetSource,
+    StyledImage, StyledRun, Styler, StylesheetSource,
 };
 pub use spine::Spine;
 pub use streaming::{
@@ -110,5 +139,5 @@ pub use validate::{
     validate_epub_reader_with_options, ValidationDiagnostic, ValidationOptions, ValidationReport,
     ValidationSeverity,
 };
-#[cfg(feature = "std")]
+#[cfg(feature = "book")]
 pub use zip::ZipLimits;
@@ -0,0 +1,276 @@
+//! Per-subsystem memory accounting.
+//!
+//! Install a [`MemoryObserver`] with [`set_memory_observer`] and the crate
+//! reports the buffers it holds, tagged by [`MemorySubsystem`]: ZIP inflate
+//! scratch, parsed style tables, and (from the render crate) page buffers and
+//! decoded images. Every `Allocated` event is matched by a `Released` event
+//! of the same size, so a running sum per subsystem is the bytes currently
+//! held. Byte counts are what the buffers hold, not allocator overhead.
+//!
+//! Without an observer each report is one atomic load.
+//!
+//! ```
+//! use mu_epub::memory::{set_memory_observer, MemorySubsystem, MemoryTally};
+//!
+//! static TALLY: MemoryTally = MemoryTally::new();
+//! set_memory_observer(&TALLY);
+//! // ... open and render books ...
+//! let peak = TALLY.peak(MemorySubsystem::ZipScratch);
+//! # let _ = peak;
+//! ```
+
+use alloc::boxed::Box;
+use core::ptr;
+#[cfg(target_has_atomic = "ptr")]
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+/// Part of the crate a memory event is charged to.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MemorySubsystem {
+    /// ZIP read and inflate scratch buffers.
+    ZipScratch,
+    /// Parsed stylesheet rules held for the chapter being styled.
+    StyleTables,
+    /// Laid-out pages buffered by a layout session.
+    PageBuffers,
+    /// Decoder working memory and the decoded-image cache.
+    ImageDecode,
+}
+
+impl MemorySubsystem {
+    /// Every subsystem, in declaration order.
+    pub const ALL: [MemorySubsystem; 4] = [
+        MemorySubsystem::ZipScratch,
+        MemorySubsystem::StyleTables,
+        MemorySubsystem::PageBuffers,
+        MemorySubsystem::ImageDecode,
+    ];
+
+    /// Short stable name, e.g. for log lines.
+    pub fn name(self) -> &'static str {
+        match self {
+            MemorySubsystem::ZipScratch => "zip_scratch",
+            MemorySubsystem::StyleTables => "style_tables",
+            MemorySubsystem::PageBuffers => "page_buffers",
+            MemorySubsystem::ImageDecode => "image_decode",
+        }
+    }
+
+    #[cfg(target_has_atomic = "ptr")]
+    fn index(self) -> usize {
+        match self {
+            MemorySubsystem::ZipScratch => 0,
+            MemorySubsystem::StyleTables => 1,
+            MemorySubsystem::PageBuffers => 2,
+            MemorySubsystem::ImageDecode => 3,
+        }
+    }
+}
+
+/// One change in the bytes a subsystem holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryEvent {
+    /// `bytes` more are held by `subsystem`.
+    Allocated {
+        /// Subsystem charged.
+        subsystem: MemorySubsystem,
+        /// Bytes added.
+        bytes: usize,
+    },
+    /// `bytes` previously reported as allocated were given back.
+    Released {
+        /// Subsystem credited.
+        subsystem: MemorySubsystem,
+        /// Bytes removed.
+        bytes: usize,
+    },
+}
+
+impl MemoryEvent {
+    /// Subsystem the event is charged to.
+    pub fn subsystem(&self) -> MemorySubsystem {
+        match *self {
+            MemoryEvent::Allocated { subsystem, .. } | MemoryEvent::Released { subsystem, .. } => {
+                subsystem
+            }
+        }
+    }
+
+    /// Bytes added or removed.
+    pub fn bytes(&self) -> usize {
+        match *self {
+            MemoryEvent::Allocated { bytes, .. } | MemoryEvent::Released { bytes, .. } => bytes,
+        }
+    }
+}
+
+/// Receiver for memory events, installed with [`set_memory_observer`].
+///
+/// Called synchronously on the thread doing the work, possibly from several
+/// threads at once; keep it short and never allocate through the crate from
+/// inside it.
+pub trait MemoryObserver: Sync {
+    /// Handle one event.
+    fn on_memory_event(&self, event: MemoryEvent);
+}
+
+static OBSERVER: AtomicPtr<&'static dyn MemoryObserver> = AtomicPtr::new(ptr::null_mut());
+
+/// Install `observer` for the whole process, replacing any earlier one.
+///
+/// Meant to be called once at startup: each call leaks one pointer-sized
+/// box. Events already in flight may still reach the previous observer.
+pub fn set_memory_observer(observer: &'static dyn MemoryObserver) {
+    let slot: &'static mut &'static dyn MemoryObserver = Box::leak(Box::new(observer));
+    OBSERVER.store(slot, Ordering::Release);
+}
+
+/// Stop reporting memory events.
+pub fn clear_memory_observer() {
+    OBSERVER.store(ptr::null_mut(), Ordering::Release);
+}
+
+fn notify(event: MemoryEvent) {
+    let slot = OBSERVER.load(Ordering::Acquire);
+    if slot.is_null() {
+        return;
+    }
+    // SAFETY: non-null values come from `Box::leak` in `set_memory_observer`
+    // and are never freed.
+    let observer: &'static dyn MemoryObserver = unsafe { *slot };
+    observer.on_memory_event(event);
+}
+
+/// Bytes a subsystem holds in one buffer or pool, reported as they change.
+///
+/// `set` reports the difference from the previous size; dropping the gauge
+/// releases whatever it still holds. Clones report their own allocation.
+#[derive(Debug, PartialEq, Eq)]
+pub struct MemoryGauge {
+    subsystem: MemorySubsystem,
+    bytes: usize,
+}
+
+impl MemoryGauge {
+    /// Gauge holding nothing yet.
+    pub const fn new(subsystem: MemorySubsystem) -> Self {
+        Self {
+            subsystem,
+            bytes: 0,
+        }
+    }
+
+    /// Gauge charged `bytes` up front, e.g. for a scoped scratch buffer.
+    pub fn with_bytes(subsystem: MemorySubsystem, bytes: usize) -> Self {
+        let mut gauge = Self::new(subsystem);
+        gauge.set(bytes);
+        gauge
+    }
+
+    /// Subsystem this gauge charges.
+    pub fn subsystem(&self) -> MemorySubsystem {
+        self.subsystem
+    }
+
+    /// Bytes currently charged.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Charge `bytes` in total, reporting the change.
+    pub fn set(&mut self, bytes: usize) {
+        let subsystem = self.subsystem;
+        if bytes > self.bytes {
+            notify(MemoryEvent::Allocated {
+                subsystem,
+                bytes: bytes - self.bytes,
+            });
+        } else if bytes < self.bytes {
+            notify(MemoryEvent::Released {
+                subsystem,
+                bytes: self.bytes - bytes,
+            });
+        }
+        self.bytes = bytes;
+    }
+}
+
+impl Clone for MemoryGauge {
+    fn clone(&self) -> Self {
+        Self::with_bytes(self.subsystem, self.bytes)
+    }
+}
+
+impl Drop for MemoryGauge {
+    fn drop(&mut self) {
+        self.set(0);
+    }
+}
+
+/// Lock-free observer keeping current and peak bytes per subsystem.
+#[cfg(target_has_atomic = "ptr")]
+#[derive(Debug, Default)]
+pub struct MemoryTally {
+    current: [AtomicUsize; 4],
+    peak: [AtomicUsize; 4],
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl MemoryTally {
+    /// Tally with every subsystem at zero, usable in a `static`.
+    pub const fn new() -> Self {
+        Self {
+            current: [const { AtomicUsize::new(0) }; 4],
+            peak: [const { AtomicUsize::new(0) }; 4],
+        }
+    }
+
+    /// Bytes `subsystem` holds now.
+    pub fn current(&self, subsystem: MemorySubsystem) -> usize {
+        self.current[subsystem.index()].load(Ordering::Relaxed)
+    }
+
+    /// Most bytes `subsystem` held at once since creation or `reset_peaks`.
+    pub fn peak(&self, subsystem: MemorySubsystem) -> usize {
+        self.peak[subsystem.index()].load(Ordering::Relaxed)
+    }
+
+    /// Bytes held across all subsystems now.
+    pub fn total(&self) -> usize {
+        MemorySubsystem::ALL
+            .iter()
+            .map(|&subsystem| self.current(subsystem))
+            .fold(0, usize::saturating_add)
+    }
+
+    /// Restart peaks from the current values.
+    pub fn reset_peaks(&self) {
+        for (current, peak) in self.current.iter().zip(&self.peak) {
+            peak.store(current.load(Ordering::Relaxed), Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl MemoryObserver for MemoryTally {
+    fn on_memory_event(&self, event: MemoryEvent) {
+        let index = event.subsystem().index();
+        match event {
+            MemoryEvent::Allocated { bytes, .. } => {
+                let now = self.current[index]
+                    .fetch_add(bytes, Ordering::Relaxed)
+                    .saturating_add(bytes);
+                self.peak[index].fetch_max(now, Ordering::Relaxed);
+            }
+            MemoryEvent::Released { bytes, .. } => {
+                let _ = self.current[index].fetch_update(
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                    |current| Some(current.saturating_sub(bytes)),
+                );
+            }
+        }
+    }
+}
@@ -12,6 +12,8 @@ use quick_xml::events::Event;
 use quick_xml::reader::Reader;
 
 use crate::error::EpubError;
+#[cfg(feature = "std")]
+use crate::io::{IoErrorInfo, IoOperation};
 
 /// Maximum number of manifest items (fixed-size constraint)
 const MAX_MANIFEST_ITEMS: usize = 1024;
@@ -24,6 +26,7 @@ const MAX_GUIDE_REFS: usize = 64;
 
 /// A single item in the EPUB manifest (id -> href mapping)
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ManifestItem {
     /// Resource identifier
     pub id: String,
@@ -37,6 +40,7 @@ pub struct ManifestItem {
 
 /// A reference from the EPUB 2.0 `<guide>` element
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GuideRef {
     /// Reference type (e.g. "cover", "toc", "text")
     pub guide_type: String,
@@ -48,6 +52,7 @@ pub struct GuideRef {
 
 /// EPUB metadata extracted from content.opf
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EpubMetadata {
     /// Book title
     pub title: String,
@@ -402,10 +407,8 @@ pub fn parse_opf(content: &[u8]) -> Result<EpubMetadata, EpubError> {
                         "description" => {
                             metadata.description = Some(text);
                         }
-                        "subject" => {
-                            if metadata.subjects.len() < MAX_SUBJECTS {
-                                metadata.subjects.push(text);
-                            }
+                        "subject" if metadata.subjects.len() < MAX_SUBJECTS => {
+                            metadata.subjects.push(text);
                         }
                         "identifier" => {
                             metadata.identifier = Some(text);
@@ -551,10 +554,8 @@ fn parse_opf_reader<R: std::io::BufRead>(reader: R) -> Result<EpubMetadata, Epub
                         "publisher" => metadata.publisher = Some(text),
                         "rights" => metadata.rights = Some(text),
                         "description" => metadata.description = Some(text),
-                        "subject" => {
-                            if metadata.subjects.len() < MAX_SUBJECTS {
-                                metadata.subjects.push(text);
-                            }
+                        "subject" if metadata.subjects.len() < MAX_SUBJECTS => {
+                            metadata.subjects.push(text);
                         }
                         "identifier" => metadata.identifier = Some(text),
                         "meta" => {
@@ -795,7 +796,7 @@ pub fn extract_metadata(
 #[cfg(feature = "std")]
 pub fn parse_container_xml_file<P: AsRef<std::path::Path>>(path: P) -> Result<String, EpubError> {
     let file = std::fs::File::open(path)
-        .map_err(|e| EpubError::Io(format!("Failed to open container.xml: {}", e)))?;
+        .map_err(|e| EpubError::Io(IoErrorInfo::from_std(&e, IoOperation::Open)))?;
     let reader = std::io::BufReader::new(file);
     parse_container_xml_reader(reader)
 }
@@ -804,7 +805,7 @@ pub fn parse_container_xml_file<P: AsRef<std::path::Path>>(path: P) -> Result<St
 #[cfg(feature = "std")]
 pub fn parse_opf_file<P: AsRef<std::path::Path>>(path: P) -> Result<EpubMetadata, EpubError> {
     let file = std::fs::File::open(path)
-        .map_err(|e| EpubError::Io(format!("Failed to open OPF: {}", e)))?;
+        .map_err(|e| EpubError::Io(IoErrorInfo::from_std(&e, IoOperation::Open)))?;
     let reader = std::io::BufReader::new(file);
     parse_opf_reader(reader)
 }
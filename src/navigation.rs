@@ -32,6 +32,7 @@ use crate::error::EpubError;
 /// Navigation points can be nested to represent hierarchical structures
 /// (e.g., chapters containing sections).
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NavPoint {
     /// Display label for this navigation point
     pub label: String,
@@ -46,6 +47,7 @@ pub struct NavPoint {
 /// Contains table of contents, page list, and landmarks extracted
 /// from either the EPUB 3.x nav document or EPUB 2.0 NCX.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Navigation {
     /// Table of contents entries
     pub toc: Vec<NavPoint>,
@@ -214,24 +216,22 @@ pub fn parse_nav_xhtml(content: &[u8]) -> Result<Navigation, EpubError> {
                     _ => {}
                 }
             }
-            Ok(Event::Text(e)) => {
-                if in_anchor && current_nav_type.is_some() {
-                    let text = reader.decoder().decode(&e).unwrap_or_default().to_string();
-                    if let Some(item) = item_stack.last_mut() {
-                        match &mut item.label {
-                            Some(existing) => {
-                                // Add space separator when concatenating text segments
-                                // from formatted anchors (e.g. "Part <em>One</em>")
-                                if !existing.is_empty()
-                                    && !existing.ends_with(' ')
-                                    && !text.starts_with(' ')
-                                {
-                                    existing.push(' ');
-                                }
-                                existing.push_str(&text);
+            Ok(Event::Text(e)) if in_anchor && current_nav_type.is_some() => {
+                let text = reader.decoder().decode(&e).unwrap_or_default().to_string();
+                if let Some(item) = item_stack.last_mut() {
+                    match &mut item.label {
+                        Some(existing) => {
+                            // Add space separator when concatenating text segments
+                            // from formatted anchors (e.g. "Part <em>One</em>")
+                            if !existing.is_empty()
+                                && !existing.ends_with(' ')
+                                && !text.starts_with(' ')
+                            {
+                                existing.push(' ');
                             }
-                            None => item.label = Some(text),
+                            existing.push_str(&text);
                         }
+                        None => item.label = Some(text),
                     }
                 }
             }
@@ -396,20 +396,18 @@ pub fn parse_ncx(content: &[u8]) -> Result<Navigation, EpubError> {
                     _ => {}
                 }
             }
-            Ok(Event::Text(e)) => {
-                if in_text {
-                    let text = reader.decoder().decode(&e).unwrap_or_default().to_string();
-                    if in_page_target {
-                        match &mut current_label {
-                            Some(existing) => existing.push_str(&text),
-                            None => current_label = Some(text),
-                        }
-                    } else if let Some(point) = nav_point_stack.last_mut() {
-                        if point.label.is_empty() {
-                            point.label = text;
-                        } else {
-                            point.label.push_str(&text);
-                        }
+            Ok(Event::Text(e)) if in_text => {
+                let text = reader.decoder().decode(&e).unwrap_or_default().to_string();
+                if in_page_target {
+                    match &mut current_label {
+                        Some(existing) => existing.push_str(&text),
+                        None => current_label = Some(text),
+                    }
+                } else if let Some(point) = nav_point_stack.last_mut() {
+                    if point.label.is_empty() {
+                        point.label = text;
+                    } else {
+                        point.label.push_str(&text);
                     }
                 }
             }
@@ -512,13 +510,13 @@ mod tests {
                     children: vec![NavPoint {
                         label: "Sec 1.1".into(),
                         href: "ch1.xhtml#s1".into(),
-                        children: vec![],
+                        children: Vec::with_capacity(0),
                     }],
                 },
                 NavPoint {
                     label: "Ch 2".into(),
                     href: "ch2.xhtml".into(),
-                    children: vec![],
+                    children: Vec::with_capacity(0),
                 },
             ],
             ..Default::default()
@@ -536,7 +534,7 @@ mod tests {
                 children: vec![NavPoint {
                     label: "Sec 1.1".into(),
                     href: "ch1.xhtml#s1".into(),
-                    children: vec![],
+                    children: Vec::with_capacity(0),
                 }],
             }],
             ..Default::default()
@@ -1069,13 +1067,13 @@ mod tests {
                         children: vec![NavPoint {
                             label: "A1".into(),
                             href: "a1.xhtml".into(),
-                            children: vec![],
+                            children: Vec::with_capacity(0),
                         }],
                     },
                     NavPoint {
                         label: "B".into(),
                         href: "b.xhtml".into(),
-                        children: vec![],
+                        children: Vec::with_capacity(0),
                     },
                 ],
             }],
@@ -1088,16 +1086,16 @@ mod tests {
     #[test]
     fn test_navigation_has_page_list_and_landmarks() {
         let nav = Navigation {
-            toc: vec![],
+            toc: Vec::with_capacity(0),
             page_list: vec![NavPoint {
                 label: "1".into(),
                 href: "p1.xhtml".into(),
-                children: vec![],
+                children: Vec::with_capacity(0),
             }],
             landmarks: vec![NavPoint {
                 label: "Cover".into(),
                 href: "cover.xhtml".into(),
-                children: vec![],
+                children: Vec::with_capacity(0),
             }],
         };
         assert!(!nav.has_toc());
@@ -5,21 +5,52 @@ extern crate alloc;
 use alloc::boxed::Box;
 use alloc::format;
 use alloc::string::{String, ToString};
+use alloc::vec;
 use alloc::vec::Vec;
 use core::cmp::min;
 use core::fmt;
+use core::ops::ControlFlow;
+use core::time::Duration;
 use quick_xml::events::Event;
 use quick_xml::reader::Reader;
 
 use crate::book::EpubBook;
 use crate::css::{
-    parse_inline_style, parse_stylesheet, CssStyle, FontSize, FontStyle, FontWeight, LineHeight,
-    Stylesheet,
+    parse_inline_style, parse_stylesheet, Color, CssRule, CssStyle, FontSize, FontStyle,
+    FontWeight, LineHeight, Stylesheet,
 };
 use crate::error::{EpubError, ErrorLimitContext, ErrorPhase, PhaseError, PhaseErrorContext};
+use crate::io::{Read, Seek};
+use crate::memory::{MemoryGauge, MemorySubsystem};
+
+/// Start of a timed phase, or `None` where no monotonic clock exists.
+#[cfg(feature = "std")]
+fn phase_clock() -> Option<std::time::Instant> {
+    if cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+        return None;
+    }
+    Some(std::time::Instant::now())
+}
+
+#[cfg(not(feature = "std"))]
+#[derive(Clone, Copy)]
+struct PhaseInstant;
+
+#[cfg(not(feature = "std"))]
+impl PhaseInstant {
+    fn elapsed(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+#[cfg(not(feature = "std"))]
+fn phase_clock() -> Option<PhaseInstant> {
+    None
+}
 
 /// Limits for stylesheet parsing and application.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StyleLimits {
     /// Maximum number of stylesheet rules to process.
     pub max_selectors: usize,
@@ -41,6 +72,7 @@ impl Default for StyleLimits {
 
 /// Limits for embedded font enumeration and registration.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FontLimits {
     /// Maximum number of font faces accepted.
     pub max_faces: usize,
@@ -62,6 +94,7 @@ impl Default for FontLimits {
 
 /// Safe layout hint clamps for text style normalization.
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LayoutHints {
     /// Default base font size in pixels.
     pub base_font_size_px: f32,
@@ -77,6 +110,18 @@ pub struct LayoutHints {
     ///
     /// This lets reader UIs scale books even when EPUB CSS uses fixed px sizes.
     pub text_scale: f32,
+    /// Device pixels per inch used to resolve absolute CSS units (`pt`, `mm`).
+    ///
+    /// Non-positive values fall back to the CSS reference density of 96.
+    pub dpi: f32,
+}
+
+impl LayoutHints {
+    /// Convert a length in points (1/72 inch) to device pixels.
+    pub fn pt_to_px(&self, pt: f32) -> f32 {
+        let dpi = if self.dpi > 0.0 { self.dpi } else { 96.0 };
+        pt * dpi / 72.0
+    }
 }
 
 impl Default for LayoutHints {
@@ -88,12 +133,14 @@ impl Default for LayoutHints {
             min_line_height: 1.1,
             max_line_height: 2.2,
             text_scale: 1.0,
+            dpi: 96.0,
         }
     }
 }
 
 /// Style engine options.
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StyleConfig {
     /// Hard parsing limits.
     pub limits: StyleLimits,
@@ -103,6 +150,7 @@ pub struct StyleConfig {
 
 /// Render-prep orchestration options.
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RenderPrepOptions {
     /// Stylesheet parsing and resolution options.
     pub style: StyleConfig,
@@ -116,6 +164,7 @@ pub struct RenderPrepOptions {
 
 /// Hard memory/resource budgets for open/parse/style/layout/render paths.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MemoryBudget {
     /// Max bytes allowed for a single heavy entry read (e.g. chapter XHTML).
     pub max_entry_bytes: usize,
@@ -125,8 +174,16 @@ pub struct MemoryBudget {
     pub max_nav_bytes: usize,
     /// Max bytes allowed for a single inline `style="..."` attribute payload.
     pub max_inline_style_bytes: usize,
+    /// Max bytes of markup kept for a single inline `<svg>` island.
+    ///
+    /// Larger islands keep only their `<title>` and are marked
+    /// `StyledImage::svg_oversize`.
+    pub max_inline_svg_bytes: usize,
     /// Max page objects allowed in memory for eager consumers.
     pub max_pages_in_memory: usize,
+    /// Max approximate bytes of page objects held in memory for eager
+    /// consumers, undrained layout sessions, and render-cache capture.
+    pub max_page_bytes_in_memory: usize,
 }
 
 impl Default for MemoryBudget {
@@ -136,7 +193,9 @@ impl Default for MemoryBudget {
             max_css_bytes: 512 * 1024,
             max_nav_bytes: 512 * 1024,
             max_inline_style_bytes: 16 * 1024,
+            max_inline_svg_bytes: 256 * 1024,
             max_pages_in_memory: 128,
+            max_page_bytes_in_memory: 8 * 1024 * 1024,
         }
     }
 }
@@ -217,6 +276,15 @@ impl RenderPrepError {
         self
     }
 
+    /// Scope to a chapter, keeping any more specific path already set.
+    fn in_chapter(mut self, chapter_index: usize, href: &str) -> Self {
+        self.chapter_index.get_or_insert(chapter_index);
+        if self.path.is_none() {
+            self.path = Some(href.into());
+        }
+        self
+    }
+
     fn with_limit(mut self, kind: &'static str, actual: usize, limit: usize) -> Self {
         self.limit = Some(Box::new(ErrorLimitContext::new(kind, actual, limit)));
         self
@@ -303,6 +371,23 @@ impl fmt::Display for RenderPrepError {
     }
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for RenderPrepError {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "{}:{=str}: {=str}", self.phase, self.code, &self.message);
+        if let Some(path) = self.path.as_deref() {
+            defmt::write!(f, " [path={=str}]", path);
+        }
+        if let Some(chapter_index) = self.chapter_index {
+            defmt::write!(f, " [chapter_index={=usize}]", chapter_index);
+        }
+        if let Some(limit) = self.limit.as_deref() {
+            defmt::write!(f, " [{}]", limit);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
 impl std::error::Error for RenderPrepError {}
 
 impl From<RenderPrepError> for PhaseError {
@@ -334,6 +419,7 @@ impl From<RenderPrepError> for PhaseError {
             code: err.code,
             message: err.message,
             context: Some(Box::new(ctx)),
+            cause: None,
         }
     }
 }
@@ -397,6 +483,7 @@ pub struct EmbeddedFontFace {
 
 /// Semantic block role for computed styles.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum BlockRole {
     /// Body text.
     Body,
@@ -425,6 +512,8 @@ pub struct ComputedTextStyle {
     pub letter_spacing: f32,
     /// Semantic block role.
     pub block_role: BlockRole,
+    /// Foreground color, when specified by CSS.
+    pub color: Option<Color>,
 }
 
 /// Styled text run.
@@ -438,6 +527,45 @@ pub struct StyledRun {
     pub font_id: u32,
     /// Resolved family selected by the font resolver.
     pub resolved_family: String,
+    /// Target of the enclosing `<a href>`, when the run is part of a link.
+    ///
+    /// Book-internal targets are OPF-relative paths with an optional
+    /// `#fragment`; external URLs are passed through unchanged.
+    pub link: Option<String>,
+    /// Element ids (`id`, or `name` on `<a>`) that start at this run,
+    /// including ids of empty or text-less elements just before it.
+    pub anchors: Vec<String>,
+}
+
+/// Styled image or inline SVG island.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StyledImage {
+    /// Archive path of the image resource (empty for inline SVG islands).
+    pub src: String,
+    /// Alternative text (`alt`, `aria-label`, or SVG `<title>`).
+    pub alt: String,
+    /// Declared width in CSS pixels, when known.
+    pub width_px: Option<f32>,
+    /// Declared height in CSS pixels, when known.
+    pub height_px: Option<f32>,
+    /// Raw `<svg>` markup for inline SVG islands.
+    pub inline_svg: Option<String>,
+    /// Inline SVG island whose markup exceeded `max_inline_svg_bytes` and
+    /// was dropped; only its `<title>` survives, as `alt`.
+    pub svg_oversize: bool,
+    /// Text of the enclosing `<figure>`'s `<figcaption>`, when present.
+    pub caption: Option<String>,
+}
+
+impl StyledImage {
+    /// Whether this image is SVG content (inline island or `.svg` resource).
+    pub fn is_svg(&self) -> bool {
+        if self.inline_svg.is_some() || self.svg_oversize {
+            return true;
+        }
+        let path = self.src.split('#').next().unwrap_or("");
+        path.to_ascii_lowercase().ends_with(".svg")
+    }
 }
 
 /// Structured block/layout events.
@@ -466,6 +594,8 @@ pub enum StyledEventOrRun {
     Event(StyledEvent),
     /// Styled text run.
     Run(StyledRun),
+    /// Image or inline SVG island.
+    Image(StyledImage),
 }
 
 /// Styled chapter output.
@@ -500,6 +630,7 @@ pub struct Styler {
     config: StyleConfig,
     memory: MemoryBudget,
     parsed: Vec<Stylesheet>,
+    tables: MemoryGauge,
 }
 
 impl Styler {
@@ -509,6 +640,7 @@ impl Styler {
             config,
             memory: MemoryBudget::default(),
             parsed: Vec::with_capacity(0),
+            tables: MemoryGauge::new(MemorySubsystem::StyleTables),
         }
     }
 
@@ -532,6 +664,7 @@ impl Styler {
 
     fn clear_stylesheets(&mut self) {
         self.parsed.clear();
+        self.tables.set(0);
     }
 
     fn push_stylesheet_source(&mut self, href: &str, css: &str) -> Result<(), RenderPrepError> {
@@ -582,6 +715,12 @@ impl Styler {
             return Err(err);
         }
         self.parsed.push(parsed);
+        let rule_bytes = self
+            .parsed
+            .iter()
+            .map(|sheet| sheet.rules.capacity() * size_of::<CssRule>())
+            .sum();
+        self.tables.set(rule_bytes);
         Ok(())
     }
 
@@ -617,15 +756,49 @@ impl Styler {
     ) -> Result<(), RenderPrepError>
     where
         F: FnMut(StyledEventOrRun),
+    {
+        self.style_chapter_bytes_timed(
+            html_bytes,
+            |item| {
+                on_item(item);
+                ControlFlow::Continue(())
+            },
+            None,
+        )
+    }
+
+    /// Style chapter bytes, adding time spent in the XML tokenizer to
+    /// `tokenize_time` when given.
+    ///
+    /// Stops tokenizing as soon as `on_item` breaks.
+    fn style_chapter_bytes_timed<F>(
+        &self,
+        html_bytes: &[u8],
+        on_item: F,
+        mut tokenize_time: Option<&mut Duration>,
+    ) -> Result<(), RenderPrepError>
+    where
+        F: FnMut(StyledEventOrRun) -> ControlFlow<()>,
     {
         let mut reader = Reader::from_reader(html_bytes);
         reader.config_mut().trim_text(false);
         let mut buf = Vec::with_capacity(0);
         let mut stack: Vec<ElementCtx> = Vec::with_capacity(0);
+        let mut anchors: Vec<String> = Vec::with_capacity(0);
         let mut skip_depth = 0usize;
+        let mut out = FigureCollector::new(on_item);
 
         loop {
-            match reader.read_event_into(&mut buf) {
+            if out.stopped {
+                return Ok(());
+            }
+            let event_start = reader_token_offset(&reader);
+            let started = tokenize_time.is_some().then(phase_clock).flatten();
+            let event = reader.read_event_into(&mut buf);
+            if let (Some(total), Some(started)) = (tokenize_time.as_deref_mut(), started) {
+                *total += started.elapsed();
+            }
+            match event {
                 Ok(Event::Start(e)) => {
                     let tag = decode_tag_name(&reader, e.name().as_ref())?;
                     if should_skip_tag(&tag) {
@@ -639,7 +812,38 @@ impl Styler {
                     }
                     let ctx =
                         element_ctx_from_start(&reader, &e, self.memory.max_inline_style_bytes)?;
-                    emit_start_event(&ctx.tag, &mut on_item);
+                    anchors.extend(ctx.id.clone());
+                    if ctx.tag == "svg" {
+                        let mut image = image_from_element(&reader, &e);
+                        // Slice-backed reads skip the island without copying it.
+                        reader.read_to_end(e.to_end().name()).map_err(|err| {
+                            RenderPrepError::new(
+                                "STYLE_TOKENIZE_ERROR",
+                                format!("XML error: {:?}", err),
+                            )
+                            .with_phase(ErrorPhase::Style)
+                            .with_source("inline svg")
+                            .with_token_offset(reader_token_offset(&reader))
+                        })?;
+                        let event_end = reader_token_offset(&reader).min(html_bytes.len());
+                        let markup = &html_bytes[event_start.min(event_end)..event_end];
+                        if image.alt.is_empty() {
+                            image.alt = svg_title(markup).unwrap_or_default();
+                        }
+                        if markup.len() > self.memory.max_inline_svg_bytes {
+                            image.svg_oversize = true;
+                        } else {
+                            image.inline_svg = Some(String::from_utf8_lossy(markup).into_owned());
+                        }
+                        out.push(StyledEventOrRun::Image(image));
+                        buf.clear();
+                        continue;
+                    }
+                    if ctx.tag == "img" {
+                        out.push(StyledEventOrRun::Image(image_from_element(&reader, &e)));
+                    }
+                    out.start_tag(&ctx.tag);
+                    emit_start_event(&ctx.tag, &mut |item| out.push(item));
                     stack.push(ctx);
                 }
                 Ok(Event::Empty(e)) => {
@@ -650,11 +854,15 @@ impl Styler {
                     }
                     let ctx =
                         element_ctx_from_start(&reader, &e, self.memory.max_inline_style_bytes)?;
-                    emit_start_event(&ctx.tag, &mut on_item);
+                    anchors.extend(ctx.id.clone());
+                    emit_start_event(&ctx.tag, &mut |item| out.push(item));
                     if ctx.tag == "br" {
-                        on_item(StyledEventOrRun::Event(StyledEvent::LineBreak));
+                        out.push(StyledEventOrRun::Event(StyledEvent::LineBreak));
                     }
-                    emit_end_event(&ctx.tag, &mut on_item);
+                    if ctx.tag == "img" {
+                        out.push(StyledEventOrRun::Image(image_from_element(&reader, &e)));
+                    }
+                    emit_end_event(&ctx.tag, &mut |item| out.push(item));
                 }
                 Ok(Event::End(e)) => {
                     let tag = decode_tag_name(&reader, e.name().as_ref())?;
@@ -667,7 +875,8 @@ impl Styler {
                         buf.clear();
                         continue;
                     }
-                    emit_end_event(&tag, &mut on_item);
+                    emit_end_event(&tag, &mut |item| out.push(item));
+                    out.end_tag(&tag);
                     if !stack.is_empty() {
                         stack.pop();
                     }
@@ -697,11 +906,13 @@ impl Styler {
                     }
                     let (resolved, role, bold_tag, italic_tag) = self.resolve_context_style(&stack);
                     let style = self.compute_style(resolved, role, bold_tag, italic_tag);
-                    on_item(StyledEventOrRun::Run(StyledRun {
+                    out.push(StyledEventOrRun::Run(StyledRun {
                         text: normalized,
                         style,
                         font_id: 0,
                         resolved_family: String::with_capacity(0),
+                        link: link_from_stack(&stack),
+                        anchors: core::mem::take(&mut anchors),
                     }));
                 }
                 Ok(Event::CData(e)) => {
@@ -730,11 +941,13 @@ impl Styler {
                     }
                     let (resolved, role, bold_tag, italic_tag) = self.resolve_context_style(&stack);
                     let style = self.compute_style(resolved, role, bold_tag, italic_tag);
-                    on_item(StyledEventOrRun::Run(StyledRun {
+                    out.push(StyledEventOrRun::Run(StyledRun {
                         text: normalized,
                         style,
                         font_id: 0,
                         resolved_family: String::with_capacity(0),
+                        link: link_from_stack(&stack),
+                        anchors: core::mem::take(&mut anchors),
                     }));
                 }
                 Ok(Event::GeneralRef(e)) => {
@@ -771,14 +984,19 @@ impl Styler {
                     }
                     let (resolved, role, bold_tag, italic_tag) = self.resolve_context_style(&stack);
                     let style = self.compute_style(resolved, role, bold_tag, italic_tag);
-                    on_item(StyledEventOrRun::Run(StyledRun {
+                    out.push(StyledEventOrRun::Run(StyledRun {
                         text: normalized,
                         style,
                         font_id: 0,
                         resolved_family: String::with_capacity(0),
+                        link: link_from_stack(&stack),
+                        anchors: core::mem::take(&mut anchors),
                     }));
                 }
-                Ok(Event::Eof) => break,
+                Ok(Event::Eof) => {
+                    out.flush();
+                    break;
+                }
                 Ok(_) => {}
                 Err(err) => {
                     return Err(RenderPrepError::new(
@@ -815,6 +1033,7 @@ impl Styler {
         let mut size_px = match resolved.font_size {
             Some(FontSize::Px(px)) => px,
             Some(FontSize::Em(em)) => self.config.hints.base_font_size_px * em,
+            Some(FontSize::Pt(pt)) => self.config.hints.pt_to_px(pt),
             None => {
                 if matches!(role, BlockRole::Heading(1 | 2)) {
                     self.config.hints.base_font_size_px * 1.25
@@ -832,6 +1051,7 @@ impl Styler {
         let mut line_height = match resolved.line_height {
             Some(LineHeight::Px(px)) => (px / size_px).max(1.0),
             Some(LineHeight::Multiplier(m)) => m,
+            Some(LineHeight::Pt(pt)) => (self.config.hints.pt_to_px(pt) / size_px).max(1.0),
             None => 1.4,
         };
         line_height = line_height.clamp(
@@ -864,6 +1084,7 @@ impl Styler {
             line_height,
             letter_spacing: 0.0,
             block_role: role,
+            color: resolved.color,
         }
     }
 
@@ -1148,6 +1369,9 @@ pub struct RenderPrep {
     opts: RenderPrepOptions,
     styler: Styler,
     font_resolver: FontResolver,
+    phase_timing: bool,
+    tokenize_time: Duration,
+    chapter_bytes: usize,
 }
 
 /// Structured trace context for a streamed chapter item.
@@ -1191,9 +1415,36 @@ impl RenderPrep {
             opts,
             styler,
             font_resolver,
+            phase_timing: false,
+            tokenize_time: Duration::ZERO,
+            chapter_bytes: 0,
         }
     }
 
+    /// Measure time spent reading and tokenizing chapter markup.
+    ///
+    /// Off by default; see [`RenderPrep::tokenize_time`]. Ignored without
+    /// `std` and on `wasm32-unknown-unknown`, which have no platform clock.
+    pub fn with_phase_timing(mut self, enabled: bool) -> Self {
+        self.phase_timing = enabled;
+        self
+    }
+
+    /// Time spent reading and tokenizing the most recently prepared chapter.
+    ///
+    /// Always zero unless phase timing is enabled.
+    pub fn tokenize_time(&self) -> Duration {
+        self.tokenize_time
+    }
+
+    /// Chapter markup bytes read from the book for the most recently
+    /// prepared chapter.
+    ///
+    /// Zero when the markup was provided by the caller.
+    pub fn chapter_bytes(&self) -> usize {
+        self.chapter_bytes
+    }
+
     /// Use serif default fallback policy.
     pub fn with_serif_default(mut self) -> Self {
         self.font_resolver =
@@ -1202,7 +1453,7 @@ impl RenderPrep {
     }
 
     /// Register all embedded fonts from a book.
-    pub fn with_embedded_fonts_from_book<R: std::io::Read + std::io::Seek>(
+    pub fn with_embedded_fonts_from_book<R: Read + Seek>(
         self,
         book: &mut EpubBook<R>,
     ) -> Result<Self, RenderPrepError> {
@@ -1218,8 +1469,8 @@ impl RenderPrep {
         self.with_registered_fonts(fonts, |href| book.read_resource(href))
     }
 
-    fn load_chapter_html_with_budget<R: std::io::Read + std::io::Seek>(
-        &self,
+    fn load_chapter_html_with_budget<R: Read + Seek>(
+        &mut self,
         book: &mut EpubBook<R>,
         index: usize,
     ) -> Result<(String, Vec<u8>), RenderPrepError> {
@@ -1233,6 +1484,7 @@ impl RenderPrep {
                 .with_path(href.clone())
                 .with_chapter_index(index)
         })?;
+        self.chapter_bytes = bytes.len();
         if bytes.len() > self.opts.memory.max_entry_bytes {
             return Err(RenderPrepError::new_with_phase(
                 ErrorPhase::Parse,
@@ -1254,7 +1506,7 @@ impl RenderPrep {
         Ok((href, bytes))
     }
 
-    fn apply_chapter_stylesheets_with_budget<R: std::io::Read + std::io::Seek>(
+    fn apply_chapter_stylesheets_with_budget<R: Read + Seek>(
         &mut self,
         book: &mut EpubBook<R>,
         chapter_index: usize,
@@ -1323,7 +1575,7 @@ impl RenderPrep {
     }
 
     /// Prepare a chapter into styled runs/events.
-    pub fn prepare_chapter<R: std::io::Read + std::io::Seek>(
+    pub fn prepare_chapter<R: Read + Seek>(
         &mut self,
         book: &mut EpubBook<R>,
         index: usize,
@@ -1336,7 +1588,7 @@ impl RenderPrep {
     }
 
     /// Prepare a chapter and append results into an output buffer.
-    pub fn prepare_chapter_into<R: std::io::Read + std::io::Seek>(
+    pub fn prepare_chapter_into<R: Read + Seek>(
         &mut self,
         book: &mut EpubBook<R>,
         index: usize,
@@ -1346,19 +1598,68 @@ impl RenderPrep {
     }
 
     /// Prepare a chapter and stream each styled item via callback.
-    pub fn prepare_chapter_with<R: std::io::Read + std::io::Seek, F: FnMut(StyledEventOrRun)>(
+    pub fn prepare_chapter_with<R: Read + Seek, F: FnMut(StyledEventOrRun)>(
         &mut self,
         book: &mut EpubBook<R>,
         index: usize,
         mut on_item: F,
     ) -> Result<(), RenderPrepError> {
+        self.prepare_chapter_with_resources(book, index, |item, _| on_item(item))
+    }
+
+    /// Prepare a chapter and stream each styled item together with the book handle.
+    ///
+    /// The callback may read additional resources (for example image payloads)
+    /// from the book while the chapter is being styled.
+    pub fn prepare_chapter_with_resources<
+        R: Read + Seek,
+        F: FnMut(StyledEventOrRun, &mut EpubBook<R>),
+    >(
+        &mut self,
+        book: &mut EpubBook<R>,
+        index: usize,
+        mut on_item: F,
+    ) -> Result<(), RenderPrepError> {
+        self.prepare_chapter_with_resources_until(book, index, |item, book| {
+            on_item(item, book);
+            ControlFlow::Continue(())
+        })
+    }
+
+    /// Like [`RenderPrep::prepare_chapter_with_resources`], but stops
+    /// tokenizing and styling the chapter as soon as the callback breaks.
+    ///
+    /// Markup after the break point is never parsed, so errors it would
+    /// raise are not reported either.
+    pub fn prepare_chapter_with_resources_until<
+        R: Read + Seek,
+        F: FnMut(StyledEventOrRun, &mut EpubBook<R>) -> ControlFlow<()>,
+    >(
+        &mut self,
+        book: &mut EpubBook<R>,
+        index: usize,
+        mut on_item: F,
+    ) -> Result<(), RenderPrepError> {
+        self.tokenize_time = Duration::ZERO;
+        let started = self.phase_timing.then(phase_clock).flatten();
         let (chapter_href, html) = self.load_chapter_html_with_budget(book, index)?;
+        if let Some(started) = started {
+            self.tokenize_time = started.elapsed();
+        }
+        let _span = phase_span!("style", chapter_index = index, href = chapter_href.as_str());
         self.apply_chapter_stylesheets_with_budget(book, index, &chapter_href, &html)?;
         let font_resolver = &self.font_resolver;
-        self.styler.style_chapter_bytes_with(&html, |item| {
-            let (item, _) = resolve_item_with_font(font_resolver, item);
-            on_item(item);
-        })
+        let tokenize_time = self.phase_timing.then_some(&mut self.tokenize_time);
+        self.styler
+            .style_chapter_bytes_timed(
+                &html,
+                |item| {
+                    let (item, _) = resolve_item_with_font(font_resolver, &chapter_href, item);
+                    on_item(item, book)
+                },
+                tokenize_time,
+            )
+            .map_err(|err| err.in_chapter(index, &chapter_href))
     }
 
     /// Prepare a chapter from caller-provided XHTML bytes and stream each styled item.
@@ -1366,9 +1667,22 @@ impl RenderPrep {
     /// This avoids re-reading chapter bytes from the ZIP archive and is intended for
     /// embedded call sites that already own a reusable chapter buffer.
     #[inline(never)]
-    pub fn prepare_chapter_bytes_with<
-        R: std::io::Read + std::io::Seek,
-        F: FnMut(StyledEventOrRun),
+    pub fn prepare_chapter_bytes_with<R: Read + Seek, F: FnMut(StyledEventOrRun)>(
+        &mut self,
+        book: &mut EpubBook<R>,
+        index: usize,
+        html: &[u8],
+        mut on_item: F,
+    ) -> Result<(), RenderPrepError> {
+        self.prepare_chapter_bytes_with_resources(book, index, html, |item, _| on_item(item))
+    }
+
+    /// Prepare caller-provided XHTML bytes and stream each styled item with the book handle.
+    ///
+    /// See [`RenderPrep::prepare_chapter_with_resources`].
+    pub fn prepare_chapter_bytes_with_resources<
+        R: Read + Seek,
+        F: FnMut(StyledEventOrRun, &mut EpubBook<R>),
     >(
         &mut self,
         book: &mut EpubBook<R>,
@@ -1381,6 +1695,7 @@ impl RenderPrep {
                 .with_chapter_index(index)
         })?;
         let chapter_href = chapter.href;
+        let _span = phase_span!("style", chapter_index = index, href = chapter_href.as_str());
         if html.len() > self.opts.memory.max_entry_bytes {
             return Err(RenderPrepError::new_with_phase(
                 ErrorPhase::Parse,
@@ -1399,17 +1714,27 @@ impl RenderPrep {
                 self.opts.memory.max_entry_bytes,
             ));
         }
+        self.chapter_bytes = 0;
         self.apply_chapter_stylesheets_with_budget(book, index, &chapter_href, html)?;
+        self.tokenize_time = Duration::ZERO;
         let font_resolver = &self.font_resolver;
-        self.styler.style_chapter_bytes_with(html, |item| {
-            let (item, _) = resolve_item_with_font(font_resolver, item);
-            on_item(item);
-        })
+        let tokenize_time = self.phase_timing.then_some(&mut self.tokenize_time);
+        self.styler
+            .style_chapter_bytes_timed(
+                html,
+                |item| {
+                    let (item, _) = resolve_item_with_font(font_resolver, &chapter_href, item);
+                    on_item(item, book);
+                    ControlFlow::Continue(())
+                },
+                tokenize_time,
+            )
+            .map_err(|err| err.in_chapter(index, &chapter_href))
     }
 
     /// Prepare a chapter and stream each styled item with structured trace context.
     pub fn prepare_chapter_with_trace_context<
-        R: std::io::Read + std::io::Seek,
+        R: Read + Seek,
         F: FnMut(StyledEventOrRun, RenderPrepTrace),
     >(
         &mut self,
@@ -1418,12 +1743,15 @@ impl RenderPrep {
         mut on_item: F,
     ) -> Result<(), RenderPrepError> {
         let (chapter_href, html) = self.load_chapter_html_with_budget(book, index)?;
+        let _span = phase_span!("style", chapter_index = index, href = chapter_href.as_str());
         self.apply_chapter_stylesheets_with_budget(book, index, &chapter_href, &html)?;
         let font_resolver = &self.font_resolver;
-        self.styler.style_chapter_bytes_with(&html, |item| {
-            let (item, trace) = resolve_item_with_font(font_resolver, item);
-            on_item(item, trace);
-        })
+        self.styler
+            .style_chapter_bytes_with(&html, |item| {
+                let (item, trace) = resolve_item_with_font(font_resolver, &chapter_href, item);
+                on_item(item, trace);
+            })
+            .map_err(|err| err.in_chapter(index, &chapter_href))
     }
 
     /// Prepare a chapter and stream each styled item with optional font-resolution trace.
@@ -1432,7 +1760,7 @@ impl RenderPrep {
         note = "Use prepare_chapter_with_trace_context for stable structured trace output."
     )]
     pub fn prepare_chapter_with_trace<
-        R: std::io::Read + std::io::Seek,
+        R: Read + Seek,
         F: FnMut(StyledEventOrRun, Option<FontResolutionTrace>),
     >(
         &mut self,
@@ -1469,6 +1797,12 @@ struct ElementCtx {
     tag: String,
     classes: Vec<String>,
     inline_style: Option<CssStyle>,
+    href: Option<String>,
+    id: Option<String>,
+}
+
+fn link_from_stack(stack: &[ElementCtx]) -> Option<String> {
+    stack.iter().rev().find_map(|ctx| ctx.href.clone())
 }
 
 fn reader_token_offset(reader: &Reader<&[u8]>) -> usize {
@@ -1513,6 +1847,8 @@ fn element_ctx_from_start(
     let tag = decode_tag_name(reader, e.name().as_ref())?;
     let mut classes = Vec::with_capacity(0);
     let mut inline_style = None;
+    let mut href = None;
+    let mut id = None;
     for attr in e.attributes().flatten() {
         let key = match reader.decoder().decode(attr.key.as_ref()) {
             Ok(v) => v.to_ascii_lowercase(),
@@ -1522,7 +1858,11 @@ fn element_ctx_from_start(
             Ok(v) => v.to_string(),
             Err(_) => continue,
         };
-        if key == "class" {
+        if key == "href" && tag == "a" && !val.trim().is_empty() {
+            href = Some(val.trim().to_string());
+        } else if (key == "id" || (key == "name" && tag == "a")) && !val.trim().is_empty() {
+            id.get_or_insert_with(|| val.trim().to_string());
+        } else if key == "class" {
             classes = val
                 .split_whitespace()
                 .map(|v| v.trim().to_string())
@@ -1573,9 +1913,195 @@ fn element_ctx_from_start(
         tag,
         classes,
         inline_style,
+        href,
+        id,
     })
 }
 
+fn image_from_element(
+    reader: &Reader<&[u8]>,
+    e: &quick_xml::events::BytesStart<'_>,
+) -> StyledImage {
+    let mut image = StyledImage {
+        src: String::with_capacity(0),
+        alt: String::with_capacity(0),
+        width_px: None,
+        height_px: None,
+        inline_svg: None,
+        svg_oversize: false,
+        caption: None,
+    };
+    let mut aria_label = None;
+    let mut view_box = None;
+    for attr in e.attributes().flatten() {
+        let key = match reader.decoder().decode(attr.key.as_ref()) {
+            Ok(v) => v.to_ascii_lowercase(),
+            Err(_) => continue,
+        };
+        let val = match attr.unescape_value() {
+            Ok(v) => v.trim().to_string(),
+            Err(_) => continue,
+        };
+        match key.as_str() {
+            "src" => image.src = val,
+            "alt" => image.alt = val,
+            "aria-label" => aria_label = Some(val),
+            "width" => image.width_px = parse_dimension_px(&val),
+            "height" => image.height_px = parse_dimension_px(&val),
+            "viewbox" => view_box = Some(val),
+            _ => {}
+        }
+    }
+    if image.alt.is_empty() {
+        image.alt = aria_label.unwrap_or_default();
+    }
+    if image.width_px.is_none() || image.height_px.is_none() {
+        let dims: Vec<f32> = view_box
+            .as_deref()
+            .unwrap_or("")
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|part| !part.is_empty())
+            .filter_map(|part| part.parse::<f32>().ok())
+            .collect();
+        if let [_, _, w, h] = dims[..] {
+            if w > 0.0 && h > 0.0 {
+                match (image.width_px, image.height_px) {
+                    (Some(width), None) => image.height_px = Some(width * h / w),
+                    (None, Some(height)) => image.width_px = Some(height * w / h),
+                    _ => {
+                        image.width_px = Some(w);
+                        image.height_px = Some(h);
+                    }
+                }
+            }
+        }
+    }
+    image
+}
+
+fn parse_dimension_px(value: &str) -> Option<f32> {
+    let trimmed = value.trim();
+    let number = trimmed.strip_suffix("px").unwrap_or(trimmed).trim();
+    number
+        .parse::<f32>()
+        .ok()
+        .filter(|v| v.is_finite() && *v > 0.0)
+}
+
+fn svg_title(markup: &[u8]) -> Option<String> {
+    let open = find_bytes(markup, b"<title")?;
+    let body_start = open + markup[open..].iter().position(|&b| b == b'>')? + 1;
+    let body_len = find_bytes(&markup[body_start..], b"</title")?;
+    let title = String::from_utf8_lossy(&markup[body_start..body_start + body_len]);
+    let title = title.trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Upper bound on items buffered while waiting for a `<figure>` to close.
+const MAX_FIGURE_BUFFERED_ITEMS: usize = 256;
+
+/// Item sink that holds back `<figure>` content until the figure closes so its
+/// `<figcaption>` text can be attached to the figure's images.
+struct FigureCollector<F> {
+    on_item: F,
+    /// `on_item` asked to stop; later items are dropped.
+    stopped: bool,
+    figure_depth: usize,
+    caption_depth: usize,
+    items: Vec<StyledEventOrRun>,
+    caption: String,
+    overflowed: bool,
+}
+
+impl<F: FnMut(StyledEventOrRun) -> ControlFlow<()>> FigureCollector<F> {
+    fn new(on_item: F) -> Self {
+        Self {
+            on_item,
+            stopped: false,
+            figure_depth: 0,
+            caption_depth: 0,
+            items: Vec::with_capacity(0),
+            caption: String::with_capacity(0),
+            overflowed: false,
+        }
+    }
+
+    fn emit(&mut self, item: StyledEventOrRun) {
+        if !self.stopped && (self.on_item)(item).is_break() {
+            self.stopped = true;
+        }
+    }
+
+    fn push(&mut self, item: StyledEventOrRun) {
+        if self.figure_depth == 0 || self.overflowed {
+            self.emit(item);
+            return;
+        }
+        if self.caption_depth > 0 {
+            if let StyledEventOrRun::Run(run) = &item {
+                self.caption.push(' ');
+                self.caption.push_str(&run.text);
+            }
+        }
+        if self.items.len() >= MAX_FIGURE_BUFFERED_ITEMS {
+            self.overflowed = true;
+            for buffered in core::mem::take(&mut self.items) {
+                self.emit(buffered);
+            }
+            self.emit(item);
+            return;
+        }
+        self.items.push(item);
+    }
+
+    fn start_tag(&mut self, tag: &str) {
+        match tag {
+            "figure" => self.figure_depth += 1,
+            "figcaption" if self.figure_depth > 0 => self.caption_depth += 1,
+            _ => {}
+        }
+    }
+
+    fn end_tag(&mut self, tag: &str) {
+        match tag {
+            "figcaption" => self.caption_depth = self.caption_depth.saturating_sub(1),
+            "figure" if self.figure_depth > 0 => {
+                self.figure_depth -= 1;
+                if self.figure_depth == 0 {
+                    self.flush();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn flush(&mut self) {
+        let caption = self
+            .caption
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        for mut item in core::mem::take(&mut self.items) {
+            if let StyledEventOrRun::Image(image) = &mut item {
+                if image.caption.is_none() && !caption.is_empty() {
+                    image.caption = Some(caption.clone());
+                }
+            }
+            self.emit(item);
+        }
+        self.caption.clear();
+        self.caption_depth = 0;
+        self.figure_depth = 0;
+        self.overflowed = false;
+    }
+}
+
 fn emit_start_event<F: FnMut(StyledEventOrRun)>(tag: &str, on_item: &mut F) {
     match tag {
         "p" | "div" => on_item(StyledEventOrRun::Event(StyledEvent::ParagraphStart)),
@@ -1668,6 +2194,7 @@ fn has_non_ascii(text: &str) -> bool {
 
 fn resolve_item_with_font(
     font_resolver: &FontResolver,
+    chapter_href: &str,
     item: StyledEventOrRun,
 ) -> (StyledEventOrRun, RenderPrepTrace) {
     match item {
@@ -1675,6 +2202,7 @@ fn resolve_item_with_font(
             let trace = font_resolver.resolve_with_trace_for_text(&run.style, Some(&run.text));
             run.font_id = trace.face.font_id;
             run.resolved_family = trace.face.family.clone();
+            run.link = run.link.map(|href| resolve_link(chapter_href, &href));
             let style = run.style.clone();
             (
                 StyledEventOrRun::Run(run),
@@ -1685,7 +2213,27 @@ fn resolve_item_with_font(
             )
         }
         StyledEventOrRun::Event(event) => (StyledEventOrRun::Event(event), RenderPrepTrace::Event),
+        StyledEventOrRun::Image(mut image) => {
+            if !image.src.is_empty() {
+                image.src = resolve_relative(chapter_href, &image.src);
+            }
+            (StyledEventOrRun::Image(image), RenderPrepTrace::Event)
+        }
+    }
+}
+
+/// Resolve an `<a href>` against the chapter, keeping external URLs as-is.
+fn resolve_link(chapter_href: &str, href: &str) -> String {
+    if href.starts_with('#') {
+        return format!("{}{}", chapter_href, href);
+    }
+    let has_scheme = href
+        .split_once(':')
+        .is_some_and(|(scheme, _)| !scheme.is_empty() && !scheme.contains('/'));
+    if has_scheme {
+        return href.to_string();
     }
+    resolve_relative(chapter_href, href)
 }
 
 fn split_family_stack(value: &str) -> Vec<String> {
@@ -1887,10 +2435,8 @@ pub(crate) fn parse_font_faces_from_css(css_href: &str, css: &str) -> Vec<Embedd
                         EmbeddedFontStyle::Normal
                     };
                 }
-                "font-stretch" => {
-                    if !value.is_empty() {
-                        stretch = Some(value.to_string());
-                    }
+                "font-stretch" if !value.is_empty() => {
+                    stretch = Some(value.to_string());
                 }
                 "src" => {
                     href = extract_font_face_src(css_href, value);
@@ -2003,6 +2549,88 @@ mod tests {
         assert!(chapter.runs().count() >= 2);
     }
 
+    #[test]
+    fn styler_emits_image_items_for_img_and_inline_svg() {
+        let styler = Styler::new(StyleConfig::default());
+        let chapter = styler
+            .style_chapter(
+                "<p><img src=\"../images/fig.png\" alt=\"Figure\" width=\"120\" height=\"80px\"/></p>\
+                 <svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 60 30\">\
+                 <title>Diagram</title><rect width=\"10\" height=\"10\"/></svg>\
+                 <p>after</p>",
+            )
+            .expect("style should succeed");
+        let images: Vec<&StyledImage> = chapter
+            .iter()
+            .filter_map(|item| match item {
+                StyledEventOrRun::Image(image) => Some(image),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].src, "../images/fig.png");
+        assert_eq!(images[0].alt, "Figure");
+        assert_eq!(images[0].width_px, Some(120.0));
+        assert_eq!(images[0].height_px, Some(80.0));
+        assert!(!images[0].is_svg());
+
+        assert!(images[1].is_svg());
+        assert_eq!(images[1].alt, "Diagram");
+        assert_eq!(images[1].width_px, Some(60.0));
+        assert_eq!(images[1].height_px, Some(30.0));
+        let markup = images[1].inline_svg.as_deref().expect("inline markup");
+        assert!(markup.starts_with("<svg"));
+        assert!(markup.ends_with("</svg>"));
+        assert_eq!(
+            chapter
+                .runs()
+                .map(|run| run.text.as_str())
+                .collect::<Vec<_>>(),
+            vec!["after"]
+        );
+    }
+
+    #[test]
+    fn styler_drops_oversized_inline_svg_markup_but_keeps_title() {
+        let styler = Styler::new(StyleConfig::default()).with_memory_budget(MemoryBudget {
+            max_inline_svg_bytes: 64,
+            ..MemoryBudget::default()
+        });
+        let chapter = styler
+            .style_chapter(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 60 30\">\
+                 <title>Big chart</title><rect width=\"10\" height=\"10\"/></svg>\
+                 <p>after</p>",
+            )
+            .expect("style should succeed");
+        let image = chapter
+            .iter()
+            .find_map(|item| match item {
+                StyledEventOrRun::Image(image) => Some(image),
+                _ => None,
+            })
+            .expect("island still yields an image");
+        assert!(image.svg_oversize);
+        assert!(image.inline_svg.is_none());
+        assert!(image.is_svg());
+        assert_eq!(image.alt, "Big chart");
+        assert_eq!(chapter.runs().count(), 1);
+    }
+
+    #[test]
+    fn svgz_resources_are_not_treated_as_svg() {
+        let image = StyledImage {
+            src: "images/chart.svgz".to_string(),
+            alt: String::with_capacity(0),
+            width_px: None,
+            height_px: None,
+            inline_svg: None,
+            svg_oversize: false,
+            caption: None,
+        };
+        assert!(!image.is_svg());
+    }
+
     #[test]
     fn styler_style_chapter_with_streams_items() {
         let mut styler = Styler::new(StyleConfig::default());
@@ -2037,6 +2665,102 @@ mod tests {
         assert!(first.style.italic);
     }
 
+    #[test]
+    fn styler_inherits_css_color_into_runs() {
+        let mut styler = Styler::new(StyleConfig::default());
+        styler
+            .load_stylesheets(&ChapterStylesheets {
+                sources: vec![StylesheetSource {
+                    href: "main.css".to_string(),
+                    css: ".note { color: #336699; }".to_string(),
+                }],
+            })
+            .expect("load should succeed");
+        let chapter = styler
+            .style_chapter("<div class=\"note\"><p>Blue <em>text</em></p></div><p>plain</p>")
+            .expect("style should succeed");
+        let colors: Vec<Option<Color>> = chapter.runs().map(|run| run.style.color).collect();
+        let blue = Some(Color::rgb(0x33, 0x66, 0x99));
+        assert_eq!(colors.first(), Some(&blue));
+        assert_eq!(colors.get(1), Some(&blue));
+        assert_eq!(colors.last(), Some(&None));
+    }
+
+    #[test]
+    fn styler_tags_link_runs_and_resolves_targets() {
+        let styler = Styler::new(StyleConfig::default());
+        let chapter = styler
+            .style_chapter("<p>See <a href=\"notes.xhtml#n1\">the <em>note</em></a> here</p>")
+            .expect("style should succeed");
+        let links: Vec<Option<&str>> = chapter.runs().map(|run| run.link.as_deref()).collect();
+        assert_eq!(
+            links,
+            vec![None, Some("notes.xhtml#n1"), Some("notes.xhtml#n1"), None]
+        );
+
+        assert_eq!(
+            resolve_link("text/ch1.xhtml", "../notes.xhtml#n1"),
+            "notes.xhtml#n1"
+        );
+        assert_eq!(
+            resolve_link("text/ch1.xhtml", "#sec2"),
+            "text/ch1.xhtml#sec2"
+        );
+        assert_eq!(
+            resolve_link("text/ch1.xhtml", "mailto:a@example.com"),
+            "mailto:a@example.com"
+        );
+    }
+
+    #[test]
+    fn styler_attaches_element_ids_to_the_next_run() {
+        let styler = Styler::new(StyleConfig::default());
+        let chapter = styler
+            .style_chapter(
+                "<section id=\"s1\"><p id=\"p1\">One <span id=\"mid\">two</span></p>\
+                 <a id=\"empty\"/><p>Three <a name=\"old\">four</a></p></section>",
+            )
+            .expect("style should succeed");
+        let anchors: Vec<(&str, Vec<&str>)> = chapter
+            .runs()
+            .map(|run| {
+                let ids = run.anchors.iter().map(String::as_str).collect();
+                (run.text.trim(), ids)
+            })
+            .collect();
+        assert_eq!(
+            anchors,
+            vec![
+                ("One", vec!["s1", "p1"]),
+                ("two", vec!["mid"]),
+                ("Three", vec!["empty"]),
+                ("four", vec!["old"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn styler_attaches_figcaption_to_figure_images() {
+        let styler = Styler::new(StyleConfig::default());
+        let chapter = styler
+            .style_chapter(
+                "<figure><img src=\"a.png\" alt=\"A\"/><figcaption>Figure <em>one</em>\n</figcaption></figure>\
+                 <img src=\"b.png\"/>",
+            )
+            .expect("style should succeed");
+        let images: Vec<&StyledImage> = chapter
+            .iter()
+            .filter_map(|item| match item {
+                StyledEventOrRun::Image(image) => Some(image),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].caption.as_deref(), Some("Figure one"));
+        assert_eq!(images[1].caption, None);
+        assert!(chapter.runs().any(|run| run.text.contains("Figure")));
+    }
+
     #[test]
     fn styler_respects_stylesheet_precedence_order() {
         let mut styler = Styler::new(StyleConfig::default());
@@ -2061,6 +2785,35 @@ mod tests {
         assert_eq!(first.style.size_px, 18.0);
     }
 
+    #[test]
+    fn styler_resolves_points_against_device_dpi() {
+        let size_at = |dpi: f32| {
+            let mut styler = Styler::new(StyleConfig {
+                hints: LayoutHints {
+                    dpi,
+                    max_font_size_px: 100.0,
+                    ..LayoutHints::default()
+                },
+                ..StyleConfig::default()
+            });
+            styler
+                .load_stylesheets(&ChapterStylesheets {
+                    sources: vec![StylesheetSource {
+                        href: "a.css".to_string(),
+                        css: "p { font-size: 9pt; }".to_string(),
+                    }],
+                })
+                .expect("load should succeed");
+            let chapter = styler
+                .style_chapter("<p>Hello</p>")
+                .expect("style should succeed");
+            let size = chapter.runs().next().expect("expected run").style.size_px;
+            size
+        };
+        assert_eq!(size_at(96.0), 12.0);
+        assert_eq!(size_at(288.0), 36.0);
+    }
+
     #[test]
     fn styler_enforces_css_byte_limit() {
         let mut styler = Styler::new(StyleConfig {
@@ -2196,6 +2949,7 @@ mod tests {
             line_height: 1.4,
             letter_spacing: 0.0,
             block_role: BlockRole::Body,
+            color: None,
         };
         let trace = resolver.resolve_with_trace(&style);
         assert_eq!(trace.face.family, "serif");
@@ -2234,6 +2988,7 @@ mod tests {
             line_height: 1.4,
             letter_spacing: 0.0,
             block_role: BlockRole::Body,
+            color: None,
         };
         let trace = resolver.resolve_with_trace(&style);
         let chosen = trace.face.embedded.expect("should match embedded");
@@ -2251,6 +3006,7 @@ mod tests {
             line_height: 1.4,
             letter_spacing: 0.0,
             block_role: BlockRole::Body,
+            color: None,
         };
         let trace = resolver.resolve_with_trace_for_text(&style, Some("Привет"));
         assert!(trace
@@ -2284,6 +3040,7 @@ mod tests {
             line_height: 1.4,
             letter_spacing: 0.0,
             block_role: BlockRole::Body,
+            color: None,
         };
         let trace = resolver.resolve_with_trace(&style);
         assert!(trace.face.embedded.is_some());
@@ -12,12 +12,15 @@ use quick_xml::events::Event;
 use quick_xml::reader::Reader;
 
 use crate::error::EpubError;
+#[cfg(feature = "std")]
+use crate::io::{IoErrorInfo, IoOperation};
 
 /// Maximum number of spine items (fixed-size constraint)
 const MAX_SPINE_ITEMS: usize = 256;
 
 /// A single item in the EPUB spine (chapter reference)
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SpineItem {
     /// Manifest item this spine entry references
     pub idref: String,
@@ -33,6 +36,7 @@ pub struct SpineItem {
 ///
 /// Tracks the ordered list of chapter IDs and provides navigation.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Spine {
     /// Ordered spine entries
     items: Vec<SpineItem>,
@@ -336,7 +340,7 @@ pub fn parse_spine(content: &[u8]) -> Result<Spine, EpubError> {
 /// Parse spine from a file-backed OPF stream.
 pub fn parse_spine_file<P: AsRef<std::path::Path>>(path: P) -> Result<Spine, EpubError> {
     let file = std::fs::File::open(path)
-        .map_err(|e| EpubError::Io(format!("Failed to open OPF: {}", e)))?;
+        .map_err(|e| EpubError::Io(IoErrorInfo::from_std(&e, IoOperation::Open)))?;
     let reader = std::io::BufReader::new(file);
     parse_spine_reader(reader)
 }
@@ -675,7 +679,7 @@ mod tests {
 
     #[test]
     fn test_from_idrefs_empty() {
-        let spine = Spine::from_idrefs(alloc::vec![]);
+        let spine = Spine::from_idrefs(Vec::with_capacity(0));
         assert!(spine.is_empty());
         assert_eq!(spine.len(), 0);
         assert_eq!(spine.current_id(), None);
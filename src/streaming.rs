@@ -58,6 +58,7 @@ impl ScratchBuffers {
 ///
 /// Prevents single large allocations by breaking work into smaller chunks.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChunkLimits {
     /// Maximum bytes to process in a single read operation.
     pub max_read_chunk: usize,
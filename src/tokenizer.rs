@@ -6,6 +6,7 @@
 
 extern crate alloc;
 
+use alloc::boxed::Box;
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
@@ -58,6 +59,42 @@ pub enum TokenizeError {
     ParseError(String),
     /// Invalid HTML structure
     InvalidStructure(String),
+    /// Failure at a byte offset into the input
+    At {
+        /// Reader position when the failure was detected.
+        offset: usize,
+        /// The failure itself.
+        error: Box<TokenizeError>,
+    },
+}
+
+impl TokenizeError {
+    /// Byte offset into the input where tokenizing stopped, when known.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            TokenizeError::At { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// Stable machine-readable code, listed in `mu_epub::error::ERROR_CODES`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TokenizeError::ParseError(_) => "TOKENIZE_PARSE_ERROR",
+            TokenizeError::InvalidStructure(_) => "TOKENIZE_INVALID_STRUCTURE",
+            TokenizeError::At { error, .. } => error.code(),
+        }
+    }
+
+    fn at(self, reader: &Reader<&[u8]>) -> Self {
+        match self {
+            TokenizeError::At { .. } => self,
+            error => TokenizeError::At {
+                offset: usize::try_from(reader.buffer_position()).unwrap_or(usize::MAX),
+                error: Box::new(error),
+            },
+        }
+    }
 }
 
 impl core::fmt::Display for TokenizeError {
@@ -65,6 +102,7 @@ impl core::fmt::Display for TokenizeError {
         match self {
             TokenizeError::ParseError(msg) => write!(f, "Parse error: {}", msg),
             TokenizeError::InvalidStructure(msg) => write!(f, "Invalid structure: {}", msg),
+            TokenizeError::At { offset, error } => write!(f, "{} at byte {}", error, offset),
         }
     }
 }
@@ -95,6 +133,7 @@ pub fn tokenize_html(html: &str) -> Result<Vec<Token>, TokenizeError> {
 
 /// Limits for bounded tokenization to prevent unbounded Vec growth.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TokenizeLimits {
     /// Maximum number of tokens to emit before returning an error.
     pub max_tokens: usize,
@@ -155,7 +194,13 @@ pub fn tokenize_html_limited(
     let mut reader = Reader::from_str(html);
     reader.config_mut().trim_text(false);
     reader.config_mut().expand_empty_elements = false;
+    tokenize_limited_events(&mut reader, limits).map_err(|err| err.at(&reader))
+}
 
+fn tokenize_limited_events(
+    reader: &mut Reader<&[u8]>,
+    limits: TokenizeLimits,
+) -> Result<Vec<Token>, TokenizeError> {
     let mut buf = Vec::with_capacity(0);
     let mut tokens = Vec::with_capacity(limits.max_tokens.min(1024));
 
@@ -173,7 +218,7 @@ pub fn tokenize_html_limited(
     loop {
         match reader.read_event_into(&mut buf) {
             Ok(Event::Start(e)) => {
-                let name = decode_name(e.name().as_ref(), &reader)?;
+                let name = decode_name(e.name().as_ref(), reader)?;
 
                 // Check if we should skip this element and its children
                 if should_skip_element(&name) {
@@ -291,7 +336,7 @@ pub fn tokenize_html_limited(
                         token_count += 1;
                     }
                     "a" => {
-                        if let Some(href) = get_attribute(&e, &reader, "href") {
+                        if let Some(href) = get_attribute(&e, reader, "href") {
                             element_stack.push(ElementType::Link);
                             if token_count >= limits.max_tokens {
                                 return Err(TokenizeError::InvalidStructure(format!(
@@ -308,8 +353,8 @@ pub fn tokenize_html_limited(
                     }
                     "img" => {
                         // <img> as a start tag (non-self-closing)
-                        if let Some(src) = get_attribute(&e, &reader, "src") {
-                            let alt = get_attribute(&e, &reader, "alt").unwrap_or_default();
+                        if let Some(src) = get_attribute(&e, reader, "src") {
+                            let alt = get_attribute(&e, reader, "alt").unwrap_or_default();
                             if token_count >= limits.max_tokens {
                                 return Err(TokenizeError::InvalidStructure(format!(
                                     "Token count exceeds max_tokens ({}",
@@ -364,7 +409,7 @@ pub fn tokenize_html_limited(
                 }
             }
             Ok(Event::End(e)) => {
-                let name = decode_name(e.name().as_ref(), &reader)?;
+                let name = decode_name(e.name().as_ref(), reader)?;
 
                 // Check if we're ending a skip element
                 if should_skip_element(&name) {
@@ -446,7 +491,7 @@ pub fn tokenize_html_limited(
                 }
             }
             Ok(Event::Empty(e)) => {
-                let name = decode_name(e.name().as_ref(), &reader)?;
+                let name = decode_name(e.name().as_ref(), reader)?;
 
                 // Skip empty elements inside script/style blocks
                 if skip_depth > 0 {
@@ -511,8 +556,8 @@ pub fn tokenize_html_limited(
                         }
                     }
                     "img" => {
-                        if let Some(src) = get_attribute(&e, &reader, "src") {
-                            let alt = get_attribute(&e, &reader, "alt").unwrap_or_default();
+                        if let Some(src) = get_attribute(&e, reader, "src") {
+                            let alt = get_attribute(&e, reader, "alt").unwrap_or_default();
                             if token_count >= limits.max_tokens {
                                 return Err(TokenizeError::InvalidStructure(format!(
                                     "Token count exceeds max_tokens ({}",
@@ -956,7 +1001,14 @@ pub fn tokenize_html_with_scratch(
     let mut reader = Reader::from_str(html);
     reader.config_mut().trim_text(false);
     reader.config_mut().expand_empty_elements = false;
+    tokenize_scratch_events(&mut reader, tokens_out, scratch).map_err(|err| err.at(&reader))
+}
 
+fn tokenize_scratch_events(
+    reader: &mut Reader<&[u8]>,
+    tokens_out: &mut Vec<Token>,
+    scratch: &mut TokenizeScratch,
+) -> Result<(), TokenizeError> {
     // Track if we're inside a tag that should be skipped (script, style, head)
     let mut skip_depth: usize = 0;
     // Track if we need a paragraph break after current block element
@@ -967,7 +1019,7 @@ pub fn tokenize_html_with_scratch(
     loop {
         match reader.read_event_into(&mut scratch.xml_buf) {
             Ok(Event::Start(e)) => {
-                let name = decode_name(e.name().as_ref(), &reader)?;
+                let name = decode_name(e.name().as_ref(), reader)?;
 
                 // Check if we should skip this element and its children
                 if should_skip_element(&name) {
@@ -1028,7 +1080,7 @@ pub fn tokenize_html_with_scratch(
                         tokens_out.push(Token::ListItemStart);
                     }
                     "a" => {
-                        if let Some(href) = get_attribute(&e, &reader, "href") {
+                        if let Some(href) = get_attribute(&e, reader, "href") {
                             scratch.element_buf.push(ElementType::Link);
                             tokens_out.push(Token::LinkStart(href));
                         } else {
@@ -1038,8 +1090,8 @@ pub fn tokenize_html_with_scratch(
                     }
                     "img" => {
                         // <img> as a start tag (non-self-closing)
-                        if let Some(src) = get_attribute(&e, &reader, "src") {
-                            let alt = get_attribute(&e, &reader, "alt").unwrap_or_default();
+                        if let Some(src) = get_attribute(&e, reader, "src") {
+                            let alt = get_attribute(&e, reader, "alt").unwrap_or_default();
                             tokens_out.push(Token::Image { src, alt });
                         }
                         scratch.element_buf.push(ElementType::Generic);
@@ -1073,7 +1125,7 @@ pub fn tokenize_html_with_scratch(
                 }
             }
             Ok(Event::End(e)) => {
-                let name = decode_name(e.name().as_ref(), &reader)?;
+                let name = decode_name(e.name().as_ref(), reader)?;
 
                 // Check if we're ending a skip element
                 if should_skip_element(&name) {
@@ -1120,7 +1172,7 @@ pub fn tokenize_html_with_scratch(
                 }
             }
             Ok(Event::Empty(e)) => {
-                let name = decode_name(e.name().as_ref(), &reader)?;
+                let name = decode_name(e.name().as_ref(), reader)?;
 
                 // Skip empty elements inside script/style blocks
                 if skip_depth > 0 {
@@ -1157,8 +1209,8 @@ pub fn tokenize_html_with_scratch(
                         }
                     }
                     "img" => {
-                        if let Some(src) = get_attribute(&e, &reader, "src") {
-                            let alt = get_attribute(&e, &reader, "alt").unwrap_or_default();
+                        if let Some(src) = get_attribute(&e, reader, "src") {
+                            let alt = get_attribute(&e, reader, "alt").unwrap_or_default();
                             tokens_out.push(Token::Image { src, alt });
                         }
                         // No src → skip
@@ -1421,7 +1473,7 @@ mod tests {
         let html = "<p></p>";
         let tokens = tokenize_html(html).unwrap();
         // Empty paragraph with nothing following produces no tokens
-        assert_eq!(tokens, vec![]);
+        assert!(tokens.is_empty());
     }
 
     #[test]
@@ -1708,7 +1760,7 @@ mod tests {
         let tokens = tokenize_html(html).unwrap();
 
         // No src → image is skipped
-        assert_eq!(tokens, vec![]);
+        assert!(tokens.is_empty());
     }
 
     #[test]
@@ -0,0 +1,33 @@
+//! Internal instrumentation macros.
+//!
+//! With the `tracing` feature, `phase_span!` enters a `tracing` span for one
+//! pipeline phase and `log_event!` mirrors a `log` record as a `tracing`
+//! event. Without it, spans compile to a zero-sized guard and field
+//! expressions are never evaluated.
+
+/// Guard returned by `phase_span!` when the `tracing` feature is disabled.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
+
+/// Enter a debug-level span named after a pipeline phase.
+///
+/// Bind the result (`let _span = phase_span!(...)`); the span closes when the
+/// guard drops.
+macro_rules! phase_span {
+    ($name:literal $(, $field:ident = $value:expr)* $(,)?) => {{
+        #[cfg(feature = "tracing")]
+        let guard = tracing::debug_span!($name $(, $field = $value)*).entered();
+        #[cfg(not(feature = "tracing"))]
+        let guard = $crate::trace::NoSpan;
+        guard
+    }};
+}
+
+/// Emit a `log` record, mirrored as a `tracing` event when enabled.
+macro_rules! log_event {
+    ($level:ident, $($arg:tt)+) => {{
+        log::$level!($($arg)+);
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+    }};
+}
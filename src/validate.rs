@@ -16,6 +16,7 @@ use std::path::Path;
 use quick_xml::events::Event;
 use quick_xml::reader::Reader;
 
+use crate::io::{IoErrorInfo, IoOperation};
 use crate::metadata::{parse_container_xml, parse_opf, EpubMetadata};
 use crate::navigation::{parse_nav_xhtml, parse_ncx};
 use crate::spine::Spine;
@@ -23,6 +24,7 @@ use crate::zip::{StreamingZip, ZipLimits};
 
 /// Severity level for a validation diagnostic.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ValidationSeverity {
     /// Violates a required structural expectation.
     Error,
@@ -32,6 +34,7 @@ pub enum ValidationSeverity {
 
 /// Structured validation diagnostic entry.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ValidationDiagnostic {
     /// Stable machine-readable diagnostic code.
     pub code: &'static str,
@@ -77,6 +80,7 @@ impl ValidationDiagnostic {
 
 /// Validation report with all discovered diagnostics.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ValidationReport {
     diagnostics: Vec<ValidationDiagnostic>,
 }
@@ -135,7 +139,8 @@ pub fn validate_epub_file_with_options<P: AsRef<Path>>(
     path: P,
     options: ValidationOptions,
 ) -> Result<ValidationReport, crate::EpubError> {
-    let file = File::open(path).map_err(|e| crate::EpubError::Io(e.to_string()))?;
+    let file = File::open(path)
+        .map_err(|e| crate::EpubError::Io(IoErrorInfo::from_std(&e, IoOperation::Open)))?;
     Ok(validate_epub_reader_with_options(file, options))
 }
 
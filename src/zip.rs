@@ -1,30 +1,46 @@
 //! Streaming ZIP reader for EPUB files
 //!
 //! Memory-efficient ZIP reader that streams files without loading entire archive.
-//! Uses fixed-size central directory cache (max 256 entries, ~4KB).
+//! Uses fixed-size central directory cache (max 256 entries, ~4KB) by default;
+//! [`ZipLimits::with_max_cd_entries`] opts into a heap-backed directory for
+//! larger archives. Forward-only streams open through
+//! [`StreamingZip::new_sequential`].
 //! Supports DEFLATE decompression using miniz_oxide.
 
 extern crate alloc;
 
+use crate::io::{IoError, IoErrorInfo, IoOperation, MemoryReader, Read, Seek, SeekFrom, Write};
+use crate::memory::{MemoryGauge, MemorySubsystem};
+use alloc::format;
 use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
 use heapless::Vec as HeaplessVec;
 use log;
 use miniz_oxide::{DataFormat, MZFlush, MZStatus};
-use std::io::{Read, Seek, SeekFrom, Write};
 
 #[cfg(target_os = "espidf")]
 const DEFAULT_ZIP_SCRATCH_BYTES: usize = 2 * 1024;
 #[cfg(not(target_os = "espidf"))]
 const DEFAULT_ZIP_SCRATCH_BYTES: usize = 8 * 1024;
 
-/// Maximum number of central directory entries to cache
+/// Maximum number of central directory entries held in the fixed cache
 const MAX_CD_ENTRIES: usize = 256;
 
 /// Maximum filename length in ZIP entries
 const MAX_FILENAME_LEN: usize = 256;
 
+/// Spool cap for forward-only archives when [`ZipLimits::max_archive_bytes`]
+/// is unset.
+#[cfg(target_os = "espidf")]
+const DEFAULT_MAX_ARCHIVE_BYTES: u64 = 4 * 1024 * 1024;
+#[cfg(not(target_os = "espidf"))]
+const DEFAULT_MAX_ARCHIVE_BYTES: u64 = 64 * 1024 * 1024;
+
 /// Runtime-configurable ZIP safety limits.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ZipLimits {
     /// Maximum compressed or uncompressed file size allowed for reads.
     pub max_file_read_size: usize,
@@ -34,6 +50,20 @@ pub struct ZipLimits {
     pub strict: bool,
     /// Maximum bytes scanned from file tail while searching for EOCD.
     pub max_eocd_scan: usize,
+    /// Maximum central directory entries to index.
+    ///
+    /// Up to 256 entries use the fixed inline cache; larger values index
+    /// entries in a heap-backed directory instead.
+    pub max_cd_entries: usize,
+    /// Maximum uncompressed-to-compressed size ratio per entry, e.g. `100`
+    /// for 100:1. `None` leaves the ratio unchecked.
+    pub max_compression_ratio: Option<u32>,
+    /// Maximum decompressed bytes across all reads from one archive.
+    /// `None` leaves the total unbounded.
+    pub max_total_decompressed: Option<u64>,
+    /// Maximum bytes a forward-only archive may spool into memory.
+    /// `None` uses a 64 MiB default (4 MiB on ESP-IDF).
+    pub max_archive_bytes: Option<u64>,
 }
 
 impl ZipLimits {
@@ -44,6 +74,10 @@ impl ZipLimits {
             max_mimetype_size,
             strict: false,
             max_eocd_scan: MAX_EOCD_SCAN,
+            max_cd_entries: MAX_CD_ENTRIES,
+            max_compression_ratio: None,
+            max_total_decompressed: None,
+            max_archive_bytes: None,
         }
     }
 
@@ -58,6 +92,65 @@ impl ZipLimits {
         self.max_eocd_scan = max_eocd_scan.max(EOCD_MIN_SIZE);
         self
     }
+
+    /// Set how many central directory entries to index.
+    ///
+    /// Values above the 256-entry fixed cache switch to a heap-backed
+    /// directory, for hosts that can afford to index every entry of large
+    /// (e.g. image-heavy) archives.
+    pub fn with_max_cd_entries(mut self, max_cd_entries: usize) -> Self {
+        self.max_cd_entries = max_cd_entries;
+        self
+    }
+
+    /// Cap how far any entry may inflate relative to its compressed size.
+    ///
+    /// Enforced on the bytes actually produced, so entries that understate
+    /// their uncompressed size still stop at the cap.
+    pub fn with_max_compression_ratio(mut self, ratio: u32) -> Self {
+        self.max_compression_ratio = Some(ratio);
+        self
+    }
+
+    /// Cap the decompressed bytes all reads from one archive may produce.
+    pub fn with_max_total_decompressed(mut self, bytes: u64) -> Self {
+        self.max_total_decompressed = Some(bytes);
+        self
+    }
+
+    /// Cap the bytes [`StreamingZip::new_sequential_with_limits`] spools
+    /// from a forward-only stream.
+    pub fn with_max_archive_bytes(mut self, bytes: u64) -> Self {
+        self.max_archive_bytes = Some(bytes);
+        self
+    }
+}
+
+/// Output caps for one entry read, from [`ZipLimits`] zip-bomb settings.
+#[derive(Clone, Copy, Debug)]
+struct OutputBudget {
+    /// Most bytes the compression ratio lets this entry inflate to.
+    ratio_cap: u64,
+    /// Bytes left in the archive-wide decompression budget.
+    remaining: u64,
+}
+
+impl OutputBudget {
+    const UNLIMITED: Self = Self {
+        ratio_cap: u64::MAX,
+        remaining: u64::MAX,
+    };
+
+    /// Fail once an entry has produced more than `produced` allows.
+    fn check(&self, produced: u64) -> Result<(), ZipError> {
+        if produced > self.ratio_cap {
+            return Err(ZipError::CompressionRatioExceeded);
+        }
+        if produced > self.remaining {
+            return Err(ZipError::DecompressBudgetExceeded);
+        }
+        Ok(())
+    }
 }
 
 /// Local file header signature (little-endian)
@@ -72,18 +165,28 @@ const SIG_EOCD: u32 = 0x06054b50;
 const SIG_ZIP64_EOCD: u32 = 0x06064b50;
 /// ZIP64 end of central directory locator signature (little-endian)
 const SIG_ZIP64_EOCD_LOCATOR: u32 = 0x07064b50;
+/// Optional data descriptor signature (little-endian)
+const SIG_DATA_DESCRIPTOR: u32 = 0x08074b50;
+/// General purpose flag: sizes and CRC follow the data in a data descriptor
+const FLAG_DATA_DESCRIPTOR: u16 = 0x0008;
 /// Minimum EOCD record size in bytes
 const EOCD_MIN_SIZE: usize = 22;
 /// Maximum EOCD search window (EOCD + max comment length)
 const MAX_EOCD_SCAN: usize = EOCD_MIN_SIZE + u16::MAX as usize;
 
 /// Compression methods
-const METHOD_STORED: u16 = 0;
+pub(crate) const METHOD_STORED: u16 = 0;
 const METHOD_DEFLATED: u16 = 8;
 
 // Re-export the crate's public ZIP error alias for module consumers.
 pub use crate::error::ZipError;
 
+/// Map a reader or writer failure to `ZipError::IoError` with where it
+/// happened.
+fn io_error(operation: IoOperation, offset: Option<u64>) -> impl FnOnce(IoError) -> ZipError {
+    move |err| ZipError::IoError(IoErrorInfo::new(err, operation, offset))
+}
+
 #[derive(Clone, Copy, Debug)]
 struct EocdInfo {
     cd_offset: u64,
@@ -113,6 +216,8 @@ pub struct CdEntry {
     pub local_header_offset: u64,
     /// CRC32 checksum
     pub crc32: u32,
+    /// General purpose bit flags
+    pub flags: u16,
     /// Filename (max 255 chars)
     pub filename: String,
 }
@@ -126,21 +231,164 @@ impl CdEntry {
             uncompressed_size: 0,
             local_header_offset: 0,
             crc32: 0,
+            flags: 0,
             filename: String::with_capacity(0),
         }
     }
+
+    /// Whether the entry's CRC and sizes trail its data in a data
+    /// descriptor (flag bit 3), leaving the local header zeroed.
+    pub fn has_data_descriptor(&self) -> bool {
+        self.flags & FLAG_DATA_DESCRIPTOR != 0
+    }
+}
+
+/// CRC to check an entry's data against.
+///
+/// For entries with a data descriptor this is the descriptor's CRC, read
+/// from `file` positioned just past the compressed data at `offset`. An
+/// archive truncated before the descriptor falls back to the central
+/// directory CRC; any other read failure is returned.
+fn expected_crc<R: Read>(
+    file: &mut R,
+    crc32: u32,
+    data_descriptor: bool,
+    offset: u64,
+) -> Result<u32, ZipError> {
+    if !data_descriptor {
+        return Ok(crc32);
+    }
+    let mut words = [0u8; 8];
+    match file.read_exact(&mut words) {
+        Ok(()) => {}
+        Err(err) if err.kind() == IoError::UnexpectedEof => return Ok(crc32),
+        Err(err) => return Err(io_error(IoOperation::Read, Some(offset))(err)),
+    }
+    let first = u32::from_le_bytes([words[0], words[1], words[2], words[3]]);
+    Ok(if first == SIG_DATA_DESCRIPTOR {
+        u32::from_le_bytes([words[4], words[5], words[6], words[7]])
+    } else {
+        first
+    })
+}
+
+/// Parsed central directory entries, plus a filename index sorted in
+/// lookup order (see [`cmp_entry_names`]).
+// The fixed cache stays inline on purpose: embedded builds rely on it not
+// being a heap allocation.
+#[allow(clippy::large_enum_variant)]
+enum CentralDirectory {
+    /// Fixed inline cache of at most `MAX_CD_ENTRIES` entries.
+    Fixed {
+        entries: HeaplessVec<CdEntry, MAX_CD_ENTRIES>,
+        index: HeaplessVec<u32, MAX_CD_ENTRIES>,
+    },
+    /// Heap-backed list for archives indexed beyond the fixed cache.
+    Heap {
+        entries: Vec<CdEntry>,
+        index: Vec<u32>,
+    },
+}
+
+impl CentralDirectory {
+    /// Storage able to index `max_entries`, expecting about `expected` of them.
+    fn with_max_entries(max_entries: usize, expected: u64) -> Self {
+        if max_entries <= MAX_CD_ENTRIES {
+            return Self::Fixed {
+                entries: HeaplessVec::new(),
+                index: HeaplessVec::new(),
+            };
+        }
+        // `expected` comes from the archive; only trust it up to the fixed
+        // cache size and let the vector grow from there.
+        let capacity = core::cmp::min(expected, MAX_CD_ENTRIES as u64) as usize;
+        Self::Heap {
+            entries: Vec::with_capacity(capacity),
+            index: Vec::with_capacity(0),
+        }
+    }
+
+    fn push(&mut self, entry: CdEntry) -> Result<(), ZipError> {
+        match self {
+            Self::Fixed { entries, .. } => {
+                entries.push(entry).map_err(|_| ZipError::CentralDirFull)
+            }
+            Self::Heap { entries, .. } => {
+                entries.push(entry);
+                Ok(())
+            }
+        }
+    }
+
+    /// Sort the filename index once every entry has been pushed.
+    fn build_index(&mut self) -> Result<(), ZipError> {
+        let count = u32::try_from(self.as_slice().len()).map_err(|_| ZipError::CentralDirFull)?;
+        let (entries, index): (&[CdEntry], &mut [u32]) = match self {
+            Self::Fixed { entries, index } => {
+                index.clear();
+                for position in 0..count {
+                    index.push(position).map_err(|_| ZipError::CentralDirFull)?;
+                }
+                (entries, index)
+            }
+            Self::Heap { entries, index } => {
+                index.clear();
+                index.extend(0..count);
+                (entries, index)
+            }
+        };
+        // Ties keep central directory order, so the first match wins as
+        // with a linear scan.
+        index.sort_unstable_by(|&a, &b| {
+            cmp_entry_names(&entries[a as usize].filename, &entries[b as usize].filename)
+                .then(a.cmp(&b))
+        });
+        Ok(())
+    }
+
+    /// Entry matching `name` in lookup order, by binary search.
+    fn find(&self, name: &str) -> Option<&CdEntry> {
+        let (entries, index): (&[CdEntry], &[u32]) = match self {
+            Self::Fixed { entries, index } => (entries, index),
+            Self::Heap { entries, index } => (entries, index),
+        };
+        let position = index.partition_point(|&i| {
+            cmp_entry_names(&entries[i as usize].filename, name) == Ordering::Less
+        });
+        let entry = &entries[*index.get(position)? as usize];
+        (cmp_entry_names(&entry.filename, name) == Ordering::Equal).then_some(entry)
+    }
+
+    fn as_slice(&self) -> &[CdEntry] {
+        match self {
+            Self::Fixed { entries, .. } => entries,
+            Self::Heap { entries, .. } => entries,
+        }
+    }
+}
+
+/// Entry name order used for lookups: ASCII case-insensitive, ignoring one
+/// leading `/` on either side.
+fn cmp_entry_names(a: &str, b: &str) -> Ordering {
+    let a = a.strip_prefix('/').unwrap_or(a);
+    let b = b.strip_prefix('/').unwrap_or(b);
+    a.bytes()
+        .map(|byte| byte.to_ascii_lowercase())
+        .cmp(b.bytes().map(|byte| byte.to_ascii_lowercase()))
 }
 
 /// Streaming ZIP file reader
 pub struct StreamingZip<F: Read + Seek> {
     /// File handle
     file: F,
-    /// Central directory entries (fixed size)
-    entries: HeaplessVec<CdEntry, MAX_CD_ENTRIES>,
+    /// Central directory entries (fixed cache unless limits opt into heap)
+    entries: CentralDirectory,
     /// Number of entries in central directory
     num_entries: usize,
     /// Optional configurable resource/safety limits.
     limits: Option<ZipLimits>,
+    /// Decompressed bytes produced so far, against `max_total_decompressed`.
+    decompressed_bytes: u64,
 }
 
 impl<F: Read + Seek> StreamingZip<F> {
@@ -157,31 +405,34 @@ impl<F: Read + Seek> StreamingZip<F> {
             .unwrap_or(MAX_EOCD_SCAN);
         let eocd = Self::find_eocd(&mut file, max_eocd_scan)?;
         let strict = limits.is_some_and(|l| l.strict);
-        if strict && eocd.num_entries > MAX_CD_ENTRIES as u64 {
+        let max_entries = limits.map(|l| l.max_cd_entries).unwrap_or(MAX_CD_ENTRIES);
+        if strict && eocd.num_entries > max_entries as u64 {
             return Err(ZipError::CentralDirFull);
         }
 
-        let mut entries: HeaplessVec<CdEntry, MAX_CD_ENTRIES> = HeaplessVec::new();
+        let mut entries = CentralDirectory::with_max_entries(max_entries, eocd.num_entries);
 
         // Parse central directory entries
         file.seek(SeekFrom::Start(eocd.cd_offset))
-            .map_err(|_| ZipError::IoError)?;
+            .map_err(io_error(IoOperation::Seek, Some(eocd.cd_offset)))?;
         let cd_end = eocd
             .cd_offset
             .checked_add(eocd.cd_size)
             .ok_or(ZipError::InvalidFormat)?;
 
-        let entries_to_scan = core::cmp::min(eocd.num_entries, MAX_CD_ENTRIES as u64);
+        let entries_to_scan = core::cmp::min(eocd.num_entries, max_entries as u64);
         for _ in 0..entries_to_scan {
-            let pos = file.stream_position().map_err(|_| ZipError::IoError)?;
+            let pos = file
+                .stream_position()
+                .map_err(io_error(IoOperation::Seek, None))?;
             if pos >= cd_end {
                 if strict {
                     return Err(ZipError::InvalidFormat);
                 }
                 break;
             }
-            if let Some(entry) = Self::read_cd_entry(&mut file)? {
-                entries.push(entry).map_err(|_| ZipError::CentralDirFull)?;
+            if let Some(entry) = Self::read_cd_entry(&mut file, pos)? {
+                entries.push(entry)?;
             } else if strict {
                 return Err(ZipError::InvalidFormat);
             } else {
@@ -189,33 +440,39 @@ impl<F: Read + Seek> StreamingZip<F> {
             }
         }
 
-        if eocd.num_entries > MAX_CD_ENTRIES as u64 {
-            log::warn!(
+        if eocd.num_entries > max_entries as u64 {
+            log_event!(
+                warn,
                 "[ZIP] Archive has {} entries but only {} were loaded (max: {})",
                 eocd.num_entries,
-                entries.len(),
-                MAX_CD_ENTRIES
+                entries.as_slice().len(),
+                max_entries
             );
         }
 
-        log::debug!(
+        log_event!(
+            debug,
             "[ZIP] Parsed {} central directory entries (offset {})",
-            entries.len(),
+            entries.as_slice().len(),
             eocd.cd_offset
         );
 
+        entries.build_index()?;
         Ok(Self {
             file,
             entries,
             num_entries: core::cmp::min(eocd.num_entries, usize::MAX as u64) as usize,
             limits,
+            decompressed_bytes: 0,
         })
     }
 
     /// Find EOCD and extract central directory info
     fn find_eocd(file: &mut F, max_eocd_scan: usize) -> Result<EocdInfo, ZipError> {
         // Get file size
-        let file_size = file.seek(SeekFrom::End(0)).map_err(|_| ZipError::IoError)?;
+        let file_size = file
+            .seek(SeekFrom::End(0))
+            .map_err(io_error(IoOperation::Seek, None))?;
 
         if file_size < EOCD_MIN_SIZE as u64 {
             return Err(ZipError::InvalidFormat);
@@ -225,9 +482,12 @@ impl<F: Read + Seek> StreamingZip<F> {
         let scan_range = file_size.min(max_eocd_scan as u64) as usize;
         let mut buffer = alloc::vec![0u8; scan_range];
 
-        file.seek(SeekFrom::Start(file_size - scan_range as u64))
-            .map_err(|_| ZipError::IoError)?;
-        let bytes_read = file.read(&mut buffer).map_err(|_| ZipError::IoError)?;
+        let scan_start = file_size - scan_range as u64;
+        file.seek(SeekFrom::Start(scan_start))
+            .map_err(io_error(IoOperation::Seek, Some(scan_start)))?;
+        let bytes_read = file
+            .read(&mut buffer)
+            .map_err(io_error(IoOperation::Read, Some(scan_start)))?;
         let scan_base = file_size - bytes_read as u64;
 
         // Scan backwards for EOCD signature
@@ -251,10 +511,10 @@ impl<F: Read + Seek> StreamingZip<F> {
                 let mut zip64_locator: Option<(u32, u64, u32)> = None;
                 if eocd_pos >= 20 {
                     file.seek(SeekFrom::Start(eocd_pos - 20))
-                        .map_err(|_| ZipError::IoError)?;
+                        .map_err(io_error(IoOperation::Seek, Some(eocd_pos - 20)))?;
                     let mut locator = [0u8; 20];
                     file.read_exact(&mut locator)
-                        .map_err(|_| ZipError::IoError)?;
+                        .map_err(io_error(IoOperation::Read, Some(eocd_pos - 20)))?;
                     if u32::from_le_bytes([locator[0], locator[1], locator[2], locator[3]])
                         == SIG_ZIP64_EOCD_LOCATOR
                     {
@@ -324,9 +584,10 @@ impl<F: Read + Seek> StreamingZip<F> {
 
     fn read_zip64_eocd(file: &mut F, offset: u64) -> Result<Zip64EocdInfo, ZipError> {
         file.seek(SeekFrom::Start(offset))
-            .map_err(|_| ZipError::IoError)?;
+            .map_err(io_error(IoOperation::Seek, Some(offset)))?;
         let mut fixed = [0u8; 56];
-        file.read_exact(&mut fixed).map_err(|_| ZipError::IoError)?;
+        file.read_exact(&mut fixed)
+            .map_err(io_error(IoOperation::Read, Some(offset)))?;
 
         let sig = u32::from_le_bytes([fixed[0], fixed[1], fixed[2], fixed[3]]);
         if sig != SIG_ZIP64_EOCD {
@@ -361,8 +622,8 @@ impl<F: Read + Seek> StreamingZip<F> {
         })
     }
 
-    /// Read a central directory entry from file
-    fn read_cd_entry(file: &mut F) -> Result<Option<CdEntry>, ZipError> {
+    /// Read the central directory entry starting at `offset`
+    fn read_cd_entry(file: &mut F, offset: u64) -> Result<Option<CdEntry>, ZipError> {
         let mut sig_buf = [0u8; 4];
         if file.read_exact(&mut sig_buf).is_err() {
             return Ok(None);
@@ -376,13 +637,15 @@ impl<F: Read + Seek> StreamingZip<F> {
         // Read fixed portion of central directory entry (42 bytes = offsets 4-45)
         // This includes everything up to and including the local header offset
         let mut buf = [0u8; 42];
-        file.read_exact(&mut buf).map_err(|_| ZipError::IoError)?;
+        file.read_exact(&mut buf)
+            .map_err(io_error(IoOperation::Read, Some(offset)))?;
 
         let mut entry = CdEntry::new();
 
         // Parse central directory entry fields
         // buf contains bytes 4-49 of the CD entry (after the 4-byte signature)
         // buf[N] corresponds to CD entry offset (N + 4)
+        entry.flags = u16::from_le_bytes([buf[4], buf[5]]); // CD offset 8
         entry.method = u16::from_le_bytes([buf[6], buf[7]]); // CD offset 10
         entry.crc32 = u32::from_le_bytes([buf[12], buf[13], buf[14], buf[15]]); // CD offset 16
         let compressed_size_32 = u32::from_le_bytes([buf[16], buf[17], buf[18], buf[19]]); // CD offset 20
@@ -399,12 +662,12 @@ impl<F: Read + Seek> StreamingZip<F> {
         if name_len > 0 && name_len <= MAX_FILENAME_LEN {
             let mut name_buf = alloc::vec![0u8; name_len];
             file.read_exact(&mut name_buf)
-                .map_err(|_| ZipError::IoError)?;
+                .map_err(io_error(IoOperation::Read, Some(offset)))?;
             entry.filename = String::from_utf8_lossy(&name_buf).to_string();
         } else if name_len > MAX_FILENAME_LEN {
             // Skip over filename bytes we can't store
             file.seek(SeekFrom::Current(name_len as i64))
-                .map_err(|_| ZipError::IoError)?;
+                .map_err(io_error(IoOperation::Seek, Some(offset)))?;
         }
 
         let needs_zip64_uncompressed = uncompressed_size_32 == u32::MAX;
@@ -418,7 +681,8 @@ impl<F: Read + Seek> StreamingZip<F> {
         let mut extra_remaining = extra_len;
         while extra_remaining >= 4 {
             let mut hdr = [0u8; 4];
-            file.read_exact(&mut hdr).map_err(|_| ZipError::IoError)?;
+            file.read_exact(&mut hdr)
+                .map_err(io_error(IoOperation::Read, Some(offset)))?;
             let header_id = u16::from_le_bytes([hdr[0], hdr[1]]);
             let field_size = u16::from_le_bytes([hdr[2], hdr[3]]) as usize;
             extra_remaining -= 4;
@@ -434,7 +698,8 @@ impl<F: Read + Seek> StreamingZip<F> {
                         return Err(ZipError::InvalidFormat);
                     }
                     let mut val = [0u8; 8];
-                    file.read_exact(&mut val).map_err(|_| ZipError::IoError)?;
+                    file.read_exact(&mut val)
+                        .map_err(io_error(IoOperation::Read, Some(offset)))?;
                     entry.uncompressed_size = u64::from_le_bytes(val);
                     got_zip64_uncompressed = true;
                     field_remaining -= 8;
@@ -444,7 +709,8 @@ impl<F: Read + Seek> StreamingZip<F> {
                         return Err(ZipError::InvalidFormat);
                     }
                     let mut val = [0u8; 8];
-                    file.read_exact(&mut val).map_err(|_| ZipError::IoError)?;
+                    file.read_exact(&mut val)
+                        .map_err(io_error(IoOperation::Read, Some(offset)))?;
                     entry.compressed_size = u64::from_le_bytes(val);
                     got_zip64_compressed = true;
                     field_remaining -= 8;
@@ -454,24 +720,25 @@ impl<F: Read + Seek> StreamingZip<F> {
                         return Err(ZipError::InvalidFormat);
                     }
                     let mut val = [0u8; 8];
-                    file.read_exact(&mut val).map_err(|_| ZipError::IoError)?;
+                    file.read_exact(&mut val)
+                        .map_err(io_error(IoOperation::Read, Some(offset)))?;
                     entry.local_header_offset = u64::from_le_bytes(val);
                     got_zip64_offset = true;
                     field_remaining -= 8;
                 }
                 if field_remaining > 0 {
                     file.seek(SeekFrom::Current(field_remaining as i64))
-                        .map_err(|_| ZipError::IoError)?;
+                        .map_err(io_error(IoOperation::Seek, Some(offset)))?;
                 }
             } else if field_size > 0 {
                 file.seek(SeekFrom::Current(field_size as i64))
-                    .map_err(|_| ZipError::IoError)?;
+                    .map_err(io_error(IoOperation::Seek, Some(offset)))?;
             }
             extra_remaining -= field_size;
         }
         if extra_remaining > 0 {
             file.seek(SeekFrom::Current(extra_remaining as i64))
-                .map_err(|_| ZipError::IoError)?;
+                .map_err(io_error(IoOperation::Seek, Some(offset)))?;
         }
 
         if (needs_zip64_uncompressed && !got_zip64_uncompressed)
@@ -483,31 +750,31 @@ impl<F: Read + Seek> StreamingZip<F> {
 
         if comment_len > 0 {
             file.seek(SeekFrom::Current(comment_len as i64))
-                .map_err(|_| ZipError::IoError)?;
+                .map_err(io_error(IoOperation::Seek, Some(offset)))?;
         }
 
         Ok(Some(entry))
     }
 
-    /// Get entry by filename (case-insensitive)
+    /// Get entry by filename (case-insensitive, ignoring a leading `/`)
+    ///
+    /// Looks the name up in an index sorted at open time, so this is
+    /// O(log n) in the number of entries.
     pub fn get_entry(&self, name: &str) -> Option<&CdEntry> {
-        self.entries.iter().find(|e| {
-            e.filename == name
-                || e.filename.eq_ignore_ascii_case(name)
-                || (name.starts_with('/') && e.filename.eq_ignore_ascii_case(&name[1..]))
-                || (e.filename.starts_with('/') && e.filename[1..].eq_ignore_ascii_case(name))
-        })
+        self.entries.find(name)
     }
 
     /// Debug: Log all entries in the ZIP (for troubleshooting)
     #[allow(dead_code)]
     fn debug_list_entries(&self) {
-        log::info!(
+        log_event!(
+            info,
             "[ZIP] Central directory contains {} entries:",
-            self.entries.len()
+            self.entries.as_slice().len()
         );
-        for (i, entry) in self.entries.iter().enumerate() {
-            log::info!(
+        for (i, entry) in self.entries.as_slice().iter().enumerate() {
+            log_event!(
+                info,
                 "[ZIP]  [{}] '{}' (method={}, compressed={}, uncompressed={})",
                 i,
                 entry.filename,
@@ -522,6 +789,7 @@ impl<F: Read + Seek> StreamingZip<F> {
     /// Returns number of bytes written to buffer
     pub fn read_file(&mut self, entry: &CdEntry, buf: &mut [u8]) -> Result<usize, ZipError> {
         let mut input_buf = alloc::vec![0u8; DEFAULT_ZIP_SCRATCH_BYTES];
+        let _gauge = MemoryGauge::with_bytes(MemorySubsystem::ZipScratch, input_buf.len());
         self.read_file_with_scratch(entry, buf, &mut input_buf)
     }
 
@@ -535,17 +803,15 @@ impl<F: Read + Seek> StreamingZip<F> {
         buf: &mut [u8],
         input_buf: &mut [u8],
     ) -> Result<usize, ZipError> {
+        let _span = phase_span!(
+            "zip.inflate",
+            method = entry.method,
+            uncompressed_size = entry.uncompressed_size
+        );
         if input_buf.is_empty() {
             return Err(ZipError::BufferTooSmall);
         }
-        if let Some(limits) = self.limits {
-            if entry.uncompressed_size > limits.max_file_read_size as u64 {
-                return Err(ZipError::FileTooLarge);
-            }
-            if entry.compressed_size > limits.max_file_read_size as u64 {
-                return Err(ZipError::FileTooLarge);
-            }
-        }
+        let budget = self.output_budget(entry)?;
         let uncompressed_size =
             usize::try_from(entry.uncompressed_size).map_err(|_| ZipError::FileTooLarge)?;
         if uncompressed_size > buf.len() {
@@ -558,7 +824,7 @@ impl<F: Read + Seek> StreamingZip<F> {
         // Seek to data
         self.file
             .seek(SeekFrom::Start(data_offset))
-            .map_err(|_| ZipError::IoError)?;
+            .map_err(io_error(IoOperation::Seek, Some(data_offset)))?;
 
         match entry.method {
             METHOD_STORED => {
@@ -568,16 +834,24 @@ impl<F: Read + Seek> StreamingZip<F> {
                 if size > buf.len() {
                     return Err(ZipError::BufferTooSmall);
                 }
+                budget.check(size as u64)?;
                 self.file
                     .read_exact(&mut buf[..size])
-                    .map_err(|_| ZipError::IoError)?;
+                    .map_err(io_error(IoOperation::Read, Some(data_offset)))?;
                 // Verify CRC32
-                if entry.crc32 != 0 {
+                let crc32 = expected_crc(
+                    &mut self.file,
+                    entry.crc32,
+                    entry.has_data_descriptor(),
+                    data_offset + entry.compressed_size,
+                )?;
+                if crc32 != 0 {
                     let calc_crc = crc32fast::hash(&buf[..size]);
-                    if calc_crc != entry.crc32 {
+                    if calc_crc != crc32 {
                         return Err(ZipError::CrcMismatch);
                     }
                 }
+                self.decompressed_bytes += size as u64;
                 Ok(size)
             }
             METHOD_DEFLATED => {
@@ -594,7 +868,13 @@ impl<F: Read + Seek> StreamingZip<F> {
                         let take = core::cmp::min(compressed_remaining, input_buf.len());
                         self.file
                             .read_exact(&mut input_buf[..take])
-                            .map_err(|_| ZipError::IoError)?;
+                            .map_err(io_error(
+                                IoOperation::Read,
+                                Some(
+                                    data_offset + entry.compressed_size
+                                        - compressed_remaining as u64,
+                                ),
+                            ))?;
                         pending = &input_buf[..take];
                         compressed_remaining -= take;
                     }
@@ -613,6 +893,7 @@ impl<F: Read + Seek> StreamingZip<F> {
                     let produced = result.bytes_written;
                     pending = &pending[consumed..];
                     written += produced;
+                    budget.check(written as u64)?;
 
                     match result.status {
                         Ok(MZStatus::StreamEnd) => {
@@ -632,12 +913,19 @@ impl<F: Read + Seek> StreamingZip<F> {
                 }
 
                 // Verify CRC32 if available
-                if entry.crc32 != 0 {
+                let crc32 = expected_crc(
+                    &mut self.file,
+                    entry.crc32,
+                    entry.has_data_descriptor(),
+                    data_offset + entry.compressed_size,
+                )?;
+                if crc32 != 0 {
                     let calc_crc = crc32fast::hash(&buf[..written]);
-                    if calc_crc != entry.crc32 {
+                    if calc_crc != crc32 {
                         return Err(ZipError::CrcMismatch);
                     }
                 }
+                self.decompressed_bytes += written as u64;
                 Ok(written)
             }
             _ => Err(ZipError::UnsupportedCompression),
@@ -654,6 +942,10 @@ impl<F: Read + Seek> StreamingZip<F> {
     ) -> Result<usize, ZipError> {
         let mut input_buf = alloc::vec![0u8; DEFAULT_ZIP_SCRATCH_BYTES];
         let mut output_buf = alloc::vec![0u8; DEFAULT_ZIP_SCRATCH_BYTES];
+        let _gauge = MemoryGauge::with_bytes(
+            MemorySubsystem::ZipScratch,
+            input_buf.len() + output_buf.len(),
+        );
         self.read_file_to_writer_with_scratch(entry, writer, &mut input_buf, &mut output_buf)
     }
 
@@ -671,22 +963,20 @@ impl<F: Read + Seek> StreamingZip<F> {
         input_buf: &mut [u8],
         output_buf: &mut [u8],
     ) -> Result<usize, ZipError> {
+        let _span = phase_span!(
+            "zip.inflate",
+            method = entry.method,
+            uncompressed_size = entry.uncompressed_size
+        );
         if input_buf.is_empty() || output_buf.is_empty() {
             return Err(ZipError::BufferTooSmall);
         }
-        if let Some(limits) = self.limits {
-            if entry.uncompressed_size > limits.max_file_read_size as u64 {
-                return Err(ZipError::FileTooLarge);
-            }
-            if entry.compressed_size > limits.max_file_read_size as u64 {
-                return Err(ZipError::FileTooLarge);
-            }
-        }
+        let budget = self.output_budget(entry)?;
 
         let data_offset = self.calc_data_offset(entry)?;
         self.file
             .seek(SeekFrom::Start(data_offset))
-            .map_err(|_| ZipError::IoError)?;
+            .map_err(io_error(IoOperation::Seek, Some(data_offset)))?;
 
         match entry.method {
             METHOD_STORED => {
@@ -695,22 +985,33 @@ impl<F: Read + Seek> StreamingZip<F> {
                 let mut hasher = crc32fast::Hasher::new();
                 let mut written = 0usize;
 
+                budget.check(remaining as u64)?;
                 while remaining > 0 {
                     let take = core::cmp::min(remaining, input_buf.len());
                     self.file
                         .read_exact(&mut input_buf[..take])
-                        .map_err(|_| ZipError::IoError)?;
+                        .map_err(io_error(
+                            IoOperation::Read,
+                            Some(data_offset + written as u64),
+                        ))?;
                     writer
                         .write_all(&input_buf[..take])
-                        .map_err(|_| ZipError::IoError)?;
+                        .map_err(io_error(IoOperation::Write, Some(written as u64)))?;
                     hasher.update(&input_buf[..take]);
                     written += take;
                     remaining -= take;
                 }
 
-                if entry.crc32 != 0 && hasher.finalize() != entry.crc32 {
+                let crc32 = expected_crc(
+                    &mut self.file,
+                    entry.crc32,
+                    entry.has_data_descriptor(),
+                    data_offset + entry.compressed_size,
+                )?;
+                if crc32 != 0 && hasher.finalize() != crc32 {
                     return Err(ZipError::CrcMismatch);
                 }
+                self.decompressed_bytes += written as u64;
                 Ok(written)
             }
             METHOD_DEFLATED => {
@@ -728,7 +1029,13 @@ impl<F: Read + Seek> StreamingZip<F> {
                         let take = core::cmp::min(compressed_remaining, input_buf.len());
                         self.file
                             .read_exact(&mut input_buf[..take])
-                            .map_err(|_| ZipError::IoError)?;
+                            .map_err(io_error(
+                                IoOperation::Read,
+                                Some(
+                                    data_offset + entry.compressed_size
+                                        - compressed_remaining as u64,
+                                ),
+                            ))?;
                         pending = &input_buf[..take];
                         compressed_remaining -= take;
                     }
@@ -744,9 +1051,10 @@ impl<F: Read + Seek> StreamingZip<F> {
                     pending = &pending[consumed..];
 
                     if produced > 0 {
+                        budget.check((written + produced) as u64)?;
                         writer
                             .write_all(&output_buf[..produced])
-                            .map_err(|_| ZipError::IoError)?;
+                            .map_err(io_error(IoOperation::Write, Some(written as u64)))?;
                         hasher.update(&output_buf[..produced]);
                         written += produced;
                     }
@@ -768,15 +1076,83 @@ impl<F: Read + Seek> StreamingZip<F> {
                     }
                 }
 
-                if entry.crc32 != 0 && hasher.finalize() != entry.crc32 {
+                let crc32 = expected_crc(
+                    &mut self.file,
+                    entry.crc32,
+                    entry.has_data_descriptor(),
+                    data_offset + entry.compressed_size,
+                )?;
+                if crc32 != 0 && hasher.finalize() != crc32 {
                     return Err(ZipError::CrcMismatch);
                 }
+                self.decompressed_bytes += written as u64;
                 Ok(written)
             }
             _ => Err(ZipError::UnsupportedCompression),
         }
     }
 
+    /// Open a streaming reader over a file's decompressed bytes.
+    ///
+    /// The entry is inflated incrementally as the caller pulls bytes, so
+    /// neither the compressed nor the decompressed entry is buffered whole.
+    /// The CRC is checked once the reader reaches the end of the entry.
+    pub fn entry_reader(&mut self, entry: &CdEntry) -> Result<ZipEntryReader<'_, F>, ZipError> {
+        let input_buf = alloc::vec![0u8; DEFAULT_ZIP_SCRATCH_BYTES];
+        let gauge = MemoryGauge::with_bytes(MemorySubsystem::ZipScratch, input_buf.len());
+        self.open_entry_reader(entry, EntryScratch::Owned(input_buf), gauge)
+    }
+
+    /// Open a streaming reader over a file's decompressed bytes using caller-provided scratch input.
+    ///
+    /// Compressed bytes are read through `input_buf`, which must be non-empty.
+    pub fn entry_reader_with_scratch<'a>(
+        &'a mut self,
+        entry: &CdEntry,
+        input_buf: &'a mut [u8],
+    ) -> Result<ZipEntryReader<'a, F>, ZipError> {
+        if input_buf.is_empty() {
+            return Err(ZipError::BufferTooSmall);
+        }
+        let gauge = MemoryGauge::new(MemorySubsystem::ZipScratch);
+        self.open_entry_reader(entry, EntryScratch::Borrowed(input_buf), gauge)
+    }
+
+    fn open_entry_reader<'a>(
+        &'a mut self,
+        entry: &CdEntry,
+        scratch: EntryScratch<'a>,
+        gauge: MemoryGauge,
+    ) -> Result<ZipEntryReader<'a, F>, ZipError> {
+        let budget = self.output_budget(entry)?;
+        if entry.method != METHOD_STORED && entry.method != METHOD_DEFLATED {
+            return Err(ZipError::UnsupportedCompression);
+        }
+
+        let data_offset = self.calc_data_offset(entry)?;
+        self.file
+            .seek(SeekFrom::Start(data_offset))
+            .map_err(io_error(IoOperation::Seek, Some(data_offset)))?;
+
+        Ok(ZipEntryReader {
+            file: &mut self.file,
+            decompressed_bytes: &mut self.decompressed_bytes,
+            budget,
+            produced: 0,
+            method: entry.method,
+            crc32: entry.crc32,
+            data_descriptor: entry.has_data_descriptor(),
+            hasher: crc32fast::Hasher::new(),
+            state: miniz_oxide::inflate::stream::InflateState::new(DataFormat::Raw),
+            compressed_remaining: entry.compressed_size,
+            offset: data_offset,
+            scratch,
+            pending: 0..0,
+            finished: false,
+            _gauge: gauge,
+        })
+    }
+
     /// Read a file by its local header offset (avoids borrow issues)
     /// This is useful when you need to read a file after getting its metadata
     pub fn read_file_at_offset(
@@ -787,6 +1163,7 @@ impl<F: Read + Seek> StreamingZip<F> {
         // Find entry by offset
         let entry = self
             .entries
+            .as_slice()
             .iter()
             .find(|e| e.local_header_offset == local_header_offset)
             .ok_or(ZipError::FileNotFound)?;
@@ -798,24 +1175,67 @@ impl<F: Read + Seek> StreamingZip<F> {
             uncompressed_size: entry.uncompressed_size,
             local_header_offset: entry.local_header_offset,
             crc32: entry.crc32,
+            flags: entry.flags,
             filename: entry.filename.clone(),
         };
 
         self.read_file(&entry_clone, buf)
     }
 
+    /// Check `entry` against the configured limits, returning the caps its
+    /// output must stay within.
+    fn output_budget(&self, entry: &CdEntry) -> Result<OutputBudget, ZipError> {
+        let Some(limits) = self.limits else {
+            return Ok(OutputBudget::UNLIMITED);
+        };
+        if entry.uncompressed_size > limits.max_file_read_size as u64 {
+            return Err(ZipError::FileTooLarge);
+        }
+        if entry.compressed_size > limits.max_file_read_size as u64 {
+            return Err(ZipError::FileTooLarge);
+        }
+        let budget = OutputBudget {
+            ratio_cap: limits.max_compression_ratio.map_or(u64::MAX, |ratio| {
+                entry
+                    .compressed_size
+                    .max(1)
+                    .saturating_mul(u64::from(ratio))
+            }),
+            remaining: limits.max_total_decompressed.map_or(u64::MAX, |total| {
+                total.saturating_sub(self.decompressed_bytes)
+            }),
+        };
+        // Declared sizes fail fast; the produced bytes are checked as they
+        // are inflated.
+        budget.check(entry.uncompressed_size)?;
+        Ok(budget)
+    }
+
+    /// Decompressed bytes produced by reads from this archive so far.
+    ///
+    /// This is what [`ZipLimits::max_total_decompressed`] is charged against.
+    pub fn decompressed_bytes(&self) -> u64 {
+        self.decompressed_bytes
+    }
+
+    /// Reset the archive-wide decompression budget, e.g. when a reader
+    /// session starts over.
+    pub fn reset_decompressed_bytes(&mut self) {
+        self.decompressed_bytes = 0;
+    }
+
     /// Calculate the offset to the actual file data (past local header)
     fn calc_data_offset(&mut self, entry: &CdEntry) -> Result<u64, ZipError> {
         let offset = entry.local_header_offset;
         self.file
             .seek(SeekFrom::Start(offset))
-            .map_err(|_| ZipError::IoError)?;
+            .map_err(io_error(IoOperation::Seek, Some(offset)))?;
 
         // Read local file header (30 bytes fixed + variable filename/extra)
         let mut header = [0u8; 30];
         self.file
             .read_exact(&mut header)
-            .map_err(|_| ZipError::IoError)?;
+            .map_err(io_error(IoOperation::Read, Some(offset)))?;
 
         // Verify signature
         let sig = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
@@ -848,6 +1268,13 @@ impl<F: Read + Seek> StreamingZip<F> {
         ])
     }
 
+    /// Read u64 from buffer at offset (little-endian)
+    fn read_u64_le(buf: &[u8], offset: usize) -> u64 {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&buf[offset..offset + 8]);
+        u64::from_le_bytes(bytes)
+    }
+
     /// Validate that the archive contains a valid EPUB mimetype file
     ///
     /// Checks that a file named "mimetype" exists and its content is exactly
@@ -896,17 +1323,17 @@ impl<F: Read + Seek> StreamingZip<F> {
 
     /// Get number of entries in central directory
     pub fn num_entries(&self) -> usize {
-        self.num_entries.min(self.entries.len())
+        self.num_entries.min(self.entries.as_slice().len())
     }
 
     /// Iterate over all entries
     pub fn entries(&self) -> impl Iterator<Item = &CdEntry> {
-        self.entries.iter()
+        self.entries.as_slice().iter()
     }
 
     /// Get entry by index
     pub fn get_entry_by_index(&self, index: usize) -> Option<&CdEntry> {
-        self.entries.get(index)
+        self.entries.as_slice().get(index)
     }
 
     /// Get the active limits used by this ZIP reader.
@@ -915,6 +1342,513 @@ impl<F: Read + Seek> StreamingZip<F> {
     }
 }
 
+impl StreamingZip<MemoryReader<Vec<u8>>> {
+    /// Open a ZIP archive from a forward-only stream (socket, UART, pipe).
+    ///
+    /// See [`Self::new_sequential_with_limits`].
+    pub fn new_sequential<R: Read>(reader: R) -> Result<Self, ZipError> {
+        Self::new_sequential_with_limits(reader, None)
+    }
+
+    /// Open a ZIP archive from a forward-only stream with explicit runtime limits.
+    ///
+    /// Local file headers are parsed front to back instead of seeking to the
+    /// central directory, and each entry's compressed bytes are spooled into
+    /// memory so the usual entry API (`get_entry`, `read_file`,
+    /// `entry_reader`, ...) works afterwards. Scanning stops at the central
+    /// directory; the rest of the stream is not read.
+    ///
+    /// Deflated entries may defer their sizes and CRC to a data descriptor
+    /// (general purpose flag bit 3); stored entries must declare their size
+    /// up front. With limits, entries whose compressed size exceeds
+    /// `max_file_read_size` fail with `FileTooLarge` before they are spooled,
+    /// and deferred-size entries are held to `max_compression_ratio` as they
+    /// are scanned. The whole spool is capped at `max_archive_bytes`, or a
+    /// 64 MiB default without limits; a stream that would grow past it fails
+    /// with `FileTooLarge`.
+    pub fn new_sequential_with_limits<R: Read>(
+        reader: R,
+        limits: Option<ZipLimits>,
+    ) -> Result<Self, ZipError> {
+        let strict = limits.is_some_and(|l| l.strict);
+        let max_spool = limits
+            .and_then(|l| l.max_archive_bytes)
+            .unwrap_or(DEFAULT_MAX_ARCHIVE_BYTES);
+        let max_entries = limits.map(|l| l.max_cd_entries).unwrap_or(MAX_CD_ENTRIES);
+        let mut source = ForwardReader::new(reader);
+        let mut spool = Vec::with_capacity(0);
+        let mut entries = CentralDirectory::with_max_entries(max_entries, 0);
+        let mut count = 0usize;
+
+        while let Some(sig) = source.read_signature()? {
+            match sig {
+                SIG_LOCAL_FILE_HEADER => {}
+                SIG_CD_ENTRY | SIG_ZIP64_EOCD | SIG_EOCD => break,
+                _ => return Err(ZipError::InvalidFormat),
+            }
+            if count == max_entries {
+                if strict {
+                    return Err(ZipError::CentralDirFull);
+                }
+                log_event!(
+                    warn,
+                    "[ZIP] Sequential scan stopped after {} entries (max: {})",
+                    count,
+                    max_entries
+                );
+                break;
+            }
+            let entry = Self::spool_local_entry(&mut source, &mut spool, limits, max_spool)?;
+            if spool.len() as u64 > max_spool {
+                return Err(ZipError::FileTooLarge);
+            }
+            entries.push(entry)?;
+            count += 1;
+        }
+
+        log_event!(
+            debug,
+            "[ZIP] Sequentially scanned {} entries ({} bytes spooled)",
+            count,
+            spool.len()
+        );
+
+        entries.build_index()?;
+        Ok(Self {
+            file: MemoryReader::new(spool),
+            entries,
+            num_entries: count,
+            limits,
+            decompressed_bytes: 0,
+        })
+    }
+
+    /// Copy one local entry (header, data, descriptor) from `source` into
+    /// `spool`, after its signature has been consumed.
+    ///
+    /// Entry data that would push `spool` past `max_spool` bytes fails with
+    /// `FileTooLarge` before it is read.
+    fn spool_local_entry<R: Read>(
+        source: &mut ForwardReader<R>,
+        spool: &mut Vec<u8>,
+        limits: Option<ZipLimits>,
+        max_spool: u64,
+    ) -> Result<CdEntry, ZipError> {
+        let header_offset = source.offset - 4;
+        let mut header = [0u8; 26];
+        source.read_exact(&mut header)?;
+        let flags = u16::from_le_bytes([header[2], header[3]]);
+        let mut entry = CdEntry::new();
+        entry.flags = flags;
+        entry.method = u16::from_le_bytes([header[4], header[5]]);
+        entry.crc32 = u32::from_le_bytes([header[10], header[11], header[12], header[13]]);
+        let compressed_size_32 =
+            u32::from_le_bytes([header[14], header[15], header[16], header[17]]);
+        let uncompressed_size_32 =
+            u32::from_le_bytes([header[18], header[19], header[20], header[21]]);
+        let name_len = u16::from_le_bytes([header[22], header[23]]) as usize;
+        let extra_len = u16::from_le_bytes([header[24], header[25]]) as usize;
+        entry.compressed_size = compressed_size_32 as u64;
+        entry.uncompressed_size = uncompressed_size_32 as u64;
+        entry.local_header_offset = spool.len() as u64;
+
+        spool.extend_from_slice(&SIG_LOCAL_FILE_HEADER.to_le_bytes());
+        spool.extend_from_slice(&header);
+        let name_start = spool.len();
+        source.read_into(spool, name_len)?;
+        if name_len <= MAX_FILENAME_LEN {
+            entry.filename = String::from_utf8_lossy(&spool[name_start..]).to_string();
+        }
+        let extra_start = spool.len();
+        source.read_into(spool, extra_len)?;
+
+        // Local ZIP64 extended information carries both sizes, in this order.
+        let mut zip64 = false;
+        let mut extra = &spool[extra_start..];
+        while extra.len() >= 4 {
+            let header_id = u16::from_le_bytes([extra[0], extra[1]]);
+            let field_size = u16::from_le_bytes([extra[2], extra[3]]) as usize;
+            let field = extra
+                .get(4..4 + field_size)
+                .ok_or(ZipError::InvalidFormat)?;
+            if header_id == 0x0001 {
+                zip64 = true;
+                if field.len() >= 16 {
+                    entry.uncompressed_size = Self::read_u64_le(field, 0);
+                    entry.compressed_size = Self::read_u64_le(field, 8);
+                }
+            }
+            extra = &extra[4 + field_size..];
+        }
+
+        let deferred = flags & FLAG_DATA_DESCRIPTOR != 0;
+        if deferred && entry.method != METHOD_DEFLATED {
+            // Without a compressed stream there is no way to find the end of
+            // the data short of guessing at descriptor signatures.
+            return Err(if entry.method == METHOD_STORED {
+                ZipError::InvalidFormat
+            } else {
+                ZipError::UnsupportedCompression
+            });
+        }
+        if !deferred && limits.is_some_and(|l| entry.compressed_size > l.max_file_read_size as u64)
+        {
+            return Err(ZipError::FileTooLarge);
+        }
+
+        if !deferred {
+            if (spool.len() as u64).saturating_add(entry.compressed_size) > max_spool {
+                return Err(ZipError::FileTooLarge);
+            }
+            let size =
+                usize::try_from(entry.compressed_size).map_err(|_| ZipError::FileTooLarge)?;
+            source.read_into(spool, size)?;
+            return Ok(entry);
+        }
+
+        // Sizes are unknown: inflate forward to find the end of the stream.
+        let data_start = spool.len();
+        let mut state = miniz_oxide::inflate::stream::InflateState::new(DataFormat::Raw);
+        let mut input = alloc::vec![0u8; DEFAULT_ZIP_SCRATCH_BYTES];
+        let mut output = alloc::vec![0u8; DEFAULT_ZIP_SCRATCH_BYTES];
+        let _gauge =
+            MemoryGauge::with_bytes(MemorySubsystem::ZipScratch, input.len() + output.len());
+        let mut hasher = crc32fast::Hasher::new();
+        let mut uncompressed = 0u64;
+        loop {
+            let available = source.read(&mut input)?;
+            if available == 0 {
+                return Err(ZipError::DecompressError);
+            }
+            let mut pending = &input[..available];
+            let mut ended = false;
+            while !pending.is_empty() && !ended {
+                let result = miniz_oxide::inflate::stream::inflate(
+                    &mut state,
+                    pending,
+                    &mut output,
+                    MZFlush::None,
+                );
+                spool.extend_from_slice(&pending[..result.bytes_consumed]);
+                pending = &pending[result.bytes_consumed..];
+                hasher.update(&output[..result.bytes_written]);
+                uncompressed += result.bytes_written as u64;
+                match result.status {
+                    Ok(MZStatus::StreamEnd) => ended = true,
+                    Ok(MZStatus::Ok) => {
+                        if result.bytes_consumed == 0 && result.bytes_written == 0 {
+                            return Err(ZipError::DecompressError);
+                        }
+                    }
+                    Ok(MZStatus::NeedDict) | Err(_) => return Err(ZipError::DecompressError),
+                }
+                if spool.len() as u64 > max_spool
+                    || limits.is_some_and(|l| {
+                        (spool.len() - data_start) as u64 > l.max_file_read_size as u64
+                            || uncompressed > l.max_file_read_size as u64
+                    })
+                {
+                    return Err(ZipError::FileTooLarge);
+                }
+            }
+            source.unread(pending);
+            if ended {
+                break;
+            }
+        }
+        let compressed = (spool.len() - data_start) as u64;
+        if limits
+            .and_then(|l| l.max_compression_ratio)
+            .is_some_and(|ratio| uncompressed > compressed.max(1).saturating_mul(u64::from(ratio)))
+        {
+            return Err(ZipError::CompressionRatioExceeded);
+        }
+
+        // Data descriptor: optional signature, CRC, then 4- or 8-byte sizes.
+        // It is spooled too, so reads can check the CRC as for seekable input.
+        let descriptor_start = spool.len();
+        source.read_into(spool, 4)?;
+        if Self::read_u32_le(spool, descriptor_start) == SIG_DATA_DESCRIPTOR {
+            source.read_into(spool, 4)?;
+        }
+        entry.crc32 = Self::read_u32_le(spool, spool.len() - 4);
+        let sizes_start = spool.len();
+        let (descriptor_compressed, descriptor_uncompressed) = if zip64 {
+            source.read_into(spool, 16)?;
+            (
+                Self::read_u64_le(spool, sizes_start),
+                Self::read_u64_le(spool, sizes_start + 8),
+            )
+        } else {
+            source.read_into(spool, 8)?;
+            (
+                Self::read_u32_le(spool, sizes_start) as u64,
+                Self::read_u32_le(spool, sizes_start + 4) as u64,
+            )
+        };
+        let (compressed_check, uncompressed_check) = if zip64 {
+            (compressed, uncompressed)
+        } else {
+            (compressed & 0xFFFF_FFFF, uncompressed & 0xFFFF_FFFF)
+        };
+        if descriptor_compressed != compressed_check
+            || descriptor_uncompressed != uncompressed_check
+        {
+            log_event!(
+                warn,
+                "[ZIP] Data descriptor sizes disagree with stream at offset {}",
+                header_offset
+            );
+            return Err(ZipError::InvalidFormat);
+        }
+        if hasher.finalize() != entry.crc32 {
+            return Err(ZipError::CrcMismatch);
+        }
+        entry.compressed_size = compressed;
+        entry.uncompressed_size = uncompressed;
+        Ok(entry)
+    }
+}
+
+/// Forward-only source for [`StreamingZip::new_sequential`], with pushback
+/// for bytes read past the end of a deflate stream.
+struct ForwardReader<R> {
+    inner: R,
+    pushback: Vec<u8>,
+    /// Stream offset of the next byte returned.
+    offset: u64,
+}
+
+impl<R: Read> ForwardReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            pushback: Vec::with_capacity(0),
+            offset: 0,
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ZipError> {
+        let n = if self.pushback.is_empty() {
+            self.inner
+                .read(buf)
+                .map_err(io_error(IoOperation::Read, Some(self.offset)))?
+        } else {
+            let n = buf.len().min(self.pushback.len());
+            buf[..n].copy_from_slice(&self.pushback[..n]);
+            self.pushback.drain(..n);
+            n
+        };
+        self.offset += n as u64;
+        Ok(n)
+    }
+
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<(), ZipError> {
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => {
+                    return Err(io_error(IoOperation::Read, Some(self.offset))(
+                        IoError::UnexpectedEof,
+                    ))
+                }
+                n => buf = &mut buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Append exactly `len` bytes to `out`.
+    fn read_into(&mut self, out: &mut Vec<u8>, len: usize) -> Result<(), ZipError> {
+        let start = out.len();
+        out.resize(start + len, 0);
+        self.read_exact(&mut out[start..])
+    }
+
+    /// Next record signature, or `None` if the stream ended cleanly first.
+    fn read_signature(&mut self) -> Result<Option<u32>, ZipError> {
+        let mut sig = [0u8; 4];
+        if self.read(&mut sig[..1])? == 0 {
+            return Ok(None);
+        }
+        self.read_exact(&mut sig[1..])?;
+        Ok(Some(u32::from_le_bytes(sig)))
+    }
+
+    /// Return `bytes` to the front of the stream.
+    fn unread(&mut self, bytes: &[u8]) {
+        self.pushback.splice(0..0, bytes.iter().copied());
+        self.offset -= bytes.len() as u64;
+    }
+}
+
+/// Compressed-input scratch owned by or lent to a [`ZipEntryReader`].
+enum EntryScratch<'a> {
+    Owned(Vec<u8>),
+    Borrowed(&'a mut [u8]),
+}
+
+impl EntryScratch<'_> {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Self::Owned(buf) => buf,
+            Self::Borrowed(buf) => buf,
+        }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        match self {
+            Self::Owned(buf) => buf,
+            Self::Borrowed(buf) => buf,
+        }
+    }
+}
+
+/// Streaming reader over one entry's decompressed bytes.
+///
+/// Created by [`StreamingZip::entry_reader`]. Implements [`crate::io::Read`],
+/// and `std::io::Read` with the `std` feature, so chapters can be consumed
+/// without materializing the whole entry. Reads fail with the underlying
+/// [`ZipError`] (`CrcMismatch` at the end of a corrupt entry).
+pub struct ZipEntryReader<'a, F: Read> {
+    file: &'a mut F,
+    /// The archive's running total, charged as bytes are produced.
+    decompressed_bytes: &'a mut u64,
+    budget: OutputBudget,
+    produced: u64,
+    method: u16,
+    crc32: u32,
+    data_descriptor: bool,
+    hasher: crc32fast::Hasher,
+    // Inline like the other inflate paths, to avoid a large heap allocation.
+    state: miniz_oxide::inflate::stream::InflateState,
+    compressed_remaining: u64,
+    /// Archive offset of the next compressed byte.
+    offset: u64,
+    scratch: EntryScratch<'a>,
+    /// Compressed bytes in `scratch` not yet fed to the inflater.
+    pending: core::ops::Range<usize>,
+    finished: bool,
+    _gauge: MemoryGauge,
+}
+
+impl<F: Read> ZipEntryReader<'_, F> {
+    /// Read up to `buf.len()` decompressed bytes, returning `0` at the end
+    /// of the entry.
+    pub fn read_decompressed(&mut self, buf: &mut [u8]) -> Result<usize, ZipError> {
+        if self.finished || buf.is_empty() {
+            return Ok(0);
+        }
+        match self.method {
+            METHOD_STORED => self.read_stored(buf),
+            _ => self.read_deflated(buf),
+        }
+    }
+
+    /// Whether the whole entry has been read and its CRC checked.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn read_stored(&mut self, buf: &mut [u8]) -> Result<usize, ZipError> {
+        let take = core::cmp::min(self.compressed_remaining, buf.len() as u64) as usize;
+        self.charge(take)?;
+        self.file
+            .read_exact(&mut buf[..take])
+            .map_err(io_error(IoOperation::Read, Some(self.offset)))?;
+        self.offset += take as u64;
+        self.compressed_remaining -= take as u64;
+        self.hasher.update(&buf[..take]);
+        if self.compressed_remaining == 0 {
+            self.finish()?;
+        }
+        Ok(take)
+    }
+
+    fn read_deflated(&mut self, buf: &mut [u8]) -> Result<usize, ZipError> {
+        loop {
+            if self.pending.is_empty() && self.compressed_remaining > 0 {
+                let input = self.scratch.as_mut_slice();
+                let take = core::cmp::min(self.compressed_remaining, input.len() as u64) as usize;
+                self.file
+                    .read_exact(&mut input[..take])
+                    .map_err(io_error(IoOperation::Read, Some(self.offset)))?;
+                self.offset += take as u64;
+                self.compressed_remaining -= take as u64;
+                self.pending = 0..take;
+            }
+
+            let result = miniz_oxide::inflate::stream::inflate(
+                &mut self.state,
+                &self.scratch.as_slice()[self.pending.clone()],
+                buf,
+                MZFlush::None,
+            );
+            let consumed = result.bytes_consumed;
+            let produced = result.bytes_written;
+            self.pending.start += consumed;
+            self.charge(produced)?;
+            self.hasher.update(&buf[..produced]);
+
+            match result.status {
+                Ok(MZStatus::StreamEnd) => {
+                    if self.compressed_remaining != 0 || !self.pending.is_empty() {
+                        return Err(ZipError::DecompressError);
+                    }
+                    self.finish()?;
+                    return Ok(produced);
+                }
+                Ok(MZStatus::Ok) => {
+                    if produced > 0 {
+                        return Ok(produced);
+                    }
+                    if consumed == 0 {
+                        return Err(ZipError::DecompressError);
+                    }
+                }
+                Ok(MZStatus::NeedDict) | Err(_) => return Err(ZipError::DecompressError),
+            }
+        }
+    }
+
+    /// Count `produced` bytes against the entry's output budget.
+    fn charge(&mut self, produced: usize) -> Result<(), ZipError> {
+        self.produced += produced as u64;
+        self.budget.check(self.produced)?;
+        *self.decompressed_bytes += produced as u64;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ZipError> {
+        self.finished = true;
+        let hasher = core::mem::replace(&mut self.hasher, crc32fast::Hasher::new());
+        let crc32 = expected_crc(self.file, self.crc32, self.data_descriptor, self.offset)?;
+        if crc32 != 0 && hasher.finalize() != crc32 {
+            return Err(ZipError::CrcMismatch);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F: Read> std::io::Read for ZipEntryReader<'_, F> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.read_decompressed(buf).map_err(|err| match err {
+            ZipError::IoError(info) => info.error().into(),
+            other => std::io::Error::new(std::io::ErrorKind::InvalidData, other),
+        })
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<F: Read> Read for ZipEntryReader<'_, F> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        self.read_decompressed(buf).map_err(|err| match err {
+            ZipError::IoError(info) => info.error(),
+            _ => IoError::InvalidData,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -951,12 +1885,14 @@ mod tests {
             ZipError::UnsupportedCompression,
             ZipError::DecompressError,
             ZipError::CrcMismatch,
-            ZipError::IoError,
+            ZipError::IoError(IoErrorInfo::new(IoError::Other, IoOperation::Read, None)),
             ZipError::CentralDirFull,
             ZipError::BufferTooSmall,
             ZipError::FileTooLarge,
             ZipError::InvalidMimetype("test".to_string()),
             ZipError::UnsupportedZip64,
+            ZipError::CompressionRatioExceeded,
+            ZipError::DecompressBudgetExceeded,
         ];
 
         // Each variant should be different from every other
@@ -992,8 +1928,15 @@ mod tests {
     /// The archive contains one file with the given name and content,
     /// stored without compression (method 0).
     fn build_single_file_zip(filename: &str, content: &[u8]) -> Vec<u8> {
+        build_single_entry_zip(filename, METHOD_STORED, content, content)
+    }
+
+    /// Helper to build a single-entry archive whose `content` is stored as
+    /// `data` with compression `method`.
+    fn build_single_entry_zip(filename: &str, method: u16, data: &[u8], content: &[u8]) -> Vec<u8> {
         let name_bytes = filename.as_bytes();
         let name_len = name_bytes.len() as u16;
+        let data_len = data.len() as u32;
         let content_len = content.len() as u32;
         let crc = crc32fast::hash(content);
 
@@ -1004,16 +1947,16 @@ mod tests {
         zip.extend_from_slice(&SIG_LOCAL_FILE_HEADER.to_le_bytes()); // signature
         zip.extend_from_slice(&20u16.to_le_bytes()); // version needed
         zip.extend_from_slice(&0u16.to_le_bytes()); // flags
-        zip.extend_from_slice(&METHOD_STORED.to_le_bytes()); // compression
+        zip.extend_from_slice(&method.to_le_bytes()); // compression
         zip.extend_from_slice(&0u16.to_le_bytes()); // mod time
         zip.extend_from_slice(&0u16.to_le_bytes()); // mod date
         zip.extend_from_slice(&crc.to_le_bytes()); // CRC32
-        zip.extend_from_slice(&content_len.to_le_bytes()); // compressed size
+        zip.extend_from_slice(&data_len.to_le_bytes()); // compressed size
         zip.extend_from_slice(&content_len.to_le_bytes()); // uncompressed size
         zip.extend_from_slice(&name_len.to_le_bytes()); // filename length
         zip.extend_from_slice(&0u16.to_le_bytes()); // extra field length
         zip.extend_from_slice(name_bytes); // filename
-        zip.extend_from_slice(content); // file data
+        zip.extend_from_slice(data); // file data
 
         // -- Central directory entry --
         let cd_offset = zip.len() as u32;
@@ -1021,11 +1964,11 @@ mod tests {
         zip.extend_from_slice(&20u16.to_le_bytes()); // version made by
         zip.extend_from_slice(&20u16.to_le_bytes()); // version needed
         zip.extend_from_slice(&0u16.to_le_bytes()); // flags
-        zip.extend_from_slice(&METHOD_STORED.to_le_bytes()); // compression
+        zip.extend_from_slice(&method.to_le_bytes()); // compression
         zip.extend_from_slice(&0u16.to_le_bytes()); // mod time
         zip.extend_from_slice(&0u16.to_le_bytes()); // mod date
         zip.extend_from_slice(&crc.to_le_bytes()); // CRC32
-        zip.extend_from_slice(&content_len.to_le_bytes()); // compressed size
+        zip.extend_from_slice(&data_len.to_le_bytes()); // compressed size
         zip.extend_from_slice(&content_len.to_le_bytes()); // uncompressed size
         zip.extend_from_slice(&name_len.to_le_bytes()); // filename length
         zip.extend_from_slice(&0u16.to_le_bytes()); // extra field length
@@ -1215,6 +2158,569 @@ mod tests {
         assert!(matches!(result, Err(ZipError::CentralDirFull)));
     }
 
+    /// Helper to build a stored ZIP archive of `count` files named
+    /// `file-<index>.txt`, each containing its own name.
+    fn build_many_files_zip(count: usize) -> Vec<u8> {
+        let mut zip = Vec::with_capacity(count * 96);
+        let mut central = Vec::with_capacity(count * 64);
+        for index in 0..count {
+            let name = format!("file-{index}.txt");
+            let name_bytes = name.as_bytes();
+            let mut header = Vec::with_capacity(26);
+            header.extend_from_slice(&20u16.to_le_bytes()); // version needed
+            header.extend_from_slice(&0u16.to_le_bytes()); // flags
+            header.extend_from_slice(&METHOD_STORED.to_le_bytes()); // compression
+            header.extend_from_slice(&[0; 4]); // mod time, mod date
+            header.extend_from_slice(&crc32fast::hash(name_bytes).to_le_bytes()); // CRC32
+            header.extend_from_slice(&(name_bytes.len() as u32).to_le_bytes()); // compressed
+            header.extend_from_slice(&(name_bytes.len() as u32).to_le_bytes()); // uncompressed
+            header.extend_from_slice(&(name_bytes.len() as u16).to_le_bytes()); // filename length
+            header.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+
+            let local_offset = zip.len() as u32;
+            zip.extend_from_slice(&SIG_LOCAL_FILE_HEADER.to_le_bytes());
+            zip.extend_from_slice(&header);
+            zip.extend_from_slice(name_bytes); // filename
+            zip.extend_from_slice(name_bytes); // file data
+
+            central.extend_from_slice(&SIG_CD_ENTRY.to_le_bytes());
+            central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+            central.extend_from_slice(&header);
+            central.extend_from_slice(&[0; 10]); // comment length, disk, attrs
+            central.extend_from_slice(&local_offset.to_le_bytes());
+            central.extend_from_slice(name_bytes);
+        }
+
+        let cd_offset = zip.len() as u32;
+        zip.extend_from_slice(&central);
+        zip.extend_from_slice(&SIG_EOCD.to_le_bytes());
+        zip.extend_from_slice(&[0; 4]); // disk number, disk with CD
+        zip.extend_from_slice(&(count as u16).to_le_bytes()); // entries on this disk
+        zip.extend_from_slice(&(count as u16).to_le_bytes()); // total entries
+        zip.extend_from_slice(&(central.len() as u32).to_le_bytes()); // CD size
+        zip.extend_from_slice(&cd_offset.to_le_bytes()); // CD offset
+        zip.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        zip
+    }
+
+    #[test]
+    fn test_fixed_cache_drops_entries_past_capacity() {
+        let count = MAX_CD_ENTRIES + 44;
+        let cursor = std::io::Cursor::new(build_many_files_zip(count));
+        let zip = StreamingZip::new(cursor).unwrap();
+        assert_eq!(zip.num_entries(), MAX_CD_ENTRIES);
+        assert!(zip.get_entry("file-255.txt").is_some());
+        assert!(zip.get_entry("file-256.txt").is_none());
+    }
+
+    #[test]
+    fn test_heap_directory_indexes_all_entries() {
+        let count = MAX_CD_ENTRIES + 44;
+        let cursor = std::io::Cursor::new(build_many_files_zip(count));
+        let limits = ZipLimits::new(1024, 1024)
+            .with_strict(true)
+            .with_max_cd_entries(count);
+        let mut zip = StreamingZip::new_with_limits(cursor, Some(limits)).unwrap();
+        assert_eq!(zip.num_entries(), count);
+        assert_eq!(zip.entries().count(), count);
+
+        let last = format!("file-{}.txt", count - 1);
+        let entry = zip.get_entry(&last).unwrap().clone();
+        assert_eq!(
+            zip.get_entry_by_index(count - 1)
+                .map(|e| e.filename.as_str()),
+            Some(last.as_str())
+        );
+        let mut buf = [0u8; 32];
+        let n = zip.read_file(&entry, &mut buf).unwrap();
+        assert_eq!(&buf[..n], last.as_bytes());
+    }
+
+    #[test]
+    fn test_strict_rejects_entries_beyond_heap_capacity() {
+        let count = MAX_CD_ENTRIES + 44;
+        let cursor = std::io::Cursor::new(build_many_files_zip(count));
+        let limits = ZipLimits::new(1024, 1024)
+            .with_strict(true)
+            .with_max_cd_entries(count - 1);
+        let result = StreamingZip::new_with_limits(cursor, Some(limits));
+        assert!(matches!(result, Err(ZipError::CentralDirFull)));
+    }
+
+    /// Raw DEFLATE stream holding `content` in stored blocks of at most
+    /// `block_len` bytes.
+    fn deflate_stored_blocks(content: &[u8], block_len: usize) -> Vec<u8> {
+        let mut out = Vec::with_capacity(content.len() + 8);
+        let mut chunks = content.chunks(block_len).peekable();
+        while let Some(chunk) = chunks.next() {
+            let len = chunk.len() as u16;
+            out.push(u8::from(chunks.peek().is_none())); // BFINAL, BTYPE=00
+            out.extend_from_slice(&len.to_le_bytes());
+            out.extend_from_slice(&(!len).to_le_bytes());
+            out.extend_from_slice(chunk);
+        }
+        out
+    }
+
+    #[test]
+    fn test_entry_reader_inflates_incrementally() {
+        let content: Vec<u8> = (0..600u32).map(|i| (i % 251) as u8).collect();
+        let data = deflate_stored_blocks(&content, 100);
+        let zip_data = build_single_entry_zip("chapter.xhtml", METHOD_DEFLATED, &data, &content);
+        let mut zip = StreamingZip::new(std::io::Cursor::new(zip_data)).unwrap();
+        let entry = zip.get_entry("chapter.xhtml").unwrap().clone();
+
+        let mut input = [0u8; 7];
+        let mut reader = zip.entry_reader_with_scratch(&entry, &mut input).unwrap();
+        let mut out = Vec::with_capacity(content.len());
+        let mut chunk = [0u8; 33];
+        loop {
+            let n = reader.read_decompressed(&mut chunk).unwrap();
+            if n == 0 {
+                break;
+            }
+            assert!(!reader.is_finished() || out.len() + n == content.len());
+            out.extend_from_slice(&chunk[..n]);
+        }
+        assert!(reader.is_finished());
+        assert_eq!(out, content);
+    }
+
+    #[test]
+    fn test_entry_reader_reports_crc_mismatch_at_end() {
+        let content = b"stored entry bytes";
+        let zip_data = build_single_file_zip("data.txt", content);
+        let mut zip = StreamingZip::new(std::io::Cursor::new(zip_data)).unwrap();
+        let mut entry = zip.get_entry("data.txt").unwrap().clone();
+        entry.crc32 ^= 1;
+
+        let mut reader = zip.entry_reader(&entry).unwrap();
+        let mut out = Vec::with_capacity(0);
+        let err = std::io::Read::read_to_end(&mut reader, &mut out).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let inner = err.get_ref().and_then(|e| e.downcast_ref::<ZipError>());
+        assert_eq!(inner, Some(&ZipError::CrcMismatch));
+    }
+
+    #[test]
+    fn test_entry_reader_respects_limits_and_method() {
+        let zip_data = build_single_entry_zip("data.bin", 12, b"xx", b"xx");
+        let mut zip = StreamingZip::new(std::io::Cursor::new(zip_data)).unwrap();
+        let entry = zip.get_entry("data.bin").unwrap().clone();
+        assert!(matches!(
+            zip.entry_reader(&entry),
+            Err(ZipError::UnsupportedCompression)
+        ));
+
+        let zip_data = build_single_file_zip("data.txt", b"1234567890");
+        let limits = ZipLimits::new(8, 8);
+        let mut zip =
+            StreamingZip::new_with_limits(std::io::Cursor::new(zip_data), Some(limits)).unwrap();
+        let entry = zip.get_entry("data.txt").unwrap().clone();
+        assert!(matches!(
+            zip.entry_reader(&entry),
+            Err(ZipError::FileTooLarge)
+        ));
+        assert!(matches!(
+            zip.entry_reader_with_scratch(&entry, &mut []),
+            Err(ZipError::BufferTooSmall)
+        ));
+    }
+
+    /// Helper to build a single deflated entry written by a streaming
+    /// packager: flag bit 3 set, zero sizes and CRC in the local header, and
+    /// the real values in a trailing data descriptor.
+    fn build_data_descriptor_zip(filename: &str, content: &[u8], signed: bool) -> Vec<u8> {
+        let name_bytes = filename.as_bytes();
+        let data = deflate_stored_blocks(content, 64);
+        let crc = crc32fast::hash(content);
+        let mut header = Vec::with_capacity(26);
+        header.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        header.extend_from_slice(&FLAG_DATA_DESCRIPTOR.to_le_bytes()); // flags
+        header.extend_from_slice(&METHOD_DEFLATED.to_le_bytes()); // compression
+        header.extend_from_slice(&[0; 4]); // mod time, mod date
+        let sizes_at = header.len();
+        header.extend_from_slice(&[0; 12]); // CRC32, compressed, uncompressed
+        header.extend_from_slice(&(name_bytes.len() as u16).to_le_bytes()); // filename length
+        header.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+
+        let mut zip = Vec::with_capacity(0);
+        zip.extend_from_slice(&SIG_LOCAL_FILE_HEADER.to_le_bytes());
+        zip.extend_from_slice(&header);
+        zip.extend_from_slice(name_bytes);
+        zip.extend_from_slice(&data);
+        if signed {
+            zip.extend_from_slice(&SIG_DATA_DESCRIPTOR.to_le_bytes());
+        }
+        zip.extend_from_slice(&crc.to_le_bytes());
+        zip.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&(content.len() as u32).to_le_bytes());
+
+        // The central directory records the real values.
+        header[sizes_at..sizes_at + 4].copy_from_slice(&crc.to_le_bytes());
+        header[sizes_at + 4..sizes_at + 8].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        header[sizes_at + 8..sizes_at + 12].copy_from_slice(&(content.len() as u32).to_le_bytes());
+        let cd_offset = zip.len() as u32;
+        zip.extend_from_slice(&SIG_CD_ENTRY.to_le_bytes());
+        zip.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        zip.extend_from_slice(&header);
+        zip.extend_from_slice(&[0; 14]); // comment length, disk, attrs, local offset 0
+        zip.extend_from_slice(name_bytes);
+        let cd_size = zip.len() as u32 - cd_offset;
+
+        zip.extend_from_slice(&SIG_EOCD.to_le_bytes());
+        zip.extend_from_slice(&[0; 4]); // disk number, disk with CD
+        zip.extend_from_slice(&1u16.to_le_bytes()); // entries on this disk
+        zip.extend_from_slice(&1u16.to_le_bytes()); // total entries
+        zip.extend_from_slice(&cd_size.to_le_bytes()); // CD size
+        zip.extend_from_slice(&cd_offset.to_le_bytes()); // CD offset
+        zip.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        zip
+    }
+
+    #[test]
+    fn test_data_descriptor_entries_read_from_seekable_archive() {
+        let content: Vec<u8> = (0..300u32).map(|i| b'a' + (i % 26) as u8).collect();
+        for signed in [true, false] {
+            let zip_data = build_data_descriptor_zip("chapter.xhtml", &content, signed);
+            let mut zip = StreamingZip::new(std::io::Cursor::new(zip_data)).unwrap();
+            let entry = zip.get_entry("chapter.xhtml").unwrap().clone();
+            assert!(entry.has_data_descriptor());
+
+            let mut buf = alloc::vec![0u8; content.len()];
+            let n = zip.read_file(&entry, &mut buf).unwrap();
+            assert_eq!(&buf[..n], content.as_slice());
+            let mut out = Vec::with_capacity(0);
+            zip.read_file_to_writer(&entry, &mut out).unwrap();
+            assert_eq!(out, content);
+            out.clear();
+            std::io::Read::read_to_end(&mut zip.entry_reader(&entry).unwrap(), &mut out).unwrap();
+            assert_eq!(out, content);
+        }
+    }
+
+    #[test]
+    fn test_data_descriptor_crc_is_authoritative() {
+        let content = b"streamed by a packager";
+        let zip_data = build_data_descriptor_zip("a.txt", content, true);
+        let eocd_pos = zip_data.len() - EOCD_MIN_SIZE;
+        let cd_crc_pos =
+            StreamingZip::<std::io::Cursor<Vec<u8>>>::read_u32_le(&zip_data, eocd_pos + 16)
+                as usize
+                + 16;
+        let descriptor_crc_pos = 30 + "a.txt".len() + deflate_stored_blocks(content, 64).len() + 4;
+
+        // A stale central directory CRC does not fail a good read.
+        let mut stale = zip_data.clone();
+        stale[cd_crc_pos] ^= 0xFF;
+        let mut zip = StreamingZip::new(std::io::Cursor::new(stale)).unwrap();
+        let entry = zip.get_entry("a.txt").unwrap().clone();
+        let mut buf = [0u8; 64];
+        assert_eq!(zip.read_file(&entry, &mut buf).unwrap(), content.len());
+
+        // A descriptor CRC that disagrees with the data does.
+        let mut corrupt = zip_data;
+        corrupt[descriptor_crc_pos] ^= 0xFF;
+        let mut zip = StreamingZip::new(std::io::Cursor::new(corrupt)).unwrap();
+        let entry = zip.get_entry("a.txt").unwrap().clone();
+        assert!(matches!(
+            zip.read_file(&entry, &mut buf),
+            Err(ZipError::CrcMismatch)
+        ));
+        let mut out = Vec::with_capacity(0);
+        assert!(matches!(
+            zip.read_file_to_writer(&entry, &mut out),
+            Err(ZipError::CrcMismatch)
+        ));
+    }
+
+    /// Cursor whose reads starting inside the 16 bytes at `at` fail with
+    /// `kind`, or hit EOF when `kind` is `None`, once `armed` is set.
+    struct FailAt {
+        inner: std::io::Cursor<Vec<u8>>,
+        at: u64,
+        kind: Option<std::io::ErrorKind>,
+        armed: std::rc::Rc<core::cell::Cell<bool>>,
+    }
+
+    impl std::io::Read for FailAt {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let pos = self.inner.position();
+            if !self.armed.get() {
+                return std::io::Read::read(&mut self.inner, buf);
+            }
+            if (self.at..self.at + 16).contains(&pos) {
+                return match self.kind {
+                    Some(kind) => Err(kind.into()),
+                    None => Ok(0),
+                };
+            }
+            let len = if pos < self.at {
+                buf.len().min((self.at - pos) as usize)
+            } else {
+                buf.len()
+            };
+            std::io::Read::read(&mut self.inner, &mut buf[..len])
+        }
+    }
+
+    impl std::io::Seek for FailAt {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            std::io::Seek::seek(&mut self.inner, pos)
+        }
+    }
+
+    #[test]
+    fn test_data_descriptor_read_errors_surface_but_truncation_falls_back() {
+        let content = b"streamed by a packager";
+        let zip_data = build_data_descriptor_zip("a.txt", content, true);
+        let descriptor_at = (30 + "a.txt".len() + deflate_stored_blocks(content, 64).len()) as u64;
+        let armed = std::rc::Rc::new(core::cell::Cell::new(false));
+        let open = |kind| {
+            armed.set(false);
+            let zip = StreamingZip::new(FailAt {
+                inner: std::io::Cursor::new(zip_data.clone()),
+                at: descriptor_at,
+                kind,
+                armed: armed.clone(),
+            })
+            .unwrap();
+            armed.set(true);
+            zip
+        };
+        let mut buf = [0u8; 64];
+
+        let mut zip = open(None);
+        let entry = zip.get_entry("a.txt").unwrap().clone();
+        assert_eq!(zip.read_file(&entry, &mut buf).unwrap(), content.len());
+
+        let mut zip = open(Some(std::io::ErrorKind::NotConnected));
+        let Err(ZipError::IoError(info)) = zip.read_file(&entry, &mut buf) else {
+            panic!("a failed descriptor read must surface");
+        };
+        assert_eq!(info.kind, IoError::NotConnected);
+        assert_eq!(info.operation, IoOperation::Read);
+        assert_eq!(info.offset, Some(descriptor_at));
+        let mut out = Vec::with_capacity(0);
+        assert!(matches!(
+            zip.read_file_to_writer(&entry, &mut out),
+            Err(ZipError::IoError(_))
+        ));
+    }
+
+    #[test]
+    fn test_sequential_scan_matches_central_directory() {
+        let content = b"application/epub+zip";
+        let zip_data = build_single_file_zip("mimetype", content);
+        let mut zip = StreamingZip::new_sequential(zip_data.as_slice()).unwrap();
+        assert_eq!(zip.num_entries(), 1);
+        let entry = zip.get_entry("mimetype").unwrap().clone();
+        assert_eq!(entry.uncompressed_size, content.len() as u64);
+        assert!(zip.validate_mimetype().is_ok());
+    }
+
+    #[test]
+    fn test_sequential_scan_reads_data_descriptors() {
+        let content: Vec<u8> = (0..300u32).map(|i| b'a' + (i % 26) as u8).collect();
+        for signed in [true, false] {
+            let zip_data = build_data_descriptor_zip("chapter.xhtml", &content, signed);
+            let mut zip = StreamingZip::new_sequential(zip_data.as_slice()).unwrap();
+            let entry = zip.get_entry("chapter.xhtml").unwrap().clone();
+            assert_eq!(entry.uncompressed_size, content.len() as u64);
+            assert_eq!(entry.crc32, crc32fast::hash(&content));
+            let mut buf = alloc::vec![0u8; content.len()];
+            let n = zip.read_file(&entry, &mut buf).unwrap();
+            assert_eq!(&buf[..n], content.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_sequential_scan_rejects_bad_descriptors_and_limits() {
+        let content = b"descriptor payload";
+        let mut zip_data = build_data_descriptor_zip("a.txt", content, true);
+        let crc_at = 30 + "a.txt".len() + deflate_stored_blocks(content, 64).len() + 4;
+        zip_data[crc_at] ^= 0xFF;
+        assert!(matches!(
+            StreamingZip::new_sequential(zip_data.as_slice()),
+            Err(ZipError::CrcMismatch)
+        ));
+
+        let mut zip_data = build_single_file_zip("a.txt", content);
+        zip_data[6] = FLAG_DATA_DESCRIPTOR as u8;
+        assert!(matches!(
+            StreamingZip::new_sequential(zip_data.as_slice()),
+            Err(ZipError::InvalidFormat)
+        ));
+
+        let zip_data = build_single_file_zip("a.txt", content);
+        let limits = ZipLimits::new(8, 8);
+        assert!(matches!(
+            StreamingZip::new_sequential_with_limits(zip_data.as_slice(), Some(limits)),
+            Err(ZipError::FileTooLarge)
+        ));
+    }
+
+    #[test]
+    fn test_sequential_scan_caps_total_spool() {
+        let limits = ZipLimits::new(1024 * 1024, 1024).with_max_archive_bytes(512);
+        let zip_data = build_many_files_zip(40);
+        assert!(matches!(
+            StreamingZip::new_sequential_with_limits(zip_data.as_slice(), Some(limits)),
+            Err(ZipError::FileTooLarge)
+        ));
+        let roomy = limits.with_max_archive_bytes(zip_data.len() as u64);
+        let zip = StreamingZip::new_sequential_with_limits(zip_data.as_slice(), Some(roomy));
+        assert_eq!(zip.map(|zip| zip.num_entries()).ok(), Some(40));
+
+        let content: Vec<u8> = (0..300u32).map(|i| b'a' + (i % 26) as u8).collect();
+        let zip_data = build_data_descriptor_zip("chapter.xhtml", &content, true);
+        let tight = limits.with_max_archive_bytes(128);
+        assert!(matches!(
+            StreamingZip::new_sequential_with_limits(zip_data.as_slice(), Some(tight)),
+            Err(ZipError::FileTooLarge)
+        ));
+    }
+
+    #[test]
+    fn test_get_entry_index_matches_case_and_leading_slash() {
+        let zip_data = build_many_files_zip(40);
+        let zip = StreamingZip::new(std::io::Cursor::new(zip_data)).unwrap();
+        for index in 0..40 {
+            let name = format!("file-{index}.txt");
+            let found = zip.get_entry(&name).map(|e| e.filename.as_str());
+            assert_eq!(found, Some(name.as_str()));
+            assert!(zip.get_entry(&name.to_ascii_uppercase()).is_some());
+            assert!(zip.get_entry(&format!("/{name}")).is_some());
+        }
+        assert!(zip.get_entry("file-40.txt").is_none());
+        assert!(zip.get_entry("file-1").is_none());
+        assert!(zip.get_entry("//file-1.txt").is_none());
+        assert!(zip.get_entry("").is_none());
+    }
+
+    #[test]
+    fn test_get_entry_prefers_first_of_equivalent_names() {
+        let mut dir = CentralDirectory::with_max_entries(MAX_CD_ENTRIES + 1, 3);
+        for (name, crc32) in [("OEBPS/B.xhtml", 1), ("/oebps/b.xhtml", 2), ("a.css", 3)] {
+            let mut entry = CdEntry::new();
+            entry.filename = name.to_string();
+            entry.crc32 = crc32;
+            dir.push(entry).unwrap();
+        }
+        dir.build_index().unwrap();
+        assert_eq!(dir.find("oebps/b.xhtml").map(|e| e.crc32), Some(1));
+        assert_eq!(dir.find("/OEBPS/B.XHTML").map(|e| e.crc32), Some(1));
+        assert_eq!(dir.find("/A.css").map(|e| e.crc32), Some(3));
+        assert!(dir.find("b.xhtml").is_none());
+    }
+
+    /// Raw DEFLATE stream (one fixed-Huffman block) for `byte` followed by
+    /// `repeats` copies of 258 more, about 158:1.
+    fn deflate_run(byte: u8, repeats: usize) -> Vec<u8> {
+        let mut out = Vec::with_capacity(repeats * 2 + 4);
+        let mut acc = 0u32;
+        let mut bits = 0u32;
+        // Huffman codes go out most significant bit first.
+        let mut push_code = |code: u32, len: u32, out: &mut Vec<u8>| {
+            for shift in (0..len).rev() {
+                acc |= ((code >> shift) & 1) << bits;
+                bits += 1;
+                if bits == 8 {
+                    out.push(acc as u8);
+                    acc = 0;
+                    bits = 0;
+                }
+            }
+        };
+        push_code(1, 1, &mut out); // BFINAL
+        push_code(0b10, 2, &mut out); // BTYPE=01 (fixed), LSB first
+        push_code(0x30 + u32::from(byte), 8, &mut out); // literal
+        for _ in 0..repeats {
+            push_code(0xC5, 8, &mut out); // length code 285: 258 bytes
+            push_code(0, 5, &mut out); // distance code 0: 1 byte back
+        }
+        push_code(0, 7, &mut out); // end of block
+        push_code(0, 7, &mut out); // pad out the final byte
+        out
+    }
+
+    #[test]
+    fn test_compression_ratio_limit_stops_understated_entries() {
+        let content = alloc::vec![b'a'; 1 + 258 * 100];
+        let data = deflate_run(b'a', 100);
+        let zip_data = build_single_entry_zip("bomb.xhtml", METHOD_DEFLATED, &data, &content);
+
+        let mut zip = StreamingZip::new(std::io::Cursor::new(zip_data.clone())).unwrap();
+        let entry = zip.get_entry("bomb.xhtml").unwrap().clone();
+        let mut out = Vec::with_capacity(0);
+        zip.read_file_to_writer(&entry, &mut out).unwrap();
+        assert_eq!(out, content);
+
+        let limits = ZipLimits::new(1024 * 1024, 64).with_max_compression_ratio(50);
+        let mut zip =
+            StreamingZip::new_with_limits(std::io::Cursor::new(zip_data.clone()), Some(limits))
+                .unwrap();
+        let entry = zip.get_entry("bomb.xhtml").unwrap().clone();
+        assert!(matches!(
+            zip.read_file_to_writer(&entry, &mut Vec::with_capacity(0)),
+            Err(ZipError::CompressionRatioExceeded)
+        ));
+
+        // Claiming a small uncompressed size gets past the up-front check,
+        // but inflation still stops at the cap.
+        let mut understated = zip_data;
+        let eocd_pos = understated.len() - EOCD_MIN_SIZE;
+        let cd_offset =
+            StreamingZip::<std::io::Cursor<Vec<u8>>>::read_u32_le(&understated, eocd_pos + 16)
+                as usize;
+        understated[cd_offset + 24..cd_offset + 28].copy_from_slice(&16u32.to_le_bytes());
+        let mut zip =
+            StreamingZip::new_with_limits(std::io::Cursor::new(understated), Some(limits)).unwrap();
+        let entry = zip.get_entry("bomb.xhtml").unwrap().clone();
+        let mut out = Vec::with_capacity(0);
+        assert!(matches!(
+            zip.read_file_to_writer(&entry, &mut out),
+            Err(ZipError::CompressionRatioExceeded)
+        ));
+        assert!(out.len() as u64 <= entry.compressed_size * 50);
+        let mut reader = zip.entry_reader(&entry).unwrap();
+        let mut chunk = [0u8; 512];
+        let err = loop {
+            match reader.read_decompressed(&mut chunk) {
+                Ok(0) => panic!("ratio limit not enforced"),
+                Ok(_) => {}
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(err, ZipError::CompressionRatioExceeded);
+    }
+
+    #[test]
+    fn test_total_decompressed_budget_spans_reads() {
+        let zip_data = build_single_file_zip("data.txt", b"1234567890");
+        let limits = ZipLimits::new(1024, 64).with_max_total_decompressed(15);
+        let mut zip =
+            StreamingZip::new_with_limits(std::io::Cursor::new(zip_data), Some(limits)).unwrap();
+        let entry = zip.get_entry("data.txt").unwrap().clone();
+        let mut buf = [0u8; 16];
+        assert_eq!(zip.read_file(&entry, &mut buf), Ok(10));
+        assert_eq!(zip.decompressed_bytes(), 10);
+        assert_eq!(
+            zip.read_file(&entry, &mut buf),
+            Err(ZipError::DecompressBudgetExceeded)
+        );
+        assert!(matches!(
+            zip.read_file_to_writer(&entry, &mut Vec::with_capacity(0)),
+            Err(ZipError::DecompressBudgetExceeded)
+        ));
+
+        zip.reset_decompressed_bytes();
+        let mut out = Vec::with_capacity(0);
+        std::io::Read::read_to_end(&mut zip.entry_reader(&entry).unwrap(), &mut out).unwrap();
+        assert_eq!(out, b"1234567890");
+        assert_eq!(zip.decompressed_bytes(), 10);
+    }
+
     #[test]
     fn test_validate_mimetype_wrong_content() {
         let zip_data = build_single_file_zip("mimetype", b"text/plain");
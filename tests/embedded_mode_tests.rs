@@ -50,7 +50,9 @@ fn embedded_render_prep() -> RenderPrepOptions {
             max_css_bytes: 16 * 1024,
             max_nav_bytes: 32 * 1024,
             max_inline_style_bytes: 1024,
+            max_inline_svg_bytes: 32 * 1024,
             max_pages_in_memory: 4,
+            max_page_bytes_in_memory: 256 * 1024,
         },
     }
 }
@@ -0,0 +1,205 @@
+//! Chapter and resource errors carry `PhaseErrorContext` and registered codes.
+//!
+//! Run with: cargo test --test error_context
+
+#![cfg(feature = "std")]
+
+use std::io::Cursor;
+
+use mu_epub::book::EpubBook;
+use mu_epub::error::{EpubError, ErrorCodeInfo, ErrorPhase, ZipError, ERROR_CODES};
+use mu_epub::tokenizer::tokenize_html;
+
+const CONTAINER: &str = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#;
+
+const OPF: &str = r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="uid">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="uid">urn:uuid:1234</dc:identifier>
+    <dc:title>Context</dc:title>
+    <dc:language>en</dc:language>
+  </metadata>
+  <manifest>
+    <item id="ok" href="ok.xhtml" media-type="application/xhtml+xml"/>
+    <item id="broken" href="broken.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine>
+    <itemref idref="ok"/>
+    <itemref idref="broken"/>
+  </spine>
+</package>"#;
+
+const OK_CHAPTER: &str =
+    r#"<html xmlns="http://www.w3.org/1999/xhtml"><body><p>Fine</p></body></html>"#;
+
+/// Closes `<em>` with `</b>`.
+const BROKEN_CHAPTER: &str =
+    r#"<html xmlns="http://www.w3.org/1999/xhtml"><body><p><em>Bad</b></p></body></html>"#;
+
+/// Stored (uncompressed) ZIP archive of `files` in order.
+fn stored_zip(files: &[(&str, &str)]) -> Vec<u8> {
+    let mut zip = Vec::with_capacity(4096);
+    let mut central = Vec::with_capacity(1024);
+    for (name, content) in files {
+        let offset = zip.len() as u32;
+        let mut header = Vec::with_capacity(26);
+        header.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        header.extend_from_slice(&[0; 8]); // flags, stored, time, date
+        header.extend_from_slice(&crc32fast::hash(content.as_bytes()).to_le_bytes());
+        header.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // extra length
+        zip.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        zip.extend_from_slice(&header);
+        zip.extend_from_slice(name.as_bytes());
+        zip.extend_from_slice(content.as_bytes());
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        central.extend_from_slice(&header);
+        central.extend_from_slice(&[0; 10]); // comment, disk, attributes
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+    let central_offset = zip.len() as u32;
+    zip.extend_from_slice(&central);
+    zip.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    zip.extend_from_slice(&[0; 4]); // disk numbers
+    zip.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    zip.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    zip.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    zip.extend_from_slice(&central_offset.to_le_bytes());
+    zip.extend_from_slice(&0u16.to_le_bytes()); // comment length
+    zip
+}
+
+fn book() -> EpubBook<Cursor<Vec<u8>>> {
+    let zip = stored_zip(&[
+        ("mimetype", "application/epub+zip"),
+        ("META-INF/container.xml", CONTAINER),
+        ("OEBPS/content.opf", OPF),
+        ("OEBPS/ok.xhtml", OK_CHAPTER),
+        ("OEBPS/broken.xhtml", BROKEN_CHAPTER),
+    ]);
+    EpubBook::from_reader(Cursor::new(zip)).expect("book should open")
+}
+
+fn assert_registered(err: &EpubError) {
+    assert!(
+        ErrorCodeInfo::lookup(err.code()).is_some(),
+        "unregistered code {}",
+        err.code()
+    );
+}
+
+#[test]
+fn registry_codes_are_unique_and_upper_snake_case() {
+    for (index, info) in ERROR_CODES.iter().enumerate() {
+        assert!(
+            info.code
+                .bytes()
+                .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_'),
+            "{}",
+            info.code
+        );
+        assert!(!info.summary.is_empty(), "{}", info.code);
+        assert!(
+            ERROR_CODES[index + 1..]
+                .iter()
+                .all(|other| other.code != info.code),
+            "duplicate code {}",
+            info.code
+        );
+    }
+    assert_eq!(
+        ErrorCodeInfo::lookup("NAV_BYTES_LIMIT").map(|info| info.code),
+        Some("NAV_BYTES_LIMIT")
+    );
+    assert!(ErrorCodeInfo::lookup("NOT_A_CODE").is_none());
+}
+
+#[test]
+fn tokenizer_errors_report_byte_offset() {
+    let err = tokenize_html(BROKEN_CHAPTER).expect_err("mismatched tags");
+    assert_eq!(err.code(), "TOKENIZE_PARSE_ERROR");
+    let offset = err.offset().expect("offset");
+    assert!(offset >= BROKEN_CHAPTER.find("</b>").expect("end tag"));
+
+    let err = EpubError::from(err);
+    assert_registered(&err);
+    assert!(matches!(err.cause(), EpubError::Parse(_)));
+    let ctx = err.phase_context().expect("context");
+    assert_eq!(ctx.token_offset, Some(offset));
+}
+
+#[test]
+fn chapter_errors_carry_chapter_index_href_and_offset() {
+    let mut book = book();
+    let err = book.tokenize_spine_item(1).expect_err("broken chapter");
+    assert_registered(&err);
+    assert_eq!(err.code(), "TOKENIZE_PARSE_ERROR");
+    let ctx = err.phase_context().expect("context");
+    assert_eq!(ctx.chapter_index, Some(1));
+    assert_eq!(ctx.href.as_deref(), Some("broken.xhtml"));
+    assert!(ctx.token_offset.is_some());
+
+    let err = book.chapter_styled_runs(1).expect_err("broken chapter");
+    assert_registered(&err);
+    let EpubError::Phase(phase) = &err else {
+        panic!("expected a phase error, got {:?}", err);
+    };
+    assert_eq!(phase.phase, ErrorPhase::Style);
+    assert_eq!(phase.code, "STYLE_TOKENIZE_ERROR");
+    let ctx = phase.context.as_deref().expect("context");
+    assert_eq!(ctx.chapter_index, Some(1));
+    assert_eq!(ctx.path.as_deref(), Some("broken.xhtml"));
+    assert!(ctx.token_offset.is_some());
+
+    let err = book.chapter_text(7).expect_err("no such chapter");
+    assert_registered(&err);
+    assert!(matches!(
+        err.cause(),
+        EpubError::ChapterOutOfBounds { index: 7, .. }
+    ));
+    let limit = err
+        .phase_context()
+        .and_then(|ctx| ctx.limit.as_deref())
+        .expect("limit");
+    assert_eq!((limit.actual, limit.limit), (7, 2));
+}
+
+#[test]
+fn resource_errors_carry_href_and_archive_path() {
+    let mut book = book();
+    let err = book
+        .read_resource("images/missing.png")
+        .expect_err("missing resource");
+    assert_registered(&err);
+    assert_eq!(err.code(), "ZIP_FILE_NOT_FOUND");
+    assert!(matches!(
+        err.cause(),
+        EpubError::Zip(ZipError::FileNotFound)
+    ));
+    let ctx = err.phase_context().expect("context");
+    assert_eq!(ctx.href.as_deref(), Some("images/missing.png"));
+    assert_eq!(ctx.path.as_deref(), Some("OEBPS/images/missing.png"));
+    assert_eq!(ctx.chapter_index, None);
+
+    let mut out = String::with_capacity(0);
+    let err = book
+        .chapter_html_into_with_limit(0, 4, &mut out)
+        .expect_err("cap");
+    assert_registered(&err);
+    let ctx = err.phase_context().expect("context");
+    assert_eq!(ctx.chapter_index, Some(0));
+    assert_eq!(ctx.path.as_deref(), Some("OEBPS/ok.xhtml"));
+    let limit = ctx.limit.as_deref().expect("limit");
+    assert_eq!(limit.kind.as_ref(), "max_bytes");
+    assert_eq!((limit.actual, limit.limit), (OK_CHAPTER.len(), 4));
+    assert!(std::error::Error::source(&err).is_some());
+}
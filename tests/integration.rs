@@ -309,7 +309,7 @@ fn test_layout_single_page() {
 #[cfg(feature = "layout")]
 #[test]
 fn test_pagination() {
-    let mut tokens = Vec::new();
+    let mut tokens = Vec::with_capacity(16);
     for i in 0..100 {
         tokens.push(Token::Text(format!(
             "This is paragraph {} with enough text to fill some space.",
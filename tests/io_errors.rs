@@ -0,0 +1,94 @@
+//! Structured I/O failures surfaced through the ZIP and book layers.
+//!
+//! Run with: cargo test --test io_errors
+
+#![cfg(feature = "std")]
+
+use std::io::{Cursor, ErrorKind, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use mu_epub::io::{IoError, IoOperation};
+use mu_epub::{EpubBook, EpubError, ZipError};
+
+const SAMPLE_EPUB_PATH: &str =
+    "tests/fixtures/Fundamental-Accessibility-Tests-Basic-Functionality-v2.0.0.epub";
+
+/// Archive bytes whose reads fail with `error` once `pulled` is set, like
+/// storage that disappears mid-read.
+struct PulledCard {
+    inner: Cursor<Vec<u8>>,
+    pulled: Arc<AtomicBool>,
+    error: fn() -> std::io::Error,
+}
+
+impl Read for PulledCard {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pulled.load(Ordering::Relaxed) {
+            return Err((self.error)());
+        }
+        self.inner.read(buf)
+    }
+}
+
+impl Seek for PulledCard {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+fn read_after_pull(error: fn() -> std::io::Error) -> Option<EpubError> {
+    let bytes = std::fs::read(SAMPLE_EPUB_PATH).ok()?;
+    let pulled = Arc::new(AtomicBool::new(false));
+    let reader = PulledCard {
+        inner: Cursor::new(bytes),
+        pulled: Arc::clone(&pulled),
+        error,
+    };
+    let mut book = EpubBook::from_reader(reader).expect("book should open");
+    let href = book.chapter(0).expect("chapter 0").href;
+    pulled.store(true, Ordering::Relaxed);
+    Some(book.read_resource(&href).expect_err("read should fail"))
+}
+
+#[test]
+fn zip_read_failure_keeps_kind_operation_and_offset() {
+    let Some(err) = read_after_pull(|| std::io::Error::new(ErrorKind::TimedOut, "bus timeout"))
+    else {
+        return;
+    };
+    assert!(
+        matches!(err.cause(), EpubError::Zip(ZipError::IoError(_))),
+        "{err}"
+    );
+    let info = err.io_error().expect("I/O details");
+    assert_eq!(info.kind, IoError::TimedOut);
+    assert_eq!(info.raw_os_error, None);
+    assert_eq!(info.operation, IoOperation::Read);
+    assert!(info.offset.is_some_and(|offset| offset > 0));
+    assert!(err.to_string().contains("timed out"), "{err}");
+}
+
+#[cfg(unix)]
+#[test]
+fn zip_read_failure_keeps_raw_os_error() {
+    // EACCES
+    let Some(err) = read_after_pull(|| std::io::Error::from_raw_os_error(13)) else {
+        return;
+    };
+    let info = err.io_error().expect("I/O details");
+    assert_eq!(info.kind, IoError::PermissionDenied);
+    assert_eq!(info.raw_os_error, Some(13));
+    assert!(err.to_string().contains("(os error 13)"), "{err}");
+}
+
+#[test]
+fn missing_book_file_reports_open_not_found() {
+    let Err(err) = EpubBook::open("tests/fixtures/does-not-exist.epub") else {
+        panic!("no such file");
+    };
+    let info = err.io_error().expect("I/O details");
+    assert_eq!(info.kind, IoError::NotFound);
+    assert_eq!(info.operation, IoOperation::Open);
+    assert!(info.raw_os_error.is_some());
+}
@@ -0,0 +1,52 @@
+//! Per-subsystem memory events reported through `mu_epub::memory`.
+//!
+//! The observer is process-global, so this file keeps a single test.
+//! Run with: cargo test --test memory_observer
+
+use mu_epub::book::EpubBook;
+use mu_epub::memory::{clear_memory_observer, set_memory_observer, MemorySubsystem, MemoryTally};
+use mu_epub::{ChapterStylesheets, StyleConfig, Styler, StylesheetSource};
+
+const SAMPLE_EPUB_PATH: &str =
+    "tests/fixtures/Fundamental-Accessibility-Tests-Basic-Functionality-v2.0.0.epub";
+
+static TALLY: MemoryTally = MemoryTally::new();
+
+#[test]
+fn zip_scratch_and_style_tables_are_charged_and_released() {
+    if !std::path::Path::new(SAMPLE_EPUB_PATH).exists() {
+        return;
+    }
+    set_memory_observer(&TALLY);
+
+    let mut book = EpubBook::open(SAMPLE_EPUB_PATH).expect("book should open");
+    let href = book.chapter(1).expect("chapter 1").href;
+    book.read_resource(&href).expect("chapter should read");
+    drop(book);
+    assert!(TALLY.peak(MemorySubsystem::ZipScratch) > 0);
+    assert_eq!(TALLY.current(MemorySubsystem::ZipScratch), 0);
+
+    let mut styler = Styler::new(StyleConfig::default());
+    styler
+        .load_stylesheets(&ChapterStylesheets {
+            sources: vec![StylesheetSource {
+                href: "style.css".to_string(),
+                css: "p { margin: 0 } h1 { font-weight: bold }".to_string(),
+            }],
+        })
+        .expect("stylesheet should load");
+    let tables = TALLY.current(MemorySubsystem::StyleTables);
+    assert!(tables > 0);
+    let copy = styler.clone();
+    assert_eq!(TALLY.current(MemorySubsystem::StyleTables), 2 * tables);
+    drop(copy);
+    drop(styler);
+    assert_eq!(TALLY.current(MemorySubsystem::StyleTables), 0);
+
+    clear_memory_observer();
+    TALLY.reset_peaks();
+    let mut book = EpubBook::open(SAMPLE_EPUB_PATH).expect("book should open");
+    book.read_resource(&href).expect("chapter should read");
+    assert_eq!(TALLY.peak(MemorySubsystem::ZipScratch), 0);
+    assert_eq!(TALLY.total(), 0);
+}
@@ -0,0 +1,48 @@
+//! End-to-end reading path over `mu_epub::io` without the `std` feature.
+//!
+//! Run with: cargo test --no-default-features --features book,layout --test no_std_book
+//! (also passes with default features, where `std` types satisfy the traits).
+
+#![cfg(feature = "book")]
+
+use mu_epub::book::{ChapterEventsOptions, EpubBook};
+use mu_epub::io::MemoryReader;
+use mu_epub::render_prep::StyledEventOrRun;
+
+const SAMPLE_EPUB_PATH: &str =
+    "tests/fixtures/Fundamental-Accessibility-Tests-Basic-Functionality-v2.0.0.epub";
+
+#[test]
+fn memory_reader_opens_streams_and_lays_out_chapters() {
+    if !std::path::Path::new(SAMPLE_EPUB_PATH).exists() {
+        return;
+    }
+    let bytes = std::fs::read(SAMPLE_EPUB_PATH).expect("fixture should be readable");
+    let mut book = EpubBook::from_reader(MemoryReader::new(bytes)).expect("book should open");
+    assert!(book.chapter_count() > 1);
+    assert!(!book.title().is_empty());
+
+    let index = (0..book.chapter_count())
+        .find(|&i| book.chapter_text(i).is_ok_and(|text| text.len() > 200))
+        .expect("a chapter should carry text");
+    let mut runs = 0usize;
+    book.chapter_events(index, ChapterEventsOptions::default(), |item| {
+        if let StyledEventOrRun::Run(run) = item {
+            runs += usize::from(!run.text.trim().is_empty());
+        }
+        Ok(())
+    })
+    .expect("chapter events should stream");
+    assert!(runs > 0);
+
+    #[cfg(feature = "layout")]
+    {
+        let tokens = book
+            .tokenize_spine_chapter(index)
+            .expect("chapter should tokenize");
+        let mut engine = mu_epub::layout::LayoutEngine::new(240.0, 320.0, 20.0);
+        let pages = engine.layout_tokens(&tokens);
+        assert!(!pages.is_empty());
+        assert!(pages.iter().any(|page| page.line_count() > 0));
+    }
+}
@@ -0,0 +1,223 @@
+//! OCF/OPF conformance failures reported by `ValidationMode::Strict`.
+//!
+//! Run with: cargo test --test strict_conformance
+
+#![cfg(feature = "std")]
+
+use std::io::Cursor;
+
+use mu_epub::book::{EpubBook, ValidationMode};
+use mu_epub::error::{EpubError, ErrorPhase, PhaseError};
+
+const SAMPLE_EPUB_PATH: &str =
+    "tests/fixtures/Fundamental-Accessibility-Tests-Basic-Functionality-v2.0.0.epub";
+
+const CONTAINER: &str = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#;
+
+const OPF: &str = r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="uid">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="uid">urn:uuid:1234</dc:identifier>
+    <dc:title>Strict</dc:title>
+    <dc:language>en</dc:language>
+  </metadata>
+  <manifest>
+    <item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine>
+    <itemref idref="c1"/>
+  </spine>
+</package>"#;
+
+const CHAPTER: &str = r#"<html xmlns="http://www.w3.org/1999/xhtml"><body><p>Hi</p></body></html>"#;
+
+/// Archive entry: name, content, and whether to store it deflated.
+type Entry<'a> = (&'a str, &'a str, bool);
+
+/// ZIP archive of `entries` in order. Deflated entries use a single stored
+/// deflate block, so no compressor is needed.
+fn build_zip(entries: &[Entry<'_>]) -> Vec<u8> {
+    let mut zip = Vec::with_capacity(4096);
+    let mut central = Vec::with_capacity(1024);
+    for &(name, content, deflated) in entries {
+        let content = content.as_bytes();
+        let data = if deflated {
+            let len = content.len() as u16;
+            let mut block = vec![0x01];
+            block.extend_from_slice(&len.to_le_bytes());
+            block.extend_from_slice(&(!len).to_le_bytes());
+            block.extend_from_slice(content);
+            block
+        } else {
+            content.to_vec()
+        };
+        let method: u16 = if deflated { 8 } else { 0 };
+        let offset = zip.len() as u32;
+        let mut header = Vec::with_capacity(26);
+        header.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        header.extend_from_slice(&0u16.to_le_bytes()); // flags
+        header.extend_from_slice(&method.to_le_bytes());
+        header.extend_from_slice(&[0; 4]); // time, date
+        header.extend_from_slice(&crc32fast::hash(content).to_le_bytes());
+        header.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // extra length
+        zip.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        zip.extend_from_slice(&header);
+        zip.extend_from_slice(name.as_bytes());
+        zip.extend_from_slice(&data);
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        central.extend_from_slice(&header);
+        central.extend_from_slice(&[0; 10]); // comment, disk, attributes
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+    let central_offset = zip.len() as u32;
+    zip.extend_from_slice(&central);
+    zip.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    zip.extend_from_slice(&[0; 4]); // disk numbers
+    zip.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    zip.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    zip.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    zip.extend_from_slice(&central_offset.to_le_bytes());
+    zip.extend_from_slice(&0u16.to_le_bytes()); // comment length
+    zip
+}
+
+fn book_entries<'a>(opf: &'a str) -> Vec<Entry<'a>> {
+    vec![
+        ("mimetype", "application/epub+zip", false),
+        ("META-INF/container.xml", CONTAINER, false),
+        ("OEBPS/content.opf", opf, false),
+        ("OEBPS/c1.xhtml", CHAPTER, false),
+    ]
+}
+
+fn open(entries: &[Entry<'_>], mode: ValidationMode) -> Result<(), EpubError> {
+    EpubBook::builder()
+        .validation_mode(mode)
+        .from_reader(Cursor::new(build_zip(entries)))
+        .map(|_| ())
+}
+
+/// Open strictly, expecting a violation that lenient mode tolerates.
+fn strict_violation(entries: &[Entry<'_>]) -> PhaseError {
+    open(entries, ValidationMode::Lenient).expect("lenient open should succeed");
+    match open(entries, ValidationMode::Strict) {
+        Err(EpubError::Phase(err)) => {
+            assert_eq!(err.phase, ErrorPhase::Open);
+            err
+        }
+        other => panic!("expected a phase error, got {:?}", other),
+    }
+}
+
+fn context_path(err: &PhaseError) -> Option<&str> {
+    err.context.as_ref().and_then(|ctx| ctx.path.as_deref())
+}
+
+#[test]
+fn conforming_books_open_strictly() {
+    open(&book_entries(OPF), ValidationMode::Strict).expect("synthetic book is conforming");
+    if std::path::Path::new(SAMPLE_EPUB_PATH).exists() {
+        EpubBook::builder()
+            .validation_mode(ValidationMode::Strict)
+            .open(SAMPLE_EPUB_PATH)
+            .expect("fixture is conforming");
+    }
+}
+
+#[test]
+fn mimetype_must_be_first_and_stored() {
+    let mut entries = book_entries(OPF);
+    entries.swap(0, 1);
+    let err = strict_violation(&entries);
+    assert_eq!(err.code, "OCF_MIMETYPE_NOT_FIRST");
+    assert_eq!(context_path(&err), Some("mimetype"));
+
+    let mut entries = book_entries(OPF);
+    entries[0].2 = true;
+    let err = strict_violation(&entries);
+    assert_eq!(err.code, "OCF_MIMETYPE_COMPRESSED");
+}
+
+#[test]
+fn container_schema_is_checked() {
+    let container = CONTAINER.replace(r#"version="1.0""#, r#"version="2.0""#);
+    let mut entries = book_entries(OPF);
+    entries[1].1 = &container;
+    let err = strict_violation(&entries);
+    assert_eq!(err.code, "OCF_CONTAINER_INVALID");
+    assert_eq!(context_path(&err), Some("META-INF/container.xml"));
+}
+
+#[test]
+fn package_schema_is_checked() {
+    let cases = [
+        (
+            OPF.replace(r#"unique-identifier="uid""#, r#"unique-identifier="isbn""#),
+            "OPF_PACKAGE_INVALID",
+        ),
+        (
+            OPF.replace("<dc:language>en</dc:language>", ""),
+            "OPF_METADATA_MISSING",
+        ),
+        (
+            OPF.replace(r#"<dc:title>"#, r#"<dc:title id="c1">"#),
+            "OPF_ID_DUPLICATE",
+        ),
+    ];
+    for (opf, code) in &cases {
+        let err = strict_violation(&book_entries(opf));
+        assert_eq!(err.code, *code, "{}", err.message);
+        assert_eq!(context_path(&err), Some("OEBPS/content.opf"));
+    }
+}
+
+#[test]
+fn manifest_integrity_is_checked() {
+    let item = r#"<item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>"#;
+    let cases = [
+        (
+            format!(r#"{item}<item id="css" href="missing.css" media-type="text/css"/>"#),
+            "MANIFEST_RESOURCE_MISSING",
+            "OEBPS/missing.css",
+        ),
+        (
+            r#"<item id="c1" href="c1.xhtml" media-type="image/png"/>"#.to_string(),
+            "MANIFEST_MEDIA_TYPE_MISMATCH",
+            "OEBPS/c1.xhtml",
+        ),
+        (
+            format!(
+                r#"{item}<item id="again" href="c1.xhtml" media-type="application/xhtml+xml"/>"#
+            ),
+            "MANIFEST_HREF_DUPLICATE",
+            "OEBPS/c1.xhtml",
+        ),
+    ];
+    for (manifest, code, path) in &cases {
+        let opf = OPF.replace(item, manifest);
+        let err = strict_violation(&book_entries(&opf));
+        assert_eq!(err.code, *code, "{}", err.message);
+        assert_eq!(context_path(&err), Some(*path));
+    }
+}
+
+#[test]
+fn spine_references_are_checked() {
+    let opf = OPF.replace("<spine>", r#"<spine toc="ncx">"#);
+    let err = strict_violation(&book_entries(&opf));
+    assert_eq!(err.code, "SPINE_TOC_NOT_IN_MANIFEST");
+
+    let opf = OPF.replace(r#"<itemref idref="c1"/>"#, "");
+    let err = strict_violation(&book_entries(&opf));
+    assert_eq!(err.code, "SPINE_EMPTY");
+}
@@ -0,0 +1,87 @@
+//! Phase spans emitted with the `tracing` feature.
+//!
+//! Run with: cargo test --features tracing --test tracing_spans
+
+#![cfg(all(feature = "std", feature = "tracing"))]
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use mu_epub::book::{ChapterEventsOptions, EpubBook};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+const SAMPLE_EPUB_PATH: &str =
+    "tests/fixtures/Fundamental-Accessibility-Tests-Basic-Functionality-v2.0.0.epub";
+
+/// Records each new span as `name{field=value,...}`.
+#[derive(Default)]
+struct SpanRecorder {
+    next_id: AtomicU64,
+    spans: Arc<Mutex<Vec<String>>>,
+}
+
+struct FieldWriter<'a>(&'a mut String);
+
+impl Visit for FieldWriter<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.push_str(&format!("{}={:?},", field.name(), value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push_str(&format!("{}={},", field.name(), value));
+    }
+}
+
+impl Subscriber for SpanRecorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut line = format!("{}{{", span.metadata().name());
+        span.record(&mut FieldWriter(&mut line));
+        line.push('}');
+        self.spans.lock().expect("recorder lock").push(line);
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[test]
+fn open_read_tokenize_and_style_emit_phase_spans() {
+    if !std::path::Path::new(SAMPLE_EPUB_PATH).exists() {
+        return;
+    }
+    let recorder = SpanRecorder::default();
+    let spans = Arc::clone(&recorder.spans);
+    tracing::subscriber::with_default(recorder, || {
+        let mut book = EpubBook::open(SAMPLE_EPUB_PATH).expect("book should open");
+        let href = book.chapter(1).expect("chapter 1").href;
+        book.tokenize_spine_item(1)
+            .expect("chapter should tokenize");
+        book.chapter_events(1, ChapterEventsOptions::default(), |_| Ok(()))
+            .expect("chapter events should stream");
+
+        let spans = spans.lock().expect("recorder lock");
+        assert_eq!(spans.first().map(String::as_str), Some("epub.open{}"));
+        let container = "zip.read{path=META-INF/container.xml,}";
+        assert!(spans.iter().any(|span| span == container), "{:?}", spans);
+        assert!(spans.iter().any(|span| span.starts_with("zip.inflate{")));
+        let tokenize = format!("tokenize{{chapter_index=1,href={},}}", href);
+        let style = format!("style{{chapter_index=1,href={},}}", href);
+        assert!(spans.contains(&tokenize), "{:?}", spans);
+        assert!(spans.contains(&style), "{:?}", spans);
+    });
+}
@@ -0,0 +1,73 @@
+//! `ZipEntryReader` streams the same bytes as the buffered ZIP readers.
+//!
+//! Run with: cargo test --test zip_entry_reader
+
+#![cfg(feature = "std")]
+
+use std::fs::File;
+use std::io::Read;
+
+use mu_epub::tokenizer::tokenize_html;
+use mu_epub::zip::StreamingZip;
+
+const SAMPLE_EPUB_PATH: &str =
+    "tests/fixtures/Fundamental-Accessibility-Tests-Basic-Functionality-v2.0.0.epub";
+
+fn open_sample() -> Option<StreamingZip<File>> {
+    let file = File::open(SAMPLE_EPUB_PATH).ok()?;
+    Some(StreamingZip::new(file).expect("parse zip"))
+}
+
+#[test]
+fn entry_reader_matches_writer_for_every_entry() {
+    let Some(mut zip) = open_sample() else {
+        return;
+    };
+    let entries: Vec<_> = zip.entries().cloned().collect();
+    assert!(!entries.is_empty());
+    for entry in &entries {
+        let mut expected = Vec::with_capacity(0);
+        zip.read_file_to_writer(entry, &mut expected)
+            .expect("writer read");
+
+        let mut streamed = Vec::with_capacity(0);
+        zip.entry_reader(entry)
+            .expect("open reader")
+            .read_to_end(&mut streamed)
+            .expect("reader read");
+        assert_eq!(streamed, expected, "{}", entry.filename);
+
+        let mut input = [0u8; 61];
+        let mut reader = zip
+            .entry_reader_with_scratch(entry, &mut input)
+            .expect("open reader");
+        let mut chunked = Vec::with_capacity(0);
+        let mut chunk = [0u8; 97];
+        loop {
+            let n = reader.read(&mut chunk).expect("chunked read");
+            if n == 0 {
+                break;
+            }
+            chunked.extend_from_slice(&chunk[..n]);
+        }
+        assert!(reader.is_finished(), "{}", entry.filename);
+        assert_eq!(chunked, expected, "{}", entry.filename);
+    }
+}
+
+#[test]
+fn streamed_chapter_tokenizes() {
+    let Some(mut zip) = open_sample() else {
+        return;
+    };
+    let entry = zip
+        .get_entry("EPUB/xhtml/introduction.xhtml")
+        .cloned()
+        .expect("introduction chapter");
+    let mut html = String::with_capacity(0);
+    zip.entry_reader(&entry)
+        .expect("open reader")
+        .read_to_string(&mut html)
+        .expect("UTF-8 chapter");
+    assert!(!tokenize_html(&html).expect("tokenize").is_empty());
+}
@@ -0,0 +1,81 @@
+//! `StreamingZip::new_sequential` over streams without `Seek`.
+//!
+//! Run with: cargo test --test zip_sequential
+
+#![cfg(feature = "std")]
+
+use std::fs::File;
+use std::io::Read;
+
+use mu_epub::zip::{StreamingZip, ZipLimits};
+
+const SAMPLE_EPUB_PATH: &str =
+    "tests/fixtures/Fundamental-Accessibility-Tests-Basic-Functionality-v2.0.0.epub";
+
+/// Forward-only source handing out a few bytes per read, like a UART.
+struct Trickle {
+    bytes: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for Trickle {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = buf.len().min(5).min(self.bytes.len() - self.pos);
+        buf[..n].copy_from_slice(&self.bytes[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[test]
+fn sequential_scan_matches_seekable_open() {
+    let Ok(bytes) = std::fs::read(SAMPLE_EPUB_PATH) else {
+        return;
+    };
+    let mut seekable = StreamingZip::new(File::open(SAMPLE_EPUB_PATH).expect("open")).expect("zip");
+    let mut sequential =
+        StreamingZip::new_sequential(Trickle { bytes, pos: 0 }).expect("sequential zip");
+
+    assert_eq!(sequential.num_entries(), seekable.num_entries());
+    assert!(sequential.is_valid_epub());
+    let entries: Vec<_> = seekable.entries().cloned().collect();
+    for expected in &entries {
+        let entry = sequential
+            .get_entry(&expected.filename)
+            .cloned()
+            .expect("entry found sequentially");
+        assert_eq!(entry.method, expected.method, "{}", entry.filename);
+        assert_eq!(entry.crc32, expected.crc32, "{}", entry.filename);
+        assert_eq!(
+            entry.uncompressed_size, expected.uncompressed_size,
+            "{}",
+            entry.filename
+        );
+
+        let mut want = Vec::with_capacity(0);
+        seekable
+            .read_file_to_writer(expected, &mut want)
+            .expect("read");
+        let mut got = Vec::with_capacity(0);
+        sequential
+            .entry_reader(&entry)
+            .expect("reader")
+            .read_to_end(&mut got)
+            .expect("read");
+        assert_eq!(got, want, "{}", entry.filename);
+    }
+}
+
+#[test]
+fn sequential_scan_honors_entry_cap() {
+    let Ok(bytes) = std::fs::read(SAMPLE_EPUB_PATH) else {
+        return;
+    };
+    let limits = ZipLimits::new(1024 * 1024, 1024).with_max_cd_entries(2);
+    let zip = StreamingZip::new_sequential_with_limits(bytes.as_slice(), Some(limits))
+        .expect("sequential zip");
+    assert_eq!(zip.num_entries(), 2);
+
+    let strict = limits.with_strict(true);
+    assert!(StreamingZip::new_sequential_with_limits(bytes.as_slice(), Some(strict)).is_err());
+}